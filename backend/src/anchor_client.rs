@@ -1,13 +1,19 @@
 // Anchor client for on-chain program verification
 // Verifies registry and profiles program accounts
 
-use solana_client::rpc_client::RpcClient;
+use base64::{engine::general_purpose, Engine as _};
+use solana_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
+use sha2::{Digest, Sha256};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::Message;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
 use std::str::FromStr;
 
 // Program IDs (should match programs/shadow-registry and programs/shadow-profiles)
-const REGISTRY_PROGRAM_ID: &str = "7Y8Zx9qR3sN2mP1wV5tU4fG6hK8jL0dA";
-const PROFILES_PROGRAM_ID: &str = "8Z9Ax0rS4tN3nQ2xW6uV5gH7iL9kM1eB";
+pub const REGISTRY_PROGRAM_ID: &str = "7Y8Zx9qR3sN2mP1wV5tU4fG6hK8jL0dA";
+pub const PROFILES_PROGRAM_ID: &str = "8Z9Ax0rS4tN3nQ2xW6uV5gH7iL9kM1eB";
 
 pub struct AnchorClient {
     rpc_url: String,
@@ -24,6 +30,26 @@ pub struct SiteAccount {
     pub storage_cid: String,
     pub created_at: i64,
     pub updated_at: i64,
+    /// Latest published version; 0 when nothing has been published
+    pub version: u32,
+    /// Wallets besides the owner allowed to update the site
+    pub editors: Vec<Pubkey>,
+}
+
+impl SiteAccount {
+    /// Whether `wallet` may update the site: the owner or a listed editor
+    pub fn can_edit(&self, wallet: &Pubkey) -> bool {
+        self.owner == *wallet || self.editors.contains(wallet)
+    }
+}
+
+/// A `SiteVersion` from the registry: the content a site had at one published version
+#[derive(Debug, Clone)]
+pub struct SiteVersionAccount {
+    pub site: Pubkey,
+    pub version: u32,
+    pub storage_cid: String,
+    pub published_at: i64,
 }
 
 #[derive(Debug, Clone)]
@@ -33,8 +59,77 @@ pub struct ProfileAccount {
     pub is_public: bool,
     pub created_at: i64,
     pub updated_at: i64,
+    pub follower_count: u64,
+    pub following_count: u64,
+    pub username: Option<String>,
 }
 
+/// A `UsernameRecord` from the profiles program: `wallet` holds `name`
+#[derive(Debug, Clone)]
+pub struct UsernameAccount {
+    pub wallet: Pubkey,
+    pub name: String,
+    pub created_at: i64,
+}
+
+/// An event the registry emits, decoded from the "Program data:" lines of a transaction's logs
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryEvent {
+    SiteRegistered {
+        site: Pubkey,
+        program_address: Pubkey,
+        owner: Pubkey,
+        name: String,
+        at: i64,
+    },
+    SiteUpdated {
+        site: Pubkey,
+        program_address: Pubkey,
+        owner: Pubkey,
+        /// The owner or the editor that signed the update
+        updated_by: Pubkey,
+        at: i64,
+    },
+}
+
+impl RegistryEvent {
+    /// JSON for broker subscribers, tagged by `type` like the backend's own events
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            RegistryEvent::SiteRegistered { site, program_address, owner, name, at } => serde_json::json!({
+                "type": "site_registered",
+                "site": site.to_string(),
+                "program_address": program_address.to_string(),
+                "owner": owner.to_string(),
+                "name": name,
+                "at": at,
+            }),
+            RegistryEvent::SiteUpdated { site, program_address, owner, updated_by, at } => serde_json::json!({
+                "type": "site_updated",
+                "site": site.to_string(),
+                "program_address": program_address.to_string(),
+                "owner": owner.to_string(),
+                "updated_by": updated_by.to_string(),
+                "at": at,
+            }),
+        }
+    }
+}
+
+/// A `Follow` account from the profiles program: `follower` follows `followee`
+#[derive(Debug, Clone)]
+pub struct FollowAccount {
+    pub follower: Pubkey,
+    pub followee: Pubkey,
+    pub created_at: i64,
+}
+
+/// Error from `delete_profile_account` while the wallet hasn't closed its profile yet
+pub const PROFILE_STILL_OPEN: &str = "Profile account still exists";
+
+/// Most accounts one getMultipleAccounts call may ask for
+const MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
 impl AnchorClient {
     pub fn new(rpc_url: String) -> Result<Self, String> {
         let registry_program = Pubkey::from_str(REGISTRY_PROGRAM_ID)
@@ -43,77 +138,208 @@ impl AnchorClient {
         let profiles_program = Pubkey::from_str(PROFILES_PROGRAM_ID)
             .map_err(|e| format!("Invalid profiles program ID: {}", e))?;
 
-        Ok(Self {
+        Ok(Self::with_programs(rpc_url, registry_program, profiles_program))
+    }
+
+    /// Client for registry and profiles programs deployed under other ids
+    pub fn with_programs(rpc_url: String, registry_program: Pubkey, profiles_program: Pubkey) -> Self {
+        Self {
             rpc_url,
             registry_program,
             profiles_program,
-        })
+        }
+    }
+
+    /// Site PDA in the registry: seeds ["site", program_address]
+    pub fn site_pda(&self, program: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"site", program.as_ref()], &self.registry_program).0
+    }
+
+    /// Fetch a site's registry account. None when it was never registered or has been deleted.
+    pub async fn get_site(&self, program_address: &str) -> Result<Option<SiteAccount>, String> {
+        let program = Pubkey::from_str(program_address)
+            .map_err(|e| format!("Invalid program pubkey: {}", e))?;
+
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let response = client.get_account_with_commitment(&self.site_pda(&program), client.commitment()).await
+            .map_err(|e| format!("Failed to fetch site account: {}", e))?;
+
+        // Only the registry can create an account at its PDA; anything else there is not a site
+        match response.value {
+            Some(account) if account.owner == self.registry_program => decode_site_account(&account.data)
+                .map(Some)
+                .ok_or_else(|| "Malformed site account".to_string()),
+            _ => Ok(None),
+        }
     }
 
-    /// Verify a site is registered on-chain
-    pub fn verify_site_registration(
+    /// The site's registry account, if it is registered to `owner_pubkey`
+    pub async fn verify_site_registration(
         &self,
         program_address: &str,
         owner_pubkey: &str,
     ) -> Result<Option<SiteAccount>, String> {
-        let program = Pubkey::from_str(program_address)
-            .map_err(|e| format!("Invalid program pubkey: {}", e))?;
-        
         let owner = Pubkey::from_str(owner_pubkey)
             .map_err(|e| format!("Invalid owner pubkey: {}", e))?;
+        Ok(self.get_site(program_address).await?.filter(|site| site.owner == owner))
+    }
 
-        let client = RpcClient::new(&self.rpc_url);
-        
-        // Derive site PDA: findProgramAddress(["site", program_address])
-        // For now, we'll use a simplified approach
-        // In production, use anchor_lang::prelude::Pubkey::find_program_address
-        
-        // Get account data
-        // This is a placeholder - in production, you'd:
-        // 1. Derive the PDA using the seeds ["site", program_address]
-        // 2. Fetch the account
-        // 3. Deserialize using Anchor's AccountDeserialize
-        
-        // Simplified check: verify program exists and is owned by registry
-        match client.get_account(&program) {
-            Ok(account) if account.owner == self.registry_program => {
-                // Account exists and is owned by registry program
-                // In production, deserialize the account data here
-                Ok(Some(SiteAccount {
-                    owner,
-                    program_address: program,
-                    name: String::new(), // Would be deserialized from account data
-                    description: String::new(),
-                    storage_cid: String::new(),
-                    created_at: 0,
-                    updated_at: 0,
-                }))
+    /// The site's registry account, if `wallet` is its owner or one of its editors
+    pub async fn verify_site_editor(
+        &self,
+        program_address: &str,
+        wallet: &str,
+    ) -> Result<Option<SiteAccount>, String> {
+        let wallet = Pubkey::from_str(wallet)
+            .map_err(|e| format!("Invalid wallet pubkey: {}", e))?;
+        Ok(self.get_site(program_address).await?.filter(|site| site.can_edit(&wallet)))
+    }
+
+    /// Version PDA in the registry: seeds ["version", site, version as u32 LE]
+    pub fn site_version_pda(&self, site: &Pubkey, version: u32) -> Pubkey {
+        Pubkey::find_program_address(&[b"version", site.as_ref(), &version.to_le_bytes()], &self.registry_program).0
+    }
+
+    /// Every version published for a site, newest first, read from their PDAs.
+    /// None when the site is not registered.
+    pub async fn get_site_versions(&self, program_address: &str) -> Result<Option<Vec<SiteVersionAccount>>, String> {
+        let Some(site) = self.get_site(program_address).await? else {
+            return Ok(None);
+        };
+        let site_address = self.site_pda(&site.program_address);
+        let addresses: Vec<Pubkey> = (1..=site.version).rev()
+            .map(|version| self.site_version_pda(&site_address, version))
+            .collect();
+
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let mut versions = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+            let accounts = client.get_multiple_accounts(chunk).await
+                .map_err(|e| format!("Failed to fetch site versions: {}", e))?;
+            for (address, account) in chunk.iter().zip(accounts) {
+                // Every number up to the site's counter was published; a gap is not expected
+                let Some(account) = account.filter(|account| account.owner == self.registry_program) else {
+                    continue;
+                };
+                let version = decode_site_version_account(&account.data)
+                    .filter(|version| version.site == site_address)
+                    .ok_or_else(|| format!("Malformed site version {}", address))?;
+                versions.push(version);
             }
-            _ => Ok(None),
         }
+        Ok(Some(versions))
     }
 
-    /// Verify a profile exists on-chain
-    pub fn verify_profile(
-        &self,
-        wallet: &str,
-    ) -> Result<Option<ProfileAccount>, String> {
-        let _wallet_pubkey = Pubkey::from_str(wallet)
+    /// Profile PDA in the profiles program: seeds ["profile", wallet]
+    pub fn profile_pda(&self, wallet: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"profile", wallet.as_ref()], &self.profiles_program).0
+    }
+
+    /// Fetch a wallet's on-chain profile. None when it has not created one.
+    pub async fn verify_profile(&self, wallet: &str) -> Result<Option<ProfileAccount>, String> {
+        let wallet = Pubkey::from_str(wallet)
             .map_err(|e| format!("Invalid wallet pubkey: {}", e))?;
 
-        let _client = RpcClient::new(&self.rpc_url);
-        
-        // Derive profile PDA: findProgramAddress(["profile", wallet])
-        // For now, simplified approach
-        
-        // In production:
-        // 1. Derive PDA using seeds ["profile", wallet]
-        // 2. Fetch account
-        // 3. Deserialize using Anchor's AccountDeserialize
-        
-        // Simplified: check if any account owned by profiles program exists
-        // This is a placeholder - full implementation would derive and fetch the PDA
-        Ok(None)
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let response = client.get_account_with_commitment(&self.profile_pda(&wallet), client.commitment()).await
+            .map_err(|e| format!("Failed to fetch profile account: {}", e))?;
+
+        match response.value {
+            Some(account) if account.owner == self.profiles_program => decode_profile_account(&account.data)
+                .filter(|profile| profile.wallet == wallet)
+                .map(Some)
+                .ok_or_else(|| "Malformed profile account".to_string()),
+            _ => Ok(None),
+        }
+    }
+
+    /// Finish deleting a wallet's profile account. The wallet signs `delete_profile` itself,
+    /// which closes the PDA, its username record and its follows and refunds their rent; the
+    /// backend only checks that it happened, failing with `PROFILE_STILL_OPEN` until it has.
+    pub async fn delete_profile_account(&self, wallet: &str) -> Result<(), String> {
+        match self.verify_profile(wallet).await? {
+            Some(_) => Err(PROFILE_STILL_OPEN.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// Username record PDA in the profiles program: seeds ["username", name]
+    pub fn username_pda(&self, name: &str) -> Pubkey {
+        Pubkey::find_program_address(&[b"username", name.as_bytes()], &self.profiles_program).0
+    }
+
+    /// Who holds a (lowercase) username. None when nobody has claimed it.
+    pub async fn resolve_username(&self, name: &str) -> Result<Option<UsernameAccount>, String> {
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let response = client.get_account_with_commitment(&self.username_pda(name), client.commitment()).await
+            .map_err(|e| format!("Failed to fetch username record: {}", e))?;
+
+        match response.value {
+            Some(account) if account.owner == self.profiles_program => decode_username_account(&account.data)
+                .map(Some)
+                .ok_or_else(|| "Malformed username record".to_string()),
+            _ => Ok(None),
+        }
+    }
+
+    /// Follow PDA in the profiles program: seeds ["follow", follower, followee]
+    pub fn follow_pda(&self, follower: &Pubkey, followee: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"follow", follower.as_ref(), followee.as_ref()], &self.profiles_program).0
+    }
+
+    /// The follow account for `follower` following `followee`. None when it does not follow them.
+    pub async fn get_follow(&self, follower: &str, followee: &str) -> Result<Option<FollowAccount>, String> {
+        let follower = Pubkey::from_str(follower)
+            .map_err(|e| format!("Invalid follower pubkey: {}", e))?;
+        let followee = Pubkey::from_str(followee)
+            .map_err(|e| format!("Invalid followee pubkey: {}", e))?;
+
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let response = client.get_account_with_commitment(&self.follow_pda(&follower, &followee), client.commitment()).await
+            .map_err(|e| format!("Failed to fetch follow account: {}", e))?;
+
+        match response.value {
+            Some(account) if account.owner == self.profiles_program => decode_follow_account(&account.data)
+                .map(Some)
+                .ok_or_else(|| "Malformed follow account".to_string()),
+            _ => Ok(None),
+        }
+    }
+
+    /// The profiles program's `follow_profile` instruction; `follower` signs and pays for the follow PDA
+    pub fn follow_instruction(&self, follower: &Pubkey, followee: &Pubkey) -> Instruction {
+        let mut data = instruction_discriminator("follow_profile").to_vec();
+        data.extend_from_slice(followee.as_ref());
+        Instruction::new_with_bytes(self.profiles_program, &data, vec![
+            AccountMeta::new(self.follow_pda(follower, followee), false),
+            AccountMeta::new(self.profile_pda(follower), false),
+            AccountMeta::new(self.profile_pda(followee), false),
+            AccountMeta::new(*follower, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ])
+    }
+
+    /// The profiles program's `unfollow_profile` instruction; the follow PDA's rent goes back to `follower`
+    pub fn unfollow_instruction(&self, follower: &Pubkey, followee: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(self.profiles_program, &instruction_discriminator("unfollow_profile"), vec![
+            AccountMeta::new(self.follow_pda(follower, followee), false),
+            AccountMeta::new(self.profile_pda(follower), false),
+            AccountMeta::new(self.profile_pda(followee), false),
+            AccountMeta::new(*follower, true),
+        ])
+    }
+
+    /// `instruction` in a base64 unsigned transaction paid for by `payer`, for the wallet to sign and send
+    pub async fn unsigned_transaction(&self, instruction: Instruction, payer: &Pubkey) -> Result<String, String> {
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let blockhash = client.get_latest_blockhash().await
+            .map_err(|e| format!("Failed to get blockhash: {}", e))?;
+
+        let message = Message::new_with_blockhash(&[instruction], Some(payer), &blockhash);
+        let transaction = Transaction::new_unsigned(message);
+        let bytes = bincode::serialize(&transaction)
+            .map_err(|e| format!("Failed to serialize transaction: {}", e))?;
+        Ok(general_purpose::STANDARD.encode(bytes))
     }
 
     /// Get registry program ID
@@ -127,3 +353,371 @@ impl AnchorClient {
     }
 }
 
+/// Anchor account discriminator: first 8 bytes of sha256("account:<Name>")
+fn account_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("account:{}", name).as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Anchor instruction discriminator: first 8 bytes of sha256("global:<name>")
+fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("global:{}", name).as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Anchor event discriminator: first 8 bytes of sha256("event:<Name>")
+fn event_discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("event:{}", name).as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Decode one registry event as `emit!` writes it: discriminator, then the Borsh fields
+pub fn decode_registry_event(data: &[u8]) -> Option<RegistryEvent> {
+    if let Some(rest) = data.strip_prefix(&event_discriminator("SiteRegistered")[..]) {
+        let mut reader = BorshReader(rest);
+        return Some(RegistryEvent::SiteRegistered {
+            site: reader.pubkey()?,
+            program_address: reader.pubkey()?,
+            owner: reader.pubkey()?,
+            name: reader.string()?,
+            at: reader.i64()?,
+        });
+    }
+    let rest = data.strip_prefix(&event_discriminator("SiteUpdated")[..])?;
+    let mut reader = BorshReader(rest);
+    Some(RegistryEvent::SiteUpdated {
+        site: reader.pubkey()?,
+        program_address: reader.pubkey()?,
+        owner: reader.pubkey()?,
+        updated_by: reader.pubkey()?,
+        at: reader.i64()?,
+    })
+}
+
+/// Registry events in a transaction's log messages, in the order they were emitted.
+/// Lines that aren't event data, or are events this backend doesn't know, are skipped.
+pub fn registry_events_from_logs(logs: &[String]) -> Vec<RegistryEvent> {
+    use base64::{engine::general_purpose, Engine as _};
+    logs.iter()
+        .filter_map(|line| line.strip_prefix("Program data: "))
+        .filter_map(|data| general_purpose::STANDARD.decode(data).ok())
+        .filter_map(|data| decode_registry_event(&data))
+        .collect()
+}
+
+/// Decode the registry's `Site` account (Borsh, after the Anchor discriminator)
+pub fn decode_site_account(data: &[u8]) -> Option<SiteAccount> {
+    let rest = data.strip_prefix(&account_discriminator("Site")[..])?;
+    let mut reader = BorshReader(rest);
+    Some(SiteAccount {
+        owner: reader.pubkey()?,
+        program_address: reader.pubkey()?,
+        name: reader.string()?,
+        description: reader.string()?,
+        storage_cid: reader.string()?,
+        created_at: reader.i64()?,
+        updated_at: reader.i64()?,
+        // Sites not yet migrated end here; they have no versions or editors
+        version: reader.u32().unwrap_or_default(),
+        editors: reader.vec(BorshReader::pubkey).unwrap_or_default(),
+    })
+}
+
+/// Decode the registry's `SiteVersion` account
+pub fn decode_site_version_account(data: &[u8]) -> Option<SiteVersionAccount> {
+    let rest = data.strip_prefix(&account_discriminator("SiteVersion")[..])?;
+    let mut reader = BorshReader(rest);
+    Some(SiteVersionAccount {
+        site: reader.pubkey()?,
+        version: reader.u32()?,
+        storage_cid: reader.string()?,
+        published_at: reader.i64()?,
+    })
+}
+
+/// Decode the profiles program's `Profile` account. Profiles not yet migrated end after
+/// `updated_at`; their counters and username read as zero and None.
+pub fn decode_profile_account(data: &[u8]) -> Option<ProfileAccount> {
+    let rest = data.strip_prefix(&account_discriminator("Profile")[..])?;
+    let mut reader = BorshReader(rest);
+    let wallet = reader.pubkey()?;
+    let profile_cid = reader.string()?;
+    let is_public = reader.bool()?;
+    let created_at = reader.i64()?;
+    let updated_at = reader.i64()?;
+    let (follower_count, following_count, username) =
+        (|| Some((reader.u64()?, reader.u64()?, reader.option(BorshReader::string)?)))().unwrap_or_default();
+    Some(ProfileAccount {
+        wallet,
+        profile_cid,
+        is_public,
+        created_at,
+        updated_at,
+        follower_count,
+        following_count,
+        username,
+    })
+}
+
+/// Decode the profiles program's `Follow` account
+pub fn decode_follow_account(data: &[u8]) -> Option<FollowAccount> {
+    let rest = data.strip_prefix(&account_discriminator("Follow")[..])?;
+    let mut reader = BorshReader(rest);
+    Some(FollowAccount {
+        follower: reader.pubkey()?,
+        followee: reader.pubkey()?,
+        created_at: reader.i64()?,
+    })
+}
+
+/// Decode the profiles program's `UsernameRecord` account
+pub fn decode_username_account(data: &[u8]) -> Option<UsernameAccount> {
+    let rest = data.strip_prefix(&account_discriminator("UsernameRecord")[..])?;
+    let mut reader = BorshReader(rest);
+    Some(UsernameAccount {
+        wallet: reader.pubkey()?,
+        name: reader.string()?,
+        created_at: reader.i64()?,
+    })
+}
+
+struct BorshReader<'a>(&'a [u8]);
+
+impl BorshReader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        Pubkey::try_from(self.take(32)?).ok()
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = u32::from_le_bytes(self.take(4)?.try_into().ok()?) as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.take(1)? {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+
+    fn vec<T>(&mut self, mut read: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        let len = self.u32()? as usize;
+        // Each element takes at least a byte, so a longer length is corrupt
+        if len > self.0.len() {
+            return None;
+        }
+        (0..len).map(|_| read(self)).collect()
+    }
+
+    fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        match self.take(1)? {
+            [0] => Some(None),
+            [1] => read(self).map(Some),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_site(site: &SiteAccount) -> Vec<u8> {
+        let mut data = account_discriminator("Site").to_vec();
+        data.extend_from_slice(site.owner.as_ref());
+        data.extend_from_slice(site.program_address.as_ref());
+        for s in [&site.name, &site.description, &site.storage_cid] {
+            data.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            data.extend_from_slice(s.as_bytes());
+        }
+        data.extend_from_slice(&site.created_at.to_le_bytes());
+        data.extend_from_slice(&site.updated_at.to_le_bytes());
+        data.extend_from_slice(&site.version.to_le_bytes());
+        data.extend_from_slice(&(site.editors.len() as u32).to_le_bytes());
+        for editor in &site.editors {
+            data.extend_from_slice(editor.as_ref());
+        }
+        // Accounts are allocated at full size, so there is trailing space
+        data.extend_from_slice(&[0; 64]);
+        data
+    }
+
+    #[test]
+    fn test_decode_site_account() {
+        let site = SiteAccount {
+            owner: Pubkey::new_unique(),
+            program_address: Pubkey::new_unique(),
+            name: "Shadow".to_string(),
+            description: "A site".to_string(),
+            storage_cid: "ipfs://bafy".to_string(),
+            created_at: 1_700_000_000,
+            updated_at: 1_700_000_500,
+            version: 7,
+            editors: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+        };
+        let decoded = decode_site_account(&encode_site(&site)).unwrap();
+        assert_eq!(decoded.owner, site.owner);
+        assert_eq!(decoded.program_address, site.program_address);
+        assert_eq!(decoded.storage_cid, "ipfs://bafy");
+        assert_eq!((decoded.created_at, decoded.updated_at), (1_700_000_000, 1_700_000_500));
+        assert_eq!(decoded.version, 7);
+        assert_eq!(decoded.editors, site.editors);
+        assert!(decoded.can_edit(&site.owner) && decoded.can_edit(&site.editors[1]));
+        assert!(!decoded.can_edit(&Pubkey::new_unique()));
+    }
+
+    #[test]
+    fn test_decode_site_version_account() {
+        let site = Pubkey::new_unique();
+        let mut data = account_discriminator("SiteVersion").to_vec();
+        data.extend_from_slice(site.as_ref());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&13u32.to_le_bytes());
+        data.extend_from_slice(b"ipfs://bafyv3");
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+
+        let decoded = decode_site_version_account(&data).unwrap();
+        assert_eq!((decoded.site, decoded.version), (site, 3));
+        assert_eq!((decoded.storage_cid.as_str(), decoded.published_at), ("ipfs://bafyv3", 1_700_000_000));
+        assert!(decode_site_version_account(&data[..50]).is_none());
+    }
+
+    #[test]
+    fn test_registry_events_from_logs() {
+        use base64::{engine::general_purpose, Engine as _};
+        let (site, program_address, owner, editor) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut registered = event_discriminator("SiteRegistered").to_vec();
+        for key in [site, program_address, owner] {
+            registered.extend_from_slice(key.as_ref());
+        }
+        registered.extend_from_slice(&6u32.to_le_bytes());
+        registered.extend_from_slice(b"Shadow");
+        registered.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        let mut updated = event_discriminator("SiteUpdated").to_vec();
+        for key in [site, program_address, owner, editor] {
+            updated.extend_from_slice(key.as_ref());
+        }
+        updated.extend_from_slice(&1_700_000_500i64.to_le_bytes());
+
+        let logs: Vec<String> = vec![
+            format!("Program {} invoke [1]", REGISTRY_PROGRAM_ID),
+            format!("Program data: {}", general_purpose::STANDARD.encode(&registered)),
+            "Program log: Site registered".to_string(),
+            // Another program's event, and a truncated one of ours
+            format!("Program data: {}", general_purpose::STANDARD.encode([7u8; 40])),
+            format!("Program data: {}", general_purpose::STANDARD.encode(&updated[..50])),
+            format!("Program data: {}", general_purpose::STANDARD.encode(&updated)),
+        ];
+        let events = registry_events_from_logs(&logs);
+        assert_eq!(events, vec![
+            RegistryEvent::SiteRegistered { site, program_address, owner, name: "Shadow".to_string(), at: 1_700_000_000 },
+            RegistryEvent::SiteUpdated { site, program_address, owner, updated_by: editor, at: 1_700_000_500 },
+        ]);
+        assert_eq!(events[1].to_json()["type"], "site_updated");
+        assert_eq!(events[1].to_json()["updated_by"], editor.to_string());
+    }
+
+    #[test]
+    fn test_decode_follow_account() {
+        let (follower, followee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = account_discriminator("Follow").to_vec();
+        data.extend_from_slice(follower.as_ref());
+        data.extend_from_slice(followee.as_ref());
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+
+        let decoded = decode_follow_account(&data).unwrap();
+        assert_eq!((decoded.follower, decoded.followee, decoded.created_at), (follower, followee, 1_700_000_000));
+        assert!(decode_follow_account(&data[..50]).is_none());
+    }
+
+    #[test]
+    fn test_decode_username_account() {
+        let wallet = Pubkey::new_unique();
+        let mut data = account_discriminator("UsernameRecord").to_vec();
+        data.extend_from_slice(wallet.as_ref());
+        data.extend_from_slice(&5u32.to_le_bytes());
+        data.extend_from_slice(b"shade");
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        // Records are sized for the longest name, so shorter ones carry trailing zeros
+        data.extend_from_slice(&[0; 27]);
+
+        let decoded = decode_username_account(&data).unwrap();
+        assert_eq!((decoded.wallet, decoded.name.as_str(), decoded.created_at), (wallet, "shade", 1_700_000_000));
+        assert!(decode_follow_account(&data).is_none());
+    }
+
+    #[test]
+    fn test_decode_profile_account() {
+        let wallet = Pubkey::new_unique();
+        let mut data = account_discriminator("Profile").to_vec();
+        data.extend_from_slice(wallet.as_ref());
+        data.extend_from_slice(&4u32.to_le_bytes());
+        data.extend_from_slice(b"ipfs");
+        data.push(1);
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.extend_from_slice(&1_700_000_500i64.to_le_bytes());
+        let legacy = data.clone();
+        data.extend_from_slice(&3u64.to_le_bytes());
+        data.extend_from_slice(&2u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&5u32.to_le_bytes());
+        data.extend_from_slice(b"shade");
+
+        let decoded = decode_profile_account(&data).unwrap();
+        assert_eq!((decoded.wallet, decoded.profile_cid.as_str(), decoded.is_public), (wallet, "ipfs", true));
+        assert_eq!((decoded.follower_count, decoded.following_count), (3, 2));
+        assert_eq!(decoded.username.as_deref(), Some("shade"));
+
+        // Not yet migrated: no counters or username
+        let decoded = decode_profile_account(&legacy).unwrap();
+        assert_eq!((decoded.follower_count, decoded.following_count, decoded.username), (0, 0, None));
+        assert!(decode_profile_account(&legacy[..60]).is_none());
+    }
+
+    #[test]
+    fn test_decode_rejects_other_accounts() {
+        let mut data = encode_site(&SiteAccount {
+            owner: Pubkey::new_unique(),
+            program_address: Pubkey::new_unique(),
+            name: String::new(),
+            description: String::new(),
+            storage_cid: String::new(),
+            created_at: 0,
+            updated_at: 0,
+            version: 0,
+            editors: Vec::new(),
+        });
+        assert!(decode_site_account(&data[..40]).is_none());
+        data[0] ^= 0xff;
+        assert!(decode_site_account(&data).is_none());
+    }
+}
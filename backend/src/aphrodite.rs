@@ -2,11 +2,23 @@
 // Handles NFT operations, metadata, and transfers
 
 use mongodb::{Collection, Database};
-use mongodb::bson::doc;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::ReplaceOptions;
+use mpl_token_metadata::accounts::Metadata;
 use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
+use std::env;
 use std::sync::Arc;
 use std::str::FromStr;
+use std::time::Duration;
+
+/// Largest page the DAS API returns for getAssetsByOwner
+const DAS_PAGE_LIMIT: u64 = 1000;
+
+/// How long cached NFT metadata is served before it is read from the chain again
+pub const NFT_METADATA_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const OFF_CHAIN_JSON_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NFT {
@@ -17,6 +29,9 @@ pub struct NFT {
     pub image_uri: Option<String>,
     pub collection: Option<String>,
     pub owner: String, // Current owner pubkey
+    /// Compressed (Bubblegum) NFT, held in a Merkle tree rather than a token account
+    #[serde(default)]
+    pub compressed: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,30 +41,275 @@ pub struct NFTTransferRequest {
     pub destination: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NFTMetadata {
     pub mint: String,
     pub name: String,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// Off-chain JSON link from the metadata account
+    #[serde(default)]
+    pub uri: Option<String>,
     pub description: Option<String>,
     pub image: Option<String>,
     pub attributes: Vec<Attribute>,
     pub collection: Option<String>,
+    /// Read from a Metaplex metadata account; false for placeholders
+    #[serde(default)]
+    pub on_chain: bool,
+    #[serde(default)]
+    pub fetched_at: Option<DateTime>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Attribute {
     pub trait_type: String,
     pub value: String,
 }
 
+/// Off-chain metadata JSON (Metaplex token standard)
+#[derive(Debug, Default, Deserialize)]
+struct OffChainMetadata {
+    name: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+    #[serde(default)]
+    attributes: Vec<OffChainAttribute>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OffChainAttribute {
+    trait_type: Option<String>,
+    value: serde_json::Value,
+}
+
+impl OffChainAttribute {
+    fn into_attribute(self) -> Attribute {
+        Attribute {
+            trait_type: self.trait_type.unwrap_or_default(),
+            // Values are strings or numbers in practice; keep either as text
+            value: match self.value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            },
+        }
+    }
+}
+
+/// On-chain strings are fixed width and padded with NULs
+fn trim_padding(s: &str) -> String {
+    s.trim_end_matches('\0').trim().to_string()
+}
+
+/// Digital Asset Standard `getAssetsByOwner` page
+#[derive(Debug, Deserialize)]
+struct DasAssetPage {
+    total: u64,
+    limit: u64,
+    page: u64,
+    #[serde(default)]
+    items: Vec<DasAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasAsset {
+    id: String,
+    #[serde(default)]
+    content: Option<DasContent>,
+    #[serde(default)]
+    grouping: Vec<DasGrouping>,
+    #[serde(default)]
+    compression: Option<DasCompression>,
+    ownership: DasOwnership,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasContent {
+    json_uri: Option<String>,
+    #[serde(default)]
+    metadata: Option<DasMetadata>,
+    #[serde(default)]
+    links: Option<DasLinks>,
+    #[serde(default)]
+    files: Vec<DasFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasMetadata {
+    name: Option<String>,
+    symbol: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasLinks {
+    image: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasFile {
+    uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasGrouping {
+    group_key: String,
+    group_value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasCompression {
+    compressed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasOwnership {
+    owner: String,
+}
+
+impl DasAsset {
+    fn is_compressed(&self) -> bool {
+        self.compression.as_ref().map(|c| c.compressed).unwrap_or(false)
+    }
+
+    fn into_nft(self) -> NFT {
+        let compressed = self.is_compressed();
+        let metadata = self.content.as_ref().and_then(|c| c.metadata.as_ref());
+        let name = metadata.and_then(|m| m.name.clone()).filter(|n| !n.is_empty());
+        let symbol = metadata.and_then(|m| m.symbol.clone()).filter(|s| !s.is_empty());
+        let image_uri = self.content.as_ref().and_then(|c| {
+            c.links.as_ref().and_then(|l| l.image.clone())
+                .or_else(|| c.files.iter().find_map(|f| f.uri.clone()))
+        });
+        let collection = self.grouping.iter()
+            .find(|g| g.group_key == "collection")
+            .map(|g| g.group_value.clone());
+
+        NFT {
+            name: name.unwrap_or_else(|| "Unknown NFT".to_string()),
+            symbol: symbol.unwrap_or_else(|| "NFT".to_string()),
+            uri: self.content.and_then(|c| c.json_uri),
+            image_uri,
+            collection,
+            owner: self.ownership.owner,
+            mint: self.id,
+            compressed,
+        }
+    }
+}
+
+impl NFTMetadata {
+    /// Stand-in for a mint without a metadata account
+    fn placeholder(mint: &str) -> Self {
+        Self {
+            mint: mint.to_string(),
+            name: "Unknown NFT".to_string(),
+            symbol: None,
+            uri: None,
+            description: None,
+            image: None,
+            attributes: Vec::new(),
+            collection: None,
+            on_chain: false,
+            fetched_at: Some(DateTime::now()),
+        }
+    }
+}
+
 pub struct AphroditeNFTManager {
     db: Arc<Database>,
     solana_rpc_url: String,
+    das_api_url: String,
+    das_api_key: Option<String>,
+    compressed_nfts_enabled: bool,
+    metadata_ttl: Duration,
 }
 
 impl AphroditeNFTManager {
     pub fn new(db: Arc<Database>, solana_rpc_url: String) -> Self {
-        Self { db, solana_rpc_url }
+        Self {
+            db,
+            solana_rpc_url,
+            das_api_url: env::var("DAS_API_URL")
+                .unwrap_or_else(|_| "https://mainnet.helius-rpc.com".to_string()),
+            das_api_key: env::var("HELIUS_API_KEY").ok().filter(|k| !k.is_empty()),
+            compressed_nfts_enabled: env::var("ENABLE_COMPRESSED_NFTS")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            metadata_ttl: NFT_METADATA_TTL,
+        }
+    }
+
+    pub fn with_metadata_ttl(mut self, ttl: Duration) -> Self {
+        self.metadata_ttl = ttl;
+        self
+    }
+
+    /// Point compressed NFT lookups at a specific DAS endpoint and enable them
+    pub fn with_das_api(mut self, url: String, api_key: Option<String>) -> Self {
+        self.das_api_url = url;
+        self.das_api_key = api_key;
+        self.compressed_nfts_enabled = true;
+        self
+    }
+
+    /// Get compressed NFTs owned by a wallet from the DAS API. These live in
+    /// Merkle trees and never show up in getTokenAccountsByOwner.
+    pub async fn get_compressed_nfts(&self, wallet_pubkey: &str) -> Result<Vec<NFT>, String> {
+        Pubkey::from_str(wallet_pubkey)
+            .map_err(|_| "Invalid pubkey".to_string())?;
+        let api_key = self.das_api_key.as_ref()
+            .ok_or_else(|| "DAS API key not configured".to_string())?;
+
+        let client = reqwest::Client::new();
+        let url = format!("{}/?api-key={}", self.das_api_url.trim_end_matches('/'), api_key);
+        let mut nfts = Vec::new();
+        let mut page = 1u64;
+
+        loop {
+            let body = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": "shadow",
+                "method": "getAssetsByOwner",
+                "params": {
+                    "ownerAddress": wallet_pubkey,
+                    "page": page,
+                    "limit": DAS_PAGE_LIMIT,
+                }
+            });
+
+            let response = client
+                .post(&url)
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| format!("DAS API error: {}", e))?;
+
+            if !response.status().is_success() {
+                return Err(format!("DAS API error: {}", response.status()));
+            }
+
+            let json: serde_json::Value = response.json().await
+                .map_err(|e| format!("Failed to parse DAS response: {}", e))?;
+            if let Some(error) = json.get("error") {
+                return Err(format!("DAS API error: {}", error));
+            }
+            let result: DasAssetPage = serde_json::from_value(json["result"].clone())
+                .map_err(|e| format!("Failed to parse DAS response: {}", e))?;
+
+            let last_page = result.items.is_empty() || result.total <= result.limit * result.page;
+            nfts.extend(
+                result.items.into_iter()
+                    .filter(|asset| asset.is_compressed())
+                    .map(DasAsset::into_nft)
+            );
+
+            if last_page {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(nfts)
     }
 
     /// Get all NFTs owned by a wallet
@@ -80,51 +340,142 @@ impl AphroditeNFTManager {
                         .map(|m| m.name.clone())
                         .unwrap_or_else(|| "Unknown NFT".to_string()),
                     symbol: metadata.as_ref()
-                        .and_then(|m| m.collection.clone())
+                        .and_then(|m| m.symbol.clone())
                         .unwrap_or_else(|| "NFT".to_string()),
-                    uri: metadata.as_ref().and_then(|m| m.image.clone()),
+                    uri: metadata.as_ref().and_then(|m| m.uri.clone()),
                     image_uri: metadata.as_ref().and_then(|m| m.image.clone()),
                     collection: metadata.as_ref().and_then(|m| m.collection.clone()),
                     owner: wallet_pubkey.to_string(),
+                    compressed: false,
                 });
             }
         }
 
+        if self.compressed_nfts_enabled && self.das_api_key.is_some() {
+            match self.get_compressed_nfts(wallet_pubkey).await {
+                Ok(compressed) => {
+                    for nft in compressed {
+                        if !nfts.iter().any(|n| n.mint == nft.mint) {
+                            nfts.push(nft);
+                        }
+                    }
+                }
+                // Regular NFTs are still worth returning when the DAS API is down
+                Err(e) => tracing::warn!("Skipping compressed NFTs for {}: {}", wallet_pubkey, e),
+            }
+        }
+
         Ok(nfts)
     }
 
-    /// Get NFT metadata (cached in database)
+    /// Get NFT metadata from its Metaplex metadata account and off-chain JSON.
+    /// Cached in the database and refreshed once older than the metadata TTL.
     pub async fn get_nft_metadata(&self, mint: &str) -> Result<NFTMetadata, String> {
         let collection: Collection<NFTMetadata> = self.db.collection("nft_metadata");
+        let mint_pubkey = Pubkey::from_str(mint)
+            .map_err(|_| "Invalid mint".to_string())?;
 
-        // Check cache first
-        if let Some(cached) = collection
+        let cached = collection
             .find_one(doc! { "mint": mint }, None)
             .await
-            .map_err(|e| format!("Database error: {}", e))? {
-            return Ok(cached);
+            .map_err(|e| format!("Database error: {}", e))?;
+        if let Some(cached) = &cached {
+            let fresh = cached.fetched_at.is_some_and(|at| {
+                DateTime::now().timestamp_millis() - at.timestamp_millis() < self.metadata_ttl.as_millis() as i64
+            });
+            if fresh {
+                return Ok(cached.clone());
+            }
         }
 
-        // Fetch from on-chain (Metaplex Token Metadata)
-        // For now, return basic metadata
-        let metadata = NFTMetadata {
-            mint: mint.to_string(),
-            name: "Unknown NFT".to_string(),
-            description: None,
-            image: None,
-            attributes: Vec::new(),
-            collection: None,
+        let metadata = match self.fetch_on_chain_metadata(&mint_pubkey).await {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => match cached {
+                // A missing account never replaces metadata we already read successfully
+                Some(cached) if cached.on_chain => return Ok(cached),
+                _ => NFTMetadata::placeholder(mint),
+            },
+            Err(e) => match cached {
+                // Serve stale metadata rather than nothing while the RPC is down
+                Some(cached) => {
+                    tracing::warn!("Serving stale metadata for {}: {}", mint, e);
+                    return Ok(cached);
+                }
+                None => return Err(e),
+            },
         };
 
-        // Cache it
         collection
-            .insert_one(&metadata, None)
+            .replace_one(
+                doc! { "mint": mint },
+                &metadata,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
         Ok(metadata)
     }
 
+    /// Read the metadata account for `mint`; None when it has none
+    async fn fetch_on_chain_metadata(&self, mint: &Pubkey) -> Result<Option<NFTMetadata>, String> {
+        use crate::solana::SolanaClient;
+        let client = SolanaClient::new(self.solana_rpc_url.clone());
+
+        let (address, _) = Metadata::find_pda(mint);
+        let account = match client.get_account(&address).await? {
+            Some(account) if account.owner == mpl_token_metadata::ID => account,
+            _ => return Ok(None),
+        };
+        let on_chain = Metadata::from_bytes(&account.data)
+            .map_err(|e| format!("Invalid metadata account {}: {}", address, e))?;
+
+        let uri = Some(trim_padding(&on_chain.uri)).filter(|u| !u.is_empty());
+        let off_chain = match &uri {
+            Some(uri) => self.fetch_off_chain_metadata(uri).await.unwrap_or_else(|e| {
+                tracing::warn!("Off-chain metadata for {} unavailable: {}", mint, e);
+                OffChainMetadata::default()
+            }),
+            None => OffChainMetadata::default(),
+        };
+
+        let name = Some(trim_padding(&on_chain.name))
+            .filter(|n| !n.is_empty())
+            .or(off_chain.name.filter(|n| !n.is_empty()))
+            .unwrap_or_else(|| "Unknown NFT".to_string());
+
+        Ok(Some(NFTMetadata {
+            mint: mint.to_string(),
+            name,
+            symbol: Some(trim_padding(&on_chain.symbol)).filter(|s| !s.is_empty()),
+            uri,
+            description: off_chain.description,
+            image: off_chain.image,
+            attributes: off_chain.attributes.into_iter().map(OffChainAttribute::into_attribute).collect(),
+            collection: on_chain.collection.map(|c| c.key.to_string()),
+            on_chain: true,
+            fetched_at: Some(DateTime::now()),
+        }))
+    }
+
+    async fn fetch_off_chain_metadata(&self, uri: &str) -> Result<OffChainMetadata, String> {
+        let client = reqwest::Client::builder()
+            .timeout(OFF_CHAIN_JSON_TIMEOUT)
+            .build()
+            .map_err(|e| format!("HTTP client error: {}", e))?;
+        let response = client
+            .get(uri)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch {}: {}", uri, e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Failed to fetch {}: {}", uri, response.status()));
+        }
+        response.json().await
+            .map_err(|e| format!("Invalid metadata JSON at {}: {}", uri, e))
+    }
+
     /// Create NFT transfer transaction
     pub async fn create_transfer_transaction(
         &self,
@@ -1,4 +1,5 @@
-use actix_web::{HttpResponse, Responder};
+use actix_web::{web, HttpResponse, Responder};
+use crate::{handlers, handlers_link, wallet_handlers, websocket};
 
 pub async fn health() -> impl Responder {
     HttpResponse::Ok().json(serde_json::json!({
@@ -7,3 +8,160 @@ pub async fn health() -> impl Responder {
     }))
 }
 
+/// Register every API route; mounted under both /api and /api/v2
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg
+        .route("/health", web::get().to(health))
+        .route("/auth/token", web::post().to(handlers::issue_auth_token))
+        .route("/profiles/search", web::get().to(handlers::search_profiles))
+        .route("/profiles/batch", web::post().to(handlers::batch_profiles))
+        .route("/profiles/{wallet}", web::get().to(handlers::get_profile))
+        .route("/profiles/{wallet}/followers", web::get().to(handlers::get_followers))
+        .route("/profiles/{wallet}/following", web::get().to(handlers::get_following))
+        .route("/profiles/{wallet}/follow", web::post().to(handlers::follow_profile))
+        .route("/profiles/{wallet}/follow", web::delete().to(handlers::unfollow_profile))
+        .route("/profiles", web::post().to(handlers::create_profile_route))
+        .route("/profiles/{wallet}", web::put().to(handlers::update_profile))
+        .route("/profiles/{wallet}", web::delete().to(handlers::delete_profile))
+        .route("/sites/search", web::get().to(handlers::search_sites))
+        .route("/sites/mine", web::get().to(handlers::list_my_sites))
+        .route("/sites/{program_address}", web::get().to(handlers::get_site))
+        .route("/sites", web::post().to(handlers::register_site))
+        .route("/sites/{program_address}", web::put().to(handlers::update_site))
+        .route("/sites/{program_address}", web::patch().to(handlers::patch_site))
+        .route("/sites/{program_address}/content", web::get().to(handlers::get_site_content))
+        .route("/sites/{program_address}/content/{path:.*}", web::get().to(handlers::get_site_content_path))
+        .route("/sites/{program_address}/token", web::get().to(handlers_link::get_site_token))
+        .route("/sites/{program_address}/content", web::post().to(handlers::publish_site_content))
+        .route("/sites/{program_address}/deploys", web::post().to(handlers::stage_deploy))
+        .route("/sites/{program_address}/deploy-tokens", web::post().to(handlers::create_deploy_token))
+        .route("/sites/{program_address}/deploy-tokens", web::get().to(handlers::list_deploy_tokens))
+        .route("/sites/{program_address}/deploy-tokens/{token_id}", web::delete().to(handlers::revoke_deploy_token))
+        .route("/sites/{program_address}/versions", web::get().to(handlers::list_site_versions))
+        .route("/sites/{program_address}/preview", web::get().to(handlers::get_site_preview))
+        .route("/sites/{program_address}/assets/{path:.*}", web::get().to(handlers::get_site_asset))
+        .route("/sites/{program_address}/backlinks", web::get().to(handlers::get_site_backlinks))
+        .route("/sites/{program_address}/ownership", web::get().to(handlers::get_site_ownership))
+        .route("/sites/{program_address}/ownership", web::put().to(handlers::set_site_ownership))
+        .route("/sites/{program_address}/collaborators", web::post().to(handlers::add_collaborator))
+        .route("/sites/{program_address}/collaborators/{wallet}", web::delete().to(handlers::remove_collaborator))
+        .route("/upload/ipfs", web::post().to(handlers::upload_ipfs))
+        .route("/upload/status/{cid}", web::get().to(handlers::get_upload_status))
+        .route("/upload/arweave", web::post().to(handlers::upload_arweave))
+        .route("/upload/arweave/estimate", web::get().to(handlers::estimate_arweave_upload))
+        .route("/upload/arweave/{tx_id}/status", web::get().to(handlers::get_arweave_upload_status))
+        .route("/sdk/deploy/plan", web::post().to(handlers::plan_deploy))
+        .route("/solana/search", web::get().to(handlers::search_solana))
+        // Olympus domain endpoints
+        .route("/domains/search", web::get().to(handlers::search_domains))
+        .route("/domains/{domain}", web::get().to(handlers::get_domain))
+        .route("/domains", web::post().to(handlers::register_domain))
+        .route("/domains/{domain}", web::put().to(handlers::update_domain))
+        .route("/domains/{domain}/verify", web::post().to(handlers::verify_domain))
+        .route("/domains/owner/{wallet}", web::get().to(handlers::list_owner_domains))
+        .route("/domains/{domain}/watch", web::post().to(handlers::watch_domain))
+        .route("/domains/{domain}/watch", web::delete().to(handlers::unwatch_domain))
+        .route("/watchlist", web::get().to(handlers::get_watchlist))
+        // Charon legacy web bridges
+        .route("/bridge", web::post().to(handlers::create_bridge))
+        .route("/bridge/key", web::get().to(handlers::get_bridge_signer))
+        .route("/bridge/assert/{legacy_domain}", web::get().to(handlers::get_bridge_assertion))
+        .route("/bridge/{legacy_domain}/verify", web::post().to(handlers::verify_bridge))
+        // Athena search endpoints
+        .route("/search", web::get().to(handlers::search_content))
+        .route("/search/index", web::post().to(handlers::index_content))
+        .route("/search/health", web::get().to(handlers::search_index_health))
+        .route("/search/click", web::post().to(handlers::record_search_click))
+        .route("/diagnostics", web::get().to(handlers::get_diagnostics))
+        // Hecate GraphQL gateway (read-only, behind ENABLE_GRAPHQL)
+        .route("/graphql", web::post().to(handlers::graphql))
+        // Chronos history/bookmarks endpoints
+        .route("/history", web::get().to(handlers::get_history))
+        .route("/history", web::post().to(handlers::record_visit))
+        .route("/history", web::delete().to(handlers::clear_history))
+        .route("/history/summary", web::get().to(handlers::get_history_summary))
+        .route("/history/{domain}", web::delete().to(handlers::delete_domain_history))
+        .route("/settings/privacy", web::get().to(handlers::get_privacy_settings))
+        .route("/settings/privacy", web::put().to(handlers::update_privacy_settings))
+        .route("/bookmarks", web::get().to(handlers::get_bookmarks))
+        .route("/bookmarks", web::post().to(handlers::add_bookmark))
+        .route("/bookmarks/export", web::get().to(handlers::export_bookmarks))
+        .route("/bookmarks/import", web::post().to(handlers::import_bookmarks))
+        .route("/bookmarks/{domain}", web::delete().to(handlers::remove_bookmark))
+        .route("/sessions", web::post().to(handlers::create_session))
+        .route("/sessions/active", web::get().to(handlers::get_active_sessions))
+        .route("/sessions/merge", web::post().to(handlers::merge_sessions))
+        // Prometheus analytics endpoints
+        .route("/analytics/{domain}", web::get().to(handlers::get_analytics))
+        .route("/analytics/{domain}/export", web::get().to(handlers::export_analytics))
+        .route("/analytics/{domain}/daily", web::get().to(handlers::get_daily_stats))
+        .route("/analytics/top", web::get().to(handlers::get_top_sites))
+        .route("/analytics/performance", web::post().to(handlers::record_performance))
+        // Pheme embeddable badges (public)
+        .route("/badge/{domain}.svg", web::get().to(handlers::get_badge_svg))
+        .route("/badge/{domain}.json", web::get().to(handlers::get_badge_json))
+        // Hephaestus cache endpoints
+        .route("/cache/stats", web::get().to(handlers::get_cache_stats))
+        .route("/cache/clear", web::post().to(handlers::clear_cache))
+        .route("/metrics", web::get().to(handlers::get_metrics))
+        .route("/ws", web::get().to(websocket::ws_handler))
+        // Wallet dApp endpoints (Phantom-like)
+        // Zeus - Wallet Management
+        .route("/wallet/create", web::post().to(wallet_handlers::create_wallet))
+        .route("/wallet/import", web::post().to(wallet_handlers::import_wallet))
+        .route("/wallet/import/mnemonic", web::post().to(wallet_handlers::import_wallet_mnemonic))
+        .route("/wallet/list", web::get().to(wallet_handlers::list_wallets))
+        .route("/wallet/active", web::get().to(wallet_handlers::get_active_wallet))
+        .route("/wallet/active", web::post().to(wallet_handlers::set_active_wallet))
+        .route("/wallet/{wallet_id}", web::patch().to(wallet_handlers::rename_wallet))
+        .route("/wallet/{wallet_id}", web::delete().to(wallet_handlers::delete_wallet))
+        .route("/wallet/{wallet_id}/export", web::get().to(wallet_handlers::export_wallet_mnemonic))
+        // Poseidon - Transaction Signing
+        .route("/wallet/transaction", web::post().to(wallet_handlers::create_transaction))
+        .route("/wallet/transaction/sign", web::post().to(wallet_handlers::sign_transaction))
+        .route("/wallet/transaction/submit", web::post().to(wallet_handlers::submit_transaction))
+        .route("/wallet/transactions", web::get().to(wallet_handlers::get_transactions))
+        .route("/wallet/transactions/pending", web::get().to(wallet_handlers::get_pending_transactions))
+        // Hades - Security Settings
+        .route("/wallet/security-settings", web::get().to(wallet_handlers::get_security_settings))
+        .route("/wallet/security-settings", web::put().to(wallet_handlers::update_security_settings))
+        // Dionysus - Tokens
+        .route("/wallet/{pubkey}/tokens", web::get().to(wallet_handlers::get_token_balances))
+        .route("/wallet/tokens/ensure-ata", web::post().to(wallet_handlers::ensure_ata))
+        .route("/wallet/{pubkey}/cleanup", web::get().to(wallet_handlers::get_cleanup_suggestions))
+        .route("/wallet/cleanup/build", web::post().to(wallet_handlers::build_cleanup))
+        // Aphrodite - NFTs
+        .route("/wallet/{pubkey}/nfts", web::get().to(wallet_handlers::get_nfts))
+        // Hestia - dApp Connections
+        .route("/wallet/dapp/connect", web::post().to(wallet_handlers::connect_dapp))
+        .route("/wallet/dapp/connections", web::get().to(wallet_handlers::get_connections))
+        .route("/wallet/dapp/disconnect", web::post().to(wallet_handlers::disconnect_dapp))
+        // Argus - Origin Reputation
+        .route("/wallet/dapp/reputation", web::get().to(wallet_handlers::assess_origin))
+        .route("/admin/dapps", web::post().to(wallet_handlers::register_known_dapp))
+        .route("/admin/dapps/block", web::post().to(wallet_handlers::block_origin))
+        // Mnemosyne - Outbox
+        .route("/admin/outbox", web::get().to(handlers::list_outbox))
+        .route("/admin/outbox/{id}/retry", web::post().to(handlers::retry_outbox_entry))
+        // Tyche - Site ownership recovery
+        .route("/admin/sites/{program_address}/ownership/recover", web::post().to(handlers::recover_site_ownership))
+        // Clio - Search analytics
+        .route("/admin/search/top-queries", web::get().to(handlers::get_top_search_queries))
+        .route("/admin/search/zero-result-queries", web::get().to(handlers::get_zero_result_search_queries))
+        // Plutus - Portfolio
+        .route("/wallet/{pubkey}/portfolio", web::get().to(wallet_handlers::get_portfolio))
+        .route("/wallet/{pubkey}/portfolio/at", web::get().to(wallet_handlers::get_portfolio_at))
+        .route("/wallet/{pubkey}/history", web::get().to(wallet_handlers::get_transaction_history))
+        // Link Converter - Token-only domains
+        .route("/links", web::get().to(handlers_link::list_links))
+        .route("/links/convert", web::post().to(handlers_link::convert_link))
+        .route("/links/general-token", web::post().to(handlers_link::create_general_token))
+        .route("/links/token/{token_mint}", web::get().to(handlers_link::get_url_from_token))
+        .route("/links/resolve", web::post().to(handlers_link::get_token_from_url))
+        // Original paths, kept for existing clients
+        .route("/convert/link", web::post().to(handlers_link::convert_link))
+        .route("/convert/general-token", web::post().to(handlers_link::create_general_token))
+        .route("/convert/token/{token_mint}", web::get().to(handlers_link::get_url_from_token))
+        .route("/convert/url", web::post().to(handlers_link::get_token_from_url))
+        .route("/linkinfo", web::get().to(handlers_link::get_link_info));
+}
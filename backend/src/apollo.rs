@@ -1,92 +1,108 @@
 // Apollo - God of Truth and Light
 // Handles input validation, sanitization, and truth verification
 
+use crate::error::ShadowError;
+use regex::Regex;
 use solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::sync::OnceLock;
 
 pub struct ApolloValidator;
 
+/// Where a storage CID points
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageCidKind {
+    IpfsV0,
+    IpfsV1,
+    Arweave,
+}
+
 impl ApolloValidator {
     pub fn new() -> Self {
         Self
     }
 
     /// Validate Solana wallet address (pubkey)
-    pub fn validate_pubkey(pubkey: &str) -> Result<Pubkey, String> {
+    pub fn validate_pubkey(pubkey: &str) -> Result<Pubkey, ShadowError> {
         Pubkey::from_str(pubkey)
-            .map_err(|e| format!("Invalid Solana pubkey: {}", e))
+            .map_err(|e| ShadowError::BadRequest(format!("Invalid Solana pubkey: {}", e)))
     }
 
     /// Validate domain name format
     /// Supports both .shadow domains and custom domains
-    pub fn validate_domain(domain: &str) -> Result<(), String> {
-        // Check if it's a .shadow domain
+    pub fn validate_domain(domain: &str) -> Result<(), ShadowError> {
         if domain.ends_with(".shadow") {
-            let domain_name = domain.strip_suffix(".shadow").unwrap();
-            
-            if domain_name.is_empty() {
-                return Err("Domain name cannot be empty".to_string());
-            }
-
-            if domain_name.len() > 63 {
-                return Err("Domain name too long (max 63 characters)".to_string());
+            if !shadow_domain_pattern().is_match(domain) {
+                return Err(ShadowError::BadRequest(format!(
+                    "Invalid .shadow domain {}: use 2-63 lowercase letters, digits or inner hyphens",
+                    domain
+                )));
             }
+            return Ok(());
+        }
 
-            if !domain_name.chars().all(|c| c.is_alphanumeric() || c == '-') {
-                return Err("Domain name can only contain alphanumeric characters and hyphens".to_string());
-            }
+        // Custom domain validation (more permissive)
+        if domain.is_empty() {
+            return Err(ShadowError::BadRequest("Domain cannot be empty".to_string()));
+        }
 
-            if domain_name.starts_with('-') || domain_name.ends_with('-') {
-                return Err("Domain name cannot start or end with a hyphen".to_string());
-            }
-        } else {
-            // Custom domain validation (more permissive)
-            if domain.is_empty() {
-                return Err("Domain cannot be empty".to_string());
-            }
+        if domain.len() > 253 {
+            return Err(ShadowError::BadRequest("Domain too long (max 253 characters)".to_string()));
+        }
 
-            if domain.len() > 253 {
-                return Err("Domain too long (max 253 characters)".to_string());
-            }
+        // Basic domain format validation
+        let parts: Vec<&str> = domain.split('.').collect();
+        if parts.len() < 2 {
+            return Err(ShadowError::BadRequest("Domain must have at least a TLD".to_string()));
+        }
 
-            // Basic domain format validation
-            let parts: Vec<&str> = domain.split('.').collect();
-            if parts.len() < 2 {
-                return Err("Domain must have at least a TLD".to_string());
+        for part in &parts {
+            if part.is_empty() {
+                return Err(ShadowError::BadRequest("Domain parts cannot be empty".to_string()));
             }
-
-            for part in &parts {
-                if part.is_empty() {
-                    return Err("Domain parts cannot be empty".to_string());
-                }
-                if part.len() > 63 {
-                    return Err("Domain part too long (max 63 characters)".to_string());
-                }
+            if part.len() > 63 {
+                return Err(ShadowError::BadRequest("Domain part too long (max 63 characters)".to_string()));
             }
         }
 
         Ok(())
     }
 
-    /// Validate IPFS CID format (basic check)
-    pub fn validate_ipfs_cid(cid: &str) -> Result<(), String> {
-        if cid.starts_with("ipfs://") {
-            let hash = cid.strip_prefix("ipfs://").unwrap();
-            if hash.is_empty() {
-                return Err("IPFS CID cannot be empty".to_string());
-            }
-            // Basic validation - IPFS CIDs are base58 encoded
-            if hash.len() < 10 || hash.len() > 100 {
-                return Err("Invalid IPFS CID length".to_string());
-            }
-        } else if !cid.is_empty() {
-            // Allow non-prefixed CIDs
-            if cid.len() < 10 || cid.len() > 100 {
-                return Err("Invalid IPFS CID length".to_string());
-            }
+    /// Validate an IPFS CID (v0 or v1), optionally `ipfs://`-prefixed and followed by a path
+    pub fn validate_ipfs_cid(cid: &str) -> Result<(), ShadowError> {
+        let hash = cid.strip_prefix("ipfs://").unwrap_or(cid);
+        let root = hash.split('/').next().unwrap_or_default();
+        if root.is_empty() {
+            return Err(ShadowError::BadRequest("IPFS CID cannot be empty".to_string()));
         }
 
-        Ok(())
+        parse_cid(root)
+            .map_err(|e| ShadowError::BadRequest(format!("Invalid IPFS CID {}: {}", root, e)))
+    }
+
+    /// Validate a site's storage CID: an IPFS CIDv0 (`Qm…`), a base32 CIDv1 (`bafy…`/`bafk…`)
+    /// or an Arweave transaction id. `ipfs://` and `arweave://` prefixes pin the protocol,
+    /// and a path may follow the root.
+    pub fn validate_storage_cid(cid: &str) -> Result<StorageCidKind, ShadowError> {
+        let (scheme, hash) = match cid.split_once("://") {
+            Some((scheme, hash)) => (Some(scheme), hash),
+            None => (None, cid),
+        };
+        let root = hash.split('/').next().unwrap_or_default();
+        if root.is_empty() {
+            return Err(ShadowError::BadRequest("Storage CID cannot be empty".to_string()));
+        }
+
+        let kind = match scheme {
+            Some("ipfs") => parse_storage_ipfs(root),
+            Some("arweave") => parse_arweave_tx(root).map(|_| StorageCidKind::Arweave),
+            Some(other) => Err(format!("unsupported scheme '{}'", other)),
+            // Arweave ids are never 46 characters and CIDv1s are longer than 43
+            None if root.len() == ARWEAVE_TX_LEN => parse_arweave_tx(root).map(|_| StorageCidKind::Arweave),
+            None => parse_storage_ipfs(root),
+        };
+        kind.map_err(|e| ShadowError::BadRequest(format!("Invalid storage CID {}: {}", root, e)))
     }
 
     /// Validate Arweave transaction ID format
@@ -100,9 +116,60 @@ impl ApolloValidator {
             if id.len() < 20 || id.len() > 100 {
                 return Err("Invalid Arweave transaction ID length".to_string());
             }
-        } else if !tx_id.is_empty() {
-            if tx_id.len() < 20 || tx_id.len() > 100 {
-                return Err("Invalid Arweave transaction ID length".to_string());
+        } else if !tx_id.is_empty() && (tx_id.len() < 20 || tx_id.len() > 100) {
+            return Err("Invalid Arweave transaction ID length".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Validate a BCP 47 style language tag, e.g. "en" or "pt-BR"
+    pub fn validate_language_tag(tag: &str) -> Result<(), String> {
+        let mut subtags = tag.split('-');
+        let primary = subtags.next().unwrap_or("");
+        if !(2..=8).contains(&primary.len()) || !primary.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(format!("Invalid language tag: {}", tag));
+        }
+        if !subtags.all(|s| (1..=8).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphanumeric())) {
+            return Err(format!("Invalid language tag: {}", tag));
+        }
+
+        Ok(())
+    }
+
+    /// Validate a shadow.json languages map (tag -> entry file relative to the site root)
+    pub fn validate_languages(
+        languages: &BTreeMap<String, String>,
+        default_language: Option<&str>,
+    ) -> Result<(), String> {
+        if languages.is_empty() {
+            return Err("Languages map cannot be empty".to_string());
+        }
+        if languages.len() > 50 {
+            return Err("Too many languages (max 50)".to_string());
+        }
+
+        for (tag, entry) in languages {
+            Self::validate_language_tag(tag)?;
+            if languages.keys().filter(|t| t.eq_ignore_ascii_case(tag)).count() > 1 {
+                return Err(format!("Duplicate language: {}", tag));
+            }
+            if entry.is_empty() || entry.len() > 256 {
+                return Err(format!("Invalid entry file for {}", tag));
+            }
+            if entry.starts_with('/')
+                || entry.contains('\\')
+                || entry.contains("://")
+                || entry.split('/').any(|segment| segment == "..")
+                || entry.chars().any(|c| c.is_control())
+            {
+                return Err(format!("Entry file for {} must be a path inside the site", tag));
+            }
+        }
+
+        if let Some(default) = default_language {
+            if !languages.contains_key(default) {
+                return Err(format!("Default language {} is not in the languages map", default));
             }
         }
 
@@ -124,35 +191,127 @@ impl ApolloValidator {
         Ok(sanitized)
     }
 
+    /// Validate a profile username: 3-32 characters of a-z, 0-9 and underscore.
+    /// Usernames are case-insensitive; returns the lowercase form the on-chain record is keyed on.
+    pub fn validate_username(name: &str) -> Result<String, ShadowError> {
+        let name = name.to_ascii_lowercase();
+        if !(3..=32).contains(&name.len())
+            || !name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+        {
+            return Err(ShadowError::BadRequest(format!(
+                "Invalid username {}: use 3-32 lowercase letters, digits or underscores",
+                name
+            )));
+        }
+        Ok(name)
+    }
+
     /// Validate search query
-    pub fn validate_search_query(query: &str) -> Result<(), String> {
+    pub fn validate_search_query(query: &str) -> Result<(), ShadowError> {
         if query.is_empty() {
-            return Err("Search query cannot be empty".to_string());
+            return Err(ShadowError::BadRequest("Search query cannot be empty".to_string()));
         }
 
-        if query.len() > 200 {
-            return Err("Search query too long (max 200 characters)".to_string());
+        if query.chars().count() > 200 {
+            return Err(ShadowError::BadRequest("Search query too long (max 200 characters)".to_string()));
         }
 
         Ok(())
     }
 
     /// Validate limit parameter
-    pub fn validate_limit(limit: Option<i64>) -> Result<i64, String> {
+    pub fn validate_limit(limit: Option<i64>) -> Result<i64, ShadowError> {
         let limit = limit.unwrap_or(10);
         
         if limit < 1 {
-            return Err("Limit must be at least 1".to_string());
+            return Err(ShadowError::BadRequest("Limit must be at least 1".to_string()));
         }
 
         if limit > 100 {
-            return Err("Limit cannot exceed 100".to_string());
+            return Err(ShadowError::BadRequest("Limit cannot exceed 100".to_string()));
         }
 
         Ok(limit)
     }
 }
 
+fn shadow_domain_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"^[a-z0-9][a-z0-9-]{0,61}[a-z0-9]\.shadow$").expect("valid domain pattern"))
+}
+
+/// Read one unsigned LEB128 varint as used by multiformats
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, String> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Ok(value);
+        }
+    }
+    Err("truncated varint".to_string())
+}
+
+/// Characters in an Arweave transaction id: 32 bytes of unpadded base64url
+const ARWEAVE_TX_LEN: usize = 43;
+
+fn parse_arweave_tx(tx_id: &str) -> Result<(), String> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    if tx_id.len() != ARWEAVE_TX_LEN {
+        return Err(format!("Arweave transaction ids are {} characters", ARWEAVE_TX_LEN));
+    }
+    URL_SAFE_NO_PAD.decode(tx_id).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// CIDv0, or a CIDv1 in the base32 multibase gateways serve (`bafy…` dag-pb, `bafk…` raw)
+fn parse_storage_ipfs(cid: &str) -> Result<StorageCidKind, String> {
+    if cid.starts_with("Qm") {
+        parse_cid(cid)?;
+        Ok(StorageCidKind::IpfsV0)
+    } else if cid.starts_with("bafy") || cid.starts_with("bafk") {
+        parse_cid(cid)?;
+        Ok(StorageCidKind::IpfsV1)
+    } else {
+        Err("expected a Qm… CIDv0, a bafy…/bafk… CIDv1 or an Arweave transaction id".to_string())
+    }
+}
+
+/// Structural CID check: a CIDv0 sha2-256 multihash, or a multibase CIDv1 with a complete multihash
+fn parse_cid(cid: &str) -> Result<(), String> {
+    if cid.starts_with("Qm") {
+        let bytes = bs58::decode(cid).into_vec().map_err(|e| e.to_string())?;
+        if cid.len() != 46 || bytes.len() != 34 || bytes[..2] != [0x12, 0x20] {
+            return Err("not a sha2-256 CIDv0".to_string());
+        }
+        return Ok(());
+    }
+
+    let mut chars = cid.chars();
+    let base = chars.next().ok_or("empty CID")?;
+    let data = chars.as_str();
+    let bytes = match base {
+        'b' if !data.chars().any(|c| c.is_ascii_uppercase()) => data_encoding::BASE32_NOPAD
+            .decode(data.to_ascii_uppercase().as_bytes())
+            .map_err(|e| e.to_string())?,
+        'z' => bs58::decode(data).into_vec().map_err(|e| e.to_string())?,
+        'f' if !data.chars().any(|c| c.is_ascii_uppercase()) => hex::decode(data).map_err(|e| e.to_string())?,
+        _ => return Err(format!("unsupported multibase prefix '{}'", base)),
+    };
+
+    let mut rest = bytes.as_slice();
+    if read_varint(&mut rest)? != 1 {
+        return Err("unsupported CID version".to_string());
+    }
+    read_varint(&mut rest)?; // content codec
+    read_varint(&mut rest)?; // multihash function
+    let digest_len = read_varint(&mut rest)?;
+    if digest_len == 0 || rest.len() as u64 != digest_len {
+        return Err("multihash length mismatch".to_string());
+    }
+    Ok(())
+}
+
 impl Default for ApolloValidator {
     fn default() -> Self {
         Self::new()
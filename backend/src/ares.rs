@@ -6,13 +6,105 @@ use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
 use sha2::{Sha256, Digest};
 use serde::{Deserialize, Serialize};
+use actix_web::HttpRequest;
+use dashmap::DashMap;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+use crate::clock::{SharedClock, SystemClock};
+use crate::error::ShadowError;
+
+/// How far a signed challenge's timestamp may be from now, either way
+pub const CHALLENGE_MAX_AGE_SECONDS: i64 = 300;
+
+/// How long a used nonce is remembered past the moment its challenge expires
+const NONCE_RETENTION_MARGIN: Duration = Duration::from_secs(60);
+
+/// Upper bound on remembered nonces; signed headers are refused while it is full
+pub const MAX_TRACKED_NONCES: usize = 100_000;
 
 #[derive(Debug, Clone)]
-pub struct AresAuth;
+pub struct AresAuth {
+    jwt_secret: Vec<u8>,
+    token_ttl_seconds: i64,
+    clock: SharedClock,
+    /// Nonces of accepted signed headers, keyed by wallet and nonce, until they can be forgotten
+    used_nonces: Arc<DashMap<String, Instant>>,
+}
+
+/// Claims carried by session tokens issued from a signed challenge
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AresClaims {
+    pub sub: String,
+    pub iat: i64,
+    pub exp: i64,
+}
 
 impl AresAuth {
+    /// Tokens are signed with a random per-process secret unless one is configured
     pub fn new() -> AresAuth {
-        AresAuth
+        AresAuth {
+            jwt_secret: (0..32).map(|_| rand::random::<u8>()).collect(),
+            token_ttl_seconds: 3600,
+            clock: SystemClock::shared(),
+            used_nonces: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_jwt_secret(mut self, secret: &str) -> Self {
+        self.jwt_secret = secret.as_bytes().to_vec();
+        self
+    }
+
+    pub fn with_token_ttl(mut self, ttl_seconds: i64) -> Self {
+        self.token_ttl_seconds = ttl_seconds;
+        self
+    }
+
+    /// Issue a session token for a wallet that has already proven ownership.
+    /// Returns the token and its expiry as a unix timestamp.
+    pub fn issue_token(&self, wallet: &str) -> Result<(String, i64), String> {
+        let now = self.clock.now_utc().timestamp();
+        let claims = AresClaims {
+            sub: wallet.to_string(),
+            iat: now,
+            exp: now + self.token_ttl_seconds,
+        };
+
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(&self.jwt_secret),
+        )
+        .map_err(|e| format!("Failed to issue token: {}", e))?;
+
+        Ok((token, claims.exp))
+    }
+
+    /// Verify a session token and return the wallet it was issued to
+    pub fn verify_token(&self, token: &str) -> Result<String, String> {
+        // Expiry is checked against our clock rather than jsonwebtoken's
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_exp = false;
+
+        let data = decode::<AresClaims>(
+            token,
+            &DecodingKey::from_secret(&self.jwt_secret),
+            &validation,
+        )
+        .map_err(|_| "Invalid token".to_string())?;
+
+        if data.claims.exp < self.clock.now_utc().timestamp() {
+            return Err("Token expired".to_string());
+        }
+        Ok(data.claims.sub)
     }
 
     /// Verify a Solana wallet signature
@@ -64,12 +156,12 @@ impl AresAuth {
         let ed_sig = EdSignature::from_bytes(&sig_array)
             .map_err(|e| format!("Invalid signature: {}", e))?;
 
-        Ok(public_key.verify(&message_hash.as_slice(), &ed_sig).is_ok())
+        Ok(public_key.verify(&message_hash[..], &ed_sig).is_ok())
     }
 
     /// Create a challenge message for the client to sign
-    pub fn create_challenge(wallet: &str, timestamp: i64) -> String {
-        format!("Shadow authentication challenge for {} at {}", wallet, timestamp)
+    pub fn create_challenge(wallet: &str, timestamp: i64, nonce: &str) -> String {
+        format!("Shadow authentication challenge for {} at {} with nonce {}", wallet, timestamp, nonce)
     }
 
     /// Verify a signed challenge
@@ -78,10 +170,55 @@ impl AresAuth {
         wallet: &str,
         signature: &str,
         timestamp: i64,
+        nonce: &str,
     ) -> Result<bool, String> {
-        let challenge = Self::create_challenge(wallet, timestamp);
+        let challenge = Self::create_challenge(wallet, timestamp, nonce);
         self.verify_signature(challenge.as_bytes(), signature, wallet)
     }
+
+    /// Record a nonce as used. It is remembered until the challenge carrying it expires,
+    /// plus a margin, so a captured header cannot be replayed while its timestamp is accepted.
+    fn consume_nonce(&self, wallet: &str, nonce: &Uuid, timestamp: i64) -> Result<(), String> {
+        let now = self.clock.now_instant();
+        let remaining = timestamp + CHALLENGE_MAX_AGE_SECONDS - self.clock.now_utc().timestamp();
+        let forget_at = now + Duration::from_secs(remaining.max(0) as u64) + NONCE_RETENTION_MARGIN;
+
+        if self.used_nonces.len() >= MAX_TRACKED_NONCES && self.evict_expired_nonces() == 0 {
+            return Err("Too many recent challenges, try again shortly".to_string());
+        }
+        match self.used_nonces.entry(format!("{}:{}", wallet, nonce)) {
+            dashmap::mapref::entry::Entry::Occupied(_) => Err("Challenge already used".to_string()),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(forget_at);
+                Ok(())
+            }
+        }
+    }
+
+    /// Forget nonces whose challenges can no longer be accepted. Returns how many were dropped.
+    pub fn evict_expired_nonces(&self) -> usize {
+        let now = self.clock.now_instant();
+        let before = self.used_nonces.len();
+        self.used_nonces.retain(|_, forget_at| *forget_at > now);
+        before - self.used_nonces.len()
+    }
+
+    /// Sweep used nonces once a minute until shutdown
+    pub fn spawn_nonce_eviction(self: Arc<Self>, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(NONCE_RETENTION_MARGIN);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                let removed = self.evict_expired_nonces();
+                if removed > 0 {
+                    tracing::debug!("Ares forgot {} used challenge nonces", removed);
+                }
+            }
+        })
+    }
 }
 
 impl Default for AresAuth {
@@ -95,6 +232,8 @@ pub struct AuthHeader {
     pub wallet: String,
     pub signature: String,
     pub timestamp: i64,
+    /// UUID the client generates for each request; a header is accepted once
+    pub nonce: String,
 }
 
 impl AuthHeader {
@@ -105,14 +244,52 @@ impl AuthHeader {
     }
 
     /// Verify the auth header
-    pub fn verify(&self, ares: &AresAuth) -> Result<bool, String> {
+    pub fn verify(&self, ares: &AresAuth) -> Result<(), String> {
         // Check timestamp is recent (within 5 minutes)
-        let now = chrono::Utc::now().timestamp();
-        if (now - self.timestamp).abs() > 300 {
+        let now = ares.clock.now_utc().timestamp();
+        if (now - self.timestamp).abs() > CHALLENGE_MAX_AGE_SECONDS {
             return Err("Challenge expired".to_string());
         }
 
-        ares.verify_challenge(&self.wallet, &self.signature, self.timestamp)
+        let nonce = Uuid::parse_str(&self.nonce)
+            .map_err(|_| "Invalid nonce".to_string())?;
+
+        if !ares.verify_challenge(&self.wallet, &self.signature, self.timestamp, &self.nonce)? {
+            return Err("Invalid signature".to_string());
+        }
+
+        // Only a correctly signed header uses up its nonce
+        ares.consume_nonce(&self.wallet, &nonce, self.timestamp)
+    }
+}
+
+/// Verify the signed challenge in the X-Shadow-Auth header and return its wallet
+pub fn verify_signed_header(req: &HttpRequest, ares: &AresAuth) -> Result<String, ShadowError> {
+    let auth_header = req.headers().get("X-Shadow-Auth")
+        .ok_or(ShadowError::Unauthorized)?
+        .to_str()
+        .map_err(|_| ShadowError::Unauthorized)?;
+
+    let auth = AuthHeader::from_header(auth_header)
+        .map_err(ShadowError::AuthFailed)?;
+    auth.verify(ares)
+        .map_err(ShadowError::AuthFailed)?;
+
+    Ok(auth.wallet)
+}
+
+/// Authenticate a request by `Authorization: Bearer <token>` or a signed
+/// X-Shadow-Auth challenge, returning the caller's wallet
+pub fn authenticate(req: &HttpRequest, ares: &AresAuth) -> Result<String, ShadowError> {
+    if let Some(value) = req.headers().get("Authorization") {
+        let token = value.to_str()
+            .ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or(ShadowError::Unauthorized)?;
+        return ares.verify_token(token.trim())
+            .map_err(ShadowError::AuthFailed);
     }
+
+    verify_signed_header(req, ares)
 }
 
@@ -0,0 +1,422 @@
+// Argus - The All-Seeing Watchman
+// Handles dApp origin reputation and phishing detection
+
+use dashmap::DashMap;
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, DateTime};
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KnownDApp {
+    #[serde(rename = "_id")]
+    pub origin: String, // Canonical origin, e.g. "https://jup.ag"
+    pub name: String,
+    pub icon: Option<String>,
+    pub blocked: bool,
+    pub updated_at: DateTime,
+}
+
+/// A registry entry supplied through configuration rather than by admins
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KnownDAppSeed {
+    pub origin: String,
+    pub name: String,
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OriginRisk {
+    Verified,
+    #[default]
+    Unknown,
+    Suspicious,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OriginAssessment {
+    pub risk: OriginRisk,
+    pub blocked: bool,
+    pub verified_name: Option<String>,
+    pub verified_icon: Option<String>,
+    pub reason: Option<String>,
+}
+
+pub struct ArgusReputation {
+    db: Arc<Database>,
+    /// Keyed by host, so one entry covers every scheme and port the dApp is reached on
+    registry: DashMap<String, KnownDApp>,
+}
+
+impl ArgusReputation {
+    pub fn new(db: Arc<Database>, seeds: &[KnownDAppSeed]) -> Self {
+        let registry = DashMap::new();
+        for seed in seeds {
+            let origin = normalize_origin(&seed.origin);
+            registry.insert(host_of(&origin).to_string(), KnownDApp {
+                origin,
+                name: seed.name.clone(),
+                icon: None,
+                blocked: seed.blocked,
+                updated_at: DateTime::now(),
+            });
+        }
+        Self { db, registry }
+    }
+
+    pub fn get_collection(&self) -> Collection<KnownDApp> {
+        self.db.collection::<KnownDApp>("known_dapps")
+    }
+
+    /// Load admin-managed entries from Mongo; they override configured seeds
+    pub async fn refresh(&self) -> Result<usize, String> {
+        let mut cursor = self.get_collection()
+            .find(doc! {}, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let mut loaded = 0;
+        while let Some(entry) = cursor.try_next().await
+            .map_err(|e| format!("Database error: {}", e))? {
+            self.registry.insert(registry_key(&entry.origin), entry);
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Reload admin-managed entries every `interval` until `shutdown`; the first load
+    /// happens at startup
+    pub fn spawn_refresh(self: Arc<Self>, interval: Duration, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                if let Err(e) = self.refresh().await {
+                    tracing::warn!("Failed to refresh dApp reputation registry: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Register or update a known dApp (admin)
+    pub async fn upsert_known_dapp(
+        &self,
+        origin: &str,
+        name: &str,
+        icon: Option<&str>,
+    ) -> Result<KnownDApp, String> {
+        let origin = normalize_origin(origin);
+        let blocked = self.registry.get(host_of(&origin)).map(|e| e.blocked).unwrap_or(false);
+        let entry = KnownDApp {
+            origin: origin.clone(),
+            name: name.to_string(),
+            icon: icon.map(|s| s.to_string()),
+            blocked,
+            updated_at: DateTime::now(),
+        };
+        self.save(&entry).await?;
+        Ok(entry)
+    }
+
+    /// Block or unblock an origin (admin)
+    pub async fn set_blocked(&self, origin: &str, blocked: bool) -> Result<KnownDApp, String> {
+        let origin = normalize_origin(origin);
+        let mut entry = self.registry.get(host_of(&origin))
+            .map(|e| e.clone())
+            .unwrap_or_else(|| KnownDApp {
+                origin: origin.clone(),
+                name: host_of(&origin).to_string(),
+                icon: None,
+                blocked,
+                updated_at: DateTime::now(),
+            });
+        entry.blocked = blocked;
+        entry.updated_at = DateTime::now();
+        self.save(&entry).await?;
+        Ok(entry)
+    }
+
+    async fn save(&self, entry: &KnownDApp) -> Result<(), String> {
+        let options = mongodb::options::ReplaceOptions::builder()
+            .upsert(true)
+            .build();
+        self.get_collection()
+            .replace_one(doc! { "_id": &entry.origin }, entry, options)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        self.registry.insert(registry_key(&entry.origin), entry.clone());
+        Ok(())
+    }
+
+    /// Assess how trustworthy a dApp origin is given the name it presents. Entries match
+    /// on the host whatever the scheme; a block also covers every subdomain, while
+    /// verification is for the exact host only.
+    pub fn assess(&self, origin: &str, dapp_name: &str) -> OriginAssessment {
+        let origin = normalize_origin(origin);
+        let host = host_of(&origin);
+
+        let blocked = std::iter::successors(Some(host), |h| h.split_once('.').map(|(_, parent)| parent))
+            .any(|h| self.registry.get(h).is_some_and(|e| e.blocked));
+        if blocked {
+            return OriginAssessment {
+                risk: OriginRisk::Suspicious,
+                blocked: true,
+                verified_name: None,
+                verified_icon: None,
+                reason: Some("Origin has been blocked".to_string()),
+            };
+        }
+        if let Some(entry) = self.registry.get(host) {
+            return OriginAssessment {
+                risk: OriginRisk::Verified,
+                blocked: false,
+                verified_name: Some(entry.name.clone()),
+                verified_icon: entry.icon.clone(),
+                reason: None,
+            };
+        }
+
+        let suspicious = |reason: String| OriginAssessment {
+            risk: OriginRisk::Suspicious,
+            blocked: false,
+            verified_name: None,
+            verified_icon: None,
+            reason: Some(reason),
+        };
+
+        let host_label = primary_label(host);
+        let known: Vec<(String, String)> = self.registry.iter()
+            .filter(|e| !e.blocked)
+            .map(|e| (e.name.clone(), primary_label(host_of(&e.origin)).to_string()))
+            .collect();
+
+        // Punycode or non-ASCII hosts are a classic homoglyph vector
+        if is_homoglyph_host(host) {
+            let target = known.iter()
+                .find(|(name, label)| lookalike(host_label, name) || lookalike(host_label, label))
+                .map(|(name, _)| name.clone());
+            return suspicious(match target {
+                Some(name) => format!("Internationalized origin imitates {}", name),
+                None => "Internationalized (punycode) origin".to_string(),
+            });
+        }
+
+        for (name, label) in &known {
+            if lookalike(dapp_name, name) {
+                return suspicious(format!("Name resembles {} but origin differs", name));
+            }
+            if label.len() >= 4 && lookalike(host_label, label) {
+                return suspicious(format!("Origin resembles {}", name));
+            }
+        }
+
+        OriginAssessment {
+            risk: OriginRisk::Unknown,
+            blocked: false,
+            verified_name: None,
+            verified_icon: None,
+            reason: None,
+        }
+    }
+}
+
+/// Lowercase, drop any path and trailing slash so "https://Jup.ag/" == "https://jup.ag"
+pub fn normalize_origin(origin: &str) -> String {
+    let origin = origin.trim().to_lowercase();
+    let (scheme, rest) = match origin.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme.to_string()), rest.to_string()),
+        None => (None, origin.clone()),
+    };
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("").to_string();
+    match scheme {
+        Some(scheme) => format!("{}://{}", scheme, host),
+        None => host,
+    }
+}
+
+/// Registry key for an origin: its host, without scheme or port
+fn registry_key(origin: &str) -> String {
+    host_of(&normalize_origin(origin)).to_string()
+}
+
+fn host_of(origin: &str) -> &str {
+    let rest = origin.split_once("://").map(|(_, r)| r).unwrap_or(origin);
+    rest.split(':').next().unwrap_or(rest)
+}
+
+/// The label a user reads as the site's name: "app.jup.ag" -> "jup"
+fn primary_label(host: &str) -> &str {
+    let labels: Vec<&str> = host.split('.').filter(|l| !l.is_empty()).collect();
+    match labels.len() {
+        0 => host,
+        1 => labels[0],
+        n => labels[n - 2],
+    }
+}
+
+fn is_homoglyph_host(host: &str) -> bool {
+    !host.is_ascii() || host.split('.').any(|label| label.starts_with("xn--"))
+}
+
+/// Fold a name to a comparison skeleton: lowercase alphanumerics with common
+/// digit and Cyrillic/Greek confusables mapped to their Latin lookalikes
+pub fn skeleton(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            '0' | 'о' | 'ο' => 'o',
+            '1' | 'і' | 'ı' | 'ӏ' => 'l',
+            '3' => 'e',
+            '5' => 's',
+            '@' | 'а' | 'α' => 'a',
+            'е' => 'e',
+            'р' | 'ρ' => 'p',
+            'с' => 'c',
+            'х' => 'x',
+            'у' => 'y',
+            'ј' => 'j',
+            'ν' => 'v',
+            other => other,
+        })
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        // "rn" renders like "m" in many fonts
+        .replace("rn", "m")
+        .replace('i', "l")
+}
+
+/// Whether two names are close enough to be confused with each other
+pub fn lookalike(a: &str, b: &str) -> bool {
+    let (a, b) = (skeleton(a), skeleton(b));
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    if a == b {
+        return true;
+    }
+    let tolerance = match a.len().max(b.len()) {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    };
+    levenshtein(&a, &b) <= tolerance
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b_chars.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b_chars.len() + 1];
+        for (j, cb) in b_chars.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b_chars.len()]
+}
+
+/// Well-known Solana dApps used when no registry is configured
+pub fn default_seeds() -> Vec<KnownDAppSeed> {
+    [
+        ("https://jup.ag", "Jupiter"),
+        ("https://raydium.io", "Raydium"),
+        ("https://www.orca.so", "Orca"),
+        ("https://magiceden.io", "Magic Eden"),
+        ("https://www.tensor.trade", "Tensor"),
+        ("https://marinade.finance", "Marinade"),
+    ]
+    .iter()
+    .map(|(origin, name)| KnownDAppSeed {
+        origin: origin.to_string(),
+        name: name.to_string(),
+        blocked: false,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn reputation() -> ArgusReputation {
+        // The in-memory registry is all assess() needs; this client never connects
+        let db = mongodb::Client::with_uri_str("mongodb://127.0.0.1:1")
+            .await
+            .unwrap()
+            .database("argus_test");
+        ArgusReputation::new(Arc::new(db), &default_seeds())
+    }
+
+    #[tokio::test]
+    async fn test_verified_origin() {
+        let argus = reputation().await;
+        let assessment = argus.assess("https://JUP.ag/swap", "Jupiter");
+        assert_eq!(assessment.risk, OriginRisk::Verified);
+        assert_eq!(assessment.verified_name.as_deref(), Some("Jupiter"));
+    }
+
+    #[tokio::test]
+    async fn test_lookalike_name_on_other_origin() {
+        let argus = reputation().await;
+        assert_eq!(argus.assess("https://jupiter-airdrop.xyz", "Jupiter").risk, OriginRisk::Suspicious);
+        assert_eq!(argus.assess("https://claim.example", "Jupit3r").risk, OriginRisk::Suspicious);
+        assert_eq!(argus.assess("https://rayd1um.io", "Swap").risk, OriginRisk::Suspicious);
+        assert_eq!(argus.assess("https://my-new-dapp.xyz", "Sunflower").risk, OriginRisk::Unknown);
+    }
+
+    #[tokio::test]
+    async fn test_punycode_origin_is_suspicious() {
+        let argus = reputation().await;
+        let assessment = argus.assess("https://xn--jp-tmc.ag", "Swap");
+        assert_eq!(assessment.risk, OriginRisk::Suspicious);
+
+        // Cyrillic "а" in place of the Latin letter
+        let assessment = argus.assess("https://rаydium.io", "Raydium");
+        assert_eq!(assessment.risk, OriginRisk::Suspicious);
+        assert!(assessment.reason.unwrap().contains("Raydium"));
+    }
+
+    #[tokio::test]
+    async fn test_blocked_seed_is_flagged() {
+        let db = mongodb::Client::with_uri_str("mongodb://127.0.0.1:1")
+            .await
+            .unwrap()
+            .database("argus_test");
+        let seeds = vec![KnownDAppSeed {
+            origin: "https://drainer.example".to_string(),
+            name: "Drainer".to_string(),
+            blocked: true,
+        }];
+        let argus = ArgusReputation::new(Arc::new(db), &seeds);
+        assert!(argus.assess("https://drainer.example", "Totally Safe").blocked);
+        // Neither another scheme nor a subdomain gets around the block
+        assert!(argus.assess("http://drainer.example:8080/claim", "Totally Safe").blocked);
+        assert!(argus.assess("https://claim.app.drainer.example", "Totally Safe").blocked);
+        assert!(!argus.assess("https://notdrainer.example", "Totally Safe").blocked);
+    }
+
+    #[tokio::test]
+    async fn test_verification_is_for_the_exact_host() {
+        let argus = reputation().await;
+        assert_eq!(argus.assess("http://jup.ag", "Jupiter").risk, OriginRisk::Verified);
+        assert_ne!(argus.assess("https://evil.jup.ag.example", "Swap").risk, OriginRisk::Verified);
+        assert_ne!(argus.assess("https://orca.so", "Swap").risk, OriginRisk::Verified);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("jupiter", "jupiter"), 0);
+        assert_eq!(levenshtein("jupiter", "jupyter"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+}
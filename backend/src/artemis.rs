@@ -1,61 +1,125 @@
 // Artemis - Goddess of the Hunt
 // Handles rate limiting and request throttling
 
-use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
-use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use crate::clock::{SharedClock, SystemClock};
 
-#[derive(Clone)]
-struct RateLimitEntry {
-    count: u32,
-    reset_at: Instant,
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
 }
 
+/// Where a key stands after an allowed request
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitInfo {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_in_seconds: u64,
+}
+
+/// Sliding-window log: every counted unit of a key's recent requests, oldest first.
+/// Unlike a fixed window this never lets a burst of twice the limit through across a boundary.
 pub struct ArtemisRateLimiter {
-    requests_per_minute: u32,
+    limits: Arc<RwLock<HashMap<String, VecDeque<Instant>>>>,
+    max_requests: u32,
     window_seconds: u64,
-    store: Arc<DashMap<String, RateLimitEntry>>,
+    clock: SharedClock,
 }
 
 impl ArtemisRateLimiter {
     pub fn new(requests_per_minute: u32) -> Self {
+        Self::with_window(requests_per_minute, 60)
+    }
+
+    /// Allow `max_requests` per window of `window_seconds`
+    pub fn with_window(max_requests: u32, window_seconds: u64) -> Self {
         Self {
-            requests_per_minute,
-            window_seconds: 60,
-            store: Arc::new(DashMap::new()),
+            limits: Arc::new(RwLock::new(HashMap::new())),
+            max_requests,
+            window_seconds: window_seconds.max(1),
+            clock: SystemClock::shared(),
         }
     }
 
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.window_seconds)
+    }
+
     /// Check if a request should be allowed
-    pub fn check_rate_limit(&self, key: &str) -> Result<(), String> {
-        let now = Instant::now();
-        
-        // Clean up old entries periodically
-        if self.store.len() > 10000 {
-            self.store.retain(|_, entry| entry.reset_at > now);
-        }
+    pub fn check_rate_limit(&self, key: &str) -> Result<RateLimitInfo, String> {
+        self.check_rate_limit_with_cost(key, 1)
+    }
 
-        let mut entry = self.store.entry(key.to_string()).or_insert_with(|| {
-            RateLimitEntry {
-                count: 0,
-                reset_at: now + Duration::from_secs(self.window_seconds),
-            }
-        });
+    /// Check a request that counts as `cost` requests against the limit
+    pub fn check_rate_limit_with_cost(&self, key: &str, cost: u32) -> Result<RateLimitInfo, String> {
+        let now = self.clock.now_instant();
+        let window = self.window();
 
-        // Reset if window expired
-        if entry.reset_at < now {
-            entry.count = 0;
-            entry.reset_at = now + Duration::from_secs(self.window_seconds);
+        let mut limits = self.limits.write().unwrap_or_else(|e| e.into_inner());
+        let log = limits.entry(key.to_string()).or_default();
+        while log.front().is_some_and(|&at| now.duration_since(at) >= window) {
+            log.pop_front();
         }
 
-        // Check limit
-        if entry.count >= self.requests_per_minute {
-            let remaining = entry.reset_at.duration_since(now).as_secs();
-            return Err(format!("Rate limit exceeded. Try again in {} seconds", remaining));
+        let used = log.len() as u32;
+        if used.saturating_add(cost) > self.max_requests {
+            // Enough of the oldest units have to age out for this request to fit
+            let must_expire = (used + cost - self.max_requests) as usize;
+            let retry_in = match log.get(must_expire - 1) {
+                Some(&at) if cost <= self.max_requests => (at + window).saturating_duration_since(now),
+                _ => window,
+            };
+            return Err(format!("Rate limit exceeded. Try again in {} seconds", ceil_secs(retry_in)));
         }
 
-        entry.count += 1;
-        Ok(())
+        log.extend(std::iter::repeat_n(now, cost as usize));
+        let reset_in = log.front().map_or(window, |&at| (at + window).saturating_duration_since(now));
+        Ok(RateLimitInfo {
+            limit: self.max_requests,
+            remaining: self.max_requests - log.len() as u32,
+            reset_in_seconds: ceil_secs(reset_in),
+        })
+    }
+
+    /// Drop keys with nothing left inside the window
+    pub fn cleanup_stale_entries(&self) -> usize {
+        let now = self.clock.now_instant();
+        let window = self.window();
+
+        let mut limits = self.limits.write().unwrap_or_else(|e| e.into_inner());
+        let before = limits.len();
+        limits.retain(|_, log| log.back().is_some_and(|&at| now.duration_since(at) < window));
+        before - limits.len()
+    }
+
+    /// Forget every key, so tests can start from a clean slate
+    pub fn reset_for_testing(&self) {
+        self.limits.write().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Sweep stale counters once per window until shutdown
+    pub fn spawn_cleanup(self: Arc<Self>, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.window());
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                let removed = self.cleanup_stale_entries();
+                if removed > 0 {
+                    tracing::debug!("Artemis dropped {} stale rate limit entries", removed);
+                }
+            }
+        })
     }
 
     /// Get client identifier from IP or wallet
@@ -70,3 +134,77 @@ impl ArtemisRateLimiter {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_is_enforced_within_window() {
+        let artemis = ArtemisRateLimiter::with_window(2, 60);
+        assert_eq!(artemis.check_rate_limit("ip:1").unwrap().remaining, 1);
+        assert_eq!(artemis.check_rate_limit("ip:1").unwrap().remaining, 0);
+        assert!(artemis.check_rate_limit("ip:1").is_err());
+        // Other keys have their own counter
+        assert!(artemis.check_rate_limit("ip:2").is_ok());
+    }
+
+    #[test]
+    fn test_requests_age_out_one_at_a_time() {
+        let clock = crate::clock::TestClock::starting_now();
+        let artemis = ArtemisRateLimiter::with_window(2, 60).with_clock(clock.clone());
+        assert_eq!(artemis.check_rate_limit("ip:1").unwrap().reset_in_seconds, 60);
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(artemis.check_rate_limit("ip:1").unwrap().reset_in_seconds, 30);
+        assert_eq!(artemis.check_rate_limit("ip:1").unwrap_err(), "Rate limit exceeded. Try again in 30 seconds");
+
+        // The first request leaves the window, the second is still counted
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(artemis.check_rate_limit("ip:1").unwrap().remaining, 0);
+        assert!(artemis.check_rate_limit("ip:1").is_err());
+    }
+
+    #[test]
+    fn test_cost_counts_as_several_requests() {
+        let clock = crate::clock::TestClock::starting_now();
+        let artemis = ArtemisRateLimiter::with_window(5, 60).with_clock(clock.clone());
+        assert_eq!(artemis.check_rate_limit_with_cost("ip:1", 2).unwrap().remaining, 3);
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(artemis.check_rate_limit_with_cost("ip:1", 2).unwrap().remaining, 1);
+
+        // Rejected requests are not counted; waiting for the first two units is enough
+        let err = artemis.check_rate_limit_with_cost("ip:1", 2).unwrap_err();
+        assert!(err.contains("50 seconds"), "{}", err);
+        assert!(artemis.check_rate_limit_with_cost("ip:1", 6).is_err());
+        assert_eq!(artemis.check_rate_limit("ip:1").unwrap().remaining, 0);
+    }
+
+    #[test]
+    fn test_cleanup_removes_only_stale_entries() {
+        let clock = crate::clock::TestClock::starting_now();
+        let artemis = ArtemisRateLimiter::with_window(10, 60).with_clock(clock.clone());
+        artemis.check_rate_limit("stale").unwrap();
+        clock.advance(Duration::from_secs(30));
+        artemis.check_rate_limit("fresh").unwrap();
+        clock.advance(Duration::from_secs(30));
+
+        assert_eq!(artemis.cleanup_stale_entries(), 1);
+        assert!(artemis.limits.read().unwrap().contains_key("fresh"));
+        assert_eq!(artemis.limits.read().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_reset_for_testing_forgets_every_key() {
+        let artemis = ArtemisRateLimiter::with_window(1, 60);
+        artemis.check_rate_limit("ip:1").unwrap();
+        assert!(artemis.check_rate_limit("ip:1").is_err());
+        artemis.reset_for_testing();
+        assert!(artemis.check_rate_limit("ip:1").is_ok());
+    }
+
+    #[test]
+    fn test_wallet_key_preferred_over_ip() {
+        assert_eq!(ArtemisRateLimiter::get_client_key(Some("1.2.3.4"), Some("abc")), "wallet:abc");
+        assert_eq!(ArtemisRateLimiter::get_client_key(Some("1.2.3.4"), None), "ip:1.2.3.4");
+        assert_eq!(ArtemisRateLimiter::get_client_key(None, None), "unknown");
+    }
+}
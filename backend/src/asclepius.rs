@@ -0,0 +1,380 @@
+// Asclepius - God of Medicine
+// Health-checks staged deploys before they replace a site's live content
+
+use crate::config::DeployConfig;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+/// Bundle file listing the build's assets (create-react-app style)
+pub const ASSET_MANIFEST: &str = "asset-manifest.json";
+/// Most referenced files fetched while checking one bundle
+const MAX_CHECKED_FILES: usize = 200;
+
+/// Elements that never have a closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+/// Elements whose closing tag HTML lets authors omit
+const OPTIONAL_CLOSE: &[&str] = &[
+    "html", "head", "body", "p", "li", "dt", "dd", "option", "optgroup", "tr", "td", "th", "thead", "tbody", "tfoot",
+    "colgroup", "caption", "rt", "rp",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployCheck {
+    pub name: String,
+    pub passed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl DeployCheck {
+    fn pass(name: &str) -> Self {
+        Self { name: name.to_string(), passed: true, detail: None }
+    }
+
+    fn result(name: &str, outcome: Result<(), String>) -> Self {
+        match outcome {
+            Ok(()) => Self::pass(name),
+            Err(detail) => Self { name: name.to_string(), passed: false, detail: Some(detail) },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployReport {
+    pub passed: bool,
+    /// Checks were skipped with the force flag
+    pub forced: bool,
+    /// Share of fetched bundle files that answered 200
+    pub success_rate: f64,
+    pub checks: Vec<DeployCheck>,
+}
+
+impl DeployReport {
+    pub fn forced() -> Self {
+        Self { passed: true, forced: true, success_rate: 0.0, checks: Vec::new() }
+    }
+
+    pub fn failures(&self) -> Vec<&DeployCheck> {
+        self.checks.iter().filter(|c| !c.passed).collect()
+    }
+}
+
+pub struct AsclepiusChecker {
+    client: reqwest::Client,
+    config: DeployConfig,
+}
+
+impl AsclepiusChecker {
+    pub fn new(config: DeployConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.request_timeout_seconds))
+            .build()
+            .unwrap_or_default();
+        Self { client, config }
+    }
+
+    /// Gateway URL for a file inside a content bundle; also how previews are served
+    pub fn content_url(&self, cid: &str, path: &str) -> String {
+        let path = path.trim_start_matches('/');
+        match cid.strip_prefix("arweave://") {
+            Some(tx) => format!("{}/{}/{}", self.config.arweave_gateway_url.trim_end_matches('/'), tx, path),
+            None => {
+                let hash = cid.strip_prefix("ipfs://").unwrap_or(cid);
+                format!("{}/{}/{}", self.config.ipfs_gateway_url.trim_end_matches('/'), hash, path)
+            }
+        }
+    }
+
+    /// Fetch one bundle file; None when the gateway does not answer 200
+    pub async fn fetch(&self, cid: &str, path: &str) -> Option<Vec<u8>> {
+        let response = self.client.get(self.content_url(cid, path)).send().await.ok()?;
+        if response.status() != reqwest::StatusCode::OK {
+            return None;
+        }
+        response.bytes().await.ok().map(|b| b.to_vec())
+    }
+
+    /// Run every check against a bundle without touching the live site
+    pub async fn check(&self, cid: &str, html_check: bool) -> DeployReport {
+        let mut checks = Vec::new();
+        let mut resolved: HashMap<String, bool> = HashMap::new();
+
+        let index = self.fetch(cid, "index.html").await;
+        resolved.insert("index.html".to_string(), index.is_some());
+        let html = index.as_deref().map(String::from_utf8_lossy).unwrap_or_default().into_owned();
+        checks.push(DeployCheck::result("index_html", match &index {
+            None => Err("index.html did not resolve".to_string()),
+            Some(_) if html.trim().is_empty() => Err("index.html is empty".to_string()),
+            Some(_) => Ok(()),
+        }));
+
+        let manifest_assets = match self.fetch(cid, ASSET_MANIFEST).await {
+            Some(body) => manifest_assets(&body),
+            None => Ok(Vec::new()),
+        };
+        let referenced = referenced_assets(&html);
+        let to_fetch: BTreeSet<String> = manifest_assets.iter().flatten().chain(referenced.iter())
+            .filter(|path| !resolved.contains_key(*path))
+            .take(MAX_CHECKED_FILES)
+            .cloned()
+            .collect();
+        for path in to_fetch {
+            let ok = self.fetch(cid, &path).await.is_some();
+            resolved.insert(path, ok);
+        }
+
+        checks.push(DeployCheck::result("manifest_assets", manifest_assets.and_then(|assets| {
+            let missing: Vec<&str> = assets.iter()
+                .filter(|path| !resolved.get(*path).copied().unwrap_or(false))
+                .map(|path| path.as_str())
+                .collect();
+            if missing.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Missing assets: {}", missing.join(", ")))
+            }
+        })));
+
+        let ok = resolved.values().filter(|ok| **ok).count();
+        let success_rate = ok as f64 / resolved.len() as f64;
+        checks.push(DeployCheck::result("success_rate", if success_rate >= self.config.min_success_rate {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} of {} files resolved ({:.0}%, need {:.0}%)",
+                ok, resolved.len(), success_rate * 100.0, self.config.min_success_rate * 100.0
+            ))
+        }));
+
+        if html_check || self.config.html_check {
+            checks.push(DeployCheck::result("html_parse", check_html(&html)));
+        }
+
+        DeployReport {
+            passed: checks.iter().all(|c| c.passed),
+            forced: false,
+            success_rate,
+            checks,
+        }
+    }
+}
+
+/// Normalize a bundle-relative reference; None for external, inline or anchor links
+fn bundle_path(reference: &str) -> Option<String> {
+    let reference = reference.trim();
+    let lower = reference.to_ascii_lowercase();
+    if reference.is_empty()
+        || reference.starts_with('#')
+        || reference.starts_with("//")
+        || lower.contains("://")
+        || lower.starts_with("data:")
+        || lower.starts_with("mailto:")
+        || lower.starts_with("javascript:")
+    {
+        return None;
+    }
+    let path = reference.split(['?', '#']).next().unwrap_or_default();
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+/// Paths listed in an asset manifest's `files` map and `entrypoints` list
+pub fn manifest_assets(body: &[u8]) -> Result<Vec<String>, String> {
+    let manifest: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|e| format!("Invalid {}: {}", ASSET_MANIFEST, e))?;
+    let files = manifest["files"].as_object().into_iter().flat_map(|files| files.values());
+    let entrypoints = manifest["entrypoints"].as_array().into_iter().flatten();
+    let assets: BTreeSet<String> = files.chain(entrypoints)
+        .filter_map(|value| value.as_str())
+        .filter_map(bundle_path)
+        .collect();
+    Ok(assets.into_iter().collect())
+}
+
+/// Bundle-relative `src` and `href` targets in a page
+pub fn referenced_assets(html: &str) -> Vec<String> {
+    let mut assets = BTreeSet::new();
+    let lower = html.to_ascii_lowercase();
+    for attribute in ["src=", "href="] {
+        let mut from = 0;
+        while let Some(found) = lower[from..].find(attribute) {
+            let start = from + found + attribute.len();
+            from = start;
+            let Some(quote) = html[start..].chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                continue;
+            };
+            let Some(len) = html[start + 1..].find(quote) else { break };
+            if let Some(path) = bundle_path(&html[start + 1..start + 1 + len]) {
+                assets.insert(path);
+            }
+        }
+    }
+    assets.into_iter().collect()
+}
+
+/// Loose well-formedness check: every explicitly closed element was opened, and only
+/// elements whose end tag is optional are left open
+pub fn check_html(html: &str) -> Result<(), String> {
+    let lower = html.to_ascii_lowercase();
+    let mut stack: Vec<String> = Vec::new();
+    let mut pos = 0;
+
+    while let Some(found) = lower[pos..].find('<') {
+        let start = pos + found;
+        let rest = &lower[start..];
+        if rest.starts_with("<!--") {
+            pos = start + rest.find("-->").map(|end| end + 3).ok_or("Unterminated comment")?;
+            continue;
+        }
+        let end = start + rest.find('>').ok_or("Unterminated tag")?;
+        let tag = &lower[start + 1..end];
+        pos = end + 1;
+
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            let Some(open) = stack.iter().rposition(|open| open == name) else {
+                return Err(format!("Unexpected </{}>", name));
+            };
+            if let Some(unclosed) = stack[open + 1..].iter().find(|t| !OPTIONAL_CLOSE.contains(&t.as_str())) {
+                return Err(format!("<{}> closed by </{}>", unclosed, name));
+            }
+            stack.truncate(open);
+            continue;
+        }
+
+        let name: String = tag.chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+        if name.is_empty() || tag.ends_with('/') || VOID_ELEMENTS.contains(&name.as_str()) {
+            continue;
+        }
+        if name == "script" || name == "style" {
+            // Raw text: skip to the matching end tag
+            let close = format!("</{}", name);
+            let close_at = pos + lower[pos..].find(&close).ok_or_else(|| format!("Unclosed <{}>", name))?;
+            pos = close_at + lower[close_at..].find('>').ok_or("Unterminated tag")? + 1;
+            continue;
+        }
+        stack.push(name);
+    }
+
+    match stack.iter().find(|t| !OPTIONAL_CLOSE.contains(&t.as_str())) {
+        Some(unclosed) => Err(format!("Unclosed <{}>", unclosed)),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionStatus {
+    Live,
+    /// Failed its checks; kept reachable through the preview endpoint
+    Preview,
+    /// Was live until a later deploy replaced it
+    Superseded,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteVersion {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub program_address: String,
+    pub storage_cid: String,
+    pub status: VersionStatus,
+    pub report: DeployReport,
+    pub deployed_by: String,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct SiteVersions {
+    db: Database,
+}
+
+impl SiteVersions {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub fn get_collection(&self) -> Collection<SiteVersion> {
+        self.db.collection::<SiteVersion>("site_versions")
+    }
+
+    /// Record a deploy; a new live version supersedes the previous one
+    pub async fn record(&self, version: &SiteVersion) -> Result<(), String> {
+        let collection = self.get_collection();
+        if version.status == VersionStatus::Live {
+            collection.update_many(
+                doc! { "program_address": &version.program_address, "status": "live" },
+                doc! { "$set": { "status": "superseded" } },
+                None,
+            ).await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+
+        let mut document = mongodb::bson::to_document(version)
+            .map_err(|e| format!("Invalid site version: {}", e))?;
+        document.insert("created_at", mongodb::bson::DateTime::from_millis(version.created_at.timestamp_millis()));
+        self.db.collection::<mongodb::bson::Document>("site_versions")
+            .insert_one(document, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(())
+    }
+
+    /// Newest first
+    pub async fn get(&self, id: &str) -> Result<Option<SiteVersion>, String> {
+        self.get_collection()
+            .find_one(doc! { "_id": id }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    pub async fn list(&self, program_address: &str, limit: i64) -> Result<Vec<SiteVersion>, String> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .build();
+        let cursor = self.get_collection()
+            .find(doc! { "program_address": program_address }, options)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        cursor.try_collect().await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_referenced_assets_skip_external_links() {
+        let html = r##"<link href="/static/app.css"><script src="./app.js?v=2"></script>
+            <a href="https://example.com">x</a><img src='img/logo.png'><a href="#top">top</a>"##;
+        assert_eq!(referenced_assets(html), vec!["app.js", "img/logo.png", "static/app.css"]);
+    }
+
+    #[test]
+    fn test_manifest_assets_read_files_and_entrypoints() {
+        let body = br#"{"files": {"main.js": "/static/main.js"}, "entrypoints": ["static/main.js", "static/main.css"]}"#;
+        assert_eq!(manifest_assets(body).unwrap(), vec!["static/main.css", "static/main.js"]);
+        assert!(manifest_assets(b"not json").is_err());
+    }
+
+    #[test]
+    fn test_check_html() {
+        assert!(check_html("<!doctype html><html><body><p>one<p>two<div><br></div><script>if (a < b) {}</script></body></html>").is_ok());
+        assert!(check_html("<html><body><div><span></div></body></html>").is_err());
+        assert!(check_html("<html><body><main>").is_err());
+        assert!(check_html("</div>").is_err());
+    }
+}
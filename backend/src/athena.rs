@@ -1,10 +1,45 @@
 // Athena: Wisdom and knowledge - Search indexing and content analysis
-use mongodb::{Collection, Database};
-use mongodb::bson::doc;
+use mongodb::{Collection, Database, IndexModel};
+use mongodb::bson::{doc, Document};
+use mongodb::options::IndexOptions;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
 use futures_util::TryStreamExt;
+use regex::Regex;
+use std::sync::OnceLock;
+use crate::apollo::ApolloValidator;
+use crate::utils::{encode_score_cursor, keyset_filter, next_page_cursor};
+
+/// Popularity every indexed site starts from
+const BASE_POPULARITY: f64 = 1.0;
+/// Each additional linking owner adds less than the last
+const LINK_DAMPING: f64 = 0.85;
+/// Upper bound on what inbound links can add to popularity
+const MAX_LINK_BOOST: f64 = 1.0;
+/// Most distinct external hosts recorded per indexed page
+const MAX_EXTERNAL_LINKS: usize = 200;
+/// Shorter queries are matched by substring; the text index drops words this short
+pub const MIN_TEXT_QUERY_LEN: usize = 3;
+
+/// Order search results are returned in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    /// Text relevance weighted by popularity
+    #[default]
+    Relevance,
+    Popularity,
+    /// Most recently indexed first
+    Recency,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchOptions {
+    pub sort: SearchSort,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchIndex {
@@ -18,6 +53,50 @@ pub struct SearchIndex {
     pub content_hash: String,
     pub indexed_at: DateTime<Utc>,
     pub popularity_score: f64,
+    /// Set for language variants of multi-language sites
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Other .shadow domains this page links to
+    #[serde(default)]
+    pub outbound_links: Vec<String>,
+    /// Hosts outside Shadow this page links to
+    #[serde(default)]
+    pub external_links: Vec<String>,
+    /// Text relevance to the query, set on search results only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Key the results were sorted by, set on search results only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f64>,
+}
+
+/// First sighting of an external host in indexed content
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkDomain {
+    #[serde(rename = "_id")]
+    pub host: String,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub first_seen_at: DateTime<Utc>,
+    /// .shadow domain whose content first linked to the host
+    pub first_seen_on: String,
+}
+
+/// A registered domain discovered through a link, waiting to be indexed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrawlRequest {
+    #[serde(rename = "_id")]
+    pub domain: String,
+    pub program_address: String,
+    pub discovered_from: String,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// An indexed site linking to another
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Backlink {
+    pub domain: String,
+    pub program_address: String,
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,6 +109,31 @@ pub struct ContentAnalysis {
     pub last_analyzed: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexHealth {
+    pub total_indexed: usize,
+    pub total_sites: usize,
+    pub coverage_percent: f64,
+    pub stale_entries: usize,
+    pub avg_keywords_per_site: f64,
+    pub oldest_index_entry: Option<DateTime<Utc>>,
+    pub newest_index_entry: Option<DateTime<Utc>>,
+}
+
+impl IndexHealth {
+    /// Human-readable reasons the index needs attention, empty when healthy
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.coverage_percent < 90.0 {
+            warnings.push(format!("Search index coverage is {:.1}%", self.coverage_percent));
+        }
+        if self.stale_entries > 10 {
+            warnings.push(format!("{} stale search index entries", self.stale_entries));
+        }
+        warnings
+    }
+}
+
 pub struct AthenaIndexer {
     db: Database,
 }
@@ -47,10 +151,20 @@ impl AthenaIndexer {
         self.db.collection::<ContentAnalysis>("content_analysis")
     }
 
+    pub fn get_crawl_collection(&self) -> Collection<CrawlRequest> {
+        self.db.collection::<CrawlRequest>("crawl_queue")
+    }
+
+    pub fn get_link_domains_collection(&self) -> Collection<LinkDomain> {
+        self.db.collection::<LinkDomain>("link_domains")
+    }
+
+    /// Index a site, or one language variant of it when `language` is given
     pub async fn index_site(
         &self,
         domain: &str,
         program_address: &str,
+        language: Option<&str>,
         title: Option<&str>,
         description: Option<&str>,
         content: &str,
@@ -59,15 +173,29 @@ impl AthenaIndexer {
         
         // Extract keywords from content
         let keywords = Self::extract_keywords(content, title, description);
+        let outbound_links = Self::extract_shadow_links(content, domain);
+        let external_links = Self::extract_external_links(content);
         
-        // Calculate popularity score (simplified)
+        // Calculate popularity score, including the boost from inbound links
         let popularity_score = self.calculate_popularity(domain).await.unwrap_or(0.0);
         
         let now = Utc::now();
         let content_hash = Self::hash_content(content);
         
+        let id = match language {
+            Some(lang) => format!("{}:{}:{}", domain, program_address, lang.to_ascii_lowercase()),
+            None => format!("{}:{}", domain, program_address),
+        };
+
+        // Links dropped since the last index still need their targets rescored
+        let previous_links = collection
+            .find_one(doc! { "_id": &id }, None)
+            .await?
+            .map(|entry| entry.outbound_links)
+            .unwrap_or_default();
+        
         let index = SearchIndex {
-            id: format!("{}:{}", domain, program_address),
+            id,
             domain: domain.to_string(),
             program_address: program_address.to_string(),
             title: title.map(|s| s.to_string()),
@@ -76,6 +204,11 @@ impl AthenaIndexer {
             content_hash,
             indexed_at: now,
             popularity_score,
+            language: language.map(|l| l.to_string()),
+            outbound_links,
+            external_links,
+            score: None,
+            rank: None,
         };
         
         let filter = doc! { "_id": &index.id };
@@ -87,49 +220,348 @@ impl AthenaIndexer {
             .build();
         
         collection.update_one(filter, update, options).await?;
+
+        for link in &index.outbound_links {
+            self.queue_crawl(link, domain).await?;
+        }
+        let touched: HashSet<&String> = previous_links.iter().chain(&index.outbound_links).collect();
+        for link in touched {
+            self.refresh_popularity(link).await?;
+        }
+        self.record_link_domains(&index.external_links, domain).await?;
         Ok(())
     }
 
+    /// Remember when each external host was first linked from indexed content.
+    /// Later sightings leave the first one untouched.
+    async fn record_link_domains(&self, hosts: &[String], seen_on: &str) -> Result<(), mongodb::error::Error> {
+        let collection = self.db.collection::<mongodb::bson::Document>("link_domains");
+        let options = mongodb::options::UpdateOptions::builder()
+            .upsert(true)
+            .build();
+        for host in hosts {
+            collection
+                .update_one(
+                    doc! { "_id": host },
+                    doc! { "$setOnInsert": { "first_seen_at": mongodb::bson::DateTime::now(), "first_seen_on": seen_on } },
+                    options.clone(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// First time `host` was seen linked from indexed content, if ever
+    pub async fn first_seen(&self, host: &str) -> Result<Option<LinkDomain>, mongodb::error::Error> {
+        self.get_link_domains_collection().find_one(doc! { "_id": host }, None).await
+    }
+
+    /// Distinct Shadow sites whose indexed content links to `host`
+    pub async fn linking_sites(&self, host: &str) -> Result<usize, mongodb::error::Error> {
+        let sites = self.get_index_collection()
+            .distinct("program_address", doc! { "external_links": host }, None)
+            .await?;
+        Ok(sites.len())
+    }
+
+    /// Queue a linked domain for indexing. Only registered domains that have not been
+    /// indexed yet are queued; unregistered names are never crawled.
+    async fn queue_crawl(&self, domain: &str, discovered_from: &str) -> Result<bool, mongodb::error::Error> {
+        let Some(registered) = self.db
+            .collection::<mongodb::bson::Document>("domains")
+            .find_one(doc! { "_id": domain }, None)
+            .await? else {
+            return Ok(false);
+        };
+        if self.get_index_collection().count_documents(doc! { "domain": domain }, None).await? > 0 {
+            return Ok(false);
+        }
+
+        let request = CrawlRequest {
+            domain: domain.to_string(),
+            program_address: registered.get_str("program_address").unwrap_or_default().to_string(),
+            discovered_from: discovered_from.to_string(),
+            queued_at: Utc::now(),
+        };
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        let result = self.get_crawl_collection()
+            .update_one(
+                doc! { "_id": domain },
+                doc! { "$setOnInsert": mongodb::bson::to_bson(&request).unwrap() },
+                options,
+            )
+            .await?;
+        Ok(result.upserted_id.is_some())
+    }
+
+    /// Recompute popularity for every index entry of `domain`
+    async fn refresh_popularity(&self, domain: &str) -> Result<(), mongodb::error::Error> {
+        let popularity_score = self.calculate_popularity(domain).await?;
+        self.get_index_collection()
+            .update_many(doc! { "domain": domain }, doc! { "$set": { "popularity_score": popularity_score } }, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Indexed sites linking to any domain that points at `program_address`
+    pub async fn backlinks(&self, program_address: &str) -> Result<Vec<Backlink>, mongodb::error::Error> {
+        let domains: Vec<String> = self.db
+            .collection::<mongodb::bson::Document>("domains")
+            .distinct("_id", doc! { "program_address": program_address }, None)
+            .await?
+            .into_iter()
+            .filter_map(|b| b.as_str().map(|s| s.to_string()))
+            .collect();
+        if domains.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let filter = doc! {
+            "outbound_links": { "$in": &domains },
+            "program_address": { "$ne": program_address },
+        };
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "popularity_score": -1, "domain": 1 })
+            .build();
+        let mut cursor = self.get_index_collection().find(filter, options).await?;
+
+        // Language variants of one site count as a single backlink
+        let mut seen = HashSet::new();
+        let mut backlinks = Vec::new();
+        while let Some(entry) = cursor.try_next().await? {
+            if seen.insert(entry.program_address.clone()) {
+                backlinks.push(Backlink {
+                    domain: entry.domain,
+                    program_address: entry.program_address,
+                    title: entry.title,
+                });
+            }
+        }
+        Ok(backlinks)
+    }
+
+    /// Distinct owners of indexed sites linking to `domain`, not counting its own owner
+    async fn linking_owners(&self, domain: &str) -> Result<usize, mongodb::error::Error> {
+        let sources = self.get_index_collection()
+            .distinct("program_address", doc! { "outbound_links": domain, "domain": { "$ne": domain } }, None)
+            .await?;
+        if sources.is_empty() {
+            return Ok(0);
+        }
+
+        let own_owner = self.db
+            .collection::<mongodb::bson::Document>("domains")
+            .find_one(doc! { "_id": domain }, None)
+            .await?
+            .and_then(|d| d.get_str("owner_pubkey").ok().map(|s| s.to_string()));
+        let owners: HashSet<String> = self.db
+            .collection::<mongodb::bson::Document>("sites")
+            .distinct("owner_pubkey", doc! { "_id": { "$in": sources } }, None)
+            .await?
+            .into_iter()
+            .filter_map(|b| b.as_str().map(|s| s.to_string()))
+            .filter(|owner| Some(owner) != own_owner.as_ref())
+            .collect();
+        Ok(owners.len())
+    }
+
+    /// Popularity added by `linking_owners` distinct owners linking in. Damped so each
+    /// extra owner counts for less, and capped so link farms can't run it up.
+    pub fn link_boost(linking_owners: usize) -> f64 {
+        let exponent = linking_owners.min(i32::MAX as usize) as i32;
+        MAX_LINK_BOOST * (1.0 - LINK_DAMPING.powi(exponent))
+    }
+
+    fn href_pattern() -> &'static Regex {
+        static HREF: OnceLock<Regex> = OnceLock::new();
+        HREF.get_or_init(|| {
+            Regex::new(r#"(?i)href\s*=\s*["']([^"']+)["']"#).expect("valid href pattern")
+        })
+    }
+
+    /// Lowercased host of an http(s) or protocol-relative link, None for anything else
+    pub fn link_host(url: &str) -> Option<String> {
+        let url = url.trim();
+        let parsed = match url.strip_prefix("//") {
+            Some(rest) => reqwest::Url::parse(&format!("https://{}", rest)),
+            None => reqwest::Url::parse(url),
+        }
+        .ok()?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return None;
+        }
+        let host = parsed.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+        (!host.is_empty()).then_some(host)
+    }
+
+    /// Hosts outside Shadow linked from `content` through http(s) hrefs
+    pub fn extract_external_links(content: &str) -> Vec<String> {
+        let mut hosts = Vec::new();
+        for captures in Self::href_pattern().captures_iter(content) {
+            let Some(host) = Self::link_host(&captures[1]) else { continue };
+            if host.ends_with(".shadow") || hosts.contains(&host) {
+                continue;
+            }
+            hosts.push(host);
+            if hosts.len() == MAX_EXTERNAL_LINKS {
+                break;
+            }
+        }
+        hosts
+    }
+
+    /// .shadow domains linked from `content` through shadow:// or *.shadow hrefs
+    pub fn extract_shadow_links(content: &str, own_domain: &str) -> Vec<String> {
+        let own_domain = own_domain.to_ascii_lowercase();
+        let mut links = Vec::new();
+        for captures in Self::href_pattern().captures_iter(content) {
+            let target = captures[1].trim();
+            let lower = target.to_ascii_lowercase();
+            let (is_shadow_scheme, rest) = if let Some(rest) = lower.strip_prefix("shadow://") {
+                (true, rest)
+            } else if let Some(rest) = lower.strip_prefix("https://").or_else(|| lower.strip_prefix("http://")) {
+                (false, rest)
+            } else if let Some(rest) = lower.strip_prefix("//") {
+                (false, rest)
+            } else {
+                continue;
+            };
+
+            let host = rest
+                .split(['/', '?', '#', ':'])
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches('.');
+            let domain = if host.ends_with(".shadow") {
+                host.to_string()
+            } else if is_shadow_scheme && !host.is_empty() {
+                format!("{}.shadow", host)
+            } else {
+                continue;
+            };
+
+            if domain != own_domain
+                && ApolloValidator::validate_domain(&domain).is_ok()
+                && !links.contains(&domain)
+            {
+                links.push(domain);
+            }
+        }
+        links
+    }
+
+    /// Create the text index `search` relies on. A collection can have only one, so its
+    /// fields and weights change by dropping `search_text` first.
+    pub async fn ensure_indexes(&self) -> Result<(), mongodb::error::Error> {
+        let index = IndexModel::builder()
+            .keys(doc! { "domain": "text", "title": "text", "description": "text", "keywords": "text" })
+            .options(
+                IndexOptions::builder()
+                    .name("search_text".to_string())
+                    // Titles and domains say more about a site than its description
+                    .weights(doc! { "title": 5, "domain": 3, "keywords": 2, "description": 1 })
+                    .build(),
+            )
+            .build();
+        self.get_index_collection().create_index(index, None).await?;
+        Ok(())
+    }
+
+    /// Index entries matching `query` in `options.sort` order, resuming after `after`
+    /// (rank, id). Returns the page and the cursor for the next one.
     pub async fn search(
         &self,
         query: &str,
+        options: SearchOptions,
         limit: i64,
-    ) -> Result<Vec<SearchIndex>, mongodb::error::Error> {
-        let collection = self.get_index_collection();
-        
-        let filter = doc! {
-            "$or": [
-                { "domain": { "$regex": query, "$options": "i" } },
-                { "title": { "$regex": query, "$options": "i" } },
-                { "description": { "$regex": query, "$options": "i" } },
-                { "keywords": { "$in": [query] } }
-            ]
+        after: Option<(f64, String)>,
+    ) -> Result<(Vec<SearchIndex>, Option<String>), mongodb::error::Error> {
+        self.search_with_filters(query, options, Vec::new(), None, limit, after).await
+    }
+
+    /// How many index entries `search` finds for `query` across all pages
+    pub async fn count(&self, query: &str) -> Result<u64, mongodb::error::Error> {
+        self.get_index_collection()
+            .count_documents(search_filter(query), None)
+            .await
+    }
+
+    /// `search`, narrowed to sites analyzed under any of `categories` and to pages in
+    /// `language`. Single-language sites match on their detected language.
+    pub async fn search_with_filters(
+        &self,
+        query: &str,
+        options: SearchOptions,
+        categories: Vec<String>,
+        language: Option<String>,
+        limit: i64,
+        after: Option<(f64, String)>,
+    ) -> Result<(Vec<SearchIndex>, Option<String>), mongodb::error::Error> {
+        let text = is_text_query(query);
+        let mut pipeline = vec![doc! { "$match": search_filter(query) }];
+        if text {
+            pipeline.push(doc! { "$addFields": { "score": { "$meta": "textScore" } } });
+        }
+        let rank = match options.sort {
+            // Substring matches carry no relevance, so popularity alone orders them
+            SearchSort::Relevance if text => doc! { "$multiply": ["$score", "$popularity_score"] },
+            SearchSort::Relevance | SearchSort::Popularity => doc! { "$toDouble": "$popularity_score" },
+            SearchSort::Recency => doc! { "$toDouble": { "$toDate": "$indexed_at" } },
         };
-        
-        let options = mongodb::options::FindOptions::builder()
-            .limit(limit)
-            .sort(doc! { "popularity_score": -1, "indexed_at": -1 })
-            .build();
-        
-        let mut cursor = collection.find(filter, options).await?;
-        let mut results = Vec::new();
-        
-        while let Some(doc) = cursor.try_next().await? {
-            results.push(doc);
+        pipeline.push(doc! { "$addFields": { "rank": rank } });
+
+        if !categories.is_empty() || language.is_some() {
+            pipeline.push(doc! { "$lookup": {
+                "from": "content_analysis",
+                "localField": "domain",
+                "foreignField": "domain",
+                "as": "analysis",
+            } });
+            if !categories.is_empty() {
+                pipeline.push(doc! { "$match": { "analysis.categories": { "$in": categories } } });
+            }
+            if let Some(language) = language {
+                pipeline.push(doc! { "$match": { "$or": [
+                    { "language": &language },
+                    { "language": null, "analysis.language": &language },
+                ] } });
+            }
+            pipeline.push(doc! { "$project": { "analysis": 0 } });
         }
-        
-        Ok(results)
+
+        if let Some((rank, id)) = after {
+            pipeline.push(doc! { "$match": keyset_filter("rank", rank, &id) });
+        }
+        pipeline.push(doc! { "$sort": { "rank": -1, "_id": -1 } });
+        pipeline.push(doc! { "$limit": limit + 1 });
+
+        let documents: Vec<Document> = self.get_index_collection()
+            .aggregate(pipeline, None)
+            .await?
+            .try_collect()
+            .await?;
+        let mut results = documents.into_iter()
+            .map(mongodb::bson::from_document::<SearchIndex>)
+            .collect::<Result<Vec<_>, _>>()?;
+        let next_cursor = next_page_cursor(&mut results, limit, |entry| {
+            encode_score_cursor(entry.rank.unwrap_or_default(), &entry.id)
+        });
+        Ok((results, next_cursor))
     }
 
+    /// Analyze a site's content; a declared `language` is trusted over detection
     pub async fn analyze_content(
         &self,
         domain: &str,
         content: &str,
+        language: Option<&str>,
     ) -> Result<ContentAnalysis, mongodb::error::Error> {
         let collection = self.get_analysis_collection();
         
         let word_count = content.split_whitespace().count();
-        let language = Self::detect_language(content);
+        let language = language
+            .map(|l| l.to_string())
+            .or_else(|| Self::detect_language(content));
         let categories = Self::categorize_content(content);
         let trust_score = self.calculate_trust_score(domain).await.unwrap_or(0.5);
         
@@ -154,6 +586,79 @@ impl AthenaIndexer {
         Ok(analysis)
     }
 
+    /// Compare the search index against the registered sites.
+    /// Coverage counts sites with at least one index entry; entries whose
+    /// program no longer has a site are reported as stale.
+    pub async fn get_index_health(&self) -> Result<IndexHealth, mongodb::error::Error> {
+        let site_addresses: HashSet<String> = self.db
+            .collection::<crate::db::Site>("sites")
+            .distinct("_id", None, None)
+            .await?
+            .into_iter()
+            .filter_map(|b| b.as_str().map(|s| s.to_string()))
+            .collect();
+
+        let mut cursor = self.get_index_collection().find(doc! {}, None).await?;
+        let mut total_indexed = 0;
+        let mut stale_entries = 0;
+        let mut total_keywords = 0;
+        let mut covered = HashSet::new();
+        let mut oldest: Option<DateTime<Utc>> = None;
+        let mut newest: Option<DateTime<Utc>> = None;
+
+        while let Some(entry) = cursor.try_next().await? {
+            total_indexed += 1;
+            total_keywords += entry.keywords.len();
+            if site_addresses.contains(&entry.program_address) {
+                covered.insert(entry.program_address);
+            } else {
+                stale_entries += 1;
+            }
+            oldest = Some(oldest.map_or(entry.indexed_at, |o| o.min(entry.indexed_at)));
+            newest = Some(newest.map_or(entry.indexed_at, |n| n.max(entry.indexed_at)));
+        }
+
+        let total_sites = site_addresses.len();
+        let coverage_percent = if total_sites == 0 {
+            100.0
+        } else {
+            covered.len() as f64 / total_sites as f64 * 100.0
+        };
+        let avg_keywords_per_site = if total_indexed == 0 {
+            0.0
+        } else {
+            total_keywords as f64 / total_indexed as f64
+        };
+
+        Ok(IndexHealth {
+            total_indexed,
+            total_sites,
+            coverage_percent,
+            stale_entries,
+            avg_keywords_per_site,
+            oldest_index_entry: oldest,
+            newest_index_entry: newest,
+        })
+    }
+
+    /// Periodically check index health and log a warning when it degrades
+    pub fn spawn_health_monitor(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match self.get_index_health().await {
+                    Ok(health) => {
+                        for warning in health.warnings() {
+                            tracing::warn!("Athena index health: {}", warning);
+                        }
+                    }
+                    Err(e) => tracing::warn!("Athena index health check failed: {}", e),
+                }
+            }
+        })
+    }
+
     fn extract_keywords(content: &str, title: Option<&str>, description: Option<&str>) -> Vec<String> {
         let mut text = String::new();
         if let Some(t) = title {
@@ -182,7 +687,7 @@ impl AthenaIndexer {
         }
         
         let mut keywords: Vec<(String, usize)> = word_count.into_iter().collect();
-        keywords.sort_by(|a, b| b.1.cmp(&a.1));
+        keywords.sort_by_key(|k| std::cmp::Reverse(k.1));
         keywords.into_iter().take(10).map(|(word, _)| word).collect()
     }
 
@@ -252,10 +757,10 @@ impl AthenaIndexer {
         categories
     }
 
-    async fn calculate_popularity(&self, _domain: &str) -> Result<f64, mongodb::error::Error> {
-        // Calculate based on visits, bookmarks, etc.
-        // Simplified for now
-        Ok(1.0)
+    async fn calculate_popularity(&self, domain: &str) -> Result<f64, mongodb::error::Error> {
+        // Visits and bookmarks aren't factored in yet, only inbound links
+        let linking_owners = self.linking_owners(domain).await?;
+        Ok(BASE_POPULARITY + Self::link_boost(linking_owners))
     }
 
     async fn calculate_trust_score(&self, domain: &str) -> Result<f64, mongodb::error::Error> {
@@ -302,7 +807,104 @@ impl AthenaIndexer {
         }
         
         // Clamp between 0.0 and 1.0
-        Ok(trust_score.min(1.0).max(0.0))
+        Ok(trust_score.clamp(0.0, 1.0))
     }
 }
 
+/// Whether `query` is long enough for the text index
+fn is_text_query(query: &str) -> bool {
+    query.trim().chars().count() >= MIN_TEXT_QUERY_LEN
+}
+
+/// Index entries `query` matches: a `$text` search, or a case-insensitive substring match
+/// on the indexed fields for queries too short for it
+fn search_filter(query: &str) -> Document {
+    if is_text_query(query) {
+        return doc! { "$text": { "$search": query } };
+    }
+    let pattern = regex::escape(query.trim());
+    let fields = ["domain", "title", "description", "keywords"]
+        .map(|field| doc! { field: { "$regex": &pattern, "$options": "i" } });
+    doc! { "$or": fields.to_vec() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(coverage_percent: f64, stale_entries: usize) -> IndexHealth {
+        IndexHealth {
+            total_indexed: 0,
+            total_sites: 0,
+            coverage_percent,
+            stale_entries,
+            avg_keywords_per_site: 0.0,
+            oldest_index_entry: None,
+            newest_index_entry: None,
+        }
+    }
+
+    #[test]
+    fn test_index_health_warnings() {
+        assert!(health(100.0, 0).warnings().is_empty());
+        assert!(health(90.0, 10).warnings().is_empty());
+        assert_eq!(health(89.9, 0).warnings().len(), 1);
+        assert_eq!(health(50.0, 11).warnings().len(), 2);
+    }
+
+    #[test]
+    fn test_extract_shadow_links() {
+        let html = r#"
+            <a href="shadow://friend">Friend</a>
+            <a href='https://Docs.Shadow/guide?x=1'>Docs</a>
+            <a href="https://example.com">Elsewhere</a>
+            <a href="shadow://friend.shadow/again">Again</a>
+            <a href="/local">Local</a>
+            <a href="https://self.shadow/">Self</a>
+            <link href="//bad_name.shadow/style.css">
+        "#;
+        assert_eq!(
+            AthenaIndexer::extract_shadow_links(html, "self.shadow"),
+            vec!["friend.shadow".to_string(), "docs.shadow".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_extract_external_links() {
+        let html = r#"
+            <a href="https://Example.com/a">A</a>
+            <a href="http://example.com./b">Same host</a>
+            <a href="https://docs.shadow/guide">Shadow</a>
+            <a href="https://trusted.org@evil.net/login">Userinfo</a>
+            <link href="//cdn.example.org/style.css">
+            <a href="mailto:hi@example.com">Mail</a>
+            <a href="javascript:alert(1)">Script</a>
+        "#;
+        assert_eq!(
+            AthenaIndexer::extract_external_links(html),
+            vec!["example.com".to_string(), "evil.net".to_string(), "cdn.example.org".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_short_queries_match_by_substring() {
+        assert_eq!(search_filter("nft"), doc! { "$text": { "$search": "nft" } });
+        let filter = search_filter("a.");
+        let fields = filter.get_array("$or").unwrap();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(
+            fields[0].as_document().unwrap(),
+            &doc! { "domain": { "$regex": "a\\.", "$options": "i" } }
+        );
+    }
+
+    #[test]
+    fn test_link_boost_is_damped_and_capped() {
+        assert_eq!(AthenaIndexer::link_boost(0), 0.0);
+        let one = AthenaIndexer::link_boost(1);
+        let two = AthenaIndexer::link_boost(2);
+        assert!(one > 0.0 && two > one);
+        assert!(two - one < one);
+        assert!(AthenaIndexer::link_boost(usize::MAX) <= MAX_LINK_BOOST);
+    }
+}
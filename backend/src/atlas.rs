@@ -0,0 +1,294 @@
+// Atlas - Titan who holds up the heavens
+// Tracks Arweave uploads from quote and payment until the data is permanently confirmed
+
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::ArweaveConfig;
+use crate::storage::{ArweaveProgress, BundlrStorage};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStatus {
+    Pending,
+    Seeded,
+    Confirmed,
+    /// Not confirmed within the stall timeout
+    Stalled,
+}
+
+impl UploadStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            UploadStatus::Pending => "pending",
+            UploadStatus::Seeded => "seeded",
+            UploadStatus::Confirmed => "confirmed",
+            UploadStatus::Stalled => "stalled",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArweaveUpload {
+    /// Arweave transaction id, without the arweave:// prefix
+    #[serde(rename = "_id")]
+    pub tx_id: String,
+    pub bytes: i64,
+    /// Price the node quoted before the upload, in atomic units of `currency`
+    pub quoted_price: i64,
+    pub currency: String,
+    pub balance_before: Option<i64>,
+    pub balance_after: Option<i64>,
+    pub status: UploadStatus,
+    /// Uploads of this data so far, counting the original
+    pub attempts: u32,
+    #[serde(default)]
+    pub replaces: Option<String>,
+    #[serde(default)]
+    pub replaced_by: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+/// What an upload of `bytes` would cost and whether the funding account covers it
+#[derive(Debug, Serialize, Clone)]
+pub struct CostEstimate {
+    pub bytes: usize,
+    pub price: u64,
+    pub currency: String,
+    pub balance: Option<u64>,
+    pub sufficient: Option<bool>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PollSummary {
+    pub seeded: u32,
+    pub confirmed: u32,
+    pub stalled: u32,
+    pub reuploaded: u32,
+}
+
+pub struct AtlasTracker {
+    db: Database,
+    bundlr: Arc<BundlrStorage>,
+    config: ArweaveConfig,
+    clock: SharedClock,
+}
+
+impl AtlasTracker {
+    pub fn new(db: Database, bundlr: Arc<BundlrStorage>, config: ArweaveConfig) -> Self {
+        Self { db, bundlr, config, clock: SystemClock::shared() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn get_collection(&self) -> Collection<ArweaveUpload> {
+        self.db.collection::<ArweaveUpload>("arweave_uploads")
+    }
+
+    pub async fn estimate(&self, bytes: usize) -> Result<CostEstimate, String> {
+        let price = self.bundlr.get_price(bytes).await?;
+        let mut warnings = Vec::new();
+        let balance = match self.bundlr.get_balance().await {
+            Ok(balance) => Some(balance),
+            Err(e) => {
+                warnings.push(format!("Could not read the Bundlr funding balance: {}", e));
+                None
+            }
+        };
+        let sufficient = balance.map(|balance| balance >= price);
+        if sufficient == Some(false) {
+            warnings.push(format!(
+                "Bundlr balance {} is below the quoted price {}; fund the account before uploading",
+                balance.unwrap_or_default(),
+                price
+            ));
+        }
+
+        Ok(CostEstimate {
+            bytes,
+            price,
+            currency: self.bundlr.currency().to_string(),
+            balance,
+            sufficient,
+            warnings,
+        })
+    }
+
+    /// Quote, upload and start tracking. Returns the new upload record.
+    pub async fn upload(&self, data: &[u8], tags: Vec<(&str, &str)>) -> Result<ArweaveUpload, String> {
+        self.upload_attempt(data, tags, 1, None).await
+    }
+
+    async fn upload_attempt(
+        &self,
+        data: &[u8],
+        tags: Vec<(&str, &str)>,
+        attempts: u32,
+        replaces: Option<&str>,
+    ) -> Result<ArweaveUpload, String> {
+        let quoted_price = self.bundlr.get_price(data.len()).await?;
+        let balance_before = self.bundlr.get_balance().await.ok();
+        let uri = self.bundlr.upload(data, tags).await?;
+        let balance_after = self.bundlr.get_balance().await.ok();
+
+        let now = self.clock.now_utc();
+        let upload = ArweaveUpload {
+            tx_id: uri.strip_prefix("arweave://").unwrap_or(&uri).to_string(),
+            bytes: data.len() as i64,
+            quoted_price: quoted_price as i64,
+            currency: self.bundlr.currency().to_string(),
+            balance_before: balance_before.map(|b| b as i64),
+            balance_after: balance_after.map(|b| b as i64),
+            status: UploadStatus::Pending,
+            attempts,
+            replaces: replaces.map(|r| r.to_string()),
+            replaced_by: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+            confirmed_at: None,
+        };
+        let bson_now = mongodb::bson::DateTime::from_millis(now.timestamp_millis());
+        self.db.collection::<Document>("arweave_uploads")
+            .insert_one(doc! {
+                "_id": &upload.tx_id,
+                "bytes": upload.bytes,
+                "quoted_price": upload.quoted_price,
+                "currency": &upload.currency,
+                "balance_before": upload.balance_before,
+                "balance_after": upload.balance_after,
+                "status": upload.status.as_str(),
+                "attempts": attempts,
+                "replaces": &upload.replaces,
+                "replaced_by": null,
+                "error": null,
+                "created_at": bson_now,
+                "updated_at": bson_now,
+                "confirmed_at": null,
+            }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(upload)
+    }
+
+    pub async fn get_upload(&self, tx_id: &str) -> Result<Option<ArweaveUpload>, String> {
+        let tx_id = tx_id.strip_prefix("arweave://").unwrap_or(tx_id);
+        self.get_collection()
+            .find_one(doc! { "_id": tx_id }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn set_status(&self, tx_id: &str, status: UploadStatus, extra: Document) -> Result<(), String> {
+        let mut set = doc! { "status": status.as_str(), "updated_at": mongodb::bson::DateTime::now() };
+        set.extend(extra);
+        self.get_collection()
+            .update_one(doc! { "_id": tx_id }, doc! { "$set": set }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(())
+    }
+
+    /// Advance every unconfirmed upload once. Uploads still unconfirmed after the
+    /// stall timeout are flagged and re-uploaded while attempts remain.
+    pub async fn poll_once(&self) -> Result<PollSummary, String> {
+        let mut summary = PollSummary::default();
+        let filter = doc! {
+            "$or": [
+                { "status": { "$in": ["pending", "seeded"] } },
+                { "status": "stalled", "replaced_by": null, "attempts": { "$lt": self.config.max_upload_attempts } },
+            ]
+        };
+        let uploads: Vec<ArweaveUpload> = self.get_collection()
+            .find(filter, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let stall_after = chrono::Duration::seconds(self.config.stall_timeout_seconds as i64);
+        for upload in uploads {
+            if upload.status != UploadStatus::Stalled {
+                match self.bundlr.get_progress(&upload.tx_id).await {
+                    Ok(ArweaveProgress::Confirmed) => {
+                        let now = mongodb::bson::DateTime::now();
+                        self.set_status(&upload.tx_id, UploadStatus::Confirmed, doc! { "confirmed_at": now }).await?;
+                        summary.confirmed += 1;
+                        continue;
+                    }
+                    Ok(ArweaveProgress::Seeded) if upload.status == UploadStatus::Pending => {
+                        self.set_status(&upload.tx_id, UploadStatus::Seeded, doc! {}).await?;
+                        summary.seeded += 1;
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("Atlas status check for {} failed: {}", upload.tx_id, e),
+                }
+                if self.clock.now_utc() - upload.created_at < stall_after {
+                    continue;
+                }
+                self.set_status(&upload.tx_id, UploadStatus::Stalled, doc! {}).await?;
+                summary.stalled += 1;
+            }
+
+            if upload.attempts >= self.config.max_upload_attempts {
+                continue;
+            }
+            match self.reupload(&upload).await {
+                Ok(replacement) => {
+                    self.set_status(&upload.tx_id, UploadStatus::Stalled, doc! { "replaced_by": &replacement.tx_id, "error": null }).await?;
+                    summary.reuploaded += 1;
+                }
+                Err(e) => {
+                    tracing::warn!("Atlas re-upload of stalled {} failed: {}", upload.tx_id, e);
+                    self.set_status(&upload.tx_id, UploadStatus::Stalled, doc! { "error": e }).await?;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    async fn reupload(&self, upload: &ArweaveUpload) -> Result<ArweaveUpload, String> {
+        let data = match self.bundlr.fetch_from_node(&upload.tx_id).await {
+            Ok(data) => data,
+            Err(_) => self.bundlr.get(&upload.tx_id).await?,
+        };
+        self.upload_attempt(&data, vec![], upload.attempts + 1, Some(&upload.tx_id)).await
+    }
+
+    /// Poll upload status on the configured interval until shutdown
+    pub fn spawn(self: Arc<Self>, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        let interval = Duration::from_secs(self.config.status_poll_interval_seconds);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.poll_once().await {
+                    Ok(summary) if summary == PollSummary::default() => {}
+                    Ok(summary) => tracing::info!("Atlas upload poll: {:?}", summary),
+                    Err(e) => tracing::warn!("Atlas upload poll failed: {}", e),
+                }
+            }
+        })
+    }
+}
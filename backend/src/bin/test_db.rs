@@ -16,7 +16,7 @@ async fn main() -> anyhow::Result<()> {
     
     // Mask password in connection string for display
     let masked_url = if database_url.contains("@") {
-        database_url.split("@").nth(0)
+        database_url.split("@").next()
             .map(|s| format!("{}@***", s))
             .unwrap_or_else(|| "mongodb://***".to_string())
     } else {
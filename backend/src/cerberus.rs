@@ -0,0 +1,221 @@
+// Cerberus - Guardian of the Gates
+// Site-scoped deploy tokens that let CI publish content without the owner's wallet
+
+use crate::error::ShadowError;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Header CI sends the raw token in
+pub const DEPLOY_TOKEN_HEADER: &str = "X-Shadow-Deploy-Token";
+const TOKEN_PREFIX: &str = "shdt";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeployToken {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub program_address: String,
+    /// Wallet that created the token. The token acts with that wallet's role on the site
+    /// and stops working once the wallet loses access.
+    pub owner_pubkey: String,
+    pub name: String,
+    /// Pages fetched with this token get the live reload script, as with `?dev=1`
+    #[serde(default)]
+    pub dev: bool,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Audit entry written every time a token is used
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeployTokenEvent {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub token_id: String,
+    pub program_address: String,
+    pub action: String,
+    pub detail: Document,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Split `shdt_<id>_<secret>` into its id and secret
+fn parse_token(raw: &str) -> Option<(&str, &str)> {
+    let rest = raw.trim().strip_prefix(TOKEN_PREFIX)?.strip_prefix('_')?;
+    let (id, secret) = rest.split_once('_')?;
+    (!id.is_empty() && !secret.is_empty()).then_some((id, secret))
+}
+
+pub struct DeployTokens {
+    db: Database,
+}
+
+impl DeployTokens {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    fn get_collection(&self) -> Collection<DeployToken> {
+        self.db.collection::<DeployToken>("deploy_tokens")
+    }
+
+    fn get_events_collection(&self) -> Collection<DeployTokenEvent> {
+        self.db.collection::<DeployTokenEvent>("deploy_token_events")
+    }
+
+    /// Create a token for one site. The raw token is returned once and only its hash is stored.
+    pub async fn create(
+        &self,
+        program_address: &str,
+        owner_pubkey: &str,
+        name: &str,
+        dev: bool,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(DeployToken, String), String> {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let secret = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let now = Utc::now();
+
+        let token = DeployToken {
+            id: id.clone(),
+            program_address: program_address.to_string(),
+            owner_pubkey: owner_pubkey.to_string(),
+            name: name.to_string(),
+            dev,
+            token_hash: hash_secret(&secret),
+            created_at: now,
+            expires_at,
+            revoked_at: None,
+            last_used_at: None,
+        };
+        let to_bson = |at: DateTime<Utc>| mongodb::bson::DateTime::from_millis(at.timestamp_millis());
+        self.db
+            .collection::<Document>("deploy_tokens")
+            .insert_one(doc! {
+                "_id": &token.id,
+                "program_address": &token.program_address,
+                "owner_pubkey": &token.owner_pubkey,
+                "name": &token.name,
+                "dev": dev,
+                "token_hash": &token.token_hash,
+                "created_at": to_bson(now),
+                "expires_at": expires_at.map(to_bson),
+                "revoked_at": null,
+                "last_used_at": null,
+            }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok((token, format!("{}_{}_{}", TOKEN_PREFIX, id, secret)))
+    }
+
+    /// Tokens for a site, newest first
+    pub async fn list(&self, program_address: &str) -> Result<Vec<DeployToken>, String> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        let cursor = self.get_collection()
+            .find(doc! { "program_address": program_address }, options)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        cursor.try_collect().await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    pub async fn get(&self, program_address: &str, token_id: &str) -> Result<Option<DeployToken>, ShadowError> {
+        Ok(self.get_collection()
+            .find_one(doc! { "_id": token_id, "program_address": program_address }, None)
+            .await?)
+    }
+
+    /// Revoke a site's token; false when it doesn't exist or was already revoked
+    pub async fn revoke(&self, program_address: &str, token_id: &str) -> Result<bool, String> {
+        let result = self.get_collection()
+            .update_one(
+                doc! { "_id": token_id, "program_address": program_address, "revoked_at": null },
+                doc! { "$set": { "revoked_at": mongodb::bson::DateTime::now() } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Resolve a raw token that must be live and scoped to `program_address`
+    pub async fn authorize(&self, raw: &str, program_address: &str) -> Result<DeployToken, ShadowError> {
+        let (id, secret) = parse_token(raw)
+            .ok_or_else(|| ShadowError::AuthFailed("Malformed deploy token".to_string()))?;
+        let token = self.get_collection()
+            .find_one(doc! { "_id": id }, None)
+            .await?
+            .filter(|token| token.token_hash == hash_secret(secret))
+            .ok_or_else(|| ShadowError::AuthFailed("Unknown deploy token".to_string()))?;
+
+        if token.revoked_at.is_some() {
+            return Err(ShadowError::AuthFailed("Deploy token has been revoked".to_string()));
+        }
+        if token.expires_at.is_some_and(|at| at <= Utc::now()) {
+            return Err(ShadowError::AuthFailed("Deploy token has expired".to_string()));
+        }
+        if token.program_address != program_address {
+            return Err(ShadowError::Forbidden("Deploy token is not valid for this site".to_string()));
+        }
+        Ok(token)
+    }
+
+    /// Mark the token used and append an audit entry
+    pub async fn record_use(&self, token: &DeployToken, action: &str, detail: Document) -> Result<(), String> {
+        let now = Utc::now();
+        self.get_collection()
+            .update_one(
+                doc! { "_id": &token.id },
+                doc! { "$set": { "last_used_at": mongodb::bson::DateTime::from_millis(now.timestamp_millis()) } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let event = DeployTokenEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            token_id: token.id.clone(),
+            program_address: token.program_address.clone(),
+            action: action.to_string(),
+            detail,
+            created_at: now,
+        };
+        self.get_events_collection()
+            .insert_one(event, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_token() {
+        assert_eq!(parse_token("shdt_abc_def"), Some(("abc", "def")));
+        assert_eq!(parse_token(" shdt_abc_def_ghi\n"), Some(("abc", "def_ghi")));
+        assert_eq!(parse_token("shdt_abc_"), None);
+        assert_eq!(parse_token("shdt_abc"), None);
+        assert_eq!(parse_token("bearer_abc_def"), None);
+    }
+}
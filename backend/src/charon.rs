@@ -0,0 +1,333 @@
+// Charon - Ferryman between the legacy web and Shadow
+// Bridges DNS domains to .shadow domains (proven with a TXT record) and signs the mappings
+
+use crate::config::BridgeConfig;
+use crate::error::ShadowError;
+use crate::olympus::OlympusCA;
+use crate::utils::parse_keypair;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use mongodb::bson::doc;
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shadow_signing::SigningError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::time::Duration;
+
+/// Label the ownership TXT record lives under, e.g. `_shadow-bridge.example.com`
+pub const BRIDGE_TXT_LABEL: &str = "_shadow-bridge";
+const ASSERTION_ISSUER: &str = "shadow-backend";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegacyBridge {
+    #[serde(rename = "_id")]
+    pub legacy_domain: String,
+    pub shadow_domain: String,
+    pub program_address: String,
+    pub owner_pubkey: String,
+    /// Value the owner publishes in the TXT record
+    pub challenge: String,
+    pub verified: bool,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+impl LegacyBridge {
+    pub fn txt_name(&self) -> String {
+        format!("{}.{}", BRIDGE_TXT_LABEL, self.legacy_domain)
+    }
+
+    pub fn txt_value(&self) -> String {
+        format!("shadow-bridge={}", self.challenge)
+    }
+}
+
+/// Claims of a signed legacy -> Shadow mapping
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct BridgeAssertion {
+    pub iss: String,
+    pub legacy_domain: String,
+    pub shadow_domain: String,
+    pub program_address: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AssertionHeader {
+    alg: String,
+    typ: String,
+    /// Signer public key (base58)
+    kid: String,
+}
+
+/// Normalize a legacy hostname: lowercase, no scheme, path, port or trailing dot.
+/// .shadow names are rejected since they never need bridging.
+pub fn normalize_legacy_domain(input: &str) -> Result<String, ShadowError> {
+    let host = input.trim().to_lowercase();
+    let host = host.split_once("://").map(|(_, rest)| rest).unwrap_or(&host);
+    let host = host.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default().trim_end_matches('.');
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid = host.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !valid {
+        return Err(ShadowError::BadRequest(format!("Invalid legacy domain: {}", input)));
+    }
+    if host.ends_with(".shadow") {
+        return Err(ShadowError::BadRequest("Legacy domain cannot be a .shadow domain".to_string()));
+    }
+    Ok(host.to_string())
+}
+
+/// Sign claims as a compact JWS (`header.payload.signature`, base64url, EdDSA).
+/// Header and claims are canonical JSON so other implementations can reproduce the bytes.
+pub fn sign_assertion(keypair: &Keypair, claims: &BridgeAssertion) -> String {
+    let header = AssertionHeader {
+        alg: "EdDSA".to_string(),
+        typ: "JWT".to_string(),
+        kid: keypair.pubkey().to_string(),
+    };
+    let signing_input = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(shadow_signing::to_canonical_vec(&header).unwrap_or_default()),
+        URL_SAFE_NO_PAD.encode(shadow_signing::to_canonical_vec(claims).unwrap_or_default()),
+    );
+    let signature = shadow_signing::sign_bytes(&keypair.to_bytes(), signing_input.as_bytes())
+        .expect("Solana keypairs are valid ed25519 keypairs");
+    format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature))
+}
+
+/// Check an assertion's signature against `signer` and that it hasn't expired at `now`
+pub fn verify_assertion(token: &str, signer: &Pubkey, now: i64) -> Result<BridgeAssertion, String> {
+    let mut parts = token.split('.');
+    let (Some(header_part), Some(payload_part), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return Err("Malformed assertion".to_string());
+    };
+
+    let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|e| format!("Malformed assertion: {}", e));
+    let header: AssertionHeader = serde_json::from_slice(&decode(header_part)?)
+        .map_err(|e| format!("Malformed assertion header: {}", e))?;
+    if header.alg != "EdDSA" {
+        return Err(format!("Unsupported algorithm {}", header.alg));
+    }
+
+    let signing_input = format!("{}.{}", header_part, payload_part);
+    shadow_signing::verify_bytes(signer.as_ref(), signing_input.as_bytes(), &decode(signature)?)
+        .map_err(|e| match e {
+            SigningError::InvalidSignature => "Invalid assertion signature".to_string(),
+            _ => "Malformed assertion signature".to_string(),
+        })?;
+
+    let claims: BridgeAssertion = serde_json::from_slice(&decode(payload_part)?)
+        .map_err(|e| format!("Malformed assertion claims: {}", e))?;
+    if claims.exp <= now {
+        return Err("Assertion has expired".to_string());
+    }
+    Ok(claims)
+}
+
+pub struct CharonBridge {
+    db: Database,
+    config: BridgeConfig,
+    keypair: Keypair,
+    client: reqwest::Client,
+}
+
+impl CharonBridge {
+    /// Signs with BRIDGE_SIGNING_KEY, or an ephemeral key when it isn't set
+    pub fn new(db: Database, config: BridgeConfig) -> Self {
+        let keypair = match config.signing_key.as_deref().map(parse_keypair) {
+            Some(Ok(keypair)) => keypair,
+            Some(Err(e)) => {
+                tracing::warn!("Ignoring BRIDGE_SIGNING_KEY ({}); bridge assertions use an ephemeral key", e);
+                Keypair::new()
+            }
+            None => {
+                tracing::warn!("BRIDGE_SIGNING_KEY not set; bridge assertions use an ephemeral key");
+                Keypair::new()
+            }
+        };
+        Self {
+            db,
+            config,
+            keypair,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn with_signing_keypair(mut self, keypair: Keypair) -> Self {
+        self.keypair = keypair;
+        self
+    }
+
+    /// Public key clients verify assertions against
+    pub fn signer(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    fn get_collection(&self) -> Collection<LegacyBridge> {
+        self.db.collection::<LegacyBridge>("legacy_bridges")
+    }
+
+    pub async fn get_bridge(&self, legacy_domain: &str) -> Result<Option<LegacyBridge>, String> {
+        self.get_collection()
+            .find_one(doc! { "_id": legacy_domain }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Start (or restart) bridging a legacy domain with a fresh TXT challenge.
+    /// A domain verified for another wallet can't be taken over this way.
+    pub async fn create_bridge(
+        &self,
+        legacy_domain: &str,
+        shadow_domain: &str,
+        program_address: &str,
+        owner_pubkey: &str,
+    ) -> Result<LegacyBridge, ShadowError> {
+        if let Some(existing) = self.get_bridge(legacy_domain).await? {
+            if existing.verified && existing.owner_pubkey != owner_pubkey {
+                return Err(ShadowError::Conflict(format!("{} is already bridged", legacy_domain)));
+            }
+        }
+
+        let now = Utc::now();
+        let bridge = LegacyBridge {
+            legacy_domain: legacy_domain.to_string(),
+            shadow_domain: shadow_domain.to_string(),
+            program_address: program_address.to_string(),
+            owner_pubkey: owner_pubkey.to_string(),
+            challenge: uuid::Uuid::new_v4().simple().to_string(),
+            verified: false,
+            created_at: now,
+            verified_at: None,
+        };
+        self.db.collection::<mongodb::bson::Document>("legacy_bridges")
+            .replace_one(
+                doc! { "_id": legacy_domain },
+                doc! {
+                    "_id": &bridge.legacy_domain,
+                    "shadow_domain": &bridge.shadow_domain,
+                    "program_address": &bridge.program_address,
+                    "owner_pubkey": &bridge.owner_pubkey,
+                    "challenge": &bridge.challenge,
+                    "verified": false,
+                    "created_at": mongodb::bson::DateTime::from_millis(now.timestamp_millis()),
+                    "verified_at": null,
+                },
+                mongodb::options::ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await?;
+        Ok(bridge)
+    }
+
+    /// Look for the challenge in the domain's TXT records and mark the bridge verified
+    pub async fn verify_bridge(&self, bridge: &LegacyBridge) -> Result<bool, ShadowError> {
+        let records = self.lookup_txt(&bridge.txt_name()).await
+            .map_err(ShadowError::BadRequest)?;
+        if !records.iter().any(|r| r.trim() == bridge.txt_value()) {
+            return Ok(false);
+        }
+
+        self.get_collection()
+            .update_one(
+                doc! { "_id": &bridge.legacy_domain, "challenge": &bridge.challenge },
+                doc! { "$set": { "verified": true, "verified_at": mongodb::bson::DateTime::now() } },
+                None,
+            )
+            .await?;
+        Ok(true)
+    }
+
+    /// TXT records for `name` over DNS-over-HTTPS (JSON API)
+    async fn lookup_txt(&self, name: &str) -> Result<Vec<String>, String> {
+        let response = self.client
+            .get(&self.config.doh_url)
+            .query(&[("name", name), ("type", "TXT")])
+            .header("Accept", "application/dns-json")
+            .send()
+            .await
+            .map_err(|e| format!("DNS lookup failed: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("DNS lookup failed: {}", response.status()));
+        }
+
+        let json: Value = response.json().await
+            .map_err(|e| format!("Invalid DNS response: {}", e))?;
+        Ok(json["Answer"]
+            .as_array()
+            .map(|answers| {
+                answers.iter()
+                    .filter(|a| a["type"].as_u64() == Some(16))
+                    .filter_map(|a| a["data"].as_str())
+                    // Long TXT values arrive as several quoted strings
+                    .map(|data| data.split("\" \"").collect::<String>().trim_matches('"').to_string())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Signed assertion for a verified bridge whose Shadow domain is still verified
+    /// and still points at the bridged program. None means nothing to vouch for.
+    pub async fn assert_bridge(&self, olympus: &OlympusCA, legacy_domain: &str) -> Result<Option<(BridgeAssertion, String)>, ShadowError> {
+        let Some(bridge) = self.get_bridge(legacy_domain).await?.filter(|b| b.verified) else {
+            return Ok(None);
+        };
+        let current = olympus.get_domain(&bridge.shadow_domain).await?
+            .filter(|d| d.verified && d.program_address == bridge.program_address);
+        if current.is_none() {
+            return Ok(None);
+        }
+
+        let now = Utc::now().timestamp();
+        let claims = BridgeAssertion {
+            iss: ASSERTION_ISSUER.to_string(),
+            legacy_domain: bridge.legacy_domain,
+            shadow_domain: bridge.shadow_domain,
+            program_address: bridge.program_address,
+            iat: now,
+            exp: now + self.config.assertion_ttl_seconds as i64,
+        };
+        let token = sign_assertion(&self.keypair, &claims);
+        Ok(Some((claims, token)))
+    }
+
+    /// Where /r sends visitors: the resolve URL with the assertion attached
+    pub fn redirect_url(&self, claims: &BridgeAssertion, token: &str) -> String {
+        let base = self.config.resolve_url.replace("{domain}", &claims.shadow_domain);
+        let separator = if base.contains('?') { '&' } else { '?' };
+        format!("{}{}assertion={}", base, separator, token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_legacy_domain() {
+        assert_eq!(normalize_legacy_domain("https://Example.COM/path?x=1").unwrap(), "example.com");
+        assert_eq!(normalize_legacy_domain("www.example.com.").unwrap(), "www.example.com");
+        assert_eq!(normalize_legacy_domain("example.com:8080").unwrap(), "example.com");
+        assert!(normalize_legacy_domain("localhost").is_err());
+        assert!(normalize_legacy_domain("bad_host.com").is_err());
+        assert!(normalize_legacy_domain("-bad.com").is_err());
+        assert!(normalize_legacy_domain("site.shadow").is_err());
+    }
+}
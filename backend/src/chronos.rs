@@ -1,11 +1,23 @@
 // Chronos: Time and history - Browser history and session management
 use mongodb::{Collection, Database};
-use mongodb::bson::doc;
+use mongodb::bson::{doc, Document};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use std::time::Duration;
 use futures_util::TryStreamExt;
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::PrivacyConfig;
+use crate::error::ShadowError;
+use crate::utils::{encode_cursor, html_escape, keyset_filter, next_page_cursor};
+use scraper::{ElementRef, Html, Selector};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 
+/// Most sessions a single merge may combine
+const MAX_MERGE_SESSIONS: usize = 50;
+
+/// Per-domain aggregate of a wallet's visits, kept for frecency and top-sites
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BrowserHistory {
     #[serde(rename = "_id")]
@@ -14,12 +26,36 @@ pub struct BrowserHistory {
     pub domain: String,
     pub program_address: String,
     pub title: Option<String>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub visited_at: DateTime<Utc>,
     pub visit_count: i32,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub last_visit: DateTime<Utc>,
     pub time_spent_seconds: i64,
 }
 
+/// A single page visit. Expires through the TTL index on `expires_at`
+/// when the wallet's effective retention is bounded.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryVisit {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub wallet_pubkey: String,
+    pub domain: String,
+    pub program_address: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    pub title: Option<String>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub visited_at: DateTime<Utc>,
+    pub duration_seconds: i64,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Created from a pre-event aggregate row by `migrate_legacy_history`
+    #[serde(default)]
+    pub legacy: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Bookmark {
     #[serde(rename = "_id")]
@@ -30,28 +66,59 @@ pub struct Bookmark {
     pub title: Option<String>,
     pub description: Option<String>,
     pub folder: Option<String>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub created_at: DateTime<Utc>,
     pub tags: Vec<String>,
 }
 
+/// A link read from a Netscape bookmark file, before it is stored as a `Bookmark`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedBookmark {
+    pub domain: String,
+    /// Only present in files Shadow exported; other browsers know nothing of programs
+    pub program_address: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Enclosing folder names, outermost first, joined with '/'
+    pub folder: Option<String>,
+    pub added_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BrowserSession {
     #[serde(rename = "_id")]
     pub session_id: String,
     pub wallet_pubkey: String,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub started_at: DateTime<Utc>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub last_activity: DateTime<Utc>,
     pub active_tabs: Vec<String>,
     pub total_visits: i32,
 }
 
+/// The part of a user record that drives history pruning
+#[derive(Debug, Deserialize)]
+struct RetentionSetting {
+    #[serde(rename = "_id")]
+    wallet_pubkey: String,
+    history_retention_days: Option<u32>,
+}
+
 pub struct ChronosManager {
     db: Database,
+    clock: SharedClock,
 }
 
 impl ChronosManager {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self { db, clock: SystemClock::shared() }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
     }
 
     pub fn get_history_collection(&self) -> Collection<BrowserHistory> {
@@ -66,19 +133,46 @@ impl ChronosManager {
         self.db.collection::<BrowserSession>("browser_sessions")
     }
 
+    pub fn get_visits_collection(&self) -> Collection<HistoryVisit> {
+        self.db.collection::<HistoryVisit>("history_visits")
+    }
+
+    /// Record one visit event and fold it into the domain's aggregate row.
+    /// `retention_days` stamps the event's expiry; None keeps it until cleared.
+    #[allow(clippy::too_many_arguments)]
     pub async fn record_visit(
         &self,
         wallet: &str,
         domain: &str,
         program_address: &str,
+        path: Option<&str>,
         title: Option<&str>,
         time_spent: Duration,
+        retention_days: Option<u32>,
     ) -> Result<(), mongodb::error::Error> {
+        let now = self.clock.now_utc();
+        let bson_now = mongodb::bson::DateTime::from_millis(now.timestamp_millis());
+        let expires_at = retention_days.map(|days| {
+            mongodb::bson::DateTime::from_millis((now + chrono::Duration::days(days as i64)).timestamp_millis())
+        });
+
+        self.db.collection::<Document>("history_visits")
+            .insert_one(doc! {
+                "_id": uuid::Uuid::new_v4().to_string(),
+                "wallet_pubkey": wallet,
+                "domain": domain,
+                "program_address": program_address,
+                "path": path,
+                "title": title,
+                "visited_at": bson_now,
+                "duration_seconds": time_spent.as_secs() as i64,
+                "expires_at": expires_at,
+                "legacy": false,
+            }, None)
+            .await?;
+
         let collection = self.get_history_collection();
         let id = format!("{}:{}", wallet, domain);
-        let now = Utc::now();
-        let bson_now = mongodb::bson::DateTime::from_millis(now.timestamp_millis());
-        
         let filter = doc! { "_id": &id };
         let update = doc! {
             "$set": {
@@ -94,6 +188,8 @@ impl ChronosManager {
             },
             "$setOnInsert": {
                 "visited_at": bson_now,
+                // Rows created alongside events never need the legacy migration
+                "events_migrated": true,
             }
         };
         let options = mongodb::options::UpdateOptions::builder()
@@ -104,10 +200,40 @@ impl ChronosManager {
         Ok(())
     }
 
+    /// A wallet's visit events, newest first, resuming after `after` (visited_at ms, id)
+    /// and then skipping `offset`. Returns the page and the cursor for the next one.
     pub async fn get_history(
         &self,
         wallet: &str,
         limit: i64,
+        offset: u64,
+        after: Option<(i64, String)>,
+    ) -> Result<(Vec<HistoryVisit>, Option<String>), mongodb::error::Error> {
+        let mut filter = doc! { "wallet_pubkey": wallet };
+        if let Some((visited_at, id)) = after {
+            filter.extend(keyset_filter("visited_at", mongodb::bson::DateTime::from_millis(visited_at), &id));
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "visited_at": -1, "_id": -1 })
+            .skip(offset)
+            .limit(limit + 1)
+            .build();
+        let mut visits: Vec<HistoryVisit> = self.get_visits_collection()
+            .find(filter, options)
+            .await?
+            .try_collect()
+            .await?;
+        let next_cursor = next_page_cursor(&mut visits, limit, |visit| {
+            encode_cursor(visit.visited_at.timestamp_millis(), &visit.id)
+        });
+        Ok((visits, next_cursor))
+    }
+
+    /// Per-domain aggregates, most recently visited first
+    pub async fn get_history_summary(
+        &self,
+        wallet: &str,
+        limit: i64,
     ) -> Result<Vec<BrowserHistory>, mongodb::error::Error> {
         let collection = self.get_history_collection();
         let filter = doc! { "wallet_pubkey": wallet };
@@ -127,12 +253,130 @@ impl ChronosManager {
     }
 
     pub async fn clear_history(&self, wallet: &str) -> Result<(), mongodb::error::Error> {
-        let collection = self.get_history_collection();
         let filter = doc! { "wallet_pubkey": wallet };
-        collection.delete_many(filter, None).await?;
+        self.get_visits_collection().delete_many(filter.clone(), None).await?;
+        self.get_history_collection().delete_many(filter, None).await?;
+        Ok(())
+    }
+
+    /// Forget one domain: its visit events and its aggregate row.
+    /// Returns the number of events removed.
+    pub async fn delete_domain_history(&self, wallet: &str, domain: &str) -> Result<u64, mongodb::error::Error> {
+        let deleted = self.get_visits_collection()
+            .delete_many(doc! { "wallet_pubkey": wallet, "domain": domain }, None)
+            .await?
+            .deleted_count;
+        self.get_history_collection()
+            .delete_one(doc! { "_id": format!("{}:{}", wallet, domain) }, None)
+            .await?;
+        Ok(deleted)
+    }
+
+    /// Number of visit events stored for a wallet
+    pub async fn count_history(&self, wallet: &str) -> Result<u64, mongodb::error::Error> {
+        self.get_visits_collection()
+            .count_documents(doc! { "wallet_pubkey": wallet }, None)
+            .await
+    }
+
+    /// Re-stamp the expiry of a wallet's events after its retention changes
+    pub async fn apply_retention(&self, wallet: &str, retention_days: Option<u32>) -> Result<(), mongodb::error::Error> {
+        let update = match retention_days {
+            Some(days) => vec![doc! {
+                "$set": { "expires_at": { "$add": ["$visited_at", days as i64 * 86_400_000] } }
+            }],
+            None => vec![doc! { "$set": { "expires_at": null } }],
+        };
+        self.get_visits_collection()
+            .update_many(doc! { "wallet_pubkey": wallet }, update, None)
+            .await?;
         Ok(())
     }
 
+    /// Turn aggregate rows written before visit events existed into one synthetic event
+    /// each, dated at the row's last visit, so old history still shows up. Idempotent.
+    pub async fn migrate_legacy_history(&self) -> Result<u64, mongodb::error::Error> {
+        let collection = self.get_history_collection();
+        let events = self.db.collection::<Document>("history_visits");
+        let mut legacy = collection.find(doc! { "events_migrated": { "$ne": true } }, None).await?;
+        let mut migrated = 0;
+        while let Some(row) = legacy.try_next().await? {
+            let event = doc! {
+                "_id": format!("legacy:{}", row.id),
+                "wallet_pubkey": &row.wallet_pubkey,
+                "domain": &row.domain,
+                "program_address": &row.program_address,
+                "path": null,
+                "title": &row.title,
+                "visited_at": mongodb::bson::DateTime::from_millis(row.last_visit.timestamp_millis()),
+                "duration_seconds": row.time_spent_seconds,
+                "expires_at": null,
+                "legacy": true,
+            };
+            let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+            events.update_one(doc! { "_id": event.get_str("_id").unwrap_or_default() }, doc! { "$setOnInsert": event }, options).await?;
+            collection.update_one(doc! { "_id": &row.id }, doc! { "$set": { "events_migrated": true } }, None).await?;
+            migrated += 1;
+        }
+        Ok(migrated)
+    }
+
+    /// Delete visit events and aggregate rows older than each wallet's retention. Wallets that
+    /// never chose one follow the configured maximum, and keep everything when there is none.
+    /// Counts removed events plus removed aggregate rows.
+    pub async fn prune_history(&self, privacy: &PrivacyConfig) -> Result<u64, mongodb::error::Error> {
+        let collection = self.get_history_collection();
+        let visits = self.get_visits_collection();
+        let now = self.clock.now_utc();
+        let cutoff = |days: u32| {
+            mongodb::bson::DateTime::from_millis((now - chrono::Duration::days(days as i64)).timestamp_millis())
+        };
+
+        let mut settings = self.db
+            .collection::<RetentionSetting>("users")
+            .find(doc! { "history_retention_days": { "$ne": null } }, None)
+            .await?;
+        let mut chosen = Vec::new();
+        let mut deleted = 0;
+        while let Some(setting) = settings.try_next().await? {
+            if let Some(days) = privacy.effective_retention_days(setting.history_retention_days) {
+                let filter = doc! { "wallet_pubkey": &setting.wallet_pubkey, "visited_at": { "$lt": cutoff(days) } };
+                deleted += visits.delete_many(filter, None).await?.deleted_count;
+                let filter = doc! { "wallet_pubkey": &setting.wallet_pubkey, "last_visit": { "$lt": cutoff(days) } };
+                deleted += collection.delete_many(filter, None).await?.deleted_count;
+            }
+            chosen.push(setting.wallet_pubkey);
+        }
+
+        if let Some(days) = privacy.effective_retention_days(None) {
+            let filter = doc! { "wallet_pubkey": { "$nin": &chosen }, "visited_at": { "$lt": cutoff(days) } };
+            deleted += visits.delete_many(filter, None).await?.deleted_count;
+            let filter = doc! { "wallet_pubkey": { "$nin": &chosen }, "last_visit": { "$lt": cutoff(days) } };
+            deleted += collection.delete_many(filter, None).await?.deleted_count;
+        }
+        Ok(deleted)
+    }
+
+    /// Prune history on the configured interval until shutdown
+    pub fn spawn_retention(self: Arc<Self>, privacy: PrivacyConfig, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        let interval = Duration::from_secs(privacy.prune_interval_seconds);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.prune_history(&privacy).await {
+                    Ok(0) => {}
+                    Ok(deleted) => tracing::debug!("Chronos pruned {} history entries", deleted),
+                    Err(e) => tracing::warn!("Chronos history pruning failed: {}", e),
+                }
+            }
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn add_bookmark(
         &self,
         wallet: &str,
@@ -145,7 +389,7 @@ impl ChronosManager {
     ) -> Result<(), mongodb::error::Error> {
         let collection = self.get_bookmarks_collection();
         let id = format!("{}:{}", wallet, domain);
-        let now = Utc::now();
+        let now = self.clock.now_utc();
         
         let bookmark = Bookmark {
             id,
@@ -160,9 +404,10 @@ impl ChronosManager {
         };
         
         let filter = doc! { "_id": &bookmark.id };
-        let update = doc! {
-            "$set": mongodb::bson::to_bson(&bookmark).unwrap()
-        };
+        // Stored as a date so it sorts and pages with the other timestamps
+        let mut fields = mongodb::bson::to_document(&bookmark).unwrap();
+        fields.insert("created_at", mongodb::bson::DateTime::from_chrono(now));
+        let update = doc! { "$set": fields };
         let options = mongodb::options::UpdateOptions::builder()
             .upsert(true)
             .build();
@@ -171,29 +416,94 @@ impl ChronosManager {
         Ok(())
     }
 
+    /// A wallet's bookmarks, newest first, optionally in one folder, resuming after `after`
+    /// (created_at ms, id). Returns the page and the cursor for the next one.
     pub async fn get_bookmarks(
         &self,
         wallet: &str,
         folder: Option<&str>,
-    ) -> Result<Vec<Bookmark>, mongodb::error::Error> {
+        limit: i64,
+        after: Option<(i64, String)>,
+    ) -> Result<(Vec<Bookmark>, Option<String>), mongodb::error::Error> {
         let collection = self.get_bookmarks_collection();
         let mut filter = doc! { "wallet_pubkey": wallet };
         if let Some(f) = folder {
             filter.insert("folder", f);
         }
+        if let Some((created_at, id)) = after {
+            filter.extend(keyset_filter("created_at", mongodb::bson::DateTime::from_millis(created_at), &id));
+        }
         
         let options = mongodb::options::FindOptions::builder()
-            .sort(doc! { "created_at": -1 })
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
             .build();
         
-        let mut cursor = collection.find(filter, options).await?;
-        let mut bookmarks = Vec::new();
-        
-        while let Some(bookmark) = cursor.try_next().await? {
-            bookmarks.push(bookmark);
+        let mut bookmarks: Vec<Bookmark> = collection.find(filter, options).await?.try_collect().await?;
+        let next_cursor = next_page_cursor(&mut bookmarks, limit, |bookmark| {
+            encode_cursor(bookmark.created_at.timestamp_millis(), &bookmark.id)
+        });
+        Ok((bookmarks, next_cursor))
+    }
+
+    /// Bookmarks used to store `created_at` as text, which sorts apart from dates and
+    /// never matches a cursor; rewrite those as dates. Idempotent.
+    pub async fn migrate_bookmark_dates(&self) -> Result<u64, mongodb::error::Error> {
+        let collection = self.get_bookmarks_collection();
+        let mut legacy = collection.find(doc! { "created_at": { "$type": "string" } }, None).await?;
+        let mut migrated = 0;
+        while let Some(bookmark) = legacy.try_next().await? {
+            let created_at = mongodb::bson::DateTime::from_chrono(bookmark.created_at);
+            collection.update_one(doc! { "_id": &bookmark.id }, doc! { "$set": { "created_at": created_at } }, None).await?;
+            migrated += 1;
         }
-        
-        Ok(bookmarks)
+        Ok(migrated)
+    }
+
+    /// All of a wallet's bookmarks as a Netscape bookmark file, grouped by folder
+    pub async fn export_bookmarks_html(&self, wallet: &str) -> Result<String, mongodb::error::Error> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1, "_id": 1 })
+            .build();
+        let bookmarks: Vec<Bookmark> = self.get_bookmarks_collection()
+            .find(doc! { "wallet_pubkey": wallet }, options)
+            .await?
+            .try_collect()
+            .await?;
+        Ok(render_bookmarks_html(&bookmarks))
+    }
+
+    /// Add the links in a Netscape bookmark file to a wallet's bookmarks. A domain that is
+    /// already bookmarked, or appears earlier in the file, is left alone. Links without an
+    /// http(s) or shadow:// host are ignored. Returns (imported, skipped_duplicates).
+    pub async fn import_bookmarks_html(&self, wallet: &str, html: &str) -> Result<(usize, usize), mongodb::error::Error> {
+        let collection = self.db.collection::<Document>("bookmarks");
+        let now = self.clock.now_utc();
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        let (mut imported, mut skipped) = (0, 0);
+
+        for entry in parse_bookmarks_html(html) {
+            let id = format!("{}:{}", wallet, entry.domain);
+            let bookmark = doc! {
+                "wallet_pubkey": wallet,
+                "domain": &entry.domain,
+                "program_address": entry.program_address.unwrap_or_default(),
+                "title": entry.title,
+                "description": entry.description,
+                "folder": entry.folder,
+                "created_at": mongodb::bson::DateTime::from_chrono(entry.added_at.unwrap_or(now)),
+                "tags": entry.tags,
+            };
+            let result = collection
+                .update_one(doc! { "_id": &id }, doc! { "$setOnInsert": bookmark }, options.clone())
+                .await?;
+            if result.upserted_id.is_some() {
+                imported += 1;
+            } else {
+                skipped += 1;
+            }
+        }
+        Ok((imported, skipped))
     }
 
     pub async fn remove_bookmark(&self, wallet: &str, domain: &str) -> Result<(), mongodb::error::Error> {
@@ -210,7 +520,7 @@ impl ChronosManager {
     ) -> Result<String, mongodb::error::Error> {
         let collection = self.get_sessions_collection();
         let session_id = uuid::Uuid::new_v4().to_string();
-        let now = Utc::now();
+        let now = self.clock.now_utc();
         
         let session = BrowserSession {
             session_id: session_id.clone(),
@@ -238,7 +548,7 @@ impl ChronosManager {
         session_id: &str,
     ) -> Result<(), mongodb::error::Error> {
         let collection = self.get_sessions_collection();
-        let now = Utc::now();
+        let now = self.clock.now_utc();
         let bson_now = mongodb::bson::DateTime::from_millis(now.timestamp_millis());
         
         let filter = doc! { "_id": session_id };
@@ -260,7 +570,7 @@ impl ChronosManager {
             "wallet_pubkey": wallet,
             "last_activity": {
                 "$gte": mongodb::bson::DateTime::from_millis(
-                    (Utc::now() - chrono::Duration::hours(1)).timestamp_millis()
+                    (self.clock.now_utc() - chrono::Duration::hours(1)).timestamp_millis()
                 )
             }
         };
@@ -274,5 +584,353 @@ impl ChronosManager {
         
         Ok(sessions)
     }
+
+    /// Most recently active session for a wallet, so a reconnecting client can resume it
+    pub async fn latest_session_id(&self, wallet: &str) -> Result<Option<String>, mongodb::error::Error> {
+        let options = mongodb::options::FindOneOptions::builder()
+            .sort(doc! { "last_activity": -1 })
+            .build();
+        let session = self.get_sessions_collection()
+            .find_one(doc! { "wallet_pubkey": wallet }, options)
+            .await?;
+        Ok(session.map(|s| s.session_id))
+    }
+
+    /// Fold sessions fragmented by reconnects into one new session and delete the originals.
+    /// Returns the merged session's id.
+    pub async fn merge_sessions(
+        &self,
+        wallet: &str,
+        session_ids: Vec<String>,
+    ) -> Result<String, ShadowError> {
+        let mut ids: Vec<String> = Vec::new();
+        for id in session_ids {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        if ids.len() < 2 {
+            return Err(ShadowError::BadRequest("At least two distinct sessions are required".to_string()));
+        }
+        if ids.len() > MAX_MERGE_SESSIONS {
+            return Err(ShadowError::BadRequest(format!("At most {} sessions can be merged", MAX_MERGE_SESSIONS)));
+        }
+
+        let collection = self.get_sessions_collection();
+        let sessions: Vec<BrowserSession> = collection
+            .find(doc! { "_id": { "$in": &ids } }, None)
+            .await?
+            .try_collect()
+            .await?;
+
+        for id in &ids {
+            match sessions.iter().find(|s| &s.session_id == id) {
+                None => return Err(ShadowError::NotFound(format!("Session {} not found", id))),
+                Some(session) if session.wallet_pubkey != wallet => {
+                    return Err(ShadowError::Forbidden(format!("Session {} belongs to another wallet", id)));
+                }
+                Some(_) => {}
+            }
+        }
+
+        let merged = merge_session_records(wallet, &sessions, self.clock.now_utc());
+        let merged_doc = doc! {
+            "_id": &merged.session_id,
+            "wallet_pubkey": wallet,
+            "started_at": mongodb::bson::DateTime::from_millis(merged.started_at.timestamp_millis()),
+            "last_activity": mongodb::bson::DateTime::from_millis(merged.last_activity.timestamp_millis()),
+            "active_tabs": &merged.active_tabs,
+            "total_visits": merged.total_visits,
+        };
+        self.db.collection::<mongodb::bson::Document>("browser_sessions")
+            .insert_one(merged_doc, None)
+            .await?;
+
+        collection
+            .delete_many(doc! { "_id": { "$in": &ids }, "wallet_pubkey": wallet }, None)
+            .await?;
+
+        Ok(merged.session_id)
+    }
+}
+
+/// Render bookmarks in the Netscape bookmark file format browsers import and export.
+/// Unfiled bookmarks come first, then one folder per distinct `folder`. Program addresses
+/// ride along in a PROGRAM attribute, which other browsers ignore.
+pub fn render_bookmarks_html(bookmarks: &[Bookmark]) -> String {
+    let mut folders: BTreeMap<Option<&str>, Vec<&Bookmark>> = BTreeMap::new();
+    for bookmark in bookmarks {
+        folders.entry(bookmark.folder.as_deref()).or_default().push(bookmark);
+    }
+
+    let mut html = String::from(concat!(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n",
+        "<!-- This is an automatically generated file.\n",
+        "     It will be read and overwritten.\n",
+        "     DO NOT EDIT! -->\n",
+        "<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n",
+        "<TITLE>Bookmarks</TITLE>\n",
+        "<H1>Bookmarks</H1>\n",
+        "<DL><p>\n",
+    ));
+    for (folder, entries) in folders {
+        let indent = match folder {
+            Some(name) => {
+                let added = entries.iter().map(|b| b.created_at.timestamp()).min().unwrap_or_default();
+                html.push_str(&format!("    <DT><H3 ADD_DATE=\"{}\">{}</H3>\n    <DL><p>\n", added, html_escape(name)));
+                "        "
+            }
+            None => "    ",
+        };
+        for bookmark in entries {
+            html.push_str(&format!(
+                "{}<DT><A HREF=\"{}\" ADD_DATE=\"{}\"",
+                indent,
+                html_escape(&bookmark_href(&bookmark.domain)),
+                bookmark.created_at.timestamp()
+            ));
+            if !bookmark.program_address.is_empty() {
+                html.push_str(&format!(" PROGRAM=\"{}\"", html_escape(&bookmark.program_address)));
+            }
+            if !bookmark.tags.is_empty() {
+                html.push_str(&format!(" TAGS=\"{}\"", html_escape(&bookmark.tags.join(","))));
+            }
+            let title = bookmark.title.as_deref().unwrap_or(&bookmark.domain);
+            html.push_str(&format!(">{}</A>\n", html_escape(title)));
+            if let Some(description) = bookmark.description.as_deref().filter(|d| !d.is_empty()) {
+                html.push_str(&format!("{}<DD>{}\n", indent, html_escape(description)));
+            }
+        }
+        if folder.is_some() {
+            html.push_str("    </DL><p>\n");
+        }
+    }
+    html.push_str("</DL><p>\n");
+    html
+}
+
+/// Links in a Netscape bookmark file, in document order. Links without an http(s) or
+/// shadow:// host, such as javascript: bookmarklets and place: queries, are dropped.
+pub fn parse_bookmarks_html(html: &str) -> Vec<ImportedBookmark> {
+    let document = Html::parse_document(html);
+    let links = Selector::parse("a[href]").expect("valid bookmark selector");
+    document
+        .select(&links)
+        .filter_map(|link| {
+            let attr = |name: &str| link.value().attr(name).map(str::trim).filter(|v| !v.is_empty());
+            let domain = bookmark_domain(attr("href")?)?;
+            let title = link.text().collect::<String>().trim().to_string();
+            Some(ImportedBookmark {
+                domain,
+                program_address: attr("program").map(str::to_string),
+                title: (!title.is_empty()).then_some(title),
+                description: bookmark_description(link),
+                folder: bookmark_folder(link),
+                added_at: attr("add_date")
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+                tags: attr("tags")
+                    .map(|t| t.split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Where an exported bookmark points: shadow:// for .shadow domains, https:// for the rest
+fn bookmark_href(domain: &str) -> String {
+    if domain.ends_with(".shadow") {
+        format!("shadow://{}", domain)
+    } else {
+        format!("https://{}", domain)
+    }
 }
 
+/// The domain a bookmarked URL is stored under. shadow://name means name.shadow.
+fn bookmark_domain(href: &str) -> Option<String> {
+    let url = reqwest::Url::parse(href).ok()?;
+    let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    if host.is_empty() {
+        return None;
+    }
+    match url.scheme() {
+        "http" | "https" => Some(host),
+        "shadow" if host.ends_with(".shadow") => Some(host),
+        "shadow" => Some(format!("{}.shadow", host)),
+        _ => None,
+    }
+}
+
+/// Text of the <DD> that follows a link's <DT>, where browsers keep descriptions
+fn bookmark_description(link: ElementRef) -> Option<String> {
+    let dt = link.parent().and_then(ElementRef::wrap)?;
+    let dd = dt.next_siblings().find_map(ElementRef::wrap).filter(|e| e.value().name() == "dd")?;
+    // Only the DD's own text; a folder's DD can swallow the folder's list
+    let text: String = dd.children().filter_map(|c| c.value().as_text()).map(|t| &**t).collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Names of the folders around a link, outermost first, joined with '/'. Each folder is an
+/// <H3> followed by its <DL>, which may sit inside the folder's <DD> description.
+fn bookmark_folder(link: ElementRef) -> Option<String> {
+    fn heading(element: ElementRef<'_>) -> Option<ElementRef<'_>> {
+        let previous = element.prev_siblings().find_map(ElementRef::wrap)?;
+        match previous.value().name() {
+            "h3" => Some(previous),
+            // A <DD> closes the folder's <DT>, leaving the heading inside it
+            "dt" => previous.children().filter_map(ElementRef::wrap).find(|e| e.value().name() == "h3"),
+            _ => None,
+        }
+    }
+    let mut names: Vec<String> = link
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .filter(|e| e.value().name() == "dl")
+        .filter_map(|dl| {
+            heading(dl).or_else(|| {
+                let parent = dl.parent().and_then(ElementRef::wrap)?;
+                (parent.value().name() == "dd").then(|| heading(parent)).flatten()
+            })
+        })
+        .map(|h3| h3.text().collect::<String>().trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.reverse();
+    (!names.is_empty()).then(|| names.join("/"))
+}
+
+/// Combine sessions: earliest start, latest activity, summed visits, and the union of tabs
+/// in first-seen order. The result gets a fresh id; `now` stands in for the times when there are no sessions.
+pub fn merge_session_records(wallet: &str, sessions: &[BrowserSession], now: DateTime<Utc>) -> BrowserSession {
+    let mut active_tabs: Vec<String> = Vec::new();
+    for tab in sessions.iter().flat_map(|s| s.active_tabs.iter()) {
+        if !active_tabs.contains(tab) {
+            active_tabs.push(tab.clone());
+        }
+    }
+
+    BrowserSession {
+        session_id: uuid::Uuid::new_v4().to_string(),
+        wallet_pubkey: wallet.to_string(),
+        started_at: sessions.iter().map(|s| s.started_at).min().unwrap_or(now),
+        last_activity: sessions.iter().map(|s| s.last_activity).max().unwrap_or(now),
+        active_tabs,
+        total_visits: sessions.iter().map(|s| s.total_visits).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, minutes_ago: i64, tabs: &[&str], visits: i32) -> BrowserSession {
+        let at = Utc::now() - chrono::Duration::minutes(minutes_ago);
+        BrowserSession {
+            session_id: id.to_string(),
+            wallet_pubkey: "wallet".to_string(),
+            started_at: at,
+            last_activity: at + chrono::Duration::minutes(5),
+            active_tabs: tabs.iter().map(|t| t.to_string()).collect(),
+            total_visits: visits,
+        }
+    }
+
+    #[test]
+    fn test_merge_session_records() {
+        let older = session("a", 60, &["app.shadow", "swap.shadow"], 4);
+        let newer = session("b", 10, &["swap.shadow", "nft.shadow"], 3);
+
+        let merged = merge_session_records("wallet", &[newer.clone(), older.clone()], Utc::now());
+        assert_eq!(merged.started_at, older.started_at);
+        assert_eq!(merged.last_activity, newer.last_activity);
+        assert_eq!(merged.total_visits, 7);
+        assert_eq!(merged.active_tabs, vec!["swap.shadow", "nft.shadow", "app.shadow"]);
+        assert_ne!(merged.session_id, "a");
+        assert_ne!(merged.session_id, "b");
+    }
+
+    fn bookmark(domain: &str, folder: Option<&str>, tags: &[&str]) -> Bookmark {
+        Bookmark {
+            id: format!("wallet:{}", domain),
+            wallet_pubkey: "wallet".to_string(),
+            domain: domain.to_string(),
+            program_address: format!("{}-program", domain),
+            title: Some(format!("<{}> & co", domain)),
+            description: None,
+            folder: folder.map(str::to_string),
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_bookmarks_html_round_trip() {
+        let mut described = bookmark("docs.shadow", Some("Work"), &["ref", "docs"]);
+        described.description = Some("Manuals \"and\" guides".to_string());
+        let bookmarks = vec![
+            bookmark("swap.shadow", None, &[]),
+            described,
+            bookmark("example.com", Some("Work"), &[]),
+        ];
+
+        let html = render_bookmarks_html(&bookmarks);
+        assert!(html.starts_with("<!DOCTYPE NETSCAPE-Bookmark-file-1>"));
+        assert!(html.contains(r#"<DT><H3 ADD_DATE="1700000000">Work</H3>"#));
+        assert!(html.contains(r#"HREF="https://example.com""#));
+
+        let parsed = parse_bookmarks_html(&html);
+        assert_eq!(parsed.len(), 3);
+        for (original, imported) in bookmarks.iter().zip(&parsed) {
+            assert_eq!(imported.domain, original.domain);
+            assert_eq!(imported.program_address.as_ref(), Some(&original.program_address));
+            assert_eq!(imported.title, original.title);
+            assert_eq!(imported.description, original.description);
+            assert_eq!(imported.folder, original.folder);
+            assert_eq!(imported.added_at, Some(original.created_at));
+            assert_eq!(imported.tags, original.tags);
+        }
+    }
+
+    #[test]
+    fn test_parse_browser_bookmark_export() {
+        // Shaped like a Firefox export: nested folders, a folder description, a bookmarklet
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+<DL><p>
+    <DT><A HREF="https://www.rust-lang.org/learn" ADD_DATE="1600000000" LAST_MODIFIED="1600000001">Learn Rust</A>
+    <DT><H3 ADD_DATE="1600000000" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks Toolbar</H3>
+    <DD>Add bookmarks to this folder to see them displayed on the Bookmarks Toolbar
+    <DL><p>
+        <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+        <DT><H3>Solana</H3>
+        <DL><p>
+            <DT><a href="shadow://swap" add_date="not a date" tags="defi, dex,">Swap</a>
+            <DD>Token swaps
+            <DT><A HREF="place:sort=8&maxResults=10">Recent Tags</A>
+        </DL><p>
+    </DL><p>
+    <DT><A HREF="HTTPS://Example.COM./page">   </A>
+</DL><p>"#;
+
+        let parsed = parse_bookmarks_html(html);
+        let domains: Vec<_> = parsed.iter().map(|b| b.domain.as_str()).collect();
+        assert_eq!(domains, vec!["www.rust-lang.org", "swap.shadow", "example.com"]);
+
+        assert_eq!(parsed[0].folder, None);
+        assert_eq!(parsed[0].added_at, DateTime::from_timestamp(1_600_000_000, 0));
+        assert_eq!(parsed[0].program_address, None);
+
+        let swap = &parsed[1];
+        assert_eq!(swap.folder.as_deref(), Some("Bookmarks Toolbar/Solana"));
+        assert_eq!(swap.title.as_deref(), Some("Swap"));
+        assert_eq!(swap.description.as_deref(), Some("Token swaps"));
+        assert_eq!(swap.tags, vec!["defi", "dex"]);
+        assert_eq!(swap.added_at, None);
+
+        assert_eq!(parsed[2].title, None);
+        assert_eq!(parsed[2].description, None);
+    }
+}
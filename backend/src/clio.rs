@@ -0,0 +1,372 @@
+// Clio - Muse of history
+// Records what people search for, off the request path, so the index can grow where it is thin
+
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::SearchAnalyticsConfig;
+use crate::metrics::MetricsCollector;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use hmac::{Hmac, Mac};
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Longest normalized query kept
+const MAX_QUERY_CHARS: usize = 200;
+/// Stored in place of queries that are a wallet or program address
+pub const ADDRESS_PLACEHOLDER: &str = "<address>";
+/// Bytes of HMAC kept in query ids and client hashes
+const TAG_BYTES: usize = 16;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchQueryRecord {
+    #[serde(rename = "_id")]
+    pub id: String,
+    /// Lowercased, whitespace-collapsed query text
+    pub query: String,
+    pub result_count: i64,
+    pub latency_ms: i64,
+    /// Keyed hash of the client address, never the address itself
+    #[serde(default)]
+    pub client_hash: Option<String>,
+    #[serde(default)]
+    pub clicked: bool,
+    #[serde(default)]
+    pub clicked_domain: Option<String>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
+    pub clicked_at: Option<DateTime<Utc>>,
+}
+
+enum SearchEvent {
+    Query(SearchQueryRecord),
+    Click { query_id: String, domain: String, at: DateTime<Utc> },
+}
+
+/// How far back the admin aggregations look
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchWindow {
+    #[default]
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+    #[serde(rename = "30d")]
+    Month,
+}
+
+impl SearchWindow {
+    pub fn duration(self) -> chrono::Duration {
+        match self {
+            SearchWindow::Day => chrono::Duration::hours(24),
+            SearchWindow::Week => chrono::Duration::days(7),
+            SearchWindow::Month => chrono::Duration::days(30),
+        }
+    }
+}
+
+/// One normalized query aggregated over a window
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueryStats {
+    pub query: String,
+    pub searches: i64,
+    pub zero_results: i64,
+    pub clicks: i64,
+    pub avg_latency_ms: f64,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub last_searched: DateTime<Utc>,
+}
+
+/// Lowercase and collapse whitespace; addresses are replaced so no wallet is ever stored
+pub fn normalize_query(query: &str) -> String {
+    let trimmed = query.trim();
+    if Pubkey::from_str(trimmed).is_ok() {
+        return ADDRESS_PLACEHOLDER.to_string();
+    }
+    trimmed
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .take(MAX_QUERY_CHARS)
+        .collect()
+}
+
+/// Buffered writer for search analytics. Recording only enqueues, so a slow or full
+/// writer drops analytics instead of slowing down searches.
+pub struct ClioRecorder {
+    db: Database,
+    config: SearchAnalyticsConfig,
+    key: Vec<u8>,
+    sender: mpsc::Sender<SearchEvent>,
+    receiver: Mutex<mpsc::Receiver<SearchEvent>>,
+    metrics: Option<Arc<MetricsCollector>>,
+    clock: SharedClock,
+}
+
+impl ClioRecorder {
+    pub fn new(db: Database, config: SearchAnalyticsConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.buffer_capacity.max(1));
+        let key = match &config.hash_key {
+            Some(key) => key.as_bytes().to_vec(),
+            None => {
+                let mut key = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+                key
+            }
+        };
+
+        Self {
+            db,
+            config,
+            key,
+            sender,
+            receiver: Mutex::new(receiver),
+            metrics: None,
+            clock: SystemClock::shared(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Count searches, zero-result searches and dropped records in the backend metrics
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn get_collection(&self) -> Collection<SearchQueryRecord> {
+        self.db.collection::<SearchQueryRecord>("search_queries")
+    }
+
+    fn tag(&self, value: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(value.as_bytes());
+        hex::encode(&mac.finalize().into_bytes()[..TAG_BYTES])
+    }
+
+    /// Queue one search and return the signed query id the click beacon reports back
+    pub fn record(&self, query: &str, result_count: usize, latency: Duration, client: Option<&str>) -> String {
+        let id = crate::utils::generate_id();
+        let record = SearchQueryRecord {
+            id: id.clone(),
+            query: normalize_query(query),
+            result_count: result_count as i64,
+            latency_ms: latency.as_millis() as i64,
+            client_hash: client.map(|client| self.tag(&format!("client:{}", client))),
+            clicked: false,
+            clicked_domain: None,
+            created_at: self.clock.now_utc(),
+            clicked_at: None,
+        };
+        if let Some(metrics) = &self.metrics {
+            metrics.record_search(result_count == 0);
+        }
+        self.enqueue(SearchEvent::Query(record));
+
+        format!("{}.{}", id, self.tag(&id))
+    }
+
+    /// Queue a click on a result of an earlier search. Only ids this server signed are accepted.
+    pub fn record_click(&self, query_id: &str, domain: &str) -> Result<(), String> {
+        let id = self.verify_query_id(query_id).ok_or("Invalid query id")?;
+        self.enqueue(SearchEvent::Click {
+            query_id: id.to_string(),
+            domain: domain.to_string(),
+            at: self.clock.now_utc(),
+        });
+        Ok(())
+    }
+
+    fn verify_query_id<'a>(&self, query_id: &'a str) -> Option<&'a str> {
+        let (id, tag) = query_id.split_once('.')?;
+        uuid::Uuid::parse_str(id).ok()?;
+        let tag = hex::decode(tag).ok().filter(|tag| tag.len() == TAG_BYTES)?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(id.as_bytes());
+        mac.verify_truncated_left(&tag).ok()?;
+        Some(id)
+    }
+
+    fn enqueue(&self, event: SearchEvent) {
+        if self.sender.try_send(event).is_err() {
+            if let Some(metrics) = &self.metrics {
+                metrics.record_search_analytics_dropped();
+            }
+            tracing::debug!("Search analytics buffer full; dropping record");
+        }
+    }
+
+    /// Write everything queued so far, in order, so a click always lands after its search.
+    /// Returns how many events were written.
+    pub async fn flush(&self) -> Result<usize, String> {
+        let mut receiver = self.receiver.lock().await;
+        let batch_size = self.config.flush_batch_size.max(1);
+        let mut written = 0;
+        loop {
+            let mut batch = Vec::with_capacity(batch_size);
+            while batch.len() < batch_size {
+                match receiver.try_recv() {
+                    Ok(event) => batch.push(event),
+                    Err(_) => break,
+                }
+            }
+            if batch.is_empty() {
+                return Ok(written);
+            }
+            written += batch.len();
+            self.write_batch(batch).await?;
+        }
+    }
+
+    async fn write_batch(&self, batch: Vec<SearchEvent>) -> Result<(), String> {
+        let mut queries = Vec::new();
+        let mut clicks = Vec::new();
+        for event in batch {
+            match event {
+                SearchEvent::Query(record) => queries.push(record),
+                SearchEvent::Click { query_id, domain, at } => clicks.push((query_id, domain, at)),
+            }
+        }
+
+        if !queries.is_empty() {
+            let options = mongodb::options::InsertManyOptions::builder().ordered(false).build();
+            let docs: Vec<Document> = queries
+                .iter()
+                .map(|record| doc! {
+                    "_id": &record.id,
+                    "query": &record.query,
+                    "result_count": record.result_count,
+                    "latency_ms": record.latency_ms,
+                    "client_hash": &record.client_hash,
+                    "clicked": false,
+                    "created_at": mongodb::bson::DateTime::from_chrono(record.created_at),
+                })
+                .collect();
+            self.db.collection::<Document>("search_queries").insert_many(docs, options).await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+
+        // Only the first click on a search counts
+        for (query_id, domain, at) in clicks {
+            self.db.collection::<Document>("search_queries")
+                .update_one(
+                    doc! { "_id": &query_id, "clicked": false },
+                    doc! { "$set": {
+                        "clicked": true,
+                        "clicked_domain": domain,
+                        "clicked_at": mongodb::bson::DateTime::from_chrono(at),
+                    } },
+                    None,
+                )
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Most searched queries in the window
+    pub async fn top_queries(&self, window: SearchWindow, limit: i64) -> Result<Vec<QueryStats>, String> {
+        self.query_stats(window, limit, doc! {}).await
+    }
+
+    /// Most searched queries in the window that found nothing
+    pub async fn zero_result_queries(&self, window: SearchWindow, limit: i64) -> Result<Vec<QueryStats>, String> {
+        self.query_stats(window, limit, doc! { "result_count": 0 }).await
+    }
+
+    async fn query_stats(&self, window: SearchWindow, limit: i64, mut filter: Document) -> Result<Vec<QueryStats>, String> {
+        let since = self.clock.now_utc() - window.duration();
+        filter.insert("created_at", doc! { "$gte": mongodb::bson::DateTime::from_chrono(since) });
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$group": {
+                "_id": "$query",
+                "searches": { "$sum": 1 },
+                "zero_results": { "$sum": { "$cond": [{ "$eq": ["$result_count", 0] }, 1, 0] } },
+                "clicks": { "$sum": { "$cond": ["$clicked", 1, 0] } },
+                "avg_latency_ms": { "$avg": "$latency_ms" },
+                "last_searched": { "$max": "$created_at" },
+            } },
+            doc! { "$sort": { "searches": -1, "_id": 1 } },
+            doc! { "$limit": limit },
+            doc! { "$project": {
+                "_id": 0,
+                "query": "$_id",
+                "searches": { "$toLong": "$searches" },
+                "zero_results": { "$toLong": "$zero_results" },
+                "clicks": { "$toLong": "$clicks" },
+                "avg_latency_ms": { "$toDouble": "$avg_latency_ms" },
+                "last_searched": 1,
+            } },
+        ];
+
+        let cursor = self.get_collection().aggregate(pipeline, None).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let docs: Vec<Document> = cursor.try_collect().await
+            .map_err(|e| format!("Database error: {}", e))?;
+        docs.into_iter()
+            .map(|doc| mongodb::bson::from_document(doc).map_err(|e| format!("Invalid query stats: {}", e)))
+            .collect()
+    }
+
+    /// TTL index that also serves the window filter
+    pub async fn ensure_indexes(&self) -> Result<(), String> {
+        let index = mongodb::IndexModel::builder()
+            .keys(doc! { "created_at": 1 })
+            .options(
+                mongodb::options::IndexOptions::builder()
+                    .expire_after(Duration::from_secs(self.config.retention_days * 86_400))
+                    .build(),
+            )
+            .build();
+        self.get_collection().create_index(index, None).await
+            .map(|_| ())
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Drain the buffer every flush interval, and once more on shutdown
+    pub fn spawn(self: Arc<Self>, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        let interval = Duration::from_millis(self.config.flush_interval_ms.max(1));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                if let Err(e) = self.flush().await {
+                    tracing::warn!("Search analytics flush failed: {}", e);
+                }
+            }
+            if let Err(e) = self.flush().await {
+                tracing::warn!("Final search analytics flush failed: {}", e);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(normalize_query("  Solana   NFT\tMarket "), "solana nft market");
+        assert_eq!(normalize_query(&Pubkey::new_unique().to_string()), ADDRESS_PLACEHOLDER);
+        assert_eq!(normalize_query(&"a".repeat(500)).len(), MAX_QUERY_CHARS);
+    }
+}
@@ -0,0 +1,94 @@
+// Clock - Source of the current time
+// Time-dependent logic asks a Clock instead of calling Utc::now()/Instant::now() directly,
+// so tests can move time forward instead of sleeping
+
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    fn now_utc(&self) -> DateTime<Utc>;
+    fn now_instant(&self) -> Instant;
+}
+
+/// Shared handle held by the components that read the time
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The real wall and monotonic clocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to. Both readings advance together.
+#[derive(Debug)]
+pub struct TestClock {
+    start_utc: DateTime<Utc>,
+    start_instant: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl TestClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            start_utc: start,
+            start_instant: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// A test clock starting at the current wall time, for tests that mix it with real timestamps
+    pub fn starting_now() -> Arc<Self> {
+        Arc::new(Self::new(Utc::now()))
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for TestClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.start_utc + chrono::Duration::from_std(self.elapsed()).expect("test clock advanced too far")
+    }
+
+    fn now_instant(&self) -> Instant {
+        self.start_instant + self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_clock_moves_only_when_advanced() {
+        let start = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let clock = TestClock::new(start);
+        let instant = clock.now_instant();
+        assert_eq!(clock.now_utc(), start);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now_utc(), start + chrono::Duration::seconds(90));
+        assert_eq!(clock.now_instant() - instant, Duration::from_secs(90));
+    }
+}
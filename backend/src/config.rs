@@ -2,6 +2,8 @@
 use serde::{Deserialize, Serialize};
 use std::env;
 use std::time::Duration;
+use crate::argus::{self, KnownDAppSeed};
+use crate::hephaestus;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
@@ -32,15 +34,194 @@ pub struct CacheConfig {
     pub max_size_mb: usize,
     pub default_ttl_seconds: u64,
     pub cleanup_interval_seconds: u64,
+    /// Objects larger than this are streamed straight through instead of cached
+    pub max_object_bytes: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RateLimitConfig {
+    /// Requests allowed per window; the window is a minute unless overridden
     pub requests_per_minute: u32,
+    pub window_seconds: u64,
     pub burst_size: Option<u32>,
     pub enabled: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub jwt_secret: Option<String>,
+    pub token_ttl_seconds: u64,
+    pub admin_wallets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReputationConfig {
+    pub known_dapps: Vec<KnownDAppSeed>,
+    /// How often admin-managed entries are reloaded, so blocks made on another instance arrive
+    pub refresh_interval_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyticsConfig {
+    pub summary_interval_seconds: u64,
+    /// Days of per-day visit and performance records kept
+    pub retention_days: u32,
+    /// Requests an export counts as against the rate limit
+    pub export_rate_limit_cost: u32,
+    /// How often recently visited domains get their daily stats re-aggregated
+    pub daily_stats_interval_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalizationConfig {
+    pub default_language: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationConfig {
+    pub interval_seconds: u64,
+    pub batch_size: i64,
+    pub rpc_budget: u32,
+    pub interstitial_after_failures: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeaturesConfig {
+    pub graphql_enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxConfig {
+    pub poll_interval_ms: u64,
+    pub batch_size: u32,
+    pub max_attempts: u32,
+    pub lease_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployConfig {
+    pub ipfs_gateway_url: String,
+    pub arweave_gateway_url: String,
+    /// Share of bundle files that must resolve before a staged deploy goes live
+    pub min_success_rate: f64,
+    pub html_check: bool,
+    pub request_timeout_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsConfig {
+    /// Cluster the RPC endpoint must be on (mainnet-beta, devnet, testnet or localnet);
+    /// inferred from the RPC URL when unset
+    pub solana_network: Option<String>,
+    /// Pin and unpin a test object on Pinata during checks
+    pub storage_probe: bool,
+    /// Webhook, renderer and other external URLs that must be reachable
+    pub probe_urls: Vec<String>,
+    pub check_timeout_seconds: u64,
+}
+
+/// Atlas tracking of Bundlr/Arweave uploads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArweaveConfig {
+    pub status_poll_interval_seconds: u64,
+    /// Uploads unconfirmed for this long are flagged stalled and re-uploaded
+    pub stall_timeout_seconds: u64,
+    /// Total uploads of the same data, counting the original
+    pub max_upload_attempts: u32,
+}
+
+/// Clio recording of Athena searches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchAnalyticsConfig {
+    /// Searches queued for the writer before new ones are dropped
+    pub buffer_capacity: usize,
+    pub flush_interval_ms: u64,
+    pub flush_batch_size: usize,
+    pub retention_days: u64,
+    /// Key for hashing client addresses and signing query ids; an ephemeral one is generated when unset
+    #[serde(skip_serializing)]
+    pub hash_key: Option<String>,
+}
+
+/// Bounds on how long per-wallet browsing history is kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyConfig {
+    /// Shortest retention a wallet may choose
+    pub min_history_retention_days: u32,
+    /// Longest history is kept for anyone; unlimited when unset
+    pub max_history_retention_days: Option<u32>,
+    pub prune_interval_seconds: u64,
+}
+
+impl PrivacyConfig {
+    /// Retention that applies to a wallet given its own setting, `None` meaning keep forever
+    pub fn effective_retention_days(&self, requested: Option<u32>) -> Option<u32> {
+        match (requested, self.max_history_retention_days) {
+            (Some(days), Some(max)) => Some(days.max(self.min_history_retention_days).min(max)),
+            (Some(days), None) => Some(days.max(self.min_history_retention_days)),
+            (None, max) => max,
+        }
+    }
+}
+
+/// Legacy web -> Shadow redirects served by Charon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    /// Where /r sends visitors; `{domain}` is replaced with the .shadow domain
+    pub resolve_url: String,
+    pub assertion_ttl_seconds: u64,
+    /// Keypair that signs mapping assertions; an ephemeral one is generated when unset
+    #[serde(skip_serializing)]
+    pub signing_key: Option<String>,
+    /// DNS-over-HTTPS JSON endpoint used to check bridge TXT records
+    pub doh_url: String,
+}
+
+/// SPL tokens minted for converted links
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkTokenConfig {
+    /// Funded keypair that pays for link token mints and holds their mint authority;
+    /// minting is refused when unset
+    #[serde(skip_serializing)]
+    pub mint_authority_key: Option<String>,
+    /// Rate limit units charged per link conversion, since a new link pays for a mint
+    pub convert_rate_limit_cost: u32,
+    /// New link tokens a wallet may mint in a day; converting a known link doesn't count
+    pub daily_mints_per_wallet: u64,
+}
+
+/// Enodia signals for the outbound-link interstitial
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkInfoConfig {
+    /// Hosts flagged on the interstitial; subdomains are covered too
+    pub blocklist: Vec<String>,
+    /// Hosts known to be safe; the blocklist wins when both match
+    pub allowlist: Vec<String>,
+    pub cache_ttl_seconds: u64,
+    /// Rate limit units charged per lookup
+    pub rate_limit_cost: u32,
+}
+
+/// Poseidon pre-flight checks on transaction submission
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionConfig {
+    /// Balance the fee payer should keep after a transaction, enough for rent exemption and
+    /// a few more fees. Users can change it in their security settings.
+    pub min_reserve_lamports: u64,
+    /// How long a broadcast transaction may take to confirm before it is marked failed
+    pub broadcast_timeout_seconds: u64,
+    /// First wait between confirmation checks; doubles after each check
+    pub broadcast_backoff_ms: u64,
+}
+
+/// Helios domain watchlists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchlistConfig {
+    /// Domains one wallet may watch at once
+    pub max_per_wallet: u64,
+    pub scan_interval_seconds: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
@@ -57,85 +238,337 @@ pub struct ShadowConfig {
     pub storage: StorageConfig,
     pub cache: CacheConfig,
     pub rate_limit: RateLimitConfig,
+    pub auth: AuthConfig,
+    pub reputation: ReputationConfig,
+    pub analytics: AnalyticsConfig,
+    pub verification: VerificationConfig,
+    pub localization: LocalizationConfig,
+    pub outbox: OutboxConfig,
+    pub deploy: DeployConfig,
+    pub arweave: ArweaveConfig,
+    pub search_analytics: SearchAnalyticsConfig,
+    pub privacy: PrivacyConfig,
+    pub diagnostics: DiagnosticsConfig,
+    pub bridge: BridgeConfig,
+    pub link_info: LinkInfoConfig,
+    pub link_tokens: LinkTokenConfig,
+    pub transactions: TransactionConfig,
+    pub watchlist: WatchlistConfig,
+    pub features: FeaturesConfig,
     pub server: ServerConfig,
 }
 
 impl ShadowConfig {
     pub fn from_env() -> Result<Self, String> {
+        Self::from_vars(|key| env::var(key))
+    }
+
+    /// Config from `var` rather than the process environment, so tests can supply
+    /// settings without `env::set_var` racing other threads
+    pub fn from_vars(var: impl Fn(&str) -> Result<String, env::VarError>) -> Result<Self, String> {
         Ok(ShadowConfig {
             database: DatabaseConfig {
-                url: env::var("DATABASE_URL")
+                url: var("DATABASE_URL")
                     .map_err(|_| "DATABASE_URL not set")?,
-                database_name: env::var("DATABASE_NAME")
+                database_name: var("DATABASE_NAME")
                     .unwrap_or_else(|_| "shadow".to_string()),
-                max_pool_size: env::var("DATABASE_MAX_POOL_SIZE")
+                max_pool_size: var("DATABASE_MAX_POOL_SIZE")
                     .ok()
                     .and_then(|s| s.parse().ok()),
-                min_pool_size: env::var("DATABASE_MIN_POOL_SIZE")
+                min_pool_size: var("DATABASE_MIN_POOL_SIZE")
                     .ok()
                     .and_then(|s| s.parse().ok()),
             },
             solana: SolanaConfig {
-                rpc_url: env::var("SOLANA_RPC_URL")
+                rpc_url: var("SOLANA_RPC_URL")
                     .unwrap_or_else(|_| "https://api.devnet.solana.com".to_string()),
-                ws_url: env::var("SOLANA_WS_URL")
+                ws_url: var("SOLANA_WS_URL")
                     .unwrap_or_else(|_| "wss://api.devnet.solana.com".to_string()),
-                commitment: env::var("SOLANA_COMMITMENT")
+                commitment: var("SOLANA_COMMITMENT")
                     .unwrap_or_else(|_| "confirmed".to_string()),
-                timeout_seconds: env::var("SOLANA_TIMEOUT_SECONDS")
+                timeout_seconds: var("SOLANA_TIMEOUT_SECONDS")
                     .ok()
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(30),
             },
             storage: StorageConfig {
-                pinata_api_key: env::var("PINATA_API_KEY").ok(),
-                pinata_secret_key: env::var("PINATA_SECRET_KEY").ok(),
-                bundlr_node_url: env::var("BUNDLR_NODE_URL").ok(),
-                bundlr_currency: env::var("BUNDLR_CURRENCY")
+                pinata_api_key: var("PINATA_API_KEY").ok(),
+                pinata_secret_key: var("PINATA_SECRET_KEY").ok(),
+                bundlr_node_url: var("BUNDLR_NODE_URL").ok(),
+                bundlr_currency: var("BUNDLR_CURRENCY")
                     .ok()
                     .or_else(|| Some("solana".to_string())),
             },
             cache: CacheConfig {
-                max_size_mb: env::var("CACHE_MAX_SIZE_MB")
+                max_size_mb: var("CACHE_MAX_SIZE_MB")
                     .ok()
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(512),
-                default_ttl_seconds: env::var("CACHE_DEFAULT_TTL_SECONDS")
+                default_ttl_seconds: var("CACHE_DEFAULT_TTL_SECONDS")
                     .ok()
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(3600),
-                cleanup_interval_seconds: env::var("CACHE_CLEANUP_INTERVAL_SECONDS")
+                cleanup_interval_seconds: var("CACHE_CLEANUP_INTERVAL_SECONDS")
                     .ok()
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(300),
+                max_object_bytes: var("CACHE_MAX_OBJECT_BYTES")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(hephaestus::DEFAULT_MAX_OBJECT_BYTES),
             },
             rate_limit: RateLimitConfig {
-                requests_per_minute: env::var("RATE_LIMIT_RPM")
+                requests_per_minute: var("RATE_LIMIT_RPM")
                     .ok()
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(60),
-                burst_size: env::var("RATE_LIMIT_BURST")
+                window_seconds: var("RATE_LIMIT_WINDOW_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(60),
+                burst_size: var("RATE_LIMIT_BURST")
                     .ok()
                     .and_then(|s| s.parse().ok()),
-                enabled: env::var("RATE_LIMIT_ENABLED")
+                enabled: var("RATE_LIMIT_ENABLED")
                     .ok()
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(true),
             },
+            auth: AuthConfig {
+                jwt_secret: var("JWT_SECRET").ok(),
+                token_ttl_seconds: var("JWT_TTL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3600),
+                admin_wallets: var("ADMIN_WALLETS")
+                    .map(|s| parse_list(&s))
+                    .unwrap_or_default(),
+            },
+            reputation: ReputationConfig {
+                known_dapps: reputation_seeds(
+                    var("KNOWN_DAPPS").ok().as_deref(),
+                    var("BLOCKED_ORIGINS").ok().as_deref(),
+                ),
+                refresh_interval_seconds: var("KNOWN_DAPPS_REFRESH_INTERVAL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(60),
+            },
+            analytics: AnalyticsConfig {
+                summary_interval_seconds: var("ANALYTICS_SUMMARY_INTERVAL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(60),
+                retention_days: var("ANALYTICS_RETENTION_DAYS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(365),
+                export_rate_limit_cost: var("ANALYTICS_EXPORT_RATE_LIMIT_COST")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(10),
+                daily_stats_interval_seconds: var("ANALYTICS_DAILY_STATS_INTERVAL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3600),
+            },
+            verification: VerificationConfig {
+                interval_seconds: var("DOMAIN_REVERIFY_INTERVAL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3600),
+                batch_size: var("DOMAIN_REVERIFY_BATCH_SIZE")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(50),
+                rpc_budget: var("DOMAIN_REVERIFY_RPC_BUDGET")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(500),
+                interstitial_after_failures: var("DOMAIN_REVERIFY_INTERSTITIAL_AFTER")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3),
+            },
+            localization: LocalizationConfig {
+                default_language: var("DEFAULT_CONTENT_LANGUAGE")
+                    .unwrap_or_else(|_| "en".to_string()),
+            },
+            outbox: OutboxConfig {
+                poll_interval_ms: var("OUTBOX_POLL_INTERVAL_MS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(1000),
+                batch_size: var("OUTBOX_BATCH_SIZE")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(100),
+                max_attempts: var("OUTBOX_MAX_ATTEMPTS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(5),
+                lease_seconds: var("OUTBOX_LEASE_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(60),
+            },
+            deploy: DeployConfig {
+                ipfs_gateway_url: var("DEPLOY_IPFS_GATEWAY_URL")
+                    .unwrap_or_else(|_| "https://gateway.pinata.cloud/ipfs".to_string()),
+                arweave_gateway_url: var("DEPLOY_ARWEAVE_GATEWAY_URL")
+                    .unwrap_or_else(|_| "https://arweave.net".to_string()),
+                min_success_rate: var("DEPLOY_MIN_SUCCESS_RATE")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0.95),
+                html_check: var("DEPLOY_HTML_CHECK")
+                    .map(|v| v == "true")
+                    .unwrap_or(false),
+                request_timeout_seconds: var("DEPLOY_CHECK_TIMEOUT_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(10),
+            },
+            arweave: ArweaveConfig {
+                status_poll_interval_seconds: var("ARWEAVE_STATUS_POLL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(120),
+                stall_timeout_seconds: var("ARWEAVE_STALL_TIMEOUT_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3 * 3600),
+                max_upload_attempts: var("ARWEAVE_MAX_UPLOAD_ATTEMPTS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3),
+            },
+            search_analytics: SearchAnalyticsConfig {
+                buffer_capacity: var("SEARCH_ANALYTICS_BUFFER")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(1024),
+                flush_interval_ms: var("SEARCH_ANALYTICS_FLUSH_MS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(1000),
+                flush_batch_size: var("SEARCH_ANALYTICS_BATCH_SIZE")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(200),
+                retention_days: var("SEARCH_ANALYTICS_RETENTION_DAYS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(90),
+                hash_key: var("SEARCH_ANALYTICS_HASH_KEY").ok(),
+            },
+            privacy: PrivacyConfig {
+                min_history_retention_days: var("HISTORY_RETENTION_MIN_DAYS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(1),
+                max_history_retention_days: var("HISTORY_RETENTION_MAX_DAYS")
+                    .ok()
+                    .and_then(|s| s.parse().ok()),
+                prune_interval_seconds: var("HISTORY_PRUNE_INTERVAL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3600),
+            },
+            diagnostics: DiagnosticsConfig {
+                solana_network: var("SOLANA_NETWORK").ok(),
+                storage_probe: var("DIAGNOSTICS_STORAGE_PROBE")
+                    .map(|v| v == "true")
+                    .unwrap_or(false),
+                probe_urls: var("DIAGNOSTICS_PROBE_URLS")
+                    .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                    .unwrap_or_default(),
+                check_timeout_seconds: var("DIAGNOSTICS_CHECK_TIMEOUT_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(5),
+            },
+            bridge: BridgeConfig {
+                resolve_url: var("BRIDGE_RESOLVE_URL")
+                    .unwrap_or_else(|_| "shadow://{domain}".to_string()),
+                assertion_ttl_seconds: var("BRIDGE_ASSERTION_TTL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(300),
+                signing_key: var("BRIDGE_SIGNING_KEY").ok(),
+                doh_url: var("BRIDGE_DOH_URL")
+                    .unwrap_or_else(|_| "https://cloudflare-dns.com/dns-query".to_string()),
+            },
+            link_info: LinkInfoConfig {
+                blocklist: var("LINKINFO_BLOCKLIST")
+                    .map(|v| parse_list(&v))
+                    .unwrap_or_default(),
+                allowlist: var("LINKINFO_ALLOWLIST")
+                    .map(|v| parse_list(&v))
+                    .unwrap_or_default(),
+                cache_ttl_seconds: var("LINKINFO_CACHE_TTL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3600),
+                rate_limit_cost: var("LINKINFO_RATE_LIMIT_COST")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(10),
+            },
+            link_tokens: LinkTokenConfig {
+                mint_authority_key: var("LINK_MINT_AUTHORITY_KEY").ok(),
+                convert_rate_limit_cost: var("LINK_CONVERT_RATE_LIMIT_COST")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(5),
+                daily_mints_per_wallet: var("LINK_DAILY_MINTS_PER_WALLET")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(20),
+            },
+            transactions: TransactionConfig {
+                min_reserve_lamports: var("TX_MIN_RESERVE_LAMPORTS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(1_000_000),
+                broadcast_timeout_seconds: var("TX_BROADCAST_TIMEOUT_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(60),
+                broadcast_backoff_ms: var("TX_BROADCAST_BACKOFF_MS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(500),
+            },
+            watchlist: WatchlistConfig {
+                max_per_wallet: var("WATCHLIST_MAX_DOMAINS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(100),
+                scan_interval_seconds: var("WATCHLIST_SCAN_INTERVAL_SECONDS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(3600),
+            },
+            features: FeaturesConfig {
+                graphql_enabled: var("ENABLE_GRAPHQL")
+                    .map(|v| v == "true")
+                    .unwrap_or(false),
+            },
             server: ServerConfig {
-                host: env::var("HOST")
+                host: var("HOST")
                     .unwrap_or_else(|_| "0.0.0.0".to_string()),
-                port: env::var("PORT")
+                port: var("PORT")
                     .ok()
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(8080),
-                workers: env::var("WORKERS")
+                workers: var("WORKERS")
                     .ok()
                     .and_then(|s| s.parse().ok()),
-                keep_alive: env::var("KEEP_ALIVE")
+                keep_alive: var("KEEP_ALIVE")
                     .ok()
                     .and_then(|s| s.parse().ok()),
-                client_timeout: env::var("CLIENT_TIMEOUT")
+                client_timeout: var("CLIENT_TIMEOUT")
                     .ok()
                     .and_then(|s| s.parse().ok()),
             },
@@ -149,6 +582,57 @@ impl ShadowConfig {
     pub fn get_solana_timeout(&self) -> Duration {
         Duration::from_secs(self.solana.timeout_seconds)
     }
+    
+    pub fn get_analytics_summary_interval(&self) -> Duration {
+        Duration::from_secs(self.analytics.summary_interval_seconds)
+    }
+
+    pub fn get_daily_stats_interval(&self) -> Duration {
+        Duration::from_secs(self.analytics.daily_stats_interval_seconds.max(1))
+    }
+
+    pub fn get_reputation_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.reputation.refresh_interval_seconds.max(1))
+    }
+
+    pub fn get_reverification_interval(&self) -> Duration {
+        Duration::from_secs(self.verification.interval_seconds)
+    }
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// KNOWN_DAPPS is "origin=Name,origin=Name"; without it the built-in list is used.
+/// BLOCKED_ORIGINS is a comma-separated list of origins to refuse outright.
+fn reputation_seeds(known: Option<&str>, blocked: Option<&str>) -> Vec<KnownDAppSeed> {
+    let mut seeds = match known {
+        Some(list) => parse_list(list)
+            .into_iter()
+            .filter_map(|entry| {
+                let (origin, name) = entry.split_once('=')?;
+                Some(KnownDAppSeed {
+                    origin: origin.trim().to_string(),
+                    name: name.trim().to_string(),
+                    blocked: false,
+                })
+            })
+            .collect(),
+        None => argus::default_seeds(),
+    };
+
+    for origin in blocked.map(parse_list).unwrap_or_default() {
+        seeds.push(KnownDAppSeed {
+            name: origin.clone(),
+            origin,
+            blocked: true,
+        });
+    }
+    seeds
 }
 
 #[cfg(test)]
@@ -157,14 +641,45 @@ mod tests {
     
     #[test]
     fn test_config_defaults() {
-        env::set_var("DATABASE_URL", "mongodb://localhost:27017");
-        let config = ShadowConfig::from_env();
+        let config = ShadowConfig::from_vars(|key| match key {
+            "DATABASE_URL" => Ok("mongodb://localhost:27017".to_string()),
+            _ => Err(env::VarError::NotPresent),
+        });
         assert!(config.is_ok());
         
         let cfg = config.unwrap();
         assert_eq!(cfg.server.port, 8080);
         assert_eq!(cfg.cache.max_size_mb, 512);
         assert_eq!(cfg.rate_limit.requests_per_minute, 60);
+        assert_eq!(cfg.rate_limit.window_seconds, 60);
+        assert_eq!(cfg.analytics.summary_interval_seconds, 60);
+        assert_eq!(cfg.verification.interstitial_after_failures, 3);
+        assert_eq!(cfg.outbox.max_attempts, 5);
+        assert_eq!(cfg.privacy.max_history_retention_days, None);
+    }
+
+    #[test]
+    fn test_effective_retention_days() {
+        let unbounded = PrivacyConfig {
+            min_history_retention_days: 1,
+            max_history_retention_days: None,
+            prune_interval_seconds: 3600,
+        };
+        assert_eq!(unbounded.effective_retention_days(None), None);
+        assert_eq!(unbounded.effective_retention_days(Some(0)), Some(1));
+        assert_eq!(unbounded.effective_retention_days(Some(7)), Some(7));
+
+        let capped = PrivacyConfig { max_history_retention_days: Some(365), ..unbounded };
+        assert_eq!(capped.effective_retention_days(None), Some(365));
+        assert_eq!(capped.effective_retention_days(Some(1000)), Some(365));
+    }
+
+    #[test]
+    fn test_reputation_seeds() {
+        let seeds = reputation_seeds(Some("https://jup.ag=Jupiter, bad"), Some("https://evil.example"));
+        assert_eq!(seeds.len(), 2);
+        assert_eq!(seeds[0].name, "Jupiter");
+        assert!(seeds[1].blocked);
     }
 }
 
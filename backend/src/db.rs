@@ -1,8 +1,10 @@
 use mongodb::{Collection, Database};
-use mongodb::bson::doc;
+use mongodb::bson::{doc, Document};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use futures_util::TryStreamExt;
+use std::collections::BTreeMap;
+use crate::utils::{encode_cursor, keyset_filter, next_page_cursor};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct User {
@@ -10,7 +12,23 @@ pub struct User {
     pub wallet_pubkey: String,
     pub profile_cid: Option<String>,
     pub is_public: bool,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub avatar: Option<String>,
+    /// Days of browsing history to keep; the configured default applies when unset
+    #[serde(default)]
+    pub history_retention_days: Option<u32>,
+    #[serde(default)]
+    pub analytics_opt_out: bool,
+    /// Copied from the on-chain profile by `set_follow_counts` whenever a follow or unfollow is confirmed
+    #[serde(default)]
+    pub follower_count: i64,
+    #[serde(default)]
+    pub following_count: i64,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub created_at: DateTime<Utc>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub updated_at: DateTime<Utc>,
 }
 
@@ -22,8 +40,137 @@ pub struct Site {
     pub storage_cid: String,
     pub name: Option<String>,
     pub description: Option<String>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub created_at: DateTime<Utc>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub updated_at: DateTime<Utc>,
+    /// Language tag -> entry file, from the languages map in shadow.json
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub languages: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_language: Option<String>,
+    /// Verified Olympus domain pointing at this site, kept in sync by the outbox relay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_domain: Option<String>,
+    /// Content from a staged deploy that failed its checks, served only as a preview
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_cid: Option<String>,
+    /// Bumped by every metadata or content write; PATCH callers send it back as a precondition
+    #[serde(default)]
+    pub revision: i64,
+    /// Domain resolvers and badges should present for this site, one of its Olympus domains
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_domain: Option<String>,
+    #[serde(default)]
+    pub access_policy: AccessPolicy,
+    /// Keep the site out of Athena search results
+    #[serde(default)]
+    pub noindex: bool,
+    /// Whether writes are authorized by `owner_pubkey` or by holding `ownership_mint`
+    #[serde(default)]
+    pub ownership_mode: OwnershipMode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ownership_mint: Option<String>,
+    /// Other wallets with access to the site; see `SiteRole` for what each may do
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collaborators: Vec<Collaborator>,
+}
+
+impl Site {
+    pub fn collaborator(&self, wallet: &str) -> Option<&Collaborator> {
+        self.collaborators.iter().find(|c| c.wallet == wallet)
+    }
+}
+
+/// Access levels on a site, lowest first. Editors change content and metadata, admins also
+/// manage domains and collaborators; the owner is whoever controls the site and is never listed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum SiteRole {
+    Editor,
+    Admin,
+    Owner,
+}
+
+impl SiteRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SiteRole::Editor => "editor",
+            SiteRole::Admin => "admin",
+            SiteRole::Owner => "owner",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Collaborator {
+    pub wallet: String,
+    pub role: SiteRole,
+    /// Wallet that granted the access
+    pub added_by: String,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OwnershipMode {
+    #[default]
+    Wallet,
+    /// Whoever currently holds the site's NFT controls it
+    Nft,
+}
+
+impl OwnershipMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OwnershipMode::Wallet => "wallet",
+            OwnershipMode::Nft => "nft",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessPolicy {
+    #[default]
+    Public,
+    /// Reachable by address or domain, but not listed anywhere
+    Unlisted,
+    Private,
+}
+
+impl AccessPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccessPolicy::Public => "public",
+            AccessPolicy::Unlisted => "unlisted",
+            AccessPolicy::Private => "private",
+        }
+    }
+}
+
+/// `follower` following `followee`, mirrored from the profiles program's Follow account.
+/// The id is "follower:followee" so each pair is stored once.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Follow {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub follower: String,
+    pub followee: String,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl Follow {
+    pub fn new(follower: &str, followee: &str, created_at: DateTime<Utc>) -> Self {
+        Self {
+            id: follow_id(follower, followee),
+            follower: follower.to_string(),
+            followee: followee.to_string(),
+            created_at,
+        }
+    }
 }
 
 pub fn get_users_collection(db: &Database) -> Collection<User> {
@@ -41,28 +188,76 @@ pub async fn get_user(db: &Database, wallet: &str) -> Result<Option<User>, mongo
     Ok(user)
 }
 
+/// Users for a set of wallets, in one query
+pub async fn get_users(db: &Database, wallets: &[String]) -> Result<Vec<User>, mongodb::error::Error> {
+    let collection = get_users_collection(db);
+    let filter = doc! { "_id": { "$in": wallets } };
+    collection.find(filter, None).await?.try_collect().await
+}
+
+/// Public profiles matching `query`, newest first, resuming after `after` (created_at ms, wallet).
+/// Returns the page and the cursor for the next one.
 pub async fn search_users(
     db: &Database,
     query: &str,
     limit: i64,
-) -> Result<Vec<User>, mongodb::error::Error> {
+    after: Option<(i64, String)>,
+) -> Result<(Vec<User>, Option<String>), mongodb::error::Error> {
     let collection = get_users_collection(db);
-    let filter = doc! {
-        "is_public": true,
-        "_id": { "$regex": query, "$options": "i" }
-    };
+    let mut filter = user_search_filter(query);
+    if let Some((created_at, wallet)) = after {
+        filter.extend(keyset_filter("created_at", mongodb::bson::DateTime::from_millis(created_at), &wallet));
+    }
     let options = mongodb::options::FindOptions::builder()
-        .limit(limit)
+        .limit(limit + 1)
+        .sort(doc! { "created_at": -1, "_id": -1 })
         .build();
     
-    let mut cursor = collection.find(filter, options).await?;
-    let mut users = Vec::new();
-    
-    while let Some(user) = cursor.try_next().await? {
-        users.push(user);
+    let mut users: Vec<User> = collection.find(filter, options).await?.try_collect().await?;
+    let next_cursor = next_page_cursor(&mut users, limit, |user| {
+        encode_cursor(user.created_at.timestamp_millis(), &user.wallet_pubkey)
+    });
+    Ok((users, next_cursor))
+}
+
+/// How many public profiles match `query` across all pages
+pub async fn count_search_users(db: &Database, query: &str) -> Result<u64, mongodb::error::Error> {
+    get_users_collection(db).count_documents(user_search_filter(query), None).await
+}
+
+/// `search_users` ordered by follower count instead, resuming after `after` (follower count, wallet)
+pub async fn search_users_by_followers(
+    db: &Database,
+    query: &str,
+    limit: i64,
+    after: Option<(i64, String)>,
+) -> Result<(Vec<User>, Option<String>), mongodb::error::Error> {
+    let mut pipeline = vec![
+        doc! { "$match": user_search_filter(query) },
+        // Profiles nobody has followed yet have no count stored
+        doc! { "$addFields": { "follower_count": { "$ifNull": ["$follower_count", 0_i64] } } },
+    ];
+    if let Some((follower_count, wallet)) = after {
+        pipeline.push(doc! { "$match": keyset_filter("follower_count", follower_count, &wallet) });
+    }
+    pipeline.push(doc! { "$sort": { "follower_count": -1, "_id": -1 } });
+    pipeline.push(doc! { "$limit": limit + 1 });
+
+    let documents: Vec<Document> = get_users_collection(db).aggregate(pipeline, None).await?.try_collect().await?;
+    let mut users = documents.into_iter()
+        .map(mongodb::bson::from_document::<User>)
+        .collect::<Result<Vec<_>, _>>()?;
+    let next_cursor = next_page_cursor(&mut users, limit, |user| {
+        encode_cursor(user.follower_count, &user.wallet_pubkey)
+    });
+    Ok((users, next_cursor))
+}
+
+fn user_search_filter(query: &str) -> Document {
+    doc! {
+        "is_public": true,
+        "_id": { "$regex": query, "$options": "i" }
     }
-    
-    Ok(users)
 }
 
 pub async fn create_or_update_user(
@@ -96,6 +291,159 @@ pub async fn create_or_update_user(
     Ok(())
 }
 
+/// Remove a wallet's user record, profile and settings alike
+pub async fn delete_user(db: &Database, wallet: &str) -> Result<(), mongodb::error::Error> {
+    get_users_collection(db).delete_one(doc! { "_id": wallet }, None).await?;
+    Ok(())
+}
+
+pub fn get_follows_collection(db: &Database) -> Collection<Follow> {
+    db.collection::<Follow>("follows")
+}
+
+fn follow_id(follower: &str, followee: &str) -> String {
+    format!("{}:{}", follower, followee)
+}
+
+/// Record `follower` following `followee` since `created_at`. Returns false when the follow
+/// already existed.
+pub async fn follow_user(
+    db: &Database,
+    follower: &str,
+    followee: &str,
+    created_at: DateTime<Utc>,
+) -> Result<bool, mongodb::error::Error> {
+    let created_at = mongodb::bson::DateTime::from_chrono(created_at);
+    let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+    let result = db.collection::<Document>("follows")
+        .update_one(
+            doc! { "_id": follow_id(follower, followee) },
+            doc! { "$setOnInsert": { "follower": follower, "followee": followee, "created_at": created_at } },
+            options,
+        )
+        .await?;
+    Ok(result.upserted_id.is_some())
+}
+
+/// Remove a follow. Returns false when there was none.
+pub async fn unfollow_user(db: &Database, follower: &str, followee: &str) -> Result<bool, mongodb::error::Error> {
+    let result = get_follows_collection(db)
+        .delete_one(doc! { "_id": follow_id(follower, followee) }, None)
+        .await?;
+    Ok(result.deleted_count > 0)
+}
+
+/// Copy a profile's follow counts from its on-chain account
+pub async fn set_follow_counts(
+    db: &Database,
+    wallet: &str,
+    follower_count: u64,
+    following_count: u64,
+) -> Result<(), mongodb::error::Error> {
+    get_users_collection(db)
+        .update_one(
+            doc! { "_id": wallet },
+            doc! { "$set": { "follower_count": follower_count as i64, "following_count": following_count as i64 } },
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Who follows `wallet`, newest first, resuming after `after` (created_at ms, follow id)
+pub async fn list_followers(
+    db: &Database,
+    wallet: &str,
+    limit: i64,
+    after: Option<(i64, String)>,
+) -> Result<(Vec<Follow>, Option<String>), mongodb::error::Error> {
+    list_follows(db, doc! { "followee": wallet }, limit, after).await
+}
+
+/// Who `wallet` follows, newest first, resuming after `after` (created_at ms, follow id)
+pub async fn list_following(
+    db: &Database,
+    wallet: &str,
+    limit: i64,
+    after: Option<(i64, String)>,
+) -> Result<(Vec<Follow>, Option<String>), mongodb::error::Error> {
+    list_follows(db, doc! { "follower": wallet }, limit, after).await
+}
+
+async fn list_follows(
+    db: &Database,
+    mut filter: Document,
+    limit: i64,
+    after: Option<(i64, String)>,
+) -> Result<(Vec<Follow>, Option<String>), mongodb::error::Error> {
+    if let Some((created_at, id)) = after {
+        filter.extend(keyset_filter("created_at", mongodb::bson::DateTime::from_millis(created_at), &id));
+    }
+    let options = mongodb::options::FindOptions::builder()
+        .limit(limit + 1)
+        .sort(doc! { "created_at": -1, "_id": -1 })
+        .build();
+
+    let mut follows: Vec<Follow> = get_follows_collection(db).find(filter, options).await?.try_collect().await?;
+    let next_cursor = next_page_cursor(&mut follows, limit, |follow| {
+        encode_cursor(follow.created_at.timestamp_millis(), &follow.id)
+    });
+    Ok((follows, next_cursor))
+}
+
+/// Set the display fields parsed from a profile; `None` leaves a field unchanged
+pub async fn set_user_display(
+    db: &Database,
+    wallet: &str,
+    display_name: Option<&str>,
+    avatar: Option<&str>,
+) -> Result<(), mongodb::error::Error> {
+    let mut set = Document::new();
+    if let Some(display_name) = display_name {
+        set.insert("display_name", display_name);
+    }
+    if let Some(avatar) = avatar {
+        set.insert("avatar", avatar);
+    }
+    if set.is_empty() {
+        return Ok(());
+    }
+    get_users_collection(db)
+        .update_one(doc! { "_id": wallet }, doc! { "$set": set }, None)
+        .await?;
+    Ok(())
+}
+
+/// Store a wallet's privacy choices. Wallets without a profile get a private user record.
+pub async fn set_privacy_settings(
+    db: &Database,
+    wallet: &str,
+    history_retention_days: Option<u32>,
+    analytics_opt_out: bool,
+) -> Result<(), mongodb::error::Error> {
+    let bson_now = mongodb::bson::DateTime::now();
+    let update = doc! {
+        "$set": {
+            "history_retention_days": history_retention_days,
+            "analytics_opt_out": analytics_opt_out,
+            "updated_at": bson_now
+        },
+        "$setOnInsert": {
+            "profile_cid": null,
+            "is_public": false,
+            "created_at": bson_now
+        }
+    };
+    let options = mongodb::options::UpdateOptions::builder()
+        .upsert(true)
+        .build();
+
+    get_users_collection(db)
+        .update_one(doc! { "_id": wallet }, update, options)
+        .await?;
+    Ok(())
+}
+
 pub async fn get_site(db: &Database, program_address: &str) -> Result<Option<Site>, mongodb::error::Error> {
     let collection = get_sites_collection(db);
     let filter = doc! { "_id": program_address };
@@ -103,32 +451,62 @@ pub async fn get_site(db: &Database, program_address: &str) -> Result<Option<Sit
     Ok(site)
 }
 
+/// Sites for a set of program addresses, in one query
+pub async fn get_sites(db: &Database, program_addresses: &[String]) -> Result<Vec<Site>, mongodb::error::Error> {
+    let collection = get_sites_collection(db);
+    let filter = doc! { "_id": { "$in": program_addresses } };
+    collection.find(filter, None).await?.try_collect().await
+}
+
+/// Sites a wallet owns or collaborates on, newest first
+pub async fn list_sites_for_wallet(db: &Database, wallet: &str) -> Result<Vec<Site>, mongodb::error::Error> {
+    let filter = doc! { "$or": [ { "owner_pubkey": wallet }, { "collaborators.wallet": wallet } ] };
+    let options = mongodb::options::FindOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .build();
+    get_sites_collection(db).find(filter, options).await?.try_collect().await
+}
+
+/// Sites matching `query`, newest first, resuming after `after` (created_at ms, program address).
+/// Returns the page and the cursor for the next one.
 pub async fn search_sites(
     db: &Database,
     query: &str,
     limit: i64,
-) -> Result<Vec<Site>, mongodb::error::Error> {
+    after: Option<(i64, String)>,
+) -> Result<(Vec<Site>, Option<String>), mongodb::error::Error> {
     let collection = get_sites_collection(db);
-    let filter = doc! {
+    let mut filter = site_search_filter(query);
+    if let Some((created_at, program_address)) = after {
+        // Both the text match and the keyset filter are `$or`s
+        let after = keyset_filter("created_at", mongodb::bson::DateTime::from_millis(created_at), &program_address);
+        filter = doc! { "$and": [filter, after] };
+    }
+    let options = mongodb::options::FindOptions::builder()
+        .limit(limit + 1)
+        .sort(doc! { "created_at": -1, "_id": -1 })
+        .build();
+    
+    let mut sites: Vec<Site> = collection.find(filter, options).await?.try_collect().await?;
+    let next_cursor = next_page_cursor(&mut sites, limit, |site| {
+        encode_cursor(site.created_at.timestamp_millis(), &site.program_address)
+    });
+    Ok((sites, next_cursor))
+}
+
+/// How many sites match `query` across all pages
+pub async fn count_search_sites(db: &Database, query: &str) -> Result<u64, mongodb::error::Error> {
+    get_sites_collection(db).count_documents(site_search_filter(query), None).await
+}
+
+fn site_search_filter(query: &str) -> Document {
+    doc! {
         "$or": [
             { "name": { "$regex": query, "$options": "i" } },
             { "description": { "$regex": query, "$options": "i" } },
             { "_id": { "$regex": query, "$options": "i" } }
         ]
-    };
-    let options = mongodb::options::FindOptions::builder()
-        .limit(limit)
-        .sort(doc! { "created_at": -1 })
-        .build();
-    
-    let mut cursor = collection.find(filter, options).await?;
-    let mut sites = Vec::new();
-    
-    while let Some(site) = cursor.try_next().await? {
-        sites.push(site);
     }
-    
-    Ok(sites)
 }
 
 pub async fn create_or_update_site(
@@ -165,3 +543,92 @@ pub async fn create_or_update_site(
     collection.update_one(filter, update, options).await?;
     Ok(())
 }
+
+/// Upsert update for a full site registration, language variants included,
+/// so the whole change can be applied (and outboxed) as a single write
+pub fn site_upsert_update(
+    owner_pubkey: &str,
+    storage_cid: &str,
+    name: Option<&str>,
+    description: Option<&str>,
+    languages: Option<&BTreeMap<String, String>>,
+    default_language: Option<&str>,
+) -> Result<Document, mongodb::bson::ser::Error> {
+    let bson_now = mongodb::bson::DateTime::now();
+    let mut set = doc! {
+        "owner_pubkey": owner_pubkey,
+        "storage_cid": storage_cid,
+        "name": name,
+        "description": description,
+        "updated_at": bson_now
+    };
+    let mut update = doc! {
+        "$setOnInsert": { "created_at": bson_now },
+        "$inc": { "revision": 1_i64 },
+    };
+    match languages {
+        Some(languages) => {
+            set.insert("languages", mongodb::bson::to_bson(languages)?);
+            set.insert("default_language", default_language);
+        }
+        None => {
+            update.insert("$unset", doc! { "languages": "", "default_language": "" });
+        }
+    }
+    update.insert("$set", set);
+    Ok(update)
+}
+
+/// Make a site upsert overwrite `created_at` instead of keeping the existing record's
+pub fn restart_site_timestamps(update: &mut Document, created_at: DateTime<Utc>) {
+    update.remove("$setOnInsert");
+    if let Ok(set) = update.get_document_mut("$set") {
+        set.insert("created_at", mongodb::bson::DateTime::from_chrono(created_at));
+    }
+}
+
+/// Whether a write failed because its `_id` (or another unique key) is already taken,
+/// e.g. an upsert whose filter missed a document that exists under the same key
+pub fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    use mongodb::error::{ErrorKind, WriteFailure};
+    const DUPLICATE_KEY: i32 = 11000;
+    match &*error.kind {
+        ErrorKind::Write(WriteFailure::WriteError(e)) => e.code == DUPLICATE_KEY,
+        ErrorKind::Command(e) => e.code == DUPLICATE_KEY,
+        _ => false,
+    }
+}
+
+/// Filter matching a site only while it is still at `revision`. Sites written
+/// before revisions existed have no field and count as revision 0.
+pub fn site_revision_filter(program_address: &str, revision: i64) -> Document {
+    doc! {
+        "_id": program_address,
+        "$expr": { "$eq": [{ "$ifNull": ["$revision", 0_i64] }, revision] },
+    }
+}
+
+/// Replace a site's language variants; `None` makes it a single-language site again
+pub async fn set_site_languages(
+    db: &Database,
+    program_address: &str,
+    languages: Option<&BTreeMap<String, String>>,
+    default_language: Option<&str>,
+) -> Result<(), mongodb::error::Error> {
+    let collection = get_sites_collection(db);
+    let filter = doc! { "_id": program_address };
+    let update = match languages {
+        Some(languages) => doc! {
+            "$set": {
+                "languages": mongodb::bson::to_bson(languages)?,
+                "default_language": default_language
+            }
+        },
+        None => doc! {
+            "$unset": { "languages": "", "default_language": "" }
+        },
+    };
+
+    collection.update_one(filter, update, None).await?;
+    Ok(())
+}
@@ -3,8 +3,14 @@
 
 use mongodb::{Collection, Database};
 use mongodb::bson::doc;
+use crate::solana::{TokenAccountInfo, TOKEN_2022_PROGRAM_ID};
 use serde::{Deserialize, Serialize};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::packet::PACKET_DATA_SIZE;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
 use std::sync::Arc;
 use std::str::FromStr;
 
@@ -26,6 +32,19 @@ pub struct TokenTransferRequest {
     pub amount: u64, // Amount in smallest unit
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnsureAtaRequest {
+    pub wallet_pubkey: String, // Owner of the token account
+    pub mint: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnsureAtaResponse {
+    pub ata_address: String,
+    pub exists: bool,
+    pub creation_transaction: Option<String>, // Base64 unsigned transaction, only when the ATA is missing
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenMetadata {
     pub mint: String,
@@ -35,6 +54,135 @@ pub struct TokenMetadata {
     pub logo_uri: Option<String>,
 }
 
+/// Base fee per signature; cleanup transactions carry only the owner's
+pub const SIGNATURE_FEE_LAMPORTS: u64 = 5_000;
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// Why a token account was left out of a cleanup
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupExclusion {
+    /// Frozen accounts cannot be closed until the freeze authority thaws them
+    Frozen,
+    HoldsNft,
+    NonZeroBalance,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CleanupCandidate {
+    pub address: String,
+    pub mint: String,
+    pub lamports: u64,
+    pub token_program: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExcludedAccount {
+    pub address: String,
+    pub mint: String,
+    pub reason: CleanupExclusion,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CleanupPlan {
+    pub wallet: String,
+    pub candidates: Vec<CleanupCandidate>,
+    pub excluded: Vec<ExcludedAccount>,
+    pub recoverable_lamports: u64,
+    pub transaction_count: usize,
+    pub estimated_fee_lamports: u64,
+    pub net_reclaimed_lamports: u64,
+    pub net_reclaimed_sol: f64,
+}
+
+/// Split a wallet's token accounts into closable empties and accounts that must stay
+pub fn plan_cleanup(wallet: &Pubkey, accounts: Vec<TokenAccountInfo>) -> Result<CleanupPlan, String> {
+    let mut candidates = Vec::new();
+    let mut excluded = Vec::new();
+
+    for account in accounts {
+        let reason = if account.is_frozen {
+            Some(CleanupExclusion::Frozen)
+        } else if account.amount == 1 && account.decimals == 0 {
+            Some(CleanupExclusion::HoldsNft)
+        } else if account.amount > 0 {
+            Some(CleanupExclusion::NonZeroBalance)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => excluded.push(ExcludedAccount { address: account.address, mint: account.mint, reason }),
+            None => candidates.push(CleanupCandidate {
+                address: account.address,
+                mint: account.mint,
+                lamports: account.lamports,
+                token_program: account.token_program,
+            }),
+        }
+    }
+
+    // Chunking doesn't depend on the blockhash, so a placeholder sizes the batch
+    let transaction_count = build_cleanup_transactions(wallet, &candidates, Hash::default())?.len();
+    let recoverable_lamports: u64 = candidates.iter().map(|c| c.lamports).sum();
+    let estimated_fee_lamports = transaction_count as u64 * SIGNATURE_FEE_LAMPORTS;
+    let net_reclaimed_lamports = recoverable_lamports.saturating_sub(estimated_fee_lamports);
+
+    Ok(CleanupPlan {
+        wallet: wallet.to_string(),
+        candidates,
+        excluded,
+        recoverable_lamports,
+        transaction_count,
+        estimated_fee_lamports,
+        net_reclaimed_lamports,
+        net_reclaimed_sol: net_reclaimed_lamports as f64 / LAMPORTS_PER_SOL,
+    })
+}
+
+/// Unsigned close_account transactions returning rent to `owner`, packed as tightly
+/// as the packet size limit allows
+pub fn build_cleanup_transactions(
+    owner: &Pubkey,
+    candidates: &[CleanupCandidate],
+    blockhash: Hash,
+) -> Result<Vec<Transaction>, String> {
+    let mut transactions = Vec::new();
+    let mut batch: Vec<Instruction> = Vec::new();
+
+    for candidate in candidates {
+        let account = Pubkey::from_str(&candidate.address)
+            .map_err(|_| format!("Invalid token account {}", candidate.address))?;
+        let program = Pubkey::from_str(&candidate.token_program)
+            .map_err(|_| format!("Invalid token program {}", candidate.token_program))?;
+        let instruction = spl_token::instruction::close_account(&program, &account, owner, owner, &[])
+            .map_err(|e| format!("Failed to create close instruction: {}", e))?;
+
+        batch.push(instruction);
+        if cleanup_transaction(owner, &batch, blockhash)?.1 > PACKET_DATA_SIZE {
+            let overflow = batch.pop().expect("batch has the instruction just pushed");
+            if batch.is_empty() {
+                return Err("A single close instruction exceeds the transaction size limit".to_string());
+            }
+            transactions.push(cleanup_transaction(owner, &batch, blockhash)?.0);
+            batch = vec![overflow];
+        }
+    }
+    if !batch.is_empty() {
+        transactions.push(cleanup_transaction(owner, &batch, blockhash)?.0);
+    }
+
+    Ok(transactions)
+}
+
+fn cleanup_transaction(owner: &Pubkey, instructions: &[Instruction], blockhash: Hash) -> Result<(Transaction, usize), String> {
+    let message = Message::new_with_blockhash(instructions, Some(owner), &blockhash);
+    let transaction = Transaction::new_unsigned(message);
+    let size = bincode::serialized_size(&transaction)
+        .map_err(|_| "Failed to size transaction".to_string())?;
+    Ok((transaction, size as usize))
+}
+
 pub struct DionysusTokenManager {
     db: Arc<Database>,
     solana_rpc_url: String,
@@ -81,6 +229,48 @@ impl DionysusTokenManager {
         Ok(balances)
     }
 
+    /// Empty token accounts whose rent the wallet can reclaim
+    pub async fn plan_cleanup(&self, wallet_pubkey: &str) -> Result<CleanupPlan, String> {
+        use crate::solana::SolanaClient;
+        let client = SolanaClient::new(self.solana_rpc_url.clone());
+
+        let pubkey = Pubkey::from_str(wallet_pubkey)
+            .map_err(|_| "Invalid pubkey".to_string())?;
+        let token_accounts = client
+            .get_token_accounts(&pubkey)
+            .await
+            .map_err(|e| format!("Failed to get token accounts: {}", e))?;
+
+        plan_cleanup(&pubkey, token_accounts)
+    }
+
+    /// Base64 unsigned transactions closing the chosen cleanup candidates
+    pub async fn build_cleanup(
+        &self,
+        wallet_pubkey: &str,
+        candidates: &[CleanupCandidate],
+    ) -> Result<Vec<String>, String> {
+        use crate::solana::SolanaClient;
+        use base64::{Engine as _, engine::general_purpose};
+        let client = SolanaClient::new(self.solana_rpc_url.clone());
+
+        let owner = Pubkey::from_str(wallet_pubkey)
+            .map_err(|_| "Invalid pubkey".to_string())?;
+        let blockhash = client
+            .get_recent_blockhash()
+            .await
+            .map_err(|e| format!("Failed to get blockhash: {}", e))?;
+
+        build_cleanup_transactions(&owner, candidates, blockhash)?
+            .iter()
+            .map(|transaction| {
+                bincode::serialize(transaction)
+                    .map(|bytes| general_purpose::STANDARD.encode(bytes))
+                    .map_err(|_| "Failed to serialize transaction".to_string())
+            })
+            .collect()
+    }
+
     /// Get token metadata (cached in database)
     pub async fn get_token_metadata(&self, mint: &str) -> Result<TokenMetadata, String> {
         let collection: Collection<TokenMetadata> = self.db.collection("token_metadata");
@@ -112,6 +302,80 @@ impl DionysusTokenManager {
         Ok(metadata)
     }
 
+    /// Make sure `wallet_pubkey` has an associated token account for `mint`.
+    /// Returns the ATA address, plus an unsigned creation transaction paid by `payer` when it doesn't exist yet.
+    pub async fn ensure_associated_token_account(
+        &self,
+        wallet_pubkey: &str,
+        mint: &str,
+        payer: &str,
+    ) -> Result<EnsureAtaResponse, String> {
+        use crate::solana::SolanaClient;
+        let client = SolanaClient::new(self.solana_rpc_url.clone());
+
+        let wallet = Pubkey::from_str(wallet_pubkey)
+            .map_err(|_| "Invalid wallet pubkey".to_string())?;
+        let mint_pubkey = Pubkey::from_str(mint)
+            .map_err(|_| "Invalid mint pubkey".to_string())?;
+        let payer = Pubkey::from_str(payer)
+            .map_err(|_| "Invalid payer pubkey".to_string())?;
+
+        // The ATA address depends on which token program owns the mint
+        let mint_account = client.fetch_account(mint)?
+            .ok_or_else(|| "Mint not found".to_string())?;
+        let token_program = if mint_account.owner == spl_token::id().to_string() {
+            spl_token::id()
+        } else if mint_account.owner == TOKEN_2022_PROGRAM_ID {
+            Pubkey::from_str(TOKEN_2022_PROGRAM_ID).map_err(|e| e.to_string())?
+        } else {
+            return Err("Account is not a token mint".to_string());
+        };
+
+        let ata = spl_associated_token_account::get_associated_token_address_with_program_id(
+            &wallet,
+            &mint_pubkey,
+            &token_program,
+        );
+
+        if client.fetch_account(&ata.to_string())?.is_some() {
+            return Ok(EnsureAtaResponse {
+                ata_address: ata.to_string(),
+                exists: true,
+                creation_transaction: None,
+            });
+        }
+
+        let instruction = spl_associated_token_account::instruction::create_associated_token_account(
+            &payer,
+            &wallet,
+            &mint_pubkey,
+            &token_program,
+        );
+
+        let blockhash = client
+            .get_recent_blockhash()
+            .await
+            .map_err(|e| format!("Failed to get blockhash: {}", e))?;
+
+        let message = solana_sdk::message::Message::new_with_blockhash(
+            &[instruction],
+            Some(&payer),
+            &blockhash,
+        );
+
+        // Serialize transaction (unsigned)
+        let transaction = solana_sdk::transaction::Transaction::new_unsigned(message);
+        use base64::{Engine as _, engine::general_purpose};
+        let tx_bytes = bincode::serialize(&transaction)
+            .map_err(|_| "Failed to serialize transaction".to_string())?;
+
+        Ok(EnsureAtaResponse {
+            ata_address: ata.to_string(),
+            exists: false,
+            creation_transaction: Some(general_purpose::STANDARD.encode(&tx_bytes)),
+        })
+    }
+
     /// Create token transfer transaction
     pub async fn create_transfer_transaction(
         &self,
@@ -0,0 +1,125 @@
+// Enodia - Goddess of crossroads and thresholds
+// Safety signals for the interstitial shown when a link leaves Shadow. Everything comes
+// from what Athena has already indexed; the linked URL itself is never fetched.
+
+use crate::athena::AthenaIndexer;
+use crate::config::LinkInfoConfig;
+use crate::error::ShadowError;
+use crate::link_converter::LinkConverter;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use mongodb::Database;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Cached hosts kept before expired entries are swept
+const MAX_CACHED_HOSTS: usize = 10_000;
+
+/// Where a host stands on the configured lists
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListStatus {
+    Blocked,
+    Allowed,
+    Unlisted,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LinkInfo {
+    /// The URL after normalization
+    pub url: String,
+    pub host: String,
+    pub list: ListStatus,
+    /// When indexed content first linked to this host; None if it never has
+    pub first_seen_at: Option<DateTime<Utc>>,
+    pub first_seen_on: Option<String>,
+    /// Distinct Shadow sites whose indexed content links to the host
+    pub linking_sites: usize,
+}
+
+pub struct EnodiaLinkInfo {
+    athena: AthenaIndexer,
+    config: LinkInfoConfig,
+    cache: DashMap<String, (LinkInfo, Instant)>,
+}
+
+impl EnodiaLinkInfo {
+    pub fn new(db: Database, config: LinkInfoConfig) -> Self {
+        Self {
+            athena: AthenaIndexer::new(db),
+            config,
+            cache: DashMap::new(),
+        }
+    }
+
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.config.cache_ttl_seconds)
+    }
+
+    pub fn rate_limit_cost(&self) -> u32 {
+        self.config.rate_limit_cost
+    }
+
+    /// Whether `host` is `entry` or one of its subdomains
+    fn matches(host: &str, entry: &str) -> bool {
+        let entry = entry.trim().trim_start_matches("*.").trim_end_matches('.').to_ascii_lowercase();
+        !entry.is_empty()
+            && (host == entry || host.strip_suffix(entry.as_str()).is_some_and(|rest| rest.ends_with('.')))
+    }
+
+    pub fn list_status(&self, host: &str) -> ListStatus {
+        if self.config.blocklist.iter().any(|entry| Self::matches(host, entry)) {
+            ListStatus::Blocked
+        } else if self.config.allowlist.iter().any(|entry| Self::matches(host, entry)) {
+            ListStatus::Allowed
+        } else {
+            ListStatus::Unlisted
+        }
+    }
+
+    /// Signals for an outbound link. Cached per host for the configured TTL.
+    pub async fn lookup(&self, url: &str) -> Result<LinkInfo, ShadowError> {
+        let url = LinkConverter::normalize_url(url);
+        let host = AthenaIndexer::link_host(&url)
+            .ok_or_else(|| ShadowError::BadRequest("Expected an http or https URL".to_string()))?;
+        if host.ends_with(".shadow") {
+            return Err(ShadowError::BadRequest("Links to .shadow domains stay inside Shadow".to_string()));
+        }
+
+        if let Some(entry) = self.cache.get(&host) {
+            if entry.1.elapsed() < self.cache_ttl() {
+                return Ok(LinkInfo { url, ..entry.0.clone() });
+            }
+        }
+
+        let first_seen = self.athena.first_seen(&host).await?;
+        let info = LinkInfo {
+            list: self.list_status(&host),
+            first_seen_at: first_seen.as_ref().map(|seen| seen.first_seen_at),
+            first_seen_on: first_seen.map(|seen| seen.first_seen_on),
+            linking_sites: self.athena.linking_sites(&host).await?,
+            url,
+            host: host.clone(),
+        };
+
+        if self.cache.len() >= MAX_CACHED_HOSTS {
+            let ttl = self.cache_ttl();
+            self.cache.retain(|_, (_, cached_at)| cached_at.elapsed() < ttl);
+        }
+        self.cache.insert(host, (info.clone(), Instant::now()));
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_matching_covers_subdomains_only() {
+        assert!(EnodiaLinkInfo::matches("evil.example", "evil.example"));
+        assert!(EnodiaLinkInfo::matches("login.evil.example", "*.Evil.Example."));
+        assert!(!EnodiaLinkInfo::matches("notevil.example", "evil.example"));
+        assert!(!EnodiaLinkInfo::matches("evil.example", ""));
+    }
+}
@@ -8,7 +8,12 @@ pub enum ShadowError {
     Solana(String),
     NotFound(String),
     BadRequest(String),
+    Conflict(String),
     Unauthorized,
+    AuthFailed(String),
+    Forbidden(String),
+    /// The resource the request depends on no longer exists, e.g. a burned control NFT
+    Gone(String),
 }
 
 impl fmt::Display for ShadowError {
@@ -19,7 +24,11 @@ impl fmt::Display for ShadowError {
             ShadowError::Solana(e) => write!(f, "Solana error: {}", e),
             ShadowError::NotFound(e) => write!(f, "Not found: {}", e),
             ShadowError::BadRequest(e) => write!(f, "Bad request: {}", e),
+            ShadowError::Conflict(e) => write!(f, "Conflict: {}", e),
             ShadowError::Unauthorized => write!(f, "Unauthorized"),
+            ShadowError::AuthFailed(e) => write!(f, "Authentication failed: {}", e),
+            ShadowError::Forbidden(e) => write!(f, "Forbidden: {}", e),
+            ShadowError::Gone(e) => write!(f, "Gone: {}", e),
         }
     }
 }
@@ -37,8 +46,9 @@ impl ResponseError for ShadowError {
                     "error": "Storage error"
                 }))
             }
+            // The RPC node failed us, not the client or this server
             ShadowError::Solana(_) => {
-                HttpResponse::InternalServerError().json(serde_json::json!({
+                HttpResponse::BadGateway().json(serde_json::json!({
                     "error": "Solana error"
                 }))
             }
@@ -52,11 +62,31 @@ impl ResponseError for ShadowError {
                     "error": msg
                 }))
             }
+            ShadowError::Conflict(msg) => {
+                HttpResponse::Conflict().json(serde_json::json!({
+                    "error": msg
+                }))
+            }
             ShadowError::Unauthorized => {
                 HttpResponse::Unauthorized().json(serde_json::json!({
                     "error": "Unauthorized"
                 }))
             }
+            ShadowError::AuthFailed(msg) => {
+                HttpResponse::Unauthorized().json(serde_json::json!({
+                    "error": msg
+                }))
+            }
+            ShadowError::Forbidden(msg) => {
+                HttpResponse::Forbidden().json(serde_json::json!({
+                    "error": msg
+                }))
+            }
+            ShadowError::Gone(msg) => {
+                HttpResponse::Gone().json(serde_json::json!({
+                    "error": msg
+                }))
+            }
         }
     }
 }
@@ -1,16 +1,50 @@
 // Hades - God of the Underworld and Security
 // Handles wallet encryption, security, and authentication
 
+use mongodb::bson::doc;
+use mongodb::Database;
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct SecuritySettings {
     pub require_password_for_transactions: bool,
     pub require_password_for_export: bool,
     pub session_timeout_minutes: u32,
     pub biometric_enabled: bool,
+    /// Overrides the server's minimum post-transaction balance, in lamports
+    #[serde(default)]
+    pub min_reserve_lamports: Option<u64>,
+}
+
+impl SecuritySettings {
+    /// Reserve to keep after a transaction, falling back to the server default
+    pub fn reserve_lamports(&self, default: u64) -> u64 {
+        self.min_reserve_lamports.unwrap_or(default)
+    }
+}
+
+/// A user's stored security settings, or the defaults if they never saved any
+pub async fn get_security_settings(db: &Database, user_id: &str) -> Result<SecuritySettings, mongodb::error::Error> {
+    Ok(db.collection::<SecuritySettings>("security_settings")
+        .find_one(doc! { "_id": user_id }, None)
+        .await?
+        .unwrap_or_default())
+}
+
+pub async fn save_security_settings(
+    db: &Database,
+    user_id: &str,
+    settings: &SecuritySettings,
+) -> Result<(), mongodb::error::Error> {
+    let options = mongodb::options::ReplaceOptions::builder().upsert(true).build();
+    let mut document = mongodb::bson::to_document(settings)?;
+    document.insert("_id", user_id);
+    db.collection::<mongodb::bson::Document>("security_settings")
+        .replace_one(doc! { "_id": user_id }, document, options)
+        .await?;
+    Ok(())
 }
 
 impl Default for SecuritySettings {
@@ -20,12 +54,19 @@ impl Default for SecuritySettings {
             require_password_for_export: true,
             session_timeout_minutes: 15,
             biometric_enabled: false,
+            min_reserve_lamports: None,
         }
     }
 }
 
 pub struct HadesSecurityManager;
 
+impl Default for HadesSecurityManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HadesSecurityManager {
     pub fn new() -> Self {
         Self
@@ -53,7 +94,7 @@ impl HadesSecurityManager {
         let mut hasher = Sha256::new();
         hasher.update(user_id.as_bytes());
         hasher.update(timestamp.to_string().as_bytes());
-        hasher.update(&rand::random::<u64>().to_be_bytes());
+        hasher.update(rand::random::<u64>().to_be_bytes());
         
         hex::encode(hasher.finalize())
     }
@@ -1,20 +1,38 @@
 use actix_web::{web, HttpRequest, HttpResponse, Result as ActixResult};
 use crate::db;
 use crate::error::ShadowError;
-use crate::storage::{PinataStorage, BundlrStorage};
+use crate::storage::{BundlrStorage, NFTStorageClient, PinataStorage, StorageStream};
 use crate::solana::SolanaClient;
 use crate::anchor_client;
-use crate::ares::{AresAuth, AuthHeader};
+use crate::ares::{authenticate, verify_signed_header, AresAuth};
 use crate::apollo::ApolloValidator;
 use crate::artemis::ArtemisRateLimiter;
 use crate::olympus::OlympusCA;
-use crate::athena::AthenaIndexer;
+use crate::athena::{AthenaIndexer, SearchOptions, SearchSort};
 use crate::chronos::ChronosManager;
-use crate::prometheus::PrometheusAnalytics;
+use crate::prometheus::{AnalyticsExport, DailyExportRow, ExportFormat, PrometheusAnalytics};
 use crate::hephaestus::HephaestusCache;
 use crate::metrics::MetricsCollector;
+use crate::config::ShadowConfig;
+use crate::iris;
+use crate::websocket;
+use crate::hecate::{self, ShadowSchema};
+use crate::mnemosyne::{Mnemosyne, OutboxPayload, OutboxStatus, PrimaryWrite};
+use crate::hygieia::Hygieia;
+use crate::asclepius::{AsclepiusChecker, DeployReport, SiteVersion, SiteVersions, VersionStatus};
+use crate::cerberus::{DeployTokens, DEPLOY_TOKEN_HEADER};
+use crate::charon::{normalize_legacy_domain, CharonBridge};
+use crate::atlas::AtlasTracker;
+use crate::metis::{self, DeployPlanRequest};
+use crate::clio::{ClioRecorder, SearchWindow};
+use crate::tyche::{SiteAction, TycheOwnership};
+use crate::helios::HeliosWatchlist;
+use crate::pheme::{self, BadgeData, BadgeQuery, BadgeStat, BadgeStatus};
+use crate::utils::{decode_cursor, decode_score_cursor};
 use serde::{Deserialize, Serialize};
 use mongodb::Database;
+use mongodb::bson::doc;
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 #[derive(Deserialize)]
@@ -22,12 +40,20 @@ pub struct CreateProfileRequest {
     pub wallet: String,
     pub profile_cid: String,
     pub is_public: bool,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub avatar: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct UpdateProfileRequest {
     pub profile_cid: Option<String>,
     pub is_public: Option<bool>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub avatar: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -36,13 +62,144 @@ pub struct ProfileResponse {
     pub profile_cid: Option<String>,
     pub is_public: bool,
     pub exists: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+}
+
+impl ProfileResponse {
+    fn from_user(user: db::User) -> Self {
+        Self {
+            wallet_pubkey: user.wallet_pubkey,
+            profile_cid: user.profile_cid,
+            is_public: user.is_public,
+            exists: true,
+            display_name: user.display_name,
+            avatar: user.avatar,
+        }
+    }
+
+    fn missing(wallet: String) -> Self {
+        Self {
+            wallet_pubkey: wallet,
+            profile_cid: None,
+            is_public: false,
+            exists: false,
+            display_name: None,
+            avatar: None,
+        }
+    }
+
+    /// What a non-owner may see of a private profile: that it exists, nothing more
+    fn private(wallet: String) -> Self {
+        Self { exists: true, ..Self::missing(wallet) }
+    }
+}
+
+/// Most wallets a single batch profile lookup may name
+const MAX_BATCH_PROFILES: usize = 100;
+/// Batch lookups cost one rate-limit unit plus one per this many wallets
+const BATCH_PROFILES_PER_UNIT: usize = 10;
+const MAX_DISPLAY_NAME_LENGTH: usize = 50;
+
+fn validate_profile_display(display_name: Option<&str>, avatar: Option<&str>) -> Result<Option<String>, ShadowError> {
+    if let Some(avatar) = avatar {
+        ApolloValidator::validate_ipfs_cid(avatar)?;
+    }
+    match display_name {
+        Some(name) => {
+            let name = ApolloValidator::sanitize_string(name.trim(), MAX_DISPLAY_NAME_LENGTH)?;
+            if name.is_empty() {
+                return Err(ShadowError::BadRequest("Display name cannot be empty".to_string()));
+            }
+            Ok(Some(name))
+        }
+        None => Ok(None),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BatchProfilesRequest {
+    pub wallets: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BatchProfileEntry {
+    Profile(ProfileResponse),
+    Error { error: String },
+}
+
+pub async fn batch_profiles(
+    db: web::Data<Database>,
+    body: web::Json<BatchProfilesRequest>,
+    ares: web::Data<AresAuth>,
+    artemis: web::Data<ArtemisRateLimiter>,
+    req: HttpRequest,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallets = body.into_inner().wallets;
+    if wallets.len() > MAX_BATCH_PROFILES {
+        return Err(ShadowError::BadRequest(format!("At most {} wallets per batch", MAX_BATCH_PROFILES)));
+    }
+
+    // One unit for the request plus a small cost per wallet looked up
+    let client_ip = req.peer_addr().map(|a| a.ip().to_string());
+    let key = ArtemisRateLimiter::get_client_key(client_ip.as_deref(), None);
+    let cost = 1 + wallets.len().div_ceil(BATCH_PROFILES_PER_UNIT) as u32;
+    artemis.check_rate_limit_with_cost(&key, cost)
+        .map_err(ShadowError::BadRequest)?;
+
+    // Owners see their own private profile in full
+    let requester = if req.headers().contains_key("X-Shadow-Auth") || req.headers().contains_key("Authorization") {
+        Some(authenticate(&req, &ares)?)
+    } else {
+        None
+    };
+
+    let mut entries = BTreeMap::new();
+    let mut valid = Vec::new();
+    for wallet in wallets {
+        if entries.contains_key(&wallet) || valid.contains(&wallet) {
+            continue;
+        }
+        match ApolloValidator::validate_pubkey(&wallet) {
+            Ok(_) => valid.push(wallet),
+            Err(error) => {
+                entries.insert(wallet, BatchProfileEntry::Error { error: error.to_string() });
+            }
+        }
+    }
+
+    if !valid.is_empty() {
+        metrics.record_database_query();
+        let mut users: std::collections::HashMap<String, db::User> = db::get_users(&db, &valid).await?
+            .into_iter()
+            .map(|user| (user.wallet_pubkey.clone(), user))
+            .collect();
+        for wallet in valid {
+            let profile = match users.remove(&wallet) {
+                Some(user) if user.is_public || requester.as_deref() == Some(wallet.as_str()) => {
+                    ProfileResponse::from_user(user)
+                }
+                Some(_) => ProfileResponse::private(wallet.clone()),
+                None => ProfileResponse::missing(wallet.clone()),
+            };
+            entries.insert(wallet, BatchProfileEntry::Profile(profile));
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "profiles": entries })))
 }
 
+/// Public profiles matching `q`, newest first; signed-in callers get the most followed first
 pub async fn search_profiles(
     db: web::Data<Database>,
     query: web::Query<SearchQuery>,
     _apollo: web::Data<ApolloValidator>,
     artemis: web::Data<ArtemisRateLimiter>,
+    ares: web::Data<AresAuth>,
     req: HttpRequest,
     metrics: web::Data<MetricsCollector>,
 ) -> ActixResult<HttpResponse, ShadowError> {
@@ -50,46 +207,226 @@ pub async fn search_profiles(
     let client_ip = req.peer_addr().map(|a| a.ip().to_string());
     let key = ArtemisRateLimiter::get_client_key(client_ip.as_deref(), None);
     artemis.check_rate_limit(&key)
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     // Validation
     ApolloValidator::validate_search_query(&query.q)?;
     let limit = ApolloValidator::validate_limit(query.limit)?;
 
+    let after = page_after(query.cursor.as_deref(), decode_cursor)?;
+
     metrics.record_database_query();
-    let users = db::search_users(&db, &query.q, limit)
-        .await?;
+    let (users, next_cursor) = if authenticate(&req, &ares).is_ok() {
+        db::search_users_by_followers(&db, &query.q, limit, after).await?
+    } else {
+        db::search_users(&db, &query.q, limit, after).await?
+    };
+    metrics.record_database_query();
+    let total = db::count_search_users(&db, &query.q).await?;
 
-    Ok(HttpResponse::Ok().json(users))
+    Ok(HttpResponse::Ok().json(search_page_body(users, next_cursor, total)))
 }
 
+/// Profile by wallet pubkey or by username, resolved through its on-chain record
 pub async fn get_profile(
     db: web::Data<Database>,
     path: web::Path<String>,
+    anchor: web::Data<anchor_client::AnchorClient>,
     metrics: web::Data<MetricsCollector>,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let wallet = path.into_inner();
+    let id = path.into_inner();
+    // Anything that parses as a pubkey is taken as a wallet, never as a username
+    let wallet = if ApolloValidator::validate_pubkey(&id).is_ok() {
+        id
+    } else {
+        let name = ApolloValidator::validate_username(&id)?;
+        metrics.record_solana_rpc();
+        match anchor.resolve_username(&name).await.map_err(ShadowError::Solana)? {
+            Some(record) => record.wallet.to_string(),
+            None => return Err(ShadowError::NotFound(format!("Username {} is not claimed", name))),
+        }
+    };
     
     metrics.record_database_query();
     match db::get_user(&db, &wallet).await? {
-        Some(user) => {
-            Ok(HttpResponse::Ok().json(ProfileResponse {
-                wallet_pubkey: user.wallet_pubkey.clone(),
-                profile_cid: user.profile_cid.clone(),
-                is_public: user.is_public,
-                exists: true,
-            }))
+        Some(user) => Ok(HttpResponse::Ok().json(ProfileResponse::from_user(user))),
+        // Return exists: false if not found, but don't error
+        None => Ok(HttpResponse::Ok().json(ProfileResponse::missing(wallet))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PageQuery {
+    #[serde(default)]
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// One side of a follow, as listed on a profile
+#[derive(Serialize)]
+struct FollowEntry {
+    wallet: String,
+    followed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// The follow as the profiles program records it, for the `follows` mirror
+fn follow_record(follow: &anchor_client::FollowAccount) -> db::Follow {
+    db::Follow::new(
+        &follow.follower.to_string(),
+        &follow.followee.to_string(),
+        chrono::DateTime::from_timestamp(follow.created_at, 0).unwrap_or_default(),
+    )
+}
+
+/// A `follow_profile` transaction for the authenticated caller to sign and send. Once it
+/// has landed, calling again records the follow in the `follows` mirror and copies both
+/// profiles' follow counts from chain; follows live on-chain and the mirror only serves reads.
+pub async fn follow_profile(
+    db: web::Data<Database>,
+    anchor: web::Data<anchor_client::AnchorClient>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<String>,
+    req: HttpRequest,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let followee = path.into_inner();
+    ApolloValidator::validate_pubkey(&followee)?;
+    let follower = authenticate(&req, &ares)?;
+    if follower == followee {
+        return Err(ShadowError::BadRequest("Cannot follow yourself".to_string()));
+    }
+
+    metrics.record_solana_rpc();
+    let follower_profile = anchor.verify_profile(&follower).await.map_err(ShadowError::Solana)?
+        .ok_or_else(|| ShadowError::BadRequest("Create a profile before following others".to_string()))?;
+    metrics.record_solana_rpc();
+    let followee_profile = anchor.verify_profile(&followee).await.map_err(ShadowError::Solana)?
+        .ok_or_else(|| ShadowError::NotFound("Profile not found".to_string()))?;
+
+    let (follower_key, followee_key) = (follower_profile.wallet, followee_profile.wallet);
+    let follow_pda = anchor.follow_pda(&follower_key, &followee_key).to_string();
+    metrics.record_solana_rpc();
+    if let Some(follow) = anchor.get_follow(&follower, &followee).await.map_err(ShadowError::Solana)? {
+        let record = follow_record(&follow);
+        metrics.record_database_query();
+        db::follow_user(&db, &record.follower, &record.followee, record.created_at).await?;
+        for profile in [&follower_profile, &followee_profile] {
+            metrics.record_database_query();
+            db::set_follow_counts(&db, &profile.wallet.to_string(), profile.follower_count, profile.following_count).await?;
         }
-        None => {
-            // Return exists: false if not found, but don't error
-            Ok(HttpResponse::Ok().json(ProfileResponse {
-                wallet_pubkey: wallet,
-                profile_cid: None,
-                is_public: false,
-                exists: false,
-            }))
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "follower": follower,
+            "followee": followee,
+            "follow": follow_pda,
+            "following": true,
+        })));
+    }
+
+    metrics.record_solana_rpc();
+    let transaction = anchor
+        .unsigned_transaction(anchor.follow_instruction(&follower_key, &followee_key), &follower_key)
+        .await
+        .map_err(ShadowError::Solana)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "follower": follower,
+        "followee": followee,
+        "follow": follow_pda,
+        "transaction": transaction,
+    })))
+}
+
+/// An `unfollow_profile` transaction for the authenticated caller to sign and send. Once it
+/// has landed, calling again drops the follow from the mirror and refreshes both counts.
+pub async fn unfollow_profile(
+    db: web::Data<Database>,
+    anchor: web::Data<anchor_client::AnchorClient>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<String>,
+    req: HttpRequest,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let followee = path.into_inner();
+    let followee_key = ApolloValidator::validate_pubkey(&followee)?;
+    let follower = authenticate(&req, &ares)?;
+    let follower_key = ApolloValidator::validate_pubkey(&follower)?;
+    let follow_pda = anchor.follow_pda(&follower_key, &followee_key).to_string();
+
+    metrics.record_solana_rpc();
+    if anchor.get_follow(&follower, &followee).await.map_err(ShadowError::Solana)?.is_some() {
+        metrics.record_solana_rpc();
+        let transaction = anchor
+            .unsigned_transaction(anchor.unfollow_instruction(&follower_key, &followee_key), &follower_key)
+            .await
+            .map_err(ShadowError::Solana)?;
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "follower": follower,
+            "followee": followee,
+            "follow": follow_pda,
+            "transaction": transaction,
+        })));
+    }
+
+    metrics.record_database_query();
+    if !db::unfollow_user(&db, &follower, &followee).await? {
+        return Err(ShadowError::NotFound("Not following this profile".to_string()));
+    }
+    for wallet in [&follower, &followee] {
+        metrics.record_solana_rpc();
+        // A followee who has deleted their profile has no counts left to copy
+        if let Some(profile) = anchor.verify_profile(wallet).await.map_err(ShadowError::Solana)? {
+            metrics.record_database_query();
+            db::set_follow_counts(&db, wallet, profile.follower_count, profile.following_count).await?;
         }
     }
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "follower": follower,
+        "followee": followee,
+        "follow": follow_pda,
+        "following": false,
+    })))
+}
+
+/// Wallets following `wallet`, newest first, from the `follows` mirror that
+/// `follow_profile` and `unfollow_profile` keep once transactions land
+pub async fn get_followers(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    query: web::Query<PageQuery>,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = path.into_inner();
+    ApolloValidator::validate_pubkey(&wallet)?;
+    let limit = ApolloValidator::validate_limit(query.limit)?;
+    let after = page_after(query.cursor.as_deref(), decode_cursor)?;
+
+    metrics.record_database_query();
+    let (follows, next_cursor) = db::list_followers(&db, &wallet, limit, after).await?;
+    let followers: Vec<_> = follows.into_iter()
+        .map(|follow| FollowEntry { wallet: follow.follower, followed_at: follow.created_at })
+        .collect();
+    Ok(HttpResponse::Ok().json(page_body(followers, next_cursor)))
+}
+
+/// Wallets `wallet` follows, newest first, from the same mirror as `get_followers`
+pub async fn get_following(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    query: web::Query<PageQuery>,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = path.into_inner();
+    ApolloValidator::validate_pubkey(&wallet)?;
+    let limit = ApolloValidator::validate_limit(query.limit)?;
+    let after = page_after(query.cursor.as_deref(), decode_cursor)?;
+
+    metrics.record_database_query();
+    let (follows, next_cursor) = db::list_following(&db, &wallet, limit, after).await?;
+    let following: Vec<_> = follows.into_iter()
+        .map(|follow| FollowEntry { wallet: follow.followee, followed_at: follow.created_at })
+        .collect();
+    Ok(HttpResponse::Ok().json(page_body(following, next_cursor)))
 }
 
 pub async fn create_profile_route(
@@ -104,17 +441,13 @@ pub async fn create_profile_route(
     
     // Validate CID
     ApolloValidator::validate_ipfs_cid(&body.profile_cid)?;
+    let display_name = validate_profile_display(body.display_name.as_deref(), body.avatar.as_deref())?;
 
     // Verify authentication
-    if let Some(auth_header) = req.headers().get("X-Shadow-Auth") {
-        if let Ok(auth_str) = auth_header.to_str() {
-            if let Ok(auth) = AuthHeader::from_header(auth_str) {
-                if auth.wallet != body.wallet {
-                    return Err(ShadowError::Unauthorized.into());
-                }
-                auth.verify(&ares)
-                    .map_err(|_e| ShadowError::Unauthorized)?;
-            }
+    if req.headers().contains_key("X-Shadow-Auth") || req.headers().contains_key("Authorization") {
+        let caller = authenticate(&req, &ares)?;
+        if caller != body.wallet {
+            return Err(ShadowError::Forbidden("Wallet does not match".to_string()));
         }
     }
 
@@ -124,6 +457,7 @@ pub async fn create_profile_route(
         Some(&body.profile_cid),
         body.is_public,
     ).await?;
+    db::set_user_display(&db, &body.wallet, display_name.as_deref(), body.avatar.as_deref()).await?;
 
     Ok(HttpResponse::Created().json(serde_json::json!({
         "success": true,
@@ -131,12 +465,15 @@ pub async fn create_profile_route(
     })))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn update_profile(
     db: web::Data<Database>,
     path: web::Path<String>,
     body: web::Json<UpdateProfileRequest>,
     ares: web::Data<AresAuth>,
     _apollo: web::Data<ApolloValidator>,
+    anchor: web::Data<anchor_client::AnchorClient>,
+    metrics: web::Data<MetricsCollector>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let wallet = path.into_inner();
@@ -145,18 +482,18 @@ pub async fn update_profile(
     ApolloValidator::validate_pubkey(&wallet)?;
 
     // Verify authentication - must own the profile
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    if auth.wallet != wallet {
-        return Err(ShadowError::Unauthorized.into());
+    let caller = authenticate(&req, &ares)?;
+    if caller != wallet {
+        return Err(ShadowError::Forbidden("Wallet does not match".to_string()));
+    }
+
+    // A profile deleted on-chain is dropped here too, so later reads see exists: false
+    metrics.record_solana_rpc();
+    if anchor.verify_profile(&wallet).await.map_err(ShadowError::Solana)?.is_none() {
+        metrics.record_database_query();
+        db::delete_user(&db, &wallet).await?;
+        return Ok(HttpResponse::Ok().json(ProfileResponse::missing(wallet)));
     }
-    
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
     
     let user = db::get_user(&db, &wallet).await?
         .ok_or_else(|| ShadowError::NotFound("Profile not found".to_string()))?;
@@ -165,22 +502,84 @@ pub async fn update_profile(
     if let Some(ref cid) = body.profile_cid {
         ApolloValidator::validate_ipfs_cid(cid)?;
     }
+    let display_name = validate_profile_display(body.display_name.as_deref(), body.avatar.as_deref())?;
 
-    let profile_cid = body.profile_cid.as_ref().map(|s| s.as_str()).or(user.profile_cid.as_deref());
+    let profile_cid = body.profile_cid.as_deref().or(user.profile_cid.as_deref());
     let is_public = body.is_public.unwrap_or(user.is_public);
 
     db::create_or_update_user(&db, &wallet, profile_cid, is_public).await?;
+    db::set_user_display(&db, &wallet, display_name.as_deref(), body.avatar.as_deref()).await?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true
     })))
 }
 
+/// Drop a profile the wallet has closed on-chain with `delete_profile`, which releases its
+/// username and follows in the same transaction. The backend can't sign for the wallet, so
+/// this only clears the record once the account is gone.
+pub async fn delete_profile(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    ares: web::Data<AresAuth>,
+    anchor: web::Data<anchor_client::AnchorClient>,
+    metrics: web::Data<MetricsCollector>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = path.into_inner();
+    ApolloValidator::validate_pubkey(&wallet)?;
+
+    let caller = authenticate(&req, &ares)?;
+    if caller != wallet {
+        return Err(ShadowError::Forbidden("Wallet does not match".to_string()));
+    }
+
+    metrics.record_solana_rpc();
+    match anchor.delete_profile_account(&wallet).await {
+        Ok(()) => {}
+        Err(e) if e == anchor_client::PROFILE_STILL_OPEN => {
+            return Err(ShadowError::Conflict(
+                "Profile account still exists; sign delete_profile with the wallet first".to_string(),
+            ));
+        }
+        Err(e) => return Err(ShadowError::Solana(e)),
+    }
+    metrics.record_database_query();
+    db::delete_user(&db, &wallet).await?;
+    Ok(HttpResponse::Ok().json(ProfileResponse::missing(wallet)))
+}
+
 #[derive(Deserialize)]
 pub struct SearchQuery {
     pub q: String,
     #[serde(default)]
     pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Result order; only content search honors it
+    #[serde(default)]
+    pub sort: SearchSort,
+}
+
+/// Decode a list endpoint's `cursor` parameter; a malformed cursor is the caller's mistake
+fn page_after<T>(cursor: Option<&str>, decode: fn(&str) -> Result<T, String>) -> Result<Option<T>, ShadowError> {
+    cursor.map(decode).transpose().map_err(ShadowError::BadRequest)
+}
+
+/// Body for one page of a cursor-paginated list
+fn page_body<T: Serialize>(items: Vec<T>, next_cursor: Option<String>) -> serde_json::Value {
+    serde_json::json!({
+        "items": items,
+        "next_cursor": next_cursor,
+    })
+}
+
+/// `page_body` for a search, with how many results there are across all pages
+fn search_page_body<T: Serialize>(items: Vec<T>, next_cursor: Option<String>, total: u64) -> serde_json::Value {
+    let mut body = page_body(items, next_cursor);
+    body["total"] = total.into();
+    body
 }
 
 #[derive(Deserialize)]
@@ -189,6 +588,24 @@ pub struct RegisterSiteRequest {
     pub storage_cid: String,
     pub name: Option<String>,
     pub description: Option<String>,
+    /// Language tag -> entry file, from the languages map in shadow.json
+    #[serde(default)]
+    pub languages: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    pub default_language: Option<String>,
+}
+
+impl RegisterSiteRequest {
+    fn validate_languages(&self) -> Result<(), ShadowError> {
+        match &self.languages {
+            Some(languages) => ApolloValidator::validate_languages(languages, self.default_language.as_deref())?,
+            None if self.default_language.is_some() => {
+                return Err(ShadowError::BadRequest("default_language requires a languages map".to_string()));
+            }
+            None => {}
+        }
+        Ok(())
+    }
 }
 
 pub async fn search_sites(
@@ -196,15 +613,21 @@ pub async fn search_sites(
     query: web::Query<SearchQuery>,
     metrics: web::Data<MetricsCollector>,
 ) -> ActixResult<HttpResponse, ShadowError> {
+    let limit = ApolloValidator::validate_limit(query.limit)?;
+    let after = page_after(query.cursor.as_deref(), decode_cursor)?;
+
     metrics.record_database_query();
-    let sites = db::search_sites(&db, &query.q, query.limit.unwrap_or(10))
+    let (sites, next_cursor) = db::search_sites(&db, &query.q, limit, after)
         .await?;
+    metrics.record_database_query();
+    let total = db::count_search_sites(&db, &query.q).await?;
 
-    Ok(HttpResponse::Ok().json(sites))
+    Ok(HttpResponse::Ok().json(search_page_body(sites, next_cursor, total)))
 }
 
 pub async fn get_site(
     db: web::Data<Database>,
+    tyche: web::Data<TycheOwnership>,
     path: web::Path<String>,
     metrics: web::Data<MetricsCollector>,
 ) -> ActixResult<HttpResponse, ShadowError> {
@@ -214,9 +637,13 @@ pub async fn get_site(
     let site = db::get_site(&db, &program_address).await?
         .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
 
-    Ok(HttpResponse::Ok().json(site))
+    let mut body = serde_json::to_value(&site)
+        .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+    body["controller"] = serde_json::json!(tyche.controller(&site).await);
+    Ok(HttpResponse::Ok().json(body))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn register_site(
     db: web::Data<Database>,
     body: web::Json<RegisterSiteRequest>,
@@ -225,25 +652,18 @@ pub async fn register_site(
     _apollo: web::Data<ApolloValidator>,
     req: HttpRequest,
     anchor: web::Data<anchor_client::AnchorClient>,
+    tyche: web::Data<TycheOwnership>,
     metrics: web::Data<MetricsCollector>,
+    mnemosyne: web::Data<Mnemosyne>,
+    hephaestus: web::Data<HephaestusCache>,
 ) -> ActixResult<HttpResponse, ShadowError> {
     // Validate inputs
     ApolloValidator::validate_pubkey(&body.owner_pubkey)?;
-    ApolloValidator::validate_ipfs_cid(&body.storage_cid)?;
+    ApolloValidator::validate_storage_cid(&body.storage_cid)?;
+    body.validate_languages()?;
 
-    // Verify authentication
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    if auth.wallet != body.owner_pubkey {
-        return Err(ShadowError::Unauthorized.into());
-    }
-    
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
+    // Verify authentication; wallets other than the owner are checked against the site's editors and collaborators below
+    let caller = authenticate(&req, &ares)?;
 
     // Verify program address exists on-chain and is registered with registry program
     let solana_client = SolanaClient::new(solana_rpc.to_string());
@@ -251,139 +671,1456 @@ pub async fn register_site(
     
     let program_address = match solana_client.search_program(&body.owner_pubkey) {
         Ok(Some(_)) => body.owner_pubkey.clone(),
-        _ => return Err(ShadowError::BadRequest("Program address not found on-chain".to_string()).into()),
+        _ => return Err(ShadowError::BadRequest("Program address not found on-chain".to_string())),
     };
     
-    // Verify on-chain registration using Anchor client
-    if let Ok(Some(site_account)) = anchor.verify_site_registration(&program_address, &body.owner_pubkey) {
-        // Site is registered on-chain, verify ownership matches
-        if site_account.owner.to_string() != body.owner_pubkey {
-            return Err(ShadowError::Unauthorized.into());
-        }
-    } else {
-        // Site not found in registry - still allow registration but log it
-        // In production, you might want to require on-chain registration first
+    // Verify on-chain registration using Anchor client. A site not found in the registry may
+    // still register; in production, you might want to require on-chain registration first
+    let on_chain = anchor.get_site(&program_address).await.ok().flatten();
+    // Site is registered on-chain, verify ownership matches
+    if on_chain.as_ref().is_some_and(|site_account| site_account.owner.to_string() != body.owner_pubkey) {
+        return Err(ShadowError::Unauthorized);
     }
-    
-    db::create_or_update_site(
-        &db,
-        &program_address,
+    let registered_at = on_chain.as_ref()
+        .and_then(|site_account| chrono::DateTime::from_timestamp(site_account.created_at, 0));
+
+    metrics.record_database_query();
+    let current = db::get_site(&db, &program_address).await?;
+
+    // The owner and the registry's editors may register; anyone else needs the editor role on the
+    // record. Off-chain the record's owner stands, so a collaborator can't hand the site to another wallet.
+    let listed = caller == body.owner_pubkey
+        || on_chain.as_ref().is_some_and(|site_account| site_account.editors.iter().any(|editor| editor.to_string() == caller));
+    if !listed {
+        let site = current.as_ref()
+            .filter(|site| on_chain.is_some() || site.owner_pubkey == body.owner_pubkey)
+            .ok_or_else(|| ShadowError::Forbidden("Wallet does not match".to_string()))?;
+        let site = db::Site { owner_pubkey: body.owner_pubkey.clone(), ..site.clone() };
+        tyche.authorize(&site, &caller, SiteAction::UpdateContent).await?;
+    }
+
+    // A site deleted on-chain and registered again starts over instead of keeping the old record's age
+    let reregistered_at = registered_at
+        .filter(|at| current.as_ref().is_some_and(|site| site.created_at < *at));
+
+    write_site(&mnemosyne, &hephaestus, &program_address, &body, current.as_ref(), reregistered_at).await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "success": true,
+        "program_address": program_address
+    })))
+}
+
+/// Upsert a site and queue its index/subscriber side effects in the same write.
+/// `reregistered_at` restarts the record's timestamps for a site registered on-chain again.
+/// Registering over `current` with other content or languages drops what was cached from it.
+async fn write_site(
+    mnemosyne: &Mnemosyne,
+    hephaestus: &HephaestusCache,
+    program_address: &str,
+    body: &RegisterSiteRequest,
+    current: Option<&db::Site>,
+    reregistered_at: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<(), ShadowError> {
+    let mut update = db::site_upsert_update(
         &body.owner_pubkey,
         &body.storage_cid,
         body.name.as_deref(),
         body.description.as_deref(),
-    ).await?;
+        body.languages.as_ref(),
+        body.default_language.as_deref(),
+    ).map_err(|e| ShadowError::BadRequest(format!("Invalid site: {}", e)))?;
+    if let Some(at) = reregistered_at {
+        db::restart_site_timestamps(&mut update, at);
+    }
 
-    Ok(HttpResponse::Created().json(serde_json::json!({
-        "success": true,
-        "program_address": program_address
+    let primary = PrimaryWrite {
+        collection: "sites".to_string(),
+        filter: doc! { "_id": program_address },
+        update,
+        upsert: true,
+    };
+    mnemosyne.write(primary, OutboxPayload::SiteUpserted { program_address: program_address.to_string() }).await
+        .map_err(ShadowError::BadRequest)?;
+    let default_language = body.languages.as_ref().and(body.default_language.as_deref());
+    if let Some(old) = current.filter(|old| !serves_same_files(old, &body.storage_cid, body.languages.as_ref(), default_language)) {
+        invalidate_site_content(hephaestus, program_address, &old.storage_cid).await;
+    }
+    Ok(())
+}
+
+/// Whether `old` would serve the same files as a site with this content and languages map;
+/// a language pointing at another entry file changes what is served from the same CID
+fn serves_same_files(
+    old: &db::Site,
+    storage_cid: &str,
+    languages: Option<&BTreeMap<String, String>>,
+    default_language: Option<&str>,
+) -> bool {
+    old.storage_cid == storage_cid
+        && old.languages.as_ref() == languages
+        && old.default_language.as_deref() == default_language
+}
+
+/// Drop what was cached from a site's replaced content: its entry file in every language,
+/// keyed by the old CID, and the files served from it, keyed by the program
+async fn invalidate_site_content(hephaestus: &HephaestusCache, program_address: &str, old_cid: &str) {
+    hephaestus.invalidate_pattern(&HephaestusCache::site_content_key(old_cid, None)).await;
+    hephaestus.invalidate_pattern(&HephaestusCache::site_asset_key(program_address, "")).await;
+}
+
+/// Full replacement kept for older clients; shares the PATCH write path without a precondition
+#[allow(clippy::too_many_arguments)]
+pub async fn update_site(
+    db: web::Data<Database>,
+    mnemosyne: web::Data<Mnemosyne>,
+    hephaestus: web::Data<HephaestusCache>,
+    ares: web::Data<AresAuth>,
+    anchor: web::Data<anchor_client::AnchorClient>,
+    tyche: web::Data<TycheOwnership>,
+    metrics: web::Data<MetricsCollector>,
+    path: web::Path<String>,
+    body: web::Json<RegisterSiteRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    ApolloValidator::validate_storage_cid(&body.storage_cid)?;
+    body.validate_languages()?;
+
+    let caller = authenticate(&req, &ares)?;
+    metrics.record_database_query();
+    let current = db::get_site(&db, &program_address).await?;
+    if current.as_ref().is_some_and(|site| site.ownership_mode == db::OwnershipMode::Nft) {
+        return Err(ShadowError::Forbidden("Site is controlled by an NFT; update it with PATCH".to_string()));
+    }
+
+    // Tyche checks the caller against the registry's owner and editors for a site on-chain, so a
+    // transfer or a removed editor takes effect at once, and against the record's collaborators;
+    // `owner_pubkey` in the body is never trusted. The record's owner follows the registry.
+    // A site with no record yet can only be written by the registry's owner or an editor, and
+    // PUT never creates a site nobody registered.
+    let owner = match &current {
+        Some(site) => {
+            metrics.record_solana_rpc();
+            tyche.authorize(site, &caller, SiteAction::UpdateContent).await?;
+            tyche.wallet_owner(site).await?
+        }
+        None => {
+            metrics.record_solana_rpc();
+            let caller_key = ApolloValidator::validate_pubkey(&caller)?;
+            match anchor.get_site(&program_address).await.map_err(ShadowError::Solana)? {
+                Some(site) if site.can_edit(&caller_key) => site.owner.to_string(),
+                Some(_) => return Err(ShadowError::Forbidden("Wallet is not an editor of this site".to_string())),
+                None => return Err(ShadowError::NotFound("Site not found".to_string())),
+            }
+        }
+    };
+
+    let update = db::site_upsert_update(
+        &owner,
+        &body.storage_cid,
+        body.name.as_deref(),
+        body.description.as_deref(),
+        body.languages.as_ref(),
+        body.default_language.as_deref(),
+    ).map_err(|e| ShadowError::BadRequest(format!("Invalid site: {}", e)))?;
+
+    match commit_site_update(
+        &db, &mnemosyne, &hephaestus, &program_address,
+        current.as_ref(), update, None, caller,
+    ).await? {
+        SiteWriteOutcome::Applied(_) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "success": true
+        }))),
+        SiteWriteOutcome::Conflict(_) => Err(ShadowError::Conflict("Site was modified concurrently".to_string())),
+    }
+}
+
+/// Partial site update. Fields left out (or null) stay as they are; an empty string
+/// clears name, description or primary_domain.
+#[derive(Deserialize)]
+pub struct PatchSiteRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub storage_cid: Option<String>,
+    pub primary_domain: Option<String>,
+    pub access_policy: Option<db::AccessPolicy>,
+    pub noindex: Option<bool>,
+    /// Precondition: the `revision` the caller last read
+    pub expected_revision: Option<i64>,
+    /// Precondition: the `updated_at` the caller last read
+    pub expected_updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+enum SiteWriteOutcome {
+    Applied(db::Site),
+    /// The precondition no longer held; carries the site as it is now
+    Conflict(Option<db::Site>),
+}
+
+/// The single write path behind PUT and PATCH. With `expected_revision` the update only
+/// lands while the site is still at that revision; without it the site is upserted.
+/// Index sync, the version record for a new CID and the Hermes event go through the
+/// outbox, so they follow exactly the writes that landed.
+#[allow(clippy::too_many_arguments)]
+async fn commit_site_update(
+    db: &Database,
+    mnemosyne: &Mnemosyne,
+    hephaestus: &HephaestusCache,
+    program_address: &str,
+    current: Option<&db::Site>,
+    update: mongodb::bson::Document,
+    expected_revision: Option<i64>,
+    updated_by: String,
+) -> Result<SiteWriteOutcome, ShadowError> {
+    let new_cid = update.get_document("$set").ok()
+        .and_then(|set| set.get_str("storage_cid").ok())
+        .filter(|cid| current.map(|site| site.storage_cid.as_str()) != Some(*cid))
+        .map(str::to_string);
+    let (filter, upsert) = match expected_revision {
+        Some(revision) => (db::site_revision_filter(program_address, revision), false),
+        None => (doc! { "_id": program_address }, true),
+    };
+
+    let primary = PrimaryWrite {
+        collection: "sites".to_string(),
+        filter,
+        update,
+        upsert,
+    };
+    let payload = OutboxPayload::SiteUpdated {
+        program_address: program_address.to_string(),
+        revision: current.map(|site| site.revision).unwrap_or(0) + 1,
+        storage_cid: new_cid.clone(),
+        updated_by,
+    };
+    let written = mnemosyne.write_if_matched(primary, payload).await
+        .map_err(ShadowError::BadRequest)?;
+
+    let site = db::get_site(db, program_address).await?;
+    if written.is_none() {
+        return Ok(SiteWriteOutcome::Conflict(site));
+    }
+    // Renames and the like leave the cached content alone
+    if let (Some(old), Some(new)) = (current, &site) {
+        if !serves_same_files(old, &new.storage_cid, new.languages.as_ref(), new.default_language.as_deref()) {
+            invalidate_site_content(hephaestus, program_address, &old.storage_cid).await;
+        }
+    }
+    site.map(SiteWriteOutcome::Applied)
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))
+}
+
+fn site_conflict(current: Option<db::Site>) -> HttpResponse {
+    HttpResponse::Conflict().json(serde_json::json!({
+        "error": "Site was modified since expected_revision/expected_updated_at",
+        "current": current,
+    }))
+}
+
+/// PATCH /sites/{program_address}: atomic partial update guarded by an optimistic-concurrency
+/// precondition. Editors may change content and metadata; primary_domain needs an admin. A stale precondition gets 409 with the current site.
+#[allow(clippy::too_many_arguments)]
+pub async fn patch_site(
+    db: web::Data<Database>,
+    mnemosyne: web::Data<Mnemosyne>,
+    hephaestus: web::Data<HephaestusCache>,
+    olympus: web::Data<OlympusCA>,
+    tyche: web::Data<TycheOwnership>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<String>,
+    body: web::Json<PatchSiteRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    if body.expected_revision.is_none() && body.expected_updated_at.is_none() {
+        return Err(ShadowError::BadRequest("expected_revision or expected_updated_at is required".to_string()));
+    }
+
+    let mut set = doc! {};
+    let mut unset = doc! {};
+    for (field, value, max_length) in [("name", &body.name, 100), ("description", &body.description, 500)] {
+        match value.as_deref().map(str::trim) {
+            Some("") => { set.insert(field, mongodb::bson::Bson::Null); }
+            Some(value) => {
+                let value = ApolloValidator::sanitize_string(value, max_length)
+                    .map_err(|e| ShadowError::BadRequest(format!("{}: {}", field, e)))?;
+                set.insert(field, value);
+            }
+            None => {}
+        }
+    }
+    if let Some(cid) = &body.storage_cid {
+        ApolloValidator::validate_storage_cid(cid)?;
+        set.insert("storage_cid", cid);
+    }
+    if let Some(policy) = body.access_policy {
+        set.insert("access_policy", policy.as_str());
+    }
+    if let Some(noindex) = body.noindex {
+        set.insert("noindex", noindex);
+    }
+
+    let caller = authenticate(&req, &ares)?;
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    let action = if body.primary_domain.is_some() {
+        SiteAction::ManageDomains
+    } else if body.storage_cid.is_some() {
+        SiteAction::UpdateContent
+    } else {
+        SiteAction::UpdateMetadata
+    };
+    tyche.authorize(&site, &caller, action).await?;
+
+    match body.primary_domain.as_deref().map(|d| d.trim().to_lowercase()) {
+        Some(domain) if domain.is_empty() => { unset.insert("primary_domain", ""); }
+        Some(domain) => {
+            let record = olympus.get_domain(&domain).await.map_err(ShadowError::BadRequest)?;
+            if record.map(|r| r.program_address) != Some(program_address.clone()) {
+                return Err(ShadowError::BadRequest("primary_domain must be a domain pointing at this site".to_string()));
+            }
+            set.insert("primary_domain", domain);
+        }
+        None => {}
+    }
+    if set.is_empty() && unset.is_empty() {
+        return Err(ShadowError::BadRequest("No fields to update".to_string()));
+    }
+
+    let fresh = body.expected_revision.is_none_or(|revision| revision == site.revision)
+        && body.expected_updated_at.is_none_or(|at| at.timestamp_millis() == site.updated_at.timestamp_millis());
+    if !fresh {
+        return Ok(site_conflict(Some(site)));
+    }
+
+    set.insert("updated_at", mongodb::bson::DateTime::now());
+    let mut update = doc! { "$set": set, "$inc": { "revision": 1_i64 } };
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+
+    match commit_site_update(
+        &db, &mnemosyne, &hephaestus, &program_address,
+        Some(&site), update, Some(site.revision), caller,
+    ).await? {
+        SiteWriteOutcome::Applied(site) => Ok(HttpResponse::Ok().json(site)),
+        SiteWriteOutcome::Conflict(current) => Ok(site_conflict(current)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StagedDeployRequest {
+    pub storage_cid: String,
+    /// Skip the health checks and go live immediately (emergencies only)
+    #[serde(default)]
+    pub force: bool,
+    /// Also require index.html to parse, on top of DEPLOY_HTML_CHECK
+    #[serde(default)]
+    pub html_check: bool,
+}
+
+/// Health-check new content and only switch the live storage CID when it passes.
+/// Failed bundles stay reachable as the site's preview.
+#[allow(clippy::too_many_arguments)]
+pub async fn stage_deploy(
+    db: web::Data<Database>,
+    mnemosyne: web::Data<Mnemosyne>,
+    hephaestus: web::Data<HephaestusCache>,
+    config: web::Data<ShadowConfig>,
+    ares: web::Data<AresAuth>,
+    tyche: web::Data<TycheOwnership>,
+    path: web::Path<String>,
+    body: web::Json<StagedDeployRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    ApolloValidator::validate_storage_cid(&body.storage_cid)?;
+
+    let caller = authenticate(&req, &ares)?;
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    tyche.authorize(&site, &caller, SiteAction::UpdateContent).await?;
+
+    let (_, response) = deploy_site_content(
+        &db, &mnemosyne, &hephaestus, &config, &site,
+        &body.storage_cid, body.force, body.html_check, caller,
+    ).await?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Shared tail of the owner and deploy-token deploy paths: run the health checks,
+/// flip the live pointer or park the bundle as a preview, and record the version
+#[allow(clippy::too_many_arguments)]
+async fn deploy_site_content(
+    db: &Database,
+    mnemosyne: &Mnemosyne,
+    hephaestus: &HephaestusCache,
+    config: &ShadowConfig,
+    site: &db::Site,
+    storage_cid: &str,
+    force: bool,
+    html_check: bool,
+    deployed_by: String,
+) -> Result<(SiteVersion, serde_json::Value), ShadowError> {
+    let program_address = site.program_address.clone();
+    let report = if force {
+        tracing::warn!("Forced deploy of {} to {} skipped health checks", storage_cid, program_address);
+        DeployReport::forced()
+    } else {
+        AsclepiusChecker::new(config.deploy.clone()).check(storage_cid, html_check).await
+    };
+
+    let version_id = uuid::Uuid::new_v4().to_string();
+    let status = if report.passed {
+        let primary = PrimaryWrite {
+            collection: "sites".to_string(),
+            filter: doc! { "_id": &program_address },
+            update: doc! {
+                "$set": { "storage_cid": storage_cid, "updated_at": mongodb::bson::DateTime::now() },
+                "$unset": { "preview_cid": "" },
+                "$inc": { "revision": 1_i64 },
+            },
+            upsert: false,
+        };
+        let payload = OutboxPayload::SiteDeployed {
+            program_address: program_address.clone(),
+            storage_cid: storage_cid.to_string(),
+            version_id: version_id.clone(),
+        };
+        mnemosyne.write(primary, payload).await
+            .map_err(ShadowError::BadRequest)?;
+        if site.storage_cid != storage_cid {
+            invalidate_site_content(hephaestus, &program_address, &site.storage_cid).await;
+        }
+        VersionStatus::Live
+    } else {
+        db.collection::<mongodb::bson::Document>("sites")
+            .update_one(doc! { "_id": &program_address }, doc! { "$set": { "preview_cid": storage_cid } }, None)
+            .await?;
+        VersionStatus::Preview
+    };
+
+    let version = SiteVersion {
+        id: version_id,
+        program_address: program_address.clone(),
+        storage_cid: storage_cid.to_string(),
+        status,
+        report,
+        deployed_by,
+        created_at: chrono::Utc::now(),
+    };
+    SiteVersions::new(db.clone()).record(&version).await
+        .map_err(ShadowError::BadRequest)?;
+
+    let live = status == VersionStatus::Live;
+    let response = serde_json::json!({
+        "version_id": version.id,
+        "live": live,
+        "storage_cid": if live { storage_cid } else { site.storage_cid.as_str() },
+        "preview_cid": if live { None } else { Some(storage_cid) },
+        "failures": version.report.failures(),
+        "report": version.report,
+    });
+    Ok((version, response))
+}
+
+#[derive(Deserialize)]
+pub struct SiteVersionsQuery {
+    pub limit: Option<i64>,
+}
+
+/// Staged deploys from the database, plus the versions published on-chain through the registry.
+/// `published` is null when the chain cannot be read, so deploy history still loads.
+pub async fn list_site_versions(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    query: web::Query<SiteVersionsQuery>,
+    anchor: web::Data<anchor_client::AnchorClient>,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    let limit = ApolloValidator::validate_limit(query.limit)?;
+
+    let versions = SiteVersions::new(db.get_ref().clone()).list(&program_address, limit).await
+        .map_err(ShadowError::BadRequest)?;
+
+    metrics.record_solana_rpc();
+    let published = match anchor.get_site_versions(&program_address).await {
+        Ok(published) => Some(published.unwrap_or_default().iter()
+            .map(|version| serde_json::json!({
+                "version": version.version,
+                "storage_cid": version.storage_cid,
+                "published_at": chrono::DateTime::from_timestamp(version.published_at, 0),
+            }))
+            .collect::<Vec<_>>()),
+        Err(e) => {
+            tracing::warn!("Could not read published versions of {}: {}", program_address, e);
+            None
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "program_address": program_address,
+        "versions": versions,
+        "published": published
+    })))
+}
+
+/// Load a site and check the caller may manage its deploy tokens; returns the caller's role
+async fn require_token_manager(
+    db: &Database,
+    tyche: &TycheOwnership,
+    program_address: &str,
+    caller: &str,
+) -> Result<db::SiteRole, ShadowError> {
+    let site = db::get_site(db, program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    tyche.authorize(&site, caller, SiteAction::ManageDeployTokens).await
+}
+
+#[derive(Deserialize)]
+pub struct CreateDeployTokenRequest {
+    pub name: String,
+    #[serde(default)]
+    pub expires_in_days: Option<u32>,
+    /// Token for a dev workflow; content fetched with it gets the live reload script
+    #[serde(default)]
+    pub dev: bool,
+}
+
+/// Mint a token that can only publish content to this one site, with the creator's access.
+/// The raw token is only shown here.
+pub async fn create_deploy_token(
+    db: web::Data<Database>,
+    ares: web::Data<AresAuth>,
+    tyche: web::Data<TycheOwnership>,
+    path: web::Path<String>,
+    body: web::Json<CreateDeployTokenRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    let name = body.name.trim();
+    if name.is_empty() || name.len() > 64 {
+        return Err(ShadowError::BadRequest("Token name must be 1-64 characters".to_string()));
+    }
+    if body.expires_in_days == Some(0) {
+        return Err(ShadowError::BadRequest("expires_in_days must be at least 1".to_string()));
+    }
+
+    let caller = authenticate(&req, &ares)?;
+    require_token_manager(&db, &tyche, &program_address, &caller).await?;
+
+    let expires_at = body.expires_in_days
+        .map(|days| chrono::Utc::now() + chrono::Duration::days(days as i64));
+    let (token, secret) = DeployTokens::new(db.get_ref().clone())
+        .create(&program_address, &caller, name, body.dev, expires_at).await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "token": secret,
+        "deploy_token": token,
+    })))
+}
+
+pub async fn list_deploy_tokens(
+    db: web::Data<Database>,
+    ares: web::Data<AresAuth>,
+    tyche: web::Data<TycheOwnership>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    let caller = authenticate(&req, &ares)?;
+    require_token_manager(&db, &tyche, &program_address, &caller).await?;
+
+    let tokens = DeployTokens::new(db.get_ref().clone()).list(&program_address).await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "program_address": program_address,
+        "deploy_tokens": tokens
+    })))
+}
+
+pub async fn revoke_deploy_token(
+    db: web::Data<Database>,
+    ares: web::Data<AresAuth>,
+    tyche: web::Data<TycheOwnership>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let (program_address, token_id) = path.into_inner();
+    let caller = authenticate(&req, &ares)?;
+    let role = require_token_manager(&db, &tyche, &program_address, &caller).await?;
+
+    // Editors can only revoke the tokens they created
+    let tokens = DeployTokens::new(db.get_ref().clone());
+    let token = tokens.get(&program_address, &token_id).await?
+        .ok_or_else(|| ShadowError::NotFound("Deploy token not found".to_string()))?;
+    if role < db::SiteRole::Admin && token.owner_pubkey != caller {
+        return Err(ShadowError::Forbidden("Editors can only revoke their own deploy tokens".to_string()));
+    }
+    let revoked = tokens.revoke(&program_address, &token_id).await
+        .map_err(ShadowError::BadRequest)?;
+    if !revoked {
+        return Err(ShadowError::NotFound("Deploy token not found".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "revoked": token_id
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct ContentFile {
+    /// Path inside the bundle, e.g. "index.html" or "assets/app.js"
+    pub path: String,
+    /// Base64-encoded file contents
+    pub content: String,
+}
+
+#[derive(Deserialize)]
+pub struct PublishContentRequest {
+    /// A bundle that was already uploaded
+    #[serde(default)]
+    pub storage_cid: Option<String>,
+    /// Files to pin as a new bundle instead
+    #[serde(default)]
+    pub files: Option<Vec<ContentFile>>,
+    #[serde(default)]
+    pub html_check: bool,
+}
+
+fn decode_content_files(files: &[ContentFile]) -> Result<Vec<(String, Vec<u8>)>, ShadowError> {
+    use base64::{Engine as _, engine::general_purpose};
+
+    if files.is_empty() {
+        return Err(ShadowError::BadRequest("files must not be empty".to_string()));
+    }
+    files.iter()
+        .map(|file| {
+            let path = file.path.trim_start_matches('/');
+            if path.is_empty() || path.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+                return Err(ShadowError::BadRequest(format!("Invalid file path: {}", file.path)));
+            }
+            let data = general_purpose::STANDARD.decode(&file.content)
+                .map_err(|e| ShadowError::BadRequest(format!("Invalid base64 for {}: {}", file.path, e)))?;
+            Ok((path.to_string(), data))
+        })
+        .collect()
+}
+
+/// CI entry point: publish new content with a deploy token from X-Shadow-Deploy-Token.
+/// The token can only run the staged deploy for its own site; forced deploys stay owner-only.
+#[allow(clippy::too_many_arguments)]
+pub async fn publish_site_content(
+    db: web::Data<Database>,
+    mnemosyne: web::Data<Mnemosyne>,
+    hephaestus: web::Data<HephaestusCache>,
+    pinata: web::Data<PinataStorage>,
+    config: web::Data<ShadowConfig>,
+    tyche: web::Data<TycheOwnership>,
+    path: web::Path<String>,
+    body: web::Json<PublishContentRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    let raw_token = req.headers().get(DEPLOY_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .ok_or(ShadowError::Unauthorized)?;
+
+    let tokens = DeployTokens::new(db.get_ref().clone());
+    let token = tokens.authorize(raw_token, &program_address).await?;
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    // The token carries its creator's access, so it dies with it
+    tyche.authorize(&site, &token.owner_pubkey, SiteAction::UpdateContent).await
+        .map_err(|_| ShadowError::Forbidden("The token's creator no longer has access to this site".to_string()))?;
+
+    let storage_cid = match (&body.storage_cid, &body.files) {
+        (Some(cid), None) => {
+            ApolloValidator::validate_storage_cid(cid)?;
+            cid.clone()
+        }
+        (None, Some(files)) => {
+            let files = decode_content_files(files)?;
+            pinata.upload_directory(&files, &program_address).await
+                .map_err(ShadowError::Storage)?
+        }
+        _ => return Err(ShadowError::BadRequest("Provide exactly one of storage_cid or files".to_string())),
+    };
+
+    let (version, response) = deploy_site_content(
+        &db, &mnemosyne, &hephaestus, &config, &site,
+        &storage_cid, false, body.html_check, format!("deploy-token:{}", token.id),
+    ).await?;
+
+    let detail = doc! { "storage_cid": &storage_cid, "version_id": &version.id, "live": response["live"].as_bool() };
+    if let Err(e) = tokens.record_use(&token, "publish_content", detail).await {
+        tracing::warn!("Failed to audit deploy token {}: {}", token.id, e);
+    }
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Whether to serve a page with the Hermes live reload script: `?dev=1`, or a dev
+/// deploy token for this site in X-Shadow-Deploy-Token
+async fn dev_reload_requested(db: &Database, req: &HttpRequest, program_address: &str, dev: Option<&str>) -> bool {
+    if matches!(dev, Some("1" | "true")) {
+        return true;
+    }
+    let Some(raw_token) = req.headers().get(DEPLOY_TOKEN_HEADER).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    DeployTokens::new(db.clone()).authorize(raw_token, program_address).await
+        .is_ok_and(|token| token.dev)
+}
+
+/// Finish an HTML response, injecting the live reload script in dev mode
+fn site_html_response(
+    mut response: actix_web::HttpResponseBuilder,
+    content: Vec<u8>,
+    program_address: &str,
+    dev: bool,
+    req: &HttpRequest,
+) -> HttpResponse {
+    if !dev {
+        return response.body(content);
+    }
+
+    let info = req.connection_info();
+    let ws_scheme = if info.scheme() == "https" { "wss" } else { "ws" };
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let page = websocket::inject_reload_script(
+        &content,
+        program_address,
+        &nonce,
+        &format!("{}://{}", ws_scheme, info.host()),
+    );
+    for policy in page.csp {
+        response.append_header((actix_web::http::header::CONTENT_SECURITY_POLICY, policy));
+    }
+    response
+        .insert_header((actix_web::http::header::CACHE_CONTROL, "no-store"))
+        .body(page.html)
+}
+
+#[derive(Deserialize)]
+pub struct SitePreviewQuery {
+    /// File inside the preview bundle; defaults to index.html
+    pub path: Option<String>,
+    /// `1` injects the live reload script into HTML
+    pub dev: Option<String>,
+}
+
+/// Serve the latest failed deploy's content for debugging, never the live site
+pub async fn get_site_preview(
+    db: web::Data<Database>,
+    config: web::Data<ShadowConfig>,
+    path: web::Path<String>,
+    query: web::Query<SitePreviewQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    let preview_cid = site.preview_cid
+        .ok_or_else(|| ShadowError::NotFound("Site has no preview".to_string()))?;
+
+    let file = query.path.as_deref().unwrap_or("index.html");
+    if file.split('/').any(|segment| segment == "..") {
+        return Err(ShadowError::BadRequest("Invalid preview path".to_string()));
+    }
+    let content = AsclepiusChecker::new(config.deploy.clone()).fetch(&preview_cid, file).await
+        .ok_or_else(|| ShadowError::NotFound(format!("{} not found in preview", file)))?;
+
+    let html = file.ends_with(".html");
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(if html { "text/html" } else { "application/octet-stream" })
+        .insert_header(("X-Shadow-Preview", preview_cid));
+    let dev = html && dev_reload_requested(&db, &req, &program_address, query.dev.as_deref()).await;
+    Ok(site_html_response(response, content, &program_address, dev, &req))
+}
+
+#[derive(Deserialize)]
+pub struct SiteContentQuery {
+    /// Explicit language choice; wins over Accept-Language
+    pub lang: Option<String>,
+    /// `1` injects the live reload script
+    pub dev: Option<String>,
+}
+
+/// Choose the language variant of a multi-language site for this request.
+/// Returns the language tag and its entry file, or None for single-language sites.
+fn negotiate_site_variant(
+    site: &db::Site,
+    accept_language: Option<&str>,
+    override_lang: Option<&str>,
+    fallback_language: &str,
+) -> Option<(String, String)> {
+    let languages = site.languages.as_ref().filter(|l| !l.is_empty())?;
+    let available: Vec<&str> = languages.keys().map(|k| k.as_str()).collect();
+
+    let default = site.default_language.as_deref()
+        .and_then(|d| available.iter().copied().find(|lang| *lang == d))
+        .or_else(|| available.iter().copied().find(|lang| lang.eq_ignore_ascii_case(fallback_language)))
+        .unwrap_or(available[0]);
+
+    let lang = iris::negotiate_language(&available, accept_language, override_lang, default);
+    Some((lang.to_string(), languages[lang].clone()))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn get_site_content(
+    db: web::Data<Database>,
+    pinata: web::Data<PinataStorage>,
+    bundlr: web::Data<BundlrStorage>,
+    hephaestus: web::Data<HephaestusCache>,
+    config: web::Data<ShadowConfig>,
+    path: web::Path<String>,
+    query: web::Query<SiteContentQuery>,
+    req: HttpRequest,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    
+    metrics.record_database_query();
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+
+    let accept_language = req.headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)
+        .and_then(|v| v.to_str().ok());
+    let variant = negotiate_site_variant(
+        &site,
+        accept_language,
+        query.lang.as_deref(),
+        &config.localization.default_language,
+    );
+    let language = variant.as_ref().map(|(lang, _)| lang.as_str());
+    let cache_key = HephaestusCache::site_content_key(&site.storage_cid, language);
+
+    let mut response = HttpResponse::Ok();
+    response.insert_header((actix_web::http::header::ACCEPT_RANGES, "bytes"));
+    if let Some(lang) = language {
+        response
+            .insert_header((actix_web::http::header::CONTENT_LANGUAGE, lang))
+            .insert_header((actix_web::http::header::VARY, "Accept-Language"));
+    }
+
+    let range = requested_range(&req);
+    let cached = match range {
+        Some(_) => None,
+        None => hephaestus.get(&cache_key).await,
+    };
+    let (content, content_type, etag) = match cached {
+        Some(cached) => {
+            metrics.record_cache_hit();
+            (cached.content, cached.content_type, cached.etag)
+        }
+        None => {
+            let location = match &variant {
+                Some((_, entry)) => format!("{}/{}", site.storage_cid.trim_end_matches('/'), entry),
+                None => site.storage_cid.clone(),
+            };
+            let stream = open_storage_stream(&pinata, &bundlr, &location, range.as_deref()).await?;
+            let content_type = stream.content_type.clone().unwrap_or_else(|| "text/html".to_string());
+            response.content_type(content_type.as_str());
+            let content = match buffer_if_cacheable(stream, &hephaestus, range.is_some()).await? {
+                Ok(content) => content,
+                Err(stream) => return Ok(stream_storage_response(response, stream, &metrics)),
+            };
+
+            metrics.record_cache_miss();
+            let etag = HephaestusCache::etag(&content);
+            hephaestus.set(cache_key, content.clone(), content_type.clone(), Some(config.get_cache_ttl())).await
+                .map_err(ShadowError::Storage)?;
+            (content, content_type, etag)
+        }
+    };
+    response.content_type(content_type);
+
+    let dev = dev_reload_requested(&db, &req, &program_address, query.dev.as_deref()).await;
+    if dev {
+        // Every dev page carries a fresh nonce, so it has no stable validator
+        return Ok(site_html_response(response, content, &program_address, dev, &req));
+    }
+    response.insert_header((actix_web::http::header::ETAG, etag.as_str()));
+    if if_none_match(&req, &etag) {
+        return Ok(response.status(actix_web::http::StatusCode::NOT_MODIFIED).finish());
+    }
+    Ok(site_html_response(response, content, &program_address, dev, &req))
+}
+
+/// Whether the client's `If-None-Match` already names `etag` (weak comparison, RFC 9110 §13.1.2)
+fn if_none_match(req: &HttpRequest, etag: &str) -> bool {
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    req.headers()
+        .get_all(actix_web::http::header::IF_NONE_MATCH)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || strip_weak(tag) == strip_weak(etag))
+}
+
+/// Serve a file from a site's storage bundle, e.g. images and video referenced by its pages.
+/// Small files go through Hephaestus; large ones and range requests stream from the gateway.
+pub async fn get_site_asset(
+    db: web::Data<Database>,
+    pinata: web::Data<PinataStorage>,
+    bundlr: web::Data<BundlrStorage>,
+    hephaestus: web::Data<HephaestusCache>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let (program_address, file) = path.into_inner();
+    if file.is_empty() || !is_safe_site_path(&file) {
+        return Err(ShadowError::BadRequest("Invalid asset path".to_string()));
+    }
+
+    metrics.record_database_query();
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+
+    let serve = SiteFile { pinata: &pinata, bundlr: &bundlr, hephaestus: &hephaestus, metrics: &metrics, req: &req };
+    serve.serve(&site, &file, HttpResponse::Ok()).await
+}
+
+/// A page or file inside a site by its path, e.g. `/content/css/site.css`. The empty path and
+/// directory paths serve the directory's index.html.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_site_content_path(
+    db: web::Data<Database>,
+    pinata: web::Data<PinataStorage>,
+    bundlr: web::Data<BundlrStorage>,
+    hephaestus: web::Data<HephaestusCache>,
+    config: web::Data<ShadowConfig>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let (program_address, file) = path.into_inner();
+    if !is_safe_site_path(&file) {
+        return Err(ShadowError::BadRequest("Invalid content path".to_string()));
+    }
+
+    metrics.record_database_query();
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+
+    let cache_control = format!("public, max-age={}", config.get_cache_ttl().as_secs());
+    let response = || {
+        let mut response = HttpResponse::Ok();
+        response.insert_header((actix_web::http::header::CACHE_CONTROL, cache_control.as_str()));
+        response
+    };
+    let serve = SiteFile { pinata: &pinata, bundlr: &bundlr, hephaestus: &hephaestus, metrics: &metrics, req: &req };
+
+    let file = file.trim_start_matches('/');
+    if file.is_empty() || file.ends_with('/') {
+        return serve.serve(&site, &format!("{}index.html", file), response()).await;
+    }
+    match serve.serve(&site, file, response()).await {
+        // Without an extension it may name a directory
+        Err(ShadowError::NotFound(_)) if !file.rsplit('/').next().unwrap_or_default().contains('.') => {
+            serve.serve(&site, &format!("{}/index.html", file), response()).await
+        }
+        served => served,
+    }
+}
+
+/// No `..` segments, so a path can't climb out of the site's storage root
+fn is_safe_site_path(path: &str) -> bool {
+    !path.split('/').any(|segment| segment == "..")
+}
+
+/// Content type for a file in a site bundle, from its extension. Gateways often answer
+/// with a generic type, and browsers won't run scripts or wasm served as one.
+fn content_type_for_path(path: &str) -> Option<&'static str> {
+    let extension = path.rsplit('/').next()?.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        "wasm" => "application/wasm",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        _ => return None,
+    })
+}
+
+/// What serving a file out of a site's storage needs from the request
+struct SiteFile<'a> {
+    pinata: &'a PinataStorage,
+    bundlr: &'a BundlrStorage,
+    hephaestus: &'a HephaestusCache,
+    metrics: &'a web::Data<MetricsCollector>,
+    req: &'a HttpRequest,
+}
+
+impl SiteFile<'_> {
+    /// Answer with `file` from `site`'s storage, on top of the headers already in `response`
+    async fn serve(
+        &self,
+        site: &db::Site,
+        file: &str,
+        mut response: actix_web::HttpResponseBuilder,
+    ) -> Result<HttpResponse, ShadowError> {
+        response.insert_header((actix_web::http::header::ACCEPT_RANGES, "bytes"));
+        let range = requested_range(self.req);
+        let cache_key = HephaestusCache::site_asset_key(&site.program_address, file);
+        if range.is_none() {
+            if let Some(cached) = self.hephaestus.get(&cache_key).await {
+                return Ok(response.content_type(cached.content_type).body(cached.content));
+            }
+        }
+
+        let location = format!("{}/{}", site.storage_cid.trim_end_matches('/'), file);
+        let stream = open_storage_stream(self.pinata, self.bundlr, &location, range.as_deref()).await?;
+        let content_type = content_type_for_path(file)
+            .map(str::to_string)
+            .or_else(|| stream.content_type.clone())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        response.content_type(content_type.as_str());
+
+        match buffer_if_cacheable(stream, self.hephaestus, range.is_some()).await? {
+            Ok(content) => {
+                self.hephaestus.set(cache_key, content.clone(), content_type, None).await
+                    .map_err(ShadowError::Storage)?;
+                Ok(response.body(content))
+            }
+            Err(stream) => Ok(stream_storage_response(response, stream, self.metrics)),
+        }
+    }
+}
+
+/// The client's `Range` header, when it asks for bytes; anything else is ignored per RFC 9110
+fn requested_range(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(actix_web::http::header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| v.trim_start().starts_with("bytes="))
+        .map(str::to_string)
+}
+
+async fn open_storage_stream(
+    pinata: &PinataStorage,
+    bundlr: &BundlrStorage,
+    location: &str,
+    range: Option<&str>,
+) -> Result<StorageStream, ShadowError> {
+    let stream = if location.starts_with("ipfs://") {
+        pinata.get_stream(location, range).await.map_err(ShadowError::Storage)?
+    } else if location.starts_with("arweave://") {
+        bundlr.get_stream(location, range).await.map_err(ShadowError::Storage)?
+    } else {
+        return Err(ShadowError::BadRequest("Invalid storage CID".to_string()));
+    };
+    if stream.status == 404 {
+        return Err(ShadowError::NotFound(format!("{} not found", location)));
+    }
+    Ok(stream)
+}
+
+/// Read a whole gateway response if it is small enough for the cache. Otherwise hand
+/// the stream back, replaying any chunks already read, so the caller can pass it through.
+/// Partial responses are never buffered since they are not the whole object.
+async fn buffer_if_cacheable(
+    mut stream: StorageStream,
+    hephaestus: &HephaestusCache,
+    partial: bool,
+) -> Result<Result<Vec<u8>, StorageStream>, ShadowError> {
+    use futures_util::StreamExt;
+
+    let too_large = stream.content_length.is_some_and(|len| !hephaestus.accepts(len));
+    if partial || stream.status != 200 || too_large {
+        return Ok(Err(stream));
+    }
+
+    let mut content = Vec::with_capacity(stream.content_length.unwrap_or(0) as usize);
+    while let Some(chunk) = stream.body.next().await {
+        content.extend_from_slice(&chunk.map_err(ShadowError::Storage)?);
+        if !hephaestus.accepts(content.len() as u64) {
+            // No Content-Length and it outgrew the cache: stream the rest
+            let read = futures_util::stream::once(async move { Ok(web::Bytes::from(content)) });
+            stream.body = Box::pin(read.chain(stream.body));
+            return Ok(Err(stream));
+        }
+    }
+    Ok(Ok(content))
+}
+
+/// Pass a gateway response through to the client chunk by chunk, keeping its status,
+/// length and range headers, and counting it as a cache bypass
+fn stream_storage_response(
+    mut response: actix_web::HttpResponseBuilder,
+    stream: StorageStream,
+    metrics: &web::Data<MetricsCollector>,
+) -> HttpResponse {
+    use futures_util::StreamExt;
+
+    metrics.record_cache_bypass();
+    let status = actix_web::http::StatusCode::from_u16(stream.status)
+        .unwrap_or(actix_web::http::StatusCode::OK);
+    response
+        .status(status)
+        .insert_header((actix_web::http::header::ACCEPT_RANGES, "bytes"));
+    if let Some(content_range) = &stream.content_range {
+        response.insert_header((actix_web::http::header::CONTENT_RANGE, content_range.as_str()));
+    }
+
+    let counter = metrics.clone();
+    let body = stream.body.inspect(move |chunk| {
+        if let Ok(chunk) = chunk {
+            counter.record_bytes_streamed(chunk.len() as u64);
+        }
+    });
+    match stream.content_length {
+        Some(length) => response.body(actix_web::body::SizedStream::new(length, body)),
+        None => response.streaming(body),
+    }
+}
+
+pub async fn upload_ipfs(
+    pinata: web::Data<PinataStorage>,
+    nft_storage: web::Data<NFTStorageClient>,
+    body: web::Bytes,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let cid = pinata.upload(&body, "upload", Some(&nft_storage)).await
+        .map_err(ShadowError::Storage)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "cid": cid
+    })))
+}
+
+/// Whether one pinning service holds a CID; `pinned` is None when it couldn't be asked
+#[derive(Serialize)]
+pub struct PinStatus {
+    pub configured: bool,
+    pub pinned: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PinStatus {
+    fn from_check(configured: bool, check: Result<bool, String>) -> Self {
+        match check {
+            Ok(pinned) => PinStatus { configured, pinned: Some(pinned), error: None },
+            Err(e) => PinStatus { configured, pinned: None, error: Some(e) },
+        }
+    }
+}
+
+/// Which pinning services hold a CID
+pub async fn get_upload_status(
+    pinata: web::Data<PinataStorage>,
+    nft_storage: web::Data<NFTStorageClient>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let cid = path.into_inner();
+    ApolloValidator::validate_ipfs_cid(&cid)?;
+
+    let pinata_status = PinStatus::from_check(pinata.has_credentials(), pinata.is_pinned(&cid).await);
+    let nft_storage_status = PinStatus::from_check(nft_storage.has_credentials(), nft_storage.is_pinned(&cid).await);
+    let pinned_on: Vec<&str> = [("pinata", &pinata_status), ("nft_storage", &nft_storage_status)]
+        .into_iter()
+        .filter(|(_, status)| status.pinned == Some(true))
+        .map(|(name, _)| name)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "cid": cid,
+        "pinned_on": pinned_on,
+        "pinata": pinata_status,
+        "nft_storage": nft_storage_status,
+    })))
+}
+
+pub async fn upload_arweave(
+    atlas: web::Data<AtlasTracker>,
+    body: web::Bytes,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let upload = atlas.upload(&body, vec![]).await
+        .map_err(ShadowError::Storage)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "tx_id": format!("arweave://{}", upload.tx_id),
+        "upload": upload
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct ArweaveEstimateQuery {
+    pub bytes: usize,
+}
+
+/// Quote an Arweave upload and warn when the Bundlr account can't cover it
+pub async fn estimate_arweave_upload(
+    atlas: web::Data<AtlasTracker>,
+    query: web::Query<ArweaveEstimateQuery>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    if query.bytes == 0 {
+        return Err(ShadowError::BadRequest("bytes must be at least 1".to_string()));
+    }
+    let estimate = atlas.estimate(query.bytes).await
+        .map_err(ShadowError::Storage)?;
+
+    Ok(HttpResponse::Ok().json(estimate))
+}
+
+/// Dry run of a deploy: what it would cost and whether it would go through, with no side effects
+pub async fn plan_deploy(
+    solana_rpc_url: web::Data<String>,
+    bundlr: web::Data<BundlrStorage>,
+    olympus: web::Data<OlympusCA>,
+    body: web::Json<DeployPlanRequest>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let solana = SolanaClient::new(solana_rpc_url.to_string());
+    let plan = metis::plan_deploy(&body, &solana, &bundlr, &olympus).await?;
+    Ok(HttpResponse::Ok().json(plan))
+}
+
+pub async fn get_arweave_upload_status(
+    atlas: web::Data<AtlasTracker>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let tx_id = path.into_inner();
+    let upload = atlas.get_upload(&tx_id).await
+        .map_err(ShadowError::Storage)?
+        .ok_or_else(|| ShadowError::NotFound("Upload not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(upload))
+}
+
+pub async fn search_solana(
+    solana_rpc_url: web::Data<String>,
+    query: web::Query<SearchQuery>,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let client = SolanaClient::new(solana_rpc_url.to_string());
+    
+    metrics.record_solana_rpc();
+    // Try to parse as pubkey first
+    if let Ok(Some(acc)) = client.search_account(&query.q) {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "type": "account",
+            "data": acc
+        })));
+    }
+
+    // Try as program
+    if let Ok(Some(prog)) = client.search_program(&query.q) {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "type": "program",
+            "data": prog
+        })));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "type": "none",
+        "data": null
     })))
 }
 
-pub async fn update_site(
+// ========== Tyche Ownership Handlers ==========
+
+/// Effective controller and ownership history for a site
+pub async fn get_site_ownership(
     db: web::Data<Database>,
+    tyche: web::Data<TycheOwnership>,
     path: web::Path<String>,
-    body: web::Json<RegisterSiteRequest>,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let program_address = path.into_inner();
-    
-    db::create_or_update_site(
-        &db,
-        &program_address,
-        &body.owner_pubkey,
-        &body.storage_cid,
-        body.name.as_deref(),
-        body.description.as_deref(),
-    ).await?;
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    let events = tyche.list_events(&program_address).await
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "success": true
+        "program_address": program_address,
+        "controller": tyche.controller(&site).await,
+        "events": events,
     })))
 }
 
-pub async fn get_site_content(
+#[derive(Deserialize)]
+pub struct SetOwnershipRequest {
+    pub mode: db::OwnershipMode,
+    /// Required for NFT mode
+    pub mint: Option<String>,
+}
+
+/// Hand control of a site to an NFT or back to a wallet; only the current controller may
+pub async fn set_site_ownership(
     db: web::Data<Database>,
-    pinata: web::Data<PinataStorage>,
-    bundlr: web::Data<BundlrStorage>,
+    tyche: web::Data<TycheOwnership>,
+    ares: web::Data<AresAuth>,
     path: web::Path<String>,
-    metrics: web::Data<MetricsCollector>,
+    body: web::Json<SetOwnershipRequest>,
+    req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let program_address = path.into_inner();
-    
-    metrics.record_database_query();
+    if let Some(mint) = &body.mint {
+        ApolloValidator::validate_pubkey(mint)?;
+    }
+
+    let caller = authenticate(&req, &ares)?;
     let site = db::get_site(&db, &program_address).await?
         .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    tyche.authorize(&site, &caller, SiteAction::ChangeOwnership).await?;
 
-    let content = if site.storage_cid.starts_with("ipfs://") {
-        pinata.get(&site.storage_cid).await
-            .map_err(|e| ShadowError::Storage(e))?
-    } else if site.storage_cid.starts_with("arweave://") {
-        bundlr.get(&site.storage_cid).await
-            .map_err(|e| ShadowError::Storage(e))?
-    } else {
-        return Err(ShadowError::BadRequest("Invalid storage CID".to_string()));
-    };
+    tyche.set_mode(&site, body.mode, body.mint.as_deref(), &caller).await?;
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "program_address": program_address,
+        "controller": tyche.controller(&site).await,
+    })))
+}
 
-    Ok(HttpResponse::Ok()
-        .content_type("text/html")
-        .body(content))
+#[derive(Deserialize)]
+pub struct AddCollaboratorRequest {
+    pub wallet: String,
+    pub role: db::SiteRole,
 }
 
-pub async fn upload_ipfs(
-    pinata: web::Data<PinataStorage>,
-    body: web::Bytes,
+/// Grant a wallet editor or admin access, or change its role; owners and admins only
+pub async fn add_collaborator(
+    db: web::Data<Database>,
+    tyche: web::Data<TycheOwnership>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<String>,
+    body: web::Json<AddCollaboratorRequest>,
+    req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let cid = pinata.upload(&body, "upload").await
-        .map_err(|e| ShadowError::Storage(e))?;
+    let program_address = path.into_inner();
+    ApolloValidator::validate_pubkey(&body.wallet)?;
+
+    let caller = authenticate(&req, &ares)?;
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    tyche.authorize(&site, &caller, SiteAction::ManageCollaborators).await?;
 
+    tyche.set_collaborator(&site, &body.wallet, body.role, &caller).await?;
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "cid": cid
+        "program_address": program_address,
+        "collaborators": site.collaborators,
     })))
 }
 
-pub async fn upload_arweave(
-    bundlr: web::Data<BundlrStorage>,
-    body: web::Bytes,
+/// Revoke a collaborator's access; owners and admins only
+pub async fn remove_collaborator(
+    db: web::Data<Database>,
+    tyche: web::Data<TycheOwnership>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let tx_id = bundlr.upload(&body, vec![]).await
-        .map_err(|e| ShadowError::Storage(e))?;
+    let (program_address, wallet) = path.into_inner();
+    let caller = authenticate(&req, &ares)?;
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    tyche.authorize(&site, &caller, SiteAction::ManageCollaborators).await?;
 
+    tyche.remove_collaborator(&site, &wallet, &caller).await?;
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "tx_id": tx_id
+        "removed": wallet
     })))
 }
 
-pub async fn search_solana(
-    solana_rpc_url: web::Data<String>,
-    query: web::Query<SearchQuery>,
-    metrics: web::Data<MetricsCollector>,
+/// Dashboard listing: sites the caller owns or collaborates on, with its role on each
+pub async fn list_my_sites(
+    db: web::Data<Database>,
+    tyche: web::Data<TycheOwnership>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let client = SolanaClient::new(solana_rpc_url.to_string());
-    
-    metrics.record_solana_rpc();
-    // Try to parse as pubkey first
-    if let Ok(account) = client.search_account(&query.q) {
-        if let Some(acc) = account {
-            return Ok(HttpResponse::Ok().json(serde_json::json!({
-                "type": "account",
-                "data": acc
-            })));
-        }
+    let caller = authenticate(&req, &ares)?;
+    let mut sites = Vec::new();
+    for site in db::list_sites_for_wallet(&db, &caller).await? {
+        // An NFT-controlled site may have changed hands since owner_pubkey was written
+        let Some(role) = tyche.role_of(&site, &caller).await? else { continue };
+        let mut entry = serde_json::to_value(&site).unwrap_or_default();
+        entry["role"] = serde_json::json!(role);
+        sites.push(entry);
     }
 
-    // Try as program
-    if let Ok(program) = client.search_program(&query.q) {
-        if let Some(prog) = program {
-            return Ok(HttpResponse::Ok().json(serde_json::json!({
-                "type": "program",
-                "data": prog
-            })));
-        }
-    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "wallet": caller,
+        "sites": sites,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct RecoverOwnershipRequest {
+    pub owner_pubkey: String,
+    pub reason: Option<String>,
+}
+
+/// Admin recovery for a site whose control NFT was burned
+pub async fn recover_site_ownership(
+    db: web::Data<Database>,
+    tyche: web::Data<TycheOwnership>,
+    ares: web::Data<AresAuth>,
+    config: web::Data<ShadowConfig>,
+    path: web::Path<String>,
+    body: web::Json<RecoverOwnershipRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let admin = crate::wallet_handlers::verify_admin(&req, &ares, &config)?;
+    let program_address = path.into_inner();
+    ApolloValidator::validate_pubkey(&body.owner_pubkey)?;
+
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+    tyche.recover(&site, &body.owner_pubkey, &admin, body.reason.as_deref()).await?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
-        "type": "none",
-        "data": null
+        "success": true
+    })))
+}
+
+// ========== Ares Auth Handlers ==========
+
+/// Exchange a signed challenge for a short-lived bearer token
+pub async fn issue_auth_token(
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    // Tokens are only minted from a fresh signature, never from another token
+    let wallet = verify_signed_header(&req, &ares)?;
+
+    let (token, expires_at) = ares.issue_token(&wallet)
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "token": token,
+        "token_type": "Bearer",
+        "expires_at": expires_at,
+        "wallet": wallet
     })))
 }
 
@@ -409,26 +2146,21 @@ pub async fn register_domain(
     ApolloValidator::validate_pubkey(&body.program_address)?;
 
     // Verify authentication
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    if auth.wallet != body.owner_pubkey {
-        return Err(ShadowError::Unauthorized.into());
+    let caller = authenticate(&req, &ares)?;
+    if caller != body.owner_pubkey {
+        return Err(ShadowError::Forbidden("Wallet does not match".to_string()));
     }
-    
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
 
-    // Register domain
-    olympus.register_domain(
+    // Register domain; one already held by another wallet can only change hands via transfer
+    let registered = olympus.register_domain(
         &body.domain,
         &body.owner_pubkey,
         &body.program_address,
     ).await
-    .map_err(|e| ShadowError::BadRequest(e))?;
+    .map_err(ShadowError::BadRequest)?;
+    if !registered {
+        return Err(ShadowError::Conflict("Domain already registered".to_string()));
+    }
 
     Ok(HttpResponse::Created().json(serde_json::json!({
         "success": true,
@@ -436,17 +2168,25 @@ pub async fn register_domain(
     })))
 }
 
+/// Domain lookup, including who controls the site it points at
 pub async fn get_domain(
+    db: web::Data<Database>,
     olympus: web::Data<OlympusCA>,
+    tyche: web::Data<TycheOwnership>,
     path: web::Path<String>,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let domain = path.into_inner();
     
     let domain_data = olympus.get_domain(&domain).await
-        .map_err(|e| ShadowError::BadRequest(e))?
+        .map_err(ShadowError::BadRequest)?
         .ok_or_else(|| ShadowError::NotFound("Domain not found".to_string()))?;
 
-    Ok(HttpResponse::Ok().json(domain_data))
+    let mut body = serde_json::to_value(&domain_data)
+        .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+    if let Some(site) = db::get_site(&db, &domain_data.program_address).await? {
+        body["site_controller"] = serde_json::json!(tyche.controller(&site).await);
+    }
+    Ok(HttpResponse::Ok().json(body))
 }
 
 pub async fn search_domains(
@@ -457,10 +2197,14 @@ pub async fn search_domains(
     ApolloValidator::validate_search_query(&query.q)?;
     let limit = ApolloValidator::validate_limit(query.limit)?;
 
-    let domains = olympus.search_domains(&query.q, limit).await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+    let after = page_after(query.cursor.as_deref(), decode_cursor)?;
 
-    Ok(HttpResponse::Ok().json(domains))
+    let (domains, next_cursor) = olympus.search_domains(&query.q, limit, after).await
+        .map_err(ShadowError::BadRequest)?;
+    let total = olympus.count_search_domains(&query.q).await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(search_page_body(domains, next_cursor, total)))
 }
 
 pub async fn update_domain(
@@ -479,30 +2223,25 @@ pub async fn update_domain(
 
     // Verify ownership
     let domain_data = olympus.get_domain(&domain).await
-        .map_err(|e| ShadowError::BadRequest(e))?
+        .map_err(ShadowError::BadRequest)?
         .ok_or_else(|| ShadowError::NotFound("Domain not found".to_string()))?;
 
     // Verify authentication
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    if auth.wallet != domain_data.owner_pubkey {
-        return Err(ShadowError::Unauthorized.into());
+    let caller = authenticate(&req, &ares)?;
+    if caller != domain_data.owner_pubkey {
+        return Err(ShadowError::Forbidden("Not the domain owner".to_string()));
     }
-    
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
 
-    // Update domain
-    olympus.register_domain(
+    // Update domain; it may have been transferred away since it was read
+    let updated = olympus.register_domain(
         &domain,
         &domain_data.owner_pubkey,
         &body.program_address,
     ).await
-    .map_err(|e| ShadowError::BadRequest(e))?;
+    .map_err(ShadowError::BadRequest)?;
+    if !updated {
+        return Err(ShadowError::Forbidden("Not the domain owner".to_string()));
+    }
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true
@@ -521,22 +2260,14 @@ pub async fn verify_domain(
     
     // Verify ownership
     let domain_data = olympus.get_domain(&domain).await
-        .map_err(|e| ShadowError::BadRequest(e))?
+        .map_err(ShadowError::BadRequest)?
         .ok_or_else(|| ShadowError::NotFound("Domain not found".to_string()))?;
 
     // Verify authentication
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    if auth.wallet != domain_data.owner_pubkey {
-        return Err(ShadowError::Unauthorized.into());
+    let caller = authenticate(&req, &ares)?;
+    if caller != domain_data.owner_pubkey {
+        return Err(ShadowError::Forbidden("Not the domain owner".to_string()));
     }
-    
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
 
     // On-chain verification: Check program exists and is executable
     metrics.record_solana_rpc();
@@ -545,20 +2276,25 @@ pub async fn verify_domain(
         Ok(Some(program_info)) => {
             // Program exists and is executable
             if program_info.data_len == 0 {
-                return Err(ShadowError::BadRequest("Program account is empty".to_string()).into());
+                return Err(ShadowError::BadRequest("Program account is empty".to_string()));
             }
         }
         Ok(None) => {
-            return Err(ShadowError::BadRequest("Program not found or not executable".to_string()).into());
+            return Err(ShadowError::BadRequest("Program not found or not executable".to_string()));
         }
         Err(e) => {
-            return Err(ShadowError::BadRequest(format!("Solana RPC error: {}", e)).into());
+            return Err(ShadowError::BadRequest(format!("Solana RPC error: {}", e)));
         }
     }
 
+    // Remember who can upgrade the program so re-verification can spot a handover
+    metrics.record_solana_rpc();
+    let authority = client.get_program_upgrade_authority(&domain_data.program_address)
+        .map_err(|e| ShadowError::BadRequest(format!("Solana RPC error: {}", e)))?;
+
     // Mark as verified (after on-chain verification)
-    olympus.verify_domain(&domain).await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+    olympus.verify_domain(&domain, authority.as_deref()).await
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
@@ -573,11 +2309,49 @@ pub async fn list_owner_domains(
     let wallet = path.into_inner();
     
     let domains = olympus.list_owner_domains(&wallet).await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(domains))
 }
 
+// ========== Helios Watchlist Handlers ==========
+
+/// Watch a domain, registered or not, for expiry and availability alerts
+pub async fn watch_domain(
+    helios: web::Data<HeliosWatchlist>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+    let entry = helios.watch(&wallet, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(entry))
+}
+
+pub async fn unwatch_domain(
+    helios: web::Data<HeliosWatchlist>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+    helios.unwatch(&wallet, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true
+    })))
+}
+
+/// The caller's watched domains with their current status
+pub async fn get_watchlist(
+    helios: web::Data<HeliosWatchlist>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+    let entries = helios.list(&wallet).await?;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
 // ========== Athena Search Handlers ==========
 
 #[derive(Deserialize)]
@@ -587,20 +2361,142 @@ pub struct IndexContentRequest {
     pub title: Option<String>,
     pub description: Option<String>,
     pub content: String,
+    /// Language of this variant for multi-language sites; each language is indexed separately
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
+/// Header carrying the signed id the click beacon reports back
+pub const SEARCH_QUERY_ID_HEADER: &str = "X-Search-Query-Id";
+
 pub async fn search_content(
     athena: web::Data<AthenaIndexer>,
+    clio: web::Data<ClioRecorder>,
     query: web::Query<SearchQuery>,
     _apollo: web::Data<ApolloValidator>,
+    req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     ApolloValidator::validate_search_query(&query.q)?;
     let limit = ApolloValidator::validate_limit(query.limit)?;
+    let after = page_after(query.cursor.as_deref(), decode_score_cursor)?;
     
-    let results = athena.search(&query.q, limit).await
+    let started = std::time::Instant::now();
+    let options = SearchOptions { sort: query.sort };
+    let (results, next_cursor) = athena.search(&query.q, options, limit, after).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+    let total = athena.count(&query.q).await
+        .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+    let client_ip = req.peer_addr().map(|a| a.ip().to_string());
+    let query_id = clio.record(&query.q, results.len(), started.elapsed(), client_ip.as_deref());
     
-    Ok(HttpResponse::Ok().json(results))
+    Ok(HttpResponse::Ok()
+        .insert_header((SEARCH_QUERY_ID_HEADER, query_id))
+        .json(search_page_body(results, next_cursor, total)))
+}
+
+#[derive(Deserialize)]
+pub struct SearchClickRequest {
+    /// Value of the X-Search-Query-Id header from the search response
+    pub query_id: String,
+    pub domain: String,
+}
+
+/// Beacon the UI sends when a search result is opened
+pub async fn record_search_click(
+    clio: web::Data<ClioRecorder>,
+    body: web::Json<SearchClickRequest>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    ApolloValidator::validate_domain(&body.domain)?;
+    clio.record_click(&body.query_id, &body.domain)
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Accepted().json(serde_json::json!({
+        "success": true
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct SearchStatsQuery {
+    #[serde(default)]
+    pub window: SearchWindow,
+    pub limit: Option<i64>,
+}
+
+pub async fn get_top_search_queries(
+    clio: web::Data<ClioRecorder>,
+    ares: web::Data<AresAuth>,
+    config: web::Data<ShadowConfig>,
+    query: web::Query<SearchStatsQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    crate::wallet_handlers::verify_admin(&req, &ares, &config)?;
+    let limit = ApolloValidator::validate_limit(query.limit)?;
+
+    let queries = clio.top_queries(query.window, limit).await
+        .map_err(ShadowError::BadRequest)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "window": query.window,
+        "queries": queries
+    })))
+}
+
+pub async fn get_zero_result_search_queries(
+    clio: web::Data<ClioRecorder>,
+    ares: web::Data<AresAuth>,
+    config: web::Data<ShadowConfig>,
+    query: web::Query<SearchStatsQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    crate::wallet_handlers::verify_admin(&req, &ares, &config)?;
+    let limit = ApolloValidator::validate_limit(query.limit)?;
+
+    let queries = clio.zero_result_queries(query.window, limit).await
+        .map_err(ShadowError::BadRequest)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "window": query.window,
+        "queries": queries
+    })))
+}
+
+pub async fn search_index_health(
+    athena: web::Data<AthenaIndexer>,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    metrics.record_database_query();
+    let health = athena.get_index_health().await?;
+
+    Ok(HttpResponse::Ok().json(health))
+}
+
+/// Indexed sites linking to this one
+pub async fn get_site_backlinks(
+    athena: web::Data<AthenaIndexer>,
+    path: web::Path<String>,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+    ApolloValidator::validate_pubkey(&program_address)?;
+
+    metrics.record_database_query();
+    let backlinks = athena.backlinks(&program_address).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "program_address": program_address,
+        "backlinks": backlinks,
+    })))
+}
+
+/// Run the deployment self-checks (admin only)
+pub async fn get_diagnostics(
+    db: web::Data<Database>,
+    config: web::Data<ShadowConfig>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    crate::wallet_handlers::verify_admin(&req, &ares, &config)?;
+
+    let report = Hygieia::new(config.as_ref().clone(), db.as_ref().clone()).run().await;
+    Ok(HttpResponse::Ok().json(report))
 }
 
 pub async fn index_content(
@@ -610,10 +2506,14 @@ pub async fn index_content(
 ) -> ActixResult<HttpResponse, ShadowError> {
     ApolloValidator::validate_domain(&body.domain)?;
     ApolloValidator::validate_pubkey(&body.program_address)?;
+    if let Some(language) = &body.language {
+        ApolloValidator::validate_language_tag(language)?;
+    }
     
     athena.index_site(
         &body.domain,
         &body.program_address,
+        body.language.as_deref(),
         body.title.as_deref(),
         body.description.as_deref(),
         &body.content,
@@ -625,71 +2525,225 @@ pub async fn index_content(
     })))
 }
 
+// ========== Charon Bridge Handlers ==========
+
+#[derive(Deserialize)]
+pub struct CreateBridgeRequest {
+    pub legacy_domain: String,
+    pub shadow_domain: String,
+}
+
+/// Start bridging a legacy domain to one of the caller's verified .shadow domains.
+/// Returns the TXT record that proves control of the legacy domain.
+pub async fn create_bridge(
+    charon: web::Data<CharonBridge>,
+    olympus: web::Data<OlympusCA>,
+    ares: web::Data<AresAuth>,
+    body: web::Json<CreateBridgeRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let legacy_domain = normalize_legacy_domain(&body.legacy_domain)?;
+    ApolloValidator::validate_domain(&body.shadow_domain)?;
+    let caller = authenticate(&req, &ares)?;
+
+    let domain = olympus.get_domain(&body.shadow_domain).await?
+        .ok_or_else(|| ShadowError::NotFound("Domain not found".to_string()))?;
+    if domain.owner_pubkey != caller {
+        return Err(ShadowError::Forbidden("Only the domain owner can bridge it".to_string()));
+    }
+    if !domain.verified {
+        return Err(ShadowError::BadRequest("Only verified domains can be bridged".to_string()));
+    }
+
+    let bridge = charon.create_bridge(&legacy_domain, &domain.domain, &domain.program_address, &caller).await?;
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "txt_record": { "name": bridge.txt_name(), "value": bridge.txt_value() },
+        "bridge": bridge,
+    })))
+}
+
+/// Check the bridge's TXT record and mark it verified when present
+pub async fn verify_bridge(
+    charon: web::Data<CharonBridge>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let legacy_domain = normalize_legacy_domain(&path.into_inner())?;
+    let caller = authenticate(&req, &ares)?;
+
+    let bridge = charon.get_bridge(&legacy_domain).await?
+        .ok_or_else(|| ShadowError::NotFound("Bridge not found".to_string()))?;
+    if bridge.owner_pubkey != caller {
+        return Err(ShadowError::Forbidden("Only the bridge owner can verify it".to_string()));
+    }
+    if !charon.verify_bridge(&bridge).await? {
+        return Err(ShadowError::BadRequest(format!(
+            "TXT record {}=\"{}\" not found",
+            bridge.txt_name(),
+            bridge.txt_value()
+        )));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "legacy_domain": legacy_domain,
+        "shadow_domain": bridge.shadow_domain,
+        "verified": true
+    })))
+}
+
+/// Signed mapping assertion for a verified bridge, for programmatic use
+pub async fn get_bridge_assertion(
+    charon: web::Data<CharonBridge>,
+    olympus: web::Data<OlympusCA>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let legacy_domain = normalize_legacy_domain(&path.into_inner())?;
+    let (claims, assertion) = charon.assert_bridge(&olympus, &legacy_domain).await?
+        .ok_or_else(|| ShadowError::NotFound("No verified bridge for this domain".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "assertion": assertion,
+        "claims": claims,
+        "signer": charon.signer().to_string(),
+    })))
+}
+
+/// Public key bridge assertions are signed with
+pub async fn get_bridge_signer(charon: web::Data<CharonBridge>) -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({
+        "alg": "EdDSA",
+        "signer": charon.signer().to_string(),
+    }))
+}
+
+/// GET /r/{legacy_domain}: bounce legacy visitors to the bridged Shadow site with a
+/// signed assertion. Anything not verifiably bridged gets a neutral page instead.
+pub async fn legacy_redirect(
+    charon: web::Data<CharonBridge>,
+    olympus: web::Data<OlympusCA>,
+    path: web::Path<String>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let requested = path.into_inner();
+    let assertion = match normalize_legacy_domain(&requested) {
+        Ok(legacy_domain) => charon.assert_bridge(&olympus, &legacy_domain).await?,
+        Err(_) => None,
+    };
+
+    match assertion {
+        Some((claims, token)) => Ok(HttpResponse::Found()
+            .insert_header(("Location", charon.redirect_url(&claims, &token)))
+            .insert_header(("Cache-Control", "no-store"))
+            .finish()),
+        None => Ok(HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .insert_header(("Cache-Control", "no-store"))
+            .body(format!(
+                "<!doctype html><html><head><meta charset=\"utf-8\"><title>Shadow</title></head>\
+                 <body><h1>No Shadow site here</h1><p>{} has no verified Shadow mapping.</p></body></html>",
+                crate::utils::html_escape(&requested)
+            ))),
+    }
+}
+
 // ========== Chronos History/Bookmarks Handlers ==========
 
 #[derive(Deserialize)]
 pub struct RecordVisitRequest {
     pub domain: String,
     pub program_address: String,
+    /// Page within the site, e.g. "/docs/intro"
+    #[serde(default)]
+    pub path: Option<String>,
     pub title: Option<String>,
     pub time_spent_seconds: u64,
 }
 
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub offset: u64,
+    /// `next_cursor` from the previous page; stable while new visits arrive, unlike `offset`
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// The wallet's visit stream, newest first
 pub async fn get_history(
     chronos: web::Data<ChronosManager>,
     ares: web::Data<AresAuth>,
-    query: web::Query<SearchQuery>,
+    query: web::Query<HistoryQuery>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let limit = query.limit.unwrap_or(50);
-    let history = chronos.get_history(&auth.wallet, limit).await
+    let wallet = authenticate(&req, &ares)?;
+    let limit = ApolloValidator::validate_limit(Some(query.limit.unwrap_or(50)))?;
+    let after = page_after(query.cursor.as_deref(), decode_cursor)?;
+
+    let (visits, next_cursor) = chronos.get_history(&wallet, limit, query.offset, after).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
-    
-    Ok(HttpResponse::Ok().json(history))
+    let next_offset = next_cursor.is_some().then(|| query.offset + limit as u64);
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "visits": visits,
+        "next_offset": next_offset,
+        "next_cursor": next_cursor,
+    })))
+}
+
+/// Per-domain visit counts and time spent
+pub async fn get_history_summary(
+    chronos: web::Data<ChronosManager>,
+    ares: web::Data<AresAuth>,
+    query: web::Query<HistoryQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+    let limit = ApolloValidator::validate_limit(Some(query.limit.unwrap_or(50)))?;
+
+    let summary = chronos.get_history_summary(&wallet, limit).await
+        .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(summary))
 }
 
 pub async fn record_visit(
+    db: web::Data<Database>,
     chronos: web::Data<ChronosManager>,
     prometheus: web::Data<PrometheusAnalytics>,
     ares: web::Data<AresAuth>,
+    config: web::Data<ShadowConfig>,
     body: web::Json<RecordVisitRequest>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
+    let wallet = authenticate(&req, &ares)?;
+    let user = db::get_user(&db, &wallet).await?;
+    let opted_out = user.as_ref().is_some_and(|user| user.analytics_opt_out);
+    let retention_days = config.privacy
+        .effective_retention_days(user.as_ref().and_then(|u| u.history_retention_days));
     
     let time_spent = Duration::from_secs(body.time_spent_seconds);
     chronos.record_visit(
-        &auth.wallet,
+        &wallet,
         &body.domain,
         &body.program_address,
+        body.path.as_deref(),
         body.title.as_deref(),
         time_spent,
+        retention_days,
     ).await
     .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
     
-    prometheus.record_visit(
-        &body.domain,
-        &body.program_address,
-        &auth.wallet,
-        body.time_spent_seconds as f64,
-    ).await
-    .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+    // Opted-out wallets keep their own history but leave no analytics trail
+    if !opted_out {
+        prometheus.record_visit(
+            &body.domain,
+            &body.program_address,
+            &wallet,
+            body.time_spent_seconds as f64,
+        ).await
+        .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+    }
     
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true
@@ -701,16 +2755,9 @@ pub async fn clear_history(
     ares: web::Data<AresAuth>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
+    let wallet = authenticate(&req, &ares)?;
     
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    chronos.clear_history(&auth.wallet).await
+    chronos.clear_history(&wallet).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
     
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -718,6 +2765,105 @@ pub async fn clear_history(
     })))
 }
 
+pub async fn delete_domain_history(
+    chronos: web::Data<ChronosManager>,
+    ares: web::Data<AresAuth>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+    let domain = path.into_inner();
+
+    let deleted = chronos.delete_domain_history(&wallet, &domain).await
+        .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "deleted_visits": deleted
+    })))
+}
+
+// ========== Privacy Settings Handlers ==========
+
+#[derive(Deserialize)]
+pub struct PrivacySettingsRequest {
+    /// Days of history to keep; the server default applies when omitted
+    #[serde(default)]
+    pub history_retention_days: Option<u32>,
+    #[serde(default)]
+    pub analytics_opt_out: bool,
+}
+
+#[derive(Serialize)]
+pub struct PrivacySettingsResponse {
+    pub history_retention_days: Option<u32>,
+    /// Retention actually applied after the server's floor and ceiling; null keeps history forever
+    pub effective_retention_days: Option<u32>,
+    pub analytics_opt_out: bool,
+    pub history_entries: u64,
+}
+
+pub async fn get_privacy_settings(
+    db: web::Data<Database>,
+    chronos: web::Data<ChronosManager>,
+    config: web::Data<ShadowConfig>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+
+    let user = db::get_user(&db, &wallet).await?;
+    let history_retention_days = user.as_ref().and_then(|u| u.history_retention_days);
+    let history_entries = chronos.count_history(&wallet).await?;
+
+    Ok(HttpResponse::Ok().json(PrivacySettingsResponse {
+        history_retention_days,
+        effective_retention_days: config.privacy.effective_retention_days(history_retention_days),
+        analytics_opt_out: user.is_some_and(|u| u.analytics_opt_out),
+        history_entries,
+    }))
+}
+
+pub async fn update_privacy_settings(
+    db: web::Data<Database>,
+    chronos: web::Data<ChronosManager>,
+    prometheus: web::Data<PrometheusAnalytics>,
+    config: web::Data<ShadowConfig>,
+    ares: web::Data<AresAuth>,
+    body: web::Json<PrivacySettingsRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+    if body.history_retention_days == Some(0) {
+        return Err(ShadowError::BadRequest("history_retention_days must be at least 1".to_string()));
+    }
+
+    let was_opted_out = db::get_user(&db, &wallet).await?
+        .is_some_and(|user| user.analytics_opt_out);
+    db::set_privacy_settings(&db, &wallet, body.history_retention_days, body.analytics_opt_out).await?;
+    let effective_retention_days = config.privacy.effective_retention_days(body.history_retention_days);
+    chronos.apply_retention(&wallet, effective_retention_days).await?;
+
+    // Existing engagement rows are anonymized off the request path
+    if body.analytics_opt_out && !was_opted_out {
+        let prometheus = prometheus.clone();
+        let wallet = wallet.clone();
+        tokio::spawn(async move {
+            if let Err(e) = prometheus.anonymize_engagement(&wallet).await {
+                tracing::warn!("Failed to anonymize engagement for {}: {}", wallet, e);
+            }
+        });
+    }
+
+    let history_entries = chronos.count_history(&wallet).await?;
+    Ok(HttpResponse::Ok().json(PrivacySettingsResponse {
+        history_retention_days: body.history_retention_days,
+        effective_retention_days,
+        analytics_opt_out: body.analytics_opt_out,
+        history_entries,
+    }))
+}
+
 #[derive(Deserialize)]
 pub struct AddBookmarkRequest {
     pub domain: String,
@@ -734,20 +2880,15 @@ pub async fn get_bookmarks(
     query: web::Query<SearchQuery>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
+    let wallet = authenticate(&req, &ares)?;
+    let limit = ApolloValidator::validate_limit(Some(query.limit.unwrap_or(100)))?;
+    let after = page_after(query.cursor.as_deref(), decode_cursor)?;
     
     let folder = if query.q.is_empty() { None } else { Some(query.q.as_str()) };
-    let bookmarks = chronos.get_bookmarks(&auth.wallet, folder).await
+    let (bookmarks, next_cursor) = chronos.get_bookmarks(&wallet, folder, limit, after).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
     
-    Ok(HttpResponse::Ok().json(bookmarks))
+    Ok(HttpResponse::Ok().json(page_body(bookmarks, next_cursor)))
 }
 
 pub async fn add_bookmark(
@@ -756,17 +2897,10 @@ pub async fn add_bookmark(
     body: web::Json<AddBookmarkRequest>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
+    let wallet = authenticate(&req, &ares)?;
     
     chronos.add_bookmark(
-        &auth.wallet,
+        &wallet,
         &body.domain,
         &body.program_address,
         body.title.as_deref(),
@@ -788,16 +2922,9 @@ pub async fn remove_bookmark(
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let domain = path.into_inner();
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
+    let wallet = authenticate(&req, &ares)?;
     
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    chronos.remove_bookmark(&auth.wallet, &domain).await
+    chronos.remove_bookmark(&wallet, &domain).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
     
     Ok(HttpResponse::Ok().json(serde_json::json!({
@@ -805,21 +2932,73 @@ pub async fn remove_bookmark(
     })))
 }
 
+/// Largest bookmark file accepted for import
+const MAX_BOOKMARK_IMPORT_BYTES: usize = 5 * 1024 * 1024;
+
+/// Download the caller's bookmarks as a Netscape bookmark file browsers can import
+pub async fn export_bookmarks(
+    chronos: web::Data<ChronosManager>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+    let html = chronos.export_bookmarks_html(&wallet).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header(("Content-Disposition", "attachment; filename=\"bookmarks.html\""))
+        .insert_header(("Cache-Control", "no-store"))
+        .body(html))
+}
+
+/// Import a browser's bookmark export, uploaded as the `file` field of a multipart form
+pub async fn import_bookmarks(
+    chronos: web::Data<ChronosManager>,
+    ares: web::Data<AresAuth>,
+    mut payload: actix_multipart::Multipart,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    use futures_util::TryStreamExt;
+
+    let wallet = authenticate(&req, &ares)?;
+    let invalid = |e: actix_multipart::MultipartError| ShadowError::BadRequest(format!("Invalid upload: {}", e));
+
+    let mut file = None;
+    while let Some(mut field) = payload.try_next().await.map_err(invalid)? {
+        if field.name() != Some("file") {
+            continue;
+        }
+        let mut data = Vec::new();
+        while let Some(chunk) = field.try_next().await.map_err(invalid)? {
+            if data.len() + chunk.len() > MAX_BOOKMARK_IMPORT_BYTES {
+                return Err(ShadowError::BadRequest(format!(
+                    "Bookmark file must be at most {} bytes",
+                    MAX_BOOKMARK_IMPORT_BYTES
+                )));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        file = Some(data);
+    }
+    let data = file.ok_or_else(|| ShadowError::BadRequest("Missing file field".to_string()))?;
+    let html = String::from_utf8(data)
+        .map_err(|_| ShadowError::BadRequest("Bookmark file must be UTF-8".to_string()))?;
+
+    let (imported, skipped_duplicates) = chronos.import_bookmarks_html(&wallet, &html).await?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "imported": imported,
+        "skipped_duplicates": skipped_duplicates
+    })))
+}
+
 pub async fn create_session(
     chronos: web::Data<ChronosManager>,
     ares: web::Data<AresAuth>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
+    let wallet = authenticate(&req, &ares)?;
     
-    let session_id = chronos.create_session(&auth.wallet).await
+    let session_id = chronos.create_session(&wallet).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
     
     Ok(HttpResponse::Created().json(serde_json::json!({
@@ -832,32 +3011,75 @@ pub async fn get_active_sessions(
     ares: web::Data<AresAuth>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
+    let wallet = authenticate(&req, &ares)?;
     
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(&ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let sessions = chronos.get_active_sessions(&auth.wallet).await
+    let sessions = chronos.get_active_sessions(&wallet).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
     
     Ok(HttpResponse::Ok().json(sessions))
 }
 
+#[derive(Deserialize)]
+pub struct MergeSessionsRequest {
+    pub session_ids: Vec<String>,
+}
+
+pub async fn merge_sessions(
+    chronos: web::Data<ChronosManager>,
+    ares: web::Data<AresAuth>,
+    body: web::Json<MergeSessionsRequest>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let wallet = authenticate(&req, &ares)?;
+
+    let session_id = chronos.merge_sessions(&wallet, body.into_inner().session_ids).await?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "session_id": session_id
+    })))
+}
+
 // ========== Prometheus Analytics Handlers ==========
 
+#[derive(Deserialize)]
+pub struct AnalyticsQuery {
+    /// Recompute the summary even while it is fresh; domain owner only
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn get_analytics(
     prometheus: web::Data<PrometheusAnalytics>,
     path: web::Path<String>,
+    query: web::Query<AnalyticsQuery>,
+    olympus: web::Data<OlympusCA>,
+    ares: web::Data<AresAuth>,
+    artemis: web::Data<ArtemisRateLimiter>,
+    metrics: web::Data<MetricsCollector>,
+    req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let domain = path.into_inner();
     
-    // Update analytics summary before returning
-    prometheus.update_analytics_summary(&domain).await
+    // Forced refreshes bypass the freshness guard, so they are owner-only and rate limited
+    if query.force {
+        let caller = authenticate(&req, &ares)?;
+        let key = format!("analytics-force:{}", ArtemisRateLimiter::get_client_key(None, Some(&caller)));
+        artemis.check_rate_limit(&key)
+            .map_err(ShadowError::BadRequest)?;
+
+        let record = olympus.get_domain(&domain).await
+            .map_err(ShadowError::BadRequest)?
+            .ok_or_else(|| ShadowError::NotFound("Domain not found".to_string()))?;
+        if record.owner_pubkey != caller {
+            return Err(ShadowError::Forbidden("Not the domain owner".to_string()));
+        }
+    }
+    
+    // Refresh analytics summary before returning (skipped while still fresh)
+    let executed = prometheus.refresh_analytics_summary(&domain, query.force).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
+    metrics.record_analytics_summary(executed);
     
     let analytics = prometheus.get_analytics(&domain).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
@@ -868,15 +3090,171 @@ pub async fn get_analytics(
     }
 }
 
+#[derive(Deserialize)]
+pub struct DailyStatsQuery {
+    /// First day, YYYY-MM-DD; defaults to 29 days before `to`
+    pub from: Option<String>,
+    /// Last day, YYYY-MM-DD; defaults to today
+    pub to: Option<String>,
+}
+
+/// Aggregated per-day stats for a domain over a date range, oldest first
+pub async fn get_daily_stats(
+    prometheus: web::Data<PrometheusAnalytics>,
+    path: web::Path<String>,
+    query: web::Query<DailyStatsQuery>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let domain = path.into_inner().to_lowercase();
+    let to = match &query.to {
+        Some(to) => parse_export_date("to", to)?,
+        None => prometheus.today(),
+    };
+    let from = match &query.from {
+        Some(from) => parse_export_date("from", from)?,
+        None => to - chrono::Duration::days(29),
+    };
+    if from > to {
+        return Err(ShadowError::BadRequest("from must not be after to".to_string()));
+    }
+
+    let days = prometheus.get_daily_stats(&domain, from, to).await?
+        .ok_or_else(|| ShadowError::NotFound("Analytics not found".to_string()))?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "domain": domain,
+        "from": from.format("%Y-%m-%d").to_string(),
+        "to": to.format("%Y-%m-%d").to_string(),
+        "daily_stats": days,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct AnalyticsExportQuery {
+    /// First day, YYYY-MM-DD; defaults to 29 days before `to`
+    pub from: Option<String>,
+    /// Last day, YYYY-MM-DD; defaults to today
+    pub to: Option<String>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Set when part of the requested range was past retention; names the first day exported
+pub const EXPORT_TRUNCATED_HEADER: &str = "X-Shadow-Export-Truncated";
+
+fn parse_export_date(field: &str, value: &str) -> Result<chrono::NaiveDate, ShadowError> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| ShadowError::BadRequest(format!("{} must be a YYYY-MM-DD date", field)))
+}
+
+/// Download per-day analytics for a domain as CSV or newline-delimited JSON. Domain owner
+/// only. Rows are streamed straight from the database rather than buffered.
+#[allow(clippy::too_many_arguments)]
+pub async fn export_analytics(
+    prometheus: web::Data<PrometheusAnalytics>,
+    olympus: web::Data<OlympusCA>,
+    ares: web::Data<AresAuth>,
+    artemis: web::Data<ArtemisRateLimiter>,
+    config: web::Data<ShadowConfig>,
+    path: web::Path<String>,
+    query: web::Query<AnalyticsExportQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    use futures_util::{StreamExt, TryStreamExt};
+
+    let domain = path.into_inner().to_lowercase();
+    let to = match &query.to {
+        Some(to) => parse_export_date("to", to)?,
+        None => prometheus.today(),
+    };
+    let mut from = match &query.from {
+        Some(from) => parse_export_date("from", from)?,
+        None => to - chrono::Duration::days(29),
+    };
+    if from > to {
+        return Err(ShadowError::BadRequest("from must not be after to".to_string()));
+    }
+
+    let caller = authenticate(&req, &ares)?;
+    // Exports are heavy, so they get their own bucket and cost several requests
+    let key = format!("analytics-export:{}", ArtemisRateLimiter::get_client_key(None, Some(&caller)));
+    artemis.check_rate_limit_with_cost(&key, config.analytics.export_rate_limit_cost)
+        .map_err(ShadowError::BadRequest)?;
+
+    let record = olympus.get_domain(&domain).await
+        .map_err(ShadowError::BadRequest)?
+        .ok_or_else(|| ShadowError::NotFound("Domain not found".to_string()))?;
+    if record.owner_pubkey != caller {
+        return Err(ShadowError::Forbidden("Not the domain owner".to_string()));
+    }
+
+    let retention_start = prometheus.retention_start();
+    let truncated = from < retention_start;
+    if truncated {
+        from = retention_start;
+    }
+
+    let export = AnalyticsExport {
+        id: uuid::Uuid::new_v4().to_string(),
+        domain: domain.clone(),
+        wallet: caller,
+        from: from.format("%Y-%m-%d").to_string(),
+        to: to.format("%Y-%m-%d").to_string(),
+        format: query.format,
+        truncated,
+        created_at: chrono::Utc::now(),
+    };
+    if let Err(e) = prometheus.record_export(&export).await {
+        tracing::warn!("Failed to audit analytics export for {}: {}", domain, e);
+    }
+
+    let rows = prometheus.export_daily(&domain, from, to).await?;
+    let format = query.format;
+    let body = rows
+        .map_ok(move |row| match format {
+            ExportFormat::Csv => row.csv_record(),
+            ExportFormat::Json => {
+                let mut line = serde_json::to_string(&row).unwrap_or_default();
+                line.push('\n');
+                line
+            }
+        })
+        .map_ok(web::Bytes::from)
+        .map_err(|e| actix_web::Error::from(ShadowError::Database(e)));
+    let header = match format {
+        ExportFormat::Csv => Some(crate::prometheus::csv_line(&DailyExportRow::CSV_HEADER)),
+        ExportFormat::Json => None,
+    };
+    let body = futures_util::stream::iter(header.map(|h| Ok(web::Bytes::from(h)))).chain(body);
+
+    let (content_type, extension) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Json => ("application/x-ndjson", "ndjson"),
+    };
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(content_type)
+        .insert_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}-analytics-{}-{}.{}\"", domain, export.from, export.to, extension),
+        ))
+        .insert_header(("Cache-Control", "no-store"));
+    if truncated {
+        response.insert_header((EXPORT_TRUNCATED_HEADER, format!(
+            "{}; retention_days={}", export.from, prometheus.retention_days(),
+        )));
+    }
+    Ok(response.streaming(body))
+}
+
 pub async fn get_top_sites(
     prometheus: web::Data<PrometheusAnalytics>,
     query: web::Query<SearchQuery>,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    let limit = query.limit.unwrap_or(10);
-    let sites = prometheus.get_top_sites(limit).await
+    let limit = ApolloValidator::validate_limit(query.limit)?;
+    let after = page_after(query.cursor.as_deref(), decode_score_cursor)?;
+    let (sites, next_cursor) = prometheus.get_top_sites(limit, after).await
         .map_err(|e| ShadowError::BadRequest(e.to_string()))?;
     
-    Ok(HttpResponse::Ok().json(sites))
+    Ok(HttpResponse::Ok().json(page_body(sites, next_cursor)))
 }
 
 #[derive(Deserialize)]
@@ -906,13 +3284,69 @@ pub async fn record_performance(
     })))
 }
 
+// ========== Pheme Badge Handlers ==========
+
+/// Public, unauthenticated badge data. Unregistered domains get a neutral "unknown"
+/// badge rather than an error so embeds never break.
+async fn badge_data(
+    olympus: &OlympusCA,
+    prometheus: &PrometheusAnalytics,
+    domain: &str,
+    query: &BadgeQuery,
+) -> Result<BadgeData, ShadowError> {
+    let label = query.label()?;
+    let domain = domain.trim().to_lowercase();
+    let status = match olympus.get_domain(&domain).await.map_err(ShadowError::BadRequest)? {
+        Some(record) if record.verified => BadgeStatus::Verified,
+        Some(_) => BadgeStatus::Unverified,
+        None => BadgeStatus::Unknown,
+    };
+
+    let visits = match (status, query.stat) {
+        (BadgeStatus::Unknown, _) | (_, BadgeStat::None) => None,
+        (_, BadgeStat::Weekly) => Some(prometheus.recent_visits(&domain, 7).await?),
+        (_, BadgeStat::Total) => Some(
+            prometheus.get_analytics(&domain).await?
+                .map(|analytics| analytics.total_visits)
+                .unwrap_or(0),
+        ),
+    };
+
+    Ok(BadgeData::new(&domain, status, label, query.stat, visits))
+}
+
+pub async fn get_badge_svg(
+    olympus: web::Data<OlympusCA>,
+    prometheus: web::Data<PrometheusAnalytics>,
+    path: web::Path<String>,
+    query: web::Query<BadgeQuery>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let badge = badge_data(&olympus, &prometheus, &path, &query).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/svg+xml; charset=utf-8")
+        .insert_header(("Cache-Control", pheme::BADGE_CACHE_CONTROL))
+        .body(pheme::render_svg(&badge, query.style)))
+}
+
+pub async fn get_badge_json(
+    olympus: web::Data<OlympusCA>,
+    prometheus: web::Data<PrometheusAnalytics>,
+    path: web::Path<String>,
+    query: web::Query<BadgeQuery>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let badge = badge_data(&olympus, &prometheus, &path, &query).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", pheme::BADGE_CACHE_CONTROL))
+        .json(badge))
+}
+
 // ========== Hephaestus Cache Handlers ==========
 
 pub async fn get_cache_stats(
     hephaestus: web::Data<HephaestusCache>,
-    metrics: web::Data<MetricsCollector>,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    metrics.record_cache_hit(); // Cache stats access is a cache operation
     let stats = hephaestus.get_stats().await;
     Ok(HttpResponse::Ok().json(stats))
 }
@@ -926,11 +3360,100 @@ pub async fn clear_cache(
     })))
 }
 
+// ========== Mnemosyne Outbox Admin Handlers ==========
+
+#[derive(Deserialize)]
+pub struct OutboxListQuery {
+    /// Only entries in this state; defaults to everything not yet relayed
+    pub status: Option<OutboxStatus>,
+    pub limit: Option<i64>,
+}
+
+pub async fn list_outbox(
+    mnemosyne: web::Data<Mnemosyne>,
+    ares: web::Data<AresAuth>,
+    config: web::Data<ShadowConfig>,
+    query: web::Query<OutboxListQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    crate::wallet_handlers::verify_admin(&req, &ares, &config)?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let entries = mnemosyne.list_entries(query.status, limit).await
+        .map_err(ShadowError::BadRequest)?;
+    let stats = mnemosyne.stats().await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "stats": stats,
+        "entries": entries
+    })))
+}
+
+pub async fn retry_outbox_entry(
+    mnemosyne: web::Data<Mnemosyne>,
+    ares: web::Data<AresAuth>,
+    config: web::Data<ShadowConfig>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    crate::wallet_handlers::verify_admin(&req, &ares, &config)?;
+
+    let id = path.into_inner();
+    if !mnemosyne.retry(&id).await.map_err(ShadowError::BadRequest)? {
+        return Err(ShadowError::NotFound("No retryable outbox entry with that id".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "id": id
+    })))
+}
+
+// ========== Hecate GraphQL Handler ==========
+
+pub async fn graphql(
+    schema: web::Data<ShadowSchema>,
+    db: web::Data<Database>,
+    config: web::Data<ShadowConfig>,
+    artemis: web::Data<ArtemisRateLimiter>,
+    body: web::Json<async_graphql::Request>,
+    req: HttpRequest,
+    metrics: web::Data<MetricsCollector>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    if !config.features.graphql_enabled {
+        return Err(ShadowError::NotFound("GraphQL endpoint is disabled".to_string()));
+    }
+
+    // Rate limiting
+    let client_ip = req.peer_addr().map(|a| a.ip().to_string());
+    let key = ArtemisRateLimiter::get_client_key(client_ip.as_deref(), None);
+    artemis.check_rate_limit(&key)
+        .map_err(ShadowError::BadRequest)?;
+
+    metrics.record_database_query();
+    let response = hecate::execute(&schema, &db, body.into_inner()).await;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
 // ========== Metrics Handler ==========
 
+/// Backend metrics as JSON, or in the Prometheus text format for scrapers that accept text/plain
 pub async fn get_metrics(
     metrics: web::Data<MetricsCollector>,
+    req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
+    let wants_text = req.headers()
+        .get(actix_web::http::header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/plain"));
+    if wants_text {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/plain; version=0.0.4; charset=utf-8")
+            .body(metrics.to_prometheus_text()));
+    }
+
     let metrics_data = metrics.get_metrics();
     Ok(HttpResponse::Ok().json(metrics_data))
 }
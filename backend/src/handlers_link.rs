@@ -2,30 +2,67 @@
 
 use actix_web::{web, HttpRequest, HttpResponse, Result as ActixResult};
 use crate::error::ShadowError;
-use crate::link_converter::{LinkConverter, ConvertLinkRequest, GeneralTokenRequest};
+use crate::link_converter::{LinkConverter, LinkMintAuthority, ConvertLinkRequest, GeneralTokenRequest};
+use crate::apollo::ApolloValidator;
 use crate::ares::AresAuth;
+use crate::artemis::ArtemisRateLimiter;
+use crate::config::ShadowConfig;
+use crate::enodia::EnodiaLinkInfo;
+use crate::db;
+use crate::olympus::OlympusCA;
+use crate::utils::decode_cursor;
 use mongodb::Database;
+use serde::Deserialize;
 use std::sync::Arc;
 
+/// Longest URL accepted by the link info lookup
+const MAX_LINK_INFO_URL: usize = 2048;
+
+#[derive(Deserialize)]
+pub struct LinkListQuery {
+    pub owner: String,
+    #[serde(default)]
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LinkInfoQuery {
+    pub url: String,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn convert_link(
     db: web::Data<Database>,
     body: web::Json<ConvertLinkRequest>,
     solana_rpc: web::Data<String>,
+    mint_authority: web::Data<LinkMintAuthority>,
     ares: web::Data<AresAuth>,
+    artemis: web::Data<ArtemisRateLimiter>,
+    config: web::Data<ShadowConfig>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
-    // Verify authentication
-    let _user_id = verify_auth(&req, &ares)?;
+    // Verify authentication; the mapping is recorded as the caller's
+    let wallet = verify_auth(&req, &ares)?;
+
+    // A new link pays for a mint, so conversions are charged per wallet and capped daily
+    let key = format!("link-convert:{}", ArtemisRateLimiter::get_client_key(None, Some(&wallet)));
+    artemis.check_rate_limit_with_cost(&key, config.link_tokens.convert_rate_limit_cost)
+        .map_err(ShadowError::BadRequest)?;
 
     let converter = LinkConverter::new(
         Arc::new(db.as_ref().clone()),
         solana_rpc.to_string(),
-    );
+    )
+    .with_mint_authority(mint_authority.get_ref().clone())
+    .with_daily_mint_quota(config.link_tokens.daily_mints_per_wallet);
 
     let result = converter
-        .convert_link(&body.url, body.sublink.as_deref())
+        .convert_link(&body.url, body.sublink.as_deref(), Some(&wallet))
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(result))
 }
@@ -34,6 +71,7 @@ pub async fn create_general_token(
     db: web::Data<Database>,
     body: web::Json<GeneralTokenRequest>,
     solana_rpc: web::Data<String>,
+    mint_authority: web::Data<LinkMintAuthority>,
     ares: web::Data<AresAuth>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
@@ -42,12 +80,13 @@ pub async fn create_general_token(
     let converter = LinkConverter::new(
         Arc::new(db.as_ref().clone()),
         solana_rpc.to_string(),
-    );
+    )
+    .with_mint_authority(mint_authority.get_ref().clone());
 
     let token_mint = converter
         .create_general_token(&body.platform, &body.token_name, &body.token_symbol)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Created().json(serde_json::json!({
         "token_mint": token_mint,
@@ -71,12 +110,12 @@ pub async fn get_url_from_token(
     );
 
     match converter.get_url_from_token(&token_mint).await
-        .map_err(|e| ShadowError::BadRequest(e))? {
+        .map_err(ShadowError::BadRequest)? {
         Some(url) => Ok(HttpResponse::Ok().json(serde_json::json!({
             "token_mint": token_mint,
             "original_url": url
         }))),
-        None => Err(ShadowError::NotFound(format!("Token {} not found", token_mint)).into()),
+        None => Err(ShadowError::NotFound(format!("Token {} not found", token_mint))),
     }
 }
 
@@ -100,28 +139,109 @@ pub async fn get_token_from_url(
     );
 
     match converter.get_token_from_url(&body.url).await
-        .map_err(|e| ShadowError::BadRequest(e))? {
+        .map_err(ShadowError::BadRequest)? {
         Some(token_mint) => Ok(HttpResponse::Ok().json(serde_json::json!({
             "url": body.url,
             "token_mint": token_mint
         }))),
-        None => Err(ShadowError::NotFound(format!("URL {} not found", body.url)).into()),
+        None => Err(ShadowError::NotFound(format!("URL {} not found", body.url))),
     }
 }
 
+/// Links a wallet has converted, newest first
+pub async fn list_links(
+    db: web::Data<Database>,
+    query: web::Query<LinkListQuery>,
+    solana_rpc: web::Data<String>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    verify_auth(&req, &ares)?;
+    ApolloValidator::validate_pubkey(&query.owner)?;
+    let limit = ApolloValidator::validate_limit(query.limit)?;
+    let after = query.cursor.as_deref().map(decode_cursor).transpose()
+        .map_err(ShadowError::BadRequest)?;
+
+    let converter = LinkConverter::new(
+        Arc::new(db.as_ref().clone()),
+        solana_rpc.to_string(),
+    );
+    let (mappings, next_cursor) = converter.list_mappings(&query.owner, limit, after).await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "items": mappings,
+        "next_cursor": next_cursor,
+    })))
+}
+
+/// Token for a site's primary domain, minted on first request
+pub async fn get_site_token(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    solana_rpc: web::Data<String>,
+    mint_authority: web::Data<LinkMintAuthority>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let program_address = path.into_inner();
+
+    let site = db::get_site(&db, &program_address).await?
+        .ok_or_else(|| ShadowError::NotFound("Site not found".to_string()))?;
+
+    // The primary domain is the owner's first verified domain pointing at this site
+    let olympus = OlympusCA::new(db.as_ref().clone());
+    let domain = olympus.list_owner_domains(&site.owner_pubkey).await
+        .map_err(ShadowError::BadRequest)?
+        .into_iter()
+        .find(|d| d.verified && d.program_address == program_address)
+        .ok_or_else(|| ShadowError::NotFound(format!("Site {} has no verified domain", program_address)))?;
+    let domain_url = format!("https://{}", domain.domain);
+
+    let converter = LinkConverter::new(
+        Arc::new(db.as_ref().clone()),
+        solana_rpc.to_string(),
+    )
+    .with_mint_authority(mint_authority.get_ref().clone());
+
+    let (token_mint, is_new) = match converter.get_token_from_url(&domain_url).await
+        .map_err(ShadowError::BadRequest)? {
+        Some(token_mint) => (token_mint, false),
+        None => {
+            let created = converter.convert_link(&domain_url, None, None).await
+                .map_err(ShadowError::BadRequest)?;
+            (created.token_mint, created.is_new)
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "token_mint": token_mint,
+        "domain": domain.domain,
+        "is_new": is_new
+    })))
+}
+
 fn verify_auth(req: &HttpRequest, ares: &AresAuth) -> Result<String, ShadowError> {
-    use crate::ares::AuthHeader;
-    
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)
-        .map_err(|_| ShadowError::Unauthorized)?;
-    auth.verify(ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    Ok(auth.wallet)
+    crate::ares::authenticate(req, ares)
 }
 
+/// Interstitial signals for a link leaving Shadow. Unauthenticated and charged heavily
+/// against the rate limit; the URL is only parsed, never requested.
+pub async fn get_link_info(
+    query: web::Query<LinkInfoQuery>,
+    enodia: web::Data<EnodiaLinkInfo>,
+    artemis: web::Data<ArtemisRateLimiter>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let client_ip = req.peer_addr().map(|a| a.ip().to_string());
+    let key = ArtemisRateLimiter::get_client_key(client_ip.as_deref(), None);
+    artemis.check_rate_limit_with_cost(&key, enodia.rate_limit_cost())
+        .map_err(ShadowError::BadRequest)?;
+
+    if query.url.len() > MAX_LINK_INFO_URL {
+        return Err(ShadowError::BadRequest(format!("URL longer than {} characters", MAX_LINK_INFO_URL)));
+    }
+    let info = enodia.lookup(&query.url).await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", format!("public, max-age={}", enodia.cache_ttl().as_secs())))
+        .json(info))
+}
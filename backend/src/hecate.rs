@@ -0,0 +1,294 @@
+// Hecate - Goddess of crossroads
+// Read-only GraphQL gateway composing sites, domains, profiles, search and analytics
+use crate::apollo::ApolloValidator;
+use crate::athena::{AthenaIndexer, SearchIndex, SearchOptions};
+use crate::db::{self, Site, User};
+use crate::olympus::{Domain, OlympusCA};
+use crate::prometheus::{PrometheusAnalytics, SiteAnalytics};
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema};
+use chrono::{DateTime, Utc};
+use mongodb::Database;
+use std::collections::HashMap;
+
+/// Deepest selection set a query may nest
+pub const MAX_QUERY_DEPTH: usize = 8;
+/// Upper bound on the summed field cost of a query
+pub const MAX_QUERY_COMPLEXITY: usize = 250;
+/// Most sites a single `sites` lookup may request
+const MAX_BATCH_SITES: usize = 50;
+
+pub type ShadowSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn build_schema(db: Database) -> ShadowSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(db)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+/// Run a request with fresh per-request loaders, so batching never leaks data between callers
+pub async fn execute(schema: &ShadowSchema, db: &Database, request: async_graphql::Request) -> async_graphql::Response {
+    let request = request
+        .data(DataLoader::new(DomainsForSiteLoader { olympus: OlympusCA::new(db.clone()) }, tokio::spawn))
+        .data(DataLoader::new(ProfileLoader { db: db.clone() }, tokio::spawn));
+    schema.execute(request).await
+}
+
+/// Batches `Site.domains` into one `$in` query per execution tick
+pub struct DomainsForSiteLoader {
+    olympus: OlympusCA,
+}
+
+impl Loader<String> for DomainsForSiteLoader {
+    type Value = Vec<Domain>;
+    type Error = String;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let mut grouped: HashMap<String, Vec<Domain>> = HashMap::new();
+        for domain in self.olympus.list_domains_for_programs(keys).await? {
+            grouped.entry(domain.program_address.clone()).or_default().push(domain);
+        }
+        Ok(grouped)
+    }
+}
+
+/// Batches owner profile lookups into one `$in` query per execution tick
+pub struct ProfileLoader {
+    db: Database,
+}
+
+impl Loader<String> for ProfileLoader {
+    type Value = User;
+    type Error = String;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Self::Value>, Self::Error> {
+        let users = db::get_users(&self.db, keys).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(users.into_iter().map(|user| (user.wallet_pubkey.clone(), user)).collect())
+    }
+}
+
+async fn load_profile(ctx: &Context<'_>, wallet: &str) -> async_graphql::Result<Option<ProfileNode>> {
+    let loader = ctx.data_unchecked::<DataLoader<ProfileLoader>>();
+    Ok(loader.load_one(wallet.to_string()).await?.map(ProfileNode))
+}
+
+pub struct QueryRoot;
+
+#[Object(name = "Query")]
+impl QueryRoot {
+    async fn site(&self, ctx: &Context<'_>, program_address: String) -> async_graphql::Result<Option<SiteNode>> {
+        let db = ctx.data_unchecked::<Database>();
+        Ok(db::get_site(db, &program_address).await?.map(SiteNode))
+    }
+
+    /// Several sites at once, in the order requested; unknown addresses are skipped
+    async fn sites(&self, ctx: &Context<'_>, program_addresses: Vec<String>) -> async_graphql::Result<Vec<SiteNode>> {
+        if program_addresses.len() > MAX_BATCH_SITES {
+            return Err(format!("At most {} sites per query", MAX_BATCH_SITES).into());
+        }
+        let db = ctx.data_unchecked::<Database>();
+        let mut sites: HashMap<String, Site> = db::get_sites(db, &program_addresses).await?
+            .into_iter()
+            .map(|site| (site.program_address.clone(), site))
+            .collect();
+        Ok(program_addresses.iter().filter_map(|address| sites.remove(address)).map(SiteNode).collect())
+    }
+
+    async fn domain(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<Option<DomainNode>> {
+        let olympus = OlympusCA::new(ctx.data_unchecked::<Database>().clone());
+        Ok(olympus.get_domain(&name).await?.map(DomainNode))
+    }
+
+    async fn profile(&self, ctx: &Context<'_>, wallet: String) -> async_graphql::Result<Option<ProfileNode>> {
+        load_profile(ctx, &wallet).await
+    }
+
+    #[graphql(complexity = "limit.unwrap_or(20) as usize * child_complexity")]
+    async fn search(&self, ctx: &Context<'_>, query: String, limit: Option<i64>) -> async_graphql::Result<Vec<SearchResultNode>> {
+        ApolloValidator::validate_search_query(&query)?;
+        let limit = ApolloValidator::validate_limit(limit)?;
+        let athena = AthenaIndexer::new(ctx.data_unchecked::<Database>().clone());
+        let (results, _) = athena.search(&query, SearchOptions::default(), limit, None).await?;
+        Ok(results.into_iter().map(SearchResultNode).collect())
+    }
+}
+
+pub struct SiteNode(Site);
+
+#[Object(name = "Site")]
+impl SiteNode {
+    async fn program_address(&self) -> &str {
+        &self.0.program_address
+    }
+
+    async fn owner_pubkey(&self) -> &str {
+        &self.0.owner_pubkey
+    }
+
+    async fn storage_cid(&self) -> &str {
+        &self.0.storage_cid
+    }
+
+    async fn name(&self) -> Option<&str> {
+        self.0.name.as_deref()
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    async fn default_language(&self) -> Option<&str> {
+        self.0.default_language.as_deref()
+    }
+
+    async fn verified_domain(&self) -> Option<&str> {
+        self.0.verified_domain.as_deref()
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+
+    async fn domains(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<DomainNode>> {
+        let loader = ctx.data_unchecked::<DataLoader<DomainsForSiteLoader>>();
+        let domains = loader.load_one(self.0.program_address.clone()).await?.unwrap_or_default();
+        Ok(domains.into_iter().map(DomainNode).collect())
+    }
+
+    async fn owner(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<ProfileNode>> {
+        load_profile(ctx, &self.0.owner_pubkey).await
+    }
+}
+
+pub struct DomainNode(Domain);
+
+#[Object(name = "Domain")]
+impl DomainNode {
+    async fn name(&self) -> &str {
+        &self.0.domain
+    }
+
+    async fn program_address(&self) -> &str {
+        &self.0.program_address
+    }
+
+    async fn owner_pubkey(&self) -> &str {
+        &self.0.owner_pubkey
+    }
+
+    async fn verified(&self) -> bool {
+        self.0.verified
+    }
+
+    async fn warning_interstitial(&self) -> bool {
+        self.0.warning_interstitial
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.0.expires_at
+    }
+
+    async fn site(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<SiteNode>> {
+        let db = ctx.data_unchecked::<Database>();
+        Ok(db::get_site(db, &self.0.program_address).await?.map(SiteNode))
+    }
+
+    async fn owner(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<ProfileNode>> {
+        load_profile(ctx, &self.0.owner_pubkey).await
+    }
+
+    async fn analytics(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<AnalyticsSummaryNode>> {
+        let prometheus = PrometheusAnalytics::new(ctx.data_unchecked::<Database>().clone());
+        Ok(prometheus.get_analytics(&self.0.domain).await?.map(AnalyticsSummaryNode))
+    }
+}
+
+pub struct ProfileNode(User);
+
+#[Object(name = "Profile")]
+impl ProfileNode {
+    async fn wallet_pubkey(&self) -> &str {
+        &self.0.wallet_pubkey
+    }
+
+    async fn profile_cid(&self) -> Option<&str> {
+        self.0.profile_cid.as_deref()
+    }
+
+    async fn is_public(&self) -> bool {
+        self.0.is_public
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+}
+
+pub struct SearchResultNode(SearchIndex);
+
+#[Object(name = "SearchResult")]
+impl SearchResultNode {
+    async fn domain(&self) -> &str {
+        &self.0.domain
+    }
+
+    async fn program_address(&self) -> &str {
+        &self.0.program_address
+    }
+
+    async fn title(&self) -> Option<&str> {
+        self.0.title.as_deref()
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    async fn language(&self) -> Option<&str> {
+        self.0.language.as_deref()
+    }
+
+    async fn popularity_score(&self) -> f64 {
+        self.0.popularity_score
+    }
+}
+
+pub struct AnalyticsSummaryNode(SiteAnalytics);
+
+#[Object(name = "AnalyticsSummary")]
+impl AnalyticsSummaryNode {
+    async fn domain(&self) -> &str {
+        &self.0.domain
+    }
+
+    async fn total_visits(&self) -> i64 {
+        self.0.total_visits
+    }
+
+    async fn unique_visitors(&self) -> i64 {
+        self.0.unique_visitors
+    }
+
+    async fn average_time_spent(&self) -> f64 {
+        self.0.average_time_spent
+    }
+
+    async fn bounce_rate(&self) -> f64 {
+        self.0.bounce_rate
+    }
+
+    async fn last_updated(&self) -> DateTime<Utc> {
+        self.0.last_updated
+    }
+}
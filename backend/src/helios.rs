@@ -0,0 +1,394 @@
+// Helios - The all-seeing sun
+// Domain watchlists. A wallet can watch any name, registered or not, and is told when
+// a watched name is about to lapse or becomes free to register.
+
+use crate::apollo::ApolloValidator;
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::WatchlistConfig;
+use crate::error::ShadowError;
+use crate::olympus::{Domain, ExpiryStatus, DOMAIN_GRACE_PERIOD_DAYS};
+use crate::themis::OwnerNotification;
+use crate::websocket::HermesBroker;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// A registered domain within this many days of `expires_at` shows as expiring
+pub const EXPIRING_SOON_DAYS: i64 = 30;
+
+/// Domains looked up per query during a scan
+const SCAN_BATCH_SIZE: usize = 100;
+
+/// A domain on a wallet's watchlist
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DomainWatch {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub wallet: String,
+    pub domain: String,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    /// Status kind when the watch was last scanned; alerts fire only when it changes
+    pub last_status: String,
+}
+
+/// What a watched domain looks like to someone who wants it
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WatchedStatus {
+    /// Never registered, or lapsed past its grace period
+    Available,
+    Registered {
+        expires_at: Option<DateTime<Utc>>,
+    },
+    /// Close to expiry or in its grace period; free to register from `available_at`
+    Expiring {
+        expires_at: DateTime<Utc>,
+        available_at: DateTime<Utc>,
+    },
+}
+
+impl WatchedStatus {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WatchedStatus::Available => "available",
+            WatchedStatus::Registered { .. } => "registered",
+            WatchedStatus::Expiring { .. } => "expiring",
+        }
+    }
+
+    /// Message sent to watchers when a domain moves into this status, if it warrants one
+    fn alert(&self, domain: &str) -> Option<(&'static str, String)> {
+        match self {
+            WatchedStatus::Available => Some((
+                "watched_domain_available",
+                format!("{} is available to register", domain),
+            )),
+            WatchedStatus::Expiring { expires_at, available_at } => Some((
+                "watched_domain_expiring",
+                format!(
+                    "{} expires on {} and becomes available on {} unless it is renewed",
+                    domain,
+                    expires_at.format("%Y-%m-%d"),
+                    available_at.format("%Y-%m-%d"),
+                ),
+            )),
+            WatchedStatus::Registered { .. } => None,
+        }
+    }
+}
+
+/// Work out a watched domain's status from its registration, if any
+pub fn status_at(record: Option<&Domain>, now: DateTime<Utc>) -> WatchedStatus {
+    let Some(record) = record else {
+        return WatchedStatus::Available;
+    };
+    match (record.expiry_status(now), record.expires_at) {
+        (ExpiryStatus::Expired, _) => WatchedStatus::Available,
+        (_, Some(expires_at)) if now + Duration::days(EXPIRING_SOON_DAYS) >= expires_at => WatchedStatus::Expiring {
+            expires_at,
+            available_at: expires_at + Duration::days(DOMAIN_GRACE_PERIOD_DAYS),
+        },
+        (_, expires_at) => WatchedStatus::Registered { expires_at },
+    }
+}
+
+/// One row of `GET /api/watchlist`
+#[derive(Debug, Serialize, Clone)]
+pub struct WatchlistEntry {
+    pub domain: String,
+    pub watched_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub status: WatchedStatus,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct WatchScanReport {
+    pub domains: usize,
+    pub alerts: usize,
+}
+
+pub struct HeliosWatchlist {
+    db: Database,
+    config: WatchlistConfig,
+    broker: Option<Arc<HermesBroker>>,
+    clock: SharedClock,
+}
+
+impl HeliosWatchlist {
+    pub fn new(db: Database, config: WatchlistConfig) -> Self {
+        Self {
+            db,
+            config,
+            broker: None,
+            clock: SystemClock::shared(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Also push watch alerts to live Hermes subscribers
+    pub fn with_broker(mut self, broker: Arc<HermesBroker>) -> Self {
+        self.broker = Some(broker);
+        self
+    }
+
+    fn get_watches_collection(&self) -> Collection<DomainWatch> {
+        self.db.collection::<DomainWatch>("domain_watches")
+    }
+
+    fn get_domains_collection(&self) -> Collection<Domain> {
+        self.db.collection::<Domain>("domains")
+    }
+
+    fn watch_id(wallet: &str, domain: &str) -> String {
+        format!("{}:{}", wallet, domain)
+    }
+
+    /// Registrations for the given names, keyed by name
+    async fn lookup(&self, domains: &[String]) -> Result<HashMap<String, Domain>, ShadowError> {
+        let records: Vec<Domain> = self.get_domains_collection()
+            .find(doc! { "_id": { "$in": domains } }, None).await?
+            .try_collect().await?;
+        Ok(records.into_iter().map(|record| (record.domain.clone(), record)).collect())
+    }
+
+    /// Add a domain to a wallet's watchlist. Watching a name twice is a no-op.
+    pub async fn watch(&self, wallet: &str, domain: &str) -> Result<WatchlistEntry, ShadowError> {
+        let domain = domain.trim().to_lowercase();
+        ApolloValidator::validate_domain(&domain)?;
+
+        let collection = self.get_watches_collection();
+        let id = Self::watch_id(wallet, &domain);
+        if let Some(existing) = collection.find_one(doc! { "_id": &id }, None).await? {
+            return self.entry(existing).await;
+        }
+
+        let watched = collection.count_documents(doc! { "wallet": wallet }, None).await?;
+        if watched >= self.config.max_per_wallet {
+            return Err(ShadowError::BadRequest(format!(
+                "A wallet can watch at most {} domains; unwatch one first",
+                self.config.max_per_wallet
+            )));
+        }
+
+        // Start from the current status so watching an already free name doesn't alert
+        let now = self.clock.now_utc();
+        let record = self.get_domains_collection().find_one(doc! { "_id": &domain }, None).await?;
+        let status = status_at(record.as_ref(), now);
+        let watch = DomainWatch {
+            id,
+            wallet: wallet.to_string(),
+            domain: domain.clone(),
+            created_at: now,
+            last_status: status.kind().to_string(),
+        };
+        collection.insert_one(&watch, None).await?;
+
+        Ok(WatchlistEntry { domain, watched_at: now, status })
+    }
+
+    pub async fn unwatch(&self, wallet: &str, domain: &str) -> Result<(), ShadowError> {
+        let id = Self::watch_id(wallet, &domain.trim().to_lowercase());
+        let result = self.get_watches_collection().delete_one(doc! { "_id": id }, None).await?;
+        if result.deleted_count == 0 {
+            return Err(ShadowError::NotFound("Domain is not on your watchlist".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn entry(&self, watch: DomainWatch) -> Result<WatchlistEntry, ShadowError> {
+        let records = self.lookup(std::slice::from_ref(&watch.domain)).await?;
+        Ok(WatchlistEntry {
+            status: status_at(records.get(&watch.domain), self.clock.now_utc()),
+            domain: watch.domain,
+            watched_at: watch.created_at,
+        })
+    }
+
+    /// A wallet's watchlist with each domain's current status, most recently watched first
+    pub async fn list(&self, wallet: &str) -> Result<Vec<WatchlistEntry>, ShadowError> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        let watches: Vec<DomainWatch> = self.get_watches_collection()
+            .find(doc! { "wallet": wallet }, options).await?
+            .try_collect().await?;
+
+        let names: Vec<String> = watches.iter().map(|w| w.domain.clone()).collect();
+        let records = self.lookup(&names).await?;
+        let now = self.clock.now_utc();
+        Ok(watches
+            .into_iter()
+            .map(|watch| WatchlistEntry {
+                status: status_at(records.get(&watch.domain), now),
+                domain: watch.domain,
+                watched_at: watch.created_at,
+            })
+            .collect())
+    }
+
+    /// Re-check every watched domain and alert the watchers of any whose status changed.
+    /// Each watch remembers the status it last saw, so an alert goes out once per change.
+    pub async fn run_once(&self, shutdown: &CancellationToken) -> Result<WatchScanReport, ShadowError> {
+        let mut report = WatchScanReport::default();
+        let mut domains: Vec<String> = self.get_watches_collection()
+            .distinct("domain", None, None).await?
+            .into_iter()
+            .filter_map(|d| d.as_str().map(str::to_string))
+            .collect();
+        domains.sort();
+
+        for batch in domains.chunks(SCAN_BATCH_SIZE) {
+            if shutdown.is_cancelled() {
+                break;
+            }
+            let records = self.lookup(batch).await?;
+            let now = self.clock.now_utc();
+            for domain in batch {
+                report.domains += 1;
+                let status = status_at(records.get(domain), now);
+                report.alerts += self.advance_watchers(domain, &status).await?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Move every watch on `domain` to `status`, notifying each watcher the first time
+    async fn advance_watchers(&self, domain: &str, status: &WatchedStatus) -> Result<usize, ShadowError> {
+        let collection = self.get_watches_collection();
+        let stale: Vec<DomainWatch> = collection
+            .find(doc! { "domain": domain, "last_status": { "$ne": status.kind() } }, None).await?
+            .try_collect().await?;
+
+        let mut alerts = 0;
+        for watch in stale {
+            // Conditional on the old status so overlapping scans can't both alert
+            let result = collection.update_one(
+                doc! { "_id": &watch.id, "last_status": &watch.last_status },
+                doc! { "$set": { "last_status": status.kind() } },
+                None,
+            ).await?;
+            if result.modified_count == 0 {
+                continue;
+            }
+            if let Some((kind, message)) = status.alert(domain) {
+                self.notify(&watch, kind, message).await?;
+                alerts += 1;
+            }
+        }
+        Ok(alerts)
+    }
+
+    async fn notify(&self, watch: &DomainWatch, kind: &str, message: String) -> Result<(), ShadowError> {
+        let notification = OwnerNotification {
+            id: uuid::Uuid::new_v4().to_string(),
+            wallet: watch.wallet.clone(),
+            domain: watch.domain.clone(),
+            kind: kind.to_string(),
+            message,
+            read: false,
+            created_at: self.clock.now_utc(),
+        };
+        self.db.collection::<OwnerNotification>("notifications").insert_one(&notification, None).await?;
+
+        if let Some(broker) = &self.broker {
+            if let Ok(json) = serde_json::to_string(&notification) {
+                broker.publish(&format!("wallet:{}", notification.wallet), json).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Scan on the configured interval until shutdown is requested
+    pub fn spawn(self: Arc<Self>, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        let interval = std::time::Duration::from_secs(self.config.scan_interval_seconds);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.run_once(&shutdown).await {
+                    Ok(report) => tracing::debug!("Helios watchlist scan: {:?}", report),
+                    Err(e) => tracing::warn!("Helios watchlist scan failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn registered(expires_at: Option<DateTime<Utc>>) -> Domain {
+        let created = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        Domain {
+            domain: "shop.shadow".to_string(),
+            owner_pubkey: "owner".to_string(),
+            program_address: "program".to_string(),
+            verified: false,
+            created_at: created,
+            updated_at: created,
+            expires_at,
+            verification_failures: 0,
+            warning_interstitial: false,
+            verification_error: None,
+            verified_authority: None,
+        }
+    }
+
+    #[test]
+    fn test_status_for_each_state() {
+        let now = Utc.with_ymd_and_hms(2026, 6, 1, 0, 0, 0).unwrap();
+        let day = Duration::days(1);
+
+        assert_eq!(status_at(None, now), WatchedStatus::Available);
+        assert_eq!(status_at(Some(&registered(None)), now), WatchedStatus::Registered { expires_at: None });
+
+        let far = now + day * 90;
+        assert_eq!(status_at(Some(&registered(Some(far))), now), WatchedStatus::Registered { expires_at: Some(far) });
+
+        let soon = now + day * 10;
+        assert_eq!(
+            status_at(Some(&registered(Some(soon))), now),
+            WatchedStatus::Expiring { expires_at: soon, available_at: soon + Duration::days(DOMAIN_GRACE_PERIOD_DAYS) }
+        );
+
+        // Still in its grace period
+        let lapsed = now - day * 5;
+        assert_eq!(status_at(Some(&registered(Some(lapsed))), now).kind(), "expiring");
+
+        let released = now - Duration::days(DOMAIN_GRACE_PERIOD_DAYS + 1);
+        assert_eq!(status_at(Some(&registered(Some(released))), now), WatchedStatus::Available);
+    }
+
+    #[test]
+    fn test_status_serializes_with_tag() {
+        let at = Utc.with_ymd_and_hms(2026, 6, 1, 0, 0, 0).unwrap();
+        let entry = WatchlistEntry {
+            domain: "shop.shadow".to_string(),
+            watched_at: at,
+            status: WatchedStatus::Expiring { expires_at: at, available_at: at + Duration::days(30) },
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["status"], "expiring");
+        assert_eq!(json["domain"], "shop.shadow");
+        assert_eq!(json["available_at"], "2026-07-01T00:00:00Z");
+
+        let json = serde_json::to_value(WatchedStatus::Available).unwrap();
+        assert_eq!(json, serde_json::json!({ "status": "available" }));
+    }
+}
@@ -1,10 +1,14 @@
 // Hephaestus: Forge and cache - Content caching and optimization
 use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use std::time::{Duration, Instant};
+use crate::clock::{SharedClock, SystemClock};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedContent {
@@ -23,43 +27,232 @@ struct CacheEntry {
     access_count: u64,
 }
 
+/// Largest object kept in the cache unless configured otherwise
+pub const DEFAULT_MAX_OBJECT_BYTES: usize = 4 * 1_048_576;
+
+/// How often expired entries are swept out in the background
+pub const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Entries closer than this to expiry are not worth writing to a snapshot
+const SNAPSHOT_MIN_REMAINING_SECS: i64 = 60;
+
+/// On-disk form of the cache: gzip-compressed bincode
+#[derive(Serialize, Deserialize)]
+struct CacheSnapshot {
+    max_size_mb: usize,
+    default_ttl_seconds: u64,
+    entries: Vec<(String, CachedContent, u64)>,
+}
+
 pub struct HephaestusCache {
     cache: Arc<RwLock<HashMap<String, CacheEntry>>>,
     max_size_mb: usize,
+    max_object_bytes: usize,
     default_ttl: Duration,
-    hits: Arc<std::sync::atomic::AtomicU64>,
-    misses: Arc<std::sync::atomic::AtomicU64>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+    /// Bytes of content held, kept in step with every insert and removal
+    size_estimate: Arc<AtomicUsize>,
+    /// Entries removed by the background sweep since startup
+    swept: Arc<AtomicU64>,
+    loaded_from_snapshot: bool,
+    clock: SharedClock,
+    sweep_interval: Duration,
+    sweeper: CancellationToken,
 }
 
 impl HephaestusCache {
+    /// Create an empty cache. When called inside a Tokio runtime this also starts the
+    /// background sweep, which runs until `shutdown` is called or the cache is dropped.
     pub fn new(max_size_mb: usize, default_ttl_seconds: u64) -> Self {
-        Self {
-            cache: Arc::new(RwLock::new(HashMap::new())),
+        Self::with_entries(max_size_mb, default_ttl_seconds, HashMap::new())
+    }
+
+    fn with_entries(max_size_mb: usize, default_ttl_seconds: u64, entries: HashMap<String, CacheEntry>) -> Self {
+        let size: usize = entries.values().map(|e| e.content.size_bytes).sum();
+        let mut cache = Self {
+            cache: Arc::new(RwLock::new(entries)),
             max_size_mb,
+            max_object_bytes: DEFAULT_MAX_OBJECT_BYTES,
             default_ttl: Duration::from_secs(default_ttl_seconds),
-            hits: Arc::new(std::sync::atomic::AtomicU64::new(0)),
-            misses: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+            size_estimate: Arc::new(AtomicUsize::new(size)),
+            swept: Arc::new(AtomicU64::new(0)),
+            loaded_from_snapshot: false,
+            clock: SystemClock::shared(),
+            sweep_interval: DEFAULT_SWEEP_INTERVAL,
+            sweeper: CancellationToken::new(),
+        };
+        cache.start_sweeper();
+        cache
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self.start_sweeper();
+        self
+    }
+
+    pub fn with_sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweep_interval = interval;
+        self.start_sweeper();
+        self
+    }
+
+    /// (Re)start the background sweep with the current clock and interval.
+    /// Outside a runtime (e.g. plain unit tests) expired entries are still dropped lazily on `get`.
+    fn start_sweeper(&mut self) {
+        self.sweeper.cancel();
+        self.sweeper = CancellationToken::new();
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let cache = Arc::clone(&self.cache);
+        let size_estimate = Arc::clone(&self.size_estimate);
+        let swept = Arc::clone(&self.swept);
+        let clock = Arc::clone(&self.clock);
+        let shutdown = self.sweeper.clone();
+        let interval = self.sweep_interval;
+        runtime.spawn(async move {
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                let removed = remove_expired(&cache, &size_estimate, clock.now_utc()).await;
+                if removed > 0 {
+                    swept.fetch_add(removed as u64, Ordering::Relaxed);
+                    tracing::debug!("Hephaestus swept {} expired entries", removed);
+                }
+            }
+        });
+    }
+
+    /// Stop the background sweep
+    pub fn shutdown(&self) {
+        self.sweeper.cancel();
+    }
+
+    /// Remove every expired entry now, returning how many were dropped
+    pub async fn sweep_expired(&self) -> usize {
+        let removed = remove_expired(&self.cache, &self.size_estimate, self.clock.now_utc()).await;
+        self.swept.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+
+    /// Number of entries held, including expired ones not yet swept
+    pub async fn len(&self) -> usize {
+        self.cache.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.cache.read().await.is_empty()
+    }
+
+    /// Bytes of content held, without walking the cache
+    pub fn size_bytes(&self) -> usize {
+        self.size_estimate.load(Ordering::Relaxed)
+    }
+
+    pub fn with_max_object_bytes(mut self, max_object_bytes: usize) -> Self {
+        self.max_object_bytes = max_object_bytes;
+        self
+    }
+
+    /// Whether an object of `size_bytes` is small enough to be cached
+    pub fn accepts(&self, size_bytes: u64) -> bool {
+        size_bytes <= self.max_object_bytes as u64
+    }
+
+    /// Cache key for a file served from a site's storage
+    pub fn site_asset_key(program_address: &str, path: &str) -> String {
+        format!("asset:{}:{}", program_address, path)
+    }
+
+    /// Cache key for a site's entry file, by the storage CID it was read from; each
+    /// negotiated language gets its own entry under the same prefix
+    pub fn site_content_key(storage_cid: &str, language: Option<&str>) -> String {
+        match language {
+            Some(lang) => format!("site:{}:{}", storage_cid, lang.to_ascii_lowercase()),
+            None => format!("site:{}", storage_cid),
         }
     }
 
+    /// Write the cache to `path`, skipping entries that expire within a minute.
+    /// The file is written next to `path` and renamed into place so a crash never leaves half a snapshot.
+    pub async fn save_snapshot(&self, path: &Path) -> Result<(), String> {
+        let cutoff = self.clock.now_utc() + chrono::Duration::seconds(SNAPSHOT_MIN_REMAINING_SECS);
+        let snapshot = {
+            let cache = self.cache.read().await;
+            CacheSnapshot {
+                max_size_mb: self.max_size_mb,
+                default_ttl_seconds: self.default_ttl.as_secs(),
+                entries: cache
+                    .iter()
+                    .filter(|(_, entry)| entry.content.expires_at > cutoff)
+                    .map(|(key, entry)| (key.clone(), entry.content.clone(), entry.access_count))
+                    .collect(),
+            }
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp_path)
+            .map_err(|e| format!("Failed to create snapshot: {}", e))?;
+        let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        bincode::serialize_into(&mut encoder, &snapshot)
+            .map_err(|e| format!("Failed to serialize snapshot: {}", e))?;
+        encoder.finish()
+            .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+        std::fs::rename(&tmp_path, path)
+            .map_err(|e| format!("Failed to write snapshot: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Rebuild a cache from a snapshot written by `save_snapshot`, dropping entries that expired meanwhile
+    pub fn load_snapshot(path: &Path) -> Result<Self, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("Failed to open snapshot: {}", e))?;
+        let snapshot: CacheSnapshot = bincode::deserialize_from(flate2::read::GzDecoder::new(file))
+            .map_err(|e| format!("Failed to read snapshot: {}", e))?;
+
+        let clock = SystemClock::shared();
+        let now = clock.now_utc();
+        let entries = snapshot.entries
+            .into_iter()
+            .filter(|(_, content, _)| content.expires_at > now)
+            .map(|(key, content, access_count)| {
+                (key, CacheEntry { content, last_accessed: clock.now_instant(), access_count })
+            })
+            .collect();
+        let mut cache = Self::with_entries(snapshot.max_size_mb, snapshot.default_ttl_seconds, entries);
+        cache.loaded_from_snapshot = true;
+        Ok(cache)
+    }
+
     pub async fn get(&self, key: &str) -> Option<CachedContent> {
         let mut cache = self.cache.write().await;
         
         if let Some(entry) = cache.get_mut(key) {
             // Check if expired
-            if Utc::now() > entry.content.expires_at {
-                cache.remove(key);
-                self.misses.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            if self.clock.now_utc() > entry.content.expires_at {
+                if let Some(expired) = cache.remove(key) {
+                    self.size_estimate.fetch_sub(expired.content.size_bytes, Ordering::Relaxed);
+                }
+                self.misses.fetch_add(1, Ordering::Relaxed);
                 return None;
             }
             
-            entry.last_accessed = Instant::now();
+            entry.last_accessed = self.clock.now_instant();
             entry.access_count += 1;
-            self.hits.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.hits.fetch_add(1, Ordering::Relaxed);
             return Some(entry.content.clone());
         }
         
-        self.misses.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.misses.fetch_add(1, Ordering::Relaxed);
         None
     }
 
@@ -70,17 +263,22 @@ impl HephaestusCache {
         content_type: String,
         ttl: Option<Duration>,
     ) -> Result<(), String> {
+        // Large objects would evict everything else; callers stream them instead
+        if !self.accepts(content.len() as u64) {
+            return Ok(());
+        }
+
         let mut cache = self.cache.write().await;
         
         // Check cache size and evict if needed
         self.evict_if_needed(&mut cache, content.len()).await;
         
-        let now = Utc::now();
+        let now = self.clock.now_utc();
         let ttl_duration = ttl.unwrap_or(self.default_ttl);
         let expires_at = now + chrono::Duration::from_std(ttl_duration)
             .map_err(|e| format!("Invalid TTL: {}", e))?;
         
-        let etag = self.generate_etag(&content);
+        let etag = Self::etag(&content);
         
         let cached_content = CachedContent {
             content: content.clone(),
@@ -93,27 +291,41 @@ impl HephaestusCache {
         
         let entry = CacheEntry {
             content: cached_content,
-            last_accessed: Instant::now(),
+            last_accessed: self.clock.now_instant(),
             access_count: 0,
         };
         
-        cache.insert(key, entry);
+        self.size_estimate.fetch_add(entry.content.size_bytes, Ordering::Relaxed);
+        if let Some(replaced) = cache.insert(key, entry) {
+            self.size_estimate.fetch_sub(replaced.content.size_bytes, Ordering::Relaxed);
+        }
         Ok(())
     }
 
     pub async fn invalidate(&self, key: &str) {
         let mut cache = self.cache.write().await;
-        cache.remove(key);
+        if let Some(removed) = cache.remove(key) {
+            self.size_estimate.fetch_sub(removed.content.size_bytes, Ordering::Relaxed);
+        }
     }
 
     pub async fn invalidate_pattern(&self, pattern: &str) {
         let mut cache = self.cache.write().await;
-        cache.retain(|k, _| !k.contains(pattern));
+        let mut freed = 0;
+        cache.retain(|k, entry| {
+            let keep = !k.contains(pattern);
+            if !keep {
+                freed += entry.content.size_bytes;
+            }
+            keep
+        });
+        self.size_estimate.fetch_sub(freed, Ordering::Relaxed);
     }
 
     pub async fn clear(&self) {
         let mut cache = self.cache.write().await;
         cache.clear();
+        self.size_estimate.store(0, Ordering::Relaxed);
     }
 
     pub async fn get_stats(&self) -> CacheStats {
@@ -128,8 +340,8 @@ impl HephaestusCache {
             total_accesses += entry.access_count;
         }
         
-        let hits = self.hits.load(std::sync::atomic::Ordering::Relaxed);
-        let misses = self.misses.load(std::sync::atomic::Ordering::Relaxed);
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
         let total_requests = hits + misses;
         let hit_rate = if total_requests > 0 {
             hits as f64 / total_requests as f64
@@ -142,6 +354,9 @@ impl HephaestusCache {
             total_size_mb: total_size as f64 / 1_048_576.0,
             total_accesses,
             hit_rate,
+            loaded_from_snapshot: self.loaded_from_snapshot,
+            size_bytes: self.size_bytes(),
+            expired_swept: self.swept.load(Ordering::Relaxed),
         }
     }
 
@@ -156,12 +371,13 @@ impl HephaestusCache {
                 .map(|(k, v)| (k.clone(), v.last_accessed, v.access_count))
                 .collect();
             
-            entries.sort_by(|a, b| a.1.cmp(&b.1));
+            entries.sort_by_key(|a| a.1);
             
             let mut freed = 0;
             for (key, _, _) in entries {
                 if let Some(entry) = cache.remove(&key) {
                     freed += entry.content.size_bytes;
+                    self.size_estimate.fetch_sub(entry.content.size_bytes, Ordering::Relaxed);
                     if current_size - freed + new_size <= max_size_bytes {
                         break;
                     }
@@ -170,7 +386,8 @@ impl HephaestusCache {
         }
     }
 
-    fn generate_etag(&self, content: &[u8]) -> String {
+    /// Strong ETag for `content`, as stored alongside it
+    pub fn etag(content: &[u8]) -> String {
         use sha2::{Sha256, Digest};
         let mut hasher = Sha256::new();
         hasher.update(content);
@@ -184,5 +401,173 @@ pub struct CacheStats {
     pub total_size_mb: f64,
     pub total_accesses: u64,
     pub hit_rate: f64,
+    pub loaded_from_snapshot: bool,
+    /// Running byte count kept alongside the entries, as opposed to `total_size_mb` which walks them
+    pub size_bytes: usize,
+    pub expired_swept: u64,
+}
+
+impl Drop for HephaestusCache {
+    fn drop(&mut self) {
+        self.sweeper.cancel();
+    }
+}
+
+/// Drop entries that expired before `now` and take their bytes off the size estimate
+async fn remove_expired(
+    cache: &RwLock<HashMap<String, CacheEntry>>,
+    size_estimate: &AtomicUsize,
+    now: DateTime<Utc>,
+) -> usize {
+    let mut cache = cache.write().await;
+    let before = cache.len();
+    let mut freed = 0;
+    cache.retain(|_, entry| {
+        let fresh = entry.content.expires_at >= now;
+        if !fresh {
+            freed += entry.content.size_bytes;
+        }
+        fresh
+    });
+    size_estimate.fetch_sub(freed, Ordering::Relaxed);
+    before - cache.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("hephaestus-{}.snapshot", uuid::Uuid::new_v4().simple()))
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip_skips_expiring_entries() {
+        let cache = HephaestusCache::new(16, 3600);
+        cache.set("site:a".to_string(), b"<h1>a</h1>".to_vec(), "text/html".to_string(), None).await.unwrap();
+        cache.set("site:b".to_string(), b"soon".to_vec(), "text/plain".to_string(), Some(Duration::from_secs(30))).await.unwrap();
+
+        let path = snapshot_path();
+        cache.save_snapshot(&path).await.unwrap();
+        let restored = HephaestusCache::load_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let stats = restored.get_stats().await;
+        assert!(stats.loaded_from_snapshot);
+        assert_eq!(stats.total_entries, 1);
+        assert_eq!(restored.get("site:a").await.unwrap().content, b"<h1>a</h1>");
+        assert!(restored.get("site:b").await.is_none());
+        assert!(!cache.get_stats().await.loaded_from_snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_site_content_cached_per_language() {
+        let en = HephaestusCache::site_content_key("ipfs://QmSite", Some("en"));
+        let es = HephaestusCache::site_content_key("ipfs://QmSite", Some("es"));
+        assert_ne!(en, es);
+        assert_eq!(es, HephaestusCache::site_content_key("ipfs://QmSite", Some("ES")));
+        assert_ne!(en, HephaestusCache::site_content_key("ipfs://QmSite", None));
+
+        let cache = HephaestusCache::new(16, 3600);
+        cache.set(en.clone(), b"Hello".to_vec(), "text/html".to_string(), None).await.unwrap();
+        cache.set(es.clone(), b"Hola".to_vec(), "text/html".to_string(), None).await.unwrap();
+        assert_eq!(cache.get(&en).await.unwrap().content, b"Hello");
+        assert_eq!(cache.get(&es).await.unwrap().content, b"Hola");
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire_after_the_ttl() {
+        let clock = crate::clock::TestClock::starting_now();
+        let cache = HephaestusCache::new(16, 60).with_clock(clock.clone());
+        cache.set("site:a".to_string(), b"a".to_vec(), "text/plain".to_string(), None).await.unwrap();
+
+        clock.advance(Duration::from_secs(60));
+        assert!(cache.get("site:a").await.is_some(), "still fresh at exactly the TTL");
+        clock.advance(Duration::from_millis(1));
+        assert!(cache.get("site:a").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_objects_are_not_cached() {
+        let cache = HephaestusCache::new(16, 3600).with_max_object_bytes(8);
+        assert!(cache.accepts(8));
+        assert!(!cache.accepts(9));
+        cache.set("asset:a".to_string(), vec![0; 9], "video/mp4".to_string(), None).await.unwrap();
+        cache.set("asset:b".to_string(), vec![0; 8], "video/mp4".to_string(), None).await.unwrap();
+        assert!(cache.get("asset:a").await.is_none());
+        assert_eq!(cache.get("asset:b").await.unwrap().size_bytes, 8);
+    }
+
+    #[tokio::test]
+    async fn test_size_tracks_inserts_and_removals() {
+        let cache = HephaestusCache::new(16, 3600);
+        cache.set("site:a".to_string(), vec![0; 10], "text/plain".to_string(), None).await.unwrap();
+        cache.set("site:b".to_string(), vec![0; 20], "text/plain".to_string(), None).await.unwrap();
+        cache.set("asset:a".to_string(), vec![0; 5], "text/plain".to_string(), None).await.unwrap();
+        assert_eq!((cache.len().await, cache.size_bytes()), (3, 35));
+
+        // Replacing an entry counts only the new content
+        cache.set("site:a".to_string(), vec![0; 4], "text/plain".to_string(), None).await.unwrap();
+        assert_eq!((cache.len().await, cache.size_bytes()), (3, 29));
+
+        cache.invalidate("site:b").await;
+        assert_eq!(cache.size_bytes(), 9);
+        cache.invalidate_pattern("asset:").await;
+        assert_eq!((cache.len().await, cache.size_bytes()), (1, 4));
+        cache.clear().await;
+        assert!(cache.is_empty().await);
+        assert_eq!(cache.size_bytes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sweep_removes_only_expired_entries() {
+        let clock = crate::clock::TestClock::starting_now();
+        let cache = HephaestusCache::new(16, 60).with_clock(clock.clone());
+        cache.set("site:a".to_string(), vec![0; 10], "text/plain".to_string(), None).await.unwrap();
+        cache.set("site:b".to_string(), vec![0; 3], "text/plain".to_string(), Some(Duration::from_secs(600))).await.unwrap();
+
+        assert_eq!(cache.sweep_expired().await, 0);
+        clock.advance(Duration::from_secs(61));
+        assert_eq!(cache.sweep_expired().await, 1);
+        assert_eq!((cache.len().await, cache.size_bytes()), (1, 3));
+        assert!(cache.get("site:b").await.is_some());
+
+        let stats = cache.get_stats().await;
+        assert_eq!((stats.size_bytes, stats.expired_swept), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_background_sweep_runs_until_shutdown() {
+        let clock = crate::clock::TestClock::starting_now();
+        let cache = HephaestusCache::new(16, 60)
+            .with_clock(clock.clone())
+            .with_sweep_interval(Duration::from_millis(10));
+        cache.set("site:a".to_string(), vec![0; 10], "text/plain".to_string(), None).await.unwrap();
+        clock.advance(Duration::from_secs(61));
+
+        // Nothing reads the entry, so only the sweep can remove it
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !cache.is_empty().await {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("expired entry was never swept");
+        assert_eq!(cache.size_bytes(), 0);
+
+        cache.shutdown();
+        cache.set("site:b".to_string(), vec![0; 10], "text/plain".to_string(), None).await.unwrap();
+        clock.advance(Duration::from_secs(61));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[test]
+    fn test_load_snapshot_rejects_garbage() {
+        let path = snapshot_path();
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        assert!(HephaestusCache::load_snapshot(&path).is_err());
+        std::fs::remove_file(&path).ok();
+    }
 }
 
@@ -1,10 +1,14 @@
 // Hestia - Goddess of Home and Connections
 // Handles dApp connections, permissions, and session management
 
-use mongodb::{Collection, Database};
-use mongodb::bson::{doc, DateTime};
+use mongodb::{Collection, Database, IndexModel};
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::options::FindOptions;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use crate::argus::OriginRisk;
+use crate::iris::ListResponse;
+use crate::utils::{cursor_filter, encode_cursor};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DAppConnection {
@@ -18,6 +22,30 @@ pub struct DAppConnection {
     pub permissions: Vec<Permission>,
     pub connected_at: DateTime,
     pub last_used: DateTime,
+    /// Connections stored before statuses existed were all approved
+    #[serde(default)]
+    pub status: ConnectionStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionStatus {
+    #[default]
+    Approved,
+    Pending,
+    Revoked,
+}
+
+/// Filters for listing a user's dApp connections, newest first.
+/// Without a status filter revoked connections are left out.
+#[derive(Debug, Default, Deserialize)]
+pub struct ConnectionListQuery {
+    pub status: Option<ConnectionStatus>,
+    pub dapp_origin: Option<String>,
+    pub created_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_before: Option<chrono::DateTime<chrono::Utc>>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -46,6 +74,12 @@ pub struct DAppConnectionResponse {
     pub dapp_icon: Option<String>,
     pub permissions: Vec<Permission>,
     pub connected_at: DateTime,
+    pub status: ConnectionStatus,
+    /// Filled in from Argus at response time so later blocks/verifications show up
+    #[serde(default)]
+    pub risk: OriginRisk,
+    #[serde(default)]
+    pub risk_reason: Option<String>,
 }
 
 pub struct HestiaConnectionManager {
@@ -89,6 +123,7 @@ impl HestiaConnectionManager {
             // Update existing connection
             conn.permissions = requested_permissions.clone();
             conn.last_used = DateTime::now();
+            conn.status = ConnectionStatus::Approved;
 
             // Convert permissions to BSON
             let permissions_bson: Vec<mongodb::bson::Bson> = conn.permissions.iter()
@@ -101,7 +136,8 @@ impl HestiaConnectionManager {
                     doc! {
                         "$set": {
                             "permissions": permissions_bson,
-                            "last_used": conn.last_used
+                            "last_used": conn.last_used,
+                            "status": "approved"
                         }
                     },
                     None,
@@ -116,6 +152,9 @@ impl HestiaConnectionManager {
                 dapp_icon: conn.dapp_icon,
                 permissions: conn.permissions,
                 connected_at: conn.connected_at,
+                status: conn.status,
+                risk: OriginRisk::Unknown,
+                risk_reason: None,
             })
         } else {
             // Create new connection
@@ -129,6 +168,7 @@ impl HestiaConnectionManager {
                 permissions: requested_permissions.clone(),
                 connected_at: DateTime::now(),
                 last_used: DateTime::now(),
+                status: ConnectionStatus::Approved,
             };
 
             collection
@@ -143,11 +183,14 @@ impl HestiaConnectionManager {
                 dapp_icon: connection.dapp_icon,
                 permissions: connection.permissions,
                 connected_at: connection.connected_at,
+                status: connection.status,
+                risk: OriginRisk::Unknown,
+                risk_reason: None,
             })
         }
     }
 
-    /// Disconnect a dApp
+    /// Disconnect a dApp. The connection is kept as revoked so it stays listable.
     pub async fn disconnect_dapp(
         &self,
         user_id: &str,
@@ -156,11 +199,12 @@ impl HestiaConnectionManager {
         let collection = self.get_collection();
 
         collection
-            .delete_one(
+            .update_one(
                 doc! {
                     "_id": connection_id,
                     "user_id": user_id
                 },
+                doc! { "$set": { "status": "revoked", "last_used": DateTime::now() } },
                 None,
             )
             .await
@@ -169,33 +213,96 @@ impl HestiaConnectionManager {
         Ok(())
     }
 
-    /// Get all connected dApps for a user
-    pub async fn get_connections(
+    /// Create the indexes behind `list_connections`
+    pub async fn ensure_indexes(&self) -> Result<(), String> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "status": 1, "connected_at": -1, "_id": -1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "dapp_origin": 1, "connected_at": -1, "_id": -1 })
+                .build(),
+        ];
+        self.get_collection()
+            .create_indexes(indexes, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(())
+    }
+
+    /// List a user's connections matching `query`, newest first.
+    /// `total` counts every match, ignoring the cursor.
+    pub async fn list_connections(
         &self,
         user_id: &str,
-    ) -> Result<Vec<DAppConnectionResponse>, String> {
+        query: &ConnectionListQuery,
+        limit: i64,
+    ) -> Result<ListResponse<DAppConnectionResponse>, String> {
         let collection = self.get_collection();
-        let filter = doc! { "user_id": user_id };
+        let mut filter = doc! { "user_id": user_id };
+        match query.status {
+            // Legacy documents have no status and count as approved
+            Some(ConnectionStatus::Approved) => filter.insert("status", doc! { "$in": ["approved", null] }),
+            Some(status) => filter.insert("status", mongodb::bson::to_bson(&status).map_err(|e| e.to_string())?),
+            None => filter.insert("status", doc! { "$ne": "revoked" }),
+        };
+        if let Some(origin) = &query.dapp_origin {
+            filter.insert("dapp_origin", origin);
+        }
+        let mut connected_at = Document::new();
+        if let Some(after) = query.created_after {
+            connected_at.insert("$gt", DateTime::from_chrono(after));
+        }
+        if let Some(before) = query.created_before {
+            connected_at.insert("$lt", DateTime::from_chrono(before));
+        }
+        if !connected_at.is_empty() {
+            filter.insert("connected_at", connected_at);
+        }
 
-        let mut connections = Vec::new();
-        let mut cursor = collection
-            .find(filter, None)
+        let total = collection
+            .count_documents(filter.clone(), None)
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
-        while let Some(conn) = cursor.try_next().await
-            .map_err(|e| format!("Database error: {}", e))? {
-            connections.push(DAppConnectionResponse {
+        if let Some(cursor) = &query.cursor {
+            filter.extend(cursor_filter("connected_at", cursor)?);
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "connected_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+        let mut connections: Vec<DAppConnection> = collection
+            .find(filter, options)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let next_cursor = if connections.len() as i64 > limit {
+            connections.truncate(limit as usize);
+            connections.last().map(|conn| encode_cursor(conn.connected_at.timestamp_millis(), &conn.id))
+        } else {
+            None
+        };
+        let items = connections.into_iter()
+            .map(|conn| DAppConnectionResponse {
                 id: conn.id,
                 dapp_origin: conn.dapp_origin,
                 dapp_name: conn.dapp_name,
                 dapp_icon: conn.dapp_icon,
                 permissions: conn.permissions,
                 connected_at: conn.connected_at,
-            });
-        }
+                status: conn.status,
+                risk: OriginRisk::Unknown,
+                risk_reason: None,
+            })
+            .collect();
 
-        Ok(connections)
+        Ok(ListResponse::new(items)
+            .with_cursor(next_cursor)
+            .with_total(Some(total)))
     }
 
     /// Check if dApp has permission
@@ -219,7 +326,7 @@ impl HestiaConnectionManager {
             )
             .await
             .map_err(|e| format!("Database error: {}", e))? {
-            Ok(conn.permissions.contains(permission))
+            Ok(conn.status == ConnectionStatus::Approved && conn.permissions.contains(permission))
         } else {
             Ok(false)
         }
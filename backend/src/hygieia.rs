@@ -0,0 +1,413 @@
+// Hygieia - Goddess of Health
+// Self-checks that catch misconfigured deployments before users do
+
+use crate::anchor_client::{PROFILES_PROGRAM_ID, REGISTRY_PROGRAM_ID};
+use crate::config::ShadowConfig;
+use crate::storage::PinataStorage;
+use chrono::{DateTime, Utc};
+use futures_util::future::{join_all, BoxFuture};
+use futures_util::FutureExt;
+use mongodb::Database;
+use serde::Serialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Genesis hashes of the public clusters
+const CLUSTER_GENESIS: &[(&str, &str)] = &[
+    ("mainnet-beta", "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d"),
+    ("devnet", "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG"),
+    ("testnet", "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY"),
+];
+
+/// Indexes the server creates at startup, by collection
+const EXPECTED_INDEXES: &[(&str, &str)] = &[
+    ("users", "is_public_1__id_1"),
+    ("sites", "created_at_-1"),
+    ("sites", "collaborators.wallet_1"),
+    ("domains", "owner_pubkey_1_verified_1"),
+    ("domains", "program_address_1"),
+    ("domain_events", "domain_1_created_at_1"),
+    ("outbox", "status_1_available_at_1"),
+    ("site_versions", "program_address_1_created_at_-1"),
+    ("search_index", "outbound_links_1"),
+    ("search_index", "external_links_1"),
+    ("deploy_tokens", "program_address_1_created_at_-1"),
+    ("history_visits", "wallet_pubkey_1_visited_at_-1"),
+    ("history_visits", "expires_at_1"),
+    ("arweave_uploads", "status_1_created_at_1"),
+    ("domain_watches", "wallet_1_created_at_-1"),
+    ("domain_watches", "domain_1_last_status_1"),
+    ("search_queries", "created_at_1"),
+    ("site_events", "program_address_1_created_at_1"),
+    ("site_visit_days", "expires_at_1"),
+    ("site_visit_day_visitors", "expires_at_1"),
+    ("performance_metrics", "expires_at_1"),
+    ("portfolio_snapshots", "wallet_1_taken_at_1"),
+    ("pending_transactions", "user_id_1_created_at_-1__id_-1"),
+    ("pending_transactions", "user_id_1_status_1_created_at_-1__id_-1"),
+    ("pending_transactions", "user_id_1_dapp_origin_1_created_at_-1__id_-1"),
+    ("dapp_connections", "user_id_1_status_1_connected_at_-1__id_-1"),
+    ("dapp_connections", "user_id_1_dapp_origin_1_connected_at_-1__id_-1"),
+];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    fn label(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    /// What an operator should change when the check doesn't pass
+    pub remediation: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl DiagnosticCheck {
+    pub fn pass(name: &str, message: impl Into<String>) -> Self {
+        Self::new(name, CheckStatus::Pass, message.into(), None)
+    }
+
+    pub fn warn(name: &str, message: impl Into<String>, remediation: &str) -> Self {
+        Self::new(name, CheckStatus::Warn, message.into(), Some(remediation))
+    }
+
+    pub fn fail(name: &str, message: impl Into<String>, remediation: &str) -> Self {
+        Self::new(name, CheckStatus::Fail, message.into(), Some(remediation))
+    }
+
+    fn new(name: &str, status: CheckStatus, message: String, remediation: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message,
+            remediation: remediation.map(|r| r.to_string()),
+            duration_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DiagnosticsReport {
+    /// Worst status among the checks
+    pub status: CheckStatus,
+    pub checks: Vec<DiagnosticCheck>,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl DiagnosticsReport {
+    pub fn new(checks: Vec<DiagnosticCheck>) -> Self {
+        let status = checks.iter().map(|c| c.status).max().unwrap_or(CheckStatus::Pass);
+        Self { status, checks, generated_at: Utc::now() }
+    }
+
+    pub fn failed(&self) -> bool {
+        self.status == CheckStatus::Fail
+    }
+
+    /// Plain-text report for terminals and CI logs
+    pub fn render(&self) -> String {
+        let count = |status| self.checks.iter().filter(|c| c.status == status).count();
+        let mut out = format!(
+            "Shadow self-check: {} ({} pass, {} warn, {} fail)\n",
+            self.status.label(),
+            count(CheckStatus::Pass),
+            count(CheckStatus::Warn),
+            count(CheckStatus::Fail),
+        );
+        let width = self.checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+        for check in &self.checks {
+            out.push_str(&format!("  [{}] {:<width$}  {}\n", check.status.label(), check.name, check.message));
+            if let Some(remediation) = &check.remediation {
+                out.push_str(&format!("         {:<width$}  -> {}\n", "", remediation));
+            }
+        }
+        out
+    }
+}
+
+pub struct Hygieia {
+    config: ShadowConfig,
+    db: Database,
+    storage: PinataStorage,
+    program_ids: Vec<(String, String)>,
+    client: reqwest::Client,
+}
+
+impl Hygieia {
+    pub fn new(config: ShadowConfig, db: Database) -> Self {
+        Self {
+            config,
+            db,
+            storage: PinataStorage::new(),
+            program_ids: vec![
+                ("registry".to_string(), REGISTRY_PROGRAM_ID.to_string()),
+                ("profiles".to_string(), PROFILES_PROGRAM_ID.to_string()),
+            ],
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_storage(mut self, storage: PinataStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Override the Anchor programs expected on the cluster, as (name, program id)
+    pub fn with_program_ids(mut self, program_ids: Vec<(String, String)>) -> Self {
+        self.program_ids = program_ids;
+        self
+    }
+
+    /// Run every check concurrently; a check that outlives the timeout fails on its own
+    pub async fn run(&self) -> DiagnosticsReport {
+        let mut checks: Vec<(String, BoxFuture<'_, DiagnosticCheck>)> = vec![
+            ("config_ranges".to_string(), async { self.check_config() }.boxed()),
+            ("storage_credentials".to_string(), async { self.check_storage_credentials() }.boxed()),
+            ("solana_network".to_string(), self.check_network().boxed()),
+            ("mongo_indexes".to_string(), self.check_indexes().boxed()),
+        ];
+        if self.config.diagnostics.storage_probe {
+            checks.push(("storage_probe".to_string(), self.check_storage_probe().boxed()));
+        }
+        for (name, id) in &self.program_ids {
+            checks.push((format!("anchor_program:{}", name), self.check_program(name, id).boxed()));
+        }
+        for url in &self.config.diagnostics.probe_urls {
+            checks.push((format!("reachable:{}", url), self.check_url(url).boxed()));
+        }
+
+        let timeout = Duration::from_secs(self.config.diagnostics.check_timeout_seconds);
+        let results = join_all(checks.into_iter().map(|(name, check)| async move {
+            let started = Instant::now();
+            let mut result = match tokio::time::timeout(timeout, check).await {
+                Ok(result) => result,
+                Err(_) => DiagnosticCheck::fail(
+                    &name,
+                    format!("Timed out after {}s", timeout.as_secs()),
+                    "Check that the dependency is up and reachable from this host",
+                ),
+            };
+            result.name = name;
+            result.duration_ms = started.elapsed().as_millis() as u64;
+            result
+        }))
+        .await;
+
+        DiagnosticsReport::new(results)
+    }
+
+    fn check_config(&self) -> DiagnosticCheck {
+        let config = &self.config;
+        let mut failures = Vec::new();
+        let mut warnings = Vec::new();
+
+        if config.rate_limit.requests_per_minute == 0 {
+            failures.push("RATE_LIMIT_RPM is 0, every request would be throttled");
+        }
+        if !(0.0..=1.0).contains(&config.deploy.min_success_rate) {
+            failures.push("DEPLOY_MIN_SUCCESS_RATE must be between 0 and 1");
+        }
+        if config
+            .privacy
+            .max_history_retention_days
+            .is_some_and(|max| max < config.privacy.min_history_retention_days)
+        {
+            failures.push("HISTORY_RETENTION_MAX_DAYS is below HISTORY_RETENTION_MIN_DAYS");
+        }
+        if config.arweave.status_poll_interval_seconds == 0 {
+            failures.push("ARWEAVE_STATUS_POLL_SECONDS must be at least 1");
+        }
+        if config.search_analytics.buffer_capacity == 0 {
+            failures.push("SEARCH_ANALYTICS_BUFFER must be at least 1");
+        }
+        if config.auth.jwt_secret.is_none() {
+            warnings.push("JWT_SECRET is unset, session tokens won't survive a restart");
+        }
+        if config.search_analytics.hash_key.is_none() {
+            warnings.push("SEARCH_ANALYTICS_HASH_KEY is unset, search click ids won't survive a restart");
+        }
+        if config.auth.admin_wallets.is_empty() {
+            warnings.push("No admin wallets configured");
+        }
+        if config.cache.max_size_mb == 0 {
+            warnings.push("CACHE_MAX_SIZE_MB is 0, caching is effectively disabled");
+        }
+
+        let remediation = "Fix the listed environment variables and restart";
+        if !failures.is_empty() {
+            let all: Vec<&str> = failures.into_iter().chain(warnings).collect();
+            DiagnosticCheck::fail("config_ranges", all.join("; "), remediation)
+        } else if !warnings.is_empty() {
+            DiagnosticCheck::warn("config_ranges", warnings.join("; "), remediation)
+        } else {
+            DiagnosticCheck::pass("config_ranges", "Configuration values are within sane ranges")
+        }
+    }
+
+    fn check_storage_credentials(&self) -> DiagnosticCheck {
+        if self.storage.has_credentials() {
+            DiagnosticCheck::pass("storage_credentials", "Pinata credentials are configured")
+        } else {
+            DiagnosticCheck::fail(
+                "storage_credentials",
+                "Pinata credentials are missing, uploads will fail",
+                "Set PINATA_API_KEY and PINATA_SECRET",
+            )
+        }
+    }
+
+    async fn check_storage_probe(&self) -> DiagnosticCheck {
+        match self.storage.probe().await {
+            Ok(()) => DiagnosticCheck::pass("storage_probe", "Test pin and unpin succeeded"),
+            Err(e) => DiagnosticCheck::fail(
+                "storage_probe",
+                e,
+                "Check the Pinata key's pinning permissions and PINATA_API_URL",
+            ),
+        }
+    }
+
+    async fn check_network(&self) -> DiagnosticCheck {
+        let rpc_url = &self.config.solana.rpc_url;
+        let genesis = match self.rpc("getGenesisHash", serde_json::json!([])).await {
+            Ok(result) => result.as_str().unwrap_or_default().to_string(),
+            Err(e) => return DiagnosticCheck::fail("solana_network", e, "Check SOLANA_RPC_URL"),
+        };
+        let actual = CLUSTER_GENESIS
+            .iter()
+            .find(|(_, hash)| *hash == genesis)
+            .map(|(name, _)| *name);
+        let expected = self.config.diagnostics.solana_network.clone().or_else(|| {
+            CLUSTER_GENESIS
+                .iter()
+                .map(|(name, _)| *name)
+                .find(|name| rpc_url.contains(name.trim_end_matches("-beta")))
+                .map(|name| name.to_string())
+        });
+
+        match (expected.as_deref(), actual) {
+            (Some(expected), Some(actual)) if expected == actual => {
+                DiagnosticCheck::pass("solana_network", format!("RPC is on {}", actual))
+            }
+            (Some(expected), Some(actual)) => DiagnosticCheck::fail(
+                "solana_network",
+                format!("RPC is on {} but {} is configured", actual, expected),
+                "Point SOLANA_RPC_URL at the configured cluster or fix SOLANA_NETWORK",
+            ),
+            (Some("localnet"), None) => DiagnosticCheck::pass("solana_network", "RPC is on a local cluster"),
+            (Some(expected), None) => DiagnosticCheck::fail(
+                "solana_network",
+                format!("RPC genesis hash {} is not {}", genesis, expected),
+                "Point SOLANA_RPC_URL at the configured cluster or fix SOLANA_NETWORK",
+            ),
+            (None, actual) => DiagnosticCheck::warn(
+                "solana_network",
+                format!("RPC is on {}, no network configured to compare against", actual.unwrap_or("an unknown cluster")),
+                "Set SOLANA_NETWORK to pin the expected cluster",
+            ),
+        }
+    }
+
+    async fn check_indexes(&self) -> DiagnosticCheck {
+        let mut missing = Vec::new();
+        let mut collection_names: Vec<&str> = EXPECTED_INDEXES.iter().map(|(c, _)| *c).collect();
+        collection_names.dedup();
+
+        for collection in collection_names {
+            let names = match self.db.collection::<mongodb::bson::Document>(collection).list_index_names().await {
+                Ok(names) => names,
+                // The collection hasn't been created yet
+                Err(e) if matches!(*e.kind, mongodb::error::ErrorKind::Command(ref c) if c.code == 26) => Vec::new(),
+                Err(e) => {
+                    return DiagnosticCheck::fail("mongo_indexes", format!("Database error: {}", e), "Check DATABASE_URL");
+                }
+            };
+            for (_, index) in EXPECTED_INDEXES.iter().filter(|(c, _)| *c == collection) {
+                if !names.iter().any(|name| name == index) {
+                    missing.push(format!("{}.{}", collection, index));
+                }
+            }
+        }
+
+        if missing.is_empty() {
+            DiagnosticCheck::pass("mongo_indexes", "All expected indexes exist")
+        } else {
+            DiagnosticCheck::warn(
+                "mongo_indexes",
+                format!("Missing indexes: {}", missing.join(", ")),
+                "Start the server once to create them, or check the database user can create indexes",
+            )
+        }
+    }
+
+    async fn check_program(&self, name: &str, program_id: &str) -> DiagnosticCheck {
+        let check = format!("anchor_program:{}", name);
+        let remediation = "Deploy the program to this cluster or update the program id";
+        if Pubkey::from_str(program_id).is_err() {
+            return DiagnosticCheck::fail(&check, format!("{} is not a valid program id", program_id), remediation);
+        }
+
+        let params = serde_json::json!([program_id, { "encoding": "base64" }]);
+        match self.rpc("getAccountInfo", params).await {
+            Ok(result) => match result.get("value") {
+                Some(Value::Object(account)) if account.get("executable") == Some(&Value::Bool(true)) => {
+                    DiagnosticCheck::pass(&check, format!("{} is deployed", program_id))
+                }
+                Some(Value::Object(_)) => {
+                    DiagnosticCheck::fail(&check, format!("{} exists but is not executable", program_id), remediation)
+                }
+                _ => DiagnosticCheck::fail(&check, format!("{} not found on the cluster", program_id), remediation),
+            },
+            Err(e) => DiagnosticCheck::fail(&check, e, "Check SOLANA_RPC_URL"),
+        }
+    }
+
+    async fn check_url(&self, url: &str) -> DiagnosticCheck {
+        let check = format!("reachable:{}", url);
+        match self.client.get(url).send().await {
+            Ok(response) if response.status().is_server_error() => DiagnosticCheck::warn(
+                &check,
+                format!("Responded with {}", response.status()),
+                "The service is reachable but unhealthy",
+            ),
+            Ok(response) => DiagnosticCheck::pass(&check, format!("Responded with {}", response.status())),
+            Err(e) => DiagnosticCheck::fail(&check, format!("Unreachable: {}", e), "Check the URL and network egress"),
+        }
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value, String> {
+        let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self.client
+            .post(&self.config.solana.rpc_url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("RPC unreachable: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid RPC response: {}", e))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(format!("RPC error: {}", error));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+}
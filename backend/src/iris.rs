@@ -0,0 +1,347 @@
+// Iris: Messenger of the gods - API versioning and response envelopes
+// v1 responses are served as the handlers produce them; v2 clients get the
+// same data mapped into camelCase envelopes by the middleware below.
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderValue},
+    middleware::Next,
+    web::Bytes,
+    Error, HttpRequest,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Media type clients can send in `Accept` to opt into v2 on the unversioned routes
+pub const V2_MEDIA_TYPE: &str = "application/vnd.shadow.v2+json";
+
+/// Standard envelope for collection responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResponse<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub total: Option<u64>,
+}
+
+impl<T> ListResponse<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        let total = items.len() as u64;
+        Self {
+            items,
+            next_cursor: None,
+            total: Some(total),
+        }
+    }
+
+    pub fn with_cursor(mut self, next_cursor: Option<String>) -> Self {
+        self.next_cursor = next_cursor;
+        self
+    }
+
+    pub fn with_total(mut self, total: Option<u64>) -> Self {
+        self.total = total;
+        self
+    }
+}
+
+/// Standard envelope for single-resource responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceResponse<T> {
+    pub data: T,
+}
+
+/// Convert a snake_case (or Mongo `_id`) key to camelCase
+pub fn to_camel_case(key: &str) -> String {
+    let key = key.trim_start_matches('_');
+    let mut out = String::with_capacity(key.len());
+    let mut upper_next = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper_next = !out.is_empty();
+        } else if upper_next {
+            out.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Convert a camelCase key to snake_case; snake_case keys pass through unchanged
+pub fn to_snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for (i, c) in key.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Fields holding user data keyed by the user, such as a site's language tag -> entry file
+/// map or a wallet's mint -> balance map. The field is renamed; nothing inside it is.
+const USER_DATA_FIELDS: &[&str] = &["languages", "metadata", "tokens", "manifest", "detail"];
+
+fn map_keys(value: Value, convert: &dyn Fn(&str) -> String) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let v = if USER_DATA_FIELDS.contains(&to_snake_case(&k).as_str()) {
+                        v
+                    } else {
+                        map_keys(v, convert)
+                    };
+                    (convert(&k), v)
+                })
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(items) => {
+            Value::Array(items.into_iter().map(|v| map_keys(v, convert)).collect())
+        }
+        other => other,
+    }
+}
+
+/// Recursively rename object keys to camelCase, leaving user data fields' contents alone
+pub fn camelize(value: Value) -> Value {
+    map_keys(value, &to_camel_case)
+}
+
+/// Recursively rename object keys to snake_case, leaving user data fields' contents alone
+pub fn snakeize(value: Value) -> Value {
+    map_keys(value, &to_snake_case)
+}
+
+/// Map a v1 response body into its v2 shape
+pub fn to_v2(value: Value, is_error: bool) -> Value {
+    let value = camelize(value);
+    if is_error {
+        return value;
+    }
+
+    match value {
+        Value::Array(items) => serde_json::to_value(ListResponse::new(items))
+            .unwrap_or(Value::Null),
+        Value::Object(map) if map.contains_key("items") => Value::Object(map),
+        data => serde_json::to_value(ResourceResponse { data }).unwrap_or(Value::Null),
+    }
+}
+
+/// Whether the request asked for v2 via the path prefix or the Accept header
+pub fn wants_v2(req: &HttpRequest) -> bool {
+    req.path().starts_with("/api/v2/")
+        || req.headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.contains(V2_MEDIA_TYPE))
+            .unwrap_or(false)
+}
+
+/// Parse an Accept-Language header into (range, q) pairs, best first.
+/// Entries with a malformed or zero quality value are dropped.
+pub fn parse_accept_language(header: &str) -> Vec<(String, f32)> {
+    let mut ranges: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let range = parts.next()?.trim();
+            if range.is_empty() {
+                return None;
+            }
+
+            let mut q = 1.0;
+            for param in parts {
+                if let Some((name, value)) = param.split_once('=') {
+                    if name.trim().eq_ignore_ascii_case("q") {
+                        q = value.trim().parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?;
+                    }
+                }
+            }
+
+            (q > 0.0).then(|| (range.to_ascii_lowercase(), q))
+        })
+        .collect();
+
+    // Stable, so equal weights keep the client's order
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges
+}
+
+fn match_language<'a>(available: &[&'a str], range: &str) -> Option<&'a str> {
+    let primary = |tag: &str| tag.split('-').next().unwrap_or("").to_ascii_lowercase();
+    available.iter().copied()
+        .find(|lang| lang.eq_ignore_ascii_case(range))
+        .or_else(|| available.iter().copied().find(|lang| primary(lang) == primary(range)))
+}
+
+/// Pick the language to serve: an explicit override wins, then the best
+/// Accept-Language match (exact tag, then primary subtag), then `default`.
+pub fn negotiate_language<'a>(
+    available: &[&'a str],
+    accept_language: Option<&str>,
+    override_lang: Option<&str>,
+    default: &'a str,
+) -> &'a str {
+    if let Some(lang) = override_lang.and_then(|l| match_language(available, l.trim())) {
+        return lang;
+    }
+
+    for (range, _) in accept_language.map(parse_accept_language).unwrap_or_default() {
+        if range == "*" {
+            return default;
+        }
+        if let Some(lang) = match_language(available, &range) {
+            return lang;
+        }
+    }
+
+    default
+}
+
+fn is_json(content_type: Option<&HeaderValue>) -> bool {
+    content_type
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false)
+}
+
+/// Response mapper for v2 clients.
+/// Request bodies may use camelCase or snake_case; responses are camelCase envelopes.
+pub async fn v2_envelope_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    if !wants_v2(req.request()) {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+
+    if is_json(req.headers().get(header::CONTENT_TYPE)) {
+        let body = req.extract::<Bytes>().await?;
+        let body = match serde_json::from_slice::<Value>(&body) {
+            Ok(value) => Bytes::from(serde_json::to_vec(&snakeize(value))?),
+            Err(_) => body,
+        };
+        req.set_payload(Payload::from(body));
+    }
+
+    let res = next.call(req).await?;
+    if !is_json(res.headers().get(header::CONTENT_TYPE)) {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let is_error = res.status().is_client_error() || res.status().is_server_error();
+    let (http_req, http_res) = res.into_parts();
+    let (head, body) = http_res.into_parts();
+    let bytes = actix_web::body::to_bytes(body)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.into()))?;
+
+    let mapped = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(value) => Bytes::from(serde_json::to_vec(&to_v2(value, is_error))?),
+        Err(_) => bytes,
+    };
+
+    let mut head = head.set_body(BoxBody::new(mapped));
+    head.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(V2_MEDIA_TYPE),
+    );
+    head.headers_mut().remove(header::CONTENT_LENGTH);
+
+    Ok(ServiceResponse::new(http_req, head))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_case_conversion() {
+        assert_eq!(to_camel_case("storage_cid"), "storageCid");
+        assert_eq!(to_camel_case("_id"), "id");
+        assert_eq!(to_camel_case("mintToken"), "mintToken");
+        assert_eq!(to_snake_case("mintToken"), "mint_token");
+        assert_eq!(to_snake_case("storage_cid"), "storage_cid");
+    }
+
+    #[test]
+    fn test_user_data_keys_are_kept() {
+        let site = serde_json::json!({
+            "storage_cid": "ipfs://cid",
+            "languages": { "en_US": "index.html", "pt-BR": "pt/index.html" },
+            "default_language": "en_US",
+        });
+        assert_eq!(camelize(site), serde_json::json!({
+            "storageCid": "ipfs://cid",
+            "languages": { "en_US": "index.html", "pt-BR": "pt/index.html" },
+            "defaultLanguage": "en_US",
+        }));
+
+        let mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let request = serde_json::json!({
+            "defaultLanguage": "pt-BR",
+            "languages": { "pt-BR": "pt/index.html" },
+            "tokens": { mint: 5 },
+            "metadata": { "my_key": { "innerKey": 1 } },
+        });
+        assert_eq!(snakeize(request), serde_json::json!({
+            "default_language": "pt-BR",
+            "languages": { "pt-BR": "pt/index.html" },
+            "tokens": { mint: 5 },
+            "metadata": { "my_key": { "innerKey": 1 } },
+        }));
+    }
+
+    #[test]
+    fn test_to_v2_envelopes() {
+        let list = to_v2(serde_json::json!([{ "wallet_pubkey": "abc" }]), false);
+        assert_eq!(list, serde_json::json!({
+            "items": [{ "walletPubkey": "abc" }],
+            "nextCursor": null,
+            "total": 1
+        }));
+
+        let single = to_v2(serde_json::json!({ "is_public": true }), false);
+        assert_eq!(single, serde_json::json!({ "data": { "isPublic": true } }));
+
+        let error = to_v2(serde_json::json!({ "error": "Not found" }), true);
+        assert_eq!(error, serde_json::json!({ "error": "Not found" }));
+    }
+
+    #[test]
+    fn test_parse_accept_language_q_values() {
+        let ranges = parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5");
+        let tags: Vec<&str> = ranges.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(tags, ["fr-ch", "fr", "en", "de", "*"]);
+
+        // Weights reorder, zero and malformed weights drop out
+        let ranges = parse_accept_language("en;q=0.2, es, de;q=0, it;q=abc, pt;q=1.5");
+        assert_eq!(ranges, vec![("es".to_string(), 1.0), ("en".to_string(), 0.2)]);
+        assert!(parse_accept_language("").is_empty());
+    }
+
+    #[test]
+    fn test_negotiate_language() {
+        let available = ["en", "es", "pt-BR"];
+        assert_eq!(negotiate_language(&available, Some("es-MX,es;q=0.9"), None, "en"), "es");
+        assert_eq!(negotiate_language(&available, Some("pt-br"), None, "en"), "pt-BR");
+        assert_eq!(negotiate_language(&available, Some("pt-PT;q=0.9, en;q=0.8"), None, "en"), "pt-BR");
+        // Nothing acceptable, wildcard, or no header: fall back to the default
+        assert_eq!(negotiate_language(&available, Some("ja, zh;q=0.5"), None, "es"), "es");
+        assert_eq!(negotiate_language(&available, Some("ja, *;q=0.1"), None, "es"), "es");
+        assert_eq!(negotiate_language(&available, None, None, "en"), "en");
+        // ?lang= beats the header, unless the site doesn't have it
+        assert_eq!(negotiate_language(&available, Some("es"), Some("en"), "en"), "en");
+        assert_eq!(negotiate_language(&available, Some("es"), Some("ja"), "en"), "es");
+    }
+}
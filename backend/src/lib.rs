@@ -0,0 +1,49 @@
+// Shadow backend library - exposes the Pantheon modules to the server binary and tests
+pub mod api;
+pub mod db;
+pub mod error;
+pub mod handlers;
+pub mod storage;
+pub mod websocket;
+pub mod solana;
+pub mod solana_ws;
+pub mod anchor_client;
+pub mod ares;
+pub mod olympus;
+pub mod apollo;
+pub mod artemis;
+pub mod athena;
+pub mod chronos;
+pub mod prometheus;
+pub mod hephaestus;
+pub mod utils;
+pub mod clock;
+pub mod middleware;
+pub mod config;
+pub mod metrics;
+pub mod zeus;
+pub mod poseidon;
+pub mod dionysus;
+pub mod aphrodite;
+pub mod hestia;
+pub mod plutus;
+pub mod hades;
+pub mod wallet_handlers;
+pub mod link_converter;
+pub mod handlers_link;
+pub mod iris;
+pub mod argus;
+pub mod themis;
+pub mod mnemosyne;
+pub mod hecate;
+pub mod asclepius;
+pub mod hygieia;
+pub mod cerberus;
+pub mod charon;
+pub mod atlas;
+pub mod pheme;
+pub mod clio;
+pub mod tyche;
+pub mod enodia;
+pub mod helios;
+pub mod metis;
@@ -1,15 +1,54 @@
 // Link Converter - Converts URLs to SPL tokens and manages sublinks
 // Part of the Shadow token-only domain system
 
+use crate::config::LinkTokenConfig;
+use crate::solana::SolanaClient;
+use crate::utils::{encode_cursor, keyset_filter, next_page_cursor, parse_keypair};
+use futures_util::TryStreamExt;
 use mongodb::{Collection, Database};
 use mongodb::bson::{doc, DateTime};
+use mpl_token_metadata::accounts::Metadata;
+use mpl_token_metadata::instructions::{CreateMetadataAccountV3, CreateMetadataAccountV3InstructionArgs};
+use mpl_token_metadata::types::DataV2;
 use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::program_pack::Pack;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_instruction, system_program, sysvar};
+use solana_sdk::transaction::Transaction;
 use sha2::{Sha256, Digest};
 use std::str::FromStr;
 use std::sync::Arc;
 use hex;
 
+/// Link tokens are whole units: one token per holder, never fractions
+pub const LINK_TOKEN_DECIMALS: u8 = 0;
+
+/// Supply minted to the mint authority's token account when a link token is created
+pub const LINK_TOKEN_INITIAL_SUPPLY: u64 = 1;
+
+/// How long a claim to mint for a mapping holds; longer than a mint takes to confirm
+const MINT_CLAIM_SECONDS: i64 = 300;
+
+/// Whether a mapping's mint exists on-chain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MintStatus {
+    /// Mappings from before the status was recorded were all minted
+    #[default]
+    Minted,
+    /// The mint transaction didn't go through; `retry_mint` tries again
+    PendingMint,
+}
+
+/// Metaplex name and symbol for a platform token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LinkMapping {
     #[serde(rename = "_id")]
@@ -17,6 +56,20 @@ pub struct LinkMapping {
     pub token_mint: String, // SPL token mint address
     pub original_url: String, // Original URL (for reference)
     pub subpaths: Vec<String>, // Array of subpaths (e.g., ["/page1", "/page2"])
+    /// Wallet that converted the link; None for platform tokens and older mappings
+    #[serde(default)]
+    pub owner_pubkey: Option<String>,
+    #[serde(default)]
+    pub status: MintStatus,
+    /// Transaction that created the mint, when it was seen to confirm
+    #[serde(default)]
+    pub mint_signature: Option<String>,
+    /// Kept so a retried mint writes the same metadata
+    #[serde(default)]
+    pub metadata: Option<TokenMetadata>,
+    /// Set while someone is minting for a pending mapping, so nobody else mints for it too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claimed_until: Option<DateTime>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
@@ -33,6 +86,8 @@ pub struct ConvertLinkResponse {
     pub url_hash: String,
     pub is_new: bool, // Whether a new token was minted
     pub subpath: Option<String>,
+    #[serde(default)]
+    pub status: MintStatus,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,14 +97,57 @@ pub struct GeneralTokenRequest {
     pub token_symbol: String,
 }
 
+/// Funded signer that pays for link token mints and holds their mint authority.
+/// Loaded once at startup so handlers share it instead of re-parsing the key.
+#[derive(Clone, Default)]
+pub struct LinkMintAuthority(Option<Arc<Keypair>>);
+
+impl LinkMintAuthority {
+    /// Signs with LINK_MINT_AUTHORITY_KEY; without a usable key minting is refused
+    pub fn from_config(config: &LinkTokenConfig) -> Self {
+        match config.mint_authority_key.as_deref().map(parse_keypair) {
+            Some(Ok(keypair)) => Self(Some(Arc::new(keypair))),
+            Some(Err(e)) => {
+                tracing::warn!("Ignoring LINK_MINT_AUTHORITY_KEY ({}); link tokens cannot be minted", e);
+                Self(None)
+            }
+            None => {
+                tracing::warn!("LINK_MINT_AUTHORITY_KEY not set; link tokens cannot be minted");
+                Self(None)
+            }
+        }
+    }
+
+    pub fn new(keypair: Keypair) -> Self {
+        Self(Some(Arc::new(keypair)))
+    }
+
+    pub fn pubkey(&self) -> Option<Pubkey> {
+        self.0.as_ref().map(|keypair| keypair.pubkey())
+    }
+}
+
 pub struct LinkConverter {
     db: Arc<Database>,
     solana_rpc_url: String,
+    mint_authority: LinkMintAuthority,
+    daily_mint_quota: Option<u64>,
 }
 
 impl LinkConverter {
     pub fn new(db: Arc<Database>, solana_rpc_url: String) -> Self {
-        Self { db, solana_rpc_url }
+        Self { db, solana_rpc_url, mint_authority: LinkMintAuthority::default(), daily_mint_quota: None }
+    }
+
+    pub fn with_mint_authority(mut self, mint_authority: LinkMintAuthority) -> Self {
+        self.mint_authority = mint_authority;
+        self
+    }
+
+    /// Cap how many new links one owner may convert, and so mint for, in a day
+    pub fn with_daily_mint_quota(mut self, quota: u64) -> Self {
+        self.daily_mint_quota = Some(quota);
+        self
     }
 
     pub fn get_collection(&self) -> Collection<LinkMapping> {
@@ -63,6 +161,11 @@ impl LinkConverter {
         hex::encode(hasher.finalize())
     }
 
+    /// Canonical form of a URL, shared by token mappings and link lookups
+    pub fn normalize_url(url: &str) -> String {
+        url.trim().to_lowercase()
+    }
+
     /// Check if URL already has a token mapping
     pub async fn get_existing_mapping(
         &self,
@@ -72,93 +175,71 @@ impl LinkConverter {
         let collection = self.get_collection();
 
         let mapping = collection
-            .find_one(doc! { "url_hash": &url_hash }, None)
+            .find_one(doc! { "_id": &url_hash }, None)
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
         Ok(mapping)
     }
 
-    /// Convert URL to token (create mapping or return existing)
+    /// Convert URL to token (create mapping or return existing). A new mapping is
+    /// recorded as `owner`'s; an existing one keeps whoever converted it first.
     pub async fn convert_link(
         &self,
         url: &str,
         sublink: Option<&str>,
+        owner: Option<&str>,
     ) -> Result<ConvertLinkResponse, String> {
-        // Normalize URL
-        let normalized_url = url.trim().to_lowercase();
-        
-        // Check if mapping already exists
-        if let Some(existing) = self.get_existing_mapping(&normalized_url).await? {
-            // If sublink provided and doesn't exist, add it
-            if let Some(subpath) = sublink {
-                if !existing.subpaths.contains(&subpath.to_string()) {
-                    let mut updated = existing.clone();
-                    updated.subpaths.push(subpath.to_string());
-                    updated.updated_at = DateTime::now();
-
-                    let collection = self.get_collection();
-                    collection
-                        .update_one(
-                            doc! { "url_hash": &updated.url_hash },
-                            doc! {
-                                "$set": {
-                                    "subpaths": &updated.subpaths,
-                                    "updated_at": updated.updated_at
-                                }
-                            },
-                            None,
-                        )
-                        .await
-                        .map_err(|e| format!("Database error: {}", e))?;
-
-                    return Ok(ConvertLinkResponse {
-                        token_mint: updated.token_mint,
-                        url_hash: updated.url_hash,
-                        is_new: false,
-                        subpath: Some(subpath.to_string()),
-                    });
+        let normalized_url = Self::normalize_url(url);
+
+        let (mapping, is_new) = match self.get_existing_mapping(&normalized_url).await? {
+            Some(existing) => (self.retry_mint(&existing).await?, false),
+            None => {
+                if let (Some(owner), Some(quota)) = (owner, self.daily_mint_quota) {
+                    let since = DateTime::from_millis(DateTime::now().timestamp_millis() - 86_400_000);
+                    if self.count_mappings_since(owner, since).await? >= quota {
+                        return Err(format!("Daily link mint quota of {} reached", quota));
+                    }
                 }
+                let subpaths = sublink.map(|s| vec![s.to_string()]).unwrap_or_default();
+                self.create_mapping(normalized_url, subpaths, owner, None).await?
             }
-
-            return Ok(ConvertLinkResponse {
-                token_mint: existing.token_mint,
-                url_hash: existing.url_hash,
-                is_new: false,
-                subpath: sublink.map(|s| s.to_string()),
-            });
-        }
-
-        // Create new token for URL
-        // In production, this would mint an SPL token
-        // For now, generate a deterministic pubkey from URL hash
-        let url_hash = Self::hash_url(&normalized_url);
-        let token_mint = self.derive_token_mint_from_hash(&url_hash)?;
-
-        // Create mapping
-        let mapping = LinkMapping {
-            url_hash: url_hash.clone(),
-            token_mint: token_mint.clone(),
-            original_url: normalized_url,
-            subpaths: sublink.map(|s| vec![s.to_string()]).unwrap_or_default(),
-            created_at: DateTime::now(),
-            updated_at: DateTime::now(),
         };
 
-        let collection = self.get_collection();
-        collection
-            .insert_one(&mapping, None)
-            .await
-            .map_err(|e| format!("Database error: {}", e))?;
+        // If sublink provided and doesn't exist, add it
+        if let Some(subpath) = sublink {
+            if !mapping.subpaths.iter().any(|s| s == subpath) {
+                self.get_collection()
+                    .update_one(
+                        doc! { "_id": &mapping.url_hash },
+                        doc! {
+                            "$addToSet": { "subpaths": subpath },
+                            "$set": { "updated_at": DateTime::now() }
+                        },
+                        None,
+                    )
+                    .await
+                    .map_err(|e| format!("Database error: {}", e))?;
+            }
+        }
 
         Ok(ConvertLinkResponse {
-            token_mint,
-            url_hash,
-            is_new: true,
+            token_mint: mapping.token_mint,
+            url_hash: mapping.url_hash,
+            is_new,
             subpath: sublink.map(|s| s.to_string()),
+            status: mapping.status,
         })
     }
 
+    /// How many mappings `owner` has converted since `since`
+    pub async fn count_mappings_since(&self, owner: &str, since: DateTime) -> Result<u64, String> {
+        self.get_collection()
+            .count_documents(doc! { "owner_pubkey": owner, "created_at": { "$gte": since } }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
     /// Create a general platform token (e.g., Twitter)
     pub async fn create_general_token(
         &self,
@@ -166,41 +247,210 @@ impl LinkConverter {
         token_name: &str,
         token_symbol: &str,
     ) -> Result<String, String> {
+        if token_name.trim().is_empty() || token_name.len() > mpl_token_metadata::MAX_NAME_LENGTH {
+            return Err(format!("Token name must be 1-{} bytes", mpl_token_metadata::MAX_NAME_LENGTH));
+        }
+        if token_symbol.trim().is_empty() || token_symbol.len() > mpl_token_metadata::MAX_SYMBOL_LENGTH {
+            return Err(format!("Token symbol must be 1-{} bytes", mpl_token_metadata::MAX_SYMBOL_LENGTH));
+        }
+
         // Check if platform token already exists
         let platform_key = format!("platform:{}", platform.to_lowercase());
         
         if let Some(existing) = self.get_existing_mapping(&platform_key).await? {
-            return Ok(existing.token_mint);
+            return Ok(self.retry_mint(&existing).await?.token_mint);
         }
 
-        // Create new token for platform
-        let url_hash = Self::hash_url(&platform_key);
-        let token_mint = self.derive_token_mint_from_hash(&url_hash)?;
+        // Create new token for platform; subpaths are added later via sublinks
+        let metadata = TokenMetadata { name: token_name.to_string(), symbol: token_symbol.to_string() };
+        let (mapping, _) = self.create_mapping(platform_key, Vec::new(), None, Some(metadata)).await?;
+
+        Ok(mapping.token_mint)
+    }
 
+    /// Claim `original_url`'s mapping as pending, then mint its token. Only the request
+    /// whose claim created the mapping mints; anyone racing it gets the claimed mapping
+    /// back with `false`. A mint that fails to go through leaves the mapping pending under
+    /// the address it was tried at, for `retry_mint`.
+    async fn create_mapping(
+        &self,
+        original_url: String,
+        subpaths: Vec<String>,
+        owner: Option<&str>,
+        metadata: Option<TokenMetadata>,
+    ) -> Result<(LinkMapping, bool), String> {
+        self.authority()?;
+        let mint = Keypair::new();
         let mapping = LinkMapping {
-            url_hash: url_hash.clone(),
-            token_mint: token_mint.clone(),
-            original_url: platform_key,
-            subpaths: Vec::new(), // Subpaths added later via sublinks
+            url_hash: Self::hash_url(&original_url),
+            token_mint: mint.pubkey().to_string(),
+            original_url,
+            subpaths,
+            owner_pubkey: owner.map(str::to_string),
+            status: MintStatus::PendingMint,
+            mint_signature: None,
+            metadata,
+            claimed_until: Some(Self::claim_expiry()),
             created_at: DateTime::now(),
             updated_at: DateTime::now(),
         };
 
-        let collection = self.get_collection();
-        collection
-            .insert_one(&mapping, None)
+        let insert = mongodb::bson::to_document(&mapping)
+            .map_err(|e| format!("Serialization error: {}", e))?;
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        let claim = self.get_collection()
+            .update_one(doc! { "_id": &mapping.url_hash }, doc! { "$setOnInsert": insert }, options)
             .await
             .map_err(|e| format!("Database error: {}", e))?;
+        if claim.upserted_id.is_none() {
+            let claimed = self.get_collection()
+                .find_one(doc! { "_id": &mapping.url_hash }, None)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or_else(|| format!("Link mapping {} disappeared", mapping.url_hash))?;
+            return Ok((claimed, false));
+        }
+
+        let minted = match self.mint_token(&mint, mapping.metadata.as_ref()).await {
+            Ok(signature) => Some((mapping.token_mint.clone(), Some(signature))),
+            Err(e) => {
+                tracing::warn!("Minting link token {} failed, left pending: {}", mapping.token_mint, e);
+                None
+            }
+        };
+        Ok((self.release_claim(&mapping, minted).await?, true))
+    }
+
+    /// Try a pending mapping's mint again; minted mappings are returned as they are, and so
+    /// are pending ones whose mint someone else is still attempting. If the earlier attempt
+    /// landed after all, its mint is kept, otherwise a fresh mint is created. A retry that
+    /// fails again leaves the mapping pending and is not an error.
+    pub async fn retry_mint(&self, mapping: &LinkMapping) -> Result<LinkMapping, String> {
+        if mapping.status != MintStatus::PendingMint {
+            return Ok(mapping.clone());
+        }
+        self.authority()?;
+
+        // Only the retry that claims the pending mapping mints for it
+        let claimed = self.get_collection()
+            .find_one_and_update(
+                doc! {
+                    "_id": &mapping.url_hash,
+                    "status": "pending_mint",
+                    "token_mint": &mapping.token_mint,
+                    "$or": [
+                        { "claimed_until": null },
+                        { "claimed_until": { "$lte": DateTime::now() } },
+                    ],
+                },
+                doc! { "$set": { "claimed_until": Self::claim_expiry() } },
+                mongodb::options::FindOneAndUpdateOptions::builder()
+                    .return_document(mongodb::options::ReturnDocument::After)
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let Some(mapping) = claimed else {
+            return self.get_collection()
+                .find_one(doc! { "_id": &mapping.url_hash }, None)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?
+                .ok_or_else(|| format!("Link mapping {} disappeared", mapping.url_hash));
+        };
+
+        let solana = SolanaClient::new(self.solana_rpc_url.clone());
+        let landed = match Pubkey::from_str(&mapping.token_mint) {
+            Ok(mint) => match solana.get_account(&mint).await {
+                Ok(account) => Some(account.is_some()),
+                Err(e) => {
+                    tracing::warn!("Could not check pending link token {}: {}", mapping.token_mint, e);
+                    None
+                }
+            },
+            Err(_) => Some(false),
+        };
+        let minted = match landed {
+            None => None,
+            Some(true) => Some((mapping.token_mint.clone(), None)),
+            Some(false) => {
+                let mint = Keypair::new();
+                match self.mint_token(&mint, mapping.metadata.as_ref()).await {
+                    Ok(signature) => Some((mint.pubkey().to_string(), Some(signature))),
+                    Err(e) => {
+                        tracing::warn!("Retrying link token for {} failed, still pending: {}", mapping.url_hash, e);
+                        None
+                    }
+                }
+            }
+        };
+        self.release_claim(&mapping, minted).await
+    }
+
+    /// End the claim on a pending mapping, recording its mint when the attempt went through
+    async fn release_claim(
+        &self,
+        mapping: &LinkMapping,
+        minted: Option<(String, Option<String>)>,
+    ) -> Result<LinkMapping, String> {
+        let mut update = doc! { "$unset": { "claimed_until": "" } };
+        match minted {
+            Some((token_mint, mint_signature)) => {
+                update.insert("$set", doc! {
+                    "status": "minted",
+                    "token_mint": token_mint,
+                    "mint_signature": mint_signature,
+                    "updated_at": DateTime::now(),
+                });
+            }
+            None => {
+                update.insert("$set", doc! { "updated_at": DateTime::now() });
+            }
+        }
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        self.get_collection()
+            .find_one_and_update(
+                doc! { "_id": &mapping.url_hash, "status": "pending_mint", "token_mint": &mapping.token_mint },
+                update,
+                options,
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| format!("Link mapping {} disappeared", mapping.url_hash))
+    }
 
-        // TODO: Actually mint the SPL token with name and symbol
-        // For now, return the derived mint address
+    /// When a claim to mint for a mapping lapses, should its holder never release it
+    fn claim_expiry() -> DateTime {
+        DateTime::from_millis(DateTime::now().timestamp_millis() + MINT_CLAIM_SECONDS * 1000)
+    }
+
+    /// Retry up to `limit` pending mints, oldest first. Returns how many are now minted.
+    pub async fn retry_pending_mints(&self, limit: i64) -> Result<usize, String> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .limit(limit)
+            .build();
+        let pending: Vec<LinkMapping> = self.get_collection()
+            .find(doc! { "status": "pending_mint" }, options)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
 
-        Ok(token_mint)
+        let mut minted = 0;
+        for mapping in &pending {
+            if self.retry_mint(mapping).await?.status == MintStatus::Minted {
+                minted += 1;
+            }
+        }
+        Ok(minted)
     }
 
     /// Get token mint from URL (if exists)
     pub async fn get_token_from_url(&self, url: &str) -> Result<Option<String>, String> {
-        let normalized_url = url.trim().to_lowercase();
+        let normalized_url = Self::normalize_url(url);
         
         if let Some(mapping) = self.get_existing_mapping(&normalized_url).await? {
             Ok(Some(mapping.token_mint))
@@ -221,6 +471,35 @@ impl LinkConverter {
         Ok(mapping.map(|m| m.original_url))
     }
 
+    /// One page of the mappings `owner` converted, newest first
+    pub async fn list_mappings(
+        &self,
+        owner: &str,
+        limit: i64,
+        after: Option<(i64, String)>,
+    ) -> Result<(Vec<LinkMapping>, Option<String>), String> {
+        let mut filter = doc! { "owner_pubkey": owner };
+        if let Some((created_at, id)) = after {
+            filter.extend(keyset_filter("created_at", DateTime::from_millis(created_at), &id));
+        }
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+
+        let mut mappings: Vec<LinkMapping> = self.get_collection()
+            .find(filter, options)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let next_cursor = next_page_cursor(&mut mappings, limit, |mapping| {
+            encode_cursor(mapping.created_at.timestamp_millis(), &mapping.url_hash)
+        });
+        Ok((mappings, next_cursor))
+    }
+
     /// Add sublink to existing token
     pub async fn add_sublink(
         &self,
@@ -243,28 +522,112 @@ impl LinkConverter {
         Ok(())
     }
 
-    /// Derive a deterministic token mint from URL hash
-    /// In production, this would actually mint an SPL token
-    fn derive_token_mint_from_hash(&self, hash: &str) -> Result<String, String> {
-        // Use hash to derive a deterministic pubkey
-        // This is a simplified version - in production, mint actual SPL token
-        let hash_bytes = hex::decode(hash)
-            .map_err(|_| "Invalid hash".to_string())?;
-        
-        // Take first 32 bytes for pubkey
-        if hash_bytes.len() < 32 {
-            return Err("Hash too short".to_string());
+    fn authority(&self) -> Result<&Arc<Keypair>, String> {
+        self.mint_authority.0.as_ref()
+            .ok_or_else(|| "Link token minting is not configured".to_string())
+    }
+
+    /// Create `mint` as an SPL mint holding the initial supply, with Metaplex metadata when
+    /// given. The mint authority pays for the accounts, keeps the authority to mint more and
+    /// holds the initial supply. Returns the confirmed transaction's signature.
+    async fn mint_token(&self, mint: &Keypair, metadata: Option<&TokenMetadata>) -> Result<String, String> {
+        let authority = self.authority()?;
+        let solana = SolanaClient::new(self.solana_rpc_url.clone());
+
+        let rent = solana
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)
+            .await?;
+        let mut instructions = Self::mint_instructions(&authority.pubkey(), &mint.pubkey(), rent)?;
+        instructions.extend(Self::supply_instructions(&authority.pubkey(), &mint.pubkey())?);
+        if let Some(metadata) = metadata {
+            instructions.push(Self::metadata_instruction(&authority.pubkey(), &mint.pubkey(), &metadata.name, &metadata.symbol));
         }
 
-        let pubkey_bytes: [u8; 32] = hash_bytes[..32]
-            .try_into()
-            .map_err(|_| "Failed to convert hash to pubkey".to_string())?;
+        let blockhash = solana.get_recent_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&authority.pubkey()),
+            &[authority.as_ref(), mint],
+            blockhash,
+        );
+        let signature = solana.send_transaction(&transaction).await
+            .map_err(|e| e.to_string())?;
+        tracing::info!("Minted link token {} in {}", mint.pubkey(), signature);
+
+        Ok(signature)
+    }
+
+    /// Allocate the mint account and initialize it with `authority` as mint authority
+    pub fn mint_instructions(authority: &Pubkey, mint: &Pubkey, rent_lamports: u64) -> Result<Vec<Instruction>, String> {
+        let initialize = spl_token::instruction::initialize_mint2(
+            &spl_token::id(),
+            mint,
+            authority,
+            None,
+            LINK_TOKEN_DECIMALS,
+        )
+        .map_err(|e| format!("Failed to build mint instruction: {}", e))?;
+
+        Ok(vec![
+            system_instruction::create_account(
+                authority,
+                mint,
+                rent_lamports,
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            initialize,
+        ])
+    }
 
-        // Create pubkey (this would be the token mint)
-        let pubkey = Pubkey::try_from(pubkey_bytes)
-            .map_err(|_| "Invalid pubkey bytes".to_string())?;
+    /// Mint the initial supply into the authority's associated token account, creating it if needed
+    pub fn supply_instructions(authority: &Pubkey, mint: &Pubkey) -> Result<Vec<Instruction>, String> {
+        let account = spl_associated_token_account::get_associated_token_address(authority, mint);
+        let mint_to = spl_token::instruction::mint_to(
+            &spl_token::id(),
+            mint,
+            &account,
+            authority,
+            &[],
+            LINK_TOKEN_INITIAL_SUPPLY,
+        )
+        .map_err(|e| format!("Failed to build mint instruction: {}", e))?;
+
+        Ok(vec![
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                authority,
+                authority,
+                mint,
+                &spl_token::id(),
+            ),
+            mint_to,
+        ])
+    }
 
-        Ok(pubkey.to_string())
+    /// Metaplex metadata carrying the token's name and symbol
+    pub fn metadata_instruction(authority: &Pubkey, mint: &Pubkey, name: &str, symbol: &str) -> Instruction {
+        CreateMetadataAccountV3 {
+            metadata: Metadata::find_pda(mint).0,
+            mint: *mint,
+            mint_authority: *authority,
+            payer: *authority,
+            update_authority: (*authority, true),
+            system_program: system_program::id(),
+            rent: Some(sysvar::rent::id()),
+        }
+        .instruction(CreateMetadataAccountV3InstructionArgs {
+            data: DataV2 {
+                name: name.to_string(),
+                symbol: symbol.to_string(),
+                uri: String::new(),
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+            is_mutable: true,
+            collection_details: None,
+        })
     }
 
     /// Validate that an address is a valid SPL token mint
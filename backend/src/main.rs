@@ -1,43 +1,15 @@
-mod api;
-mod db;
-mod error;
-mod handlers;
-mod storage;
-mod websocket;
-mod solana;
-mod solana_ws;
-mod anchor_client;
-mod ares;
-mod olympus;
-mod apollo;
-mod artemis;
-mod athena;
-mod chronos;
-mod prometheus;
-mod hephaestus;
-mod utils;
-mod middleware;
-mod config;
-mod metrics;
-mod zeus;
-mod poseidon;
-mod dionysus;
-mod aphrodite;
-mod hestia;
-mod plutus;
-mod hades;
-mod wallet_handlers;
-mod link_converter;
-
-#[path = "handlers_link.rs"]
-mod handlers_link;
+use shadow_backend::{
+    anchor_client, api, apollo, atlas, ares, argus, asclepius, artemis, athena, cerberus, charon, chronos, clio, config, db,
+    enodia, handlers, hecate, helios, hephaestus, hestia, hygieia, iris, link_converter, metrics, middleware, mnemosyne, olympus, plutus, poseidon, prometheus, solana_ws, storage, themis, tyche,
+    websocket,
+};
 
 use actix_web::{web, App, HttpServer, middleware::Logger};
 use actix_cors::Cors;
 use mongodb::{Client as MongoClient, options::ClientOptions, IndexModel};
 use std::env;
 use std::sync::Arc;
-use tracing_subscriber;
+use tokio_util::sync::CancellationToken;
 
 #[actix_web::main]
 async fn main() -> anyhow::Result<()> {
@@ -53,6 +25,15 @@ async fn main() -> anyhow::Result<()> {
     let client_options = ClientOptions::parse(&database_url).await?;
     let client = MongoClient::with_options(client_options)?;
     let db = Arc::new(client.database("shadow"));
+
+    // `--self-check` reports on the deployment and exits without serving, for CI/CD gates
+    if env::args().any(|arg| arg == "--self-check") {
+        let config = config::ShadowConfig::from_env()
+            .map_err(|e| anyhow::anyhow!("Config error: {}", e))?;
+        let report = hygieia::Hygieia::new(config, (*db).clone()).run().await;
+        print!("{}", report.render());
+        std::process::exit(if report.failed() { 1 } else { 0 });
+    }
     
     // Create indexes for better performance
     let users_collection = db.collection::<db::User>("users");
@@ -66,8 +47,16 @@ async fn main() -> anyhow::Result<()> {
         .keys(mongodb::bson::doc! { "created_at": -1 })
         .build();
     sites_collection.create_index(sites_index, None).await?;
+
+    // Dashboards look up the sites a wallet collaborates on
+    let sites_collaborators_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "collaborators.wallet": 1 })
+        .build();
+    sites_collection.create_index(sites_collaborators_index, None).await?;
     
     // Create indexes for Olympus domains
+    // Domain names are the _id, so MongoDB's built-in _id index already keeps them
+    // unique (it rejects an explicit `unique` option on _id)
     let domains_collection = db.collection::<olympus::Domain>("domains");
     let domains_index = IndexModel::builder()
         .keys(mongodb::bson::doc! { "owner_pubkey": 1, "verified": 1 })
@@ -79,6 +68,99 @@ async fn main() -> anyhow::Result<()> {
         .build();
     domains_collection.create_index(domains_program_index, None).await?;
 
+    let domain_events_collection = db.collection::<olympus::DomainEvent>("domain_events");
+    let domain_events_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "domain": 1, "created_at": 1 })
+        .build();
+    domain_events_collection.create_index(domain_events_index, None).await?;
+
+    let outbox_collection = db.collection::<mnemosyne::OutboxEntry>("outbox");
+    let outbox_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "status": 1, "available_at": 1 })
+        .build();
+    outbox_collection.create_index(outbox_index, None).await?;
+
+    let site_versions_collection = db.collection::<asclepius::SiteVersion>("site_versions");
+    let site_versions_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "program_address": 1, "created_at": -1 })
+        .build();
+    site_versions_collection.create_index(site_versions_index, None).await?;
+
+    let search_index_collection = db.collection::<athena::SearchIndex>("search_index");
+    let outbound_links_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "outbound_links": 1 })
+        .build();
+    search_index_collection.create_index(outbound_links_index, None).await?;
+    let external_links_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "external_links": 1 })
+        .build();
+    search_index_collection.create_index(external_links_index, None).await?;
+
+    let deploy_tokens_collection = db.collection::<cerberus::DeployToken>("deploy_tokens");
+    let deploy_tokens_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "program_address": 1, "created_at": -1 })
+        .build();
+    deploy_tokens_collection.create_index(deploy_tokens_index, None).await?;
+
+    let link_mappings_collection = db.collection::<link_converter::LinkMapping>("link_mappings");
+    let link_owner_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "owner_pubkey": 1, "created_at": -1, "_id": -1 })
+        .build();
+    link_mappings_collection.create_index(link_owner_index, None).await?;
+
+    let history_visits_collection = db.collection::<chronos::HistoryVisit>("history_visits");
+    let history_visits_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "wallet_pubkey": 1, "visited_at": -1 })
+        .build();
+    history_visits_collection.create_index(history_visits_index, None).await?;
+    let history_visits_ttl = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "expires_at": 1 })
+        .options(mongodb::options::IndexOptions::builder().expire_after(std::time::Duration::from_secs(0)).build())
+        .build();
+    history_visits_collection.create_index(history_visits_ttl, None).await?;
+
+    let site_events_collection = db.collection::<tyche::SiteEvent>("site_events");
+    let site_events_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "program_address": 1, "created_at": 1 })
+        .build();
+    site_events_collection.create_index(site_events_index, None).await?;
+
+    // Per-day analytics records expire after the configured retention
+    for collection in ["site_visit_days", "site_visit_day_visitors", "performance_metrics"] {
+        let ttl_index = IndexModel::builder()
+            .keys(mongodb::bson::doc! { "expires_at": 1 })
+            .options(mongodb::options::IndexOptions::builder().expire_after(std::time::Duration::from_secs(0)).build())
+            .build();
+        db.collection::<mongodb::bson::Document>(collection).create_index(ttl_index, None).await?;
+    }
+
+    let portfolio_snapshots_collection = db.collection::<plutus::PortfolioSnapshot>("portfolio_snapshots");
+    let portfolio_snapshots_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "wallet": 1, "taken_at": 1 })
+        .build();
+    portfolio_snapshots_collection.create_index(portfolio_snapshots_index, None).await?;
+
+    let arweave_uploads_collection = db.collection::<atlas::ArweaveUpload>("arweave_uploads");
+    let arweave_uploads_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "status": 1, "created_at": 1 })
+        .build();
+    arweave_uploads_collection.create_index(arweave_uploads_index, None).await?;
+
+    let domain_watches_collection = db.collection::<helios::DomainWatch>("domain_watches");
+    let domain_watches_wallet_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "wallet": 1, "created_at": -1 })
+        .build();
+    domain_watches_collection.create_index(domain_watches_wallet_index, None).await?;
+    let domain_watches_domain_index = IndexModel::builder()
+        .keys(mongodb::bson::doc! { "domain": 1, "last_status": 1 })
+        .build();
+    domain_watches_collection.create_index(domain_watches_domain_index, None).await?;
+
+    poseidon::PoseidonTransactionManager::new(Arc::clone(&db)).ensure_indexes().await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    hestia::HestiaConnectionManager::new(Arc::clone(&db)).ensure_indexes().await
+        .map_err(|e| anyhow::anyhow!(e))?;
+
     let solana_rpc_url = env::var("SOLANA_RPC_URL")
         .unwrap_or_else(|_| "https://api.devnet.solana.com".to_string());
     
@@ -91,6 +173,13 @@ async fn main() -> anyhow::Result<()> {
 
     println!("🚀 Shadow backend starting on port {}", port);
 
+    // Load configuration
+    let config = config::ShadowConfig::from_env()
+        .map_err(|e| anyhow::anyhow!("Config error: {}", e))?;
+
+    // Cancelled once the HTTP server stops so background jobs can wind down
+    let shutdown = CancellationToken::new();
+
     let db_clone = Arc::clone(&db);
     let solana_rpc_clone = solana_rpc_url.clone();
     let solana_ws_clone = solana_ws_url.clone();
@@ -98,25 +187,89 @@ async fn main() -> anyhow::Result<()> {
     // Initialize Olympus CA (domain system) - will be created per request
     
     // Initialize Ares (authentication)
-    let ares = Arc::new(ares::AresAuth::new());
+    let mut ares_auth = ares::AresAuth::new().with_token_ttl(config.auth.token_ttl_seconds as i64);
+    if let Some(secret) = &config.auth.jwt_secret {
+        ares_auth = ares_auth.with_jwt_secret(secret);
+    }
+    let ares = Arc::new(ares_auth);
+    let ares_handle = Arc::clone(&ares).spawn_nonce_eviction(shutdown.clone());
     
     // Initialize Artemis (rate limiting)
-    let artemis = Arc::new(artemis::ArtemisRateLimiter::new(60)); // 60 requests per minute
+    let artemis = Arc::new(artemis::ArtemisRateLimiter::with_window(
+        config.rate_limit.requests_per_minute,
+        config.rate_limit.window_seconds,
+    ));
+    let artemis_handle = Arc::clone(&artemis).spawn_cleanup(shutdown.clone());
     
     // Initialize Apollo (validation)
     let apollo = Arc::new(apollo::ApolloValidator::new());
     
     // Initialize Athena (search indexing)
     let athena = Arc::new(athena::AthenaIndexer::new((*db_clone).clone()));
+    athena.ensure_indexes().await?;
+    Arc::clone(&athena).spawn_health_monitor(std::time::Duration::from_secs(3600));
     
     // Initialize Chronos (history/bookmarks)
     let chronos = Arc::new(chronos::ChronosManager::new((*db_clone).clone()));
+    match chronos.migrate_legacy_history().await {
+        Ok(0) => {}
+        Ok(migrated) => tracing::info!("Chronos migrated {} legacy history rows into visit events", migrated),
+        Err(e) => tracing::warn!("Chronos legacy history migration failed: {}", e),
+    }
+    match chronos.migrate_bookmark_dates().await {
+        Ok(0) => {}
+        Ok(migrated) => tracing::info!("Chronos converted {} bookmark timestamps to dates", migrated),
+        Err(e) => tracing::warn!("Chronos bookmark date migration failed: {}", e),
+    }
+    let chronos_handle = Arc::clone(&chronos).spawn_retention(config.privacy.clone(), shutdown.clone());
     
     // Initialize Prometheus (analytics)
-    let prometheus = Arc::new(prometheus::PrometheusAnalytics::new((*db_clone).clone()));
+    let prometheus = Arc::new(
+        prometheus::PrometheusAnalytics::new((*db_clone).clone())
+            .with_summary_interval(config.get_analytics_summary_interval())
+            .with_retention_days(config.analytics.retention_days)
+    );
+    let daily_stats_handle = Arc::clone(&prometheus).spawn_daily_stats(config.get_daily_stats_interval(), shutdown.clone());
+    
+    // Initialize Argus (dApp origin reputation)
+    let argus = Arc::new(argus::ArgusReputation::new(Arc::clone(&db_clone), &config.reputation.known_dapps));
+    if let Err(e) = argus.refresh().await {
+        eprintln!("Failed to load dApp reputation registry: {}", e);
+    }
+    let argus_handle = Arc::clone(&argus).spawn_refresh(config.get_reputation_refresh_interval(), shutdown.clone());
     
     // Initialize Hephaestus (caching)
-    let hephaestus = Arc::new(hephaestus::HephaestusCache::new(512, 3600)); // 512MB cache, 1hr TTL
+    // Warm start from CACHE_SNAPSHOT_PATH when a previous run left a snapshot behind
+    let snapshot_path = env::var("CACHE_SNAPSHOT_PATH").ok().map(std::path::PathBuf::from);
+    let hephaestus = Arc::new(
+        snapshot_path
+            .as_deref()
+            .filter(|path| path.exists())
+            .and_then(|path| match hephaestus::HephaestusCache::load_snapshot(path) {
+                Ok(cache) => Some(cache),
+                Err(e) => {
+                    eprintln!("Ignoring cache snapshot: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_else(|| hephaestus::HephaestusCache::new(512, 3600)) // 512MB cache, 1hr TTL
+            .with_max_object_bytes(config.cache.max_object_bytes)
+    );
+
+    // Save a snapshot on Ctrl+C, or when the server stops for any other reason
+    let snapshot_handle = snapshot_path.map(|path| {
+        let cache = Arc::clone(&hephaestus);
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = shutdown.cancelled() => {}
+            }
+            if let Err(e) = cache.save_snapshot(&path).await {
+                eprintln!("Failed to save cache snapshot: {}", e);
+            }
+        })
+    });
     
     // Initialize metrics collector
     let metrics = Arc::new(metrics::MetricsCollector::new());
@@ -128,22 +281,71 @@ async fn main() -> anyhow::Result<()> {
     );
     
     // Initialize Solana WebSocket client
-    let hermes_broker = Arc::new(websocket::HermesBroker::new());
+    // REDIS_URL shares broker events between backend instances
+    let hermes_broker = Arc::new(
+        websocket::HermesBroker::from_env().await
+            .map_err(|e| anyhow::anyhow!(e))?
+    );
     let solana_ws_client = Arc::new(
         solana_ws::SolanaWebSocketClient::new(solana_ws_clone.clone(), Arc::clone(&hermes_broker))
     );
     
-    // Start Solana WebSocket connection (non-blocking)
-    let ws_client_clone = Arc::clone(&solana_ws_client);
-    tokio::spawn(async move {
-        if let Err(e) = ws_client_clone.start().await {
-            eprintln!("Failed to start Solana WebSocket client: {}", e);
-        }
-    });
+    // Start Themis (periodic domain re-verification)
+    let themis = Arc::new(
+        themis::ThemisVerifier::new((*db_clone).clone(), solana_rpc_url.clone(), config.verification.clone())
+            .with_broker(Arc::clone(&hermes_broker))
+    );
+    let themis_handle = themis.spawn(shutdown.clone());
+
+    // Start Helios (domain watchlist alerts)
+    let helios = Arc::new(
+        helios::HeliosWatchlist::new((*db_clone).clone(), config.watchlist.clone())
+            .with_broker(Arc::clone(&hermes_broker))
+    );
+    let helios_handle = Arc::clone(&helios).spawn(shutdown.clone());
+
+    // Start the Mnemosyne outbox relay and share one writer across workers
+    let outbox_relay = Arc::new(
+        mnemosyne::OutboxRelay::new((*db_clone).clone(), config.outbox.clone())
+            .with_broker(Arc::clone(&hermes_broker))
+            .with_metrics(Arc::clone(&metrics))
+    );
+    let outbox_handle = outbox_relay.spawn(shutdown.clone());
+    let mnemosyne = Arc::new(mnemosyne::Mnemosyne::new((*db_clone).clone()));
+
+    // Start Atlas (Arweave upload status tracking)
+    let bundlr = Arc::new(storage::BundlrStorage::new());
+    let atlas = Arc::new(atlas::AtlasTracker::new((*db_clone).clone(), Arc::clone(&bundlr), config.arweave.clone()));
+    let atlas_handle = Arc::clone(&atlas).spawn(shutdown.clone());
+
+    // Start Clio (buffered search analytics writer)
+    let clio = Arc::new(
+        clio::ClioRecorder::new((*db_clone).clone(), config.search_analytics.clone())
+            .with_metrics(Arc::clone(&metrics))
+    );
+    clio.ensure_indexes().await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let clio_handle = Arc::clone(&clio).spawn(shutdown.clone());
+
+    // Initialize Tyche (NFT-controlled site ownership, registry owners and editors, collaborator roles)
+    let tyche = Arc::new(
+        tyche::TycheOwnership::new((*db_clone).clone(), solana_rpc_url.clone())
+            .with_broker(Arc::clone(&hermes_broker))
+            .with_registry(Arc::clone(&anchor_client)),
+    );
+
+    // Initialize Charon (legacy web bridges)
+    let charon = Arc::new(charon::CharonBridge::new((*db_clone).clone(), config.bridge.clone()));
+
+    // Initialize Enodia (outbound link interstitial signals)
+    let enodia = Arc::new(enodia::EnodiaLinkInfo::new((*db_clone).clone(), config.link_info.clone()));
+    let link_mint_authority = link_converter::LinkMintAuthority::from_config(&config.link_tokens);
+
+    // Initialize Hecate (GraphQL read gateway)
+    let graphql_schema = hecate::build_schema((*db_clone).clone());
     
-    // Load configuration
-    let config = config::ShadowConfig::from_env()
-        .map_err(|e| anyhow::anyhow!("Config error: {}", e))?;
+    // Start Solana WebSocket connection (non-blocking; reconnects until closed)
+    let solana_ws_handle = solana_ws_client.start();
     
     HttpServer::new(move || {
         let cors = Cors::default()
@@ -162,7 +364,9 @@ async fn main() -> anyhow::Result<()> {
             .app_data(web::Data::new(solana_rpc_clone.clone()))
             .app_data(web::Data::new(solana_ws_clone.clone()))
             .app_data(web::Data::new(storage::PinataStorage::new()))
-            .app_data(web::Data::new(storage::BundlrStorage::new()))
+            .app_data(web::Data::new(storage::NFTStorageClient::new()))
+            .app_data(web::Data::from(Arc::clone(&bundlr)))
+            .app_data(web::Data::from(Arc::clone(&atlas)))
             .app_data(web::Data::from(Arc::clone(&ares)))
             .app_data(web::Data::from(Arc::clone(&artemis)))
             .app_data(web::Data::from(Arc::clone(&apollo)))
@@ -170,86 +374,46 @@ async fn main() -> anyhow::Result<()> {
             .app_data(web::Data::from(Arc::clone(&athena)))
             .app_data(web::Data::from(Arc::clone(&chronos)))
             .app_data(web::Data::from(Arc::clone(&prometheus)))
+            .app_data(web::Data::from(Arc::clone(&argus)))
             .app_data(web::Data::from(Arc::clone(&hephaestus)))
             .app_data(web::Data::from(Arc::clone(&metrics)))
             .app_data(web::Data::from(Arc::clone(&anchor_client)))
             .app_data(web::Data::from(Arc::clone(&hermes_broker)))
+            .app_data(web::Data::from(Arc::clone(&mnemosyne)))
+            .app_data(web::Data::from(Arc::clone(&charon)))
+            .app_data(web::Data::from(Arc::clone(&clio)))
+            .app_data(web::Data::from(Arc::clone(&tyche)))
+            .app_data(web::Data::from(Arc::clone(&enodia)))
+            .app_data(web::Data::new(link_mint_authority.clone()))
+            .app_data(web::Data::from(Arc::clone(&helios)))
+            .app_data(web::Data::new(graphql_schema.clone()))
             .app_data(web::Data::new(config.clone()))
-            .service(
-                web::scope("/api")
-                    .route("/health", web::get().to(api::health))
-                    .route("/profiles/search", web::get().to(handlers::search_profiles))
-                    .route("/profiles/{wallet}", web::get().to(handlers::get_profile))
-                    .route("/profiles", web::post().to(handlers::create_profile_route))
-                    .route("/profiles/{wallet}", web::put().to(handlers::update_profile))
-                    .route("/sites/search", web::get().to(handlers::search_sites))
-                    .route("/sites/{program_address}", web::get().to(handlers::get_site))
-                    .route("/sites", web::post().to(handlers::register_site))
-                    .route("/sites/{program_address}", web::put().to(handlers::update_site))
-                    .route("/sites/{program_address}/content", web::get().to(handlers::get_site_content))
-                    .route("/upload/ipfs", web::post().to(handlers::upload_ipfs))
-                    .route("/upload/arweave", web::post().to(handlers::upload_arweave))
-                    .route("/solana/search", web::get().to(handlers::search_solana))
-                    // Olympus domain endpoints
-                    .route("/domains/search", web::get().to(handlers::search_domains))
-                    .route("/domains/{domain}", web::get().to(handlers::get_domain))
-                    .route("/domains", web::post().to(handlers::register_domain))
-                    .route("/domains/{domain}", web::put().to(handlers::update_domain))
-                    .route("/domains/{domain}/verify", web::post().to(handlers::verify_domain))
-                    .route("/domains/owner/{wallet}", web::get().to(handlers::list_owner_domains))
-                    // Athena search endpoints
-                    .route("/search", web::get().to(handlers::search_content))
-                    .route("/search/index", web::post().to(handlers::index_content))
-                    // Chronos history/bookmarks endpoints
-                    .route("/history", web::get().to(handlers::get_history))
-                    .route("/history", web::post().to(handlers::record_visit))
-                    .route("/history", web::delete().to(handlers::clear_history))
-                    .route("/bookmarks", web::get().to(handlers::get_bookmarks))
-                    .route("/bookmarks", web::post().to(handlers::add_bookmark))
-                    .route("/bookmarks/{domain}", web::delete().to(handlers::remove_bookmark))
-                    .route("/sessions", web::post().to(handlers::create_session))
-                    .route("/sessions/active", web::get().to(handlers::get_active_sessions))
-                    // Prometheus analytics endpoints
-                    .route("/analytics/{domain}", web::get().to(handlers::get_analytics))
-                    .route("/analytics/top", web::get().to(handlers::get_top_sites))
-                    .route("/analytics/performance", web::post().to(handlers::record_performance))
-                    // Hephaestus cache endpoints
-                    .route("/cache/stats", web::get().to(handlers::get_cache_stats))
-                    .route("/cache/clear", web::post().to(handlers::clear_cache))
-                    .route("/metrics", web::get().to(handlers::get_metrics))
-                    .route("/ws", web::get().to(websocket::ws_handler))
-                    // Wallet dApp endpoints (Phantom-like)
-                    // Zeus - Wallet Management
-                    .route("/wallet/create", web::post().to(wallet_handlers::create_wallet))
-                    .route("/wallet/import", web::post().to(wallet_handlers::import_wallet))
-                    .route("/wallet/list", web::get().to(wallet_handlers::list_wallets))
-                    .route("/wallet/active", web::get().to(wallet_handlers::get_active_wallet))
-                    .route("/wallet/active", web::post().to(wallet_handlers::set_active_wallet))
-                    // Poseidon - Transaction Signing
-                    .route("/wallet/transaction", web::post().to(wallet_handlers::create_transaction))
-                    .route("/wallet/transaction/sign", web::post().to(wallet_handlers::sign_transaction))
-                    .route("/wallet/transactions/pending", web::get().to(wallet_handlers::get_pending_transactions))
-                    // Dionysus - Tokens
-                    .route("/wallet/{pubkey}/tokens", web::get().to(wallet_handlers::get_token_balances))
-                    // Aphrodite - NFTs
-                    .route("/wallet/{pubkey}/nfts", web::get().to(wallet_handlers::get_nfts))
-                    // Hestia - dApp Connections
-                    .route("/wallet/dapp/connect", web::post().to(wallet_handlers::connect_dapp))
-                    .route("/wallet/dapp/connections", web::get().to(wallet_handlers::get_connections))
-                    .route("/wallet/dapp/disconnect", web::post().to(wallet_handlers::disconnect_dapp))
-                    // Plutus - Portfolio
-                    .route("/wallet/{pubkey}/portfolio", web::get().to(wallet_handlers::get_portfolio))
-                    .route("/wallet/{pubkey}/history", web::get().to(wallet_handlers::get_transaction_history))
-                    // Link Converter - Token-only domains
-                    .route("/convert/link", web::post().to(handlers_link::convert_link))
-                    .route("/convert/general-token", web::post().to(handlers_link::create_general_token))
-                    .route("/convert/token/{token_mint}", web::get().to(handlers_link::get_url_from_token))
-                    .route("/convert/url", web::post().to(handlers_link::get_token_from_url))
-            )
+            .wrap(actix_web::middleware::from_fn(iris::v2_envelope_middleware))
+            // v2 must be registered first so the /api scope doesn't swallow its paths
+            .service(web::scope("/api/v2").configure(api::configure))
+            .service(web::scope("/api").configure(api::configure))
+            .route("/r/{legacy_domain}", web::get().to(handlers::legacy_redirect))
     })
     .bind(("0.0.0.0", port))?
     .run()
     .await?;
 
+    shutdown.cancel();
+    let _ = themis_handle.await;
+    let _ = helios_handle.await;
+    let _ = outbox_handle.await;
+    let _ = ares_handle.await;
+    let _ = artemis_handle.await;
+    let _ = chronos_handle.await;
+    let _ = daily_stats_handle.await;
+    let _ = argus_handle.await;
+    let _ = atlas_handle.await;
+    let _ = clio_handle.await;
+    solana_ws_client.close();
+    let _ = solana_ws_handle.await;
+    if let Some(handle) = snapshot_handle {
+        let _ = handle.await;
+    }
+
     Ok(())
 }
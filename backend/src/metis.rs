@@ -0,0 +1,207 @@
+// Metis - Titaness of counsel
+// Plans a deploy without making it: checks shadow.json, prices the storage and accounts the
+// deploy would pay for, and whether the wallet and domain are ready. Nothing is written.
+
+use crate::apollo::ApolloValidator;
+use crate::error::ShadowError;
+use crate::olympus::OlympusCA;
+use crate::solana::SolanaClient;
+use crate::storage::BundlrStorage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Limits the registry program enforces on a site account
+pub const MAX_SITE_NAME_LEN: usize = 100;
+pub const MAX_SITE_DESCRIPTION_LEN: usize = 500;
+const MAX_CID_LEN: usize = 100;
+const MAX_EDITORS: usize = 5;
+/// SPL token mint account size
+const MINT_ACCOUNT_LEN: usize = 82;
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// The shadow.json fields a deploy depends on
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowManifest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// "ipfs" (the default) or "arweave"
+    #[serde(default)]
+    pub storage: Option<String>,
+    #[serde(default)]
+    pub network: Option<String>,
+    /// Language tag -> entry file
+    #[serde(default)]
+    pub languages: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    pub default_language: Option<String>,
+}
+
+/// One file the deploy would upload
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PlannedFile {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployPlanRequest {
+    /// shadow.json as written; checked here rather than at deserialization so the error says what's wrong
+    pub manifest: serde_json::Value,
+    pub files: Vec<PlannedFile>,
+    /// Wallet that would pay for the deploy
+    pub wallet: String,
+    #[serde(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    pub mint_token: bool,
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+/// What a deploy would cost and whether it can go ahead
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeployPlan {
+    /// Rent for the site (and token mint) accounts, transaction fees and storage
+    pub estimated_lamports: u64,
+    pub balance_lamports: u64,
+    pub files: usize,
+    pub total_bytes: u64,
+    /// None when no domain was asked for
+    pub domain_available: Option<bool>,
+    pub deployable: bool,
+    /// Why the deploy would fail; empty when it's deployable
+    pub problems: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Bytes the registry allocates for a site account (shadow-registry's `Site::space`)
+pub fn site_account_space(name_len: usize, description_len: usize) -> usize {
+    8 + 32 + 32 + (4 + name_len) + (4 + description_len) + (4 + MAX_CID_LEN) + 8 + 8 + 4 + (4 + 32 * MAX_EDITORS)
+}
+
+/// Check shadow.json and the file list against each other
+pub fn validate_manifest(manifest: &serde_json::Value, files: &[PlannedFile]) -> Result<ShadowManifest, ShadowError> {
+    let manifest: ShadowManifest = serde_json::from_value(manifest.clone())
+        .map_err(|e| ShadowError::BadRequest(format!("Invalid shadow.json: {}", e)))?;
+    if manifest.name.trim().is_empty() || manifest.name.len() > MAX_SITE_NAME_LEN {
+        return Err(ShadowError::BadRequest(format!("shadow.json name must be 1-{} characters", MAX_SITE_NAME_LEN)));
+    }
+    if manifest.description.as_ref().is_some_and(|d| d.len() > MAX_SITE_DESCRIPTION_LEN) {
+        return Err(ShadowError::BadRequest(format!(
+            "shadow.json description is over {} characters",
+            MAX_SITE_DESCRIPTION_LEN
+        )));
+    }
+    if let Some(storage) = manifest.storage.as_deref() {
+        if storage != "ipfs" && storage != "arweave" {
+            return Err(ShadowError::BadRequest(format!("Unknown storage {} in shadow.json; use ipfs or arweave", storage)));
+        }
+    }
+
+    if files.is_empty() {
+        return Err(ShadowError::BadRequest("No files to deploy".to_string()));
+    }
+    for file in files {
+        if file.path.is_empty()
+            || file.path.starts_with('/')
+            || file.path.contains('\\')
+            || file.path.split('/').any(|segment| segment == "..")
+        {
+            return Err(ShadowError::BadRequest(format!("File path {} must be inside the site", file.path)));
+        }
+    }
+
+    match &manifest.languages {
+        Some(languages) => {
+            ApolloValidator::validate_languages(languages, manifest.default_language.as_deref())
+                .map_err(|e| ShadowError::BadRequest(format!("shadow.json: {}", e)))?;
+            if let Some((tag, entry)) = languages.iter().find(|(_, entry)| !files.iter().any(|f| &f.path == *entry)) {
+                return Err(ShadowError::BadRequest(format!("Entry file {} for {} is not in the deploy", entry, tag)));
+            }
+        }
+        None if manifest.default_language.is_some() => {
+            return Err(ShadowError::BadRequest("shadow.json defaultLanguage requires a languages map".to_string()));
+        }
+        None => {}
+    }
+    Ok(manifest)
+}
+
+/// Plan `request` against the chain, the storage node and the domain registry
+pub async fn plan_deploy(
+    request: &DeployPlanRequest,
+    solana: &SolanaClient,
+    bundlr: &BundlrStorage,
+    olympus: &OlympusCA,
+) -> Result<DeployPlan, ShadowError> {
+    ApolloValidator::validate_pubkey(&request.wallet)?;
+    let manifest = validate_manifest(&request.manifest, &request.files)?;
+    if let Some(domain) = &request.domain {
+        ApolloValidator::validate_domain(domain)?;
+    }
+
+    let mut problems = Vec::new();
+    let mut warnings = Vec::new();
+    if let (Some(wanted), Some(network)) = (manifest.network.as_deref(), request.network.as_deref()) {
+        if wanted != network {
+            problems.push(format!("shadow.json targets {} but the deploy is to {}", wanted, network));
+        }
+    }
+
+    let total_bytes: u64 = request.files.iter().map(|f| f.size).sum();
+    let site_space = site_account_space(manifest.name.len(), manifest.description.as_deref().map_or(0, str::len));
+    let mut estimated_lamports = solana.get_minimum_balance_for_rent_exemption(site_space).await
+        .map_err(ShadowError::Solana)?
+        + LAMPORTS_PER_SIGNATURE;
+    if request.mint_token {
+        // The mint's own keypair signs its creation alongside the wallet
+        estimated_lamports += solana.get_minimum_balance_for_rent_exemption(MINT_ACCOUNT_LEN).await
+            .map_err(ShadowError::Solana)?
+            + 2 * LAMPORTS_PER_SIGNATURE;
+    }
+    if manifest.storage.as_deref() == Some("arweave") {
+        let price = bundlr.get_price(total_bytes as usize).await.map_err(ShadowError::Storage)?;
+        if bundlr.currency() == "solana" {
+            estimated_lamports += price;
+        } else {
+            warnings.push(format!("Arweave storage is paid in {} and isn't included in the estimate", bundlr.currency()));
+        }
+    }
+
+    let balance_lamports = solana.get_balance(&request.wallet).await.map_err(ShadowError::Solana)?;
+    if balance_lamports < estimated_lamports {
+        problems.push(format!(
+            "Wallet balance {} lamports is below the estimated {} lamports",
+            balance_lamports, estimated_lamports
+        ));
+    }
+
+    // Re-registering a domain the wallet already holds is fine, as with register_domain
+    let domain_available = match &request.domain {
+        Some(domain) => {
+            let existing = olympus.get_domain(domain).await.map_err(ShadowError::BadRequest)?;
+            let available = existing.is_none_or(|d| d.owner_pubkey == request.wallet);
+            if !available {
+                problems.push(format!("Domain {} is already registered", domain));
+            }
+            Some(available)
+        }
+        None => None,
+    };
+
+    Ok(DeployPlan {
+        estimated_lamports,
+        balance_lamports,
+        files: request.files.len(),
+        total_bytes,
+        domain_available,
+        deployable: problems.is_empty(),
+        problems,
+        warnings,
+    })
+}
@@ -1,8 +1,106 @@
 // Metrics collection for Shadow backend
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
+/// Prefix on every metric name in the Prometheus exposition
+const PROMETHEUS_PREFIX: &str = "shadow_";
+
+/// Histogram every HTTP request's duration is observed into, in seconds
+pub const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+
+/// Upper bounds of the histogram buckets, in seconds; the same defaults Prometheus clients use
+pub const DEFAULT_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// A count that only goes up, exported as a Prometheus counter
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+impl Counter {
+    pub fn inc_by(&self, amount: u64) {
+        self.value.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// Observations sorted into fixed buckets, with their sum and count
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    /// Per-bucket counts, not cumulative; the last bucket is everything above the highest bound
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    /// f64 bits, since there is no atomic float
+    sum: AtomicU64,
+}
+
+/// Point-in-time view of a histogram
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramSnapshot {
+    /// (upper bound, observations at or below it), cumulative like Prometheus `le` buckets
+    pub buckets: Vec<(f64, u64)>,
+    pub sum: f64,
+    pub count: u64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &[f64]) -> Self {
+        let mut bounds = bounds.to_vec();
+        bounds.retain(|b| b.is_finite());
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        Self {
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            bounds,
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    /// Record one observation. NaN is ignored; it belongs in no bucket.
+    pub fn observe(&self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        let bucket = self.bounds.iter().position(|bound| value <= *bound).unwrap_or(self.bounds.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let _ = self.sum.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f64::from_bits(bits) + value).to_bits())
+        });
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = self.bounds.iter().zip(&self.buckets)
+            .map(|(bound, bucket)| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+        HistogramSnapshot {
+            buckets,
+            sum: f64::from_bits(self.sum.load(Ordering::Relaxed)),
+            count: self.count.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum.store(0f64.to_bits(), Ordering::Relaxed);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackendMetrics {
     pub total_requests: u64,
@@ -11,8 +109,28 @@ pub struct BackendMetrics {
     pub average_response_time_ms: f64,
     pub cache_hits: u64,
     pub cache_misses: u64,
+    /// Objects too large for the cache that were streamed through instead
+    pub cache_bypasses: u64,
+    pub bytes_streamed: u64,
     pub database_queries: u64,
     pub solana_rpc_calls: u64,
+    pub analytics_summaries_executed: u64,
+    pub analytics_summaries_skipped: u64,
+    pub outbox_relayed: u64,
+    pub outbox_relay_failures: u64,
+    pub outbox_pending: u64,
+    pub outbox_failed: u64,
+    pub outbox_lag_seconds: u64,
+    pub search_queries: u64,
+    pub search_zero_results: u64,
+    /// Share of searches that found nothing
+    pub search_zero_result_rate: f64,
+    pub search_analytics_dropped: u64,
+    /// Counters recorded by name through `record_counter`
+    #[serde(default)]
+    pub counters: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub histograms: BTreeMap<String, HistogramSnapshot>,
 }
 
 pub struct MetricsCollector {
@@ -22,8 +140,22 @@ pub struct MetricsCollector {
     response_times: Arc<dashmap::DashMap<String, Vec<u64>>>,
     cache_hits: Arc<AtomicU64>,
     cache_misses: Arc<AtomicU64>,
+    cache_bypasses: Arc<AtomicU64>,
+    bytes_streamed: Arc<AtomicU64>,
     database_queries: Arc<AtomicU64>,
     solana_rpc_calls: Arc<AtomicU64>,
+    analytics_summaries_executed: Arc<AtomicU64>,
+    analytics_summaries_skipped: Arc<AtomicU64>,
+    outbox_relayed: Arc<AtomicU64>,
+    outbox_relay_failures: Arc<AtomicU64>,
+    outbox_pending: Arc<AtomicU64>,
+    outbox_failed: Arc<AtomicU64>,
+    outbox_lag_seconds: Arc<AtomicU64>,
+    search_queries: Arc<AtomicU64>,
+    search_zero_results: Arc<AtomicU64>,
+    search_analytics_dropped: Arc<AtomicU64>,
+    counters: Arc<dashmap::DashMap<String, Counter>>,
+    histograms: Arc<dashmap::DashMap<String, Histogram>>,
 }
 
 impl MetricsCollector {
@@ -35,8 +167,22 @@ impl MetricsCollector {
             response_times: Arc::new(dashmap::DashMap::new()),
             cache_hits: Arc::new(AtomicU64::new(0)),
             cache_misses: Arc::new(AtomicU64::new(0)),
+            cache_bypasses: Arc::new(AtomicU64::new(0)),
+            bytes_streamed: Arc::new(AtomicU64::new(0)),
             database_queries: Arc::new(AtomicU64::new(0)),
             solana_rpc_calls: Arc::new(AtomicU64::new(0)),
+            analytics_summaries_executed: Arc::new(AtomicU64::new(0)),
+            analytics_summaries_skipped: Arc::new(AtomicU64::new(0)),
+            outbox_relayed: Arc::new(AtomicU64::new(0)),
+            outbox_relay_failures: Arc::new(AtomicU64::new(0)),
+            outbox_pending: Arc::new(AtomicU64::new(0)),
+            outbox_failed: Arc::new(AtomicU64::new(0)),
+            outbox_lag_seconds: Arc::new(AtomicU64::new(0)),
+            search_queries: Arc::new(AtomicU64::new(0)),
+            search_zero_results: Arc::new(AtomicU64::new(0)),
+            search_analytics_dropped: Arc::new(AtomicU64::new(0)),
+            counters: Arc::new(dashmap::DashMap::new()),
+            histograms: Arc::new(dashmap::DashMap::new()),
         }
     }
     
@@ -50,7 +196,7 @@ impl MetricsCollector {
         }
         
         let mut times = self.response_times.entry(endpoint.to_string())
-            .or_insert_with(Vec::new);
+            .or_default();
         times.push(response_time_ms);
         
         // Keep only last 1000 measurements per endpoint
@@ -67,6 +213,15 @@ impl MetricsCollector {
         self.cache_misses.fetch_add(1, Ordering::Relaxed);
     }
     
+    /// A storage object streamed past the cache because of its size or a range request
+    pub fn record_cache_bypass(&self) {
+        self.cache_bypasses.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    pub fn record_bytes_streamed(&self, bytes: u64) {
+        self.bytes_streamed.fetch_add(bytes, Ordering::Relaxed);
+    }
+    
     pub fn record_database_query(&self) {
         self.database_queries.fetch_add(1, Ordering::Relaxed);
     }
@@ -75,6 +230,61 @@ impl MetricsCollector {
         self.solana_rpc_calls.fetch_add(1, Ordering::Relaxed);
     }
     
+    pub fn record_analytics_summary(&self, executed: bool) {
+        if executed {
+            self.analytics_summaries_executed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.analytics_summaries_skipped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    pub fn record_outbox_relay(&self, success: bool) {
+        if success {
+            self.outbox_relayed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.outbox_relay_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// Snapshot of the outbox backlog, refreshed by the relay after each pass
+    pub fn set_outbox_backlog(&self, pending: u64, failed: u64, lag_seconds: u64) {
+        self.outbox_pending.store(pending, Ordering::Relaxed);
+        self.outbox_failed.store(failed, Ordering::Relaxed);
+        self.outbox_lag_seconds.store(lag_seconds, Ordering::Relaxed);
+    }
+    
+    pub fn record_search(&self, zero_results: bool) {
+        self.search_queries.fetch_add(1, Ordering::Relaxed);
+        if zero_results {
+            self.search_zero_results.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// A search record that didn't fit in the Clio buffer
+    pub fn record_search_analytics_dropped(&self) {
+        self.search_analytics_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Add to the named counter, creating it on first use
+    pub fn record_counter(&self, name: &str, amount: u64) {
+        if let Some(counter) = self.counters.get(name) {
+            counter.inc_by(amount);
+            return;
+        }
+        self.counters.entry(name.to_string()).or_default().inc_by(amount);
+    }
+    
+    /// Observe `value` into the named histogram, creating it with `DEFAULT_BUCKETS` on first use
+    pub fn record_histogram_observation(&self, name: &str, value: f64) {
+        if let Some(histogram) = self.histograms.get(name) {
+            histogram.observe(value);
+            return;
+        }
+        self.histograms.entry(name.to_string())
+            .or_insert_with(|| Histogram::new(&DEFAULT_BUCKETS))
+            .observe(value);
+    }
+    
     pub fn get_metrics(&self) -> BackendMetrics {
         let total = self.total_requests.load(Ordering::Relaxed);
         let successful = self.successful_requests.load(Ordering::Relaxed);
@@ -93,6 +303,14 @@ impl MetricsCollector {
             0.0
         };
         
+        let searches = self.search_queries.load(Ordering::Relaxed);
+        let zero_results = self.search_zero_results.load(Ordering::Relaxed);
+        let zero_result_rate = if searches > 0 {
+            zero_results as f64 / searches as f64
+        } else {
+            0.0
+        };
+        
         BackendMetrics {
             total_requests: total,
             successful_requests: successful,
@@ -100,9 +318,80 @@ impl MetricsCollector {
             average_response_time_ms: avg_time,
             cache_hits: self.cache_hits.load(Ordering::Relaxed),
             cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            cache_bypasses: self.cache_bypasses.load(Ordering::Relaxed),
+            bytes_streamed: self.bytes_streamed.load(Ordering::Relaxed),
             database_queries: self.database_queries.load(Ordering::Relaxed),
             solana_rpc_calls: self.solana_rpc_calls.load(Ordering::Relaxed),
+            analytics_summaries_executed: self.analytics_summaries_executed.load(Ordering::Relaxed),
+            analytics_summaries_skipped: self.analytics_summaries_skipped.load(Ordering::Relaxed),
+            outbox_relayed: self.outbox_relayed.load(Ordering::Relaxed),
+            outbox_relay_failures: self.outbox_relay_failures.load(Ordering::Relaxed),
+            outbox_pending: self.outbox_pending.load(Ordering::Relaxed),
+            outbox_failed: self.outbox_failed.load(Ordering::Relaxed),
+            outbox_lag_seconds: self.outbox_lag_seconds.load(Ordering::Relaxed),
+            search_queries: searches,
+            search_zero_results: zero_results,
+            search_zero_result_rate: zero_result_rate,
+            search_analytics_dropped: self.search_analytics_dropped.load(Ordering::Relaxed),
+            counters: self.counters.iter().map(|c| (c.key().clone(), c.value().get())).collect(),
+            histograms: self.histograms.iter().map(|h| (h.key().clone(), h.value().snapshot())).collect(),
+        }
+    }
+    
+    /// Every metric in the Prometheus text exposition format (version 0.0.4), names prefixed with `shadow_`
+    pub fn to_prometheus_text(&self) -> String {
+        let m = self.get_metrics();
+        let fixed: [(&str, &str, &str, f64); 21] = [
+            ("requests_total", "counter", "HTTP requests handled", m.total_requests as f64),
+            ("requests_successful_total", "counter", "HTTP requests answered with a status below 400", m.successful_requests as f64),
+            ("requests_failed_total", "counter", "HTTP requests answered with a status of 400 or above", m.failed_requests as f64),
+            ("average_response_time_ms", "gauge", "Mean response time over the recent requests kept per endpoint", m.average_response_time_ms),
+            ("cache_hits_total", "counter", "Content cache hits", m.cache_hits as f64),
+            ("cache_misses_total", "counter", "Content cache misses", m.cache_misses as f64),
+            ("cache_bypasses_total", "counter", "Storage objects streamed past the cache", m.cache_bypasses as f64),
+            ("bytes_streamed_total", "counter", "Bytes streamed from storage", m.bytes_streamed as f64),
+            ("database_queries_total", "counter", "Database queries issued", m.database_queries as f64),
+            ("solana_rpc_calls_total", "counter", "Solana RPC calls made", m.solana_rpc_calls as f64),
+            ("analytics_summaries_executed_total", "counter", "Analytics summaries computed", m.analytics_summaries_executed as f64),
+            ("analytics_summaries_skipped_total", "counter", "Analytics summaries skipped", m.analytics_summaries_skipped as f64),
+            ("outbox_relayed_total", "counter", "Outbox entries relayed", m.outbox_relayed as f64),
+            ("outbox_relay_failures_total", "counter", "Outbox relay attempts that failed", m.outbox_relay_failures as f64),
+            ("outbox_pending", "gauge", "Outbox entries waiting to be relayed", m.outbox_pending as f64),
+            ("outbox_failed", "gauge", "Outbox entries that gave up", m.outbox_failed as f64),
+            ("outbox_lag_seconds", "gauge", "Age of the oldest pending outbox entry", m.outbox_lag_seconds as f64),
+            ("search_queries_total", "counter", "Search queries served", m.search_queries as f64),
+            ("search_zero_results_total", "counter", "Search queries that found nothing", m.search_zero_results as f64),
+            ("search_zero_result_rate", "gauge", "Share of searches that found nothing", m.search_zero_result_rate),
+            ("search_analytics_dropped_total", "counter", "Search records dropped from a full Clio buffer", m.search_analytics_dropped as f64),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in fixed {
+            let name = format!("{}{}", PROMETHEUS_PREFIX, name);
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}\n{} {}", name, help, name, kind, name, value);
+        }
+        for (name, value) in &m.counters {
+            let mut name = prometheus_name(name);
+            if !name.ends_with("_total") {
+                name.push_str("_total");
+            }
+            let _ = writeln!(out, "# HELP {} Counter {}\n# TYPE {} counter\n{} {}", name, name, name, name, value);
+        }
+        for (name, histogram) in &m.histograms {
+            let name = prometheus_name(name);
+            let help = if name == format!("{}{}", PROMETHEUS_PREFIX, HTTP_REQUEST_DURATION) {
+                "HTTP request duration in seconds".to_string()
+            } else {
+                format!("Histogram {}", name)
+            };
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} histogram", name, help, name);
+            for (bound, count) in &histogram.buckets {
+                let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+            }
+            let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+            let _ = writeln!(out, "{}_sum {}\n{}_count {}", name, histogram.sum, name, histogram.count);
         }
+        out
     }
     
     pub fn reset(&self) {
@@ -112,11 +401,35 @@ impl MetricsCollector {
         self.response_times.clear();
         self.cache_hits.store(0, Ordering::Relaxed);
         self.cache_misses.store(0, Ordering::Relaxed);
+        self.cache_bypasses.store(0, Ordering::Relaxed);
+        self.bytes_streamed.store(0, Ordering::Relaxed);
         self.database_queries.store(0, Ordering::Relaxed);
         self.solana_rpc_calls.store(0, Ordering::Relaxed);
+        self.analytics_summaries_executed.store(0, Ordering::Relaxed);
+        self.analytics_summaries_skipped.store(0, Ordering::Relaxed);
+        self.outbox_relayed.store(0, Ordering::Relaxed);
+        self.outbox_relay_failures.store(0, Ordering::Relaxed);
+        self.outbox_pending.store(0, Ordering::Relaxed);
+        self.outbox_failed.store(0, Ordering::Relaxed);
+        self.outbox_lag_seconds.store(0, Ordering::Relaxed);
+        self.search_queries.store(0, Ordering::Relaxed);
+        self.search_zero_results.store(0, Ordering::Relaxed);
+        self.search_analytics_dropped.store(0, Ordering::Relaxed);
+        self.counters.clear();
+        for histogram in self.histograms.iter() {
+            histogram.reset();
+        }
     }
 }
 
+/// `name` with the shadow_ prefix and anything Prometheus doesn't allow in a name replaced by '_'
+fn prometheus_name(name: &str) -> String {
+    let sanitized: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == ':' { c } else { '_' })
+        .collect();
+    format!("{}{}", PROMETHEUS_PREFIX, sanitized)
+}
+
 impl Default for MetricsCollector {
     fn default() -> Self {
         Self::new()
@@ -137,6 +450,16 @@ mod tests {
         metrics.record_cache_miss();
         metrics.record_database_query();
         metrics.record_solana_rpc();
+        metrics.record_analytics_summary(true);
+        metrics.record_analytics_summary(false);
+        metrics.record_analytics_summary(false);
+        metrics.record_outbox_relay(true);
+        metrics.record_outbox_relay(false);
+        metrics.set_outbox_backlog(4, 1, 30);
+        metrics.record_search(true);
+        metrics.record_search(false);
+        metrics.record_search(false);
+        metrics.record_search(false);
         
         let result = metrics.get_metrics();
         assert_eq!(result.total_requests, 2);
@@ -146,6 +469,60 @@ mod tests {
         assert_eq!(result.cache_misses, 1);
         assert_eq!(result.database_queries, 1);
         assert_eq!(result.solana_rpc_calls, 1);
+        assert_eq!(result.analytics_summaries_executed, 1);
+        assert_eq!(result.analytics_summaries_skipped, 2);
+        assert_eq!(result.outbox_relayed, 1);
+        assert_eq!(result.outbox_relay_failures, 1);
+        assert_eq!(result.outbox_pending, 4);
+        assert_eq!(result.outbox_lag_seconds, 30);
+        assert_eq!(result.search_queries, 4);
+        assert_eq!(result.search_zero_result_rate, 0.25);
+    }
+    
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let histogram = Histogram::new(&[1.0, 0.1, 0.5, f64::INFINITY]);
+        for value in [0.05, 0.1, 0.3, 2.0, f64::NAN] {
+            histogram.observe(value);
+        }
+        
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.buckets, vec![(0.1, 2), (0.5, 3), (1.0, 3)]);
+        assert_eq!(snapshot.count, 4);
+        assert!((snapshot.sum - 2.45).abs() < 1e-9);
+        
+        histogram.reset();
+        assert_eq!(histogram.snapshot().count, 0);
+    }
+    
+    #[test]
+    fn test_prometheus_text_format() {
+        let metrics = MetricsCollector::new();
+        metrics.record_request(true, 100, "/api/test");
+        metrics.record_counter("sites-deployed", 2);
+        metrics.record_counter("sites-deployed", 1);
+        metrics.record_histogram_observation(HTTP_REQUEST_DURATION, 0.02);
+        metrics.record_histogram_observation(HTTP_REQUEST_DURATION, 30.0);
+        
+        let text = metrics.to_prometheus_text();
+        assert!(text.contains("# HELP shadow_requests_total HTTP requests handled\n# TYPE shadow_requests_total counter\nshadow_requests_total 1\n"));
+        assert!(text.contains("# TYPE shadow_outbox_pending gauge\nshadow_outbox_pending 0\n"));
+        assert!(text.contains("# TYPE shadow_sites_deployed_total counter\nshadow_sites_deployed_total 3\n"));
+        assert!(text.contains("# TYPE shadow_http_request_duration_seconds histogram\n"));
+        assert!(text.contains("shadow_http_request_duration_seconds_bucket{le=\"0.01\"} 0\n"));
+        assert!(text.contains("shadow_http_request_duration_seconds_bucket{le=\"0.025\"} 1\n"));
+        assert!(text.contains("shadow_http_request_duration_seconds_bucket{le=\"10\"} 1\n"));
+        assert!(text.contains("shadow_http_request_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("shadow_http_request_duration_seconds_sum 30.02\nshadow_http_request_duration_seconds_count 2\n"));
+        // Every sample line is preceded by its own HELP and TYPE
+        for line in text.lines().filter(|l| !l.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            let family = name.trim_end_matches("_bucket").trim_end_matches("_sum").trim_end_matches("_count");
+            assert!(text.contains(&format!("# TYPE {} ", family)), "{}", line);
+        }
+        
+        metrics.reset();
+        assert!(metrics.to_prometheus_text().contains("shadow_http_request_duration_seconds_count 0\n"));
     }
 }
 
@@ -7,7 +7,7 @@ use actix_web::middleware::Next;
 use actix_web::http::header::{HeaderName, HeaderValue};
 use std::time::Instant;
 use tracing::{info, warn};
-use crate::metrics::MetricsCollector;
+use crate::metrics::{MetricsCollector, HTTP_REQUEST_DURATION};
 
 /// Request timing middleware with metrics collection
 pub async fn timing_middleware(
@@ -31,6 +31,7 @@ pub async fn timing_middleware(
     // Record metrics if available
     if let Some(metrics) = metrics_opt {
         metrics.record_request(success, duration_ms, &path);
+        metrics.record_histogram_observation(HTTP_REQUEST_DURATION, duration.as_secs_f64());
     }
     
     if status >= 400 {
@@ -0,0 +1,645 @@
+// Mnemosyne - Titaness of memory
+// Transactional outbox: cross-store side effects are remembered next to the Mongo write
+// that caused them and replayed by a relay until they have happened exactly once.
+use crate::asclepius::{DeployReport, SiteVersion, SiteVersions, VersionStatus};
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::OutboxConfig;
+use crate::metrics::MetricsCollector;
+use crate::websocket::{HermesBroker, CONTENT_UPDATED_EVENT};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
+
+/// What happened; handlers re-read current state, so payloads only carry keys
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutboxPayload {
+    SiteUpserted { program_address: String },
+    /// A conditional site update landed at `revision`; `storage_cid` is set when the content changed
+    SiteUpdated {
+        program_address: String,
+        revision: i64,
+        storage_cid: Option<String>,
+        updated_by: String,
+    },
+    /// A staged deploy went live as `version_id`
+    SiteDeployed {
+        program_address: String,
+        storage_cid: String,
+        version_id: String,
+    },
+    DomainVerified { domain: String, program_address: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    /// Written ahead of a non-transactional primary write, not yet confirmed
+    Staged,
+    Pending,
+    Processing,
+    Done,
+    Failed,
+}
+
+impl OutboxStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OutboxStatus::Staged => "staged",
+            OutboxStatus::Pending => "pending",
+            OutboxStatus::Processing => "processing",
+            OutboxStatus::Done => "done",
+            OutboxStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub payload: OutboxPayload,
+    pub status: OutboxStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+    /// Earliest time the relay may (re)claim the entry: lease expiry or retry backoff
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub available_at: DateTime<Utc>,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
+    pub processed_at: Option<DateTime<Utc>>,
+}
+
+/// The primary document write an outbox entry is attached to
+#[derive(Debug, Clone)]
+pub struct PrimaryWrite {
+    pub collection: String,
+    pub filter: Document,
+    pub update: Document,
+    pub upsert: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutboxStats {
+    pub pending: u64,
+    pub failed: u64,
+    /// Age of the oldest entry still waiting to be relayed
+    pub lag_seconds: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RelayReport {
+    pub processed: u32,
+    pub retried: u32,
+    pub failed: u32,
+}
+
+/// How long a staged entry waits for its primary write before the relay takes over
+const STAGED_GRACE_SECONDS: i64 = 60;
+
+/// Delay before retrying an entry that has failed `attempts` times
+pub fn retry_backoff(attempts: u32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempts.min(16)).min(300))
+}
+
+pub struct Mnemosyne {
+    db: Database,
+    supports_transactions: OnceCell<bool>,
+    clock: SharedClock,
+}
+
+impl Mnemosyne {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            supports_transactions: OnceCell::new(),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn get_outbox_collection(&self) -> Collection<OutboxEntry> {
+        self.db.collection::<OutboxEntry>("outbox")
+    }
+
+    /// Multi-document transactions need a replica set or a mongos router
+    async fn transactions_supported(&self) -> bool {
+        *self.supports_transactions.get_or_init(|| async {
+            match self.db.run_command(doc! { "hello": 1 }, None).await {
+                Ok(hello) => hello.contains_key("setName") || hello.get_str("msg") == Ok("isdbgrid"),
+                Err(_) => false,
+            }
+        }).await
+    }
+
+    /// Apply `primary` and record `payload` for the relay as one unit. Uses a transaction
+    /// where the deployment supports it; otherwise the entry is staged first, promoted once
+    /// the primary write lands, and removed again if it fails.
+    pub async fn write(&self, primary: PrimaryWrite, payload: OutboxPayload) -> Result<String, String> {
+        self.write_inner(primary, payload, false).await
+            .map(|id| id.unwrap_or_default())
+    }
+
+    /// Like `write`, but for conditional updates: when `primary.filter` matches nothing the
+    /// entry is discarded and `None` is returned, so side effects only follow a real write.
+    pub async fn write_if_matched(&self, primary: PrimaryWrite, payload: OutboxPayload) -> Result<Option<String>, String> {
+        self.write_inner(primary, payload, true).await
+    }
+
+    async fn write_inner(&self, primary: PrimaryWrite, payload: OutboxPayload, require_match: bool) -> Result<Option<String>, String> {
+        let now = self.clock.now_utc();
+        let mut entry = OutboxEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            payload,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            created_at: now,
+            available_at: now,
+            processed_at: None,
+        };
+        let target = self.db.collection::<Document>(&primary.collection);
+        let options = mongodb::options::UpdateOptions::builder()
+            .upsert(primary.upsert)
+            .build();
+        let matched = |result: &mongodb::results::UpdateResult| {
+            !require_match || result.matched_count > 0 || result.upserted_id.is_some()
+        };
+
+        if self.transactions_supported().await {
+            let outbox = self.get_outbox_collection();
+            let mut session = outbox.client().start_session(None).await
+                .map_err(|e| format!("Database error: {}", e))?;
+            session.start_transaction(None).await
+                .map_err(|e| format!("Database error: {}", e))?;
+            let result = match target.update_one_with_session(primary.filter, primary.update, options, &mut session).await {
+                Ok(result) => result,
+                // A concurrent transaction on the same document won; for a conditional write that is a miss
+                Err(e) if require_match && e.contains_label(mongodb::error::TRANSIENT_TRANSACTION_ERROR) => {
+                    let _ = session.abort_transaction().await;
+                    return Ok(None);
+                }
+                Err(e) => return Err(format!("Database error: {}", e)),
+            };
+            if !matched(&result) {
+                session.abort_transaction().await
+                    .map_err(|e| format!("Database error: {}", e))?;
+                return Ok(None);
+            }
+            outbox.insert_one_with_session(&entry, None, &mut session).await
+                .map_err(|e| format!("Database error: {}", e))?;
+            session.commit_transaction().await
+                .map_err(|e| format!("Database error: {}", e))?;
+            return Ok(Some(entry.id));
+        }
+
+        // Staged entries become claimable after a grace period, so a crash between the two
+        // writes still gets relayed; handlers check current state before acting.
+        entry.status = OutboxStatus::Staged;
+        entry.available_at = now + chrono::Duration::seconds(STAGED_GRACE_SECONDS);
+        self.get_outbox_collection().insert_one(&entry, None).await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        match target.update_one(primary.filter, primary.update, options).await {
+            Ok(result) if matched(&result) => {}
+            Ok(_) => {
+                self.get_outbox_collection().delete_one(doc! { "_id": &entry.id }, None).await
+                    .map_err(|e| format!("Database error: {}", e))?;
+                return Ok(None);
+            }
+            Err(e) => {
+                let _ = self.get_outbox_collection().delete_one(doc! { "_id": &entry.id }, None).await;
+                return Err(format!("Database error: {}", e));
+            }
+        }
+
+        self.get_outbox_collection()
+            .update_one(
+                doc! { "_id": &entry.id, "status": OutboxStatus::Staged.as_str() },
+                doc! { "$set": {
+                    "status": OutboxStatus::Pending.as_str(),
+                    "available_at": mongodb::bson::DateTime::from_millis(now.timestamp_millis())
+                } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(Some(entry.id))
+    }
+
+    pub async fn get_entry(&self, id: &str) -> Result<Option<OutboxEntry>, String> {
+        self.get_outbox_collection().find_one(doc! { "_id": id }, None).await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Entries oldest first, optionally narrowed to one status
+    pub async fn list_entries(&self, status: Option<OutboxStatus>, limit: i64) -> Result<Vec<OutboxEntry>, String> {
+        let filter = match status {
+            Some(status) => doc! { "status": status.as_str() },
+            None => doc! { "status": { "$ne": OutboxStatus::Done.as_str() } },
+        };
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .limit(limit)
+            .build();
+
+        self.get_outbox_collection().find(filter, options).await
+            .map_err(|e| format!("Database error: {}", e))?
+            .try_collect().await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Make a failed or stuck entry claimable again right away. Returns false for
+    /// unknown ids and entries that have already been relayed.
+    pub async fn retry(&self, id: &str) -> Result<bool, String> {
+        let filter = doc! {
+            "_id": id,
+            "status": { "$in": [
+                OutboxStatus::Staged.as_str(),
+                OutboxStatus::Pending.as_str(),
+                OutboxStatus::Processing.as_str(),
+                OutboxStatus::Failed.as_str(),
+            ] }
+        };
+        let update = doc! {
+            "$set": {
+                "status": OutboxStatus::Pending.as_str(),
+                "attempts": 0,
+                "available_at": mongodb::bson::DateTime::now()
+            },
+            "$unset": { "last_error": "" }
+        };
+
+        let result = self.get_outbox_collection().update_one(filter, update, None).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(result.matched_count > 0)
+    }
+
+    pub async fn stats(&self) -> Result<OutboxStats, String> {
+        let collection = self.get_outbox_collection();
+        let waiting = doc! { "status": { "$in": [
+            OutboxStatus::Staged.as_str(),
+            OutboxStatus::Pending.as_str(),
+            OutboxStatus::Processing.as_str(),
+        ] } };
+
+        let pending = collection.count_documents(waiting.clone(), None).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let failed = collection.count_documents(doc! { "status": OutboxStatus::Failed.as_str() }, None).await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let options = mongodb::options::FindOneOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+        let oldest = collection.find_one(waiting, options).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let lag_seconds = oldest
+            .map(|entry| (self.clock.now_utc() - entry.created_at).num_seconds().max(0) as u64)
+            .unwrap_or(0);
+
+        Ok(OutboxStats { pending, failed, lag_seconds })
+    }
+}
+
+/// Background relay that performs the side effects recorded in the outbox
+pub struct OutboxRelay {
+    db: Database,
+    outbox: Mnemosyne,
+    config: OutboxConfig,
+    broker: Option<Arc<HermesBroker>>,
+    metrics: Option<Arc<MetricsCollector>>,
+    clock: SharedClock,
+}
+
+impl OutboxRelay {
+    pub fn new(db: Database, config: OutboxConfig) -> Self {
+        Self {
+            outbox: Mnemosyne::new(db.clone()),
+            db,
+            config,
+            broker: None,
+            metrics: None,
+            clock: SystemClock::shared(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.outbox = self.outbox.with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    /// Publish relayed events to live Hermes subscribers
+    pub fn with_broker(mut self, broker: Arc<HermesBroker>) -> Self {
+        self.broker = Some(broker);
+        self
+    }
+
+    /// Report relay outcomes and outbox lag in the backend metrics
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Lease the oldest claimable entry. Expired leases are reclaimed, which is how
+    /// entries held by a relay that died mid-processing get finished.
+    async fn claim_next(&self) -> Result<Option<OutboxEntry>, String> {
+        let now = self.clock.now_utc();
+        let lease_until = now + chrono::Duration::seconds(self.config.lease_seconds as i64);
+        let filter = doc! {
+            "status": { "$in": [
+                OutboxStatus::Staged.as_str(),
+                OutboxStatus::Pending.as_str(),
+                OutboxStatus::Processing.as_str(),
+            ] },
+            "available_at": { "$lte": mongodb::bson::DateTime::from_millis(now.timestamp_millis()) }
+        };
+        let update = doc! {
+            "$set": {
+                "status": OutboxStatus::Processing.as_str(),
+                "available_at": mongodb::bson::DateTime::from_millis(lease_until.timestamp_millis())
+            },
+            "$inc": { "attempts": 1 }
+        };
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .sort(doc! { "available_at": 1 })
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+
+        self.outbox.get_outbox_collection().find_one_and_update(filter, update, options).await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    /// Relay up to one batch of entries
+    pub async fn run_once(&self, shutdown: &CancellationToken) -> Result<RelayReport, String> {
+        let mut report = RelayReport::default();
+
+        for _ in 0..self.config.batch_size {
+            if shutdown.is_cancelled() {
+                break;
+            }
+            let Some(entry) = self.claim_next().await? else { break };
+            self.complete(entry, &mut report).await?;
+        }
+
+        if let Some(metrics) = &self.metrics {
+            let stats = self.outbox.stats().await?;
+            metrics.set_outbox_backlog(stats.pending, stats.failed, stats.lag_seconds);
+        }
+
+        Ok(report)
+    }
+
+    async fn complete(&self, entry: OutboxEntry, report: &mut RelayReport) -> Result<(), String> {
+        let collection = self.outbox.get_outbox_collection();
+        // Only the holder of the current lease may settle the entry
+        let filter = doc! { "_id": &entry.id, "status": OutboxStatus::Processing.as_str(), "attempts": entry.attempts };
+
+        let update = match self.apply(&entry).await {
+            Ok(()) => {
+                report.processed += 1;
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_outbox_relay(true);
+                }
+                doc! {
+                    "$set": { "status": OutboxStatus::Done.as_str(), "processed_at": mongodb::bson::DateTime::now() },
+                    "$unset": { "last_error": "" }
+                }
+            }
+            Err(e) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_outbox_relay(false);
+                }
+                if entry.attempts >= self.config.max_attempts {
+                    report.failed += 1;
+                    tracing::warn!("Outbox entry {} failed permanently: {}", entry.id, e);
+                    doc! { "$set": { "status": OutboxStatus::Failed.as_str(), "last_error": e } }
+                } else {
+                    report.retried += 1;
+                    let retry_at = self.clock.now_utc() + chrono::Duration::from_std(retry_backoff(entry.attempts))
+                        .unwrap_or_else(|_| chrono::Duration::seconds(300));
+                    doc! { "$set": {
+                        "status": OutboxStatus::Pending.as_str(),
+                        "last_error": e,
+                        "available_at": mongodb::bson::DateTime::from_millis(retry_at.timestamp_millis())
+                    } }
+                }
+            }
+        };
+
+        collection.update_one(filter, update, None).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(())
+    }
+
+    /// Perform an entry's side effects. Every step is idempotent, so an entry that is
+    /// relayed twice after a crash leaves the same state behind as one relayed once.
+    async fn apply(&self, entry: &OutboxEntry) -> Result<(), String> {
+        match &entry.payload {
+            OutboxPayload::SiteUpserted { program_address } => {
+                let Some(site) = self.sync_search_index(program_address).await? else {
+                    return Ok(());
+                };
+
+                self.publish(program_address, serde_json::json!({
+                    "type": "site_updated",
+                    "event_id": entry.id,
+                    "program_address": program_address,
+                    "storage_cid": site.storage_cid,
+                })).await;
+            }
+            OutboxPayload::SiteUpdated { program_address, revision, storage_cid, updated_by } => {
+                let Some(site) = self.sync_search_index(program_address).await? else {
+                    return Ok(());
+                };
+
+                // Keyed by the outbox id so a replay cannot record the version twice
+                if let Some(storage_cid) = storage_cid {
+                    let versions = SiteVersions::new(self.db.clone());
+                    if versions.get(&entry.id).await?.is_none() {
+                        versions.record(&SiteVersion {
+                            id: entry.id.clone(),
+                            program_address: program_address.clone(),
+                            storage_cid: storage_cid.clone(),
+                            status: VersionStatus::Live,
+                            report: DeployReport::forced(),
+                            deployed_by: updated_by.clone(),
+                            created_at: entry.created_at,
+                        }).await?;
+                    }
+                }
+
+                self.publish(program_address, serde_json::json!({
+                    "type": "site_updated",
+                    "event_id": entry.id,
+                    "program_address": program_address,
+                    "storage_cid": site.storage_cid,
+                    "revision": revision,
+                })).await;
+                if let Some(storage_cid) = storage_cid {
+                    self.publish_content_updated(&entry.id, program_address, storage_cid, &entry.id).await;
+                }
+            }
+            OutboxPayload::SiteDeployed { program_address, storage_cid, version_id } => {
+                let Some(site) = self.sync_search_index(program_address).await? else {
+                    return Ok(());
+                };
+
+                self.publish(program_address, serde_json::json!({
+                    "type": "site_updated",
+                    "event_id": entry.id,
+                    "program_address": program_address,
+                    "storage_cid": site.storage_cid,
+                })).await;
+                self.publish_content_updated(&entry.id, program_address, storage_cid, version_id).await;
+            }
+            OutboxPayload::DomainVerified { domain, program_address } => {
+                // Skip entries whose primary write never landed or has been superseded
+                let current = self.db.collection::<Document>("domains")
+                    .find_one(doc! { "_id": domain, "program_address": program_address, "verified": true }, None)
+                    .await
+                    .map_err(|e| format!("Database error: {}", e))?;
+                if current.is_none() {
+                    return Ok(());
+                }
+
+                self.db.collection::<Document>("sites")
+                    .update_one(
+                        doc! { "_id": program_address },
+                        doc! { "$set": { "verified_domain": domain } },
+                        None,
+                    )
+                    .await
+                    .map_err(|e| format!("Database error: {}", e))?;
+
+                // Keyed by the outbox id so a replay cannot log the event twice
+                let options = mongodb::options::UpdateOptions::builder()
+                    .upsert(true)
+                    .build();
+                self.db.collection::<Document>("domain_events")
+                    .update_one(
+                        doc! { "_id": &entry.id },
+                        doc! { "$setOnInsert": {
+                            "domain": domain,
+                            "kind": "verified",
+                            "reason": mongodb::bson::Bson::Null,
+                            "created_at": mongodb::bson::DateTime::from_millis(entry.created_at.timestamp_millis())
+                        } },
+                        options,
+                    )
+                    .await
+                    .map_err(|e| format!("Database error: {}", e))?;
+
+                self.publish(program_address, serde_json::json!({
+                    "type": "domain_verified",
+                    "event_id": entry.id,
+                    "domain": domain,
+                    "program_address": program_address,
+                })).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep the search index's denormalized title and description current, and drop
+    /// the site from search while it is marked noindex. Returns the site if it exists.
+    async fn sync_search_index(&self, program_address: &str) -> Result<Option<crate::db::Site>, String> {
+        let Some(site) = crate::db::get_site(&self.db, program_address).await
+            .map_err(|e| format!("Database error: {}", e))? else {
+            return Ok(None);
+        };
+
+        let search_index = self.db.collection::<Document>("search_index");
+        if site.noindex {
+            search_index.delete_many(doc! { "program_address": program_address }, None).await
+                .map_err(|e| format!("Database error: {}", e))?;
+            return Ok(Some(site));
+        }
+
+        let mut set = Document::new();
+        if let Some(name) = &site.name {
+            set.insert("title", name);
+        }
+        if let Some(description) = &site.description {
+            set.insert("description", description);
+        }
+        if !set.is_empty() {
+            search_index
+                .update_many(doc! { "program_address": program_address }, doc! { "$set": set }, None)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+        Ok(Some(site))
+    }
+
+    /// Delivery to live subscribers is at-least-once; `event_id` lets clients dedupe
+    async fn publish(&self, program_address: &str, event: serde_json::Value) {
+        if let Some(broker) = &self.broker {
+            broker.publish(&format!("program:{}", program_address), event.to_string()).await;
+        }
+    }
+
+    /// Tells dev pages served with the live reload script to refresh
+    async fn publish_content_updated(&self, event_id: &str, program_address: &str, storage_cid: &str, version_id: &str) {
+        self.publish(program_address, serde_json::json!({
+            "type": CONTENT_UPDATED_EVENT,
+            "event_id": event_id,
+            "program_address": program_address,
+            "storage_cid": storage_cid,
+            "version_id": version_id,
+        })).await;
+    }
+
+    pub fn spawn(self: Arc<Self>, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        let interval = Duration::from_millis(self.config.poll_interval_ms);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.run_once(&shutdown).await {
+                    Ok(report) if report.failed > 0 => tracing::warn!("Outbox relay: {:?}", report),
+                    Ok(report) => tracing::debug!("Outbox relay: {:?}", report),
+                    Err(e) => tracing::warn!("Outbox relay failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff_grows_and_caps() {
+        assert_eq!(retry_backoff(1), Duration::from_secs(2));
+        assert_eq!(retry_backoff(3), Duration::from_secs(8));
+        assert_eq!(retry_backoff(20), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_payload_is_tagged() {
+        let payload = OutboxPayload::DomainVerified {
+            domain: "app.shadow".to_string(),
+            program_address: "prog".to_string(),
+        };
+        let bson = mongodb::bson::to_document(&payload).unwrap();
+        assert_eq!(bson.get_str("type").unwrap(), "domain_verified");
+        assert_eq!(mongodb::bson::from_document::<OutboxPayload>(bson).unwrap(), payload);
+    }
+}
@@ -5,6 +5,12 @@ use mongodb::{Collection, Database};
 use mongodb::bson::doc;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::clock::{SharedClock, SystemClock};
+use crate::mnemosyne::{Mnemosyne, OutboxPayload, PrimaryWrite};
+use crate::utils::{encode_cursor, keyset_filter, next_page_cursor};
+
+/// How long a domain keeps resolving after `expires_at` before it lapses
+pub const DOMAIN_GRACE_PERIOD_DAYS: i64 = 30;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Domain {
@@ -13,9 +19,53 @@ pub struct Domain {
     pub owner_pubkey: String,              // Wallet that owns the domain
     pub program_address: String,           // Solana program address it points to
     pub verified: bool,                    // Whether domain is verified on-chain
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub created_at: DateTime<Utc>,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
     pub updated_at: DateTime<Utc>,
+    #[serde(default, deserialize_with = "crate::utils::deserialize_optional_datetime")]
     pub expires_at: Option<DateTime<Utc>>, // Optional expiration
+    #[serde(default)]
+    pub verification_failures: u32,        // Consecutive failed re-verification checks
+    #[serde(default)]
+    pub warning_interstitial: bool,        // Resolvers should warn before loading the site
+    #[serde(default)]
+    pub verification_error: Option<String>, // Why the last re-verification failed
+    #[serde(default)]
+    pub verified_authority: Option<String>, // Upgrade authority seen when the domain was verified
+}
+
+/// Where a domain is relative to its expiration
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryStatus {
+    Active,
+    /// Past `expires_at` but still resolving while the owner renews
+    GracePeriod,
+    Expired,
+}
+
+impl Domain {
+    /// Expiry status at `now`. Domains without an expiration never expire.
+    pub fn expiry_status(&self, now: DateTime<Utc>) -> ExpiryStatus {
+        match self.expires_at {
+            Some(at) if now >= at + chrono::Duration::days(DOMAIN_GRACE_PERIOD_DAYS) => ExpiryStatus::Expired,
+            Some(at) if now >= at => ExpiryStatus::GracePeriod,
+            _ => ExpiryStatus::Active,
+        }
+    }
+}
+
+/// Audit trail entry for changes Olympus makes to a domain on its own
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DomainEvent {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub domain: String,
+    pub kind: String,
+    pub reason: Option<String>,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,40 +77,65 @@ pub struct DomainRegistration {
 
 pub struct OlympusCA {
     db: Database,
+    outbox: Mnemosyne,
+    clock: SharedClock,
 }
 
 impl OlympusCA {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self {
+            outbox: Mnemosyne::new(db.clone()),
+            db,
+            clock: SystemClock::shared(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.outbox = self.outbox.with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    /// Expiry status of a domain right now
+    pub fn expiry_status(&self, domain: &Domain) -> ExpiryStatus {
+        domain.expiry_status(self.clock.now_utc())
     }
 
     fn get_domains_collection(&self) -> Collection<Domain> {
         self.db.collection::<Domain>("domains")
     }
 
+    fn get_events_collection(&self) -> Collection<DomainEvent> {
+        self.db.collection::<DomainEvent>("domain_events")
+    }
+
     /// Register a new domain (Pantheon entry)
-    /// Maps a domain to a Solana program/contract address
+    /// Maps a domain to a Solana program/contract address. Returns false, writing nothing,
+    /// when another wallet holds the domain; the owner is part of the upsert's filter, so
+    /// two wallets racing for a domain can't both get it.
     pub async fn register_domain(
         &self,
         domain: &str,
         owner_pubkey: &str,
         program_address: &str,
-    ) -> Result<(), String> {
+    ) -> Result<bool, String> {
         // Domain validation is done by Apollo, just check it's not empty
         if domain.is_empty() {
             return Err("Domain cannot be empty".to_string());
         }
 
         let collection = self.get_domains_collection();
-        let now = Utc::now();
+        let now = self.clock.now_utc();
         let bson_now = mongodb::bson::DateTime::from_millis(now.timestamp_millis());
 
-        let filter = doc! { "_id": domain };
+        let filter = doc! { "_id": domain, "owner_pubkey": owner_pubkey };
         let update = doc! {
             "$set": {
                 "owner_pubkey": owner_pubkey,
                 "program_address": program_address,
                 "verified": false,
+                "verification_failures": 0,
+                "warning_interstitial": false,
                 "updated_at": bson_now
             },
             "$setOnInsert": {
@@ -72,10 +147,11 @@ impl OlympusCA {
             .upsert(true)
             .build();
 
-        collection.update_one(filter, update, options).await
-            .map_err(|e| format!("Database error: {}", e))?;
-
-        Ok(())
+        match collection.update_one(filter, update, options).await {
+            Ok(_) => Ok(true),
+            Err(e) if crate::db::is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(format!("Database error: {}", e)),
+        }
     }
 
     /// Get domain by name
@@ -87,6 +163,21 @@ impl OlympusCA {
             .map_err(|e| format!("Database error: {}", e))
     }
 
+    /// Domains pointing at any of the given programs, in one query
+    pub async fn list_domains_for_programs(&self, program_addresses: &[String]) -> Result<Vec<Domain>, String> {
+        use futures_util::TryStreamExt;
+        let collection = self.get_domains_collection();
+        let filter = doc! { "program_address": { "$in": program_addresses } };
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "_id": 1 })
+            .build();
+
+        let cursor = collection.find(filter, options).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        cursor.try_collect().await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
     /// Get domain by program address
     pub async fn get_domain_by_program(&self, program_address: &str) -> Result<Option<Domain>, String> {
         let collection = self.get_domains_collection();
@@ -97,25 +188,77 @@ impl OlympusCA {
     }
 
     /// Verify domain ownership (mark as verified after on-chain verification)
-    pub async fn verify_domain(&self, domain: &str) -> Result<(), String> {
-        let collection = self.get_domains_collection();
-        let now = Utc::now();
-        let bson_now = mongodb::bson::DateTime::from_millis(now.timestamp_millis());
+    /// Clears any re-verification failure streak and records the program's upgrade authority.
+    /// The event log, site denormalization and subscriber notification follow via the outbox relay.
+    pub async fn verify_domain(&self, domain: &str, authority: Option<&str>) -> Result<(), String> {
+        let record = self.get_domain(domain).await?
+            .ok_or_else(|| "Domain not found".to_string())?;
+        let bson_now = mongodb::bson::DateTime::now();
 
-        let filter = doc! { "_id": domain };
-        let update = doc! {
-            "$set": {
-                "verified": true,
-                "updated_at": bson_now
-            }
+        let primary = PrimaryWrite {
+            collection: "domains".to_string(),
+            filter: doc! { "_id": domain },
+            update: doc! {
+                "$set": {
+                    "verified": true,
+                    "verification_failures": 0,
+                    "warning_interstitial": false,
+                    "verified_authority": authority,
+                    "updated_at": bson_now
+                },
+                "$unset": { "verification_error": "" }
+            },
+            upsert: false,
+        };
+        let payload = OutboxPayload::DomainVerified {
+            domain: domain.to_string(),
+            program_address: record.program_address,
         };
 
-        collection.update_one(filter, update, None).await
+        self.outbox.write(primary, payload).await?;
+        Ok(())
+    }
+
+    /// Append an entry to the domain's event log
+    pub async fn record_event(
+        &self,
+        domain: &str,
+        kind: &str,
+        reason: Option<&str>,
+    ) -> Result<(), String> {
+        let event = DomainEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            domain: domain.to_string(),
+            kind: kind.to_string(),
+            reason: reason.map(|r| r.to_string()),
+            created_at: self.clock.now_utc(),
+        };
+
+        self.get_events_collection().insert_one(event, None).await
             .map_err(|e| format!("Database error: {}", e))?;
 
         Ok(())
     }
 
+    /// Event log for a domain, oldest first
+    pub async fn list_events(&self, domain: &str) -> Result<Vec<DomainEvent>, String> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+
+        let mut cursor = self.get_events_collection().find(doc! { "domain": domain }, options).await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let mut events = Vec::new();
+        use futures_util::TryStreamExt;
+        while let Some(event) = cursor.try_next().await
+            .map_err(|e| format!("Database error: {}", e))? {
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+
     /// Transfer domain ownership
     pub async fn transfer_domain(
         &self,
@@ -123,7 +266,7 @@ impl OlympusCA {
         new_owner: &str,
     ) -> Result<(), String> {
         let collection = self.get_domains_collection();
-        let now = Utc::now();
+        let now = self.clock.now_utc();
         let bson_now = mongodb::bson::DateTime::from_millis(now.timestamp_millis());
 
         let filter = doc! { "_id": domain };
@@ -159,33 +302,55 @@ impl OlympusCA {
         Ok(domains)
     }
 
-    /// Search domains
-    pub async fn search_domains(&self, query: &str, limit: i64) -> Result<Vec<Domain>, String> {
+    /// Verified domains matching `query`, newest first, resuming after `after`
+    /// (created_at ms, domain). Returns the page and the cursor for the next one.
+    pub async fn search_domains(
+        &self,
+        query: &str,
+        limit: i64,
+        after: Option<(i64, String)>,
+    ) -> Result<(Vec<Domain>, Option<String>), String> {
         let collection = self.get_domains_collection();
-        let filter = doc! {
-            "$or": [
-                { "_id": { "$regex": query, "$options": "i" } },
-                { "program_address": { "$regex": query, "$options": "i" } }
-            ],
-            "verified": true
-        };
+        let mut filter = Self::domain_search_filter(query);
+        if let Some((created_at, domain)) = after {
+            let after = keyset_filter("created_at", mongodb::bson::DateTime::from_millis(created_at), &domain);
+            filter = doc! { "$and": [filter, after] };
+        }
 
         let options = mongodb::options::FindOptions::builder()
-            .limit(limit)
-            .sort(doc! { "created_at": -1 })
+            .limit(limit + 1)
+            .sort(doc! { "created_at": -1, "_id": -1 })
             .build();
 
-        let mut cursor = collection.find(filter, options).await
+        use futures_util::TryStreamExt;
+        let mut domains: Vec<Domain> = collection.find(filter, options).await
+            .map_err(|e| format!("Database error: {}", e))?
+            .try_collect()
+            .await
             .map_err(|e| format!("Database error: {}", e))?;
 
-        let mut domains = Vec::new();
-        use futures_util::TryStreamExt;
-        while let Some(domain) = cursor.try_next().await
-            .map_err(|e| format!("Database error: {}", e))? {
-            domains.push(domain);
-        }
+        let next_cursor = next_page_cursor(&mut domains, limit, |domain| {
+            encode_cursor(domain.created_at.timestamp_millis(), &domain.domain)
+        });
+        Ok((domains, next_cursor))
+    }
 
-        Ok(domains)
+    /// How many verified domains match `query` across all pages
+    pub async fn count_search_domains(&self, query: &str) -> Result<u64, String> {
+        self.get_domains_collection()
+            .count_documents(Self::domain_search_filter(query), None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    fn domain_search_filter(query: &str) -> mongodb::bson::Document {
+        doc! {
+            "$or": [
+                { "_id": { "$regex": query, "$options": "i" } },
+                { "program_address": { "$regex": query, "$options": "i" } }
+            ],
+            "verified": true
+        }
     }
 }
 
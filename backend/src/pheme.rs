@@ -0,0 +1,187 @@
+// Pheme - Goddess of fame and renown
+// Renders the embeddable "Live on Shadow" badges site owners put on their legacy pages
+
+use crate::error::ShadowError;
+use crate::utils::html_escape;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_LABEL: &str = "Live on Shadow";
+const MAX_LABEL_CHARS: usize = 32;
+
+/// Browsers keep a badge for a day; shared caches refetch every five minutes
+pub const BADGE_CACHE_CONTROL: &str = "public, max-age=86400, s-maxage=300";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeStyle {
+    #[default]
+    Flat,
+    Plastic,
+}
+
+/// Which number the badge shows next to the verification status
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeStat {
+    #[default]
+    Weekly,
+    Total,
+    None,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BadgeStatus {
+    Verified,
+    Unverified,
+    /// Not a registered Shadow domain
+    Unknown,
+}
+
+impl BadgeStatus {
+    fn color(self) -> &'static str {
+        match self {
+            BadgeStatus::Verified => "#2ea44f",
+            BadgeStatus::Unverified => "#dfb317",
+            BadgeStatus::Unknown => "#9f9f9f",
+        }
+    }
+
+    fn text(self) -> &'static str {
+        match self {
+            BadgeStatus::Verified => "verified",
+            BadgeStatus::Unverified => "unverified",
+            BadgeStatus::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct BadgeQuery {
+    #[serde(default)]
+    pub style: BadgeStyle,
+    #[serde(default)]
+    pub stat: BadgeStat,
+    pub label: Option<String>,
+}
+
+impl BadgeQuery {
+    /// Label text to render, rejecting anything that can't sit on a one-line badge
+    pub fn label(&self) -> Result<&str, ShadowError> {
+        let label = self.label.as_deref().map(str::trim).unwrap_or(DEFAULT_LABEL);
+        if label.is_empty() || label.chars().count() > MAX_LABEL_CHARS {
+            return Err(ShadowError::BadRequest(format!(
+                "label must be 1-{} characters",
+                MAX_LABEL_CHARS
+            )));
+        }
+        if label.chars().any(char::is_control) {
+            return Err(ShadowError::BadRequest("label cannot contain control characters".to_string()));
+        }
+        Ok(label)
+    }
+}
+
+/// Everything a badge shows, also served as JSON for custom widgets
+#[derive(Debug, Serialize, Clone)]
+pub struct BadgeData {
+    pub domain: String,
+    pub status: BadgeStatus,
+    pub label: String,
+    pub stat: BadgeStat,
+    /// Visits for the selected stat; None when no stat was asked for or the domain is unknown
+    pub visits: Option<i64>,
+    pub message: String,
+}
+
+impl BadgeData {
+    pub fn new(domain: &str, status: BadgeStatus, label: &str, stat: BadgeStat, visits: Option<i64>) -> Self {
+        let visits = visits.filter(|_| status != BadgeStatus::Unknown && stat != BadgeStat::None);
+        let message = match (stat, visits) {
+            (BadgeStat::Weekly, Some(visits)) => {
+                format!("{} · {} visits this week", status.text(), format_count(visits))
+            }
+            (BadgeStat::Total, Some(visits)) => format!("{} · {} visits", status.text(), format_count(visits)),
+            _ => status.text().to_string(),
+        };
+
+        Self {
+            domain: domain.to_string(),
+            status,
+            label: label.to_string(),
+            stat,
+            visits,
+            message,
+        }
+    }
+}
+
+/// 950 → "950", 1_234 → "1.2k", 2_500_000 → "2.5M"
+pub fn format_count(count: i64) -> String {
+    match count {
+        c if c >= 1_000_000 => format!("{:.1}M", c as f64 / 1_000_000.0),
+        c if c >= 1_000 => format!("{:.1}k", c as f64 / 1_000.0),
+        c => c.max(0).to_string(),
+    }
+}
+
+/// Rough Verdana 11px width; good enough to size the badge segments
+fn text_width(text: &str) -> u32 {
+    text.chars().count() as u32 * 7
+}
+
+pub fn render_svg(badge: &BadgeData, style: BadgeStyle) -> String {
+    let label = html_escape(&badge.label);
+    let message = html_escape(&badge.message);
+    let label_width = text_width(&badge.label) + 10;
+    let message_width = text_width(&badge.message) + 10;
+    let width = label_width + message_width;
+    let (radius, gloss) = match style {
+        BadgeStyle::Flat => (3, ""),
+        BadgeStyle::Plastic => (
+            4,
+            "<linearGradient id=\"s\" x2=\"0\" y2=\"100%\">\
+             <stop offset=\"0\" stop-color=\"#fff\" stop-opacity=\".7\"/>\
+             <stop offset=\".1\" stop-color=\"#aaa\" stop-opacity=\".1\"/>\
+             <stop offset=\".9\" stop-opacity=\".3\"/>\
+             <stop offset=\"1\" stop-opacity=\".5\"/></linearGradient>",
+        ),
+    };
+    let overlay = if gloss.is_empty() {
+        String::new()
+    } else {
+        format!("<rect width=\"{}\" height=\"20\" fill=\"url(#s)\"/>", width)
+    };
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"20\" role=\"img\" aria-label=\"{label}: {message}\">\
+         <title>{label}: {message}</title>{gloss}\
+         <clipPath id=\"r\"><rect width=\"{width}\" height=\"20\" rx=\"{radius}\" fill=\"#fff\"/></clipPath>\
+         <g clip-path=\"url(#r)\"><rect width=\"{label_width}\" height=\"20\" fill=\"#555\"/>\
+         <rect x=\"{label_width}\" width=\"{message_width}\" height=\"20\" fill=\"{color}\"/>{overlay}</g>\
+         <g fill=\"#fff\" text-anchor=\"middle\" font-family=\"Verdana,Geneva,DejaVu Sans,sans-serif\" font-size=\"11\">\
+         <text x=\"{label_x}\" y=\"14\">{label}</text><text x=\"{message_x}\" y=\"14\">{message}</text></g></svg>",
+        color = badge.status.color(),
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(950), "950");
+        assert_eq!(format_count(1_234), "1.2k");
+        assert_eq!(format_count(2_500_000), "2.5M");
+    }
+
+    #[test]
+    fn test_unknown_domains_show_no_stat() {
+        let badge = BadgeData::new("nope.shadow", BadgeStatus::Unknown, DEFAULT_LABEL, BadgeStat::Weekly, Some(10));
+        assert_eq!(badge.message, "unknown");
+        assert_eq!(badge.visits, None);
+    }
+}
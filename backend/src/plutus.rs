@@ -1,12 +1,37 @@
 // Plutus - God of Wealth and Portfolio
 // Handles portfolio tracking, balance aggregation, and transaction history
 
+use crate::error::ShadowError;
+use chrono::{NaiveDate, Utc};
 use mongodb::{Collection, Database};
 use mongodb::bson::{doc, DateTime};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use solana_sdk::pubkey::Pubkey;
-use std::sync::Arc;
+use futures_util::future::join_all;
+use std::collections::BTreeMap;
+use std::env;
+use std::sync::{Arc, OnceLock};
 use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Furthest a reconstruction will replay from its snapshot
+pub const MAX_REPLAY_DAYS: i64 = 90;
+/// Most transactions a single reconstruction will fetch and replay
+pub const MAX_REPLAY_TRANSACTIONS: usize = 500;
+/// Signatures scanned while looking for the replay window before giving up
+const MAX_SIGNATURE_SCAN: usize = 5_000;
+const SIGNATURE_PAGE_SIZE: usize = 1_000;
+
+const COINGECKO_API_URL: &str = "https://api.coingecko.com/api/v3";
+const COINGECKO_PRO_API_URL: &str = "https://pro-api.coingecko.com/api/v3";
+/// CoinGecko's public API limit
+pub const COINGECKO_CALLS_PER_MINUTE: usize = 50;
+/// Longest a lookup queues for a CoinGecko call before giving up on the price
+const COINGECKO_QUEUE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a cached SOL or token price is served before it's fetched again
+const PRICE_TTL_SECS: i64 = 300;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Portfolio {
@@ -68,14 +93,312 @@ pub enum TransactionStatus {
     Failed,
 }
 
+/// A wallet's holdings at one moment, recorded at most daily when its live portfolio is read
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PortfolioSnapshot {
+    /// `{wallet}:{YYYY-MM-DD}`
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub wallet: String,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub taken_at: chrono::DateTime<Utc>,
+    pub sol_balance: u64,
+    /// Raw token amounts by mint
+    #[serde(default)]
+    pub tokens: BTreeMap<String, u64>,
+}
+
+/// SOL and token balance changes one transaction made to a wallet
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceDelta {
+    pub signature: String,
+    pub block_time: i64,
+    /// Lamports, fees included
+    pub sol: i64,
+    /// Raw token amounts by mint
+    pub tokens: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReconstructionConfidence {
+    /// Replayed from one snapshot and landed exactly on the next
+    Exact,
+    /// Bounded on one side only, or the snapshots and history disagree
+    Estimated,
+}
+
+/// Holdings replayed from a snapshot to a point in time
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReplayedHoldings {
+    pub sol_balance: u64,
+    pub tokens: BTreeMap<String, u64>,
+    pub confidence: ReconstructionConfidence,
+    #[serde(deserialize_with = "crate::utils::deserialize_datetime")]
+    pub snapshot_taken_at: chrono::DateTime<Utc>,
+    pub transactions_replayed: usize,
+}
+
+/// A wallet's holdings at the end of a past day, rebuilt from snapshots and history
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReconstructedPortfolio {
+    pub wallet: String,
+    pub date: String,
+    /// Always true; these are never live balances
+    pub reconstructed: bool,
+    #[serde(flatten)]
+    pub holdings: ReplayedHoldings,
+}
+
+/// Running balances during a replay; signed so a bad history can't wrap around
+struct Holdings {
+    sol: i128,
+    tokens: BTreeMap<String, i128>,
+}
+
+impl Holdings {
+    fn from_snapshot(snapshot: &PortfolioSnapshot) -> Self {
+        Self {
+            sol: snapshot.sol_balance as i128,
+            tokens: snapshot.tokens.iter().map(|(mint, amount)| (mint.clone(), *amount as i128)).collect(),
+        }
+    }
+
+    fn apply(&mut self, delta: &BalanceDelta, sign: i128) {
+        self.sol += sign * delta.sol as i128;
+        for (mint, amount) in &delta.tokens {
+            *self.tokens.entry(mint.clone()).or_default() += sign * *amount as i128;
+        }
+        self.tokens.retain(|_, amount| *amount != 0);
+    }
+
+    fn matches(&self, snapshot: &PortfolioSnapshot) -> bool {
+        let tokens: BTreeMap<&String, i128> = snapshot.tokens.iter()
+            .filter(|(_, amount)| **amount != 0)
+            .map(|(mint, amount)| (mint, *amount as i128))
+            .collect();
+        self.sol == snapshot.sol_balance as i128
+            && self.tokens.len() == tokens.len()
+            && self.tokens.iter().all(|(mint, amount)| tokens.get(mint) == Some(amount))
+    }
+
+    fn is_negative(&self) -> bool {
+        self.sol < 0 || self.tokens.values().any(|amount| *amount < 0)
+    }
+}
+
+/// Replay `deltas` (oldest first, covering everything between the snapshots) from the
+/// nearest snapshot to `target`. Forward from `before` when there is one, backward from
+/// `after` otherwise. Exact only when `before` replays cleanly onto `after`.
+pub fn replay(
+    before: Option<&PortfolioSnapshot>,
+    after: Option<&PortfolioSnapshot>,
+    target: chrono::DateTime<Utc>,
+    deltas: &[BalanceDelta],
+) -> Option<ReplayedHoldings> {
+    let target = target.timestamp();
+    let (mut holdings, base, replayed, confidence) = match (before, after) {
+        (Some(before), after) => {
+            let start = before.taken_at.timestamp();
+            let mut holdings = Holdings::from_snapshot(before);
+            let mut replayed = 0;
+            for delta in deltas.iter().filter(|d| d.block_time > start && d.block_time < target) {
+                holdings.apply(delta, 1);
+                replayed += 1;
+            }
+
+            // Carry on to the next snapshot to see whether the history adds up
+            let confidence = match after {
+                Some(after) => {
+                    let end = after.taken_at.timestamp();
+                    let mut check = Holdings { sol: holdings.sol, tokens: holdings.tokens.clone() };
+                    for delta in deltas.iter().filter(|d| d.block_time >= target && d.block_time <= end) {
+                        check.apply(delta, 1);
+                    }
+                    if check.matches(after) { ReconstructionConfidence::Exact } else { ReconstructionConfidence::Estimated }
+                }
+                None => ReconstructionConfidence::Estimated,
+            };
+            (holdings, before, replayed, confidence)
+        }
+        (None, Some(after)) => {
+            let end = after.taken_at.timestamp();
+            let mut holdings = Holdings::from_snapshot(after);
+            let mut replayed = 0;
+            for delta in deltas.iter().rev().filter(|d| d.block_time >= target && d.block_time <= end) {
+                holdings.apply(delta, -1);
+                replayed += 1;
+            }
+            (holdings, after, replayed, ReconstructionConfidence::Estimated)
+        }
+        (None, None) => return None,
+    };
+
+    let confidence = if holdings.is_negative() { ReconstructionConfidence::Estimated } else { confidence };
+    holdings.tokens.retain(|_, amount| *amount > 0);
+    Some(ReplayedHoldings {
+        sol_balance: holdings.sol.clamp(0, u64::MAX as i128) as u64,
+        tokens: holdings.tokens.into_iter()
+            .map(|(mint, amount)| (mint, amount.min(u64::MAX as i128) as u64))
+            .collect(),
+        confidence,
+        snapshot_taken_at: base.taken_at,
+        transactions_replayed: replayed,
+    })
+}
+
+/// Balance changes a jsonParsed `getTransaction` result made to `wallet`
+pub fn balance_delta(tx: &Value, wallet: &str, signature: &str, block_time: i64) -> BalanceDelta {
+    let meta = &tx["meta"];
+    let static_keys = tx["transaction"]["message"]["accountKeys"].as_array().into_iter().flatten()
+        .map(|key| key["pubkey"].as_str().or(key.as_str()));
+    let loaded_keys = ["writable", "readonly"].into_iter()
+        .flat_map(|kind| meta["loadedAddresses"][kind].as_array().into_iter().flatten())
+        .map(|key| key.as_str());
+    let sol = static_keys.chain(loaded_keys)
+        .position(|key| key == Some(wallet))
+        .and_then(|index| Some(meta["postBalances"][index].as_i64()? - meta["preBalances"][index].as_i64()?))
+        .unwrap_or(0);
+
+    let mut tokens: BTreeMap<String, i64> = BTreeMap::new();
+    for (balances, sign) in [(&meta["postTokenBalances"], 1), (&meta["preTokenBalances"], -1)] {
+        for balance in balances.as_array().into_iter().flatten().filter(|b| b["owner"] == wallet) {
+            let (Some(mint), Some(amount)) = (
+                balance["mint"].as_str(),
+                balance["uiTokenAmount"]["amount"].as_str().and_then(|a| a.parse::<i64>().ok()),
+            ) else {
+                continue;
+            };
+            *tokens.entry(mint.to_string()).or_default() += sign * amount;
+        }
+    }
+    tokens.retain(|_, amount| *amount != 0);
+
+    BalanceDelta { signature: signature.to_string(), block_time, sol, tokens }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedReconstruction {
+    #[serde(rename = "_id")]
+    id: String,
+    portfolio: ReconstructedPortfolio,
+}
+
+/// CoinGecko calls made in the last minute, shared by every manager in the process
+fn coingecko_calls() -> Arc<Semaphore> {
+    static CALLS: OnceLock<Arc<Semaphore>> = OnceLock::new();
+    Arc::clone(CALLS.get_or_init(|| Arc::new(Semaphore::new(COINGECKO_CALLS_PER_MINUTE))))
+}
+
+/// USD prices from CoinGecko. Every call takes a permit that is only handed back a minute
+/// later, so no more than `COINGECKO_CALLS_PER_MINUTE` go out in any minute; the rest queue.
+#[derive(Clone)]
+pub struct CoinGecko {
+    http: reqwest::Client,
+    api_url: String,
+    api_key: Option<String>,
+    calls: Arc<Semaphore>,
+    window: Duration,
+}
+
+impl CoinGecko {
+    /// Public API, or the Pro API when `api_key` is set
+    pub fn new(api_key: Option<String>) -> Self {
+        let api_url = if api_key.is_some() { COINGECKO_PRO_API_URL } else { COINGECKO_API_URL };
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            api_url: api_url.to_string(),
+            api_key,
+            calls: coingecko_calls(),
+            window: Duration::from_secs(60),
+        }
+    }
+
+    /// Configured from `COINGECKO_API_KEY` and `COINGECKO_API_URL`
+    pub fn from_env() -> Self {
+        let prices = Self::new(env::var("COINGECKO_API_KEY").ok().filter(|k| !k.is_empty()));
+        match env::var("COINGECKO_API_URL") {
+            Ok(url) if !url.is_empty() => prices.with_api_url(url),
+            _ => prices,
+        }
+    }
+
+    pub fn with_api_url(mut self, url: String) -> Self {
+        self.api_url = url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Allow `calls` per `window` instead of sharing the process-wide CoinGecko limit
+    pub fn with_rate_limit(mut self, calls: usize, window: Duration) -> Self {
+        self.calls = Arc::new(Semaphore::new(calls));
+        self.window = window;
+        self
+    }
+
+    /// SOL in USD
+    pub async fn sol_price(&self) -> Result<f64, String> {
+        let json = self.get("/simple/price?ids=solana&vs_currencies=usd").await?
+            .ok_or_else(|| "CoinGecko has no SOL price".to_string())?;
+        json["solana"]["usd"].as_f64()
+            .ok_or_else(|| "CoinGecko response has no SOL price".to_string())
+    }
+
+    /// A token in USD by its mint; None when CoinGecko doesn't list or price it
+    pub async fn token_price(&self, mint: &str) -> Result<Option<f64>, String> {
+        Pubkey::from_str(mint).map_err(|_| "Invalid mint".to_string())?;
+        let json = self.get(&format!("/coins/solana/contract/{}", mint)).await?;
+        Ok(json.and_then(|json| json["market_data"]["current_price"]["usd"].as_f64()))
+    }
+
+    /// GET `path` once a call is free; None when CoinGecko answers 404
+    async fn get(&self, path: &str) -> Result<Option<Value>, String> {
+        let permit = tokio::time::timeout(COINGECKO_QUEUE_TIMEOUT, Arc::clone(&self.calls).acquire_owned())
+            .await
+            .map_err(|_| "CoinGecko rate limit reached".to_string())?
+            .map_err(|e| format!("CoinGecko rate limiter closed: {}", e))?;
+        let window = self.window;
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            drop(permit);
+        });
+
+        let mut request = self.http.get(format!("{}{}", self.api_url, path));
+        if let Some(api_key) = &self.api_key {
+            request = request.header("x-cg-pro-api-key", api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("CoinGecko unreachable: {}", e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("CoinGecko error: {}", response.status()));
+        }
+        response.json().await
+            .map(Some)
+            .map_err(|e| format!("Invalid CoinGecko response: {}", e))
+    }
+}
+
 pub struct PlutusPortfolioManager {
     db: Arc<Database>,
     solana_rpc_url: String,
+    prices: CoinGecko,
 }
 
 impl PlutusPortfolioManager {
     pub fn new(db: Arc<Database>, solana_rpc_url: String) -> Self {
-        Self { db, solana_rpc_url }
+        Self { db, solana_rpc_url, prices: CoinGecko::from_env() }
+    }
+
+    pub fn with_prices(mut self, prices: CoinGecko) -> Self {
+        self.prices = prices;
+        self
     }
 
     /// Get complete portfolio for a wallet
@@ -115,27 +438,44 @@ impl PlutusPortfolioManager {
             .await
             .map_err(|e| format!("Failed to get NFTs: {}", e))?;
 
-        // Calculate USD values (simplified - in production use price APIs)
-        let sol_price_usd = self.get_sol_price().await.unwrap_or(100.0);
-        let sol_value_usd = (sol_balance as f64 / 1_000_000_000.0) * sol_price_usd;
+        // Unpriced holdings count for nothing rather than failing the whole portfolio
+        let sol_value_usd = match self.get_sol_price().await {
+            Ok(price) => (sol_balance as f64 / 1_000_000_000.0) * price,
+            Err(e) => {
+                tracing::warn!("No SOL price for {}'s portfolio: {}", wallet_pubkey, e);
+                0.0
+            }
+        };
+        let token_values: Vec<Option<f64>> = join_all(tokens.iter().map(|t| async move {
+            match self.get_token_price(&t.mint).await {
+                Ok(price) => price.map(|price| t.ui_amount * price),
+                Err(e) => {
+                    tracing::warn!("No price for token {}: {}", t.mint, e);
+                    None
+                }
+            }
+        }))
+        .await;
+        let total_value_usd = sol_value_usd + token_values.iter().flatten().sum::<f64>();
 
-        // Calculate total value
-        let total_value_usd = sol_value_usd;
-        // Token USD values would be fetched from price API in production
+        let snapshot_tokens = tokens.iter().map(|t| (t.mint.clone(), t.amount)).collect();
+        if let Err(e) = self.record_snapshot(wallet_pubkey, sol_balance, snapshot_tokens).await {
+            tracing::warn!("Failed to record portfolio snapshot for {}: {}", wallet_pubkey, e);
+        }
 
         Ok(Portfolio {
             sol_balance,
             sol_value_usd,
             token_count: tokens.len(),
             nft_count: nfts.len(),
-            total_value_usd: total_value_usd,
-            tokens: tokens.into_iter().map(|t| TokenBalance {
+            total_value_usd,
+            tokens: tokens.into_iter().zip(token_values).map(|(t, value_usd)| TokenBalance {
                 mint: t.mint,
                 amount: t.amount,
                 decimals: t.decimals,
                 ui_amount: t.ui_amount,
                 symbol: t.symbol,
-                value_usd: None, // Would fetch from price API
+                value_usd,
             }).collect(),
             nfts: nfts.into_iter().map(|n| NFT {
                 mint: n.mint,
@@ -193,23 +533,205 @@ impl PlutusPortfolioManager {
         Ok(history)
     }
 
-    /// Get SOL price in USD (cached)
-    async fn get_sol_price(&self) -> Result<f64, String> {
+    fn get_snapshots_collection(&self) -> Collection<PortfolioSnapshot> {
+        self.db.collection::<PortfolioSnapshot>("portfolio_snapshots")
+    }
+
+    /// Keep today's snapshot of a wallet's holdings current
+    pub async fn record_snapshot(
+        &self,
+        wallet_pubkey: &str,
+        sol_balance: u64,
+        tokens: BTreeMap<String, u64>,
+    ) -> Result<(), String> {
+        let now = Utc::now();
+        let tokens: mongodb::bson::Document = tokens.into_iter()
+            .map(|(mint, amount)| (mint, mongodb::bson::Bson::Int64(amount as i64)))
+            .collect();
+        let options = mongodb::options::UpdateOptions::builder().upsert(true).build();
+        self.db.collection::<mongodb::bson::Document>("portfolio_snapshots")
+            .update_one(
+                doc! { "_id": format!("{}:{}", wallet_pubkey, now.format("%Y-%m-%d")) },
+                doc! { "$set": {
+                    "wallet": wallet_pubkey,
+                    "taken_at": DateTime::from_chrono(now),
+                    "sol_balance": sol_balance as i64,
+                    "tokens": tokens,
+                } },
+                options,
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(())
+    }
+
+    /// Holdings at the end of `date` (UTC), replayed from the nearest snapshot through the
+    /// wallet's transaction history. Past days never change, so their results are cached.
+    pub async fn portfolio_at(&self, wallet_pubkey: &str, date: NaiveDate) -> Result<ReconstructedPortfolio, ShadowError> {
+        Pubkey::from_str(wallet_pubkey)
+            .map_err(|_| ShadowError::BadRequest("Invalid pubkey".to_string()))?;
+        let today = Utc::now().date_naive();
+        if date > today {
+            return Err(ShadowError::BadRequest("date must not be in the future".to_string()));
+        }
+
+        let cache_key = format!("{}:{}", wallet_pubkey, date.format("%Y-%m-%d"));
+        let cache = self.db.collection::<CachedReconstruction>("portfolio_reconstructions");
+        if let Some(cached) = cache.find_one(doc! { "_id": &cache_key }, None).await? {
+            return Ok(cached.portfolio);
+        }
+
+        let target = date.succ_opt()
+            .and_then(|next| next.and_hms_opt(0, 0, 0))
+            .ok_or_else(|| ShadowError::BadRequest("date out of range".to_string()))?
+            .and_utc();
+        let window = chrono::Duration::days(MAX_REPLAY_DAYS);
+        let snapshots = self.get_snapshots_collection();
+        let before = snapshots
+            .find_one(
+                doc! { "wallet": wallet_pubkey, "taken_at": { "$lte": DateTime::from_chrono(target), "$gte": DateTime::from_chrono(target - window) } },
+                mongodb::options::FindOneOptions::builder().sort(doc! { "taken_at": -1 }).build(),
+            )
+            .await?;
+        let after = snapshots
+            .find_one(
+                doc! { "wallet": wallet_pubkey, "taken_at": { "$gt": DateTime::from_chrono(target), "$lte": DateTime::from_chrono(target + window) } },
+                mongodb::options::FindOneOptions::builder().sort(doc! { "taken_at": 1 }).build(),
+            )
+            .await?;
+        if before.is_none() && after.is_none() {
+            return Err(ShadowError::NotFound(format!(
+                "No portfolio snapshot within {} days of {}",
+                MAX_REPLAY_DAYS, date
+            )));
+        }
+
+        let from = before.as_ref().map_or(target, |s| s.taken_at).timestamp();
+        let to = after.as_ref().map_or(target, |s| s.taken_at).timestamp();
+        let (deltas, complete) = self.fetch_deltas(wallet_pubkey, from, to).await?;
+        let mut holdings = replay(before.as_ref(), after.as_ref(), target, &deltas)
+            .ok_or_else(|| ShadowError::NotFound("No portfolio snapshot to replay from".to_string()))?;
+        if !complete {
+            holdings.confidence = ReconstructionConfidence::Estimated;
+        }
+
+        let portfolio = ReconstructedPortfolio {
+            wallet: wallet_pubkey.to_string(),
+            date: date.format("%Y-%m-%d").to_string(),
+            reconstructed: true,
+            holdings,
+        };
+        if date < today {
+            let entry = CachedReconstruction { id: cache_key, portfolio: portfolio.clone() };
+            if let Err(e) = cache.insert_one(entry, None).await {
+                tracing::warn!("Failed to cache portfolio reconstruction: {}", e);
+            }
+        }
+        Ok(portfolio)
+    }
+
+    /// Balance deltas of every transaction in `(from, to]`, oldest first. The flag is false
+    /// when some transaction could not be loaded and was left out of the replay.
+    async fn fetch_deltas(&self, wallet_pubkey: &str, from: i64, to: i64) -> Result<(Vec<BalanceDelta>, bool), ShadowError> {
+        let mut signatures: Vec<(String, i64)> = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut scanned = 0;
+        'pages: loop {
+            let mut options = serde_json::json!({ "limit": SIGNATURE_PAGE_SIZE });
+            if let Some(before) = &cursor {
+                options["before"] = Value::String(before.clone());
+            }
+            let page = self.rpc("getSignaturesForAddress", serde_json::json!([wallet_pubkey, options])).await?;
+            let page = page.as_array()
+                .ok_or_else(|| ShadowError::Solana("Invalid getSignaturesForAddress response".to_string()))?;
+
+            // Newest first: skip past `to`, stop once we're back at `from`
+            for entry in page {
+                scanned += 1;
+                let (Some(signature), Some(block_time)) = (entry["signature"].as_str(), entry["blockTime"].as_i64()) else {
+                    continue;
+                };
+                if block_time <= from {
+                    break 'pages;
+                }
+                if block_time <= to {
+                    signatures.push((signature.to_string(), block_time));
+                }
+            }
+            if signatures.len() > MAX_REPLAY_TRANSACTIONS {
+                return Err(ShadowError::BadRequest(format!(
+                    "More than {} transactions to replay; pick a date closer to a snapshot",
+                    MAX_REPLAY_TRANSACTIONS
+                )));
+            }
+            if page.len() < SIGNATURE_PAGE_SIZE {
+                break;
+            }
+            if scanned >= MAX_SIGNATURE_SCAN {
+                return Err(ShadowError::BadRequest("Transaction history is too long to reconstruct this date".to_string()));
+            }
+            cursor = page.last().and_then(|entry| entry["signature"].as_str()).map(str::to_string);
+        }
+
+        let mut deltas = Vec::with_capacity(signatures.len());
+        let mut complete = true;
+        for (signature, block_time) in signatures.into_iter().rev() {
+            let tx = self.rpc(
+                "getTransaction",
+                serde_json::json!([signature, { "encoding": "jsonParsed", "maxSupportedTransactionVersion": 0 }]),
+            ).await?;
+            if tx.is_null() {
+                tracing::warn!("Transaction {} is unavailable; reconstruction will be estimated", signature);
+                complete = false;
+                continue;
+            }
+            deltas.push(balance_delta(&tx, wallet_pubkey, &signature, block_time));
+        }
+        Ok((deltas, complete))
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value, ShadowError> {
+        let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = reqwest::Client::new()
+            .post(&self.solana_rpc_url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ShadowError::Solana(format!("RPC unreachable: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| ShadowError::Solana(format!("Invalid RPC response: {}", e)))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(ShadowError::Solana(format!("RPC error: {}", error)));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    /// Get SOL price in USD (cached). A stale price is served when CoinGecko can't be reached.
+    pub async fn get_sol_price(&self) -> Result<f64, String> {
         let collection: Collection<PriceCache> = self.db.collection("price_cache");
 
-        // Check cache (5 minute TTL)
-        if let Some(cached) = collection
+        let cached = collection
             .find_one(doc! { "symbol": "SOL" }, None)
             .await
-            .map_err(|e| format!("Database error: {}", e))? {
-            let age = (DateTime::now().timestamp_millis() - cached.updated_at.timestamp_millis()) / 1000;
-            if age < 300 { // 5 minutes
+            .map_err(|e| format!("Database error: {}", e))?;
+        if let Some(cached) = &cached {
+            if is_fresh(cached.updated_at) {
                 return Ok(cached.price);
             }
         }
 
-        // Fetch from API (simplified - in production use CoinGecko or similar)
-        let price = 100.0; // Placeholder
+        let price = match self.prices.sol_price().await {
+            Ok(price) => price,
+            Err(e) => match cached {
+                Some(cached) => {
+                    tracing::warn!("Serving stale SOL price: {}", e);
+                    return Ok(cached.price);
+                }
+                None => return Err(e),
+            },
+        };
 
         // Cache it
         let cache = PriceCache {
@@ -231,6 +753,44 @@ impl PlutusPortfolioManager {
 
         Ok(price)
     }
+
+    /// Get a token's price in USD by mint (cached), None when CoinGecko doesn't price it.
+    /// Unpriced tokens are cached too, so wallets full of unlisted tokens don't use up the rate limit.
+    pub async fn get_token_price(&self, mint: &str) -> Result<Option<f64>, String> {
+        let collection: Collection<TokenPriceCache> = self.db.collection("token_price_cache");
+
+        if let Some(cached) = collection
+            .find_one(doc! { "mint": mint }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))? {
+            if is_fresh(cached.updated_at) {
+                return Ok(cached.price);
+            }
+        }
+
+        let price = self.prices.token_price(mint).await?;
+        let cache = TokenPriceCache {
+            mint: mint.to_string(),
+            price,
+            updated_at: DateTime::now(),
+        };
+        collection
+            .replace_one(
+                doc! { "mint": mint },
+                &cache,
+                mongodb::options::ReplaceOptions::builder()
+                    .upsert(true)
+                    .build(),
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(price)
+    }
+}
+
+fn is_fresh(updated_at: DateTime) -> bool {
+    (DateTime::now().timestamp_millis() - updated_at.timestamp_millis()) / 1000 < PRICE_TTL_SECS
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -240,4 +800,129 @@ struct PriceCache {
     updated_at: DateTime,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct TokenPriceCache {
+    mint: String,
+    price: Option<f64>,
+    updated_at: DateTime,
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const WALLET: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+
+    fn at(day: u32, hour: u32) -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap()
+    }
+
+    fn snapshot(taken_at: chrono::DateTime<Utc>, sol: u64, usdc: u64) -> PortfolioSnapshot {
+        PortfolioSnapshot {
+            id: format!("{}:{}", WALLET, taken_at.format("%Y-%m-%d")),
+            wallet: WALLET.to_string(),
+            taken_at,
+            sol_balance: sol,
+            tokens: BTreeMap::from([(USDC.to_string(), usdc)]),
+        }
+    }
+
+    fn delta(signature: &str, block_time: chrono::DateTime<Utc>, sol: i64, usdc: i64) -> BalanceDelta {
+        BalanceDelta {
+            signature: signature.to_string(),
+            block_time: block_time.timestamp(),
+            sol,
+            tokens: BTreeMap::from([(USDC.to_string(), usdc)]),
+        }
+    }
+
+    /// A swap on the 29th (SOL out plus fee, USDC in) and an incoming transfer on the 30th,
+    /// between snapshots on the 28th and the 31st
+    fn history() -> (PortfolioSnapshot, Vec<BalanceDelta>, PortfolioSnapshot) {
+        let before = snapshot(at(28, 12), 10_000_000_000, 5_000_000);
+        let deltas = vec![
+            delta("swap", at(29, 10), -1_000_005_000, 2_000_000),
+            delta("gift", at(30, 9), 500_000_000, 0),
+        ];
+        let after = snapshot(at(31, 1), 9_499_995_000, 7_000_000);
+        (before, deltas, after)
+    }
+
+    #[test]
+    fn test_replay_between_consistent_snapshots_is_exact() {
+        let (before, deltas, after) = history();
+
+        let holdings = replay(Some(&before), Some(&after), at(30, 0), &deltas).unwrap();
+        assert_eq!(holdings.confidence, ReconstructionConfidence::Exact);
+        assert_eq!(holdings.sol_balance, 8_999_995_000);
+        assert_eq!(holdings.tokens[USDC], 7_000_000);
+        assert_eq!(holdings.transactions_replayed, 1);
+        assert_eq!(holdings.snapshot_taken_at, before.taken_at);
+
+        // The history doesn't add up to the later snapshot
+        let drifted = snapshot(at(31, 1), 9_499_995_000, 6_000_000);
+        let holdings = replay(Some(&before), Some(&drifted), at(30, 0), &deltas).unwrap();
+        assert_eq!(holdings.confidence, ReconstructionConfidence::Estimated);
+        assert_eq!(holdings.sol_balance, 8_999_995_000);
+    }
+
+    #[test]
+    fn test_replay_from_one_side_is_estimated() {
+        let (before, deltas, after) = history();
+
+        let forward = replay(Some(&before), None, at(30, 0), &deltas[..1]).unwrap();
+        assert_eq!(forward.confidence, ReconstructionConfidence::Estimated);
+        assert_eq!(forward.sol_balance, 8_999_995_000);
+
+        // Backward from the later snapshot undoes the transfer
+        let backward = replay(None, Some(&after), at(30, 0), &deltas).unwrap();
+        assert_eq!(backward.confidence, ReconstructionConfidence::Estimated);
+        assert_eq!(backward.sol_balance, 8_999_995_000);
+        assert_eq!(backward.tokens[USDC], 7_000_000);
+        assert_eq!(backward.transactions_replayed, 1);
+        assert_eq!(backward.snapshot_taken_at, after.taken_at);
+
+        // A history that spends more than the snapshot held can't be exact
+        let overdrawn = [delta("drain", at(29, 10), -20_000_000_000, 0)];
+        let holdings = replay(Some(&before), Some(&after), at(30, 0), &overdrawn).unwrap();
+        assert_eq!(holdings.sol_balance, 0);
+        assert_eq!(holdings.confidence, ReconstructionConfidence::Estimated);
+
+        assert!(replay(None, None, at(30, 0), &deltas).is_none());
+    }
+
+    #[test]
+    fn test_balance_delta_from_parsed_transaction() {
+        let other = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+        let tx = serde_json::json!({
+            "meta": {
+                "preBalances": [10_000_000_000_i64, 2_039_280],
+                "postBalances": [8_999_995_000_i64, 2_039_280],
+                "preTokenBalances": [
+                    { "accountIndex": 1, "mint": USDC, "owner": WALLET, "uiTokenAmount": { "amount": "5000000" } },
+                    { "accountIndex": 2, "mint": USDC, "owner": other, "uiTokenAmount": { "amount": "9000000" } }
+                ],
+                "postTokenBalances": [
+                    { "accountIndex": 1, "mint": USDC, "owner": WALLET, "uiTokenAmount": { "amount": "7000000" } },
+                    { "accountIndex": 2, "mint": USDC, "owner": other, "uiTokenAmount": { "amount": "7000000" } }
+                ],
+                "loadedAddresses": { "writable": [], "readonly": [other] }
+            },
+            "transaction": { "message": { "accountKeys": [
+                { "pubkey": WALLET, "signer": true, "writable": true },
+                { "pubkey": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA", "signer": false, "writable": false }
+            ] } }
+        });
+
+        let delta = balance_delta(&tx, WALLET, "swap", 0);
+        assert_eq!(delta.sol, -1_000_005_000);
+        assert_eq!(delta.tokens, BTreeMap::from([(USDC.to_string(), 2_000_000)]));
+
+        // Looked up through the address table; no balance entries of its own
+        let delta = balance_delta(&tx, other, "swap", 0);
+        assert_eq!(delta.sol, 0);
+        assert_eq!(delta.tokens, BTreeMap::from([(USDC.to_string(), -2_000_000)]));
+    }
+}
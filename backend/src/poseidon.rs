@@ -1,15 +1,27 @@
 // Poseidon - God of Transactions
 // Handles transaction signing, approval, and management
 
-use mongodb::{Collection, Database};
-use mongodb::bson::{doc, DateTime};
+use mongodb::{Collection, Database, IndexModel};
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::options::FindOptions;
 use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
 use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
     signature::Keypair,
     transaction::Transaction,
 };
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use base64::{Engine as _, engine::general_purpose};
+use crate::argus::OriginRisk;
+use crate::config::TransactionConfig;
+use crate::dionysus::SIGNATURE_FEE_LAMPORTS;
+use crate::error::ShadowError;
+use crate::iris::ListResponse;
+use crate::utils::{cursor_filter, encode_cursor};
+use serde_json::Value;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PendingTransaction {
@@ -21,10 +33,88 @@ pub struct PendingTransaction {
     pub transaction_data: String, // Base64 encoded transaction
     pub message: Option<String>, // Human-readable message
     pub status: TransactionStatus,
+    #[serde(default)]
+    pub risk: OriginRisk, // Reputation of dapp_origin when the request was made
+    /// Outcome of the last pre-flight check before submission
+    #[serde(default)]
+    pub preflight: Option<PreflightCheck>,
+    /// Signature of the submitted transaction
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Signature of the transaction broadcast by `sign_and_broadcast`
+    #[serde(default)]
+    pub on_chain_signature: Option<String>,
+    /// Why broadcasting failed
+    #[serde(default)]
+    pub error: Option<String>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
 
+/// Simulation and fee-reserve check run on a signed transaction right before it is sent
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PreflightCheck {
+    /// Why simulation failed; None when it succeeded
+    pub simulation_error: Option<String>,
+    #[serde(default)]
+    pub simulation_logs: Vec<String>,
+    pub fee_payer: String,
+    /// Fee payer's balance after the transaction and its fee
+    pub post_balance_lamports: u64,
+    pub reserve_lamports: u64,
+    /// The user chose to submit despite a failed simulation
+    pub simulation_overridden: bool,
+    /// The user accepted ending below the reserve
+    pub low_balance_acknowledged: bool,
+}
+
+impl PreflightCheck {
+    pub fn low_balance(&self) -> bool {
+        self.post_balance_lamports < self.reserve_lamports
+    }
+
+    /// What stops submission, if anything, given the overrides on the request
+    pub fn blocker(&self) -> Option<PreflightBlock> {
+        if self.simulation_error.is_some() && !self.simulation_overridden {
+            Some(PreflightBlock::SimulationFailed)
+        } else if self.low_balance() && !self.low_balance_acknowledged {
+            Some(PreflightBlock::LowBalance)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightBlock {
+    /// Needs allow_simulation_failure
+    SimulationFailed,
+    /// Needs acknowledge_low_balance
+    LowBalance,
+}
+
+impl PreflightBlock {
+    pub fn message(&self) -> &'static str {
+        match self {
+            PreflightBlock::SimulationFailed => "Transaction failed simulation; set allow_simulation_failure to submit anyway",
+            PreflightBlock::LowBalance => "Transaction would leave the fee payer below its reserve; set acknowledge_low_balance to proceed",
+        }
+    }
+}
+
+/// Audit entry for a transaction's pre-flight checks and submission
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TransactionEvent {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub transaction_id: String,
+    pub user_id: String,
+    pub kind: String,
+    pub detail: Document,
+    pub created_at: DateTime,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum TransactionStatus {
@@ -32,13 +122,38 @@ pub enum TransactionStatus {
     Approved,
     Rejected,
     Signed,
+    /// Sent to the cluster
+    Submitted,
     Failed,
+    Expired,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SignTransactionRequest {
     pub transaction_id: String,
     pub password: String, // To decrypt wallet
+    /// Send the signed transaction and wait for it to confirm
+    #[serde(default)]
+    pub broadcast: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitTransactionRequest {
+    pub transaction_id: String,
+    /// Base64 encoded, fully signed copy of the requested transaction
+    pub signed_transaction: String,
+    #[serde(default)]
+    pub allow_simulation_failure: bool,
+    #[serde(default)]
+    pub acknowledge_low_balance: bool,
+}
+
+/// Result of a submission attempt. Blocked attempts can be retried with the matching override.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SubmitOutcome {
+    Submitted { signature: String, preflight: PreflightCheck },
+    Blocked { reason: PreflightBlock, message: String, preflight: PreflightCheck },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -55,6 +170,67 @@ pub struct TransactionResponse {
     pub status: TransactionStatus,
     pub signed_transaction: Option<String>, // Base64 encoded signed transaction
     pub message: Option<String>,
+    pub risk: OriginRisk,
+    pub dapp_origin: String,
+    pub on_chain_signature: Option<String>,
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TransactionResponse {
+    fn from_pending(tx: PendingTransaction, signed_transaction: Option<String>) -> Self {
+        Self {
+            id: tx.id,
+            status: tx.status,
+            signed_transaction,
+            message: tx.message,
+            risk: tx.risk,
+            dapp_origin: tx.dapp_origin,
+            on_chain_signature: tx.on_chain_signature,
+            error: tx.error,
+            created_at: tx.created_at.to_chrono(),
+        }
+    }
+}
+
+/// Longest wait between confirmation checks while broadcasting
+const MAX_BROADCAST_BACKOFF: Duration = Duration::from_secs(5);
+
+/// How long `sign_and_broadcast` waits for confirmation, and how often it checks
+#[derive(Debug, Clone, Copy)]
+pub struct BroadcastOptions {
+    pub timeout: Duration,
+    /// Wait before the first check; doubles after each one up to `MAX_BROADCAST_BACKOFF`
+    pub initial_backoff: Duration,
+}
+
+impl From<&TransactionConfig> for BroadcastOptions {
+    fn from(config: &TransactionConfig) -> Self {
+        Self {
+            timeout: Duration::from_secs(config.broadcast_timeout_seconds),
+            initial_backoff: Duration::from_millis(config.broadcast_backoff_ms),
+        }
+    }
+}
+
+/// Result of sending a transaction and waiting for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct BroadcastOutcome {
+    /// None when the RPC refused the transaction
+    pub signature: Option<String>,
+    /// None once the transaction is confirmed
+    pub error: Option<String>,
+}
+
+/// Filters for listing a user's transaction requests, newest first
+#[derive(Debug, Default, Deserialize)]
+pub struct TransactionListQuery {
+    pub status: Option<TransactionStatus>,
+    pub dapp_origin: Option<String>,
+    pub created_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_before: Option<chrono::DateTime<chrono::Utc>>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
 }
 
 pub struct PoseidonTransactionManager {
@@ -78,6 +254,7 @@ impl PoseidonTransactionManager {
         dapp_origin: &str,
         transaction_data: &str,
         message: Option<&str>,
+        risk: OriginRisk,
     ) -> Result<TransactionResponse, String> {
         // Validate transaction data
         let tx_bytes = general_purpose::STANDARD.decode(transaction_data)
@@ -94,6 +271,11 @@ impl PoseidonTransactionManager {
             transaction_data: transaction_data.to_string(),
             message: message.map(|s| s.to_string()),
             status: TransactionStatus::Pending,
+            risk,
+            preflight: None,
+            signature: None,
+            on_chain_signature: None,
+            error: None,
             created_at: DateTime::now(),
             updated_at: DateTime::now(),
         };
@@ -104,42 +286,89 @@ impl PoseidonTransactionManager {
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
-        Ok(TransactionResponse {
-            id: pending.id,
-            status: pending.status,
-            signed_transaction: None,
-            message: pending.message.clone(),
-        })
+        Ok(TransactionResponse::from_pending(pending, None))
     }
 
-    /// Get pending transactions for a user
-    pub async fn get_pending_transactions(
+    /// Create the indexes behind `list_transactions`
+    pub async fn ensure_indexes(&self) -> Result<(), String> {
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "created_at": -1, "_id": -1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "status": 1, "created_at": -1, "_id": -1 })
+                .build(),
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "dapp_origin": 1, "created_at": -1, "_id": -1 })
+                .build(),
+        ];
+        self.get_collection()
+            .create_indexes(indexes, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(())
+    }
+
+    /// List a user's transactions matching `query`, newest first.
+    /// `total` counts every match, ignoring the cursor.
+    pub async fn list_transactions(
         &self,
         user_id: &str,
-    ) -> Result<Vec<TransactionResponse>, String> {
+        query: &TransactionListQuery,
+        limit: i64,
+    ) -> Result<ListResponse<TransactionResponse>, String> {
         let collection = self.get_collection();
-        let filter = doc! {
-            "user_id": user_id,
-            "status": "pending"
-        };
+        let mut filter = doc! { "user_id": user_id };
+        if let Some(status) = &query.status {
+            filter.insert("status", mongodb::bson::to_bson(status).map_err(|e| e.to_string())?);
+        }
+        if let Some(origin) = &query.dapp_origin {
+            filter.insert("dapp_origin", origin);
+        }
+        let mut created_at = Document::new();
+        if let Some(after) = query.created_after {
+            created_at.insert("$gt", DateTime::from_chrono(after));
+        }
+        if let Some(before) = query.created_before {
+            created_at.insert("$lt", DateTime::from_chrono(before));
+        }
+        if !created_at.is_empty() {
+            filter.insert("created_at", created_at);
+        }
 
-        let mut transactions = Vec::new();
-        let mut cursor = collection
-            .find(filter, None)
+        let total = collection
+            .count_documents(filter.clone(), None)
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
-        while let Some(tx) = cursor.try_next().await
-            .map_err(|e| format!("Database error: {}", e))? {
-            transactions.push(TransactionResponse {
-                id: tx.id,
-                status: tx.status,
-                signed_transaction: None,
-                message: tx.message.clone(),
-            });
+        if let Some(cursor) = &query.cursor {
+            filter.extend(cursor_filter("created_at", cursor)?);
         }
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+        let mut transactions: Vec<PendingTransaction> = collection
+            .find(filter, options)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .try_collect()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
 
-        Ok(transactions)
+        let next_cursor = if transactions.len() as i64 > limit {
+            transactions.truncate(limit as usize);
+            transactions.last().map(|tx| encode_cursor(tx.created_at.timestamp_millis(), &tx.id))
+        } else {
+            None
+        };
+        let items = transactions.into_iter()
+            .map(|tx| TransactionResponse::from_pending(tx, None))
+            .collect();
+
+        Ok(ListResponse::new(items)
+            .with_cursor(next_cursor)
+            .with_total(Some(total)))
     }
 
     /// Sign a transaction
@@ -190,6 +419,7 @@ impl PoseidonTransactionManager {
                 doc! {
                     "$set": {
                         "status": "signed",
+                        "transaction_data": &signed_base64,
                         "updated_at": DateTime::now()
                     }
                 },
@@ -198,14 +428,253 @@ impl PoseidonTransactionManager {
             .await
             .map_err(|e| format!("Database error: {}", e))?;
 
-        Ok(TransactionResponse {
-            id: tx.id,
-            status: TransactionStatus::Signed,
-            signed_transaction: Some(signed_base64),
-            message: tx.message.clone(),
+        Ok(TransactionResponse::from_pending(tx, Some(signed_base64)))
+    }
+
+    /// Send a signed transaction and record whether it confirmed. The request ends up
+    /// `Signed` with its on-chain signature, or `Failed` with the reason.
+    pub async fn sign_and_broadcast(
+        &self,
+        rpc_url: &str,
+        transaction_id: &str,
+        user_id: &str,
+        transaction: &Transaction,
+        options: &BroadcastOptions,
+    ) -> Result<TransactionResponse, String> {
+        let collection = self.get_collection();
+        let mut tx = collection
+            .find_one(doc! { "_id": transaction_id, "user_id": user_id }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?
+            .ok_or_else(|| "Transaction not found".to_string())?;
+        if tx.status != TransactionStatus::Signed {
+            return Err("Transaction must be signed before it is broadcast".to_string());
+        }
+
+        let outcome = Self::broadcast(rpc_url, transaction, options).await;
+        tx.status = if outcome.error.is_none() { TransactionStatus::Signed } else { TransactionStatus::Failed };
+        tx.on_chain_signature = outcome.signature;
+        tx.error = outcome.error;
+        tx.transaction_data = general_purpose::STANDARD.encode(
+            bincode::serialize(transaction).map_err(|_| "Failed to serialize transaction".to_string())?,
+        );
+        tx.updated_at = DateTime::now();
+
+        collection
+            .update_one(
+                doc! { "_id": &tx.id },
+                doc! { "$set": {
+                    "status": mongodb::bson::to_bson(&tx.status).map_err(|e| e.to_string())?,
+                    "on_chain_signature": tx.on_chain_signature.as_deref(),
+                    "error": tx.error.as_deref(),
+                    "transaction_data": &tx.transaction_data,
+                    "updated_at": tx.updated_at,
+                } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        self.record_event(&tx, "broadcast", doc! {
+            "signature": tx.on_chain_signature.as_deref(),
+            "error": tx.error.as_deref(),
+        }).await;
+
+        let signed_transaction = Some(tx.transaction_data.clone());
+        Ok(TransactionResponse::from_pending(tx, signed_transaction))
+    }
+
+    /// Send a signed transaction with the RPC's preflight on, then poll its status with
+    /// exponential back-off until it is confirmed or finalized, fails, or `options.timeout` passes.
+    pub async fn broadcast(rpc_url: &str, transaction: &Transaction, options: &BroadcastOptions) -> BroadcastOutcome {
+        let client = AsyncRpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
+        let config = RpcSendTransactionConfig {
+            skip_preflight: false,
+            preflight_commitment: Some(CommitmentLevel::Confirmed),
+            max_retries: Some(3),
+            ..Default::default()
+        };
+        let signature = match client.send_transaction_with_config(transaction, config).await {
+            Ok(signature) => signature,
+            Err(e) => return BroadcastOutcome { signature: None, error: Some(format!("Failed to send transaction: {}", e)) },
+        };
+
+        let failed = |error: String| BroadcastOutcome { signature: Some(signature.to_string()), error: Some(error) };
+        let deadline = Instant::now() + options.timeout;
+        let mut backoff = options.initial_backoff;
+        loop {
+            match client.get_signature_statuses(&[signature]).await {
+                Ok(response) => if let Some(status) = response.value.into_iter().next().flatten() {
+                    if let Some(err) = status.err {
+                        return failed(format!("Transaction failed: {}", err));
+                    }
+                    // Confirmed or finalized
+                    if status.satisfies_commitment(CommitmentConfig::confirmed()) {
+                        return BroadcastOutcome { signature: Some(signature.to_string()), error: None };
+                    }
+                },
+                // The transaction is already out; a flaky status check shouldn't fail it
+                Err(e) => tracing::warn!("Failed to check status of {}: {}", signature, e),
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return failed(format!("Transaction not confirmed within {}s", options.timeout.as_secs()));
+            }
+            tokio::time::sleep(backoff.min(remaining)).await;
+            backoff = (backoff * 2).min(MAX_BROADCAST_BACKOFF);
+        }
+    }
+
+    fn get_events_collection(&self) -> Collection<TransactionEvent> {
+        self.db.collection::<TransactionEvent>("transaction_events")
+    }
+
+    /// Simulate a signed transaction and work out where it leaves the fee payer's balance
+    pub async fn preflight(
+        rpc_url: &str,
+        transaction: &Transaction,
+        reserve_lamports: u64,
+    ) -> Result<PreflightCheck, ShadowError> {
+        let fee_payer = transaction.message.account_keys.first()
+            .ok_or_else(|| ShadowError::BadRequest("Transaction has no fee payer".to_string()))?
+            .to_string();
+        let encoded = general_purpose::STANDARD.encode(
+            bincode::serialize(transaction).map_err(|_| ShadowError::BadRequest("Failed to serialize transaction".to_string()))?,
+        );
+
+        let simulation = rpc(rpc_url, "simulateTransaction", serde_json::json!([encoded, {
+            "encoding": "base64",
+            "sigVerify": true,
+            "commitment": "confirmed",
+            "accounts": { "encoding": "base64", "addresses": [&fee_payer] },
+        }])).await?;
+        let value = &simulation["value"];
+        let simulation_error = match &value["err"] {
+            Value::Null => None,
+            err => Some(err.to_string()),
+        };
+        let simulation_logs = value["logs"].as_array()
+            .map(|logs| logs.iter().filter_map(|l| l.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+
+        // Failed simulations don't return account state, so fall back to the current balance.
+        // The fee is taken off either way; erring low is the safe side for a reserve check.
+        let balance = match value["accounts"][0]["lamports"].as_u64() {
+            Some(lamports) => lamports,
+            None => rpc(rpc_url, "getBalance", serde_json::json!([&fee_payer, { "commitment": "confirmed" }])).await?
+                ["value"].as_u64()
+                .ok_or_else(|| ShadowError::Solana("getBalance returned no value".to_string()))?,
+        };
+        let fee = transaction.signatures.len() as u64 * SIGNATURE_FEE_LAMPORTS;
+
+        Ok(PreflightCheck {
+            simulation_error,
+            simulation_logs,
+            fee_payer,
+            post_balance_lamports: balance.saturating_sub(fee),
+            reserve_lamports,
+            simulation_overridden: false,
+            low_balance_acknowledged: false,
         })
     }
 
+    /// Final gate before a signed transaction goes to the cluster: it must be the requested
+    /// transaction, simulate cleanly and leave the fee payer above its reserve, unless the
+    /// request explicitly overrides the failed check. Every attempt is recorded.
+    pub async fn submit_transaction(
+        &self,
+        rpc_url: &str,
+        user_id: &str,
+        request: &SubmitTransactionRequest,
+        reserve_lamports: u64,
+    ) -> Result<SubmitOutcome, ShadowError> {
+        let collection = self.get_collection();
+        let tx = collection
+            .find_one(doc! { "_id": &request.transaction_id, "user_id": user_id }, None)
+            .await?
+            .ok_or_else(|| ShadowError::NotFound("Transaction not found".to_string()))?;
+        if !matches!(tx.status, TransactionStatus::Pending | TransactionStatus::Signed) {
+            return Err(ShadowError::Conflict("Transaction already processed".to_string()));
+        }
+
+        let requested = decode_transaction(&tx.transaction_data)?;
+        let signed = decode_transaction(&request.signed_transaction)?;
+        if signed.message != requested.message {
+            return Err(ShadowError::BadRequest("Signed transaction does not match the request".to_string()));
+        }
+        if !signed.is_signed() || signed.verify().is_err() {
+            return Err(ShadowError::BadRequest("Transaction is not fully signed".to_string()));
+        }
+
+        let mut preflight = Self::preflight(rpc_url, &signed, reserve_lamports).await?;
+        preflight.simulation_overridden = preflight.simulation_error.is_some() && request.allow_simulation_failure;
+        preflight.low_balance_acknowledged = preflight.low_balance() && request.acknowledge_low_balance;
+        let blocker = preflight.blocker();
+
+        collection
+            .update_one(
+                doc! { "_id": &tx.id },
+                doc! { "$set": {
+                    "preflight": mongodb::bson::to_bson(&preflight).map_err(|e| ShadowError::BadRequest(e.to_string()))?,
+                    "updated_at": DateTime::now(),
+                } },
+                None,
+            )
+            .await?;
+        self.record_event(&tx, "preflight", doc! {
+            "simulation_error": preflight.simulation_error.as_deref(),
+            "simulation_overridden": preflight.simulation_overridden,
+            "post_balance_lamports": preflight.post_balance_lamports as i64,
+            "reserve_lamports": preflight.reserve_lamports as i64,
+            "low_balance_acknowledged": preflight.low_balance_acknowledged,
+            "blocked": blocker.map(|b| mongodb::bson::to_bson(&b).unwrap_or_default()),
+        }).await;
+
+        if let Some(reason) = blocker {
+            return Ok(SubmitOutcome::Blocked { reason, message: reason.message().to_string(), preflight });
+        }
+
+        // The RPC's own preflight would refuse a transaction the user chose to send anyway
+        let signature = rpc(rpc_url, "sendTransaction", serde_json::json!([request.signed_transaction, {
+            "encoding": "base64",
+            "skipPreflight": preflight.simulation_overridden,
+            "preflightCommitment": "confirmed",
+        }])).await?
+            .as_str()
+            .ok_or_else(|| ShadowError::Solana("sendTransaction returned no signature".to_string()))?
+            .to_string();
+
+        collection
+            .update_one(
+                doc! { "_id": &tx.id },
+                doc! { "$set": {
+                    "status": "submitted",
+                    "signature": &signature,
+                    "transaction_data": &request.signed_transaction,
+                    "updated_at": DateTime::now(),
+                } },
+                None,
+            )
+            .await?;
+        self.record_event(&tx, "submitted", doc! { "signature": &signature }).await;
+
+        Ok(SubmitOutcome::Submitted { signature, preflight })
+    }
+
+    async fn record_event(&self, tx: &PendingTransaction, kind: &str, detail: Document) {
+        let event = TransactionEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            transaction_id: tx.id.clone(),
+            user_id: tx.user_id.clone(),
+            kind: kind.to_string(),
+            detail,
+            created_at: DateTime::now(),
+        };
+        if let Err(e) = self.get_events_collection().insert_one(event, None).await {
+            tracing::warn!("Failed to audit transaction {}: {}", tx.id, e);
+        }
+    }
+
     /// Reject a transaction
     pub async fn reject_transaction(
         &self,
@@ -231,6 +700,18 @@ impl PoseidonTransactionManager {
         Ok(())
     }
 
+    /// The stored request, including which wallet it is for
+    pub async fn get_pending_transaction(
+        &self,
+        transaction_id: &str,
+        user_id: &str,
+    ) -> Result<Option<PendingTransaction>, String> {
+        self.get_collection()
+            .find_one(doc! { "_id": transaction_id, "user_id": user_id }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
     /// Get transaction by ID
     pub async fn get_transaction(
         &self,
@@ -243,16 +724,9 @@ impl PoseidonTransactionManager {
             .find_one(doc! { "_id": transaction_id, "user_id": user_id }, None)
             .await
             .map_err(|e| format!("Database error: {}", e))? {
-            Ok(Some(TransactionResponse {
-                id: tx.id.clone(),
-                status: tx.status.clone(),
-                signed_transaction: if tx.status == TransactionStatus::Signed {
-                    Some(tx.transaction_data.clone())
-                } else {
-                    None
-                },
-                message: tx.message.clone(),
-            }))
+            let signed_transaction = (tx.status == TransactionStatus::Signed)
+                .then(|| tx.transaction_data.clone());
+            Ok(Some(TransactionResponse::from_pending(tx, signed_transaction)))
         } else {
             Ok(None)
         }
@@ -261,4 +735,27 @@ impl PoseidonTransactionManager {
 
 use futures_util::TryStreamExt;
 
+pub fn decode_transaction(data: &str) -> Result<Transaction, ShadowError> {
+    let bytes = general_purpose::STANDARD.decode(data)
+        .map_err(|_| ShadowError::BadRequest("Invalid base64 transaction data".to_string()))?;
+    bincode::deserialize(&bytes)
+        .map_err(|_| ShadowError::BadRequest("Invalid transaction format".to_string()))
+}
 
+async fn rpc(rpc_url: &str, method: &str, params: Value) -> Result<Value, ShadowError> {
+    let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let response: Value = reqwest::Client::new()
+        .post(rpc_url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| ShadowError::Solana(format!("RPC unreachable: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| ShadowError::Solana(format!("Invalid RPC response: {}", e)))?;
+
+    if let Some(error) = response.get("error") {
+        return Err(ShadowError::Solana(format!("RPC error: {}", error)));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
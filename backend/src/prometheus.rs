@@ -2,8 +2,21 @@
 use mongodb::{Collection, Database};
 use mongodb::bson::doc;
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
-use futures_util::TryStreamExt;
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::clock::{SharedClock, SystemClock};
+use crate::utils::{encode_score_cursor, keyset_filter, next_page_cursor};
+use dashmap::DashMap;
+use futures_util::{Stream, TryStreamExt};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// Load time samples kept per domain per day for the export percentiles
+const MAX_LOAD_SAMPLES: i32 = 500;
+/// Days of `daily_stats` kept on each site's analytics
+pub const MAX_DAILY_STATS: i32 = 90;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SiteAnalytics {
@@ -14,8 +27,12 @@ pub struct SiteAnalytics {
     pub unique_visitors: i64,
     pub average_time_spent: f64,
     pub bounce_rate: f64,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub last_updated: DateTime<Utc>,
     pub daily_stats: Vec<DailyStats>,
+    /// When the engagement summary was last recomputed
+    #[serde(default, with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime_optional")]
+    pub last_summary_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -38,6 +55,142 @@ pub struct PerformanceMetrics {
     pub measured_at: DateTime<Utc>,
 }
 
+/// Per-day counters in `site_visit_days`. Days recorded before unique visitors and time were
+/// tracked only have `visits`.
+#[derive(Debug, Deserialize, Clone)]
+struct VisitDay {
+    date: String,
+    visits: i64,
+    unique_visitors: Option<i64>,
+    /// Visitors seen exactly once that day
+    single_visit_visitors: Option<i64>,
+    time_spent_seconds: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct PerformanceDay {
+    #[serde(rename = "_id")]
+    id: String,
+    #[serde(default)]
+    load_samples: Vec<f64>,
+}
+
+impl PerformanceDay {
+    fn date(&self) -> &str {
+        self.id.rsplit(':').next().unwrap_or_default()
+    }
+}
+
+/// One day of an analytics export. Fields the stored data can't answer are None.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DailyExportRow {
+    pub date: String,
+    pub visits: i64,
+    pub unique_visitors: Option<i64>,
+    pub avg_time_spent: Option<f64>,
+    pub bounce_rate: Option<f64>,
+    pub p50_load_ms: Option<f64>,
+    pub p90_load_ms: Option<f64>,
+}
+
+impl DailyExportRow {
+    pub const CSV_HEADER: [&'static str; 7] = [
+        "date", "visits", "unique_visitors", "avg_time_spent", "bounce_rate", "p50_load_ms", "p90_load_ms",
+    ];
+
+    fn new(date: String, visits: Option<VisitDay>, performance: Option<PerformanceDay>) -> Self {
+        let visits = visits.as_ref();
+        let unique = visits.and_then(|day| day.unique_visitors).filter(|&unique| unique > 0);
+        let mut samples = performance.map(|day| day.load_samples).unwrap_or_default();
+        samples.sort_by(f64::total_cmp);
+        Self {
+            date,
+            visits: visits.map_or(0, |day| day.visits),
+            unique_visitors: visits.and_then(|day| day.unique_visitors),
+            avg_time_spent: visits
+                .filter(|day| day.visits > 0)
+                .and_then(|day| day.time_spent_seconds.map(|time| time / day.visits as f64)),
+            bounce_rate: unique.zip(visits.and_then(|day| day.single_visit_visitors))
+                .map(|(unique, single)| single as f64 / unique as f64),
+            p50_load_ms: percentile(&samples, 0.5),
+            p90_load_ms: percentile(&samples, 0.9),
+        }
+    }
+
+    pub fn csv_record(&self) -> String {
+        let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        csv_line(&[
+            self.date.clone(),
+            self.visits.to_string(),
+            self.unique_visitors.map(|v| v.to_string()).unwrap_or_default(),
+            optional(self.avg_time_spent),
+            optional(self.bounce_rate),
+            optional(self.p50_load_ms),
+            optional(self.p90_load_ms),
+        ])
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// RFC 4180 line: fields holding commas, quotes or line breaks are quoted, quotes doubled
+pub fn csv_line<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields.iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// Newline-delimited JSON, one record per day
+    Json,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Audit entry for an analytics download
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnalyticsExport {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub domain: String,
+    pub wallet: String,
+    pub from: String,
+    pub to: String,
+    pub format: ExportFormat,
+    /// Part of the requested range was past retention
+    pub truncated: bool,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserEngagement {
     pub domain: String,
@@ -50,11 +203,56 @@ pub struct UserEngagement {
 
 pub struct PrometheusAnalytics {
     db: Database,
+    summary_interval: Duration,
+    summary_locks: DashMap<String, Arc<Mutex<()>>>,
+    clock: SharedClock,
+    retention_days: u32,
 }
 
 impl PrometheusAnalytics {
     pub fn new(db: Database) -> Self {
-        Self { db }
+        Self {
+            db,
+            summary_interval: Duration::from_secs(60),
+            summary_locks: DashMap::new(),
+            clock: SystemClock::shared(),
+            retention_days: 365,
+        }
+    }
+
+    /// How long per-day visit and performance records are kept (default 365 days)
+    pub fn with_retention_days(mut self, days: u32) -> Self {
+        self.retention_days = days.max(1);
+        self
+    }
+
+    pub fn retention_days(&self) -> u32 {
+        self.retention_days
+    }
+
+    /// Oldest day still within retention
+    pub fn retention_start(&self) -> NaiveDate {
+        self.clock.now_utc().date_naive() - chrono::Duration::days(self.retention_days as i64 - 1)
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.clock.now_utc().date_naive()
+    }
+
+    /// When a per-day record written now should expire
+    fn day_record_expiry(&self) -> mongodb::bson::DateTime {
+        mongodb::bson::DateTime::from_chrono(self.clock.now_utc() + chrono::Duration::days(self.retention_days as i64 + 1))
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Override how long a computed summary stays fresh (default 60s)
+    pub fn with_summary_interval(mut self, interval: Duration) -> Self {
+        self.summary_interval = interval;
+        self
     }
 
     pub fn get_analytics_collection(&self) -> Collection<SiteAnalytics> {
@@ -86,7 +284,7 @@ impl PrometheusAnalytics {
             },
             "$set": {
                 "program_address": program_address,
-                "last_updated": mongodb::bson::DateTime::from_millis(Utc::now().timestamp_millis()),
+                "last_updated": mongodb::bson::DateTime::from_millis(self.clock.now_utc().timestamp_millis()),
             },
             "$setOnInsert": {
                 "domain": domain,
@@ -113,7 +311,7 @@ impl PrometheusAnalytics {
             "$set": {
                 "domain": domain,
                 "wallet_pubkey": wallet,
-                "last_visit": mongodb::bson::DateTime::from_millis(Utc::now().timestamp_millis()),
+                "last_visit": mongodb::bson::DateTime::from_millis(self.clock.now_utc().timestamp_millis()),
             },
             "$setOnInsert": {
                 "favorite": false,
@@ -124,9 +322,93 @@ impl PrometheusAnalytics {
             .build();
         engagement_col.update_one(filter, update, options).await?;
 
+        // Per-day counters behind the public badge stats and exports. Unique visitors are
+        // counted through short-lived markers keyed by a hash, never the wallet itself.
+        let today = self.clock.now_utc().format("%Y-%m-%d").to_string();
+        let marker = hex::encode(Sha256::digest(format!("{}:{}:{}", domain, today, wallet)));
+        let options = mongodb::options::FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(mongodb::options::ReturnDocument::After)
+            .build();
+        let seen = self.db.collection::<mongodb::bson::Document>("site_visit_day_visitors")
+            .find_one_and_update(
+                doc! { "_id": marker },
+                doc! {
+                    "$inc": { "visits": 1 },
+                    "$setOnInsert": {
+                        "expires_at": mongodb::bson::DateTime::from_chrono(self.clock.now_utc() + chrono::Duration::days(2)),
+                    },
+                },
+                options,
+            )
+            .await?
+            .and_then(|marker| marker.get_i32("visits").ok())
+            .unwrap_or(1);
+        let mut inc = doc! { "visits": 1, "time_spent_seconds": time_spent_seconds };
+        match seen {
+            1 => {
+                inc.insert("unique_visitors", 1);
+                inc.insert("single_visit_visitors", 1);
+            }
+            2 => { inc.insert("single_visit_visitors", -1); }
+            _ => {}
+        }
+
+        let options = mongodb::options::UpdateOptions::builder()
+            .upsert(true)
+            .build();
+        self.db.collection::<mongodb::bson::Document>("site_visit_days")
+            .update_one(
+                doc! { "_id": format!("{}:{}", domain, today) },
+                doc! {
+                    "$inc": inc,
+                    "$setOnInsert": { "domain": domain, "date": &today, "expires_at": self.day_record_expiry() },
+                },
+                options,
+            )
+            .await?;
+
         Ok(())
     }
 
+    /// Visits to `domain` over the last `days` calendar days, today included
+    pub async fn recent_visits(&self, domain: &str, days: i64) -> Result<i64, mongodb::error::Error> {
+        let today = self.clock.now_utc().date_naive();
+        let ids: Vec<String> = (0..days)
+            .map(|offset| format!("{}:{}", domain, (today - chrono::Duration::days(offset)).format("%Y-%m-%d")))
+            .collect();
+        let mut cursor = self.db.collection::<mongodb::bson::Document>("site_visit_days")
+            .find(doc! { "_id": { "$in": ids } }, None)
+            .await?;
+
+        let mut visits = 0;
+        while let Some(day) = cursor.try_next().await? {
+            visits += day.get_i64("visits").or_else(|_| day.get_i32("visits").map(i64::from)).unwrap_or(0);
+        }
+        Ok(visits)
+    }
+
+    /// Detach a wallet's engagement rows from it. Each row keeps its counts under a random
+    /// visitor id, so site totals and bounce rates are unchanged. Returns rows rewritten.
+    pub async fn anonymize_engagement(&self, wallet: &str) -> Result<u64, mongodb::error::Error> {
+        let collection = self.db.collection::<mongodb::bson::Document>("user_engagement");
+        let mut cursor = collection.find(doc! { "wallet_pubkey": wallet }, None).await?;
+
+        let mut anonymized = 0;
+        while let Some(mut row) = cursor.try_next().await? {
+            let Some(old_id) = row.remove("_id") else { continue };
+            let domain = row.get_str("domain").unwrap_or_default().to_string();
+            let visitor = format!("anonymous:{}", uuid::Uuid::new_v4());
+            row.insert("_id", format!("{}:{}", domain, visitor));
+            row.insert("wallet_pubkey", visitor);
+
+            collection.insert_one(row, None).await?;
+            collection.delete_one(doc! { "_id": old_id }, None).await?;
+            anonymized += 1;
+        }
+        Ok(anonymized)
+    }
+
     pub async fn record_performance(
         &self,
         domain: &str,
@@ -136,8 +418,8 @@ impl PrometheusAnalytics {
         request_count: i32,
     ) -> Result<(), mongodb::error::Error> {
         let collection = self.get_performance_collection();
-        let id = format!("{}:{}", domain, Utc::now().format("%Y-%m-%d"));
-        let now = Utc::now();
+        let now = self.clock.now_utc();
+        let id = format!("{}:{}", domain, now.format("%Y-%m-%d"));
         
         let metrics = PerformanceMetrics {
             id,
@@ -151,7 +433,9 @@ impl PrometheusAnalytics {
         
         let filter = doc! { "_id": &metrics.id };
         let update = doc! {
-            "$set": mongodb::bson::to_bson(&metrics).unwrap()
+            "$set": mongodb::bson::to_bson(&metrics).unwrap(),
+            "$push": { "load_samples": { "$each": [load_time_ms], "$slice": -MAX_LOAD_SAMPLES } },
+            "$setOnInsert": { "expires_at": self.day_record_expiry() },
         };
         let options = mongodb::options::UpdateOptions::builder()
             .upsert(true)
@@ -161,6 +445,170 @@ impl PrometheusAnalytics {
         Ok(())
     }
 
+    /// Per-day rows for `domain` from `from` to `to` inclusive, oldest first. Days with no
+    /// visits or performance samples are left out. Rows are read lazily from both collections.
+    pub async fn export_daily(
+        &self,
+        domain: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<impl Stream<Item = Result<DailyExportRow, mongodb::error::Error>> + 'static, mongodb::error::Error> {
+        // Per-day ids are `domain:YYYY-MM-DD`, so the date range is an _id range
+        let range = doc! { "_id": {
+            "$gte": format!("{}:{}", domain, from.format("%Y-%m-%d")),
+            "$lte": format!("{}:{}", domain, to.format("%Y-%m-%d")),
+        } };
+        let sorted = || mongodb::options::FindOptions::builder().sort(doc! { "_id": 1 }).build();
+        let visits = self.db.collection::<VisitDay>("site_visit_days").find(range.clone(), sorted()).await?;
+        let performance = self.db.collection::<PerformanceDay>("performance_metrics").find(range, sorted()).await?;
+
+        // Merge the two date-ordered cursors
+        let state = (visits, performance, None::<VisitDay>, None::<PerformanceDay>);
+        Ok(futures_util::stream::try_unfold(state, |(mut visits, mut performance, mut visit, mut perf)| async move {
+            if visit.is_none() {
+                visit = visits.try_next().await?;
+            }
+            if perf.is_none() {
+                perf = performance.try_next().await?;
+            }
+            let date = match (&visit, &perf) {
+                (None, None) => return Ok(None),
+                (Some(v), Some(p)) => v.date.as_str().min(p.date()).to_string(),
+                (Some(v), None) => v.date.clone(),
+                (None, Some(p)) => p.date().to_string(),
+            };
+            let day_visits = if visit.as_ref().is_some_and(|v| v.date == date) { visit.take() } else { None };
+            let day_perf = if perf.as_ref().is_some_and(|p| p.date() == date) { perf.take() } else { None };
+            let row = DailyExportRow::new(date, day_visits, day_perf);
+            Ok(Some((row, (visits, performance, visit, perf))))
+        }))
+    }
+
+    /// Recompute `domain`'s stats for `date` (YYYY-MM-DD) from the engagement rows whose last
+    /// visit fell on that day, and store them in its `daily_stats`, replacing any earlier entry
+    /// for the day. Only the latest `MAX_DAILY_STATS` days are kept. Engagement rows hold each
+    /// visitor's running totals as of their latest visit, so a day counts the visitors last
+    /// seen on it; aggregating soon after the day ends is what makes the numbers meaningful.
+    pub async fn aggregate_daily_stats(&self, domain: &str, date: &str) -> Result<DailyStats, mongodb::error::Error> {
+        let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+            let invalid = std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{} is not a YYYY-MM-DD date", date));
+            mongodb::error::Error::from(mongodb::error::ErrorKind::from(invalid))
+        })?;
+        let start = day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        let filter = doc! {
+            "domain": domain,
+            "last_visit": {
+                "$gte": mongodb::bson::DateTime::from_chrono(start),
+                "$lt": mongodb::bson::DateTime::from_chrono(start + chrono::Duration::days(1)),
+            },
+        };
+
+        // Only the counters are needed, and $inc may have stored them as either integer width
+        let count = |row: &mongodb::bson::Document, field: &str| {
+            row.get_i64(field).or_else(|_| row.get_i32(field).map(i64::from)).unwrap_or(0)
+        };
+        let mut cursor = self.db.collection::<mongodb::bson::Document>("user_engagement").find(filter, None).await?;
+        let (mut visits, mut unique_visitors, mut total_time) = (0i64, 0i64, 0i64);
+        while let Some(engagement) = cursor.try_next().await? {
+            visits += count(&engagement, "visit_count");
+            unique_visitors += 1;
+            total_time += count(&engagement, "total_time_spent");
+        }
+        let stats = DailyStats {
+            date: day.format("%Y-%m-%d").to_string(),
+            visits,
+            unique_visitors,
+            avg_time_spent: if unique_visitors > 0 { total_time as f64 / unique_visitors as f64 } else { 0.0 },
+        };
+
+        // $pull and $push can't touch the same field in one update
+        let analytics_col = self.get_analytics_collection();
+        analytics_col
+            .update_one(doc! { "_id": domain }, doc! { "$pull": { "daily_stats": { "date": &stats.date } } }, None)
+            .await?;
+        analytics_col
+            .update_one(
+                doc! { "_id": domain },
+                doc! { "$push": { "daily_stats": {
+                    "$each": [mongodb::bson::to_bson(&stats)?],
+                    "$sort": { "date": 1 },
+                    "$slice": -MAX_DAILY_STATS,
+                } } },
+                None,
+            )
+            .await?;
+        Ok(stats)
+    }
+
+    /// Domains with an engagement row updated since `since`
+    pub async fn domains_visited_since(&self, since: DateTime<Utc>) -> Result<Vec<String>, mongodb::error::Error> {
+        let domains = self.get_engagement_collection()
+            .distinct("domain", doc! { "last_visit": { "$gte": mongodb::bson::DateTime::from_chrono(since) } }, None)
+            .await?;
+        Ok(domains.into_iter().filter_map(|domain| domain.as_str().map(str::to_string)).collect())
+    }
+
+    /// Aggregate daily stats for every domain visited in the past 24 hours, for each day that
+    /// window touches. Returns how many domains were aggregated.
+    pub async fn aggregate_recent_daily_stats(&self) -> Result<usize, mongodb::error::Error> {
+        let now = self.clock.now_utc();
+        let since = now - chrono::Duration::hours(24);
+        let mut days = vec![since.date_naive()];
+        if now.date_naive() != since.date_naive() {
+            days.push(now.date_naive());
+        }
+
+        let domains = self.domains_visited_since(since).await?;
+        for domain in &domains {
+            for day in &days {
+                self.aggregate_daily_stats(domain, &day.format("%Y-%m-%d").to_string()).await?;
+            }
+        }
+        Ok(domains.len())
+    }
+
+    /// Run `aggregate_recent_daily_stats` every `interval` until `shutdown` fires
+    pub fn spawn_daily_stats(self: Arc<Self>, interval: Duration, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.aggregate_recent_daily_stats().await {
+                    Ok(0) => {}
+                    Ok(domains) => tracing::debug!("Prometheus aggregated daily stats for {} domains", domains),
+                    Err(e) => tracing::warn!("Prometheus daily stats aggregation failed: {}", e),
+                }
+            }
+        })
+    }
+
+    /// `daily_stats` entries for `domain` from `from` to `to` inclusive, oldest first.
+    /// None when the domain has no analytics.
+    pub async fn get_daily_stats(
+        &self,
+        domain: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Option<Vec<DailyStats>>, mongodb::error::Error> {
+        let (from, to) = (from.format("%Y-%m-%d").to_string(), to.format("%Y-%m-%d").to_string());
+        Ok(self.get_analytics(domain).await?.map(|analytics| {
+            let mut days: Vec<_> = analytics.daily_stats
+                .into_iter()
+                .filter(|day| day.date >= from && day.date <= to)
+                .collect();
+            days.sort_by(|a, b| a.date.cmp(&b.date));
+            days
+        }))
+    }
+
+    pub async fn record_export(&self, export: &AnalyticsExport) -> Result<(), mongodb::error::Error> {
+        self.db.collection::<AnalyticsExport>("analytics_exports").insert_one(export, None).await?;
+        Ok(())
+    }
+
     pub async fn get_analytics(
         &self,
         domain: &str,
@@ -171,24 +619,27 @@ impl PrometheusAnalytics {
         Ok(analytics)
     }
 
+    /// Sites by total visits, resuming after `after` (visits, domain).
+    /// Returns the page and the cursor for the next one.
     pub async fn get_top_sites(
         &self,
         limit: i64,
-    ) -> Result<Vec<SiteAnalytics>, mongodb::error::Error> {
+        after: Option<(f64, String)>,
+    ) -> Result<(Vec<SiteAnalytics>, Option<String>), mongodb::error::Error> {
         let collection = self.get_analytics_collection();
+        let filter = after
+            .map(|(visits, domain)| keyset_filter("total_visits", visits, &domain))
+            .unwrap_or_default();
         let options = mongodb::options::FindOptions::builder()
-            .limit(limit)
-            .sort(doc! { "total_visits": -1 })
+            .limit(limit + 1)
+            .sort(doc! { "total_visits": -1, "_id": -1 })
             .build();
         
-        let mut cursor = collection.find(doc! {}, options).await?;
-        let mut sites = Vec::new();
-        
-        while let Some(site) = cursor.try_next().await? {
-            sites.push(site);
-        }
-        
-        Ok(sites)
+        let mut sites: Vec<SiteAnalytics> = collection.find(filter, options).await?.try_collect().await?;
+        let next_cursor = next_page_cursor(&mut sites, limit, |site| {
+            encode_score_cursor(site.total_visits as f64, &site.domain)
+        });
+        Ok((sites, next_cursor))
     }
 
     pub async fn calculate_bounce_rate(
@@ -214,6 +665,50 @@ impl PrometheusAnalytics {
         Ok(single_visit / total_visits)
     }
 
+    /// Recompute the summary for a domain unless a fresh one already exists.
+    /// Concurrent callers for the same domain wait on a single computation
+    /// instead of each scanning the engagement collection.
+    /// Returns true when this call actually ran the computation.
+    pub async fn refresh_analytics_summary(
+        &self,
+        domain: &str,
+        force: bool,
+    ) -> Result<bool, mongodb::error::Error> {
+        let requested_at = self.clock.now_utc();
+        let lock = self.summary_locks
+            .entry(domain.to_string())
+            .or_default()
+            .clone();
+
+        let result = {
+            let _guard = lock.lock().await;
+
+            let last_summary_at = self.get_analytics(domain).await?
+                .and_then(|analytics| analytics.last_summary_at);
+
+            let fresh = match last_summary_at {
+                // A summary finished while we were waiting satisfies even forced refreshes
+                Some(at) if at >= requested_at => true,
+                Some(at) if !force => {
+                    let age = (self.clock.now_utc() - at).to_std().unwrap_or_default();
+                    age < self.summary_interval
+                }
+                _ => false,
+            };
+
+            if fresh {
+                Ok(false)
+            } else {
+                self.update_analytics_summary(domain).await.map(|_| true)
+            }
+        };
+
+        drop(lock);
+        self.summary_locks.remove_if(domain, |_, lock| Arc::strong_count(lock) == 1);
+
+        result
+    }
+
     pub async fn update_analytics_summary(
         &self,
         domain: &str,
@@ -248,6 +743,7 @@ impl PrometheusAnalytics {
                 "average_time_spent": avg_time,
                 "bounce_rate": bounce_rate,
                 "total_visits": visit_count,
+                "last_summary_at": mongodb::bson::DateTime::from_millis(self.clock.now_utc().timestamp_millis()),
             }
         };
         
@@ -256,3 +752,23 @@ impl PrometheusAnalytics {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_line_escaping() {
+        let line = csv_line(&["plain", "Shop, \"Best\" Deals", "two\nlines", ""]);
+        assert_eq!(line, "plain,\"Shop, \"\"Best\"\" Deals\",\"two\nlines\",\r\n");
+    }
+
+    #[test]
+    fn test_percentiles_use_nearest_rank() {
+        let samples: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(percentile(&samples, 0.5), Some(5.0));
+        assert_eq!(percentile(&samples, 0.9), Some(9.0));
+        assert_eq!(percentile(&[42.0], 0.9), Some(42.0));
+        assert_eq!(percentile(&[], 0.5), None);
+    }
+}
@@ -1,7 +1,31 @@
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
 use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_client::rpc_response::{Response, RpcKeyedAccount};
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::program_pack::Pack;
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::signers::Signers;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_token::state::{Account as SplTokenAccount, AccountState, Mint};
+use std::collections::HashMap;
 use std::str::FromStr;
 
+/// Token-2022 program; mints owned by it get their ATA derived under it
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+/// Most accounts getMultipleAccounts returns per call
+const MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
+/// How often `send_transaction` checks whether a sent transaction has confirmed
+const CONFIRMATION_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
 pub struct SolanaClient {
     rpc_url: String,
 }
@@ -29,6 +53,24 @@ impl SolanaClient {
         }
     }
 
+    /// Like `search_account`, but RPC failures are reported instead of looking like a missing account
+    pub fn fetch_account(&self, address: &str) -> Result<Option<AccountInfo>, String> {
+        let pubkey = Pubkey::from_str(address)
+            .map_err(|e| format!("Invalid pubkey: {}", e))?;
+
+        let client = RpcClient::new(&self.rpc_url);
+        let response = client.get_account_with_commitment(&pubkey, client.commitment())
+            .map_err(|e| format!("RPC error: {}", e))?;
+
+        Ok(response.value.map(|account| AccountInfo {
+            address: address.to_string(),
+            lamports: account.lamports,
+            owner: account.owner.to_string(),
+            executable: account.executable,
+            data_len: account.data.len(),
+        }))
+    }
+
     pub fn search_program(&self, address: &str) -> Result<Option<ProgramInfo>, String> {
         let pubkey = Pubkey::from_str(address)
             .map_err(|e| format!("Invalid pubkey: {}", e))?;
@@ -46,46 +88,23 @@ impl SolanaClient {
         }
     }
 
-    /// Verify program ownership by checking upgrade authority
-    /// Returns true if the owner_pubkey matches the program's upgrade authority
+    /// Whether `owner_pubkey` is the program's upgrade authority. Programs nobody can upgrade
+    /// (immutable, closed, or deployed with an older loader) have no owner to match.
     pub fn verify_program_ownership(
         &self,
         program_address: &str,
         owner_pubkey: &str,
     ) -> Result<bool, String> {
-        let program = Pubkey::from_str(program_address)
-            .map_err(|e| format!("Invalid program pubkey: {}", e))?;
-        
         let owner = Pubkey::from_str(owner_pubkey)
             .map_err(|e| format!("Invalid owner pubkey: {}", e))?;
 
-        let client = RpcClient::new(&self.rpc_url);
-        
-        // Get program account data
-        let account = client.get_account(&program)
-            .map_err(|e| format!("Failed to get program account: {}", e))?;
-
-        // Check if account is executable (is a program)
-        if !account.executable {
-            return Ok(false);
-        }
-
-        // Get program data account (BPF upgradeable loader)
-        // For upgradeable programs, the upgrade authority is stored in the program data account
-        // This is a simplified check - in production, you'd parse the program data account
-        // For now, we'll check if the owner matches the program's owner field
-        // (which for BPF programs is the BPF loader)
-        
-        // For a more accurate check, we'd need to:
-        // 1. Get the program data account (derived from program address)
-        // 2. Parse the upgrade authority from the program data
-        // 3. Compare with owner_pubkey
-        
-        // Simplified: check if owner matches (this works for non-upgradeable programs)
-        Ok(account.owner == owner)
+        let authority = self.get_program_upgrade_authority(program_address)?;
+        Ok(authority == Some(owner.to_string()))
     }
 
-    /// Get program upgrade authority (for upgradeable programs)
+    /// Upgrade authority of a program deployed with the upgradeable BPF loader, read from its
+    /// ProgramData account. None when the program is not executable, was deployed with an
+    /// older loader, has been made immutable, or has been closed.
     pub fn get_program_upgrade_authority(
         &self,
         program_address: &str,
@@ -95,22 +114,23 @@ impl SolanaClient {
 
         let client = RpcClient::new(&self.rpc_url);
         
-        // Get program account
         let account = client.get_account(&program)
             .map_err(|e| format!("Failed to get program account: {}", e))?;
 
-        if !account.executable {
+        if !account.executable || account.owner != bpf_loader_upgradeable::id() {
             return Ok(None);
         }
+        let programdata_address = program_data_address(&program, &account.data)?;
 
-        // For upgradeable programs, derive program data account
-        // Program data = findProgramAddress([program, "upgrade"])
-        // This is a simplified version - full implementation would derive the PDA
-        // and parse the upgrade authority from the account data
-        
-        // Placeholder: return None for now
-        // In production, implement proper PDA derivation and data parsing
-        Ok(None)
+        let response = client.get_account_with_commitment(&programdata_address, client.commitment())
+            .map_err(|e| format!("Failed to get program data account: {}", e))?;
+        let Some(programdata) = response.value else {
+            return Ok(None);
+        };
+        if programdata.owner != bpf_loader_upgradeable::id() {
+            return Err(format!("Program data account {} is not owned by the upgradeable loader", programdata_address));
+        }
+        Ok(upgrade_authority(&programdata.data)?.map(|authority| authority.to_string()))
     }
 
     /// Get SOL balance for a pubkey
@@ -118,25 +138,158 @@ impl SolanaClient {
         let pubkey = Pubkey::from_str(pubkey)
             .map_err(|e| format!("Invalid pubkey: {}", e))?;
         
-        let client = RpcClient::new(&self.rpc_url);
-        client.get_balance(&pubkey)
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        client.get_balance(&pubkey).await
             .map_err(|e| format!("RPC error: {}", e))
     }
 
     /// Get recent blockhash
     pub async fn get_recent_blockhash(&self) -> Result<solana_sdk::hash::Hash, String> {
-        let client = RpcClient::new(&self.rpc_url);
-        let hash = client.get_latest_blockhash()
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let hash = client.get_latest_blockhash().await
             .map_err(|e| format!("RPC error: {}", e))?;
         Ok(hash)
     }
 
-    /// Get token accounts for a pubkey
-    pub async fn get_token_accounts(&self, _pubkey: &Pubkey) -> Result<Vec<TokenAccountInfo>, String> {
-        // TODO: Implement proper token account fetching
-        // The solana-client API for get_token_accounts_by_owner has complex type requirements
-        // For now, return empty vector - will be implemented with proper parsing later
-        Ok(Vec::new())
+    /// Raw account, or None when it does not exist
+    pub async fn get_account(&self, pubkey: &Pubkey) -> Result<Option<solana_sdk::account::Account>, String> {
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let response = client.get_account_with_commitment(pubkey, client.commitment()).await
+            .map_err(|e| format!("RPC error: {}", e))?;
+        Ok(response.value)
+    }
+
+    /// Lamports an account holding `data_len` bytes needs to be rent exempt
+    pub async fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> Result<u64, String> {
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        client.get_minimum_balance_for_rent_exemption(data_len).await
+            .map_err(|e| format!("RPC error: {}", e))
+    }
+
+    /// Submit a signed transaction and wait until it is confirmed; returns the signature
+    pub async fn send_transaction(&self, transaction: &Transaction) -> Result<String, SolanaError> {
+        // Anything over one packet is rejected by the node; no need for the round trip
+        let size = bincode::serialized_size(transaction)
+            .map_err(|e| SolanaError::Unknown(format!("Failed to serialize transaction: {}", e)))?;
+        if size > PACKET_DATA_SIZE as u64 {
+            return Err(SolanaError::Unknown(format!(
+                "Transaction is {} bytes, over the {} byte limit",
+                size, PACKET_DATA_SIZE
+            )));
+        }
+
+        let client = AsyncRpcClient::new_with_commitment(self.rpc_url.clone(), CommitmentConfig::confirmed());
+        let config = RpcSendTransactionConfig {
+            preflight_commitment: Some(CommitmentLevel::Confirmed),
+            ..Default::default()
+        };
+        let signature = client.send_transaction_with_config(transaction, config).await?;
+
+        // Polled here rather than through the client's spinner helpers, which draw a progress bar
+        loop {
+            if let Some(status) = client.get_signature_status_with_commitment(&signature, CommitmentConfig::confirmed()).await? {
+                return status.map(|()| signature.to_string()).map_err(SolanaError::from);
+            }
+            // Once its blockhash expires the transaction can no longer land
+            if !client.is_blockhash_valid(&transaction.message.recent_blockhash, CommitmentConfig::processed()).await? {
+                return Err(SolanaError::Timeout);
+            }
+            tokio::time::sleep(CONFIRMATION_POLL_INTERVAL).await;
+        }
+    }
+
+    /// `send_transaction`, trying up to `max_retries` more times when the transaction times out
+    /// or its blockhash is unknown. Once the blockhash has expired the earlier attempt can no
+    /// longer land, so the transaction is re-signed by `signers` on a fresh one; until then the
+    /// same transaction is resent, which the cluster deduplicates by signature.
+    pub async fn send_transaction_with_retries<T: Signers + ?Sized>(
+        &self,
+        transaction: &Transaction,
+        signers: &T,
+        max_retries: u8,
+    ) -> Result<String, SolanaError> {
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let mut transaction = transaction.clone();
+        let mut retries = 0;
+        loop {
+            let expired = match self.send_transaction(&transaction).await {
+                Err(SolanaError::BlockhashNotFound) if retries < max_retries => true,
+                Err(SolanaError::Timeout) if retries < max_retries => {
+                    // If the check itself fails, resending the same transaction is the safe choice
+                    !client
+                        .is_blockhash_valid(&transaction.message.recent_blockhash, CommitmentConfig::processed())
+                        .await
+                        .unwrap_or(true)
+                }
+                result => return result,
+            };
+            retries += 1;
+
+            if expired {
+                let blockhash = self.get_recent_blockhash().await.map_err(SolanaError::Unknown)?;
+                transaction
+                    .try_sign(signers, blockhash)
+                    .map_err(|e| SolanaError::Unknown(format!("Failed to re-sign transaction: {}", e)))?;
+            }
+            tracing::debug!("Retrying transaction {} ({}/{})", transaction.signatures[0], retries, max_retries);
+        }
+    }
+
+    /// Token accounts owned by a wallet under SPL Token and Token-2022, with their mints' decimals
+    pub async fn get_token_accounts(&self, pubkey: &Pubkey) -> Result<Vec<TokenAccountInfo>, String> {
+        let client = AsyncRpcClient::new(self.rpc_url.clone());
+        let token_2022 = Pubkey::from_str(TOKEN_2022_PROGRAM_ID).map_err(|e| e.to_string())?;
+
+        let mut accounts = Vec::new();
+        for program in [spl_token::id(), token_2022] {
+            // Asked for as base64 so the accounts can be unpacked rather than read from jsonParsed
+            let response: Response<Vec<RpcKeyedAccount>> = client
+                .send(
+                    RpcRequest::GetTokenAccountsByOwner,
+                    serde_json::json!([
+                        pubkey.to_string(),
+                        { "programId": program.to_string() },
+                        { "encoding": UiAccountEncoding::Base64, "commitment": client.commitment().commitment },
+                    ]),
+                )
+                .await
+                .map_err(|e| format!("RPC error: {}", e))?;
+
+            for keyed in response.value {
+                let data = keyed.account.data.decode()
+                    .ok_or_else(|| format!("Undecodable token account {}", keyed.pubkey))?;
+                // Token-2022 accounts start with the SPL Token layout and append extensions
+                let state = data.get(..SplTokenAccount::LEN)
+                    .and_then(|base| SplTokenAccount::unpack(base).ok())
+                    .ok_or_else(|| format!("Invalid token account {}", keyed.pubkey))?;
+                accounts.push((keyed.pubkey, keyed.account.lamports, program, state));
+            }
+        }
+
+        let mut mints: Vec<Pubkey> = accounts.iter().map(|(_, _, _, state)| state.mint).collect();
+        mints.sort();
+        mints.dedup();
+        let mut decimals = HashMap::new();
+        for chunk in mints.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+            let mint_accounts = client.get_multiple_accounts(chunk).await
+                .map_err(|e| format!("RPC error: {}", e))?;
+            for (mint, account) in chunk.iter().zip(mint_accounts) {
+                let mint_state = account
+                    .and_then(|account| account.data.get(..Mint::LEN).and_then(|base| Mint::unpack(base).ok()))
+                    .ok_or_else(|| format!("Invalid mint {}", mint))?;
+                decimals.insert(*mint, mint_state.decimals);
+            }
+        }
+
+        Ok(accounts.into_iter().map(|(address, lamports, program, state)| TokenAccountInfo {
+            address,
+            mint: state.mint.to_string(),
+            amount: state.amount,
+            decimals: decimals[&state.mint],
+            lamports,
+            is_frozen: state.state == AccountState::Frozen,
+            token_program: program.to_string(),
+        }).collect())
     }
 
     /// Get signatures for an address
@@ -161,11 +314,117 @@ impl SolanaClient {
     }
 }
 
+/// Address of the ProgramData account an upgradeable program's account points at. It must be the
+/// one derived from `program`, or the account was not written by the loader.
+pub fn program_data_address(program: &Pubkey, program_account_data: &[u8]) -> Result<Pubkey, String> {
+    let state: UpgradeableLoaderState = bincode::deserialize(program_account_data)
+        .map_err(|e| format!("Malformed program account {}: {}", program, e))?;
+    let UpgradeableLoaderState::Program { programdata_address } = state else {
+        return Err(format!("Account {} is not an upgradeable program", program));
+    };
+
+    let derived = Pubkey::find_program_address(&[program.as_ref()], &bpf_loader_upgradeable::id()).0;
+    if programdata_address != derived {
+        return Err(format!("Program {} points at program data {}, expected {}", program, programdata_address, derived));
+    }
+    Ok(programdata_address)
+}
+
+/// Upgrade authority recorded in a ProgramData account; None once the program is immutable.
+/// Only the metadata is read, not the program bytes that follow it.
+pub fn upgrade_authority(programdata_account_data: &[u8]) -> Result<Option<Pubkey>, String> {
+    let metadata = programdata_account_data
+        .get(..UpgradeableLoaderState::size_of_programdata_metadata())
+        .ok_or_else(|| "Program data account is too short".to_string())?;
+    match bincode::deserialize(metadata).map_err(|e| format!("Malformed program data account: {}", e))? {
+        UpgradeableLoaderState::ProgramData { upgrade_authority_address, .. } => Ok(upgrade_authority_address),
+        _ => Err("Account is not a program data account".to_string()),
+    }
+}
+
+/// Why a transaction did not land
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolanaError {
+    /// The fee payer can't cover fees or rent, or an instruction ran out of lamports
+    InsufficientFunds,
+    /// The fee payer or a program the transaction calls does not exist
+    AccountNotFound,
+    /// An instruction failed with this program-specific error code
+    ProgramError(u32),
+    /// The transaction was not confirmed in time
+    Timeout,
+    /// The cluster doesn't know the transaction's blockhash, usually because it expired
+    BlockhashNotFound,
+    Unknown(String),
+}
+
+impl std::fmt::Display for SolanaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolanaError::InsufficientFunds => write!(f, "Transaction failed: insufficient funds"),
+            SolanaError::AccountNotFound => write!(f, "Transaction failed: account not found"),
+            SolanaError::ProgramError(code) => write!(f, "Transaction failed: program error {}", code),
+            SolanaError::Timeout => write!(f, "Transaction failed: not confirmed in time"),
+            SolanaError::BlockhashNotFound => write!(f, "Transaction failed: blockhash not found"),
+            SolanaError::Unknown(e) => write!(f, "Transaction failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SolanaError {}
+
+impl From<SolanaError> for String {
+    fn from(err: SolanaError) -> Self {
+        err.to_string()
+    }
+}
+
+impl From<TransactionError> for SolanaError {
+    fn from(err: TransactionError) -> Self {
+        match err {
+            TransactionError::InsufficientFundsForFee
+            | TransactionError::InsufficientFundsForRent { .. }
+            | TransactionError::InstructionError(_, InstructionError::InsufficientFunds) => SolanaError::InsufficientFunds,
+            TransactionError::AccountNotFound | TransactionError::ProgramAccountNotFound => SolanaError::AccountNotFound,
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => SolanaError::ProgramError(code),
+            TransactionError::BlockhashNotFound => SolanaError::BlockhashNotFound,
+            other => SolanaError::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl From<ClientError> for SolanaError {
+    fn from(err: ClientError) -> Self {
+        // Preflight failures and on-chain failures both carry the TransactionError
+        if let Some(tx_err) = err.get_transaction_error() {
+            return tx_err.into();
+        }
+        match err.kind() {
+            ClientErrorKind::Reqwest(e) if e.is_timeout() => SolanaError::Timeout,
+            // What the client gives up with when a transaction never confirms
+            ClientErrorKind::RpcError(RpcError::ForUser(message))
+                if message.starts_with("unable to confirm transaction")
+                    || message.starts_with("transaction not finalized") =>
+            {
+                SolanaError::Timeout
+            }
+            _ => SolanaError::Unknown(err.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TokenAccountInfo {
+    /// The token account itself
+    pub address: String,
     pub mint: String,
     pub amount: u64,
     pub decimals: u8,
+    /// Rent held by the account, returned to the owner when it is closed
+    pub lamports: u64,
+    pub is_frozen: bool,
+    /// SPL Token or Token-2022 program that owns the account
+    pub token_program: String,
 }
 
 #[derive(Debug, Clone)]
@@ -3,19 +3,48 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
-use futures_util::StreamExt;
+use tokio_util::sync::CancellationToken;
+use futures_util::{SinkExt, StreamExt};
 use std::str::FromStr;
 use solana_sdk::pubkey::Pubkey;
+use crate::anchor_client::registry_events_from_logs;
+
+/// Wait before the first reconnect; doubled for each failed attempt after it
+pub const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Longest wait between reconnect attempts
+pub const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Opening the first connection
+    Connecting,
+    Connected,
+    /// The connection dropped; waiting out the backoff before opening another
+    Reconnecting,
+}
 
 #[derive(Debug, Clone)]
 pub struct SolanaWebSocketClient {
     ws_url: String,
     broker: Arc<crate::websocket::HermesBroker>,
+    /// Every subscription made so far, replayed on each new connection
+    subscriptions: Arc<Mutex<Vec<SolanaSubscription>>>,
+    /// Sends subscriptions over the open connection, while there is one
+    live: Arc<Mutex<Option<mpsc::UnboundedSender<SolanaSubscription>>>>,
+    next_id: Arc<AtomicU64>,
+    state: Arc<watch::Sender<ConnectionState>>,
+    closed: CancellationToken,
+    initial_delay: Duration,
+    max_delay: Duration,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct SolanaSubscription {
     jsonrpc: String,
     id: u64,
@@ -23,6 +52,13 @@ struct SolanaSubscription {
     params: Vec<serde_json::Value>,
 }
 
+/// The validator's answer to a subscribe request, carrying its id for the subscription
+#[derive(Debug, Deserialize)]
+struct SubscribeResponse {
+    id: u64,
+    result: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SolanaNotification {
     jsonrpc: String,
@@ -41,9 +77,48 @@ struct SolanaNotificationResult {
     value: serde_json::Value,
 }
 
+/// `value` of a logsNotification
+#[derive(Debug, Deserialize)]
+struct LogsValue {
+    signature: String,
+    #[serde(default)]
+    err: serde_json::Value,
+    #[serde(default)]
+    logs: Vec<String>,
+}
+
+impl SolanaSubscription {
+    fn message(&self) -> Message {
+        Message::Text(serde_json::to_string(self).expect("subscriptions serialize"))
+    }
+}
+
 impl SolanaWebSocketClient {
     pub fn new(ws_url: String, broker: Arc<crate::websocket::HermesBroker>) -> Self {
-        Self { ws_url, broker }
+        Self {
+            ws_url,
+            broker,
+            subscriptions: Arc::new(Mutex::new(Vec::new())),
+            live: Arc::new(Mutex::new(None)),
+            next_id: Arc::new(AtomicU64::new(1)),
+            state: Arc::new(watch::channel(ConnectionState::Connecting).0),
+            closed: CancellationToken::new(),
+            initial_delay: INITIAL_RECONNECT_DELAY,
+            max_delay: MAX_RECONNECT_DELAY,
+        }
+    }
+
+    /// Override the reconnect backoff, e.g. to keep tests fast
+    pub fn with_backoff(mut self, initial_delay: Duration, max_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Broker topic a subscription's notifications are published on. The id is the one
+    /// a `subscribe_*` method returned, so it survives reconnects.
+    pub fn subscription_topic(id: u64) -> String {
+        format!("solana:{}", id)
     }
 
     /// Subscribe to account changes
@@ -54,22 +129,13 @@ impl SolanaWebSocketClient {
         let pubkey = Pubkey::from_str(account)
             .map_err(|e| format!("Invalid pubkey: {}", e))?;
 
-        let _subscription = SolanaSubscription {
-            jsonrpc: "2.0".to_string(),
-            id: 1,
-            method: "accountSubscribe".to_string(),
-            params: vec![
-                json!(pubkey.to_string()),
-                json!({
-                    "encoding": "jsonParsed",
-                    "commitment": "confirmed"
-                }),
-            ],
-        };
-
-        // In a full implementation, we'd maintain a WebSocket connection
-        // and forward events to the broker. For now, this is a placeholder.
-        Ok(1)
+        Ok(self.subscribe("accountSubscribe", vec![
+            json!(pubkey.to_string()),
+            json!({
+                "encoding": "jsonParsed",
+                "commitment": "confirmed"
+            }),
+        ]))
     }
 
     /// Subscribe to program account changes
@@ -80,60 +146,161 @@ impl SolanaWebSocketClient {
         let pubkey = Pubkey::from_str(program)
             .map_err(|e| format!("Invalid pubkey: {}", e))?;
 
-        let _subscription = SolanaSubscription {
+        Ok(self.subscribe("programSubscribe", vec![
+            json!(pubkey.to_string()),
+            json!({
+                "encoding": "jsonParsed",
+                "commitment": "confirmed",
+                "filters": []
+            }),
+        ]))
+    }
+
+    /// Subscribe to the logs of transactions mentioning `program`. Registry events found in
+    /// them are published decoded, one message per event, with the transaction's signature.
+    pub async fn subscribe_logs(
+        &self,
+        program: &str,
+    ) -> Result<u64, String> {
+        let pubkey = Pubkey::from_str(program)
+            .map_err(|e| format!("Invalid pubkey: {}", e))?;
+
+        Ok(self.subscribe("logsSubscribe", vec![
+            json!({ "mentions": [pubkey.to_string()] }),
+            json!({ "commitment": "confirmed" }),
+        ]))
+    }
+
+    /// Record a subscription for every connection from now on, sending it right away when connected
+    fn subscribe(&self, method: &str, params: Vec<serde_json::Value>) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let subscription = SolanaSubscription {
             jsonrpc: "2.0".to_string(),
-            id: 2,
-            method: "programSubscribe".to_string(),
-            params: vec![
-                json!(pubkey.to_string()),
-                json!({
-                    "encoding": "jsonParsed",
-                    "commitment": "confirmed",
-                    "filters": []
-                }),
-            ],
+            id,
+            method: method.to_string(),
+            params,
         };
 
-        // In a full implementation, we'd maintain a WebSocket connection
-        // and forward events to the broker. For now, this is a placeholder.
-        Ok(2)
+        // Held while sending so a connection coming up can't miss it or replay it twice
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if let Some(live) = self.live.lock().unwrap().as_ref() {
+            let _ = live.send(subscription.clone());
+        }
+        subscriptions.push(subscription);
+        id
     }
 
-    /// Start WebSocket connection and forward events to broker
-    pub async fn start(&self) -> Result<(), String> {
-        // This would establish a persistent WebSocket connection to Solana RPC
-        // and forward account/program notifications to the Hermes broker
-        // For now, this is a placeholder that can be expanded
-        
-        let (ws_stream, _) = connect_async(&self.ws_url)
-            .await
-            .map_err(|e| format!("Failed to connect to Solana WebSocket: {}", e))?;
+    /// Connection state as it changes
+    pub fn state(&self) -> watch::Receiver<ConnectionState> {
+        self.state.subscribe()
+    }
 
-        let (_write, mut read) = ws_stream.split();
+    /// Stop reconnecting and drop the connection
+    pub fn close(&self) {
+        self.closed.cancel();
+    }
 
-        // Spawn task to handle incoming messages
-        let broker = Arc::clone(&self.broker);
+    /// Hold a connection to the validator and forward notifications to the broker until
+    /// `close` is called. Dropped connections are reopened with exponential backoff and
+    /// every subscription is sent again.
+    pub fn start(&self) -> tokio::task::JoinHandle<()> {
+        let client = self.clone();
         tokio::spawn(async move {
-            while let Some(msg) = read.next().await {
-                match msg {
-                    Ok(Message::Text(text)) => {
+            let mut delay = client.initial_delay;
+            loop {
+                let connected = tokio::select! {
+                    connected = client.run_connection() => connected,
+                    _ = client.closed.cancelled() => break,
+                };
+                *client.live.lock().unwrap() = None;
+                client.state.send_replace(ConnectionState::Reconnecting);
+                if connected {
+                    delay = client.initial_delay;
+                }
+                tracing::warn!("Solana WebSocket disconnected, reconnecting in {:?}", delay);
+
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = client.closed.cancelled() => break,
+                }
+                delay = (delay * 2).min(client.max_delay);
+            }
+            *client.live.lock().unwrap() = None;
+        })
+    }
+
+    /// Publish a notification on its subscription's topic; logs are published as the events decoded from them
+    async fn forward(&self, id: u64, notification: SolanaNotification, text: String) {
+        let topic = Self::subscription_topic(id);
+        if notification.method != "logsNotification" {
+            self.broker.publish(&topic, text).await;
+            return;
+        }
+
+        let Ok(logs) = serde_json::from_value::<LogsValue>(notification.params.result.value) else { return };
+        // A failed transaction's events never happened
+        if !logs.err.is_null() {
+            return;
+        }
+        for event in registry_events_from_logs(&logs.logs) {
+            let mut message = event.to_json();
+            message["signature"] = json!(logs.signature);
+            self.broker.publish(&topic, message.to_string()).await;
+        }
+    }
+
+    /// Subscribe over one connection and forward its notifications until it closes.
+    /// Returns whether the connection was made.
+    async fn run_connection(&self) -> bool {
+        let ws_stream = match connect_async(&self.ws_url).await {
+            Ok((ws_stream, _)) => ws_stream,
+            Err(e) => {
+                tracing::warn!("Failed to connect to Solana WebSocket: {}", e);
+                return false;
+            }
+        };
+        let (mut write, mut read) = ws_stream.split();
+
+        let (live, mut added) = mpsc::unbounded_channel();
+        let replay = {
+            let subscriptions = self.subscriptions.lock().unwrap();
+            *self.live.lock().unwrap() = Some(live);
+            subscriptions.clone()
+        };
+        self.state.send_replace(ConnectionState::Connected);
+        for subscription in &replay {
+            if write.send(subscription.message()).await.is_err() {
+                return true;
+            }
+        }
+
+        // The validator numbers subscriptions per connection; map its ids back to ours
+        let mut subscription_ids = HashMap::new();
+        loop {
+            tokio::select! {
+                Some(subscription) = added.recv() => {
+                    if write.send(subscription.message()).await.is_err() {
+                        return true;
+                    }
+                }
+                msg = read.next() => match msg {
+                    Some(Ok(Message::Text(text))) => {
                         if let Ok(notification) = serde_json::from_str::<SolanaNotification>(&text) {
-                            // Forward to broker
-                            let topic = format!("solana:{}", notification.params.subscription);
-                            let _ = broker.publish(&topic, text).await;
+                            if let Some(id) = subscription_ids.get(&notification.params.subscription) {
+                                self.forward(*id, notification, text).await;
+                            }
+                        } else if let Ok(response) = serde_json::from_str::<SubscribeResponse>(&text) {
+                            subscription_ids.insert(response.result, response.id);
                         }
                     }
-                    Ok(Message::Close(_)) => break,
-                    Err(e) => {
-                        eprintln!("WebSocket error: {}", e);
-                        break;
+                    Some(Ok(Message::Close(_))) | None => return true,
+                    Some(Err(e)) => {
+                        tracing::warn!("Solana WebSocket error: {}", e);
+                        return true;
                     }
-                    _ => {}
+                    Some(Ok(_)) => {}
                 }
             }
-        });
-
-        Ok(())
+        }
     }
 }
-
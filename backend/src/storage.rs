@@ -1,9 +1,177 @@
+use actix_web::web::Bytes;
+use futures_util::stream::BoxStream;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::env;
 
+/// Body of a gateway response, read a chunk at a time as the client consumes it
+pub type ByteStream = BoxStream<'static, Result<Bytes, String>>;
+
+/// A gateway response passed through without buffering it
+pub struct StorageStream {
+    /// 200, 206 for a satisfied range, 416 when the range is past the end, or 404 when
+    /// nothing is stored at the path
+    pub status: u16,
+    /// Bytes in this response, which is the range length for a 206
+    pub content_length: Option<u64>,
+    pub content_range: Option<String>,
+    pub content_type: Option<String>,
+    pub body: ByteStream,
+}
+
+/// Open `url` on a gateway, forwarding a `Range` header when given
+async fn open_stream(url: &str, range: Option<&str>, source: &str) -> Result<StorageStream, String> {
+    let mut request = reqwest::Client::new().get(url);
+    if let Some(range) = range {
+        request = request.header(reqwest::header::RANGE, range);
+    }
+    let response = request.send().await
+        .map_err(|e| format!("Failed to fetch from {}: {}", source, e))?;
+
+    let status = response.status();
+    if !status.is_success()
+        && status != reqwest::StatusCode::RANGE_NOT_SATISFIABLE
+        && status != reqwest::StatusCode::NOT_FOUND
+    {
+        return Err(format!("{} fetch error: {}", source, status));
+    }
+
+    let header = |name: reqwest::header::HeaderName| {
+        response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+    };
+    let content_range = header(reqwest::header::CONTENT_RANGE);
+    let content_type = header(reqwest::header::CONTENT_TYPE);
+    let content_length = response.content_length();
+    let source = source.to_string();
+    let body = futures_util::stream::try_unfold(response, move |mut response| {
+        let source = source.clone();
+        async move {
+            let chunk = response.chunk().await
+                .map_err(|e| format!("Failed to read {} data: {}", source, e))?;
+            Ok(chunk.map(|chunk| (chunk, response)))
+        }
+    });
+
+    Ok(StorageStream {
+        status: status.as_u16(),
+        content_length,
+        content_range,
+        content_type,
+        body: Box::pin(body),
+    })
+}
+
+/// Which IPFS pinning service uploads go to, from `STORAGE_BACKEND`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Pinata,
+    NFTStorage,
+    /// Pinata first, NFT.Storage when Pinata isn't configured or fails
+    Auto,
+}
+
+impl StorageBackend {
+    /// `pinata`, `nft_storage` or `auto`; anything else, or nothing, is `Auto`
+    pub fn from_env() -> Self {
+        match env::var("STORAGE_BACKEND").unwrap_or_default().to_lowercase().as_str() {
+            "pinata" => StorageBackend::Pinata,
+            "nft_storage" | "nftstorage" | "nft.storage" => StorageBackend::NFTStorage,
+            _ => StorageBackend::Auto,
+        }
+    }
+}
+
+/// NFT.Storage, the fallback pinning service
+pub struct NFTStorageClient {
+    api_key: Option<String>,
+    api_url: String,
+}
+
+impl Default for NFTStorageClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NFTStorageClient {
+    pub fn new() -> Self {
+        Self {
+            api_key: env::var("NFT_STORAGE_API_KEY").ok().filter(|k| !k.is_empty()),
+            api_url: env::var("NFT_STORAGE_API_URL")
+                .unwrap_or_else(|_| "https://api.nft.storage".to_string()),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn has_credentials(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    /// Pin `data` and return its CIDv1
+    pub async fn upload(&self, data: &[u8]) -> Result<String, String> {
+        let api_key = self.api_key.as_ref()
+            .ok_or_else(|| "NFT.Storage API key not configured".to_string())?;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/upload", self.api_url))
+            .bearer_auth(api_key)
+            .body(data.to_vec())
+            .send()
+            .await
+            .map_err(|e| format!("NFT.Storage upload error: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("NFT.Storage error: {}", response.status()));
+        }
+
+        let json: Value = response.json().await
+            .map_err(|e| format!("Failed to parse NFT.Storage response: {}", e))?;
+        json["value"]["cid"].as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "Missing cid in NFT.Storage response".to_string())
+    }
+
+    /// Whether NFT.Storage has finished pinning `cid`
+    pub async fn is_pinned(&self, cid: &str) -> Result<bool, String> {
+        let cid = cid.strip_prefix("ipfs://").unwrap_or(cid);
+        let response = reqwest::Client::new()
+            .get(format!("{}/check/{}", self.api_url, cid))
+            .send()
+            .await
+            .map_err(|e| format!("NFT.Storage check error: {}", e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            return Err(format!("NFT.Storage error: {}", response.status()));
+        }
+
+        let json: Value = response.json().await
+            .map_err(|e| format!("Failed to parse NFT.Storage response: {}", e))?;
+        Ok(json["value"]["pin"]["status"] == "pinned")
+    }
+}
+
 pub struct PinataStorage {
     api_key: Option<String>,
     secret: Option<String>,
+    api_url: String,
+    gateway_url: String,
+    backend: StorageBackend,
+}
+
+impl Default for PinataStorage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PinataStorage {
@@ -11,10 +179,105 @@ impl PinataStorage {
         Self {
             api_key: env::var("PINATA_API_KEY").ok(),
             secret: env::var("PINATA_SECRET").ok(),
+            api_url: env::var("PINATA_API_URL")
+                .unwrap_or_else(|_| "https://api.pinata.cloud".to_string()),
+            gateway_url: env::var("PINATA_GATEWAY_URL")
+                .unwrap_or_else(|_| "https://gateway.pinata.cloud/ipfs".to_string()),
+            backend: StorageBackend::from_env(),
+        }
+    }
+
+    pub fn with_backend(mut self, backend: StorageBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn backend(&self) -> StorageBackend {
+        self.backend
+    }
+
+    pub fn with_credentials(mut self, api_key: &str, secret: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_gateway_url(mut self, gateway_url: &str) -> Self {
+        self.gateway_url = gateway_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn has_credentials(&self) -> bool {
+        self.api_key.is_some() && self.secret.is_some()
+    }
+
+    /// Pin a tiny JSON document and unpin it again to prove the credentials work
+    pub async fn probe(&self) -> Result<(), String> {
+        let (Some(api_key), Some(secret)) = (&self.api_key, &self.secret) else {
+            return Err("Pinata credentials not configured".to_string());
+        };
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("{}/pinning/pinJSONToIPFS", self.api_url))
+            .header("pinata_api_key", api_key)
+            .header("pinata_secret_api_key", secret)
+            .json(&serde_json::json!({
+                "pinataContent": { "shadow": "self-check" },
+                "pinataMetadata": { "name": "shadow-self-check" },
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Pinata pin error: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Pinata pin error: {}", response.status()));
+        }
+
+        let json: Value = response.json().await
+            .map_err(|e| format!("Failed to parse Pinata response: {}", e))?;
+        let ipfs_hash = json["IpfsHash"].as_str()
+            .ok_or_else(|| "Missing IpfsHash in response".to_string())?;
+
+        let response = client
+            .delete(format!("{}/pinning/unpin/{}", self.api_url, ipfs_hash))
+            .header("pinata_api_key", api_key)
+            .header("pinata_secret_api_key", secret)
+            .send()
+            .await
+            .map_err(|e| format!("Pinata unpin error: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Pinata unpin error: {}", response.status()));
+        }
+        Ok(())
+    }
+
+    /// Pin `data` with the configured backend. In `Auto`, a failed or unconfigured Pinata
+    /// upload goes to `fallback` instead when there is one.
+    pub async fn upload(&self, data: &[u8], name: &str, fallback: Option<&NFTStorageClient>) -> Result<String, String> {
+        let nft_storage = || async {
+            let fallback = fallback.ok_or_else(|| "NFT.Storage is not available".to_string())?;
+            fallback.upload(data).await.map(|cid| format!("ipfs://{}", cid))
+        };
+        match self.backend {
+            StorageBackend::Pinata => self.upload_to_pinata(data, name).await,
+            StorageBackend::NFTStorage => nft_storage().await,
+            StorageBackend::Auto => match self.upload_to_pinata(data, name).await {
+                Ok(cid) => Ok(cid),
+                Err(e) if fallback.is_some_and(NFTStorageClient::has_credentials) => {
+                    tracing::warn!("Pinata upload failed, falling back to NFT.Storage: {}", e);
+                    nft_storage().await
+                }
+                Err(e) => Err(e),
+            },
         }
     }
 
-    pub async fn upload(&self, data: &[u8], name: &str) -> Result<String, String> {
+    async fn upload_to_pinata(&self, data: &[u8], name: &str) -> Result<String, String> {
         if self.api_key.is_none() || self.secret.is_none() {
             return Err("Pinata credentials not configured".to_string());
         }
@@ -26,7 +289,7 @@ impl PinataStorage {
             .part("file", reqwest::multipart::Part::bytes(data.to_vec()).file_name(name.to_string()));
 
         let response = client
-            .post("https://api.pinata.cloud/pinning/pinFileToIPFS")
+            .post(format!("{}/pinning/pinFileToIPFS", self.api_url))
             .header("pinata_api_key", self.api_key.as_ref().unwrap())
             .header("pinata_secret_api_key", self.secret.as_ref().unwrap())
             .multipart(form)
@@ -47,9 +310,68 @@ impl PinataStorage {
         Ok(format!("ipfs://{}", ipfs_hash))
     }
 
+    /// Pin several files as one directory so `index.html` resolves under the returned CID
+    pub async fn upload_directory(&self, files: &[(String, Vec<u8>)], name: &str) -> Result<String, String> {
+        let (Some(api_key), Some(secret)) = (&self.api_key, &self.secret) else {
+            return Err("Pinata credentials not configured".to_string());
+        };
+
+        let mut form = reqwest::multipart::Form::new()
+            .text("pinataOptions", r#"{"cidVersion":1,"wrapWithDirectory":false}"#)
+            .text("pinataMetadata", format!(r#"{{"name":"{}"}}"#, name));
+        for (path, data) in files {
+            let file_name = format!("{}/{}", name, path.trim_start_matches('/'));
+            form = form.part("file", reqwest::multipart::Part::bytes(data.clone()).file_name(file_name));
+        }
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/pinning/pinFileToIPFS", self.api_url))
+            .header("pinata_api_key", api_key)
+            .header("pinata_secret_api_key", secret)
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| format!("Pinata upload error: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("Pinata error: {}", response.status()));
+        }
+
+        let json: Value = response.json().await
+            .map_err(|e| format!("Failed to parse Pinata response: {}", e))?;
+        let ipfs_hash = json["IpfsHash"].as_str()
+            .ok_or_else(|| "Missing IpfsHash in response".to_string())?;
+
+        Ok(format!("ipfs://{}", ipfs_hash))
+    }
+
+    /// Whether `cid` is pinned on this Pinata account
+    pub async fn is_pinned(&self, cid: &str) -> Result<bool, String> {
+        let (Some(api_key), Some(secret)) = (&self.api_key, &self.secret) else {
+            return Err("Pinata credentials not configured".to_string());
+        };
+        let cid = cid.strip_prefix("ipfs://").unwrap_or(cid);
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/data/pinList", self.api_url))
+            .query(&[("hashContains", cid), ("status", "pinned")])
+            .header("pinata_api_key", api_key)
+            .header("pinata_secret_api_key", secret)
+            .send()
+            .await
+            .map_err(|e| format!("Pinata pin list error: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Pinata error: {}", response.status()));
+        }
+
+        let json: Value = response.json().await
+            .map_err(|e| format!("Failed to parse Pinata response: {}", e))?;
+        Ok(json["rows"].as_array().into_iter().flatten().any(|row| row["ipfs_pin_hash"] == cid))
+    }
+
     pub async fn get(&self, cid: &str) -> Result<Vec<u8>, String> {
         let cid = cid.strip_prefix("ipfs://").unwrap_or(cid);
-        let url = format!("https://gateway.pinata.cloud/ipfs/{}", cid);
+        let url = format!("{}/{}", self.gateway_url, cid);
         
         let client = reqwest::Client::new();
         let response = client.get(&url)
@@ -66,11 +388,70 @@ impl PinataStorage {
 
         Ok(bytes.to_vec())
     }
+
+    /// Stream an object from the gateway, optionally just the bytes in `range`
+    pub async fn get_stream(&self, cid: &str, range: Option<&str>) -> Result<StorageStream, String> {
+        let cid = cid.strip_prefix("ipfs://").unwrap_or(cid);
+        open_stream(&format!("{}/{}", self.gateway_url, cid), range, "IPFS").await
+    }
 }
 
 pub struct BundlrStorage {
     node_url: String,
     private_key: Option<String>,
+    currency: String,
+    gateway_url: String,
+    /// Cluster funding transfers to the node are sent through
+    solana_rpc_url: String,
+}
+
+/// Extra funded on top of a shortfall, so a small price move doesn't need another top-up
+const FUNDING_MARGIN_PERCENT: u64 = 10;
+
+/// A data item as the Irys node's POST /tx takes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IrysTransaction {
+    /// The payload, base64url without padding
+    pub data: String,
+    pub tags: Vec<IrysTag>,
+    /// Signer's Solana public key, base58
+    pub owner: String,
+    /// base64url ed25519 signature over `id`; empty while the id is computed
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IrysTag {
+    pub name: String,
+    pub value: String,
+}
+
+impl IrysTransaction {
+    /// SHA-256 of the transaction serialized with an empty signature; this is what gets signed
+    pub fn id(&self) -> Result<[u8; 32], String> {
+        use sha2::{Digest, Sha256};
+        let unsigned = IrysTransaction { signature: String::new(), ..self.clone() };
+        let bytes = serde_json::to_vec(&unsigned)
+            .map_err(|e| format!("Failed to serialize transaction: {}", e))?;
+        Ok(Sha256::digest(&bytes).into())
+    }
+}
+
+/// Where an upload is according to the Bundlr node and the Arweave gateway
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArweaveProgress {
+    /// Accepted by the node, not yet bundled
+    Pending,
+    /// Bundled and seeded to Arweave, awaiting confirmations
+    Seeded,
+    /// Mined into an Arweave block
+    Confirmed,
+}
+
+impl Default for BundlrStorage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BundlrStorage {
@@ -79,30 +460,206 @@ impl BundlrStorage {
             node_url: env::var("BUNDLR_NODE_URL")
                 .unwrap_or_else(|_| "https://devnet.bundlr.network".to_string()),
             private_key: env::var("BUNDLR_PRIVATE_KEY").ok(),
+            currency: env::var("BUNDLR_CURRENCY")
+                .unwrap_or_else(|_| "solana".to_string()),
+            gateway_url: env::var("ARWEAVE_GATEWAY_URL")
+                .unwrap_or_else(|_| "https://arweave.net".to_string()),
+            solana_rpc_url: env::var("SOLANA_RPC_URL")
+                .unwrap_or_else(|_| "https://api.devnet.solana.com".to_string()),
         }
     }
 
-    pub async fn upload(&self, data: &[u8], tags: Vec<(&str, &str)>) -> Result<String, String> {
-        if self.private_key.is_none() {
-            return Err("Bundlr private key not configured".to_string());
+    pub fn with_solana_rpc_url(mut self, rpc_url: &str) -> Self {
+        self.solana_rpc_url = rpc_url.to_string();
+        self
+    }
+
+    pub fn with_node_url(mut self, node_url: &str) -> Self {
+        self.node_url = node_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_gateway_url(mut self, gateway_url: &str) -> Self {
+        self.gateway_url = gateway_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_private_key(mut self, private_key: &str) -> Self {
+        self.private_key = Some(private_key.to_string());
+        self
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    /// Funding account the configured key pays from
+    pub fn address(&self) -> Result<String, String> {
+        use solana_sdk::signature::Signer;
+        Ok(self.keypair()?.pubkey().to_string())
+    }
+
+    /// Quoted price in atomic units of the funding currency for `bytes` of data
+    pub async fn get_price(&self, bytes: usize) -> Result<u64, String> {
+        let response = reqwest::Client::new()
+            .get(format!("{}/price/{}/{}", self.node_url, self.currency, bytes))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get price: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Bundlr price error: {}", response.status()));
         }
 
-        // Parse Solana private key (base58 or hex)
-        let key_bytes = self.parse_private_key()?;
-        let keypair = solana_sdk::signer::keypair::Keypair::from_bytes(&key_bytes)
-            .map_err(|e| format!("Invalid keypair: {}", e))?;
+        // Nodes answer with a bare integer; older ones wrap it as {"atomicPrice": n}
+        let body = response.text().await
+            .map_err(|e| format!("Failed to read price: {}", e))?;
+        parse_atomic(&body)
+            .or_else(|| serde_json::from_str::<Value>(&body).ok().and_then(|json| atomic_value(&json["atomicPrice"])))
+            .ok_or_else(|| "Missing price in response".to_string())
+    }
+
+    /// Funded balance of the configured account, in atomic units
+    pub async fn get_balance(&self) -> Result<u64, String> {
+        let address = self.address()?;
+        let response = reqwest::Client::new()
+            .get(format!("{}/account/balance/{}", self.node_url, self.currency))
+            .query(&[("address", &address)])
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get balance: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Bundlr balance error: {}", response.status()));
+        }
 
-        // Create and sign Bundlr transaction
-        // Bundlr uses Arweave transactions signed with Solana keypairs
-        let tx = self.create_bundlr_transaction(data, &tags, &keypair).await?;
+        let json: Value = response.json().await
+            .map_err(|e| format!("Failed to parse balance: {}", e))?;
+        atomic_value(&json["balance"]).ok_or_else(|| "Missing balance in response".to_string())
+    }
 
+    /// Ask the gateway first (confirmed once mined), then the node (seeded once bundled)
+    pub async fn get_progress(&self, tx_id: &str) -> Result<ArweaveProgress, String> {
+        let tx_id = tx_id.strip_prefix("arweave://").unwrap_or(tx_id);
         let client = reqwest::Client::new();
-        let url = format!("{}/tx", self.node_url);
 
-        let response = client
-            .post(&url)
-            .header("Content-Type", "application/octet-stream")
-            .body(tx)
+        let gateway = client
+            .get(format!("{}/tx/{}/status", self.gateway_url, tx_id))
+            .send()
+            .await
+            .map_err(|e| format!("Arweave status error: {}", e))?;
+        if gateway.status() == reqwest::StatusCode::OK {
+            let json: Value = gateway.json().await.unwrap_or_default();
+            if json["number_of_confirmations"].as_u64().unwrap_or(0) > 0 {
+                return Ok(ArweaveProgress::Confirmed);
+            }
+        }
+
+        let node = client
+            .get(format!("{}/tx/{}/status", self.node_url, tx_id))
+            .send()
+            .await
+            .map_err(|e| format!("Bundlr status error: {}", e))?;
+        if !node.status().is_success() {
+            return Ok(ArweaveProgress::Pending);
+        }
+        let json: Value = node.json().await
+            .map_err(|e| format!("Failed to parse Bundlr status: {}", e))?;
+        Ok(match json["status"].as_str().unwrap_or_default() {
+            "FINALIZED" => ArweaveProgress::Confirmed,
+            "CONFIRMED" => ArweaveProgress::Seeded,
+            _ => ArweaveProgress::Pending,
+        })
+    }
+
+    /// Data the node still holds for an upload, used to re-upload stalled transactions
+    pub async fn fetch_from_node(&self, tx_id: &str) -> Result<Vec<u8>, String> {
+        let tx_id = tx_id.strip_prefix("arweave://").unwrap_or(tx_id);
+        let response = reqwest::Client::new()
+            .get(format!("{}/tx/{}/data", self.node_url, tx_id))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch from Bundlr: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Bundlr fetch error: {}", response.status()));
+        }
+        let bytes = response.bytes().await
+            .map_err(|e| format!("Failed to read Bundlr data: {}", e))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Top up the funding account when its balance is below the price of `bytes`, by
+    /// transferring the shortfall plus a margin to the node and registering the transfer.
+    /// Returns the funding transaction's signature, or None when the balance already covers it.
+    pub async fn fund_if_needed(&self, bytes: usize) -> Result<Option<String>, String> {
+        use solana_sdk::signature::Signer;
+        let price = self.get_price(bytes).await?;
+        let balance = self.get_balance().await?;
+        if balance >= price {
+            return Ok(None);
+        }
+        if self.currency != "solana" {
+            return Err(format!("Funding is only supported in solana, not {}", self.currency));
+        }
+
+        let shortfall = price - balance;
+        let amount = shortfall + shortfall * FUNDING_MARGIN_PERCENT / 100;
+        let keypair = self.keypair()?;
+        let node_address = self.node_address().await?;
+        let solana = crate::solana::SolanaClient::new(self.solana_rpc_url.clone());
+        let blockhash = solana.get_recent_blockhash().await?;
+        let transfer = solana_sdk::system_instruction::transfer(&keypair.pubkey(), &node_address, amount);
+        let tx = solana_sdk::transaction::Transaction::new_signed_with_payer(
+            &[transfer],
+            Some(&keypair.pubkey()),
+            &[&keypair],
+            blockhash,
+        );
+        let signature = solana.send_transaction(&tx).await
+            .map_err(|e| format!("Funding transfer failed: {}", e))?;
+
+        // The node credits the balance once it has seen the transfer
+        let response = reqwest::Client::new()
+            .post(format!("{}/account/balance/{}", self.node_url, self.currency))
+            .json(&serde_json::json!({ "tx_id": signature }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to register funding: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("Bundlr funding error: {} - {}", status, error_text));
+        }
+        Ok(Some(signature))
+    }
+
+    /// Account the node takes funding transfers on, from its /info
+    async fn node_address(&self) -> Result<solana_sdk::pubkey::Pubkey, String> {
+        let response = reqwest::Client::new()
+            .get(format!("{}/info", self.node_url))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get node info: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Bundlr info error: {}", response.status()));
+        }
+        let json: Value = response.json().await
+            .map_err(|e| format!("Failed to parse node info: {}", e))?;
+        json["addresses"][&self.currency].as_str()
+            .ok_or_else(|| format!("Node has no {} address", self.currency))?
+            .parse()
+            .map_err(|e| format!("Invalid node address: {}", e))
+    }
+
+    /// Upload `data` as a signed data item, topping up the funding account first when it
+    /// can't cover the upload. Returns the arweave:// URI.
+    pub async fn upload(&self, data: &[u8], tags: Vec<(&str, &str)>) -> Result<String, String> {
+        let keypair = self.keypair()?;
+        self.fund_if_needed(data.len()).await?;
+        let tx = Self::create_transaction(data, &tags, &keypair)?;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/tx", self.node_url))
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(&tx).map_err(|e| format!("Failed to serialize transaction: {}", e))?)
             .send()
             .await
             .map_err(|e| format!("Bundlr upload error: {}", e))?;
@@ -122,6 +679,37 @@ impl BundlrStorage {
         Ok(format!("arweave://{}", tx_id))
     }
 
+    /// Data item for `data`, signed by `keypair` over its id
+    pub fn create_transaction(
+        data: &[u8],
+        tags: &[(&str, &str)],
+        keypair: &solana_sdk::signer::keypair::Keypair,
+    ) -> Result<IrysTransaction, String> {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+        use solana_sdk::signature::Signer;
+
+        let mut tx = IrysTransaction {
+            data: URL_SAFE_NO_PAD.encode(data),
+            tags: tags.iter()
+                .map(|(name, value)| IrysTag { name: name.to_string(), value: value.to_string() })
+                .collect(),
+            owner: keypair.pubkey().to_string(),
+            signature: String::new(),
+        };
+        let signature = keypair.try_sign_message(&tx.id()?)
+            .map_err(|e| format!("Failed to sign: {}", e))?;
+        tx.signature = URL_SAFE_NO_PAD.encode(signature.as_ref());
+        Ok(tx)
+    }
+
+    fn keypair(&self) -> Result<solana_sdk::signer::keypair::Keypair, String> {
+        if self.private_key.is_none() {
+            return Err("Bundlr private key not configured".to_string());
+        }
+        solana_sdk::signer::keypair::Keypair::from_bytes(&self.parse_private_key()?)
+            .map_err(|e| format!("Invalid keypair: {}", e))
+    }
+
     fn parse_private_key(&self) -> Result<Vec<u8>, String> {
         let key_str = self.private_key.as_ref().unwrap();
         
@@ -142,54 +730,9 @@ impl BundlrStorage {
         Err("Invalid private key format. Expected base58 or hex (64 bytes)".to_string())
     }
 
-    async fn create_bundlr_transaction(
-        &self,
-        data: &[u8],
-        _tags: &[(&str, &str)],
-        keypair: &solana_sdk::signer::keypair::Keypair,
-    ) -> Result<Vec<u8>, String> {
-        // Get price from Bundlr
-        let client = reqwest::Client::new();
-        let price_url = format!("{}/price/{}", self.node_url, data.len());
-        
-        let price_response = client
-            .get(&price_url)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get price: {}", e))?;
-        
-        let price_json: Value = price_response.json().await
-            .map_err(|e| format!("Failed to parse price: {}", e))?;
-        
-        let _price = price_json["atomicPrice"].as_u64()
-            .ok_or_else(|| "Missing price in response".to_string())?;
-
-        // Create transaction payload for Bundlr
-        // Bundlr accepts data with signature in a specific format
-        // We'll create a signed payload that Bundlr can verify
-        let mut payload = Vec::new();
-        
-        // Add data
-        payload.extend_from_slice(data);
-        
-        // Create signature over the data
-        use solana_sdk::signature::Signer;
-        let signature = keypair.try_sign_message(&payload)
-            .map_err(|e| format!("Failed to sign: {}", e))?;
-        
-        // Bundlr transaction format: data + signature + public key
-        let mut tx = payload;
-        tx.extend_from_slice(signature.as_ref());
-        tx.extend_from_slice(&keypair.pubkey().to_bytes());
-        
-        // Add tags as metadata (Bundlr will handle this)
-        // For now, we'll send the transaction and let Bundlr process it
-        Ok(tx)
-    }
-
     pub async fn get(&self, tx_id: &str) -> Result<Vec<u8>, String> {
         let tx_id = tx_id.strip_prefix("arweave://").unwrap_or(tx_id);
-        let url = format!("https://arweave.net/{}", tx_id);
+        let url = format!("{}/{}", self.gateway_url, tx_id);
         
         let client = reqwest::Client::new();
         let response = client.get(&url)
@@ -206,5 +749,19 @@ impl BundlrStorage {
 
         Ok(bytes.to_vec())
     }
+
+    /// Stream an object from the gateway, optionally just the bytes in `range`
+    pub async fn get_stream(&self, tx_id: &str, range: Option<&str>) -> Result<StorageStream, String> {
+        let tx_id = tx_id.strip_prefix("arweave://").unwrap_or(tx_id);
+        open_stream(&format!("{}/{}", self.gateway_url, tx_id), range, "Arweave").await
+    }
 }
 
+/// Atomic amounts come back as JSON numbers or as decimal strings
+fn atomic_value(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str().and_then(parse_atomic))
+}
+
+fn parse_atomic(value: &str) -> Option<u64> {
+    value.trim().trim_matches('"').parse().ok()
+}
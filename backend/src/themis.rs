@@ -0,0 +1,456 @@
+// Themis - Titaness of divine law and order
+// Periodically re-verifies domains against the chain. A domain whose program was
+// closed or changed hands since it was verified loses its checkmark, the owner is
+// told how to fix it, and repeated failures put a warning interstitial in front of it.
+
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::VerificationConfig;
+use crate::olympus::OlympusCA;
+use crate::solana::SolanaClient;
+use crate::websocket::HermesBroker;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+/// Message left for a wallet owner about one of their resources
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OwnerNotification {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub wallet: String,
+    pub domain: String,
+    pub kind: String,
+    pub message: String,
+    pub read: bool,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// The fields of a domain the job needs; avoids depending on how the rest was written
+#[derive(Debug, Deserialize, Clone)]
+struct DomainRecord {
+    #[serde(rename = "_id")]
+    domain: String,
+    owner_pubkey: String,
+    program_address: String,
+    #[serde(default)]
+    verified: bool,
+    #[serde(default)]
+    verification_failures: u32,
+    #[serde(default)]
+    warning_interstitial: bool,
+    #[serde(default)]
+    verified_authority: Option<String>,
+}
+
+/// Result of the on-chain checks for one domain
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckOutcome {
+    Passed { authority: Option<String> },
+    Failed(String),
+    /// The RPC node could not answer; says nothing about the domain
+    Unavailable(String),
+}
+
+/// What a check outcome means for a domain's stored state
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transition {
+    Healthy,
+    Failed {
+        failures: u32,
+        downgrade: bool,
+        raise_interstitial: bool,
+    },
+    Skipped,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ReverificationReport {
+    pub checked: usize,
+    pub downgraded: usize,
+    pub interstitials: usize,
+    pub skipped: usize,
+    pub rpc_calls: u32,
+}
+
+/// Decide how a domain changes given the outcome of its checks.
+/// Downgrades and interstitials only fire on the transition, so repeated failures stay quiet.
+pub fn next_transition(
+    verified: bool,
+    failures: u32,
+    has_interstitial: bool,
+    outcome: &CheckOutcome,
+    interstitial_after: u32,
+) -> Transition {
+    match outcome {
+        CheckOutcome::Passed { .. } => Transition::Healthy,
+        CheckOutcome::Unavailable(_) => Transition::Skipped,
+        CheckOutcome::Failed(_) => {
+            let failures = failures + 1;
+            Transition::Failed {
+                failures,
+                downgrade: verified,
+                raise_interstitial: !has_interstitial && failures >= interstitial_after,
+            }
+        }
+    }
+}
+
+/// Run the same checks as the verify endpoint, plus an upgrade authority comparison.
+/// Blocking; returns the outcome and the number of RPC calls spent.
+fn check_program(
+    rpc_url: &str,
+    program_address: &str,
+    verified_authority: Option<&str>,
+) -> (CheckOutcome, u32) {
+    let client = SolanaClient::new(rpc_url.to_string());
+
+    let account = match client.fetch_account(program_address) {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            return (CheckOutcome::Failed("Program account no longer exists".to_string()), 1);
+        }
+        Err(e) => return (CheckOutcome::Unavailable(e), 1),
+    };
+    if !account.executable {
+        return (CheckOutcome::Failed("Program account is no longer executable".to_string()), 1);
+    }
+    if account.data_len == 0 {
+        return (CheckOutcome::Failed("Program account is empty".to_string()), 1);
+    }
+
+    let authority = match client.get_program_upgrade_authority(program_address) {
+        Ok(authority) => authority,
+        Err(e) => return (CheckOutcome::Unavailable(e), 2),
+    };
+    if let Some(expected) = verified_authority {
+        if authority.as_deref() != Some(expected) {
+            let reason = format!(
+                "Upgrade authority changed from {} to {}",
+                expected,
+                authority.as_deref().unwrap_or("none")
+            );
+            return (CheckOutcome::Failed(reason), 2);
+        }
+    }
+
+    (CheckOutcome::Passed { authority }, 2)
+}
+
+pub struct ThemisVerifier {
+    db: Database,
+    olympus: OlympusCA,
+    rpc_url: String,
+    config: VerificationConfig,
+    broker: Option<Arc<HermesBroker>>,
+    clock: SharedClock,
+}
+
+impl ThemisVerifier {
+    pub fn new(db: Database, rpc_url: String, config: VerificationConfig) -> Self {
+        Self {
+            olympus: OlympusCA::new(db.clone()),
+            db,
+            rpc_url,
+            config,
+            broker: None,
+            clock: SystemClock::shared(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.olympus = self.olympus.with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+
+    /// Also push owner notifications to live Hermes subscribers
+    pub fn with_broker(mut self, broker: Arc<HermesBroker>) -> Self {
+        self.broker = Some(broker);
+        self
+    }
+
+    fn get_domains_collection(&self) -> Collection<DomainRecord> {
+        self.db.collection::<DomainRecord>("domains")
+    }
+
+    fn get_notifications_collection(&self) -> Collection<OwnerNotification> {
+        self.db.collection::<OwnerNotification>("notifications")
+    }
+
+    /// Notifications for a wallet, newest first
+    pub async fn list_notifications(&self, wallet: &str) -> Result<Vec<OwnerNotification>, String> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+
+        let cursor = self.get_notifications_collection().find(doc! { "wallet": wallet }, options).await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        cursor.try_collect().await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
+    async fn notify(&self, record: &DomainRecord, kind: &str, message: String) -> Result<(), String> {
+        let notification = OwnerNotification {
+            id: uuid::Uuid::new_v4().to_string(),
+            wallet: record.owner_pubkey.clone(),
+            domain: record.domain.clone(),
+            kind: kind.to_string(),
+            message,
+            read: false,
+            created_at: self.clock.now_utc(),
+        };
+
+        self.get_notifications_collection().insert_one(&notification, None).await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        if let Some(broker) = &self.broker {
+            if let Ok(json) = serde_json::to_string(&notification) {
+                broker.publish(&format!("wallet:{}", notification.wallet), json).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-check one batch after another until every candidate has been seen this run,
+    /// the RPC budget is spent, or shutdown is requested.
+    pub async fn run_once(&self, shutdown: &CancellationToken) -> Result<ReverificationReport, String> {
+        let run_started = mongodb::bson::DateTime::now();
+        let mut report = ReverificationReport::default();
+
+        // Previously verified domains, plus ones still on a failure streak
+        let filter = doc! {
+            "$and": [
+                { "$or": [ { "verified": true }, { "verification_failures": { "$gt": 0 } } ] },
+                { "$or": [ { "last_checked_at": { "$exists": false } }, { "last_checked_at": { "$lt": run_started } } ] }
+            ]
+        };
+
+        while report.rpc_calls < self.config.rpc_budget && !shutdown.is_cancelled() {
+            let options = mongodb::options::FindOptions::builder()
+                .sort(doc! { "last_checked_at": 1 })
+                .limit(self.config.batch_size)
+                .build();
+
+            let batch: Vec<DomainRecord> = self.get_domains_collection()
+                .find(filter.clone(), options).await
+                .map_err(|e| format!("Database error: {}", e))?
+                .try_collect().await
+                .map_err(|e| format!("Database error: {}", e))?;
+
+            if batch.is_empty() {
+                break;
+            }
+
+            for record in batch {
+                if report.rpc_calls >= self.config.rpc_budget || shutdown.is_cancelled() {
+                    break;
+                }
+                self.reverify(record, &mut report).await?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn reverify(&self, record: DomainRecord, report: &mut ReverificationReport) -> Result<(), String> {
+        let rpc_url = self.rpc_url.clone();
+        let program = record.program_address.clone();
+        let expected = record.verified_authority.clone();
+        let (outcome, calls) = tokio::task::spawn_blocking(move || {
+            check_program(&rpc_url, &program, expected.as_deref())
+        })
+        .await
+        .map_err(|e| format!("Verification task failed: {}", e))?;
+
+        report.checked += 1;
+        report.rpc_calls += calls;
+
+        let transition = next_transition(
+            record.verified,
+            record.verification_failures,
+            record.warning_interstitial,
+            &outcome,
+            self.config.interstitial_after_failures,
+        );
+
+        let collection = self.db.collection::<mongodb::bson::Document>("domains");
+        let filter = doc! { "_id": &record.domain };
+        let now = mongodb::bson::DateTime::now();
+
+        match (&transition, &outcome) {
+            (Transition::Healthy, CheckOutcome::Passed { authority }) => {
+                let mut set = doc! {
+                    "verification_failures": 0,
+                    "warning_interstitial": false,
+                    "last_checked_at": now,
+                };
+                // Domains verified before authorities were recorded adopt the current one
+                if record.verified && record.verified_authority.is_none() {
+                    set.insert("verified_authority", authority.clone());
+                }
+                collection.update_one(filter, doc! { "$set": set, "$unset": { "verification_error": "" } }, None).await
+                    .map_err(|e| format!("Database error: {}", e))?;
+            }
+            (Transition::Failed { failures, downgrade, raise_interstitial }, CheckOutcome::Failed(reason)) => {
+                let mut set = doc! {
+                    "verification_failures": *failures,
+                    "verification_error": reason,
+                    "last_checked_at": now,
+                };
+                if *downgrade {
+                    set.insert("verified", false);
+                    set.insert("updated_at", now);
+                }
+                if *raise_interstitial {
+                    set.insert("warning_interstitial", true);
+                }
+                collection.update_one(filter, doc! { "$set": set }, None).await
+                    .map_err(|e| format!("Database error: {}", e))?;
+
+                if *downgrade {
+                    report.downgraded += 1;
+                    self.olympus.record_event(&record.domain, "verification_revoked", Some(reason)).await?;
+                    self.notify(&record, "verification_revoked", format!(
+                        "{} is no longer verified: {}. Point it at a live program you control with \
+                         PUT /api/domains/{}, then call POST /api/domains/{}/verify to restore the checkmark.",
+                        record.domain, reason, record.domain, record.domain
+                    )).await?;
+                }
+                if *raise_interstitial {
+                    report.interstitials += 1;
+                    self.olympus.record_event(&record.domain, "warning_interstitial", Some(reason)).await?;
+                }
+            }
+            _ => {
+                report.skipped += 1;
+                if let CheckOutcome::Unavailable(e) = &outcome {
+                    tracing::warn!("Themis skipped {}: {}", record.domain, e);
+                }
+                collection.update_one(filter, doc! { "$set": { "last_checked_at": now } }, None).await
+                    .map_err(|e| format!("Database error: {}", e))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run the job on the configured interval until shutdown is requested
+    pub fn spawn(self: Arc<Self>, shutdown: CancellationToken) -> tokio::task::JoinHandle<()> {
+        let interval = std::time::Duration::from_secs(self.config.interval_seconds);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                match self.run_once(&shutdown).await {
+                    Ok(report) if report.downgraded > 0 || report.interstitials > 0 => {
+                        tracing::warn!("Themis re-verification: {:?}", report);
+                    }
+                    Ok(report) => tracing::debug!("Themis re-verification: {:?}", report),
+                    Err(e) => tracing::warn!("Themis re-verification failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed() -> CheckOutcome {
+        CheckOutcome::Failed("Program account no longer exists".to_string())
+    }
+
+    #[test]
+    fn test_first_failure_downgrades_verified_domain() {
+        assert_eq!(
+            next_transition(true, 0, false, &failed(), 3),
+            Transition::Failed { failures: 1, downgrade: true, raise_interstitial: false }
+        );
+        // Already downgraded: the streak grows without another downgrade
+        assert_eq!(
+            next_transition(false, 1, false, &failed(), 3),
+            Transition::Failed { failures: 2, downgrade: false, raise_interstitial: false }
+        );
+    }
+
+    #[test]
+    fn test_interstitial_raised_once_at_threshold() {
+        assert_eq!(
+            next_transition(false, 2, false, &failed(), 3),
+            Transition::Failed { failures: 3, downgrade: false, raise_interstitial: true }
+        );
+        assert_eq!(
+            next_transition(false, 3, true, &failed(), 3),
+            Transition::Failed { failures: 4, downgrade: false, raise_interstitial: false }
+        );
+    }
+
+    async fn rpc_with_account(value: serde_json::Value) -> wiremock::MockServer {
+        use wiremock::matchers::{body_partial_json, method};
+        let server = wiremock::MockServer::start().await;
+        // The RPC client asks for the node version before picking an account encoding
+        wiremock::Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "result": { "solana-core": "1.18.26", "feature-set": 0 },
+                "id": 1
+            })))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "result": { "context": { "slot": 1 }, "value": value },
+                "id": 1
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_check_program_against_rpc() {
+        let program = solana_sdk::pubkey::Pubkey::new_unique().to_string();
+
+        let deployed = rpc_with_account(serde_json::json!({
+            "data": ["AQID", "base64"],
+            "executable": true,
+            "lamports": 1_141_440,
+            "owner": "BPFLoader2111111111111111111111111111111111",
+            "rentEpoch": 0,
+            "space": 3
+        })).await;
+        let (outcome, calls) = check_program(&deployed.uri(), &program, None);
+        assert_eq!(outcome, CheckOutcome::Passed { authority: None });
+        assert_eq!(calls, 2);
+
+        let closed = rpc_with_account(serde_json::Value::Null).await;
+        let (outcome, calls) = check_program(&closed.uri(), &program, None);
+        assert_eq!(outcome, CheckOutcome::Failed("Program account no longer exists".to_string()));
+        assert_eq!(calls, 1);
+
+        let (outcome, _) = check_program("http://127.0.0.1:1", &program, None);
+        assert!(matches!(outcome, CheckOutcome::Unavailable(_)));
+    }
+
+    #[test]
+    fn test_rpc_outage_leaves_domain_alone() {
+        let outcome = CheckOutcome::Unavailable("RPC error: timeout".to_string());
+        assert_eq!(next_transition(true, 0, false, &outcome, 3), Transition::Skipped);
+        let outcome = CheckOutcome::Passed { authority: None };
+        assert_eq!(next_transition(false, 2, false, &outcome, 3), Transition::Healthy);
+    }
+}
@@ -0,0 +1,474 @@
+// Tyche - Goddess of fortune
+// Works out who controls a site when control follows an NFT instead of a wallet,
+// and what its collaborators may do
+
+use crate::anchor_client::AnchorClient;
+use crate::db::{Collaborator, OwnershipMode, Site, SiteRole};
+use crate::error::ShadowError;
+use crate::themis::OwnerNotification;
+use crate::websocket::HermesBroker;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use futures_util::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a looked-up NFT holder is trusted before asking the chain again
+pub const HOLDER_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Current holder of a control NFT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NftHolder {
+    Wallet(String),
+    /// Supply burned or the mint closed; nobody can act on the site until an admin recovers it
+    Burned,
+}
+
+/// Who may write to a site right now, as shown on the dashboard and in domain lookups
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct SiteController {
+    pub mode: OwnershipMode,
+    /// Controlling wallet; None when the NFT is burned or its holder couldn't be looked up
+    pub wallet: Option<String>,
+    pub mint: Option<String>,
+    pub nft_burned: bool,
+}
+
+/// Writes that need a role on the site
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SiteAction {
+    UpdateContent,
+    UpdateMetadata,
+    ManageDeployTokens,
+    ManageDomains,
+    ManageCollaborators,
+    ChangeOwnership,
+}
+
+impl SiteAction {
+    /// Lowest role allowed to perform the action
+    pub fn required_role(self) -> SiteRole {
+        match self {
+            SiteAction::UpdateContent | SiteAction::UpdateMetadata | SiteAction::ManageDeployTokens => SiteRole::Editor,
+            SiteAction::ManageDomains | SiteAction::ManageCollaborators => SiteRole::Admin,
+            SiteAction::ChangeOwnership => SiteRole::Owner,
+        }
+    }
+}
+
+/// Audit trail entry for ownership and collaborator changes on a site
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SiteEvent {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub program_address: String,
+    pub kind: String,
+    /// Wallet that made the change
+    pub actor: String,
+    pub mode: OwnershipMode,
+    pub mint: Option<String>,
+    pub reason: Option<String>,
+    /// Wallet whose access changed, for collaborator events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collaborator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<SiteRole>,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct TycheOwnership {
+    db: Database,
+    rpc_url: String,
+    client: reqwest::Client,
+    cache_ttl: Duration,
+    holders: DashMap<String, (NftHolder, Instant)>,
+    broker: Option<Arc<HermesBroker>>,
+    registry: Option<Arc<AnchorClient>>,
+}
+
+impl TycheOwnership {
+    pub fn new(db: Database, rpc_url: String) -> Self {
+        Self {
+            db,
+            rpc_url,
+            client: reqwest::Client::new(),
+            cache_ttl: HOLDER_CACHE_TTL,
+            holders: DashMap::new(),
+            broker: None,
+            registry: None,
+        }
+    }
+
+    /// Take wallet-mode owners and editors from the registry for sites registered on it,
+    /// so `transfer_ownership` and `remove_editor` take effect on the next write
+    pub fn with_registry(mut self, registry: Arc<AnchorClient>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Also push collaborator notifications to live Hermes subscribers
+    pub fn with_broker(mut self, broker: Arc<HermesBroker>) -> Self {
+        self.broker = Some(broker);
+        self
+    }
+
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    pub fn get_events_collection(&self) -> Collection<SiteEvent> {
+        self.db.collection::<SiteEvent>("site_events")
+    }
+
+    async fn rpc(&self, method: &str, params: Value) -> Result<Value, String> {
+        let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: Value = self.client
+            .post(&self.rpc_url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("RPC unreachable: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid RPC response: {}", e))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(format!("RPC error: {}", error));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    /// Wallet holding `mint`, from its largest token account. Cached for the TTL.
+    pub async fn holder(&self, mint: &str) -> Result<NftHolder, String> {
+        if let Some(entry) = self.holders.get(mint) {
+            if entry.1.elapsed() < self.cache_ttl {
+                return Ok(entry.0.clone());
+            }
+        }
+
+        let holder = self.lookup_holder(mint).await?;
+        self.holders.insert(mint.to_string(), (holder.clone(), Instant::now()));
+        Ok(holder)
+    }
+
+    /// Drop a cached holder so the next check asks the chain
+    pub fn forget(&self, mint: &str) {
+        self.holders.remove(mint);
+    }
+
+    async fn lookup_holder(&self, mint: &str) -> Result<NftHolder, String> {
+        let largest = match self.rpc("getTokenLargestAccounts", serde_json::json!([mint])).await {
+            Ok(result) => result,
+            // The mint account itself is gone once the last token is burned and the mint closed
+            Err(e) if e.contains("could not find mint") => return Ok(NftHolder::Burned),
+            Err(e) => return Err(e),
+        };
+        let held: Vec<&Value> = largest["value"].as_array()
+            .ok_or("Invalid getTokenLargestAccounts response")?
+            .iter()
+            .filter(|account| account["amount"].as_str().is_some_and(|amount| amount != "0"))
+            .collect();
+        let account = match held.as_slice() {
+            [] => return Ok(NftHolder::Burned),
+            [account] if account["amount"] == "1" && account["decimals"] == 0 => account,
+            _ => return Err(format!("{} is not an NFT mint", mint)),
+        };
+
+        let address = account["address"].as_str().ok_or("Token account without an address")?;
+        let info = self.rpc("getAccountInfo", serde_json::json!([address, { "encoding": "jsonParsed" }])).await?;
+        info["value"]["data"]["parsed"]["info"]["owner"].as_str()
+            .map(|owner| NftHolder::Wallet(owner.to_string()))
+            .ok_or_else(|| format!("Could not read the owner of token account {}", address))
+    }
+
+    fn burned_error(mint: &str) -> ShadowError {
+        ShadowError::Gone(format!(
+            "The site's control NFT {} has been burned; an admin must recover the site",
+            mint
+        ))
+    }
+
+    /// Owner and on-chain editors of a wallet-mode site: the registry's for a site registered
+    /// on it, the record's owner and nobody else otherwise
+    async fn wallet_control(&self, site: &Site) -> Result<(String, Vec<String>), ShadowError> {
+        let on_chain = match &self.registry {
+            Some(registry) => registry.get_site(&site.program_address).await.map_err(ShadowError::Solana)?,
+            None => None,
+        };
+        Ok(match on_chain {
+            Some(account) => (account.owner.to_string(), account.editors.iter().map(|editor| editor.to_string()).collect()),
+            None => (site.owner_pubkey.clone(), Vec::new()),
+        })
+    }
+
+    /// The wallet-mode owner, which the record should carry after a registry transfer
+    pub async fn wallet_owner(&self, site: &Site) -> Result<String, ShadowError> {
+        Ok(self.wallet_control(site).await?.0)
+    }
+
+    /// Whether `wallet` controls the site: its owner in wallet mode, the NFT holder in NFT mode.
+    /// Fails with `Gone` when the control NFT was burned.
+    pub async fn is_controller(&self, site: &Site, wallet: &str) -> Result<bool, ShadowError> {
+        let mint = match (site.ownership_mode, &site.ownership_mint) {
+            (OwnershipMode::Nft, Some(mint)) => mint,
+            _ => return Ok(self.wallet_owner(site).await? == wallet),
+        };
+        match self.holder(mint).await.map_err(ShadowError::Solana)? {
+            NftHolder::Wallet(holder) => Ok(holder == wallet),
+            NftHolder::Burned => Err(Self::burned_error(mint)),
+        }
+    }
+
+    /// Effective controller for display. Lookup failures leave the wallet empty rather than failing the read.
+    pub async fn controller(&self, site: &Site) -> SiteController {
+        let mint = match (site.ownership_mode, &site.ownership_mint) {
+            (OwnershipMode::Nft, Some(mint)) => mint,
+            _ => {
+                return SiteController {
+                    mode: OwnershipMode::Wallet,
+                    wallet: Some(site.owner_pubkey.clone()),
+                    mint: None,
+                    nft_burned: false,
+                }
+            }
+        };
+        let holder = self.holder(mint).await
+            .inspect_err(|e| tracing::warn!("NFT holder lookup for {} failed: {}", mint, e))
+            .ok();
+        SiteController {
+            mode: OwnershipMode::Nft,
+            wallet: match &holder {
+                Some(NftHolder::Wallet(wallet)) => Some(wallet.clone()),
+                _ => None,
+            },
+            mint: Some(mint.clone()),
+            nft_burned: holder == Some(NftHolder::Burned),
+        }
+    }
+
+    /// The wallet's role on the site: Owner for its controller, else the higher of its
+    /// collaborator role and Editor for a registry editor
+    pub async fn role_of(&self, site: &Site, wallet: &str) -> Result<Option<SiteRole>, ShadowError> {
+        let editors = match (site.ownership_mode, &site.ownership_mint) {
+            (OwnershipMode::Nft, Some(_)) if self.is_controller(site, wallet).await? => return Ok(Some(SiteRole::Owner)),
+            (OwnershipMode::Nft, Some(_)) => Vec::new(),
+            _ => {
+                let (owner, editors) = self.wallet_control(site).await?;
+                if owner == wallet {
+                    return Ok(Some(SiteRole::Owner));
+                }
+                editors
+            }
+        };
+        let editor = editors.iter().any(|editor| editor == wallet).then_some(SiteRole::Editor);
+        Ok(site.collaborator(wallet).map(|c| c.role).max(editor))
+    }
+
+    /// Check `wallet` may perform `action` on the site and return its role
+    pub async fn authorize(&self, site: &Site, wallet: &str, action: SiteAction) -> Result<SiteRole, ShadowError> {
+        let required = action.required_role();
+        match self.role_of(site, wallet).await? {
+            Some(role) if role >= required => Ok(role),
+            _ => Err(ShadowError::Forbidden(format!("Requires the {} role on this site", required.as_str()))),
+        }
+    }
+
+    /// Hand control of a site to an NFT, or back to a wallet. `actor` must already be checked
+    /// as the current controller; in wallet mode it becomes the owner.
+    pub async fn set_mode(
+        &self,
+        site: &Site,
+        mode: OwnershipMode,
+        mint: Option<&str>,
+        actor: &str,
+    ) -> Result<(), ShadowError> {
+        let update = match (mode, mint) {
+            (OwnershipMode::Nft, Some(mint)) => {
+                self.forget(mint);
+                if self.holder(mint).await.map_err(ShadowError::BadRequest)? == NftHolder::Burned {
+                    return Err(Self::burned_error(mint));
+                }
+                doc! { "$set": { "ownership_mode": mode.as_str(), "ownership_mint": mint } }
+            }
+            (OwnershipMode::Nft, None) => {
+                return Err(ShadowError::BadRequest("NFT ownership needs a mint".to_string()));
+            }
+            (OwnershipMode::Wallet, _) => doc! {
+                "$set": { "ownership_mode": mode.as_str(), "owner_pubkey": actor },
+                "$unset": { "ownership_mint": "" },
+            },
+        };
+        let event = Self::event(site, "ownership_mode_changed", actor, mode, mint);
+        self.apply(site, update, event).await
+    }
+
+    /// Admin path for sites whose control NFT was burned: back to wallet mode under `new_owner`
+    pub async fn recover(&self, site: &Site, new_owner: &str, admin: &str, reason: Option<&str>) -> Result<(), ShadowError> {
+        let mint = match (site.ownership_mode, &site.ownership_mint) {
+            (OwnershipMode::Nft, Some(mint)) => mint,
+            _ => return Err(ShadowError::Conflict("Site is not controlled by an NFT".to_string())),
+        };
+        self.forget(mint);
+        if self.holder(mint).await.map_err(ShadowError::Solana)? != NftHolder::Burned {
+            return Err(ShadowError::Conflict("The control NFT still exists; its holder controls the site".to_string()));
+        }
+
+        let update = doc! {
+            "$set": { "ownership_mode": OwnershipMode::Wallet.as_str(), "owner_pubkey": new_owner },
+            "$unset": { "ownership_mint": "" },
+        };
+        let event = SiteEvent {
+            reason: reason.map(str::to_string),
+            ..Self::event(site, "ownership_recovered", admin, OwnershipMode::Wallet, Some(mint))
+        };
+        self.apply(site, update, event).await
+    }
+
+    /// Give `wallet` a collaborator role, or change the one it has. The owner can't be a collaborator.
+    pub async fn set_collaborator(&self, site: &Site, wallet: &str, role: SiteRole, actor: &str) -> Result<(), ShadowError> {
+        if role == SiteRole::Owner {
+            return Err(ShadowError::BadRequest("A site has a single owner; collaborators are editors or admins".to_string()));
+        }
+        if self.is_controller(site, wallet).await? {
+            return Err(ShadowError::Conflict("The site owner is not a collaborator".to_string()));
+        }
+
+        let mut collaborators = site.collaborators.clone();
+        let kind = match collaborators.iter_mut().find(|c| c.wallet == wallet) {
+            Some(existing) if existing.role == role => return Ok(()),
+            Some(existing) => {
+                existing.role = role;
+                "collaborator_role_changed"
+            }
+            None => {
+                collaborators.push(Collaborator {
+                    wallet: wallet.to_string(),
+                    role,
+                    added_by: actor.to_string(),
+                    added_at: Utc::now(),
+                });
+                "collaborator_added"
+            }
+        };
+        let update = doc! { "$set": { "collaborators": Self::collaborators_bson(&collaborators) } };
+        let event = SiteEvent {
+            collaborator: Some(wallet.to_string()),
+            role: Some(role),
+            ..Self::event(site, kind, actor, site.ownership_mode, site.ownership_mint.as_deref())
+        };
+        self.apply(site, update, event).await?;
+        self.notify(site, wallet, kind, format!("You are now an {} on {}", role.as_str(), Self::site_label(site))).await;
+        Ok(())
+    }
+
+    /// Take away a collaborator's access; effective on their next request
+    pub async fn remove_collaborator(&self, site: &Site, wallet: &str, actor: &str) -> Result<(), ShadowError> {
+        let Some(existing) = site.collaborator(wallet) else {
+            return Err(ShadowError::NotFound("Not a collaborator on this site".to_string()));
+        };
+        let remaining: Vec<Collaborator> = site.collaborators.iter().filter(|c| c.wallet != wallet).cloned().collect();
+        let update = doc! { "$set": { "collaborators": Self::collaborators_bson(&remaining) } };
+        let event = SiteEvent {
+            collaborator: Some(wallet.to_string()),
+            role: Some(existing.role),
+            ..Self::event(site, "collaborator_removed", actor, site.ownership_mode, site.ownership_mint.as_deref())
+        };
+        self.apply(site, update, event).await?;
+        self.notify(site, wallet, "collaborator_removed", format!(
+            "Your {} access to {} was removed",
+            existing.role.as_str(),
+            Self::site_label(site),
+        )).await;
+        Ok(())
+    }
+
+    fn collaborators_bson(collaborators: &[Collaborator]) -> Vec<mongodb::bson::Document> {
+        collaborators.iter()
+            .map(|c| doc! {
+                "wallet": &c.wallet,
+                "role": c.role.as_str(),
+                "added_by": &c.added_by,
+                "added_at": mongodb::bson::DateTime::from_chrono(c.added_at),
+            })
+            .collect()
+    }
+
+    fn site_label(site: &Site) -> &str {
+        site.primary_domain.as_deref()
+            .or(site.verified_domain.as_deref())
+            .unwrap_or(&site.program_address)
+    }
+
+    /// Leave the affected wallet a notification. Failures are logged; the change itself already landed.
+    async fn notify(&self, site: &Site, wallet: &str, kind: &str, message: String) {
+        let notification = OwnerNotification {
+            id: uuid::Uuid::new_v4().to_string(),
+            wallet: wallet.to_string(),
+            domain: Self::site_label(site).to_string(),
+            kind: kind.to_string(),
+            message,
+            read: false,
+            created_at: Utc::now(),
+        };
+        if let Err(e) = self.db.collection::<OwnerNotification>("notifications").insert_one(&notification, None).await {
+            tracing::warn!("Failed to notify {} about {}: {}", wallet, kind, e);
+            return;
+        }
+        if let Some(broker) = &self.broker {
+            if let Ok(json) = serde_json::to_string(&notification) {
+                broker.publish(&format!("wallet:{}", wallet), json).await;
+            }
+        }
+    }
+
+    fn event(site: &Site, kind: &str, actor: &str, mode: OwnershipMode, mint: Option<&str>) -> SiteEvent {
+        SiteEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            program_address: site.program_address.clone(),
+            kind: kind.to_string(),
+            actor: actor.to_string(),
+            mode,
+            mint: mint.map(str::to_string),
+            reason: None,
+            collaborator: None,
+            role: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    async fn apply(&self, site: &Site, mut update: mongodb::bson::Document, event: SiteEvent) -> Result<(), ShadowError> {
+        update.insert("$inc", doc! { "revision": 1_i64 });
+        update.get_document_mut("$set")
+            .expect("site control updates always $set")
+            .insert("updated_at", mongodb::bson::DateTime::now());
+
+        // Guard on the revision so a concurrent change can't be silently overwritten
+        let result = self.db.collection::<mongodb::bson::Document>("sites")
+            .update_one(crate::db::site_revision_filter(&site.program_address, site.revision), update, None)
+            .await?;
+        if result.matched_count == 0 {
+            return Err(ShadowError::Conflict("Site changed while updating who controls it; retry".to_string()));
+        }
+
+        self.get_events_collection().insert_one(event, None).await?;
+        Ok(())
+    }
+
+    /// Ownership event log for a site, oldest first
+    pub async fn list_events(&self, program_address: &str) -> Result<Vec<SiteEvent>, String> {
+        let options = mongodb::options::FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+        let cursor = self.get_events_collection()
+            .find(doc! { "program_address": program_address }, options).await
+            .map_err(|e| format!("Database error: {}", e))?;
+        cursor.try_collect().await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+}
@@ -35,12 +35,16 @@ pub fn percentage_change(old: f64, new: f64) -> f64 {
     }
 }
 
-/// Truncate string to max length with ellipsis
+/// Truncate string to max length (in chars) with ellipsis
 pub fn truncate_string(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    if s.chars().count() <= max_len {
         s.to_string()
+    } else if max_len < 3 {
+        // No room for an ellipsis
+        s.chars().take(max_len).collect()
     } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+        let head: String = s.chars().take(max_len - 3).collect();
+        format!("{}...", head)
     }
 }
 
@@ -116,6 +120,16 @@ pub fn format_bytes(bytes: u64) -> String {
     }
 }
 
+/// Escape text for interpolation into HTML or SVG markup
+pub fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
 /// Check if string is valid base58
 pub fn is_base58(s: &str) -> bool {
     s.chars().all(|c| {
@@ -123,6 +137,119 @@ pub fn is_base58(s: &str) -> bool {
     })
 }
 
+/// Keypair from base58 bytes or a JSON byte array (solana-keygen output)
+pub fn parse_keypair(value: &str) -> Result<solana_sdk::signature::Keypair, String> {
+    let value = value.trim();
+    let bytes = if value.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(value).map_err(|e| e.to_string())?
+    } else {
+        bs58::decode(value).into_vec().map_err(|e| e.to_string())?
+    };
+    solana_sdk::signature::Keypair::from_bytes(&bytes).map_err(|e| e.to_string())
+}
+
+/// Opaque keyset cursor for lists sorted newest first by (timestamp, id)
+pub fn encode_cursor(timestamp_ms: i64, id: &str) -> String {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    URL_SAFE_NO_PAD.encode(format!("{}:{}", timestamp_ms, id))
+}
+
+/// Decode a cursor from `encode_cursor` back into (timestamp, id)
+pub fn decode_cursor(cursor: &str) -> Result<(i64, String), String> {
+    let (timestamp, id) = split_cursor(cursor)?;
+    let timestamp = timestamp.parse().map_err(|_| "Invalid cursor".to_string())?;
+    Ok((timestamp, id))
+}
+
+/// Opaque keyset cursor for lists sorted highest first by (score, id)
+pub fn encode_score_cursor(score: f64, id: &str) -> String {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    URL_SAFE_NO_PAD.encode(format!("{}:{}", score, id))
+}
+
+/// Decode a cursor from `encode_score_cursor` back into (score, id)
+pub fn decode_score_cursor(cursor: &str) -> Result<(f64, String), String> {
+    let (score, id) = split_cursor(cursor)?;
+    let score = score.parse::<f64>()
+        .ok()
+        .filter(|score| score.is_finite())
+        .ok_or_else(|| "Invalid cursor".to_string())?;
+    Ok((score, id))
+}
+
+fn split_cursor(cursor: &str) -> Result<(String, String), String> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    let raw = URL_SAFE_NO_PAD.decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(|| "Invalid cursor".to_string())?;
+    let (key, id) = raw.split_once(':').ok_or_else(|| "Invalid cursor".to_string())?;
+    if id.is_empty() {
+        return Err("Invalid cursor".to_string());
+    }
+    Ok((key.to_string(), id.to_string()))
+}
+
+/// Filter matching documents after `cursor` in a `{ field: -1, _id: -1 }` sort
+pub fn cursor_filter(field: &str, cursor: &str) -> Result<mongodb::bson::Document, String> {
+    let (timestamp, id) = decode_cursor(cursor)?;
+    Ok(keyset_filter(field, mongodb::bson::DateTime::from_millis(timestamp), &id))
+}
+
+/// Filter matching documents after (`key`, `id`) in a `{ field: -1, _id: -1 }` sort
+pub fn keyset_filter(field: &str, key: impl Into<mongodb::bson::Bson>, id: &str) -> mongodb::bson::Document {
+    use mongodb::bson::doc;
+    let key = key.into();
+    doc! {
+        "$or": [
+            { field: { "$lt": key.clone() } },
+            { field: key, "_id": { "$lt": id } },
+        ]
+    }
+}
+
+/// Trim a page fetched with `limit + 1` rows back to `limit`. The extra row means
+/// another page exists, so the cursor for the last kept item is returned.
+pub fn next_page_cursor<T>(items: &mut Vec<T>, limit: i64, cursor: impl Fn(&T) -> String) -> Option<String> {
+    if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items.last().map(cursor)
+    } else {
+        None
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum StoredDateTime {
+    Bson(mongodb::bson::DateTime),
+    Text(chrono::DateTime<chrono::Utc>),
+}
+
+/// Deserialize a timestamp written either as a BSON date or as an RFC 3339 string.
+/// Lets plain `DateTime<Utc>` fields read documents updated with `bson::DateTime` values.
+pub fn deserialize_datetime<'de, D>(deserializer: D) -> Result<chrono::DateTime<chrono::Utc>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match <StoredDateTime as serde::Deserialize>::deserialize(deserializer)? {
+        StoredDateTime::Bson(dt) => dt.to_chrono(),
+        StoredDateTime::Text(dt) => dt,
+    })
+}
+
+/// Optional variant of `deserialize_datetime`
+pub fn deserialize_optional_datetime<'de, D>(deserializer: D) -> Result<Option<chrono::DateTime<chrono::Utc>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match <Option<StoredDateTime> as serde::Deserialize>::deserialize(deserializer)? {
+        Some(StoredDateTime::Bson(dt)) => Some(dt.to_chrono()),
+        Some(StoredDateTime::Text(dt)) => Some(dt),
+        None => None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,9 +282,135 @@ mod tests {
         assert_eq!(format_bytes(512), "512 B");
     }
     
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = encode_cursor(1_700_000_000_123, "a1b2:c3");
+        assert_eq!(decode_cursor(&cursor).unwrap(), (1_700_000_000_123, "a1b2:c3".to_string()));
+        assert!(decode_cursor("not a cursor!").is_err());
+        assert!(decode_cursor(&encode_cursor(5, "")).is_err());
+    }
+
+    #[test]
+    fn test_score_cursor_round_trip() {
+        let cursor = encode_score_cursor(0.75, "docs.shadow:/intro");
+        assert_eq!(decode_score_cursor(&cursor).unwrap(), (0.75, "docs.shadow:/intro".to_string()));
+        assert_eq!(decode_score_cursor(&encode_score_cursor(42.0, "a")).unwrap().0, 42.0);
+        assert!(decode_score_cursor(&encode_cursor(5, "a")).is_ok());
+        assert!(decode_score_cursor(&encode_score_cursor(f64::NAN, "a")).is_err());
+        assert!(decode_cursor(&encode_score_cursor(0.5, "a")).is_err());
+    }
+
+    #[test]
+    fn test_next_page_cursor_only_when_more_rows_exist() {
+        let mut items = vec![5, 4, 3];
+        assert_eq!(next_page_cursor(&mut items, 2, |n| n.to_string()), Some("4".to_string()));
+        assert_eq!(items, vec![5, 4]);
+
+        let mut items = vec![5, 4];
+        assert_eq!(next_page_cursor(&mut items, 2, |n| n.to_string()), None);
+        assert_eq!(items, vec![5, 4]);
+    }
+
+    #[test]
+    fn test_deserialize_datetime_accepts_bson_and_text() {
+        #[derive(serde::Deserialize)]
+        struct Stamped {
+            #[serde(deserialize_with = "deserialize_datetime")]
+            at: chrono::DateTime<chrono::Utc>,
+            #[serde(default, deserialize_with = "deserialize_optional_datetime")]
+            until: Option<chrono::DateTime<chrono::Utc>>,
+        }
+
+        let now = mongodb::bson::DateTime::now();
+        let from_bson: Stamped = mongodb::bson::from_document(mongodb::bson::doc! { "at": now, "until": now }).unwrap();
+        assert_eq!(from_bson.at, now.to_chrono());
+        assert_eq!(from_bson.until, Some(now.to_chrono()));
+
+        let from_json: Stamped = serde_json::from_str(r#"{"at":"2024-01-01T00:00:00Z"}"#).unwrap();
+        assert_eq!(from_json.at.timestamp(), 1_704_067_200);
+        assert!(from_json.until.is_none());
+    }
+
     #[test]
     fn test_is_base58() {
         assert!(is_base58("11111111111111111111111111111111"));
         assert!(!is_base58("invalid-base58-0OIl"));
     }
+
+    #[test]
+    fn test_parse_keypair_formats() {
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let keypair = Keypair::new();
+        let base58 = keypair.to_base58_string();
+        let json = serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap();
+        assert_eq!(parse_keypair(&base58).unwrap().pubkey(), keypair.pubkey());
+        assert_eq!(parse_keypair(&json).unwrap().pubkey(), keypair.pubkey());
+        assert!(parse_keypair("not a key").is_err());
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Read a format_bytes string back into an approximate byte count
+    fn parse_formatted_bytes(formatted: &str) -> f64 {
+        const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+        let (value, unit) = formatted.split_once(' ').unwrap();
+        let exponent = UNITS.iter().position(|u| *u == unit).unwrap() as i32;
+        value.parse::<f64>().unwrap() * 1024f64.powi(exponent)
+    }
+
+    proptest! {
+        #[test]
+        fn normalize_domain_never_panics(domain in ".{0,1000}") {
+            let _ = normalize_domain(&domain);
+        }
+
+        #[test]
+        fn format_bytes_is_monotonic(a in any::<u64>(), b in any::<u64>()) {
+            let (small, large) = if a <= b { (a, b) } else { (b, a) };
+            prop_assert!(
+                parse_formatted_bytes(&format_bytes(small)) <= parse_formatted_bytes(&format_bytes(large))
+            );
+        }
+
+        #[test]
+        fn percentage_change_round_trips(
+            old in prop_oneof![-1e6f64..-1e-3, 1e-3f64..1e6],
+            new in -1e6f64..1e6,
+        ) {
+            let change = percentage_change(old, new);
+            let restored = old + old * change / 100.0;
+            prop_assert!((restored - new).abs() <= 1e-6 * new.abs().max(1.0));
+        }
+
+        #[test]
+        fn truncate_string_respects_max_len(s in ".{0,200}", max_len in 0usize..100) {
+            prop_assert!(truncate_string(&s, max_len).chars().count() <= max_len);
+        }
+
+        #[test]
+        fn hash_content_is_deterministic(content in proptest::collection::vec(any::<u8>(), 0..1024)) {
+            prop_assert_eq!(hash_content(&content), hash_content(&content));
+        }
+
+        #[test]
+        fn parse_solana_address_requires_32_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..64)) {
+            let encoded = bs58::encode(&bytes).into_string();
+            match parse_solana_address(&encoded) {
+                Ok(parsed) => prop_assert_eq!(&parsed[..], &bytes[..]),
+                Err(_) => prop_assert_ne!(bytes.len(), 32),
+            }
+        }
+
+        #[test]
+        fn parse_solana_address_rejects_arbitrary_strings(s in ".{0,64}") {
+            if let Ok(parsed) = parse_solana_address(&s) {
+                prop_assert_eq!(bs58::decode(&s).into_vec().unwrap().len(), parsed.len());
+            }
+        }
+    }
 }
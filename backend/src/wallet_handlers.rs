@@ -3,16 +3,23 @@
 
 use actix_web::{web, HttpRequest, HttpResponse, Result as ActixResult};
 use crate::error::ShadowError;
-use crate::zeus::{ZeusWalletManager, CreateWalletRequest, ImportWalletRequest};
-use crate::poseidon::{PoseidonTransactionManager, SignTransactionRequest, CreateTransactionRequest};
-use crate::dionysus::DionysusTokenManager;
+use crate::zeus::{ZeusWalletManager, CreateWalletRequest, ExportMnemonicRequest, ImportMnemonicRequest, ImportWalletRequest};
+use crate::poseidon::{decode_transaction, BroadcastOptions, PoseidonTransactionManager, SignTransactionRequest, SubmitTransactionRequest, SubmitOutcome, CreateTransactionRequest, TransactionListQuery, TransactionStatus};
+use crate::hades::{self, SecuritySettings};
+use crate::dionysus::{DionysusTokenManager, EnsureAtaRequest, SIGNATURE_FEE_LAMPORTS};
 use crate::aphrodite::AphroditeNFTManager;
-use crate::hestia::{HestiaConnectionManager, ConnectDAppRequest};
+use crate::hestia::{HestiaConnectionManager, ConnectDAppRequest, ConnectionListQuery};
 use crate::plutus::PlutusPortfolioManager;
 use crate::ares::AresAuth;
+use crate::argus::{ArgusReputation, OriginRisk};
+use crate::config::ShadowConfig;
+use crate::apollo::ApolloValidator;
 use mongodb::Database;
 use serde::Deserialize;
 
+/// Origin recorded on cleanup transactions queued by the wallet itself
+const CLEANUP_ORIGIN: &str = "shadow://wallet/cleanup";
+
 // ========== Zeus (Wallet Management) ==========
 
 pub async fn create_wallet(
@@ -33,7 +40,7 @@ pub async fn create_wallet(
     let wallet = manager
         .create_wallet(&user_id, &body.name, &body.password)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Created().json(wallet))
 }
@@ -55,11 +62,65 @@ pub async fn import_wallet(
     let wallet = manager
         .import_wallet(&user_id, &body.name, &body.private_key, &body.password)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Created().json(wallet))
 }
 
+/// Import a wallet from a recovery phrase, as exported by `export_wallet_mnemonic`
+pub async fn import_wallet_mnemonic(
+    db: web::Data<Database>,
+    body: web::Json<ImportMnemonicRequest>,
+    solana_rpc: web::Data<String>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let user_id = verify_auth(&req, &ares)?;
+
+    let manager = ZeusWalletManager::new(
+        Arc::new(db.as_ref().clone()),
+        solana_rpc.to_string(),
+    );
+
+    let wallet = manager
+        .import_wallet_from_mnemonic(&user_id, &body.name, &body.mnemonic, &body.password)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Created().json(wallet))
+}
+
+/// Recovery phrase for one of the caller's wallets. Needs the wallet password;
+/// someone else's wallet is treated like a missing one.
+pub async fn export_wallet_mnemonic(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    body: web::Json<ExportMnemonicRequest>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let user_id = verify_auth(&req, &ares)?;
+    let wallet_id = path.into_inner();
+
+    let manager = ZeusWalletManager::new(
+        Arc::new(db.as_ref().clone()),
+        "".to_string(), // Not needed for this operation
+    );
+
+    if manager.get_wallet(&user_id, &wallet_id).await.map_err(ShadowError::BadRequest)?.is_none() {
+        return Err(ShadowError::Unauthorized);
+    }
+    let mnemonic = manager
+        .export_mnemonic(&wallet_id, &body.password)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "id": wallet_id,
+        "mnemonic": mnemonic,
+    })))
+}
+
 pub async fn list_wallets(
     db: web::Data<Database>,
     solana_rpc: web::Data<String>,
@@ -76,7 +137,7 @@ pub async fn list_wallets(
     let wallets = manager
         .list_wallets(&user_id)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(wallets))
 }
@@ -95,9 +156,9 @@ pub async fn get_active_wallet(
     );
 
     match manager.get_active_wallet(&user_id).await
-        .map_err(|e| ShadowError::BadRequest(e))? {
+        .map_err(ShadowError::BadRequest)? {
         Some(wallet) => Ok(HttpResponse::Ok().json(wallet)),
-        None => Err(ShadowError::NotFound("No active wallet".to_string()).into()),
+        None => Err(ShadowError::NotFound("No active wallet".to_string())),
     }
 }
 
@@ -122,7 +183,66 @@ pub async fn set_active_wallet(
     manager
         .set_active_wallet(&user_id, &body.wallet_id)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "success": true })))
+}
+
+#[derive(Deserialize)]
+pub struct RenameWalletRequest {
+    pub name: String,
+}
+
+/// Change a wallet's nickname. Someone else's wallet is treated like a missing one.
+pub async fn rename_wallet(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    body: web::Json<RenameWalletRequest>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let user_id = verify_auth(&req, &ares)?;
+    let wallet_id = path.into_inner();
+
+    let manager = ZeusWalletManager::new(
+        Arc::new(db.as_ref().clone()),
+        "".to_string(), // Not needed for this operation
+    );
+
+    if !manager.rename_wallet(&user_id, &wallet_id, &body.name).await.map_err(ShadowError::BadRequest)? {
+        return Err(ShadowError::Unauthorized);
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "id": wallet_id,
+        "name": body.name.trim(),
+    })))
+}
+
+/// Remove a wallet. The last remaining wallet cannot be deleted; deleting the active
+/// one makes another wallet active.
+pub async fn delete_wallet(
+    db: web::Data<Database>,
+    path: web::Path<String>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let user_id = verify_auth(&req, &ares)?;
+    let wallet_id = path.into_inner();
+
+    let manager = ZeusWalletManager::new(
+        Arc::new(db.as_ref().clone()),
+        "".to_string(), // Not needed for this operation
+    );
+
+    if manager.get_wallet(&user_id, &wallet_id).await.map_err(ShadowError::BadRequest)?.is_none() {
+        return Err(ShadowError::Unauthorized);
+    }
+    manager
+        .delete_wallet(&user_id, &wallet_id)
+        .await
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({ "success": true })))
 }
@@ -133,10 +253,17 @@ pub async fn create_transaction(
     db: web::Data<Database>,
     body: web::Json<CreateTransactionRequest>,
     ares: web::Data<AresAuth>,
+    argus: web::Data<ArgusReputation>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let user_id = verify_auth(&req, &ares)?;
 
+    // Transactions carry no dApp name, so only the origin itself is judged
+    let assessment = argus.assess(&body.dapp_origin, "");
+    if assessment.blocked {
+        return Err(ShadowError::Forbidden("dApp origin is blocked".to_string()));
+    }
+
     let manager = PoseidonTransactionManager::new(Arc::new(db.as_ref().clone()));
 
     let tx = manager
@@ -146,9 +273,10 @@ pub async fn create_transaction(
             &body.dapp_origin,
             &body.transaction_data,
             body.message.as_deref(),
+            assessment.risk,
         )
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Created().json(tx))
 }
@@ -156,7 +284,8 @@ pub async fn create_transaction(
 pub async fn sign_transaction(
     db: web::Data<Database>,
     body: web::Json<SignTransactionRequest>,
-    _solana_rpc: web::Data<String>,
+    solana_rpc: web::Data<String>,
+    config: web::Data<ShadowConfig>,
     ares: web::Data<AresAuth>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
@@ -165,35 +294,144 @@ pub async fn sign_transaction(
     let poseidon = PoseidonTransactionManager::new(Arc::new(db.as_ref().clone()));
 
     // Get transaction to find wallet_id
-    let _tx = poseidon
-        .get_transaction(&body.transaction_id, &user_id)
+    let tx = poseidon
+        .get_pending_transaction(&body.transaction_id, &user_id)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?
+        .map_err(ShadowError::BadRequest)?
         .ok_or_else(|| ShadowError::NotFound("Transaction not found".to_string()))?;
 
-    // Get private key (requires password)
-    // In production, get wallet_id from transaction and decrypt using password
-    // For now, return error - this needs proper implementation
-    return Err(ShadowError::BadRequest("Transaction signing requires wallet decryption - not yet implemented".to_string()).into());
+    // Decrypt the wallet's key (requires password)
+    let zeus = ZeusWalletManager::new(
+        Arc::new(db.as_ref().clone()),
+        solana_rpc.to_string(),
+    );
+    if zeus.get_wallet(&user_id, &tx.wallet_id).await.map_err(ShadowError::BadRequest)?.is_none() {
+        return Err(ShadowError::NotFound("Wallet not found".to_string()));
+    }
+    let private_key = zeus
+        .get_private_key(&tx.wallet_id, &body.password)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    let signed = poseidon
+        .sign_transaction(&body.transaction_id, &user_id, &private_key)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+    if !body.broadcast {
+        return Ok(HttpResponse::Ok().json(signed));
+    }
+
+    let transaction = signed.signed_transaction.as_deref()
+        .map(decode_transaction)
+        .transpose()?
+        .ok_or_else(|| ShadowError::BadRequest("Transaction was not signed".to_string()))?;
+    let response = poseidon
+        .sign_and_broadcast(
+            &solana_rpc,
+            &body.transaction_id,
+            &user_id,
+            &transaction,
+            &BroadcastOptions::from(&config.transactions),
+        )
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Send a signed transaction after a final simulation and fee-reserve check. A blocked
+/// submission returns 409 with the check results; retry with the matching override flag.
+pub async fn submit_transaction(
+    db: web::Data<Database>,
+    body: web::Json<SubmitTransactionRequest>,
+    solana_rpc: web::Data<String>,
+    config: web::Data<ShadowConfig>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let user_id = verify_auth(&req, &ares)?;
+
+    let settings = hades::get_security_settings(&db, &user_id).await?;
+    let reserve_lamports = settings.reserve_lamports(config.transactions.min_reserve_lamports);
+
+    let poseidon = PoseidonTransactionManager::new(Arc::new(db.as_ref().clone()));
+    let outcome = poseidon
+        .submit_transaction(&solana_rpc, &user_id, &body, reserve_lamports)
+        .await?;
+
+    Ok(match outcome {
+        SubmitOutcome::Submitted { .. } => HttpResponse::Ok().json(outcome),
+        SubmitOutcome::Blocked { .. } => HttpResponse::Conflict().json(outcome),
+    })
 }
 
 pub async fn get_pending_transactions(
     db: web::Data<Database>,
     ares: web::Data<AresAuth>,
+    query: web::Query<TransactionListQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let mut query = query.into_inner();
+    query.status = Some(TransactionStatus::Pending);
+    list_transactions(db, ares, query, req).await
+}
+
+/// Every transaction request for the caller, in any status
+pub async fn get_transactions(
+    db: web::Data<Database>,
+    ares: web::Data<AresAuth>,
+    query: web::Query<TransactionListQuery>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    list_transactions(db, ares, query.into_inner(), req).await
+}
+
+async fn list_transactions(
+    db: web::Data<Database>,
+    ares: web::Data<AresAuth>,
+    query: TransactionListQuery,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let user_id = verify_auth(&req, &ares)?;
+    let limit = ApolloValidator::validate_limit(Some(query.limit.unwrap_or(50)))?;
 
     let manager = PoseidonTransactionManager::new(Arc::new(db.as_ref().clone()));
 
     let transactions = manager
-        .get_pending_transactions(&user_id)
+        .list_transactions(&user_id, &query, limit)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(transactions))
 }
 
+// ========== Hades (Security Settings) ==========
+
+pub async fn get_security_settings(
+    db: web::Data<Database>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let user_id = verify_auth(&req, &ares)?;
+    let settings = hades::get_security_settings(&db, &user_id).await?;
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+pub async fn update_security_settings(
+    db: web::Data<Database>,
+    body: web::Json<SecuritySettings>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let user_id = verify_auth(&req, &ares)?;
+    if body.session_timeout_minutes == 0 {
+        return Err(ShadowError::BadRequest("session_timeout_minutes must be at least 1".to_string()));
+    }
+
+    hades::save_security_settings(&db, &user_id, &body).await?;
+    Ok(HttpResponse::Ok().json(body.into_inner()))
+}
+
 // ========== Dionysus (Tokens) ==========
 
 pub async fn get_token_balances(
@@ -214,11 +452,144 @@ pub async fn get_token_balances(
     let balances = manager
         .get_token_balances(&wallet_pubkey)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(balances))
 }
 
+/// Pre-create a recipient's token account; the caller pays for it
+pub async fn ensure_ata(
+    body: web::Json<EnsureAtaRequest>,
+    db: web::Data<Database>,
+    solana_rpc: web::Data<String>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let caller = verify_auth(&req, &ares)?;
+
+    let manager = DionysusTokenManager::new(
+        Arc::new(db.as_ref().clone()),
+        solana_rpc.to_string(),
+    );
+
+    let response = manager
+        .ensure_associated_token_account(&body.wallet_pubkey, &body.mint, &caller)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Empty token accounts the wallet could close to reclaim rent
+pub async fn get_cleanup_suggestions(
+    path: web::Path<String>,
+    db: web::Data<Database>,
+    solana_rpc: web::Data<String>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    verify_auth(&req, &ares)?;
+    let wallet_pubkey = path.into_inner();
+
+    let manager = DionysusTokenManager::new(
+        Arc::new(db.as_ref().clone()),
+        solana_rpc.to_string(),
+    );
+
+    let plan = manager
+        .plan_cleanup(&wallet_pubkey)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(plan))
+}
+
+#[derive(Deserialize)]
+pub struct BuildCleanupRequest {
+    pub wallet_id: String,
+    /// Subset of candidate accounts to close; all candidates when omitted
+    pub accounts: Option<Vec<String>>,
+}
+
+/// Queue close_account transactions for the user to sign
+pub async fn build_cleanup(
+    body: web::Json<BuildCleanupRequest>,
+    db: web::Data<Database>,
+    solana_rpc: web::Data<String>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    let user_id = verify_auth(&req, &ares)?;
+    let db = Arc::new(db.as_ref().clone());
+
+    let wallet = ZeusWalletManager::new(db.clone(), solana_rpc.to_string())
+        .get_wallet(&user_id, &body.wallet_id)
+        .await
+        .map_err(ShadowError::BadRequest)?
+        .ok_or_else(|| ShadowError::NotFound("Wallet not found".to_string()))?;
+
+    let dionysus = DionysusTokenManager::new(db.clone(), solana_rpc.to_string());
+    // Re-plan rather than trusting the client's list, balances may have moved
+    let plan = dionysus
+        .plan_cleanup(&wallet.pubkey)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    let candidates = match &body.accounts {
+        Some(accounts) => {
+            let mut selected = Vec::with_capacity(accounts.len());
+            for account in accounts {
+                let candidate = plan.candidates.iter().find(|c| &c.address == account).ok_or_else(|| {
+                    ShadowError::BadRequest(format!("{} is not a closable token account", account))
+                })?;
+                selected.push(candidate.clone());
+            }
+            selected
+        }
+        None => plan.candidates.clone(),
+    };
+    if candidates.is_empty() {
+        return Err(ShadowError::BadRequest("No token accounts to close".to_string()));
+    }
+
+    let transactions = dionysus
+        .build_cleanup(&wallet.pubkey, &candidates)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    let poseidon = PoseidonTransactionManager::new(db);
+    let total = transactions.len();
+    let mut pending = Vec::with_capacity(total);
+    for (i, transaction_data) in transactions.iter().enumerate() {
+        let message = format!("Close empty token accounts ({}/{})", i + 1, total);
+        let tx = poseidon
+            .create_transaction(
+                &user_id,
+                &wallet.id,
+                CLEANUP_ORIGIN,
+                transaction_data,
+                Some(&message),
+                OriginRisk::Verified,
+            )
+            .await
+            .map_err(ShadowError::BadRequest)?;
+        pending.push(tx);
+    }
+
+    let recoverable_lamports: u64 = candidates.iter().map(|c| c.lamports).sum();
+    let estimated_fee_lamports = total as u64 * SIGNATURE_FEE_LAMPORTS;
+    let net_reclaimed_lamports = recoverable_lamports.saturating_sub(estimated_fee_lamports);
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "transactions": pending,
+        "accounts_closed": candidates.len(),
+        "recoverable_lamports": recoverable_lamports,
+        "estimated_fee_lamports": estimated_fee_lamports,
+        "net_reclaimed_lamports": net_reclaimed_lamports,
+        "net_reclaimed_sol": net_reclaimed_lamports as f64 / 1_000_000_000.0,
+    })))
+}
+
 // ========== Aphrodite (NFTs) ==========
 
 pub async fn get_nfts(
@@ -239,7 +610,7 @@ pub async fn get_nfts(
     let nfts = manager
         .get_nfts(&wallet_pubkey)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(nfts))
 }
@@ -250,13 +621,19 @@ pub async fn connect_dapp(
     db: web::Data<Database>,
     body: web::Json<ConnectDAppRequest>,
     ares: web::Data<AresAuth>,
+    argus: web::Data<ArgusReputation>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let user_id = verify_auth(&req, &ares)?;
 
+    let assessment = argus.assess(&body.dapp_origin, &body.dapp_name);
+    if assessment.blocked {
+        return Err(ShadowError::Forbidden("dApp origin is blocked".to_string()));
+    }
+
     let manager = HestiaConnectionManager::new(Arc::new(db.as_ref().clone()));
 
-    let connection = manager
+    let mut connection = manager
         .connect_dapp(
             &user_id,
             &body.wallet_id,
@@ -266,7 +643,9 @@ pub async fn connect_dapp(
             body.requested_permissions.clone(),
         )
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
+    connection.risk = assessment.risk;
+    connection.risk_reason = assessment.reason;
 
     Ok(HttpResponse::Created().json(connection))
 }
@@ -274,16 +653,25 @@ pub async fn connect_dapp(
 pub async fn get_connections(
     db: web::Data<Database>,
     ares: web::Data<AresAuth>,
+    argus: web::Data<ArgusReputation>,
+    query: web::Query<ConnectionListQuery>,
     req: HttpRequest,
 ) -> ActixResult<HttpResponse, ShadowError> {
     let user_id = verify_auth(&req, &ares)?;
+    let limit = ApolloValidator::validate_limit(Some(query.limit.unwrap_or(50)))?;
 
     let manager = HestiaConnectionManager::new(Arc::new(db.as_ref().clone()));
 
-    let connections = manager
-        .get_connections(&user_id)
+    let mut connections = manager
+        .list_connections(&user_id, &query, limit)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
+
+    for connection in connections.items.iter_mut() {
+        let assessment = argus.assess(&connection.dapp_origin, &connection.dapp_name);
+        connection.risk = assessment.risk;
+        connection.risk_reason = assessment.reason;
+    }
 
     Ok(HttpResponse::Ok().json(connections))
 }
@@ -306,7 +694,7 @@ pub async fn disconnect_dapp(
     manager
         .disconnect_dapp(&user_id, &body.connection_id)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({ "success": true })))
 }
@@ -331,8 +719,37 @@ pub async fn get_portfolio(
     let portfolio = manager
         .get_portfolio(&wallet_pubkey)
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(portfolio))
+}
+
+#[derive(Deserialize)]
+pub struct PortfolioAtQuery {
+    /// YYYY-MM-DD; holdings are reconstructed as of the end of that day (UTC)
+    pub date: String,
+}
+
+/// Holdings on a past date, rebuilt from the nearest snapshot and the transaction history
+pub async fn get_portfolio_at(
+    path: web::Path<String>,
+    query: web::Query<PortfolioAtQuery>,
+    db: web::Data<Database>,
+    solana_rpc: web::Data<String>,
+    ares: web::Data<AresAuth>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    verify_auth(&req, &ares)?;
+    let wallet_pubkey = path.into_inner();
+    let date = chrono::NaiveDate::parse_from_str(&query.date, "%Y-%m-%d")
+        .map_err(|_| ShadowError::BadRequest("date must be YYYY-MM-DD".to_string()))?;
 
+    let manager = PlutusPortfolioManager::new(
+        Arc::new(db.as_ref().clone()),
+        solana_rpc.to_string(),
+    );
+
+    let portfolio = manager.portfolio_at(&wallet_pubkey, date).await?;
     Ok(HttpResponse::Ok().json(portfolio))
 }
 
@@ -358,25 +775,91 @@ pub async fn get_transaction_history(
     let history = manager
         .get_transaction_history(&wallet_pubkey, Some(limit))
         .await
-        .map_err(|e| ShadowError::BadRequest(e))?;
+        .map_err(ShadowError::BadRequest)?;
 
     Ok(HttpResponse::Ok().json(history))
 }
 
+// ========== Argus (Origin Reputation) ==========
+
+#[derive(Deserialize)]
+pub struct KnownDAppRequest {
+    pub origin: String,
+    pub name: String,
+    pub icon: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BlockOriginRequest {
+    pub origin: String,
+    #[serde(default = "default_true")]
+    pub blocked: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct AssessOriginQuery {
+    pub origin: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+pub async fn assess_origin(
+    argus: web::Data<ArgusReputation>,
+    query: web::Query<AssessOriginQuery>,
+) -> ActixResult<HttpResponse, ShadowError> {
+    Ok(HttpResponse::Ok().json(argus.assess(&query.origin, &query.name)))
+}
+
+pub async fn register_known_dapp(
+    argus: web::Data<ArgusReputation>,
+    body: web::Json<KnownDAppRequest>,
+    ares: web::Data<AresAuth>,
+    config: web::Data<ShadowConfig>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    verify_admin(&req, &ares, &config)?;
+
+    let entry = argus
+        .upsert_known_dapp(&body.origin, &body.name, body.icon.as_deref())
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(entry))
+}
+
+pub async fn block_origin(
+    argus: web::Data<ArgusReputation>,
+    body: web::Json<BlockOriginRequest>,
+    ares: web::Data<AresAuth>,
+    config: web::Data<ShadowConfig>,
+    req: HttpRequest,
+) -> ActixResult<HttpResponse, ShadowError> {
+    verify_admin(&req, &ares, &config)?;
+
+    let entry = argus
+        .set_blocked(&body.origin, body.blocked)
+        .await
+        .map_err(ShadowError::BadRequest)?;
+
+    Ok(HttpResponse::Ok().json(entry))
+}
+
+// Helper function to verify the caller is a configured admin wallet
+pub(crate) fn verify_admin(req: &HttpRequest, ares: &AresAuth, config: &ShadowConfig) -> Result<String, ShadowError> {
+    let wallet = verify_auth(req, ares)?;
+    if !config.auth.admin_wallets.contains(&wallet) {
+        return Err(ShadowError::Forbidden("Admin access required".to_string()));
+    }
+    Ok(wallet)
+}
+
 // Helper function to verify authentication
 fn verify_auth(req: &HttpRequest, ares: &AresAuth) -> Result<String, ShadowError> {
-    use crate::ares::AuthHeader;
-    
-    let auth_header = req.headers().get("X-Shadow-Auth")
-        .ok_or_else(|| ShadowError::Unauthorized)?
-        .to_str()
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    let auth = AuthHeader::from_header(auth_header)?;
-    auth.verify(ares)
-        .map_err(|_| ShadowError::Unauthorized)?;
-    
-    Ok(auth.wallet) // Using wallet as user_id for now
+    crate::ares::authenticate(req, ares)
 }
 
 use std::sync::Arc;
@@ -6,8 +6,17 @@ use actix_ws::Message;
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// Redis channel every instance publishes broker events on
+pub const REDIS_CHANNEL: &str = "shadow:hermes";
+
+/// Wait before re-subscribing after the Redis subscription drops
+const REDIS_RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+type Channels = tokio::sync::Mutex<HashMap<String, broadcast::Sender<String>>>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum HermesMessage {
@@ -31,20 +40,84 @@ pub enum HermesResponse {
     Error { message: String },
 }
 
+/// Where published events go beyond this instance's own sockets
+#[derive(Clone)]
+pub enum BrokerBackend {
+    InProcess,
+    /// Events are also published to Redis and relayed to the sockets of every other instance
+    Redis(redis::aio::ConnectionManager),
+}
+
+impl std::fmt::Debug for BrokerBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrokerBackend::InProcess => f.write_str("InProcess"),
+            BrokerBackend::Redis(_) => f.write_str("Redis"),
+        }
+    }
+}
+
+/// An event as it travels between instances
+#[derive(Debug, Serialize, Deserialize)]
+struct RelayedEvent {
+    origin: String,
+    topic: String,
+    message: String,
+}
+
 #[derive(Clone, Debug)]
 pub struct HermesBroker {
     // Broadcast channels for different topics
-    // In production, this would be backed by Redis or similar
-    channels: Arc<tokio::sync::Mutex<HashMap<String, broadcast::Sender<String>>>>,
+    channels: Arc<Channels>,
+    backend: BrokerBackend,
+    /// Tags events this instance published so its relay doesn't deliver them twice
+    instance_id: String,
 }
 
 impl HermesBroker {
     pub fn new() -> Self {
         Self {
             channels: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            backend: BrokerBackend::InProcess,
+            instance_id: uuid::Uuid::new_v4().simple().to_string(),
         }
     }
 
+    /// Share events through Redis when REDIS_URL is set, otherwise keep them in process
+    pub async fn from_env() -> Result<Self, String> {
+        match std::env::var("REDIS_URL") {
+            Ok(url) if !url.is_empty() => Self::connect(&url).await,
+            _ => Ok(Self::new()),
+        }
+    }
+
+    /// Broker that shares events with every other instance connected to the same Redis.
+    /// Returns once the relay is subscribed, so nothing published afterwards is missed.
+    pub async fn connect(redis_url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(redis_url)
+            .map_err(|e| format!("Invalid REDIS_URL: {}", e))?;
+        let publisher = client.get_connection_manager().await
+            .map_err(|e| format!("Failed to connect to Redis: {}", e))?;
+        let subscription = subscribe_redis(&client).await
+            .map_err(|e| format!("Failed to subscribe to Redis: {}", e))?;
+
+        let broker = Self {
+            backend: BrokerBackend::Redis(publisher),
+            ..Self::new()
+        };
+        tokio::spawn(relay_redis(
+            client,
+            subscription,
+            Arc::downgrade(&broker.channels),
+            broker.instance_id.clone(),
+        ));
+        Ok(broker)
+    }
+
+    pub fn backend(&self) -> &BrokerBackend {
+        &self.backend
+    }
+
     pub async fn subscribe(&self, topic: String) -> broadcast::Receiver<String> {
         let mut channels = self.channels.lock().await;
         let sender = channels.entry(topic.clone())
@@ -58,10 +131,88 @@ impl HermesBroker {
     }
 
     pub async fn publish(&self, topic: &str, message: String) {
-        let channels = self.channels.lock().await;
-        if let Some(sender) = channels.get(topic) {
-            let _ = sender.send(message);
+        let relayed = match &self.backend {
+            BrokerBackend::Redis(_) => serde_json::to_string(&RelayedEvent {
+                origin: self.instance_id.clone(),
+                topic: topic.to_string(),
+                message: message.clone(),
+            })
+            .ok(),
+            BrokerBackend::InProcess => None,
+        };
+
+        deliver(&self.channels, topic, message).await;
+
+        if let (BrokerBackend::Redis(publisher), Some(payload)) = (&self.backend, relayed) {
+            let mut publisher = publisher.clone();
+            let published: redis::RedisResult<i64> = redis::cmd("PUBLISH")
+                .arg(REDIS_CHANNEL)
+                .arg(payload)
+                .query_async(&mut publisher)
+                .await;
+            if let Err(e) = published {
+                tracing::warn!("Hermes failed to relay {} through Redis: {}", topic, e);
+            }
+        }
+    }
+}
+
+/// Hand a message to this instance's subscribers of `topic`
+async fn deliver(channels: &Channels, topic: &str, message: String) {
+    let channels = channels.lock().await;
+    if let Some(sender) = channels.get(topic) {
+        let _ = sender.send(message);
+    }
+}
+
+async fn subscribe_redis(client: &redis::Client) -> redis::RedisResult<redis::aio::PubSub> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(REDIS_CHANNEL).await?;
+    Ok(pubsub)
+}
+
+/// Deliver events other instances publish to local subscribers, re-subscribing whenever
+/// the connection drops. Stops once every broker sharing `channels` is gone.
+async fn relay_redis(
+    client: redis::Client,
+    subscription: redis::aio::PubSub,
+    channels: Weak<Channels>,
+    instance_id: String,
+) {
+    let mut subscription = Some(subscription);
+    loop {
+        let pubsub = match subscription.take() {
+            Some(pubsub) => pubsub,
+            None => match subscribe_redis(&client).await {
+                Ok(pubsub) => pubsub,
+                Err(e) => {
+                    tracing::warn!("Hermes failed to resubscribe to Redis: {}", e);
+                    if channels.strong_count() == 0 {
+                        return;
+                    }
+                    tokio::time::sleep(REDIS_RESUBSCRIBE_DELAY).await;
+                    continue;
+                }
+            },
+        };
+
+        let mut messages = pubsub.into_on_message();
+        while let Some(msg) = messages.next().await {
+            let Some(local) = channels.upgrade() else { return };
+            let event = msg
+                .get_payload::<String>()
+                .ok()
+                .and_then(|payload| serde_json::from_str::<RelayedEvent>(&payload).ok());
+            if let Some(event) = event.filter(|event| event.origin != instance_id) {
+                deliver(&local, &event.topic, event.message).await;
+            }
+        }
+
+        if channels.strong_count() == 0 {
+            return;
         }
+        tracing::warn!("Hermes lost its Redis subscription, reconnecting");
+        tokio::time::sleep(REDIS_RESUBSCRIBE_DELAY).await;
     }
 }
 
@@ -71,80 +222,284 @@ impl Default for HermesBroker {
     }
 }
 
+/// Topics named by a Subscribe/Unsubscribe message
+fn message_topics(wallet: Option<String>, program: Option<String>) -> Vec<String> {
+    wallet.map(|w| format!("wallet:{}", w))
+        .into_iter()
+        .chain(program.map(|p| format!("program:{}", p)))
+        .collect()
+}
+
+/// Relay one broker topic to a socket until it unsubscribes or disconnects
+async fn forward_topic(
+    topic: String,
+    mut receiver: broadcast::Receiver<String>,
+    events: mpsc::UnboundedSender<HermesResponse>,
+) {
+    loop {
+        match receiver.recv().await {
+            Ok(message) => {
+                let data = serde_json::from_str(&message)
+                    .unwrap_or(serde_json::Value::String(message));
+                if events.send(HermesResponse::Event { topic: topic.clone(), data }).is_err() {
+                    break;
+                }
+            }
+            // A slow socket misses events rather than stalling the broker
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
 pub async fn ws_handler(
     req: HttpRequest,
     body: web::Payload,
+    broker: web::Data<HermesBroker>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let (response, mut session, mut msg_stream) = actix_ws::handle(&req, body)?;
+    let broker = broker.into_inner();
 
     actix_web::rt::spawn(async move {
-        let mut subscriptions: Vec<String> = Vec::new();
-        
-        while let Some(Ok(msg)) = msg_stream.next().await {
-            match msg {
-                Message::Text(text) => {
-                    // Parse message
-                    if let Ok(hermes_msg) = serde_json::from_str::<HermesMessage>(&text) {
-                        match hermes_msg {
-                            HermesMessage::Subscribe { wallet, program } => {
-                                if let Some(w) = wallet {
-                                    let topic = format!("wallet:{}", w);
-                                    subscriptions.push(topic.clone());
-                                    let response = HermesResponse::Subscribed { topic };
-                                    if let Ok(json) = serde_json::to_string(&response) {
-                                        let _ = session.text(json).await;
-                                    }
-                                }
-                                if let Some(p) = program {
-                                    let topic = format!("program:{}", p);
-                                    subscriptions.push(topic.clone());
-                                    let response = HermesResponse::Subscribed { topic };
-                                    if let Ok(json) = serde_json::to_string(&response) {
-                                        let _ = session.text(json).await;
-                                    }
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel::<HermesResponse>();
+        let mut subscriptions: HashMap<String, tokio::task::JoinHandle<()>> = HashMap::new();
+
+        loop {
+            let responses = tokio::select! {
+                msg = msg_stream.next() => match msg {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<HermesMessage>(&text) {
+                        Ok(HermesMessage::Subscribe { wallet, program }) => {
+                            let mut responses = Vec::new();
+                            for topic in message_topics(wallet, program) {
+                                if !subscriptions.contains_key(&topic) {
+                                    let receiver = broker.subscribe(topic.clone()).await;
+                                    let forwarder = actix_web::rt::spawn(
+                                        forward_topic(topic.clone(), receiver, events_tx.clone()),
+                                    );
+                                    subscriptions.insert(topic.clone(), forwarder);
                                 }
+                                responses.push(HermesResponse::Subscribed { topic });
                             }
-                            HermesMessage::Unsubscribe { wallet, program } => {
-                                if let Some(w) = wallet {
-                                    let topic = format!("wallet:{}", w);
-                                    subscriptions.retain(|t| t != &topic);
-                                    let response = HermesResponse::Unsubscribed { topic };
-                                    if let Ok(json) = serde_json::to_string(&response) {
-                                        let _ = session.text(json).await;
-                                    }
-                                }
-                                if let Some(p) = program {
-                                    let topic = format!("program:{}", p);
-                                    subscriptions.retain(|t| t != &topic);
-                                    let response = HermesResponse::Unsubscribed { topic };
-                                    if let Ok(json) = serde_json::to_string(&response) {
-                                        let _ = session.text(json).await;
+                            responses
+                        }
+                        Ok(HermesMessage::Unsubscribe { wallet, program }) => {
+                            message_topics(wallet, program).into_iter()
+                                .map(|topic| {
+                                    if let Some(forwarder) = subscriptions.remove(&topic) {
+                                        forwarder.abort();
                                     }
-                                }
-                            }
-                            HermesMessage::Ping => {
-                                let response = HermesResponse::Pong;
-                                if let Ok(json) = serde_json::to_string(&response) {
-                                    let _ = session.text(json).await;
-                                }
-                            }
+                                    HermesResponse::Unsubscribed { topic }
+                                })
+                                .collect()
                         }
-                    } else {
-                        // Echo for debugging (remove in production)
-                        let response = HermesResponse::Error {
+                        Ok(HermesMessage::Ping) => vec![HermesResponse::Pong],
+                        Err(_) => vec![HermesResponse::Error {
                             message: "Invalid message format".to_string(),
-                        };
-                        if let Ok(json) = serde_json::to_string(&response) {
-                            let _ = session.text(json).await;
-                        }
-                    }
+                        }],
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                },
+                Some(event) = events_rx.recv() => vec![event],
+            };
+
+            for response in responses {
+                if let Ok(json) = serde_json::to_string(&response) {
+                    let _ = session.text(json).await;
                 }
-                Message::Close(_) => break,
-                _ => {}
             }
         }
+
+        for forwarder in subscriptions.into_values() {
+            forwarder.abort();
+        }
     });
 
     Ok(response)
 }
 
+/// Event type published on a program's topic when its live content changes
+pub const CONTENT_UPDATED_EVENT: &str = "content_updated";
+
+/// A dev page with the live reload script injected
+#[derive(Debug, Clone)]
+pub struct DevReloadPage {
+    pub html: Vec<u8>,
+    /// Policies to send as Content-Security-Policy headers; the page's own meta
+    /// policies, moved out of the document and extended to allow the script
+    pub csp: Vec<String>,
+}
+
+/// Script that subscribes to the program topic and reloads the page when new content goes live
+pub fn reload_script(program_address: &str, nonce: &str) -> String {
+    let program = serde_json::to_string(program_address)
+        .unwrap_or_default()
+        .replace("</", "<\\/");
+    format!(
+        "<script nonce=\"{nonce}\">(function(){{var p={program};\
+         var u=(location.protocol===\"https:\"?\"wss://\":\"ws://\")+location.host+\"/api/ws\";\
+         function c(){{var s=new WebSocket(u);\
+         s.onopen=function(){{s.send(JSON.stringify({{Subscribe:{{wallet:null,program:p}}}}))}};\
+         s.onmessage=function(m){{try{{var e=JSON.parse(m.data).Event;\
+         if(e&&e.data&&e.data.type===\"{event}\")location.reload()}}catch(_){{}}}};\
+         s.onclose=function(){{setTimeout(c,1000)}}}}c()}})();</script>",
+        nonce = nonce,
+        program = program,
+        event = CONTENT_UPDATED_EVENT,
+    )
+}
+
+/// Inject the reload script into a page served in dev mode. `ws_source` is the
+/// origin the script connects back to, e.g. `ws://localhost:8080`. Pages that
+/// declare a strict policy in a meta tag keep it, with just the script and its
+/// socket allowed. Non-UTF-8 bodies are returned untouched.
+pub fn inject_reload_script(html: &[u8], program_address: &str, nonce: &str, ws_source: &str) -> DevReloadPage {
+    let Ok(html) = std::str::from_utf8(html) else {
+        return DevReloadPage { html: html.to_vec(), csp: Vec::new() };
+    };
+    let mut html = html.to_string();
+    let csp = take_meta_csp(&mut html).iter()
+        .map(|policy| allow_reload(policy, nonce, ws_source))
+        .collect();
+
+    let lower = html.to_ascii_lowercase();
+    let at = lower.find("</head>")
+        .or_else(|| lower.find("</body>"))
+        .unwrap_or(html.len());
+    html.insert_str(at, &reload_script(program_address, nonce));
+    DevReloadPage { html: html.into_bytes(), csp }
+}
+
+/// Remove `<meta http-equiv="Content-Security-Policy">` tags, returning their policies
+fn take_meta_csp(html: &mut String) -> Vec<String> {
+    let mut policies = Vec::new();
+    let mut from = 0;
+    loop {
+        let lower = html.to_ascii_lowercase();
+        let Some(start) = lower[from..].find("<meta").map(|i| from + i) else { break };
+        let Some(end) = lower[start..].find('>').map(|i| start + i + 1) else { break };
+        let equiv = attribute(&html[start..end], "http-equiv");
+        if !equiv.is_some_and(|v| v.trim().eq_ignore_ascii_case("content-security-policy")) {
+            from = end;
+            continue;
+        }
+        if let Some(content) = attribute(&html[start..end], "content") {
+            policies.push(
+                content.replace("&quot;", "\"").replace("&#39;", "'").replace("&amp;", "&"),
+            );
+        }
+        html.replace_range(start..end, "");
+        from = start;
+    }
+    policies
+}
+
+/// Value of a quoted or bare attribute inside a single tag
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search = 0;
+    while let Some(at) = lower[search..].find(name).map(|i| search + i) {
+        search = at + name.len();
+        if !lower[..at].ends_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(rest) = lower[search..].trim_start().strip_prefix('=') else { continue };
+        let value = &tag[tag.len() - rest.trim_start().len()..];
+        return match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].find(quote).map(|end| &value[1..1 + end]),
+            _ => value.split(|c: char| c.is_ascii_whitespace() || c == '>').next(),
+        };
+    }
+    None
+}
+
+/// Extend a page policy so the nonce'd reload script can run and reach the socket
+fn allow_reload(policy: &str, nonce: &str, ws_source: &str) -> String {
+    let mut directives: Vec<Vec<String>> = policy.split(';')
+        .map(|d| d.split_ascii_whitespace().map(str::to_string).collect::<Vec<_>>())
+        .filter(|d| !d.is_empty())
+        .collect();
+
+    let nonce_source = format!("'nonce-{}'", nonce);
+    // 'unsafe-inline' is ignored once a nonce is listed, so leave inline-friendly policies alone
+    let allows_inline = |sources: &[String]| {
+        sources.iter().any(|s| s.eq_ignore_ascii_case("'unsafe-inline'"))
+            && !sources.iter().any(|s| s.starts_with("'nonce-") || s.starts_with("'sha"))
+    };
+    allow_source(&mut directives, &["script-src-elem", "script-src"], &nonce_source, allows_inline);
+    allow_source(&mut directives, &["connect-src"], ws_source, |sources| sources.iter().any(|s| s == "*"));
+
+    directives.iter()
+        .map(|d| d.join(" "))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Add `source` to each named directive, or to a copy of default-src when none is set
+fn allow_source(directives: &mut Vec<Vec<String>>, names: &[&str], source: &str, already_allowed: impl Fn(&[String]) -> bool) {
+    let is = |d: &Vec<String>, name: &str| d[0].eq_ignore_ascii_case(name);
+    let mut found = false;
+    for directive in directives.iter_mut().filter(|d| names.iter().any(|name| is(d, name))) {
+        found = true;
+        if !already_allowed(&directive[1..]) {
+            directive.retain(|s| !s.eq_ignore_ascii_case("'none'"));
+            directive.push(source.to_string());
+        }
+    }
+    if found {
+        return;
+    }
+    let Some(default) = directives.iter().find(|d| is(d, "default-src")).cloned() else { return };
+    if already_allowed(&default[1..]) {
+        return;
+    }
+    let mut directive = vec![names[names.len() - 1].to_string()];
+    directive.extend(default[1..].iter().filter(|s| !s.eq_ignore_ascii_case("'none'")).cloned());
+    directive.push(source.to_string());
+    directives.push(directive);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+
+    #[test]
+    fn test_script_goes_before_head_close() {
+        let page = inject_reload_script(b"<html><head><title>x</title></head><body></body></html>", PROGRAM, "abc", "ws://localhost");
+        let html = String::from_utf8(page.html).unwrap();
+        assert!(html.contains("<script nonce=\"abc\">"));
+        assert!(html.find("<script").unwrap() < html.find("</head>").unwrap());
+        assert!(html.contains(PROGRAM));
+        assert!(page.csp.is_empty());
+
+        let page = inject_reload_script(b"<p>bare</p>", PROGRAM, "abc", "ws://localhost");
+        assert!(String::from_utf8(page.html).unwrap().starts_with("<p>bare</p><script"));
+        let binary = [0xff, 0xfe, 0x00];
+        assert_eq!(inject_reload_script(&binary, PROGRAM, "abc", "ws://localhost").html, binary);
+    }
+
+    #[test]
+    fn test_strict_meta_policy_moves_to_header_with_nonce() {
+        let html = b"<head><META http-equiv='Content-Security-Policy' content=\"default-src 'self'; img-src *\"></head>";
+        let page = inject_reload_script(html, PROGRAM, "abc", "ws://localhost:8080");
+        assert!(!String::from_utf8(page.html).unwrap().to_ascii_lowercase().contains("http-equiv"));
+        assert_eq!(page.csp, vec![
+            "default-src 'self'; img-src *; script-src 'self' 'nonce-abc'; connect-src 'self' ws://localhost:8080"
+        ]);
+    }
+
+    #[test]
+    fn test_policy_extension_keeps_page_rules() {
+        let policy = "script-src 'none'; connect-src https://rpc.example; object-src 'none'";
+        assert_eq!(
+            allow_reload(policy, "n", "wss://shadow"),
+            "script-src 'nonce-n'; connect-src https://rpc.example wss://shadow; object-src 'none'"
+        );
+        // Inline scripts already pass, and a nonce would switch 'unsafe-inline' off
+        let policy = "script-src 'self' 'unsafe-inline'; connect-src *";
+        assert_eq!(allow_reload(policy, "n", "wss://shadow"), policy);
+        assert_eq!(allow_reload("img-src 'self'", "n", "wss://shadow"), "img-src 'self'");
+    }
+}
@@ -8,7 +8,19 @@ use solana_sdk::{
     signature::{Keypair, Signer},
 };
 use std::sync::Arc;
-use sha2::{Sha256, Digest};
+use sha2::Sha256;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use bip39::{Language, Mnemonic, Seed};
+
+/// Longest wallet nickname accepted by `rename_wallet`, in characters
+pub const MAX_WALLET_NAME_LEN: usize = 64;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const NONCE_LEN: usize = 12;
+/// GCM authentication tag appended to the ciphertext
+const TAG_LEN: usize = 16;
+/// Keys stored before AES-GCM are the bare 64-byte keypair XORed with the derived key
+const LEGACY_KEY_LEN: usize = 64;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Wallet {
@@ -17,7 +29,7 @@ pub struct Wallet {
     pub user_id: String, // User identifier (can be email, username, etc.)
     pub pubkey: String, // Solana public key
     pub name: String, // Wallet nickname
-    pub encrypted_private_key: String, // AES-encrypted private key
+    pub encrypted_private_key: String, // AES-256-GCM, hex nonce || ciphertext || tag
     pub salt: String, // Salt for encryption (hex)
     pub is_active: bool, // Active wallet for user
     pub created_at: DateTime,
@@ -39,6 +51,18 @@ pub struct ImportWalletRequest {
     pub password: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportMnemonicRequest {
+    pub name: String,
+    pub mnemonic: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportMnemonicRequest {
+    pub password: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WalletResponse {
     pub id: String,
@@ -46,6 +70,7 @@ pub struct WalletResponse {
     pub name: String,
     pub is_active: bool,
     pub balance: Option<u64>, // SOL balance in lamports
+    pub last_known_session_id: Option<String>, // Browser session to resume on reconnect
 }
 
 pub struct ZeusWalletManager {
@@ -122,6 +147,7 @@ impl ZeusWalletManager {
 
         // Get balance
         let balance = self.get_balance(&pubkey).await.ok();
+        let last_known_session_id = None; // A fresh keypair has never browsed
 
         Ok(WalletResponse {
             id: wallet.id,
@@ -129,6 +155,7 @@ impl ZeusWalletManager {
             name: wallet.name,
             is_active: wallet.is_active,
             balance,
+            last_known_session_id,
         })
     }
 
@@ -192,6 +219,7 @@ impl ZeusWalletManager {
             .map_err(|e| format!("Database error: {}", e))?;
 
         let balance = self.get_balance(&pubkey).await.ok();
+        let last_known_session_id = self.last_known_session_id(&pubkey).await;
 
         Ok(WalletResponse {
             id: wallet.id,
@@ -199,6 +227,7 @@ impl ZeusWalletManager {
             name: wallet.name,
             is_active: wallet.is_active,
             balance,
+            last_known_session_id,
         })
     }
 
@@ -216,12 +245,14 @@ impl ZeusWalletManager {
         while let Some(wallet) = cursor.try_next().await
             .map_err(|e| format!("Database error: {}", e))? {
             let balance = self.get_balance(&wallet.pubkey).await.ok();
+            let last_known_session_id = self.last_known_session_id(&wallet.pubkey).await;
             wallets.push(WalletResponse {
                 id: wallet.id,
                 pubkey: wallet.pubkey,
                 name: wallet.name,
                 is_active: wallet.is_active,
                 balance,
+                last_known_session_id,
             });
         }
 
@@ -238,18 +269,28 @@ impl ZeusWalletManager {
             .await
             .map_err(|e| format!("Database error: {}", e))? {
             let balance = self.get_balance(&wallet.pubkey).await.ok();
+            let last_known_session_id = self.last_known_session_id(&wallet.pubkey).await;
             Ok(Some(WalletResponse {
                 id: wallet.id,
                 pubkey: wallet.pubkey,
                 name: wallet.name,
                 is_active: wallet.is_active,
                 balance,
+                last_known_session_id,
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// A wallet owned by `user_id`
+    pub async fn get_wallet(&self, user_id: &str, wallet_id: &str) -> Result<Option<Wallet>, String> {
+        self.get_collection()
+            .find_one(doc! { "_id": wallet_id, "user_id": user_id }, None)
+            .await
+            .map_err(|e| format!("Database error: {}", e))
+    }
+
     /// Set active wallet
     pub async fn set_active_wallet(
         &self,
@@ -259,7 +300,7 @@ impl ZeusWalletManager {
         let collection = self.get_collection();
 
         // Verify wallet belongs to user
-        let wallet = collection
+        let _wallet = collection
             .find_one(doc! { "_id": wallet_id, "user_id": user_id }, None)
             .await
             .map_err(|e| format!("Database error: {}", e))?
@@ -288,6 +329,27 @@ impl ZeusWalletManager {
         Ok(())
     }
 
+    /// Change a wallet's nickname. Returns false when `user_id` has no wallet `wallet_id`.
+    pub async fn rename_wallet(&self, user_id: &str, wallet_id: &str, name: &str) -> Result<bool, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Wallet name cannot be empty".to_string());
+        }
+        if name.chars().count() > MAX_WALLET_NAME_LEN {
+            return Err(format!("Wallet name must be at most {} characters", MAX_WALLET_NAME_LEN));
+        }
+
+        let result = self.get_collection()
+            .update_one(
+                doc! { "_id": wallet_id, "user_id": user_id },
+                doc! { "$set": { "name": name, "updated_at": DateTime::now() } },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        Ok(result.matched_count > 0)
+    }
+
     /// Decrypt and get private key (requires password)
     pub async fn get_private_key(
         &self,
@@ -301,7 +363,52 @@ impl ZeusWalletManager {
             .map_err(|e| format!("Database error: {}", e))?
             .ok_or_else(|| "Wallet not found".to_string())?;
 
-        self.decrypt_private_key(&wallet.encrypted_private_key, &wallet.salt, password)
+        let key_bytes = self.decrypt_private_key(&wallet.encrypted_private_key, &wallet.salt, password, &wallet.pubkey)?;
+        if is_legacy_key(&wallet.encrypted_private_key) {
+            self.migrate_legacy_key(&wallet, &key_bytes, password).await;
+        }
+        Ok(key_bytes)
+    }
+
+    /// Re-encrypt a legacy XOR key with AES-GCM now that the password is known. Only
+    /// replaces the blob it read, and a failed write just leaves it for the next unlock.
+    async fn migrate_legacy_key(&self, wallet: &Wallet, key_bytes: &[u8], password: &str) {
+        let (encrypted, salt) = match self.encrypt_private_key(key_bytes, password) {
+            Ok(encrypted) => encrypted,
+            Err(e) => {
+                tracing::warn!("Could not re-encrypt legacy key for wallet {}: {}", wallet.id, e);
+                return;
+            }
+        };
+        let result = self.get_collection()
+            .update_one(
+                doc! { "_id": &wallet.id, "encrypted_private_key": &wallet.encrypted_private_key },
+                doc! { "$set": { "encrypted_private_key": encrypted, "salt": salt, "updated_at": DateTime::now() } },
+                None,
+            )
+            .await;
+        if let Err(e) = result {
+            tracing::warn!("Could not migrate legacy key for wallet {}: {}", wallet.id, e);
+        }
+    }
+
+    /// Recovery phrase for a wallet's key (requires password). See `keypair_to_mnemonic`.
+    pub async fn export_mnemonic(&self, wallet_id: &str, password: &str) -> Result<String, String> {
+        let key_bytes = self.get_private_key(wallet_id, password).await?;
+        keypair_to_mnemonic(&key_bytes)
+    }
+
+    /// Import a wallet from a recovery phrase. See `keypair_from_mnemonic`.
+    pub async fn import_wallet_from_mnemonic(
+        &self,
+        user_id: &str,
+        name: &str,
+        mnemonic: &str,
+        password: &str,
+    ) -> Result<WalletResponse, String> {
+        let key_bytes = keypair_from_mnemonic(mnemonic)?;
+        self.import_wallet(user_id, name, &bs58::encode(key_bytes).into_string(), password)
+            .await
     }
 
     /// Delete wallet
@@ -350,7 +457,8 @@ impl ZeusWalletManager {
         Ok(())
     }
 
-    /// Encrypt private key using AES-256-GCM with PBKDF2
+    /// Encrypt private key using AES-256-GCM with a PBKDF2-derived key.
+    /// Returns hex `nonce || ciphertext || tag` and the hex salt.
     fn encrypt_private_key(
         &self,
         key_bytes: &[u8],
@@ -362,57 +470,61 @@ impl ZeusWalletManager {
             .collect::<Vec<u8>>();
         let salt_hex = hex::encode(&salt);
 
-        // Derive key using PBKDF2
-        let mut key = [0u8; 32];
-        pbkdf2::pbkdf2::<hmac::Hmac<Sha256>>(
-            password.as_bytes(),
-            &salt,
-            100000, // iterations
-            &mut key,
-        );
-
-        // For simplicity, we'll use a basic encryption
-        // In production, use proper AES-GCM with nonce
-        let encrypted = self.simple_xor_encrypt(key_bytes, &key);
-        let encrypted_hex = hex::encode(&encrypted);
-
-        Ok((encrypted_hex, salt_hex))
+        let cipher = Aes256Gcm::new(&derive_key(password, &salt)?.into());
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = cipher
+            .encrypt(&Nonce::from(nonce), key_bytes)
+            .map_err(|_| "Encryption failed".to_string())?;
+
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend_from_slice(&ciphertext);
+        Ok((hex::encode(&encrypted), salt_hex))
     }
 
-    /// Decrypt private key
+    /// Decrypt private key; fails when the password is wrong or the ciphertext was altered.
+    /// Legacy XOR keys carry no tag, so they only count as decrypted when they come out as
+    /// the keypair for `pubkey`.
     fn decrypt_private_key(
         &self,
         encrypted_hex: &str,
         salt_hex: &str,
         password: &str,
+        pubkey: &str,
     ) -> Result<Vec<u8>, String> {
         let encrypted = hex::decode(encrypted_hex)
             .map_err(|_| "Invalid encrypted key format".to_string())?;
         let salt = hex::decode(salt_hex)
             .map_err(|_| "Invalid salt format".to_string())?;
+        if encrypted.len() == LEGACY_KEY_LEN {
+            let key = derive_key(password, &salt)?;
+            let decrypted: Vec<u8> = encrypted.iter()
+                .enumerate()
+                .map(|(i, &b)| b ^ key[i % key.len()])
+                .collect();
+            return match Keypair::from_bytes(&decrypted) {
+                Ok(keypair) if keypair.pubkey().to_string() == pubkey => Ok(decrypted),
+                _ => Err("Invalid password or corrupted key".to_string()),
+            };
+        }
+        if encrypted.len() < NONCE_LEN + TAG_LEN {
+            return Err("Invalid encrypted key format".to_string());
+        }
 
-        // Derive key
-        let mut key = [0u8; 32];
-        pbkdf2::pbkdf2::<hmac::Hmac<Sha256>>(
-            password.as_bytes(),
-            &salt,
-            100000,
-            &mut key,
-        );
-
-        let decrypted = self.simple_xor_decrypt(&encrypted, &key);
-        Ok(decrypted)
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| "Invalid encrypted key format".to_string())?;
+        let cipher = Aes256Gcm::new(&derive_key(password, &salt)?.into());
+        cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| "Invalid password or corrupted key".to_string())
     }
 
-    fn simple_xor_encrypt(&self, data: &[u8], key: &[u8]) -> Vec<u8> {
-        data.iter()
-            .enumerate()
-            .map(|(i, &b)| b ^ key[i % key.len()])
-            .collect()
-    }
-
-    fn simple_xor_decrypt(&self, data: &[u8], key: &[u8]) -> Vec<u8> {
-        self.simple_xor_encrypt(data, key) // XOR is symmetric
+    /// Latest Chronos session for the wallet; lookup failures just mean no resume hint
+    async fn last_known_session_id(&self, pubkey: &str) -> Option<String> {
+        crate::chronos::ChronosManager::new((*self.db).clone())
+            .latest_session_id(pubkey)
+            .await
+            .ok()
+            .flatten()
     }
 
     /// Get SOL balance for a pubkey
@@ -425,4 +537,143 @@ impl ZeusWalletManager {
 
 use futures_util::TryStreamExt;
 
+/// Whether a stored key is still in the pre-AES-GCM XOR format
+fn is_legacy_key(encrypted_hex: &str) -> bool {
+    encrypted_hex.len() == LEGACY_KEY_LEN * 2
+}
+
+/// 32-byte AES key from the wallet password and its salt
+fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<hmac::Hmac<Sha256>>(password.as_bytes(), salt, PBKDF2_ITERATIONS, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+/// 24-word BIP-39 phrase whose entropy is the keypair's 32-byte secret key.
+/// This encodes the key itself, so it restores only through `keypair_from_mnemonic`;
+/// it is not a seed phrase for BIP-44 derivation in other wallets.
+pub fn keypair_to_mnemonic(key_bytes: &[u8]) -> Result<String, String> {
+    let keypair = Keypair::from_bytes(key_bytes).map_err(|_| "Invalid keypair bytes".to_string())?;
+    let mnemonic = Mnemonic::from_entropy(keypair.secret().as_bytes(), Language::English)
+        .map_err(|e| format!("Mnemonic encoding failed: {}", e))?;
+    Ok(mnemonic.into_phrase())
+}
+
+/// 64-byte keypair for a recovery phrase. 24-word phrases are read back as
+/// `keypair_to_mnemonic` writes them; shorter ones are treated as wallet seed phrases
+/// and use the first 32 bytes of their BIP-39 seed, as `solana-keygen recover` does.
+pub fn keypair_from_mnemonic(phrase: &str) -> Result<Vec<u8>, String> {
+    let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mnemonic = Mnemonic::from_phrase(&phrase, Language::English)
+        .map_err(|e| format!("Invalid mnemonic: {}", e))?;
+    let secret = match mnemonic.entropy() {
+        entropy if entropy.len() == 32 => entropy.to_vec(),
+        _ => Seed::new(&mnemonic, "").as_bytes()[..32].to_vec(),
+    };
+
+    let secret = ed25519_dalek::SecretKey::from_bytes(&secret)
+        .map_err(|_| "Invalid mnemonic key".to_string())?;
+    let public = ed25519_dalek::PublicKey::from(&secret);
+    let mut key_bytes = secret.as_bytes().to_vec();
+    key_bytes.extend_from_slice(public.as_bytes());
+    Ok(key_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn manager() -> ZeusWalletManager {
+        let client = mongodb::Client::with_uri_str("mongodb://127.0.0.1:1").await.unwrap();
+        ZeusWalletManager::new(Arc::new(client.database("zeus_offline")), "http://127.0.0.1:1".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_private_key_round_trip() {
+        let zeus = manager().await;
+        let keypair = Keypair::new();
+        let (encrypted, salt) = zeus.encrypt_private_key(&keypair.to_bytes(), "hunter2").unwrap();
 
+        let stored = hex::decode(&encrypted).unwrap();
+        assert_eq!(stored.len(), NONCE_LEN + keypair.to_bytes().len() + TAG_LEN);
+        assert_eq!(zeus.decrypt_private_key(&encrypted, &salt, "hunter2", &keypair.pubkey().to_string()).unwrap(), keypair.to_bytes());
+
+        // A fresh nonce every time, so the same key never encrypts the same way twice
+        let (again, _) = zeus.encrypt_private_key(&keypair.to_bytes(), "hunter2").unwrap();
+        assert_ne!(again[..NONCE_LEN * 2], encrypted[..NONCE_LEN * 2]);
+    }
+
+    #[test]
+    fn test_mnemonic_round_trip() {
+        let keypair = solana_sdk::signer::keypair::keypair_from_seed(&[7u8; 32]).unwrap();
+        let phrase = keypair_to_mnemonic(&keypair.to_bytes()).unwrap();
+        assert_eq!(phrase.split(' ').count(), 24);
+        assert_eq!(keypair_from_mnemonic(&phrase).unwrap(), keypair.to_bytes());
+        // Spacing and case don't matter
+        let sloppy = format!("  {}\n", phrase.to_uppercase().replace(' ', "   "));
+        assert_eq!(keypair_from_mnemonic(&sloppy).unwrap(), keypair.to_bytes());
+
+        assert!(keypair_to_mnemonic(&keypair.to_bytes()[..32]).is_err());
+        assert!(keypair_from_mnemonic("not a real phrase").is_err());
+        // Last word carries the checksum
+        let (rest, _) = phrase.rsplit_once(' ').unwrap();
+        assert!(keypair_from_mnemonic(&format!("{} zoo", rest)).is_err());
+    }
+
+    #[test]
+    fn test_twelve_word_phrases_use_the_seed() {
+        // Not one of ours, so it goes through the seed with no passphrase
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let key_bytes = keypair_from_mnemonic(phrase).unwrap();
+        let keypair = Keypair::from_bytes(&key_bytes).unwrap();
+        let seed = Seed::new(&Mnemonic::from_phrase(phrase, Language::English).unwrap(), "");
+        assert_eq!(keypair.secret().as_bytes(), &seed.as_bytes()[..32]);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password_fails_to_decrypt() {
+        let zeus = manager().await;
+        let keypair = Keypair::new();
+        let (encrypted, salt) = zeus.encrypt_private_key(&keypair.to_bytes(), "hunter2").unwrap();
+        let err = zeus.decrypt_private_key(&encrypted, &salt, "hunter3", &keypair.pubkey().to_string()).unwrap_err();
+        assert_eq!(err, "Invalid password or corrupted key");
+    }
+
+    #[tokio::test]
+    async fn test_tampered_ciphertext_fails_to_decrypt() {
+        let zeus = manager().await;
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey().to_string();
+        let (encrypted, salt) = zeus.encrypt_private_key(&keypair.to_bytes(), "hunter2").unwrap();
+        let mut bytes = hex::decode(&encrypted).unwrap();
+        bytes[NONCE_LEN] ^= 0x01;
+        assert!(zeus.decrypt_private_key(&hex::encode(&bytes), &salt, "hunter2", &pubkey).is_err());
+        assert!(zeus.decrypt_private_key(&encrypted[..20], &salt, "hunter2", &pubkey).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_xor_key_decrypts() {
+        let zeus = manager().await;
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey().to_string();
+        // How keys were stored before AES-GCM
+        let salt = [9u8; 16];
+        let key = derive_key("hunter2", &salt).unwrap();
+        let legacy: Vec<u8> = keypair.to_bytes().iter()
+            .enumerate()
+            .map(|(i, &b)| b ^ key[i % key.len()])
+            .collect();
+        let (legacy, salt) = (hex::encode(legacy), hex::encode(salt));
+        assert!(is_legacy_key(&legacy));
+
+        assert_eq!(zeus.decrypt_private_key(&legacy, &salt, "hunter2", &pubkey).unwrap(), keypair.to_bytes());
+        // With no tag to check, a wrong password shows up as the wrong keypair
+        let err = zeus.decrypt_private_key(&legacy, &salt, "hunter3", &pubkey).unwrap_err();
+        assert_eq!(err, "Invalid password or corrupted key");
+        assert!(zeus.decrypt_private_key(&legacy, &salt, "hunter2", &Keypair::new().pubkey().to_string()).is_err());
+
+        let (encrypted, _) = zeus.encrypt_private_key(&keypair.to_bytes(), "hunter2").unwrap();
+        assert!(!is_legacy_key(&encrypted));
+    }
+}
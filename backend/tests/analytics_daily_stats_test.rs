@@ -0,0 +1,106 @@
+// Integration tests for aggregating Prometheus daily stats
+mod common;
+
+use actix_web::{test, web, App};
+use chrono::{NaiveDate, TimeZone, Utc};
+use serde_json::Value;
+use shadow_backend::clock::{SharedClock, TestClock};
+use shadow_backend::handlers;
+use shadow_backend::prometheus::{PrometheusAnalytics, MAX_DAILY_STATS};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use std::time::Duration;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+macro_rules! daily_app {
+    ($prometheus:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($prometheus))
+                .route("/api/analytics/{domain}/daily", web::get().to(handlers::get_daily_stats)),
+        )
+        .await
+    };
+}
+
+fn daily(domain: &str, query: &str) -> test::TestRequest {
+    test::TestRequest::get().uri(&format!("/api/analytics/{}/daily?{}", domain, query))
+}
+
+#[actix_web::test]
+async fn test_rejects_bad_ranges_and_dates() {
+    let db = common::offline_db().await;
+    let prometheus = PrometheusAnalytics::new(db.clone());
+    assert!(prometheus.aggregate_daily_stats("shop.shadow", "03/01/2026").await.is_err());
+
+    let app = daily_app!(prometheus);
+    for query in ["from=03/01/2026", "to=2026-02-30", "from=2026-03-05&to=2026-03-01"] {
+        let resp = test::call_service(&app, daily("shop.shadow", query).to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", query);
+    }
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_recent_domains_are_aggregated_per_day() {
+    let db = common::test_db().await;
+    let clock = Arc::new(TestClock::new(Utc.with_ymd_and_hms(2026, 3, 1, 20, 0, 0).unwrap()));
+    let prometheus = PrometheusAnalytics::new(db.clone()).with_clock(clock.clone() as SharedClock);
+    let domain = format!("daily-{}.shadow", Pubkey::new_unique().to_string().to_lowercase());
+    let program = Pubkey::new_unique().to_string();
+    let (regular, drive_by) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+
+    // The regular visits twice on the 1st; the drive-by is last seen on the 2nd
+    prometheus.record_visit(&domain, &program, &regular, 30.0).await.unwrap();
+    prometheus.record_visit(&domain, &program, &regular, 90.0).await.unwrap();
+    clock.advance(HOUR * 6);
+    prometheus.record_visit(&domain, &program, &drive_by, 10.0).await.unwrap();
+
+    assert!(prometheus.domains_visited_since(Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap()).await.unwrap().contains(&domain));
+    assert!(prometheus.aggregate_recent_daily_stats().await.unwrap() >= 1);
+    // Running again replaces the days rather than adding to them
+    prometheus.aggregate_recent_daily_stats().await.unwrap();
+
+    let app = daily_app!(prometheus);
+    let body: Value = test::call_and_read_body_json(&app, daily(&domain, "from=2026-02-01&to=2026-03-31").to_request()).await;
+    assert_eq!(body["daily_stats"], serde_json::json!([
+        { "date": "2026-03-01", "visits": 2, "unique_visitors": 1, "avg_time_spent": 120.0 },
+        { "date": "2026-03-02", "visits": 1, "unique_visitors": 1, "avg_time_spent": 10.0 },
+    ]));
+
+    // Defaults to the 30 days up to today, and the range is inclusive
+    let body: Value = test::call_and_read_body_json(&app, daily(&domain, "").to_request()).await;
+    assert_eq!(body["to"], "2026-03-02");
+    assert_eq!(body["from"], "2026-02-01");
+    assert_eq!(body["daily_stats"].as_array().unwrap().len(), 2);
+    let body: Value = test::call_and_read_body_json(&app, daily(&domain, "from=2026-03-02&to=2026-03-02").to_request()).await;
+    assert_eq!(body["daily_stats"][0]["date"], "2026-03-02");
+    assert_eq!(body["daily_stats"].as_array().unwrap().len(), 1);
+
+    let resp = test::call_service(&app, daily("nobody.shadow", "").to_request()).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_daily_stats_keep_the_latest_days() {
+    let db = common::test_db().await;
+    let prometheus = PrometheusAnalytics::new(db.clone());
+    let domain = format!("capped-{}.shadow", Pubkey::new_unique().to_string().to_lowercase());
+    prometheus.record_visit(&domain, &Pubkey::new_unique().to_string(), &Pubkey::new_unique().to_string(), 5.0).await.unwrap();
+
+    let first = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+    let days: Vec<String> = (0..MAX_DAILY_STATS as i64 + 5)
+        .map(|offset| (first + chrono::Duration::days(offset)).format("%Y-%m-%d").to_string())
+        .collect();
+    // Newest first, so the cap has to keep by date rather than by arrival
+    for day in days.iter().rev() {
+        prometheus.aggregate_daily_stats(&domain, day).await.unwrap();
+    }
+
+    let stats = prometheus.get_analytics(&domain).await.unwrap().unwrap().daily_stats;
+    assert_eq!(stats.len(), MAX_DAILY_STATS as usize);
+    assert_eq!(stats[0].date, days[5]);
+    assert_eq!(stats.last().unwrap().date, *days.last().unwrap());
+}
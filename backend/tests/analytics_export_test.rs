@@ -0,0 +1,148 @@
+// Integration tests for Prometheus analytics exports
+mod common;
+
+use actix_web::{test, web, App};
+use chrono::{TimeZone, Utc};
+use mongodb::bson::doc;
+use mongodb::{Client, Database};
+use serde_json::Value;
+use shadow_backend::artemis::ArtemisRateLimiter;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::clock::{SharedClock, TestClock};
+use shadow_backend::handlers;
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::prometheus::PrometheusAnalytics;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+use std::time::Duration;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+macro_rules! export_app {
+    ($db:expr, $prometheus:expr, $requests_per_minute:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($prometheus))
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(ArtemisRateLimiter::new($requests_per_minute)))
+                .app_data(web::Data::new(common::test_config()))
+                .route("/api/analytics/{domain}/export", web::get().to(handlers::export_analytics)),
+        )
+        .await
+    };
+}
+
+fn export(domain: &str, query: &str, wallet: &Keypair) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(&format!("/api/analytics/{}/export?{}", domain, query))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+}
+
+/// Like `common::offline_db`, but gives up on the missing server quickly
+async fn unreachable_db() -> Database {
+    Client::with_uri_str("mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100")
+        .await
+        .unwrap()
+        .database("shadow_offline")
+}
+
+#[actix_web::test]
+async fn test_rejects_bad_ranges() {
+    let db = common::offline_db().await;
+    let app = export_app!(db, PrometheusAnalytics::new(db.clone()), 600);
+    let owner = Keypair::new();
+
+    for query in ["from=03/01/2026", "to=2026-02-30", "from=2026-03-05&to=2026-03-01"] {
+        let resp = test::call_service(&app, export("shop.shadow", query, &owner).to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", query);
+    }
+    let req = test::TestRequest::get().uri("/api/analytics/shop.shadow/export").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_web::test]
+async fn test_exports_have_their_own_rate_limit() {
+    // Each export costs ten units, so a 25/minute budget allows two
+    let db = unreachable_db().await;
+    let app = export_app!(db, PrometheusAnalytics::new(db.clone()), 25);
+    let owner = Keypair::new();
+    let mut errors = Vec::new();
+    for _ in 0..3 {
+        let body: Value = test::call_and_read_body_json(&app, export("shop.shadow", "format=json", &owner).to_request()).await;
+        errors.push(body["error"].as_str().unwrap().to_string());
+    }
+    assert!(errors[..2].iter().all(|e| !e.contains("Rate limit")), "{:?}", errors);
+    assert!(errors[2].contains("Rate limit exceeded"), "{:?}", errors);
+}
+
+/// Four days of traffic from 2026-03-01; the clock is left on the 5th
+async fn seed_traffic(db: &Database, domain: &str) -> Arc<TestClock> {
+    let clock = Arc::new(TestClock::new(Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()));
+    let prometheus = PrometheusAnalytics::new(db.clone()).with_clock(clock.clone() as SharedClock);
+    let program = Pubkey::new_unique().to_string();
+    let (regular, drive_by) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+    for day in 0..4 {
+        prometheus.record_visit(domain, &program, &regular, 30.0).await.unwrap();
+        prometheus.record_visit(domain, &program, &regular, 90.0).await.unwrap();
+        prometheus.record_visit(domain, &program, &drive_by, 0.0).await.unwrap();
+        for load_time_ms in [100.0, 200.0, 300.0, 400.0] {
+            prometheus.record_performance(domain, load_time_ms * (day + 1) as f64, 50.0, 1024, 4).await.unwrap();
+        }
+        clock.advance(DAY);
+    }
+    clock
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_csv_and_ndjson_exports() {
+    let db = common::test_db().await;
+    let owner = Keypair::new();
+    let domain = "agency.shadow";
+    OlympusCA::new(db.clone())
+        .register_domain(domain, &owner.pubkey().to_string(), &Pubkey::new_unique().to_string())
+        .await
+        .unwrap();
+    let clock = seed_traffic(&db, domain).await;
+    let prometheus = PrometheusAnalytics::new(db.clone()).with_clock(clock as SharedClock).with_retention_days(30);
+    let app = export_app!(db, prometheus, 600);
+
+    // Only the two days asked for, after the header row
+    let resp = test::call_service(&app, export(domain, "from=2026-03-02&to=2026-03-03", &owner).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/csv; charset=utf-8");
+    assert!(resp.headers().get("x-shadow-export-truncated").is_none());
+    let csv = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    let lines: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert_eq!(lines, vec![
+        "date,visits,unique_visitors,avg_time_spent,bounce_rate,p50_load_ms,p90_load_ms",
+        "2026-03-02,3,2,40,0.5,400,800",
+        "2026-03-03,3,2,40,0.5,600,1200",
+    ]);
+
+    // One JSON record per line, and the range is clipped to the 30-day retention
+    let resp = test::call_service(&app, export(domain, "from=2025-01-01&to=2026-03-31&format=json", &owner).to_request()).await;
+    assert_eq!(resp.headers().get("content-type").unwrap(), "application/x-ndjson");
+    let truncated = resp.headers().get("x-shadow-export-truncated").unwrap().to_str().unwrap().to_string();
+    assert!(truncated.starts_with("2026-02-04;"), "{}", truncated);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.ends_with('\n'));
+    let records: Vec<Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0]["date"], "2026-03-01");
+    assert_eq!(records[3]["p90_load_ms"], 1600.0);
+
+    // Someone else's domain
+    let resp = test::call_service(&app, export(domain, "", &Keypair::new()).to_request()).await;
+    assert_eq!(resp.status(), 403);
+
+    let audited = db.collection::<mongodb::bson::Document>("analytics_exports")
+        .count_documents(doc! { "domain": domain, "wallet": owner.pubkey().to_string() }, None)
+        .await
+        .unwrap();
+    assert_eq!(audited, 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
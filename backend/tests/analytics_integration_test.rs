@@ -0,0 +1,98 @@
+// Integration tests for Prometheus analytics summary deduplication
+mod common;
+
+use actix_web::{test, web, App};
+use futures_util::future::join_all;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::artemis::ArtemisRateLimiter;
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::prometheus::PrometheusAnalytics;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+
+const DOMAIN: &str = "popular.shadow";
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_concurrent_get_analytics_computes_summary_once() {
+    let db = common::test_db().await;
+
+    let prometheus = Arc::new(PrometheusAnalytics::new(db.clone()));
+    let metrics = Arc::new(MetricsCollector::new());
+    let owner = Keypair::new();
+    OlympusCA::new(db.clone())
+        .register_domain(DOMAIN, &owner.pubkey().to_string(), &Pubkey::new_unique().to_string())
+        .await
+        .unwrap();
+
+    for wallet in ["wallet_a", "wallet_b", "wallet_a"] {
+        prometheus.record_visit(DOMAIN, "program", wallet, 30.0).await.unwrap();
+    }
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(Arc::clone(&prometheus)))
+            .app_data(web::Data::from(Arc::clone(&metrics)))
+            .app_data(web::Data::new(OlympusCA::new(db.clone())))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(ArtemisRateLimiter::new(60)))
+            .route("/api/analytics/{domain}", web::get().to(handlers::get_analytics)),
+    )
+    .await;
+
+    let requests = (0..20).map(|_| {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/analytics/{}", DOMAIN))
+            .to_request();
+        test::call_service(&app, req)
+    });
+    for resp in join_all(requests).await {
+        assert_eq!(resp.status(), 200);
+    }
+
+    let snapshot = metrics.get_metrics();
+    assert_eq!(snapshot.analytics_summaries_executed, 1);
+    assert_eq!(snapshot.analytics_summaries_skipped, 19);
+
+    // Only the owner may force a refresh
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/analytics/{}?force=true", DOMAIN))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    assert_eq!(metrics.get_metrics().analytics_summaries_executed, 1);
+
+    // force=true bypasses the freshness guard
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/analytics/{}?force=true", DOMAIN))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["unique_visitors"], 2);
+    assert_eq!(metrics.get_metrics().analytics_summaries_executed, 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_forced_refresh_needs_a_signed_request() {
+    let db = common::offline_db().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(PrometheusAnalytics::new(db.clone())))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .app_data(web::Data::new(OlympusCA::new(db.clone())))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(ArtemisRateLimiter::new(60)))
+            .route("/api/analytics/{domain}", web::get().to(handlers::get_analytics)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/analytics/{}?force=true", DOMAIN))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
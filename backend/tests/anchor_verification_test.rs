@@ -0,0 +1,263 @@
+// Integration tests for reading registry sites and profiles through their PDAs
+use base64::{engine::general_purpose, Engine as _};
+use sha2::{Digest, Sha256};
+use shadow_backend::anchor_client::AnchorClient;
+use solana_sdk::pubkey::Pubkey;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn borsh_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// `Site` account bytes as the registry program stores them
+fn site_account(owner: &Pubkey, program: &Pubkey, name: &str) -> Vec<u8> {
+    versioned_site_account(owner, program, name, 0)
+}
+
+fn versioned_site_account(owner: &Pubkey, program: &Pubkey, name: &str, version: u32) -> Vec<u8> {
+    edited_site_account(owner, program, name, version, &[])
+}
+
+/// Space taken by the editor list at its full size of five
+const EDITORS_LEN: usize = 4 + 32 * 5;
+
+fn edited_site_account(owner: &Pubkey, program: &Pubkey, name: &str, version: u32, editors: &[Pubkey]) -> Vec<u8> {
+    let mut data = Sha256::digest(b"account:Site")[..8].to_vec();
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(program.as_ref());
+    borsh_string(&mut data, name);
+    borsh_string(&mut data, "A site in the shadows");
+    borsh_string(&mut data, "bafysite");
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data.extend_from_slice(&1_700_000_500i64.to_le_bytes());
+    data.extend_from_slice(&version.to_le_bytes());
+    data.extend_from_slice(&(editors.len() as u32).to_le_bytes());
+    for editor in editors {
+        data.extend_from_slice(editor.as_ref());
+    }
+    data.resize(8 + 32 + 32 + 104 + 504 + 104 + 8 + 8 + 4 + EDITORS_LEN, 0);
+    data
+}
+
+/// `SiteVersion` account bytes as the registry program stores them
+fn site_version_account(site: &Pubkey, version: u32, storage_cid: &str) -> Vec<u8> {
+    let mut data = Sha256::digest(b"account:SiteVersion")[..8].to_vec();
+    data.extend_from_slice(site.as_ref());
+    data.extend_from_slice(&version.to_le_bytes());
+    borsh_string(&mut data, storage_cid);
+    data.extend_from_slice(&(1_700_000_000 + version as i64 * 60).to_le_bytes());
+    data.resize(8 + 32 + 4 + 104 + 8, 0);
+    data
+}
+
+fn ui_account(data: &[u8], owner: &Pubkey) -> serde_json::Value {
+    serde_json::json!({
+        "data": [general_purpose::STANDARD.encode(data), "base64"],
+        "executable": false,
+        "lamports": 5_000_000,
+        "owner": owner.to_string(),
+        "rentEpoch": 0,
+        "space": data.len(),
+    })
+}
+
+/// `Profile` account bytes as the profiles program stores them
+fn profile_account(wallet: &Pubkey, username: Option<&str>) -> Vec<u8> {
+    let mut data = Sha256::digest(b"account:Profile")[..8].to_vec();
+    data.extend_from_slice(wallet.as_ref());
+    borsh_string(&mut data, "ipfs://bafyprofile");
+    data.push(1);
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data.extend_from_slice(&1_700_000_500i64.to_le_bytes());
+    data.extend_from_slice(&12u64.to_le_bytes());
+    data.extend_from_slice(&7u64.to_le_bytes());
+    match username {
+        Some(name) => {
+            data.push(1);
+            borsh_string(&mut data, name);
+        }
+        None => data.push(0),
+    }
+    data.resize(8 + 32 + 104 + 1 + 8 + 8 + 8 + 8 + 37, 0);
+    data
+}
+
+/// Mock RPC answering the version check the client makes before its first query
+async fn rpc_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "solana-core": "1.18.26", "feature-set": 0 },
+            "id": 1
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Serve `data`, owned by `owner`, at `address`
+async fn mount_account(server: &MockServer, address: &Pubkey, data: Vec<u8>, owner: &Pubkey) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "method": "getAccountInfo",
+            "params": [address.to_string()]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": ui_account(&data, owner) },
+            "id": 1
+        })))
+        .mount(server)
+        .await;
+}
+
+/// Every other account does not exist
+async fn mount_missing_accounts(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": null },
+            "id": 1
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_site_is_verified_against_its_owner() {
+    let (registry, profiles) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (owner, program, squatted) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), registry, profiles);
+
+    mount_account(&rpc, &anchor.site_pda(&program), site_account(&owner, &program, "Shade"), &registry).await;
+    // Account data that looks like a site but is not owned by the registry
+    mount_account(&rpc, &anchor.site_pda(&squatted), site_account(&owner, &squatted, "Fake"), &Pubkey::new_unique())
+        .await;
+    mount_missing_accounts(&rpc).await;
+
+    let site = anchor.verify_site_registration(&program.to_string(), &owner.to_string()).await.unwrap().unwrap();
+    assert_eq!((site.owner, site.program_address), (owner, program));
+    assert_eq!(site.name, "Shade");
+    assert_eq!(site.description, "A site in the shadows");
+    assert_eq!(site.storage_cid, "bafysite");
+    assert_eq!((site.created_at, site.updated_at), (1_700_000_000, 1_700_000_500));
+
+    let stranger = Pubkey::new_unique().to_string();
+    assert!(anchor.verify_site_registration(&program.to_string(), &stranger).await.unwrap().is_none());
+    assert!(anchor.get_site(&program.to_string()).await.unwrap().is_some());
+
+    assert!(anchor.verify_site_registration(&squatted.to_string(), &owner.to_string()).await.unwrap().is_none());
+    let unregistered = Pubkey::new_unique().to_string();
+    assert!(anchor.verify_site_registration(&unregistered, &owner.to_string()).await.unwrap().is_none());
+
+    let err = anchor.verify_site_registration("not-a-program", &owner.to_string()).await.unwrap_err();
+    assert!(err.contains("Invalid program pubkey"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_site_editors_can_edit() {
+    let (registry, profiles) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (owner, editor, program) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), registry, profiles);
+
+    let data = edited_site_account(&owner, &program, "Shade", 2, &[editor]);
+    mount_account(&rpc, &anchor.site_pda(&program), data, &registry).await;
+    mount_missing_accounts(&rpc).await;
+
+    let program = program.to_string();
+    let site = anchor.verify_site_editor(&program, &editor.to_string()).await.unwrap().unwrap();
+    assert_eq!((site.version, site.editors), (2, vec![editor]));
+    assert!(anchor.verify_site_editor(&program, &owner.to_string()).await.unwrap().is_some());
+    // Editors are not owners
+    assert!(anchor.verify_site_registration(&program, &editor.to_string()).await.unwrap().is_none());
+
+    let stranger = Pubkey::new_unique().to_string();
+    assert!(anchor.verify_site_editor(&program, &stranger).await.unwrap().is_none());
+    let unregistered = Pubkey::new_unique().to_string();
+    assert!(anchor.verify_site_editor(&unregistered, &editor.to_string()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_profile_is_read_from_its_pda() {
+    let (registry, profiles) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (wallet, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), registry, profiles);
+
+    mount_account(&rpc, &anchor.profile_pda(&wallet), profile_account(&wallet, Some("shade")), &profiles).await;
+    // A profile account at another wallet's address is corrupt, not that wallet's profile
+    mount_account(&rpc, &anchor.profile_pda(&other), profile_account(&wallet, None), &profiles).await;
+    mount_missing_accounts(&rpc).await;
+
+    let profile = anchor.verify_profile(&wallet.to_string()).await.unwrap().unwrap();
+    assert_eq!(profile.wallet, wallet);
+    assert_eq!(profile.profile_cid, "ipfs://bafyprofile");
+    assert!(profile.is_public);
+    assert_eq!((profile.follower_count, profile.following_count), (12, 7));
+    assert_eq!(profile.username.as_deref(), Some("shade"));
+
+    let err = anchor.verify_profile(&other.to_string()).await.unwrap_err();
+    assert!(err.contains("Malformed profile account"), "{}", err);
+    assert!(anchor.verify_profile(&Pubkey::new_unique().to_string()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_site_versions_are_read_from_their_pdas() {
+    let (registry, profiles) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (owner, program) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), registry, profiles);
+    let site = anchor.site_pda(&program);
+
+    mount_account(&rpc, &site, versioned_site_account(&owner, &program, "Shade", 3), &registry).await;
+    let cids = ["ipfs://bafyone", "ipfs://bafytwo", "ipfs://bafythree"];
+    // Asked for newest first, in one batch
+    let addresses: Vec<String> = (1..=3u32).rev().map(|v| anchor.site_version_pda(&site, v).to_string()).collect();
+    let accounts: Vec<_> = (1..=3u32).rev()
+        .map(|v| ui_account(&site_version_account(&site, v, cids[v as usize - 1]), &registry))
+        .collect();
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getMultipleAccounts", "params": [addresses] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": accounts },
+            "id": 1
+        })))
+        .expect(1)
+        .mount(&rpc)
+        .await;
+    mount_missing_accounts(&rpc).await;
+
+    let versions = anchor.get_site_versions(&program.to_string()).await.unwrap().unwrap();
+    let listed: Vec<(u32, &str)> = versions.iter().map(|v| (v.version, v.storage_cid.as_str())).collect();
+    assert_eq!(listed, [(3, "ipfs://bafythree"), (2, "ipfs://bafytwo"), (1, "ipfs://bafyone")]);
+    assert_eq!(versions[0].published_at, 1_700_000_180);
+
+    // Unregistered sites have no versions to list
+    assert!(anchor.get_site_versions(&Pubkey::new_unique().to_string()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_unpublished_site_lists_no_versions() {
+    let (registry, profiles) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (owner, program) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), registry, profiles);
+
+    // Registered before versioning: the account ends before the version counter
+    let mut legacy = site_account(&owner, &program, "Shade");
+    legacy.truncate(legacy.len() - 4 - EDITORS_LEN);
+    mount_account(&rpc, &anchor.site_pda(&program), legacy, &registry).await;
+
+    let site = anchor.get_site(&program.to_string()).await.unwrap().unwrap();
+    assert_eq!(site.version, 0);
+    assert!(site.editors.is_empty());
+    assert_eq!(anchor.get_site_versions(&program.to_string()).await.unwrap().unwrap().len(), 0);
+}
@@ -0,0 +1,138 @@
+// Contract tests pinning the v2 response shapes served by the Iris mapper.
+// Set UPDATE_SNAPSHOTS=1 to rewrite the files under tests/snapshots/v2.
+mod common;
+
+use actix_web::{middleware::from_fn, test, web, App};
+use serde_json::Value;
+use shadow_backend::apollo::ApolloValidator;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::artemis::ArtemisRateLimiter;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::{api, iris};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use std::path::PathBuf;
+
+macro_rules! contract_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(ApolloValidator::new()))
+                .app_data(web::Data::new(ArtemisRateLimiter::new(60)))
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .wrap(from_fn(iris::v2_envelope_middleware))
+                .service(web::scope("/api/v2").configure(api::configure))
+                .service(web::scope("/api").configure(api::configure)),
+        )
+        .await
+    };
+}
+
+fn assert_snapshot(name: &str, actual: &Value) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots/v2")
+        .join(format!("{}.json", name));
+
+    if std::env::var("UPDATE_SNAPSHOTS").is_ok() {
+        let pretty = serde_json::to_string_pretty(actual).unwrap();
+        std::fs::write(&path, pretty + "\n").expect("Failed to write snapshot");
+        return;
+    }
+
+    let expected: Value = serde_json::from_str(
+        &std::fs::read_to_string(&path).expect("Snapshot missing; run with UPDATE_SNAPSHOTS=1"),
+    )
+    .expect("Snapshot is not valid JSON");
+    assert_eq!(&expected, actual, "v2 shape drifted for snapshot {}", name);
+}
+
+/// Replace the value at a JSON pointer with a stable placeholder
+fn redact(value: &mut Value, pointer: &str) {
+    if let Some(v) = value.pointer_mut(pointer) {
+        *v = Value::String("<redacted>".to_string());
+    }
+}
+
+#[tokio::test]
+async fn test_v2_health_snapshot() {
+    let db = common::offline_db().await;
+    let app = contract_app!(db);
+
+    let req = test::TestRequest::get().uri("/api/v2/health").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_snapshot("health", &body);
+
+    // The Accept header opts into the same shape on the unversioned route
+    let req = test::TestRequest::get()
+        .uri("/api/health")
+        .insert_header(("Accept", iris::V2_MEDIA_TYPE))
+        .to_request();
+    let negotiated: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(negotiated, body);
+
+    // v1 is untouched
+    let req = test::TestRequest::get().uri("/api/health").to_request();
+    let v1: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(v1["status"], "ok");
+    assert!(v1.get("data").is_none());
+}
+
+#[tokio::test]
+async fn test_v2_auth_token_snapshot() {
+    let db = common::offline_db().await;
+    let app = contract_app!(db);
+
+    let keypair = Keypair::new();
+    let req = test::TestRequest::post()
+        .uri("/api/v2/auth/token")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&keypair)))
+        .to_request();
+    let mut body: Value = test::call_and_read_body_json(&app, req).await;
+    for pointer in ["/data/token", "/data/expiresAt", "/data/wallet"] {
+        redact(&mut body, pointer);
+    }
+    assert_snapshot("auth_token", &body);
+}
+
+#[tokio::test]
+async fn test_v2_accepts_camel_case_input_and_keeps_error_shape() {
+    let db = common::offline_db().await;
+    let app = contract_app!(db);
+
+    let signer = Keypair::new();
+    let body = serde_json::json!({
+        "domain": "contract.shadow",
+        "programAddress": Pubkey::new_unique().to_string(),
+        "ownerPubkey": Pubkey::new_unique().to_string(),
+    });
+    let req = test::TestRequest::post()
+        .uri("/api/v2/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&signer)))
+        .set_json(body)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    // Reaching the ownership check proves the camelCase body deserialized
+    assert_eq!(resp.status(), 403);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_snapshot("domain_forbidden", &body);
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_v2_profile_search_snapshot() {
+    let db = common::test_db().await;
+    let app = contract_app!(db);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v2/profiles/search?q=nobody")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_snapshot("profile_search_empty", &body);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,225 @@
+// Integration tests for Irys uploads and funding, and Atlas cost estimates and status tracking
+mod common;
+
+use shadow_backend::atlas::{AtlasTracker, UploadStatus};
+use shadow_backend::config::ArweaveConfig;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use shadow_backend::storage::{BundlrStorage, IrysTag, IrysTransaction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::system_instruction::SystemInstruction;
+use std::sync::Arc;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn arweave_config(stall_timeout_seconds: u64) -> ArweaveConfig {
+    ArweaveConfig {
+        status_poll_interval_seconds: 60,
+        stall_timeout_seconds,
+        max_upload_attempts: 3,
+    }
+}
+
+fn bundlr(node: &MockServer, gateway: &MockServer) -> Arc<BundlrStorage> {
+    Arc::new(
+        BundlrStorage::new()
+            .with_node_url(&node.uri())
+            .with_gateway_url(&gateway.uri())
+            .with_private_key(&Keypair::new().to_base58_string()),
+    )
+}
+
+async fn mount_pricing(node: &MockServer, price: u64, balance: u64) {
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/price/solana/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(price.to_string()))
+        .mount(node)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/account/balance/solana"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "balance": balance.to_string() })))
+        .mount(node)
+        .await;
+}
+
+async fn mount_upload(node: &MockServer, tx_id: &str) {
+    Mock::given(method("POST"))
+        .and(path("/tx"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": tx_id })))
+        .up_to_n_times(1)
+        .mount(node)
+        .await;
+}
+
+#[tokio::test]
+async fn test_estimate_warns_when_balance_is_short() {
+    let (node, gateway) = (MockServer::start().await, MockServer::start().await);
+    mount_pricing(&node, 5_000, 1_200).await;
+    let atlas = AtlasTracker::new(common::offline_db().await, bundlr(&node, &gateway), arweave_config(3600));
+
+    let estimate = atlas.estimate(2048).await.unwrap();
+    assert_eq!(estimate.price, 5_000);
+    assert_eq!(estimate.balance, Some(1_200));
+    assert_eq!(estimate.sufficient, Some(false));
+    assert_eq!(estimate.warnings.len(), 1);
+    assert!(estimate.warnings[0].contains("below the quoted price"));
+}
+
+#[tokio::test]
+async fn test_estimate_without_balance_still_quotes() {
+    let (node, gateway) = (MockServer::start().await, MockServer::start().await);
+    Mock::given(method("GET"))
+        .and(path_regex(r"^/price/solana/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "atomicPrice": 900 })))
+        .mount(&node)
+        .await;
+    let atlas = AtlasTracker::new(common::offline_db().await, bundlr(&node, &gateway), arweave_config(3600));
+
+    let estimate = atlas.estimate(10).await.unwrap();
+    assert_eq!(estimate.price, 900);
+    assert_eq!(estimate.sufficient, None);
+    assert!(estimate.warnings[0].contains("balance"));
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_upload_is_tracked_until_confirmed() {
+    let db = common::test_db().await;
+    let (node, gateway) = (MockServer::start().await, MockServer::start().await);
+    mount_pricing(&node, 700, 10_000).await;
+    mount_upload(&node, "tx-confirmed").await;
+    let atlas = AtlasTracker::new(db.clone(), bundlr(&node, &gateway), arweave_config(3600));
+
+    let upload = atlas.upload(b"hello arweave", vec![]).await.unwrap();
+    assert_eq!(upload.tx_id, "tx-confirmed");
+    assert_eq!(upload.status, UploadStatus::Pending);
+    assert_eq!(upload.quoted_price, 700);
+    assert_eq!(upload.balance_before, Some(10_000));
+
+    Mock::given(method("GET"))
+        .and(path("/tx/tx-confirmed/status"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "number_of_confirmations": 12 })))
+        .mount(&gateway)
+        .await;
+    let summary = atlas.poll_once().await.unwrap();
+    assert_eq!(summary.confirmed, 1);
+
+    let stored = atlas.get_upload("arweave://tx-confirmed").await.unwrap().unwrap();
+    assert_eq!(stored.status, UploadStatus::Confirmed);
+    assert!(stored.confirmed_at.is_some());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_stalled_upload_is_reuploaded() {
+    let db = common::test_db().await;
+    let (node, gateway) = (MockServer::start().await, MockServer::start().await);
+    mount_pricing(&node, 700, 10_000).await;
+    mount_upload(&node, "tx-original").await;
+    let atlas = AtlasTracker::new(db.clone(), bundlr(&node, &gateway), arweave_config(0));
+    atlas.upload(b"stuck bytes", vec![]).await.unwrap();
+
+    Mock::given(method("GET"))
+        .and(path("/tx/tx-original/status"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "status": "PENDING" })))
+        .mount(&node)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/tx/tx-original/data"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"stuck bytes".to_vec()))
+        .mount(&node)
+        .await;
+    mount_upload(&node, "tx-retry").await;
+
+    let summary = atlas.poll_once().await.unwrap();
+    assert_eq!(summary.stalled, 1);
+    assert_eq!(summary.reuploaded, 1);
+
+    let original = atlas.get_upload("tx-original").await.unwrap().unwrap();
+    assert_eq!(original.status, UploadStatus::Stalled);
+    assert_eq!(original.replaced_by.as_deref(), Some("tx-retry"));
+    let retry = atlas.get_upload("tx-retry").await.unwrap().unwrap();
+    assert_eq!(retry.status, UploadStatus::Pending);
+    assert_eq!(retry.attempts, 2);
+    assert_eq!(retry.replaces.as_deref(), Some("tx-original"));
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+async fn test_upload_posts_signed_data_item() {
+    let (node, gateway) = (MockServer::start().await, MockServer::start().await);
+    mount_pricing(&node, 700, 10_000).await;
+    mount_upload(&node, "tx-signed").await;
+    let keypair = Keypair::new();
+    let bundlr = BundlrStorage::new()
+        .with_node_url(&node.uri())
+        .with_gateway_url(&gateway.uri())
+        .with_private_key(&keypair.to_base58_string());
+
+    let uri = bundlr.upload(b"hello irys", vec![("Content-Type", "text/plain")]).await.unwrap();
+    assert_eq!(uri, "arweave://tx-signed");
+
+    let requests = node.received_requests().await.unwrap();
+    let post = requests.iter().find(|r| r.url.path() == "/tx").unwrap();
+    assert_eq!(post.headers.get("content-type").unwrap(), "application/json");
+    let tx: IrysTransaction = serde_json::from_slice(&post.body).unwrap();
+    assert_eq!(URL_SAFE_NO_PAD.decode(&tx.data).unwrap(), b"hello irys");
+    assert_eq!(tx.tags, vec![IrysTag { name: "Content-Type".to_string(), value: "text/plain".to_string() }]);
+    assert_eq!(tx.owner, keypair.pubkey().to_string());
+
+    let signature = Signature::try_from(URL_SAFE_NO_PAD.decode(&tx.signature).unwrap().as_slice()).unwrap();
+    assert!(signature.verify(keypair.pubkey().as_ref(), &tx.id().unwrap()));
+    // Changing the content changes the id, so the signature no longer holds
+    let tampered = IrysTransaction { data: URL_SAFE_NO_PAD.encode(b"other"), ..tx };
+    assert!(!signature.verify(keypair.pubkey().as_ref(), &tampered.id().unwrap()));
+}
+
+#[tokio::test]
+async fn test_fund_if_needed_tops_up_the_shortfall() {
+    let (node, gateway) = (MockServer::start().await, MockServer::start().await);
+    let (rpc, sent) = common::submitting_rpc().await;
+    let node_address = Pubkey::new_unique();
+    mount_pricing(&node, 5_000, 1_000).await;
+    Mock::given(method("GET"))
+        .and(path("/info"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "addresses": { "solana": node_address.to_string() }
+        })))
+        .mount(&node)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/account/balance/solana"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "confirmed": true })))
+        .expect(1)
+        .mount(&node)
+        .await;
+    let keypair = Keypair::new();
+    let bundlr = BundlrStorage::new()
+        .with_node_url(&node.uri())
+        .with_gateway_url(&gateway.uri())
+        .with_solana_rpc_url(&rpc.uri())
+        .with_private_key(&keypair.to_base58_string());
+
+    let signature = bundlr.fund_if_needed(2048).await.unwrap().unwrap();
+    let tx = sent.lock().unwrap()[0].clone();
+    assert_eq!(tx.signatures[0].to_string(), signature);
+    assert_eq!(tx.message.account_keys[..2], [keypair.pubkey(), node_address]);
+    // 4_000 short, plus a 10% margin
+    let transfer: SystemInstruction = bincode::deserialize(&tx.message.instructions[0].data).unwrap();
+    assert_eq!(transfer, SystemInstruction::Transfer { lamports: 4_400 });
+
+    let registered = node.received_requests().await.unwrap().into_iter()
+        .find(|r| r.method.as_str() == "POST" && r.url.path() == "/account/balance/solana")
+        .unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&registered.body).unwrap()["tx_id"], signature);
+
+    // Covered already, so nothing moves
+    let covered = MockServer::start().await;
+    mount_pricing(&covered, 700, 10_000).await;
+    let bundlr = bundlr.with_node_url(&covered.uri());
+    assert_eq!(bundlr.fund_if_needed(2048).await.unwrap(), None);
+    assert_eq!(sent.lock().unwrap().len(), 1);
+}
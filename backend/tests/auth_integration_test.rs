@@ -0,0 +1,314 @@
+// Integration tests for Ares authentication at the HTTP handler level
+mod common;
+
+use actix_web::{test, web, App};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use mongodb::Database;
+use serde_json::Value;
+use shadow_backend::apollo::ApolloValidator;
+use shadow_backend::ares::{AresAuth, AresClaims, AuthHeader};
+use shadow_backend::clock::{Clock, TestClock};
+use shadow_backend::handlers;
+use shadow_backend::olympus::OlympusCA;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const JWT_SECRET: &str = "ares-test-secret";
+
+macro_rules! auth_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new().with_jwt_secret(JWT_SECRET)))
+                .app_data(web::Data::new(ApolloValidator::new()))
+                .service(
+                    web::scope("/api")
+                        .route("/auth/token", web::post().to(handlers::issue_auth_token))
+                        .route("/domains", web::post().to(handlers::register_domain)),
+                ),
+        )
+        .await
+    };
+}
+
+fn register_body(owner: &Pubkey) -> Value {
+    serde_json::json!({
+        "domain": "secure.shadow",
+        "program_address": Pubkey::new_unique().to_string(),
+        "owner_pubkey": owner.to_string(),
+    })
+}
+
+async fn cleanup(db: Database) {
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+async fn test_missing_auth_header_is_unauthorized() {
+    let db = common::offline_db().await;
+    let app = auth_app!(db);
+
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .set_json(register_body(&Pubkey::new_unique()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[tokio::test]
+async fn test_malformed_auth_header_is_unauthorized() {
+    let db = common::offline_db().await;
+    let app = auth_app!(db);
+
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", "{not json"))
+        .set_json(register_body(&Pubkey::new_unique()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[tokio::test]
+async fn test_forged_signature_is_unauthorized() {
+    let db = common::offline_db().await;
+    let app = auth_app!(db);
+
+    let owner = Keypair::new();
+    let forger = Keypair::new();
+    let timestamp = chrono::Utc::now().timestamp();
+    let nonce = uuid::Uuid::new_v4().to_string();
+    let challenge = AresAuth::create_challenge(&owner.pubkey().to_string(), timestamp, &nonce);
+    let header = serde_json::json!({
+        "wallet": owner.pubkey().to_string(),
+        "signature": common::sign_message(&forger, challenge.as_bytes()),
+        "timestamp": timestamp,
+        "nonce": nonce,
+    });
+
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", header.to_string()))
+        .set_json(register_body(&owner.pubkey()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[tokio::test]
+async fn test_wrong_wallet_in_body_is_forbidden() {
+    let db = common::offline_db().await;
+    let app = auth_app!(db);
+
+    let signer = Keypair::new();
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&signer)))
+        .set_json(register_body(&Pubkey::new_unique()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+#[tokio::test]
+async fn test_expired_challenge_is_unauthorized() {
+    let db = common::offline_db().await;
+    let app = auth_app!(db);
+
+    let owner = Keypair::new();
+    let stale = chrono::Utc::now().timestamp() - 301;
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header_at(&owner, stale)))
+        .set_json(register_body(&owner.pubkey()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "Challenge expired");
+}
+
+#[tokio::test]
+async fn test_challenge_expires_after_exactly_300_seconds() {
+    let clock = TestClock::starting_now();
+    let ares = AresAuth::new().with_clock(clock.clone());
+    let owner = Keypair::new();
+    let signed_at = clock.now_utc().timestamp();
+    let header = AuthHeader::from_header(&common::auth_header_at(&owner, signed_at)).unwrap();
+
+    clock.advance(std::time::Duration::from_secs(300));
+    assert_eq!(header.verify(&ares), Ok(()));
+    clock.advance(std::time::Duration::from_secs(1));
+    assert_eq!(header.verify(&ares), Err("Challenge expired".to_string()));
+}
+
+#[tokio::test]
+async fn test_replayed_header_is_unauthorized() {
+    let db = common::offline_db().await;
+    let app = auth_app!(db);
+
+    // A wallet mismatch is only reported once the header has been accepted
+    let owner = Keypair::new();
+    let header = common::auth_header(&owner);
+    let request = || {
+        test::TestRequest::post()
+            .uri("/api/domains")
+            .insert_header(("X-Shadow-Auth", header.clone()))
+            .set_json(register_body(&Pubkey::new_unique()))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, request()).await.status(), 403);
+
+    let resp = test::call_service(&app, request()).await;
+    assert_eq!(resp.status(), 401);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "Challenge already used");
+
+    // A fresh header for the same wallet still works
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(register_body(&Pubkey::new_unique()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+#[tokio::test]
+async fn test_header_without_a_uuid_nonce_is_unauthorized() {
+    let ares = AresAuth::new();
+    let owner = Keypair::new();
+    let wallet = owner.pubkey().to_string();
+    let timestamp = chrono::Utc::now().timestamp();
+
+    let header = AuthHeader::from_header(&serde_json::json!({
+        "wallet": wallet,
+        "signature": common::sign_message(&owner, AresAuth::create_challenge(&wallet, timestamp, "1").as_bytes()),
+        "timestamp": timestamp,
+        "nonce": "1",
+    }).to_string()).unwrap();
+    assert_eq!(header.verify(&ares), Err("Invalid nonce".to_string()));
+
+    let without_nonce = serde_json::json!({ "wallet": wallet, "signature": "sig", "timestamp": timestamp });
+    assert!(AuthHeader::from_header(&without_nonce.to_string()).is_err());
+}
+
+#[tokio::test]
+async fn test_used_nonces_are_forgotten_after_their_challenge_expires() {
+    let clock = TestClock::starting_now();
+    let ares = AresAuth::new().with_clock(clock.clone());
+    let owner = Keypair::new();
+    let signed_at = clock.now_utc().timestamp();
+    let header = AuthHeader::from_header(&common::auth_header_at(&owner, signed_at)).unwrap();
+    assert_eq!(header.verify(&ares), Ok(()));
+
+    // Remembered for as long as the timestamp is accepted
+    clock.advance(std::time::Duration::from_secs(300));
+    assert_eq!(ares.evict_expired_nonces(), 0);
+    assert_eq!(header.verify(&ares), Err("Challenge already used".to_string()));
+
+    clock.advance(std::time::Duration::from_secs(61));
+    assert_eq!(ares.evict_expired_nonces(), 1);
+    assert_eq!(header.verify(&ares), Err("Challenge expired".to_string()));
+}
+
+#[tokio::test]
+async fn test_session_token_expires_with_the_clock() {
+    let clock = TestClock::starting_now();
+    let ares = AresAuth::new().with_jwt_secret(JWT_SECRET).with_token_ttl(60).with_clock(clock.clone());
+    let (token, _) = ares.issue_token("wallet").unwrap();
+
+    clock.advance(std::time::Duration::from_secs(60));
+    assert_eq!(ares.verify_token(&token).as_deref(), Ok("wallet"));
+    clock.advance(std::time::Duration::from_secs(1));
+    assert_eq!(ares.verify_token(&token), Err("Token expired".to_string()));
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_valid_signature_and_wallet_succeeds() {
+    let db = common::test_db().await;
+    let app = auth_app!(db);
+
+    let owner = Keypair::new();
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(register_body(&owner.pubkey()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+    cleanup(db).await;
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_token_exchange_authenticates_bearer_requests() {
+    let db = common::offline_db().await;
+    let app = auth_app!(db);
+
+    let owner = Keypair::new();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/token")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["wallet"], owner.pubkey().to_string());
+    let token = body["token"].as_str().expect("token issued").to_string();
+
+    // The bearer token identifies the caller, so a mismatched body wallet is 403 rather than 401
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(register_body(&Pubkey::new_unique()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    // A token cannot be traded in for another token
+    let req = test::TestRequest::post()
+        .uri("/api/auth/token")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+    let db = common::test_db().await;
+    let app = auth_app!(db);
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(register_body(&owner.pubkey()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+    cleanup(db).await;
+}
+
+#[tokio::test]
+async fn test_expired_token_is_unauthorized() {
+    let db = common::offline_db().await;
+    let app = auth_app!(db);
+
+    let owner = Keypair::new();
+    let now = chrono::Utc::now().timestamp();
+    let claims = AresClaims {
+        sub: owner.pubkey().to_string(),
+        iat: now - 7200,
+        exp: now - 3600,
+    };
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+    .unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+        .set_json(register_body(&owner.pubkey()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["error"], "Token expired");
+}
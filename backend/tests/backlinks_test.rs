@@ -0,0 +1,141 @@
+// Integration tests for the .shadow link graph: crawl queueing, backlinks and link boost
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::athena::{AthenaIndexer, SearchIndex};
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+
+fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/backlinks").join(name);
+    std::fs::read_to_string(path).unwrap()
+}
+
+async fn register(db: &mongodb::Database, domain: &str, program: &str, owner: &str) {
+    let now = mongodb::bson::DateTime::now();
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": program,
+            "owner_pubkey": owner,
+            "storage_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            "name": domain,
+            "description": null,
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+    db.collection::<Document>("domains")
+        .insert_one(doc! {
+            "_id": domain,
+            "owner_pubkey": owner,
+            "program_address": program,
+            "verified": true,
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+}
+
+async fn index(athena: &AthenaIndexer, domain: &str, program: &str, page: &str) {
+    athena
+        .index_site(domain, program, None, Some(domain), None, &fixture(page))
+        .await
+        .unwrap();
+}
+
+async fn popularity(db: &mongodb::Database, domain: &str) -> f64 {
+    db.collection::<SearchIndex>("search_index")
+        .find_one(doc! { "domain": domain }, None)
+        .await
+        .unwrap()
+        .unwrap()
+        .popularity_score
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_link_graph_queues_backlinks_and_boosts_once_per_owner() {
+    let db = common::test_db().await;
+    let athena = AthenaIndexer::new(db.clone());
+
+    let prolific = Pubkey::new_unique().to_string();
+    let other = Pubkey::new_unique().to_string();
+    let (alpha, beta, gamma, target) = (
+        Pubkey::new_unique().to_string(),
+        Pubkey::new_unique().to_string(),
+        Pubkey::new_unique().to_string(),
+        Pubkey::new_unique().to_string(),
+    );
+    register(&db, "alpha.shadow", &alpha, &prolific).await;
+    register(&db, "beta.shadow", &beta, &prolific).await;
+    register(&db, "gamma.shadow", &gamma, &other).await;
+    register(&db, "target.shadow", &target, &Pubkey::new_unique().to_string()).await;
+
+    // The registered target is queued, the unregistered ghost is not
+    index(&athena, "alpha.shadow", &alpha, "alpha.html").await;
+    let entry = db.collection::<SearchIndex>("search_index")
+        .find_one(doc! { "domain": "alpha.shadow" }, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.outbound_links, vec!["target.shadow", "ghost.shadow"]);
+    let queued: Vec<String> = db.collection::<Document>("crawl_queue")
+        .distinct("_id", None, None)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|b| b.as_str().map(|s| s.to_string()))
+        .collect();
+    assert_eq!(queued, vec!["target.shadow"]);
+
+    index(&athena, "target.shadow", &target, "target.html").await;
+    let one_owner = 1.0 + AthenaIndexer::link_boost(1);
+    assert_eq!(popularity(&db, "target.shadow").await, one_owner);
+
+    // A second site from the same owner adds nothing
+    index(&athena, "beta.shadow", &beta, "beta.html").await;
+    assert_eq!(popularity(&db, "target.shadow").await, one_owner);
+
+    // A different owner does
+    index(&athena, "gamma.shadow", &gamma, "gamma.html").await;
+    assert_eq!(popularity(&db, "target.shadow").await, 1.0 + AthenaIndexer::link_boost(2));
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AthenaIndexer::new(db.clone())))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/sites/{program_address}/backlinks", web::get().to(handlers::get_site_backlinks)),
+    )
+    .await;
+    let req = test::TestRequest::get().uri(&format!("/api/sites/{}/backlinks", target)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let mut linking: Vec<&str> = body["backlinks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| b["domain"].as_str().unwrap())
+        .collect();
+    linking.sort();
+    assert_eq!(linking, vec!["alpha.shadow", "beta.shadow", "gamma.shadow"]);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_backlinks_rejects_invalid_program() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(AthenaIndexer::new(common::offline_db().await)))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/sites/{program_address}/backlinks", web::get().to(handlers::get_site_backlinks)),
+    )
+    .await;
+    let req = test::TestRequest::get().uri("/api/sites/not-a-program/backlinks").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
@@ -0,0 +1,129 @@
+// Tests for Pheme embeddable site badges.
+// Set UPDATE_SNAPSHOTS=1 to rewrite the files under tests/snapshots/badges.
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::handlers;
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::pheme::{render_svg, BadgeData, BadgeStat, BadgeStatus, BadgeStyle, DEFAULT_LABEL};
+use shadow_backend::prometheus::PrometheusAnalytics;
+use std::path::PathBuf;
+
+const MALICIOUS_LABEL: &str = "</text><script>alert(1)</script>";
+
+macro_rules! badge_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(PrometheusAnalytics::new($db.clone())))
+                .route("/api/badge/{domain}.svg", web::get().to(handlers::get_badge_svg))
+                .route("/api/badge/{domain}.json", web::get().to(handlers::get_badge_json)),
+        )
+        .await
+    };
+}
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots/badges")
+        .join(format!("{}.svg", name));
+
+    if std::env::var("UPDATE_SNAPSHOTS").is_ok() {
+        std::fs::create_dir_all(path.parent().unwrap()).expect("Failed to create snapshot dir");
+        std::fs::write(&path, format!("{}\n", actual)).expect("Failed to write snapshot");
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).expect("Snapshot missing; run with UPDATE_SNAPSHOTS=1");
+    assert_eq!(expected.trim_end(), actual, "badge output drifted for snapshot {}", name);
+}
+
+#[actix_web::test]
+async fn test_verified_badge_snapshot() {
+    let badge = BadgeData::new("docs.shadow", BadgeStatus::Verified, DEFAULT_LABEL, BadgeStat::Weekly, Some(1_234));
+    assert_eq!(badge.message, "verified · 1.2k visits this week");
+    assert_snapshot("verified_flat", &render_svg(&badge, BadgeStyle::Flat));
+}
+
+#[actix_web::test]
+async fn test_unverified_badge_snapshot() {
+    let badge = BadgeData::new("docs.shadow", BadgeStatus::Unverified, "Shadow", BadgeStat::None, Some(99));
+    assert_eq!(badge.message, "unverified");
+    assert_snapshot("unverified_plastic", &render_svg(&badge, BadgeStyle::Plastic));
+}
+
+#[actix_web::test]
+async fn test_label_is_escaped_in_svg() {
+    let badge = BadgeData::new("docs.shadow", BadgeStatus::Verified, MALICIOUS_LABEL, BadgeStat::None, None);
+    let svg = render_svg(&badge, BadgeStyle::Flat);
+    assert!(!svg.contains("<script>"));
+    assert!(!svg.contains("</text><script"));
+    assert!(svg.contains("&lt;/text&gt;&lt;script&gt;alert(1)&lt;/script&gt;"));
+}
+
+#[actix_web::test]
+async fn test_badge_params_are_validated() {
+    let app = badge_app!(common::offline_db().await);
+    for uri in [
+        "/api/badge/docs.shadow.svg?style=3d",
+        "/api/badge/docs.shadow.svg?stat=revenue",
+        "/api/badge/docs.shadow.json?label=",
+        "/api/badge/docs.shadow.svg?label=aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+    ] {
+        let resp = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_badge_endpoints_serve_domain_status() {
+    let db = common::test_db().await;
+    let now = mongodb::bson::DateTime::now();
+    db.collection::<Document>("domains")
+        .insert_one(doc! {
+            "_id": "docs.shadow",
+            "owner_pubkey": "owner",
+            "program_address": "program",
+            "verified": true,
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+    let prometheus = PrometheusAnalytics::new(db.clone());
+    for _ in 0..3 {
+        prometheus.record_visit("docs.shadow", "program", "wallet", 5.0).await.unwrap();
+    }
+    let app = badge_app!(db);
+
+    let req = test::TestRequest::get().uri("/api/badge/docs.shadow.json").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("Cache-Control").unwrap(), "public, max-age=86400, s-maxage=300");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["status"], "verified");
+    assert_eq!(body["visits"], 3);
+    assert_eq!(body["message"], "verified · 3 visits this week");
+
+    let uri = format!("/api/badge/docs.shadow.svg?label={}", urlencode(MALICIOUS_LABEL));
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("Content-Type").unwrap(), "image/svg+xml; charset=utf-8");
+    let svg = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(!svg.contains("<script>"));
+
+    // Unregistered domains get a neutral badge, not an error
+    let req = test::TestRequest::get().uri("/api/badge/nobody.shadow.json?stat=total").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "unknown");
+    assert!(body["visits"].is_null());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+fn urlencode(value: &str) -> String {
+    value.bytes().map(|b| format!("%{:02X}", b)).collect()
+}
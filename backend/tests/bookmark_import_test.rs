@@ -0,0 +1,128 @@
+// Integration tests for importing and exporting Chronos bookmarks as Netscape bookmark files
+mod common;
+
+use actix_web::{test, web, App};
+use shadow_backend::ares::AresAuth;
+use shadow_backend::chronos::ChronosManager;
+use shadow_backend::handlers;
+use solana_sdk::signature::{Keypair, Signer};
+
+macro_rules! bookmark_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(ChronosManager::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/bookmarks/export", web::get().to(handlers::export_bookmarks))
+                .route("/api/bookmarks/import", web::post().to(handlers::import_bookmarks)),
+        )
+        .await
+    };
+}
+
+const BOUNDARY: &str = "shadow-bookmarks-boundary";
+
+/// A multipart/form-data body with a single file field named `field`
+fn multipart_body(field: &str, content: &str) -> String {
+    format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"{field}\"; filename=\"bookmarks.html\"\r\nContent-Type: text/html\r\n\r\n{content}\r\n--{b}--\r\n",
+        b = BOUNDARY,
+        field = field,
+        content = content
+    )
+}
+
+fn import_request(keypair: &Keypair, field: &str, content: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/bookmarks/import")
+        .insert_header(("X-Shadow-Auth", common::auth_header(keypair)))
+        .insert_header(("Content-Type", format!("multipart/form-data; boundary={}", BOUNDARY)))
+        .set_payload(multipart_body(field, content))
+}
+
+const BROWSER_EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><A HREF="https://docs.rs/tokio" ADD_DATE="1600000000">tokio docs</A>
+    <DT><H3>Shadow</H3>
+    <DL><p>
+        <DT><A HREF="shadow://swap" ADD_DATE="1600000100" TAGS="defi">Swap</A>
+        <DT><A HREF="shadow://swap.shadow/pools">Swap pools</A>
+    </DL><p>
+</DL><p>"#;
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_import_then_export_bookmarks() {
+    let db = common::test_db().await;
+    let app = bookmark_app!(db);
+    let keypair = Keypair::new();
+    let wallet = keypair.pubkey().to_string();
+
+    let resp: serde_json::Value =
+        test::call_and_read_body_json(&app, import_request(&keypair, "file", BROWSER_EXPORT).to_request()).await;
+    // The second swap.shadow link duplicates the first
+    assert_eq!(resp, serde_json::json!({ "imported": 2, "skipped_duplicates": 1 }));
+
+    let chronos = ChronosManager::new(db.clone());
+    let (bookmarks, _) = chronos.get_bookmarks(&wallet, Some("Shadow"), 10, None).await.unwrap();
+    assert_eq!(bookmarks.len(), 1);
+    assert_eq!(bookmarks[0].domain, "swap.shadow");
+    assert_eq!(bookmarks[0].title.as_deref(), Some("Swap"));
+    assert_eq!(bookmarks[0].tags, vec!["defi"]);
+    assert_eq!(bookmarks[0].created_at.timestamp(), 1_600_000_100);
+
+    // Importing again changes nothing, and leaves existing bookmarks as they were
+    chronos.add_bookmark(&wallet, "docs.rs", "program", Some("Renamed"), None, None, Vec::new()).await.unwrap();
+    let resp: serde_json::Value =
+        test::call_and_read_body_json(&app, import_request(&keypair, "file", BROWSER_EXPORT).to_request()).await;
+    assert_eq!(resp, serde_json::json!({ "imported": 0, "skipped_duplicates": 3 }));
+
+    let req = test::TestRequest::get()
+        .uri("/api/bookmarks/export")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&keypair)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert_eq!(resp.headers().get("Content-Type").unwrap(), "text/html; charset=utf-8");
+    let html = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(html.contains(r#"<DT><A HREF="https://docs.rs" ADD_DATE="#), "{}", html);
+    assert!(html.contains("Renamed"));
+    assert!(html.contains(r#"<DT><H3 ADD_DATE="1600000100">Shadow</H3>"#), "{}", html);
+    assert!(html.contains(r#"HREF="shadow://swap.shadow""#));
+
+    // Another wallet's export is empty
+    let other = Keypair::new();
+    let req = test::TestRequest::get()
+        .uri("/api/bookmarks/export")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&other)))
+        .to_request();
+    let html = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    assert!(!html.contains("<DT>"));
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_import_rejects_bad_uploads() {
+    let db = common::offline_db().await;
+    let app = bookmark_app!(db);
+    let keypair = Keypair::new();
+
+    let resp = test::call_service(&app, import_request(&keypair, "bookmarks", BROWSER_EXPORT).to_request()).await;
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body.to_string().contains("Missing file field"), "{}", body);
+
+    let huge = "x".repeat(5 * 1024 * 1024 + 1);
+    let resp = test::call_service(&app, import_request(&keypair, "file", &huge).to_request()).await;
+    assert_eq!(resp.status(), 400);
+
+    let req = test::TestRequest::post()
+        .uri("/api/bookmarks/import")
+        .insert_header(("Content-Type", format!("multipart/form-data; boundary={}", BOUNDARY)))
+        .set_payload(multipart_body("file", BROWSER_EXPORT))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
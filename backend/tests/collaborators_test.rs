@@ -0,0 +1,400 @@
+// Integration tests for site collaborators and the Tyche role matrix
+mod common;
+
+use actix_web::{test, web, App};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use serde_json::Value;
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::cerberus::DEPLOY_TOKEN_HEADER;
+use shadow_backend::db::{Site, SiteRole};
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::mnemosyne::Mnemosyne;
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::tyche::{SiteAction, TycheOwnership};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+
+const LIVE_CID: &str = "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+const NEXT_CID: &str = "ipfs://bafybeib3azuaj5wr2b3roph6judaaltkmhtpehblfzsiif4week7dl6nry";
+
+macro_rules! collaborators_app {
+    ($db:expr) => {
+        collaborators_app!(
+            $db,
+            Arc::new(AnchorClient::with_programs("http://127.0.0.1:1".to_string(), Pubkey::new_unique(), Pubkey::new_unique())),
+            TycheOwnership::new($db.clone(), "http://127.0.0.1:1".to_string())
+        )
+    };
+    ($db:expr, $anchor:expr) => {{
+        let anchor = Arc::new($anchor);
+        let registry = Arc::clone(&anchor);
+        collaborators_app!($db, anchor, TycheOwnership::new($db.clone(), "http://127.0.0.1:1".to_string()).with_registry(registry))
+    }};
+    ($db:expr, $anchor:expr, $tyche:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(Mnemosyne::new($db.clone())))
+                .app_data(web::Data::new(HephaestusCache::new(16, 60)))
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(common::test_config()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new($tyche))
+                .app_data(web::Data::from($anchor))
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .route("/api/sites/mine", web::get().to(handlers::list_my_sites))
+                .route("/api/sites/{program_address}", web::put().to(handlers::update_site))
+                .route("/api/sites/{program_address}", web::patch().to(handlers::patch_site))
+                .route("/api/sites/{program_address}/deploys", web::post().to(handlers::stage_deploy))
+                .route("/api/sites/{program_address}/content", web::post().to(handlers::publish_site_content))
+                .route("/api/sites/{program_address}/deploy-tokens", web::post().to(handlers::create_deploy_token))
+                .route("/api/sites/{program_address}/collaborators", web::post().to(handlers::add_collaborator))
+                .route("/api/sites/{program_address}/collaborators/{wallet}", web::delete().to(handlers::remove_collaborator)),
+        )
+        .await
+    };
+}
+
+async fn insert_site(db: &Database, program: &str, owner: &Keypair) {
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": program,
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": LIVE_CID,
+            "name": "Agency site",
+            "description": null,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+}
+
+/// PATCH at the site's current revision
+async fn patch(db: &Database, program: &str, wallet: &Keypair, fields: Value) -> test::TestRequest {
+    let site = shadow_backend::db::get_site(db, program).await.unwrap().unwrap();
+    let mut body = fields;
+    body["expected_revision"] = serde_json::json!(site.revision);
+    test::TestRequest::patch()
+        .uri(&format!("/api/sites/{}", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+        .set_json(body)
+}
+
+fn add(program: &str, caller: &Keypair, wallet: &Keypair, role: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(&format!("/api/sites/{}/collaborators", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(caller)))
+        .set_json(serde_json::json!({ "wallet": wallet.pubkey().to_string(), "role": role }))
+}
+
+fn remove(program: &str, caller: &Keypair, wallet: &Keypair) -> test::TestRequest {
+    test::TestRequest::delete()
+        .uri(&format!("/api/sites/{}/collaborators/{}", program, wallet.pubkey()))
+        .insert_header(("X-Shadow-Auth", common::auth_header(caller)))
+}
+
+fn deploy(program: &str, wallet: &Keypair) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(&format!("/api/sites/{}/deploys", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+        .set_json(serde_json::json!({ "storage_cid": NEXT_CID, "force": true }))
+}
+
+const ACTIONS: [SiteAction; 6] = [
+    SiteAction::UpdateContent,
+    SiteAction::UpdateMetadata,
+    SiteAction::ManageDeployTokens,
+    SiteAction::ManageDomains,
+    SiteAction::ManageCollaborators,
+    SiteAction::ChangeOwnership,
+];
+
+fn site_with(owner: &str, collaborators: &[(&str, &str)]) -> Site {
+    let collaborators: Vec<Document> = collaborators.iter()
+        .map(|(wallet, role)| doc! {
+            "wallet": *wallet,
+            "role": *role,
+            "added_by": owner,
+            "added_at": mongodb::bson::DateTime::now(),
+        })
+        .collect();
+    mongodb::bson::from_document(doc! {
+        "_id": Pubkey::new_unique().to_string(),
+        "owner_pubkey": owner,
+        "storage_cid": LIVE_CID,
+        "name": null,
+        "description": null,
+        "collaborators": collaborators,
+        "created_at": mongodb::bson::DateTime::now(),
+        "updated_at": mongodb::bson::DateTime::now(),
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_role_matrix() {
+    let (owner, admin, editor, stranger) = (
+        Pubkey::new_unique().to_string(),
+        Pubkey::new_unique().to_string(),
+        Pubkey::new_unique().to_string(),
+        Pubkey::new_unique().to_string(),
+    );
+    let site = site_with(&owner, &[(&admin, "admin"), (&editor, "editor")]);
+    let tyche = TycheOwnership::new(common::offline_db().await, "http://127.0.0.1:1".to_string());
+
+    for (wallet, expected) in [
+        (&owner, [true; 6]),
+        (&admin, [true, true, true, true, true, false]),
+        (&editor, [true, true, true, false, false, false]),
+        (&stranger, [false; 6]),
+    ] {
+        let mut allowed = [false; 6];
+        for (i, action) in ACTIONS.into_iter().enumerate() {
+            allowed[i] = tyche.authorize(&site, wallet, action).await.is_ok();
+        }
+        assert_eq!(allowed, expected, "{}", wallet);
+    }
+
+    assert_eq!(tyche.role_of(&site, &editor).await.unwrap(), Some(SiteRole::Editor));
+    assert_eq!(tyche.role_of(&site, &stranger).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_registry_decides_owner_and_editors() {
+    let (record_owner, chain_owner, chain_editor, admin) = (Keypair::new(), Keypair::new(), Keypair::new(), Keypair::new());
+    let [record_owner, chain_owner, chain_editor, admin] =
+        [record_owner, chain_owner, chain_editor, admin].map(|wallet| wallet.pubkey());
+    let mut site = site_with(&record_owner.to_string(), &[(&admin.to_string(), "admin")]);
+    let program = Pubkey::new_unique();
+    site.program_address = program.to_string();
+
+    let (rpc, anchor) = common::empty_registry().await;
+    common::mount_registry_site(&rpc, &anchor, &program, &chain_owner, &[chain_editor]).await;
+    let tyche = TycheOwnership::new(common::offline_db().await, "http://127.0.0.1:1".to_string())
+        .with_registry(Arc::new(anchor));
+
+    for (wallet, role) in [
+        (chain_owner, Some(SiteRole::Owner)),
+        (record_owner, None),
+        (chain_editor, Some(SiteRole::Editor)),
+        (admin, Some(SiteRole::Admin)),
+    ] {
+        assert_eq!(tyche.role_of(&site, &wallet.to_string()).await.unwrap(), role, "{}", wallet);
+    }
+    assert_eq!(tyche.wallet_owner(&site).await.unwrap(), chain_owner.to_string());
+
+    // Sites the registry doesn't know keep the record's owner
+    site.program_address = Pubkey::new_unique().to_string();
+    assert_eq!(tyche.role_of(&site, &record_owner.to_string()).await.unwrap(), Some(SiteRole::Owner));
+    assert_eq!(tyche.role_of(&site, &chain_editor.to_string()).await.unwrap(), None);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_collaborator_management_needs_admin() {
+    let db = common::test_db().await;
+    let (owner, admin, editor, designer) = (Keypair::new(), Keypair::new(), Keypair::new(), Keypair::new());
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+    let app = collaborators_app!(db);
+
+    let resp = test::call_service(&app, add(&program, &owner, &admin, "admin").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let resp = test::call_service(&app, add(&program, &admin, &editor, "editor").to_request()).await;
+    assert_eq!(resp.status(), 200);
+
+    // Editors can't hand out access, and nobody can add a second owner
+    let resp = test::call_service(&app, add(&program, &editor, &designer, "editor").to_request()).await;
+    assert_eq!(resp.status(), 403);
+    let resp = test::call_service(&app, add(&program, &owner, &designer, "owner").to_request()).await;
+    assert_eq!(resp.status(), 400);
+    let resp = test::call_service(&app, add(&program, &admin, &owner, "editor").to_request()).await;
+    assert_eq!(resp.status(), 409);
+
+    let resp = test::call_service(&app, remove(&program, &editor, &admin).to_request()).await;
+    assert_eq!(resp.status(), 403);
+    let resp = test::call_service(&app, remove(&program, &admin, &designer).to_request()).await;
+    assert_eq!(resp.status(), 404);
+
+    // Every change is in the site's audit trail and in the affected wallet's notifications
+    let options = mongodb::options::FindOptions::builder().sort(doc! { "created_at": 1 }).build();
+    let events: Vec<Document> = db.collection::<Document>("site_events")
+        .find(doc! { "program_address": &program }, options).await.unwrap()
+        .try_collect().await.unwrap();
+    let kinds: Vec<(&str, &str)> = events.iter()
+        .map(|e| (e.get_str("kind").unwrap(), e.get_str("role").unwrap()))
+        .collect();
+    assert_eq!(kinds, vec![("collaborator_added", "admin"), ("collaborator_added", "editor")]);
+    assert_eq!(events[1].get_str("actor").unwrap(), admin.pubkey().to_string());
+    let notified = db.collection::<Document>("notifications")
+        .count_documents(doc! { "wallet": editor.pubkey().to_string(), "kind": "collaborator_added" }, None)
+        .await
+        .unwrap();
+    assert_eq!(notified, 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_editor_edits_content_but_not_domains() {
+    let db = common::test_db().await;
+    let (owner, editor) = (Keypair::new(), Keypair::new());
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+    let app = collaborators_app!(db);
+    test::call_service(&app, add(&program, &owner, &editor, "editor").to_request()).await;
+
+    for fields in [
+        serde_json::json!({ "name": "Redesign" }),
+        serde_json::json!({ "access_policy": "public" }),
+        serde_json::json!({ "noindex": true }),
+    ] {
+        let resp = test::call_service(&app, patch(&db, &program, &editor, fields.clone()).await.to_request()).await;
+        assert_eq!(resp.status(), 200, "{}", fields);
+    }
+    let resp = test::call_service(&app, deploy(&program, &editor).to_request()).await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = test::call_service(
+        &app,
+        patch(&db, &program, &editor, serde_json::json!({ "primary_domain": "agency.shadow" })).await.to_request(),
+    ).await;
+    assert_eq!(resp.status(), 403);
+
+    // The dashboard lists the site with the editor's role
+    let req = test::TestRequest::get()
+        .uri("/api/sites/mine")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&editor)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["sites"].as_array().unwrap().len(), 1);
+    assert_eq!(body["sites"][0]["program_address"], program);
+    assert_eq!(body["sites"][0]["role"], "editor");
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_removal_revokes_access_immediately() {
+    let db = common::test_db().await;
+    let (owner, editor) = (Keypair::new(), Keypair::new());
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+    let app = collaborators_app!(db);
+    test::call_service(&app, add(&program, &owner, &editor, "editor").to_request()).await;
+
+    // A token minted by the editor carries the editor's access
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/sites/{}/deploy-tokens", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&editor)))
+        .set_json(serde_json::json!({ "name": "designer" }))
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    let token = created["token"].as_str().unwrap().to_string();
+    assert_eq!(created["deploy_token"]["owner_pubkey"], editor.pubkey().to_string());
+    let publish = |token: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/sites/{}/content", program))
+            .insert_header((DEPLOY_TOKEN_HEADER, token.to_string()))
+            .set_json(serde_json::json!({ "storage_cid": NEXT_CID }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, remove(&program, &owner, &editor).to_request()).await;
+    assert_eq!(resp.status(), 200);
+
+    let resp = test::call_service(&app, patch(&db, &program, &editor, serde_json::json!({ "name": "Late" })).await.to_request()).await;
+    assert_eq!(resp.status(), 403);
+    let resp = test::call_service(&app, deploy(&program, &editor).to_request()).await;
+    assert_eq!(resp.status(), 403);
+    let resp = test::call_service(&app, publish(&token)).await;
+    assert_eq!(resp.status(), 403);
+
+    let req = test::TestRequest::get()
+        .uri("/api/sites/mine")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&editor)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["sites"].as_array().unwrap().is_empty());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_editor_puts_until_removed() {
+    let db = common::test_db().await;
+    let (owner, editor) = (Keypair::new(), Keypair::new());
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+    // Not in the registry, so only the record's owner and collaborators count
+    let (_rpc, anchor) = common::empty_registry().await;
+    let app = collaborators_app!(db, anchor);
+    test::call_service(&app, add(&program, &owner, &editor, "editor").to_request()).await;
+    let put = |wallet: &Keypair, name: &str| {
+        test::TestRequest::put()
+            .uri(&format!("/api/sites/{}", program))
+            .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+            .set_json(serde_json::json!({ "owner_pubkey": wallet.pubkey().to_string(), "storage_cid": NEXT_CID, "name": name }))
+            .to_request()
+    };
+
+    assert_eq!(test::call_service(&app, put(&editor, "Redesign")).await.status(), 200);
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!((site.name.as_deref(), site.storage_cid.as_str()), (Some("Redesign"), NEXT_CID));
+    assert_eq!(site.owner_pubkey, owner.pubkey().to_string());
+
+    let resp = test::call_service(&app, remove(&program, &owner, &editor).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(test::call_service(&app, put(&editor, "Late")).await.status(), 403);
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.name.as_deref(), Some("Redesign"));
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_registry_demotions_apply_to_every_write() {
+    let db = common::test_db().await;
+    let (old_owner, new_owner, editor) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let program_key = Pubkey::new_unique();
+    let program = program_key.to_string();
+    insert_site(&db, &program, &old_owner).await;
+    let (rpc, anchor) = common::empty_registry().await;
+    let registry = AnchorClient::with_programs(rpc.uri(), *anchor.registry_program_id(), Pubkey::new_unique());
+    common::mount_registry_site(&rpc, &registry, &program_key, &old_owner.pubkey(), &[editor.pubkey()]).await;
+    let app = collaborators_app!(db, anchor);
+
+    // Listed as an editor on-chain, without being a collaborator on the record
+    let resp = test::call_service(&app, patch(&db, &program, &editor, serde_json::json!({ "name": "Draft" })).await.to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(test::call_service(&app, deploy(&program, &editor).to_request()).await.status(), 200);
+
+    // remove_editor and transfer_ownership land on-chain; the record still names the old owner
+    rpc.reset().await;
+    common::mount_empty_registry(&rpc).await;
+    common::mount_registry_site(&rpc, &registry, &program_key, &new_owner.pubkey(), &[]).await;
+
+    for wallet in [&editor, &old_owner] {
+        let resp = test::call_service(&app, patch(&db, &program, wallet, serde_json::json!({ "name": "Late" })).await.to_request()).await;
+        assert_eq!(resp.status(), 403);
+        assert_eq!(test::call_service(&app, deploy(&program, wallet).to_request()).await.status(), 403);
+    }
+    let resp = test::call_service(&app, add(&program, &old_owner, &editor, "editor").to_request()).await;
+    assert_eq!(resp.status(), 403);
+
+    let resp = test::call_service(&app, patch(&db, &program, &new_owner, serde_json::json!({ "name": "Handed over" })).await.to_request()).await;
+    assert_eq!(resp.status(), 200);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
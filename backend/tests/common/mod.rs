@@ -0,0 +1,220 @@
+// Shared helpers for Shadow backend integration tests
+#![allow(dead_code)]
+
+use mongodb::{options::ClientOptions, Client, Database};
+use sha2::{Digest, Sha256};
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::config::ShadowConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use std::env;
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Placeholder DATABASE_URL for tests that only need a config, never a connection
+const OFFLINE_DATABASE_URL: &str = "mongodb://127.0.0.1:1";
+
+/// Connect to a fresh, uniquely named test database.
+/// Panics when DATABASE_URL is not set; tests that call this are #[ignore]d and run with --include-ignored.
+pub async fn test_db() -> Database {
+    dotenv::dotenv().ok();
+
+    let database_url = match env::var("DATABASE_URL") {
+        Ok(url) if url != OFFLINE_DATABASE_URL => url,
+        _ => panic!("DATABASE_URL must be set to run MongoDB-backed tests"),
+    };
+
+    let client_options = ClientOptions::parse(&database_url).await
+        .expect("Failed to parse DATABASE_URL");
+    let client = Client::with_options(client_options)
+        .expect("Failed to create MongoDB client");
+
+    let name = format!("shadow_test_{}", uuid::Uuid::new_v4().simple());
+    client.database(&name)
+}
+
+/// Redis for broker tests, from REDIS_URL. Panics when it is not set; those tests are #[ignore]d.
+pub fn test_redis_url() -> String {
+    dotenv::dotenv().ok();
+    match env::var("REDIS_URL") {
+        Ok(url) if !url.is_empty() => url,
+        _ => panic!("REDIS_URL must be set to run Redis-backed tests"),
+    }
+}
+
+/// A database handle that never connects, for tests that fail before touching MongoDB
+pub async fn offline_db() -> Database {
+    Client::with_uri_str(OFFLINE_DATABASE_URL)
+        .await
+        .expect("Failed to build offline MongoDB client")
+        .database("shadow_offline")
+}
+
+/// Config from the environment, with a placeholder DATABASE_URL when none is set.
+/// The environment itself is left alone, so Mongo-backed tests in the same binary still skip or run as before.
+pub fn test_config() -> ShadowConfig {
+    dotenv::dotenv().ok();
+    ShadowConfig::from_vars(|key| match env::var(key) {
+        Err(_) if key == "DATABASE_URL" => Ok(OFFLINE_DATABASE_URL.to_string()),
+        value => value,
+    })
+    .expect("Failed to load test config")
+}
+
+/// Sign a message the same way a Solana wallet's signMessage does for AresAuth
+pub fn sign_message(keypair: &Keypair, message: &[u8]) -> String {
+    let mut message_with_prefix = Vec::new();
+    message_with_prefix.extend_from_slice(b"\xffsolana offchain message");
+    message_with_prefix.push(message.len() as u8);
+    message_with_prefix.extend_from_slice(message);
+
+    let mut hasher = Sha256::new();
+    hasher.update(&message_with_prefix);
+    let message_hash = hasher.finalize();
+
+    keypair.sign_message(&message_hash[..]).to_string()
+}
+
+/// Build an X-Shadow-Auth header value for the given keypair at the given timestamp
+pub fn auth_header_at(keypair: &Keypair, timestamp: i64) -> String {
+    let wallet = keypair.pubkey().to_string();
+    let nonce = uuid::Uuid::new_v4().to_string();
+    let challenge = AresAuth::create_challenge(&wallet, timestamp, &nonce);
+    let signature = sign_message(keypair, challenge.as_bytes());
+
+    serde_json::json!({
+        "wallet": wallet,
+        "signature": signature,
+        "timestamp": timestamp,
+        "nonce": nonce,
+    })
+    .to_string()
+}
+
+/// Build a fresh X-Shadow-Auth header value for the given keypair
+pub fn auth_header(keypair: &Keypair) -> String {
+    auth_header_at(keypair, chrono::Utc::now().timestamp())
+}
+
+/// JSON-RPC stand-in that accepts and confirms every transaction sent to it
+pub struct SubmittingRpc {
+    pub sent: Arc<Mutex<Vec<Transaction>>>,
+}
+
+impl Respond for SubmittingRpc {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        use base64::{engine::general_purpose, Engine as _};
+
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let result = match body["method"].as_str().unwrap_or_default() {
+            "getVersion" => serde_json::json!({ "solana-core": "1.18.26", "feature-set": 0 }),
+            "getLatestBlockhash" => serde_json::json!({
+                "context": { "slot": 1 },
+                "value": { "blockhash": solana_sdk::hash::Hash::new_unique().to_string(), "lastValidBlockHeight": 100 }
+            }),
+            "getMinimumBalanceForRentExemption" => serde_json::json!(1_461_600),
+            "sendTransaction" => {
+                let encoded = body["params"][0].as_str().unwrap();
+                let bytes = general_purpose::STANDARD.decode(encoded).unwrap();
+                let tx: Transaction = bincode::deserialize(&bytes).unwrap();
+                let signature = tx.signatures[0].to_string();
+                self.sent.lock().unwrap().push(tx);
+                serde_json::json!(signature)
+            }
+            "getSignatureStatuses" => serde_json::json!({
+                "context": { "slot": 1 },
+                "value": [{ "slot": 1, "confirmations": null, "err": null, "status": { "Ok": null }, "confirmationStatus": "finalized" }]
+            }),
+            "isBlockhashValid" => serde_json::json!({ "context": { "slot": 1 }, "value": true }),
+            other => panic!("unexpected RPC method {}", other),
+        };
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": result,
+            "id": body["id"]
+        }))
+    }
+}
+
+/// Mock RPC that confirms submitted transactions, plus the list they are recorded in
+pub async fn submitting_rpc() -> (MockServer, Arc<Mutex<Vec<Transaction>>>) {
+    let server = MockServer::start().await;
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    Mock::given(method("POST"))
+        .respond_with(SubmittingRpc { sent: Arc::clone(&sent) })
+        .mount(&server)
+        .await;
+    (server, sent)
+}
+
+/// Mock RPC without any registry accounts, for handlers that look sites up on-chain
+pub async fn empty_registry() -> (MockServer, AnchorClient) {
+    let server = MockServer::start().await;
+    mount_empty_registry(&server).await;
+    let anchor = AnchorClient::with_programs(server.uri(), Pubkey::new_unique(), Pubkey::new_unique());
+    (server, anchor)
+}
+
+/// Answer the version check, and every account lookup with nothing. Accounts mounted on top win.
+pub async fn mount_empty_registry(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "solana-core": "1.18.26", "feature-set": 0 },
+            "id": 1
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": null },
+            "id": 1
+        })))
+        .with_priority(10)
+        .mount(server)
+        .await;
+}
+
+/// Serve the registry `Site` account for `program`, owned by `owner` with `editors` listed
+pub async fn mount_registry_site(server: &MockServer, anchor: &AnchorClient, program: &Pubkey, owner: &Pubkey, editors: &[Pubkey]) {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let mut data = Sha256::digest(b"account:Site")[..8].to_vec();
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(program.as_ref());
+    for s in ["Team", "", "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"] {
+        data.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        data.extend_from_slice(s.as_bytes());
+    }
+    data.extend_from_slice(&[0; 8 + 8 + 4]);
+    data.extend_from_slice(&(editors.len() as u32).to_le_bytes());
+    for editor in editors {
+        data.extend_from_slice(editor.as_ref());
+    }
+
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "method": "getAccountInfo",
+            "params": [anchor.site_pda(program).to_string()]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": {
+                "data": [general_purpose::STANDARD.encode(&data), "base64"],
+                "executable": false,
+                "lamports": 5_000_000,
+                "owner": anchor.registry_program_id().to_string(),
+                "rentEpoch": 0,
+                "space": data.len(),
+            } },
+            "id": 1
+        })))
+        .mount(server)
+        .await;
+}
@@ -0,0 +1,72 @@
+// Integration tests for Aphrodite compressed NFT lookups via the DAS API
+mod common;
+
+use serde_json::Value;
+use shadow_backend::aphrodite::AphroditeNFTManager;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use wiremock::matchers::{body_partial_json, method, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn asset(id: &str, name: &str, compressed: bool, owner: &str) -> Value {
+    serde_json::json!({
+        "id": id,
+        "content": {
+            "json_uri": format!("https://arweave.net/{}", id),
+            "metadata": { "name": name, "symbol": "CNFT" },
+            "links": { "image": format!("https://img.example/{}.png", id) },
+            "files": []
+        },
+        "grouping": [{ "group_key": "collection", "group_value": "Coll1111" }],
+        "compression": { "compressed": compressed },
+        "ownership": { "owner": owner }
+    })
+}
+
+async fn mount_page(server: &MockServer, page: u64, total: u64, items: Vec<Value>) {
+    Mock::given(method("POST"))
+        .and(query_param("api-key", "test-key"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAssetsByOwner", "params": { "page": page } })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "shadow",
+            "result": { "total": total, "limit": 1000, "page": page, "items": items }
+        })))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_compressed_nfts_follow_pagination_and_skip_uncompressed() {
+    let owner = Pubkey::new_unique().to_string();
+    let das = MockServer::start().await;
+    mount_page(&das, 1, 1001, vec![
+        asset("cnft-1", "Leaf One", true, &owner),
+        asset("regular-1", "Token Account NFT", false, &owner),
+    ]).await;
+    mount_page(&das, 2, 1, vec![asset("cnft-2", "", true, &owner)]).await;
+
+    let manager = AphroditeNFTManager::new(Arc::new(common::offline_db().await), "http://127.0.0.1:1".to_string())
+        .with_das_api(das.uri(), Some("test-key".to_string()));
+    let nfts = manager.get_compressed_nfts(&owner).await.unwrap();
+
+    assert_eq!(nfts.len(), 2);
+    assert_eq!(nfts[0].mint, "cnft-1");
+    assert_eq!(nfts[0].name, "Leaf One");
+    assert_eq!(nfts[0].symbol, "CNFT");
+    assert_eq!(nfts[0].uri.as_deref(), Some("https://arweave.net/cnft-1"));
+    assert_eq!(nfts[0].image_uri.as_deref(), Some("https://img.example/cnft-1.png"));
+    assert_eq!(nfts[0].collection.as_deref(), Some("Coll1111"));
+    assert_eq!(nfts[0].owner, owner);
+    assert!(nfts[0].compressed);
+    assert_eq!(nfts[1].name, "Unknown NFT");
+}
+
+#[tokio::test]
+async fn test_compressed_nfts_need_an_api_key() {
+    let manager = AphroditeNFTManager::new(Arc::new(common::offline_db().await), "http://127.0.0.1:1".to_string())
+        .with_das_api("http://127.0.0.1:1".to_string(), None);
+    let err = manager.get_compressed_nfts(&Pubkey::new_unique().to_string()).await.unwrap_err();
+    assert!(err.contains("not configured"));
+}
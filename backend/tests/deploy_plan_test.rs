@@ -0,0 +1,151 @@
+// Integration tests for dry-run deploy plans (POST /api/sdk/deploy/plan)
+mod common;
+
+use actix_web::{test, web, App};
+use serde_json::Value;
+use shadow_backend::handlers;
+use shadow_backend::metis::{site_account_space, DeployPlan};
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::storage::BundlrStorage;
+use solana_sdk::pubkey::Pubkey;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// RPC stand-in for rent and balance lookups; rent is 6960 lamports per byte plus the account header
+struct PlanRpc {
+    balance: u64,
+}
+
+fn rent(len: usize) -> u64 {
+    (len as u64 + 128) * 6_960
+}
+
+impl Respond for PlanRpc {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        let result = match body["method"].as_str().unwrap_or_default() {
+            "getVersion" => serde_json::json!({ "solana-core": "1.18.26", "feature-set": 0 }),
+            "getMinimumBalanceForRentExemption" => serde_json::json!(rent(body["params"][0].as_u64().unwrap() as usize)),
+            "getBalance" => serde_json::json!({ "context": { "slot": 1 }, "value": self.balance }),
+            other => panic!("unexpected RPC method {}", other),
+        };
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "result": result, "id": body["id"] }))
+    }
+}
+
+async fn rpc(balance: u64) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(PlanRpc { balance }).mount(&server).await;
+    server
+}
+
+macro_rules! plan_app {
+    ($db:expr, $rpc:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($rpc.uri()))
+                .app_data(web::Data::new(BundlrStorage::new()))
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .route("/api/sdk/deploy/plan", web::post().to(handlers::plan_deploy)),
+        )
+        .await
+    };
+}
+
+fn plan_request(wallet: &str, domain: Option<&str>, mint_token: bool) -> Value {
+    serde_json::json!({
+        "manifest": { "name": "site", "version": "0.1.0", "storage": "ipfs", "network": "devnet" },
+        "files": [{ "path": "index.html", "size": 2048 }, { "path": "shadow.json", "size": 70 }],
+        "wallet": wallet,
+        "domain": domain,
+        "mintToken": mint_token,
+        "network": "devnet"
+    })
+}
+
+/// Site account rent plus the registration fee, and the mint's when a token is minted
+fn estimate(mint_token: bool) -> u64 {
+    let site = rent(site_account_space("site".len(), 0)) + 5_000;
+    if mint_token { site + rent(82) + 10_000 } else { site }
+}
+
+#[actix_web::test]
+async fn test_plan_with_insufficient_balance() {
+    let rpc = rpc(estimate(false) + 1).await;
+    let app = plan_app!(common::offline_db().await, rpc);
+    let wallet = Pubkey::new_unique().to_string();
+
+    let req = test::TestRequest::post().uri("/api/sdk/deploy/plan").set_json(plan_request(&wallet, None, false)).to_request();
+    let plan: DeployPlan = test::call_and_read_body_json(&app, req).await;
+    assert!(plan.deployable, "{:?}", plan.problems);
+    assert_eq!(plan.estimated_lamports, estimate(false));
+    assert_eq!((plan.files, plan.total_bytes), (2, 2118));
+    assert_eq!(plan.domain_available, None);
+
+    // Minting the site token as well costs more than the wallet holds
+    let req = test::TestRequest::post().uri("/api/sdk/deploy/plan").set_json(plan_request(&wallet, None, true)).to_request();
+    let plan: DeployPlan = test::call_and_read_body_json(&app, req).await;
+    assert!(!plan.deployable);
+    assert_eq!(plan.estimated_lamports, estimate(true));
+    assert_eq!(plan.balance_lamports, estimate(false) + 1);
+    assert_eq!(plan.problems.len(), 1);
+    assert!(plan.problems[0].contains("below the estimated"), "{}", plan.problems[0]);
+}
+
+#[actix_web::test]
+async fn test_plan_rejects_invalid_projects() {
+    let rpc = rpc(0).await;
+    let app = plan_app!(common::offline_db().await, rpc);
+    let wallet = Pubkey::new_unique().to_string();
+
+    let mut unnamed = plan_request(&wallet, None, false);
+    unnamed["manifest"]["name"] = serde_json::json!("");
+    let mut unknown_storage = plan_request(&wallet, None, false);
+    unknown_storage["manifest"]["storage"] = serde_json::json!("s3");
+    let mut escaping = plan_request(&wallet, None, false);
+    escaping["files"][0]["path"] = serde_json::json!("../secrets");
+    let mut missing_entry = plan_request(&wallet, None, false);
+    missing_entry["manifest"]["languages"] = serde_json::json!({ "en": "en/index.html" });
+    let mut empty = plan_request(&wallet, None, false);
+    empty["files"] = serde_json::json!([]);
+    let bad_wallet = plan_request("not-a-wallet", None, false);
+    let bad_domain = plan_request(&wallet, Some("no spaces.shadow"), false);
+
+    for body in [unnamed, unknown_storage, escaping, missing_entry, empty, bad_wallet, bad_domain] {
+        let req = test::TestRequest::post().uri("/api/sdk/deploy/plan").set_json(&body).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400, "{}", body);
+    }
+    // Nothing was priced for any of them
+    assert!(rpc.received_requests().await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_plan_with_domain_taken() {
+    let db = common::test_db().await;
+    let rpc = rpc(1_000_000_000).await;
+    let app = plan_app!(db, rpc);
+    let (wallet, holder) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+    let olympus = OlympusCA::new(db.clone());
+    olympus.register_domain("taken.shadow", &holder, &Pubkey::new_unique().to_string()).await.unwrap();
+    olympus.register_domain("mine.shadow", &wallet, &Pubkey::new_unique().to_string()).await.unwrap();
+
+    let req = test::TestRequest::post().uri("/api/sdk/deploy/plan").set_json(plan_request(&wallet, Some("taken.shadow"), false)).to_request();
+    let plan: DeployPlan = test::call_and_read_body_json(&app, req).await;
+    assert!(!plan.deployable);
+    assert_eq!(plan.domain_available, Some(false));
+    assert_eq!(plan.problems, ["Domain taken.shadow is already registered"]);
+
+    // The wallet's own domain, or a free one, can be deployed to
+    for domain in ["mine.shadow", "free.shadow"] {
+        let req = test::TestRequest::post().uri("/api/sdk/deploy/plan").set_json(plan_request(&wallet, Some(domain), false)).to_request();
+        let plan: DeployPlan = test::call_and_read_body_json(&app, req).await;
+        assert!(plan.deployable, "{}: {:?}", domain, plan.problems);
+        assert_eq!(plan.domain_available, Some(true));
+    }
+
+    // Planning wrote nothing
+    assert!(olympus.get_domain("free.shadow").await.unwrap().is_none());
+    db.drop(None).await.expect("Failed to drop test database");
+}
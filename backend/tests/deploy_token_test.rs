@@ -0,0 +1,203 @@
+// Integration tests for site-scoped deploy tokens publishing content from CI
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::apollo::ApolloValidator;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::config::DeployConfig;
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::mnemosyne::Mnemosyne;
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::storage::PinataStorage;
+use shadow_backend::tyche::TycheOwnership;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::path::Path;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const LIVE_CID: &str = "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+const CLEAN_CID: &str = "ipfs://bafybeib3azuaj5wr2b3roph6judaaltkmhtpehblfzsiif4week7dl6nry";
+
+macro_rules! deploy_app {
+    ($db:expr, $config:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(Mnemosyne::new($db.clone())))
+                .app_data(web::Data::new(HephaestusCache::new(16, 60)))
+                .app_data(web::Data::new(PinataStorage::new()))
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(ApolloValidator::new()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(TycheOwnership::new($db.clone(), "http://127.0.0.1:1".to_string())))
+                .app_data(web::Data::new($config))
+                .route("/api/sites/{program_address}/content", web::post().to(handlers::publish_site_content))
+                .route("/api/sites/{program_address}/deploy-tokens", web::post().to(handlers::create_deploy_token))
+                .route("/api/sites/{program_address}/deploy-tokens", web::get().to(handlers::list_deploy_tokens))
+                .route(
+                    "/api/sites/{program_address}/deploy-tokens/{token_id}",
+                    web::delete().to(handlers::revoke_deploy_token),
+                )
+                .route("/api/domains/{domain}", web::put().to(handlers::update_domain)),
+        )
+        .await
+    };
+}
+
+/// Serve the clean fixture bundle under `/{cid}/` on the mock gateway
+async fn mount_clean_bundle(gateway: &MockServer) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/deploy/clean");
+    let hash = CLEAN_CID.strip_prefix("ipfs://").unwrap();
+    let mut dirs = vec![root.clone()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let file = entry.unwrap().path();
+            if file.is_dir() {
+                dirs.push(file);
+                continue;
+            }
+            let relative = file.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/");
+            Mock::given(method("GET"))
+                .and(path(format!("/{}/{}", hash, relative)))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(std::fs::read(&file).unwrap()))
+                .mount(gateway)
+                .await;
+        }
+    }
+}
+
+async fn insert_site(db: &mongodb::Database, program: &str, owner: &str) {
+    let now = mongodb::bson::DateTime::now();
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": program,
+            "owner_pubkey": owner,
+            "storage_cid": LIVE_CID,
+            "name": "CI site",
+            "description": null,
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+}
+
+fn publish(program: &str, token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(&format!("/api/sites/{}/content", program))
+        .insert_header(("X-Shadow-Deploy-Token", token))
+        .set_json(serde_json::json!({ "storage_cid": CLEAN_CID }))
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_token_is_scoped_to_one_site_and_revocable() {
+    let db = common::test_db().await;
+    let gateway = MockServer::start().await;
+    mount_clean_bundle(&gateway).await;
+
+    let owner = Keypair::new();
+    let site_a = Pubkey::new_unique().to_string();
+    let site_b = Pubkey::new_unique().to_string();
+    insert_site(&db, &site_a, &owner.pubkey().to_string()).await;
+    insert_site(&db, &site_b, &owner.pubkey().to_string()).await;
+
+    let mut config = common::test_config();
+    config.deploy = DeployConfig {
+        ipfs_gateway_url: gateway.uri(),
+        arweave_gateway_url: gateway.uri(),
+        min_success_rate: 0.95,
+        html_check: false,
+        request_timeout_seconds: 5,
+    };
+    let app = deploy_app!(db, config);
+
+    // Only the owner can mint tokens
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/sites/{}/deploy-tokens", site_a))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .set_json(serde_json::json!({ "name": "github-actions" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/sites/{}/deploy-tokens", site_a))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(serde_json::json!({ "name": "github-actions", "expires_in_days": 30 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let created: Value = test::read_body_json(resp).await;
+    let token = created["token"].as_str().unwrap().to_string();
+    let token_id = created["deploy_token"]["_id"].as_str().unwrap().to_string();
+    assert!(created["deploy_token"].get("token_hash").is_none());
+
+    let body: Value = test::call_and_read_body_json(&app, publish(&site_a, &token).to_request()).await;
+    assert_eq!(body["live"], true, "{}", body);
+    let site = shadow_backend::db::get_site(&db, &site_a).await.unwrap().unwrap();
+    assert_eq!(site.storage_cid, CLEAN_CID);
+    let version = db.collection::<Document>("site_versions")
+        .find_one(doc! { "program_address": &site_a }, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(version.get_str("deployed_by").unwrap(), format!("deploy-token:{}", token_id));
+
+    // Use is tracked and audit-logged with the token id
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/sites/{}/deploy-tokens", site_a))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .to_request();
+    let listed: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(!listed["deploy_tokens"][0]["last_used_at"].is_null());
+    let event = db.collection::<Document>("deploy_token_events")
+        .find_one(doc! { "token_id": &token_id }, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.get_str("action").unwrap(), "publish_content");
+    assert_eq!(event.get_document("detail").unwrap().get_str("version_id").unwrap(), body["version_id"].as_str().unwrap());
+
+    // The same owner's other site is out of scope
+    assert_eq!(test::call_service(&app, publish(&site_b, &token).to_request()).await.status(), 403);
+    assert_eq!(shadow_backend::db::get_site(&db, &site_b).await.unwrap().unwrap().storage_cid, LIVE_CID);
+
+    // Domain endpoints don't accept deploy tokens at all
+    let req = test::TestRequest::put()
+        .uri("/api/domains/ci.shadow")
+        .insert_header(("X-Shadow-Deploy-Token", token.as_str()))
+        .set_json(serde_json::json!({
+            "domain": "ci.shadow",
+            "program_address": &site_b,
+            "owner_pubkey": owner.pubkey().to_string(),
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/sites/{}/deploy-tokens/{}", site_a, token_id))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    assert_eq!(test::call_service(&app, publish(&site_a, &token).to_request()).await.status(), 401);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_missing_or_malformed_token_is_rejected() {
+    let app = deploy_app!(common::offline_db().await, common::test_config());
+    let program = Pubkey::new_unique().to_string();
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/sites/{}/content", program))
+        .set_json(serde_json::json!({ "storage_cid": CLEAN_CID }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+    assert_eq!(test::call_service(&app, publish(&program, "not-a-token").to_request()).await.status(), 401);
+}
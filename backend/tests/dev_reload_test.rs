@@ -0,0 +1,146 @@
+// Integration tests for the Hermes live reload channel used by `hermes dev`
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::cerberus::DeployTokens;
+use shadow_backend::config::OutboxConfig;
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::mnemosyne::{Mnemosyne, OutboxRelay};
+use shadow_backend::storage::{BundlrStorage, PinataStorage};
+use shadow_backend::tyche::TycheOwnership;
+use shadow_backend::websocket::{HermesBroker, CONTENT_UPDATED_EVENT};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+const LIVE_CID: &str = "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+const NEXT_CID: &str = "ipfs://bafybeib3azuaj5wr2b3roph6judaaltkmhtpehblfzsiif4week7dl6nry";
+const STRICT_PAGE: &str = "<html><head>\
+    <meta http-equiv=\"Content-Security-Policy\" content=\"default-src 'self'\">\
+    </head><body>Hello</body></html>";
+
+async fn insert_site(db: &Database, program: &str, owner: &Keypair) {
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": program,
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": LIVE_CID,
+            "name": "Dev site",
+            "description": null,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_content_rotation_publishes_content_updated() {
+    let db = common::test_db().await;
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(Mnemosyne::new(db.clone())))
+            .app_data(web::Data::new(HephaestusCache::new(16, 60)))
+            .app_data(web::Data::new(common::test_config()))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(TycheOwnership::new(db.clone(), "http://127.0.0.1:1".to_string())))
+            .route("/api/sites/{program_address}/deploys", web::post().to(handlers::stage_deploy)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/sites/{}/deploys", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(serde_json::json!({ "storage_cid": NEXT_CID, "force": true }))
+        .to_request();
+    let deployed: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(deployed["live"], true);
+
+    let broker = Arc::new(HermesBroker::new());
+    let mut events = broker.subscribe(format!("program:{}", program)).await;
+    let config = OutboxConfig { poll_interval_ms: 10, batch_size: 10, max_attempts: 3, lease_seconds: 60 };
+    let relay = OutboxRelay::new(db.clone(), config).with_broker(Arc::clone(&broker));
+    assert_eq!(relay.run_once(&CancellationToken::new()).await.unwrap().processed, 1);
+
+    let published: Vec<Value> = std::iter::from_fn(|| events.try_recv().ok())
+        .map(|event| serde_json::from_str(&event).unwrap())
+        .collect();
+    let kinds: Vec<&str> = published.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(kinds, vec!["site_updated", CONTENT_UPDATED_EVENT]);
+    assert_eq!(published[1]["storage_cid"], NEXT_CID);
+    assert_eq!(published[1]["version_id"], deployed["version_id"]);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_reload_script_only_in_dev_mode() {
+    let db = common::test_db().await;
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+
+    // Pre-warm the cache so the content never has to come from a gateway
+    let hephaestus = HephaestusCache::new(16, 60);
+    hephaestus
+        .set(HephaestusCache::site_content_key(LIVE_CID, None), STRICT_PAGE.as_bytes().to_vec(), "text/html".to_string(), None)
+        .await
+        .unwrap();
+    let config = common::test_config();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(PinataStorage::new()))
+            .app_data(web::Data::new(BundlrStorage::new()))
+            .app_data(web::Data::new(hephaestus))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .app_data(web::Data::new(config))
+            .route("/api/sites/{program_address}/content", web::get().to(handlers::get_site_content)),
+    )
+    .await;
+    let content_uri = format!("/api/sites/{}/content", program);
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&content_uri).to_request()).await;
+    assert!(resp.headers().get("content-security-policy").is_none());
+    let body = test::read_body(resp).await;
+    assert_eq!(body, STRICT_PAGE.as_bytes());
+
+    let req = test::TestRequest::get().uri(&format!("{}?dev=1", content_uri)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("cache-control").unwrap(), "no-store");
+    let csp = resp.headers().get("content-security-policy").unwrap().to_str().unwrap().to_string();
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(!body.contains("http-equiv"), "meta policy moved to the header");
+    let nonce = body.split("<script nonce=\"").nth(1).unwrap().split('"').next().unwrap();
+    assert!(csp.contains(&format!("script-src 'self' 'nonce-{}'", nonce)), "{}", csp);
+    assert!(csp.contains("connect-src 'self' ws://"), "{}", csp);
+
+    // Deploy tokens only switch on dev mode when minted for it
+    let tokens = DeployTokens::new(db.clone());
+    let owner_key = owner.pubkey().to_string();
+    let (_, ci_token) = tokens.create(&program, &owner_key, "ci", false, None).await.unwrap();
+    let (_, dev_token) = tokens.create(&program, &owner_key, "dev", true, None).await.unwrap();
+    for (token, injected) in [(ci_token, false), (dev_token, true)] {
+        let req = test::TestRequest::get()
+            .uri(&content_uri)
+            .insert_header(("X-Shadow-Deploy-Token", token))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(String::from_utf8(body.to_vec()).unwrap().contains("<script nonce="), injected);
+    }
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
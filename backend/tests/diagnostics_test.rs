@@ -0,0 +1,188 @@
+// Tests for the Hygieia self-checks against stubbed dependencies
+mod common;
+
+use actix_web::{test, web, App};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::config::ShadowConfig;
+use shadow_backend::handlers;
+use shadow_backend::hygieia::{CheckStatus, DiagnosticCheck, DiagnosticsReport, Hygieia};
+use shadow_backend::storage::PinataStorage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::time::{Duration, Instant};
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const DEVNET_GENESIS: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+
+fn rpc_result(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+/// A devnet RPC that knows one deployed program
+async fn stub_rpc(deployed: &str) -> MockServer {
+    let rpc = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getGenesisHash" })))
+        .respond_with(rpc_result(Value::String(DEVNET_GENESIS.to_string())))
+        .mount(&rpc)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo", "params": [deployed] })))
+        .respond_with(rpc_result(serde_json::json!({
+            "context": { "slot": 1 },
+            "value": { "executable": true, "lamports": 1, "owner": "BPFLoaderUpgradeab1e11111111111111111111111", "data": ["", "base64"], "rentEpoch": 0 },
+        })))
+        .mount(&rpc)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(rpc_result(serde_json::json!({ "context": { "slot": 1 }, "value": null })))
+        .mount(&rpc)
+        .await;
+    rpc
+}
+
+fn config(rpc: &MockServer, network: Option<&str>, probe_urls: Vec<String>) -> ShadowConfig {
+    let mut config = common::test_config();
+    config.solana.rpc_url = rpc.uri();
+    config.auth.jwt_secret = Some("secret".to_string());
+    config.auth.admin_wallets = vec![Pubkey::new_unique().to_string()];
+    config.search_analytics.hash_key = Some("search-key".to_string());
+    config.diagnostics.solana_network = network.map(|n| n.to_string());
+    config.diagnostics.storage_probe = true;
+    config.diagnostics.probe_urls = probe_urls;
+    config.diagnostics.check_timeout_seconds = 1;
+    config
+}
+
+fn status_of(report: &DiagnosticsReport, name: &str) -> CheckStatus {
+    report.checks.iter().find(|c| c.name == name).unwrap_or_else(|| panic!("no {} check", name)).status
+}
+
+#[actix_web::test]
+async fn test_report_renders_pass_warn_and_fail() {
+    let healthy = DiagnosticsReport::new(vec![
+        DiagnosticCheck::pass("config_ranges", "fine"),
+        DiagnosticCheck::warn("solana_network", "no network configured", "Set SOLANA_NETWORK"),
+    ]);
+    assert_eq!(healthy.status, CheckStatus::Warn);
+    assert!(!healthy.failed());
+
+    let broken = DiagnosticsReport::new(vec![
+        DiagnosticCheck::pass("config_ranges", "fine"),
+        DiagnosticCheck::fail("storage_credentials", "missing", "Set PINATA_API_KEY"),
+    ]);
+    assert!(broken.failed());
+    let text = broken.render();
+    assert!(text.starts_with("Shadow self-check: FAIL (1 pass, 0 warn, 1 fail)"));
+    assert!(text.contains("[PASS] config_ranges"));
+    assert!(text.contains("[FAIL] storage_credentials"));
+    assert!(text.contains("-> Set PINATA_API_KEY"));
+    assert!(healthy.render().contains("[WARN] solana_network"));
+}
+
+#[tokio::test]
+async fn test_checks_against_stubbed_dependencies() {
+    let program = Pubkey::new_unique().to_string();
+    let rpc = stub_rpc(&program).await;
+
+    let pinata = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/pinning/pinJSONToIPFS"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "IpfsHash": "bafyselfcheck" })))
+        .mount(&pinata)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/pinning/unpin/bafyselfcheck"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&pinata)
+        .await;
+
+    let services = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/renderer")).respond_with(ResponseTemplate::new(200)).mount(&services).await;
+    Mock::given(method("GET")).and(path("/webhook")).respond_with(ResponseTemplate::new(503)).mount(&services).await;
+    let urls = vec![format!("{}/renderer", services.uri()), format!("{}/webhook", services.uri())];
+
+    let report = Hygieia::new(config(&rpc, Some("devnet"), urls.clone()), common::offline_db().await)
+        .with_storage(PinataStorage::new().with_credentials("key", "secret").with_api_url(&pinata.uri()))
+        .with_program_ids(vec![
+            ("registry".to_string(), program.clone()),
+            ("profiles".to_string(), Pubkey::new_unique().to_string()),
+        ])
+        .run()
+        .await;
+
+    assert_eq!(status_of(&report, "config_ranges"), CheckStatus::Pass);
+    assert_eq!(status_of(&report, "storage_credentials"), CheckStatus::Pass);
+    assert_eq!(status_of(&report, "storage_probe"), CheckStatus::Pass);
+    assert_eq!(status_of(&report, "solana_network"), CheckStatus::Pass);
+    assert_eq!(status_of(&report, "anchor_program:registry"), CheckStatus::Pass);
+    assert_eq!(status_of(&report, "anchor_program:profiles"), CheckStatus::Fail);
+    assert_eq!(status_of(&report, &format!("reachable:{}", urls[0])), CheckStatus::Pass);
+    assert_eq!(status_of(&report, &format!("reachable:{}", urls[1])), CheckStatus::Warn);
+    assert!(report.failed());
+
+    // Pointing a mainnet deployment at a devnet RPC is caught
+    let mut mainnet = config(&rpc, Some("mainnet-beta"), vec![]);
+    mainnet.diagnostics.storage_probe = false;
+    let report = Hygieia::new(mainnet, common::offline_db().await)
+        .with_storage(PinataStorage::new().with_credentials("key", "secret"))
+        .run()
+        .await;
+    let network = report.checks.iter().find(|c| c.name == "solana_network").unwrap();
+    assert_eq!(network.status, CheckStatus::Fail);
+    assert!(network.message.contains("devnet"));
+}
+
+#[tokio::test]
+async fn test_hung_dependency_times_out_alone() {
+    let rpc = stub_rpc(&Pubkey::new_unique().to_string()).await;
+    let services = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(30)))
+        .mount(&services)
+        .await;
+
+    let mut config = config(&rpc, None, vec![services.uri()]);
+    config.diagnostics.storage_probe = false;
+    let started = Instant::now();
+    let report = Hygieia::new(config, common::offline_db().await)
+        .with_storage(PinataStorage::new().with_credentials("key", "secret"))
+        .run()
+        .await;
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let hung = report.checks.iter().find(|c| c.name.starts_with("reachable:")).unwrap();
+    assert_eq!(hung.status, CheckStatus::Fail);
+    assert!(hung.message.contains("Timed out"));
+    // Other checks still completed
+    assert_eq!(status_of(&report, "solana_network"), CheckStatus::Warn);
+    assert!(report.checks.iter().all(|c| c.name != "storage_probe"));
+}
+
+#[actix_web::test]
+async fn test_diagnostics_endpoint_requires_admin() {
+    let admin = Keypair::new();
+    let mut config = common::test_config();
+    config.auth.admin_wallets = vec![admin.pubkey().to_string()];
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(common::offline_db().await))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(config))
+            .route("/api/diagnostics", web::get().to(handlers::get_diagnostics)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/diagnostics")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    let req = test::TestRequest::get().uri("/api/diagnostics").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
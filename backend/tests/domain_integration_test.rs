@@ -0,0 +1,334 @@
+// Integration tests for the Olympus domain lifecycle
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::Database;
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::apollo::ApolloValidator;
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::clock::{Clock, TestClock};
+use shadow_backend::olympus::{Domain, ExpiryStatus, OlympusCA, DOMAIN_GRACE_PERIOD_DAYS};
+use shadow_backend::tyche::TycheOwnership;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const DOMAIN: &str = "myapp.shadow";
+
+macro_rules! domain_app {
+    ($db:expr, $rpc_url:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(TycheOwnership::new($db.clone(), $rpc_url.to_string())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(ApolloValidator::new()))
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .app_data(web::Data::new($rpc_url))
+                .service(
+                    web::scope("/api")
+                        .route("/domains/search", web::get().to(handlers::search_domains))
+                        .route("/domains/{domain}", web::get().to(handlers::get_domain))
+                        .route("/domains", web::post().to(handlers::register_domain))
+                        .route("/domains/{domain}", web::put().to(handlers::update_domain))
+                        .route("/domains/{domain}/verify", web::post().to(handlers::verify_domain))
+                        .route("/domains/owner/{wallet}", web::get().to(handlers::list_owner_domains)),
+                ),
+        )
+        .await
+    };
+}
+
+/// Mock Solana RPC where every account lookup comes back empty
+async fn empty_rpc() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": null },
+            "id": 1
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn register_body(domain: &str, owner: &Keypair, program: &Pubkey) -> Value {
+    serde_json::json!({
+        "domain": domain,
+        "program_address": program.to_string(),
+        "owner_pubkey": owner.pubkey().to_string(),
+    })
+}
+
+async fn cleanup(db: Database) {
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_register_domain_returns_created() {
+    let db = common::test_db().await;
+    let rpc = empty_rpc().await;
+    let app = domain_app!(db, rpc.uri());
+
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique();
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(register_body(DOMAIN, &owner, &program))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["domain"], DOMAIN);
+
+    cleanup(db).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_register_taken_domain_conflicts() {
+    let db = common::test_db().await;
+    let rpc = empty_rpc().await;
+    let app = domain_app!(db, rpc.uri());
+
+    let owner = Keypair::new();
+    let squatter = Keypair::new();
+    let program = Pubkey::new_unique();
+
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(register_body(DOMAIN, &owner, &program))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&squatter)))
+        .set_json(register_body(DOMAIN, &squatter, &program))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+    // Re-registering as the same owner is an update
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(register_body(DOMAIN, &owner, &program))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+    cleanup(db).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_duplicate_domain_insert_is_a_duplicate_key() {
+    let db = common::test_db().await;
+    let rpc = empty_rpc().await;
+    let app = domain_app!(db, rpc.uri());
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique();
+
+    // Domain names are the _id, so the built-in _id index keeps them unique
+    let domains = db.collection::<mongodb::bson::Document>("domains");
+    domains.insert_one(mongodb::bson::doc! { "_id": DOMAIN, "owner_pubkey": owner.pubkey().to_string() }, None)
+        .await
+        .unwrap();
+    let err = domains.insert_one(mongodb::bson::doc! { "_id": DOMAIN, "owner_pubkey": "someone_else" }, None)
+        .await
+        .unwrap_err();
+    match *err.kind {
+        ErrorKind::Write(WriteFailure::WriteError(ref e)) => assert_eq!(e.code, 11000, "{}", err),
+        _ => panic!("expected a duplicate key error, got {}", err),
+    }
+
+    // And the API turns that into a conflict for anyone but the owner
+    let squatter = Keypair::new();
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&squatter)))
+        .set_json(register_body(DOMAIN, &squatter, &program))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+
+    cleanup(db).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_racing_registrations_have_one_winner() {
+    let db = common::test_db().await;
+    let olympus = OlympusCA::new(db.clone());
+    let wallets: Vec<String> = (0..8).map(|_| Pubkey::new_unique().to_string()).collect();
+    let program = Pubkey::new_unique().to_string();
+
+    // With no read before the write, only one wallet's upsert can create the domain
+    let results = futures_util::future::join_all(
+        wallets.iter().map(|wallet| olympus.register_domain(DOMAIN, wallet, &program)),
+    )
+    .await;
+    let winners: Vec<&String> = wallets.iter()
+        .zip(&results)
+        .filter(|(_, registered)| *registered.as_ref().unwrap())
+        .map(|(wallet, _)| wallet)
+        .collect();
+    assert_eq!(winners.len(), 1);
+    let record = olympus.get_domain(DOMAIN).await.unwrap().unwrap();
+    assert_eq!(&record.owner_pubkey, winners[0]);
+
+    // The winner can still re-register; everyone else is turned away
+    assert!(olympus.register_domain(DOMAIN, winners[0], &program).await.unwrap());
+    let loser = wallets.iter().find(|wallet| *wallet != winners[0]).unwrap();
+    assert!(!olympus.register_domain(DOMAIN, loser, &program).await.unwrap());
+
+    cleanup(db).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_verify_domain_without_program_on_chain() {
+    let db = common::test_db().await;
+    let rpc = empty_rpc().await;
+    let app = domain_app!(db, rpc.uri());
+
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique();
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(register_body(DOMAIN, &owner, &program))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/domains/{}/verify", DOMAIN))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    // The RPC mock must actually have been consulted
+    assert!(!rpc.received_requests().await.unwrap_or_default().is_empty());
+
+    // Unverified domains report verified: false
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/domains/{}", DOMAIN))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["verified"], false);
+
+    cleanup(db).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_update_domain_points_to_new_program() {
+    let db = common::test_db().await;
+    let rpc = empty_rpc().await;
+    let app = domain_app!(db, rpc.uri());
+
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique();
+    let new_program = Pubkey::new_unique();
+
+    let req = test::TestRequest::post()
+        .uri("/api/domains")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(register_body(DOMAIN, &owner, &program))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 201);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/domains/{}", DOMAIN))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(register_body(DOMAIN, &owner, &new_program))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/domains/{}", DOMAIN))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["program_address"], new_program.to_string());
+
+    cleanup(db).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_list_and_search_domains() {
+    let db = common::test_db().await;
+    let rpc = empty_rpc().await;
+    let app = domain_app!(db, rpc.uri());
+
+    let owner = Keypair::new();
+    for domain in ["myapp.shadow", "myblog.shadow"] {
+        let req = test::TestRequest::post()
+            .uri("/api/domains")
+            .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+            .set_json(register_body(domain, &owner, &Pubkey::new_unique()))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 201);
+    }
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/domains/owner/{}", owner.pubkey()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body.as_array().map(|a| a.len()), Some(2));
+
+    // Search only surfaces verified domains
+    OlympusCA::new(db.clone()).verify_domain("myapp.shadow", None).await.unwrap();
+
+    let req = test::TestRequest::get()
+        .uri("/api/domains/search?q=myap")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let results = body["items"].as_array().expect("search returns a page");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["_id"], "myapp.shadow");
+    assert!(body["next_cursor"].is_null());
+
+    cleanup(db).await;
+}
+
+#[tokio::test]
+async fn test_domain_enters_grace_period_then_expires() {
+    let clock = TestClock::starting_now();
+    let olympus = OlympusCA::new(common::offline_db().await).with_clock(clock.clone());
+    let now = clock.now_utc();
+    let domain = Domain {
+        domain: "renewing.shadow".to_string(),
+        owner_pubkey: Pubkey::new_unique().to_string(),
+        program_address: Pubkey::new_unique().to_string(),
+        verified: true,
+        created_at: now,
+        updated_at: now,
+        expires_at: Some(now + chrono::Duration::days(1)),
+        verification_failures: 0,
+        warning_interstitial: false,
+        verification_error: None,
+        verified_authority: None,
+    };
+    let day = std::time::Duration::from_secs(86_400);
+
+    assert_eq!(olympus.expiry_status(&domain), ExpiryStatus::Active);
+    clock.advance(day - std::time::Duration::from_secs(1));
+    assert_eq!(olympus.expiry_status(&domain), ExpiryStatus::Active);
+    clock.advance(std::time::Duration::from_secs(1));
+    assert_eq!(olympus.expiry_status(&domain), ExpiryStatus::GracePeriod);
+    clock.advance(day * DOMAIN_GRACE_PERIOD_DAYS as u32);
+    assert_eq!(olympus.expiry_status(&domain), ExpiryStatus::Expired);
+
+    let permanent = Domain { expires_at: None, ..domain };
+    assert_eq!(olympus.expiry_status(&permanent), ExpiryStatus::Active);
+}
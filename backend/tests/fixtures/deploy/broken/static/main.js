@@ -0,0 +1 @@
+document.getElementById("root").textContent = "ok";
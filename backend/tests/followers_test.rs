@@ -0,0 +1,340 @@
+// Integration tests for following profiles on-chain and listing follows through the follows mirror
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::db;
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn follow_account(follower: &Pubkey, followee: &Pubkey, created_at: i64) -> Vec<u8> {
+    let mut data = Sha256::digest(b"account:Follow")[..8].to_vec();
+    data.extend_from_slice(follower.as_ref());
+    data.extend_from_slice(followee.as_ref());
+    data.extend_from_slice(&created_at.to_le_bytes());
+    data
+}
+
+fn profile_account(wallet: &Pubkey, follower_count: u64, following_count: u64) -> Vec<u8> {
+    let mut data = Sha256::digest(b"account:Profile")[..8].to_vec();
+    data.extend_from_slice(wallet.as_ref());
+    data.extend_from_slice(&14u32.to_le_bytes());
+    data.extend_from_slice(b"ipfs://bafyone");
+    data.push(1);
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data.extend_from_slice(&follower_count.to_le_bytes());
+    data.extend_from_slice(&following_count.to_le_bytes());
+    data.push(0);
+    data
+}
+
+fn rpc_result(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "result": result, "id": 1 }))
+}
+
+/// Mock RPC answering the version check and blockhash requests the client makes
+async fn rpc_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(rpc_result(serde_json::json!({ "solana-core": "1.18.26", "feature-set": 0 })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getLatestBlockhash" })))
+        .respond_with(rpc_result(serde_json::json!({
+            "context": { "slot": 1 },
+            "value": { "blockhash": Hash::new_unique().to_string(), "lastValidBlockHeight": 100 }
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Answer getAccountInfo for `address` with `data` owned by `program`, or with nothing
+async fn mount_account(rpc: &MockServer, address: &Pubkey, data: Option<Vec<u8>>, program: &Pubkey) {
+    let value = data.map(|data| serde_json::json!({
+        "data": [general_purpose::STANDARD.encode(&data), "base64"],
+        "executable": false,
+        "lamports": 2_000_000,
+        "owner": program.to_string(),
+        "rentEpoch": 0,
+        "space": data.len(),
+    }));
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo", "params": [address.to_string()] })))
+        .respond_with(rpc_result(serde_json::json!({ "context": { "slot": 1 }, "value": value })))
+        .mount(rpc)
+        .await;
+}
+
+macro_rules! follow_app {
+    ($db:expr, $anchor:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new($anchor))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .route("/api/profiles/{wallet}/followers", web::get().to(handlers::get_followers))
+                .route("/api/profiles/{wallet}/following", web::get().to(handlers::get_following))
+                .route("/api/profiles/{wallet}/follow", web::post().to(handlers::follow_profile))
+                .route("/api/profiles/{wallet}/follow", web::delete().to(handlers::unfollow_profile)),
+        )
+        .await
+    };
+}
+
+fn follow_request(method: test::TestRequest, wallet: &Keypair, followee: &Keypair) -> test::TestRequest {
+    method
+        .uri(&format!("/api/profiles/{}/follow", followee.pubkey()))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+}
+
+fn decode_transaction(body: &Value) -> Transaction {
+    let bytes = general_purpose::STANDARD.decode(body["transaction"].as_str().unwrap()).unwrap();
+    bincode::deserialize(&bytes).unwrap()
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_followers_are_listed_from_the_mirror_alone() {
+    let db = common::test_db().await;
+    let followee = Pubkey::new_unique();
+    let (early, late) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for (follower, at) in [(&early, 1_700_000_000), (&late, 1_700_000_900)] {
+        let at = chrono::DateTime::from_timestamp(at, 0).unwrap();
+        db::follow_user(&db, &follower.to_string(), &followee.to_string(), at).await.unwrap();
+    }
+
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), Pubkey::new_unique());
+    let app = follow_app!(db, anchor);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/profiles/{}/followers", followee))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+    assert_eq!(body["items"][0]["wallet"], late.to_string());
+    assert_eq!(body["items"][0]["followed_at"], "2023-11-14T22:28:20Z");
+    assert_eq!(body["items"][1]["wallet"], early.to_string());
+
+    let req = test::TestRequest::get().uri("/api/profiles/not-a-wallet/followers").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    // Reads never reach the RPC node
+    assert!(rpc.received_requests().await.unwrap().is_empty());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_follow_hands_back_a_transaction_to_sign() {
+    let profiles = Pubkey::new_unique();
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), profiles);
+    let follow_pda = anchor.follow_pda(&alice.pubkey(), &bob.pubkey());
+    for wallet in [&alice, &bob] {
+        mount_account(&rpc, &anchor.profile_pda(&wallet.pubkey()), Some(profile_account(&wallet.pubkey(), 0, 0)), &profiles).await;
+    }
+    mount_account(&rpc, &follow_pda, None, &profiles).await;
+    let (alice_profile, bob_profile) = (anchor.profile_pda(&alice.pubkey()), anchor.profile_pda(&bob.pubkey()));
+    let app = follow_app!(common::offline_db().await, anchor);
+
+    let resp = test::call_service(&app, follow_request(test::TestRequest::post(), &alice, &bob).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["follow"], follow_pda.to_string());
+
+    // The wallet pays and signs; nothing is recorded until the follow lands on-chain
+    let transaction = decode_transaction(&body);
+    let message = &transaction.message;
+    assert_eq!(message.account_keys[0], alice.pubkey());
+    assert_eq!(message.header.num_required_signatures, 1);
+    let instruction = &message.instructions[0];
+    assert_eq!(message.account_keys[instruction.program_id_index as usize], profiles);
+    let mut data = Sha256::digest(b"global:follow_profile")[..8].to_vec();
+    data.extend_from_slice(bob.pubkey().as_ref());
+    assert_eq!(instruction.data, data);
+    let accounts: Vec<Pubkey> = instruction.accounts.iter()
+        .map(|&index| message.account_keys[index as usize])
+        .collect();
+    assert_eq!(accounts[..4], [follow_pda, alice_profile, bob_profile, alice.pubkey()]);
+}
+
+#[actix_web::test]
+async fn test_unfollow_hands_back_a_transaction_once_following() {
+    let profiles = Pubkey::new_unique();
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), profiles);
+    let follow_pda = anchor.follow_pda(&alice.pubkey(), &bob.pubkey());
+    for wallet in [&alice, &bob] {
+        mount_account(&rpc, &anchor.profile_pda(&wallet.pubkey()), Some(profile_account(&wallet.pubkey(), 0, 0)), &profiles).await;
+    }
+    mount_account(&rpc, &follow_pda, Some(follow_account(&alice.pubkey(), &bob.pubkey(), 1_700_000_000)), &profiles).await;
+    let app = follow_app!(common::offline_db().await, anchor);
+
+    let resp = test::call_service(&app, follow_request(test::TestRequest::delete(), &alice, &bob).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let transaction = decode_transaction(&test::read_body_json(resp).await);
+    let instruction = &transaction.message.instructions[0];
+    assert_eq!(instruction.data, Sha256::digest(b"global:unfollow_profile")[..8].to_vec());
+    assert_eq!(transaction.message.account_keys[instruction.accounts[0] as usize], follow_pda);
+    assert_eq!(transaction.message.account_keys[0], alice.pubkey());
+}
+
+#[actix_web::test]
+async fn test_follow_rejects_self_missing_profiles_and_anonymous_callers() {
+    let profiles = Pubkey::new_unique();
+    let (alice, stranger) = (Keypair::new(), Keypair::new());
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), profiles);
+    mount_account(&rpc, &anchor.profile_pda(&alice.pubkey()), Some(profile_account(&alice.pubkey(), 0, 0)), &profiles).await;
+    mount_account(&rpc, &anchor.profile_pda(&stranger.pubkey()), None, &profiles).await;
+    let app = follow_app!(common::offline_db().await, anchor);
+
+    let resp = test::call_service(&app, follow_request(test::TestRequest::post(), &alice, &alice).to_request()).await;
+    assert_eq!(resp.status(), 400);
+    // Following someone without a profile, or without one of your own
+    let resp = test::call_service(&app, follow_request(test::TestRequest::post(), &alice, &stranger).to_request()).await;
+    assert_eq!(resp.status(), 404);
+    let resp = test::call_service(&app, follow_request(test::TestRequest::post(), &stranger, &alice).to_request()).await;
+    assert_eq!(resp.status(), 400);
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/profiles/{}/follow", alice.pubkey()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+async fn insert_profile(db: &Database, wallet: &Pubkey) {
+    db.collection::<Document>("users")
+        .insert_one(doc! {
+            "_id": wallet.to_string(),
+            "profile_cid": null,
+            "is_public": true,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+}
+
+/// Chain state for Alice following Bob: both profiles with these counts, and the follow if any
+async fn chain_with_follow(profiles: &Pubkey, alice: &Keypair, bob: &Keypair, counts: [(u64, u64); 2], follows: bool) -> (MockServer, AnchorClient) {
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), *profiles);
+    for (wallet, (followers, following)) in [alice, bob].into_iter().zip(counts) {
+        let profile = profile_account(&wallet.pubkey(), followers, following);
+        mount_account(&rpc, &anchor.profile_pda(&wallet.pubkey()), Some(profile), profiles).await;
+    }
+    let follow = follows.then(|| follow_account(&alice.pubkey(), &bob.pubkey(), 1_700_000_000));
+    mount_account(&rpc, &anchor.follow_pda(&alice.pubkey(), &bob.pubkey()), follow, profiles).await;
+    (rpc, anchor)
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_confirming_follows_updates_the_mirror_and_counts() {
+    let db = common::test_db().await;
+    let profiles = Pubkey::new_unique();
+    let (alice, bob) = (Keypair::new(), Keypair::new());
+    for wallet in [&alice, &bob] {
+        insert_profile(&db, &wallet.pubkey()).await;
+    }
+    let counts = |wallet: Pubkey| {
+        let db = db.clone();
+        async move {
+            let user = db::get_user(&db, &wallet.to_string()).await.unwrap().unwrap();
+            (user.follower_count, user.following_count)
+        }
+    };
+    let followers_of_bob = format!("/api/profiles/{}/followers", bob.pubkey());
+
+    // Alice's follow has landed, and Bob picked up another follower elsewhere
+    let (_rpc, anchor) = chain_with_follow(&profiles, &alice, &bob, [(0, 1), (2, 0)], true).await;
+    let app = follow_app!(db, anchor);
+    for _ in 0..2 {
+        let resp = test::call_service(&app, follow_request(test::TestRequest::post(), &alice, &bob).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["following"], true);
+        assert!(body.get("transaction").is_none());
+    }
+    let body: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&followers_of_bob).to_request()).await;
+    assert_eq!(body["items"][0]["wallet"], alice.pubkey().to_string());
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+    assert_eq!(counts(bob.pubkey()).await, (2, 0));
+    assert_eq!(counts(alice.pubkey()).await, (0, 1));
+
+    // Then her unfollow lands
+    let (_rpc, anchor) = chain_with_follow(&profiles, &alice, &bob, [(0, 0), (1, 0)], false).await;
+    let app = follow_app!(db, anchor);
+    let resp = test::call_service(&app, follow_request(test::TestRequest::delete(), &alice, &bob).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["following"], false);
+    let body: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&followers_of_bob).to_request()).await;
+    assert!(body["items"].as_array().unwrap().is_empty());
+    assert_eq!(counts(bob.pubkey()).await, (1, 0));
+    assert_eq!(counts(alice.pubkey()).await, (0, 0));
+
+    // Nothing left to undo
+    let resp = test::call_service(&app, follow_request(test::TestRequest::delete(), &alice, &bob).to_request()).await;
+    assert_eq!(resp.status(), 404);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_followers_and_following_page_newest_first() {
+    let db = common::test_db().await;
+    let star = Pubkey::new_unique();
+    let fans: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    for (i, fan) in fans.iter().enumerate() {
+        let at = chrono::DateTime::from_timestamp(1_700_000_000 + i as i64, 0).unwrap();
+        db::follow_user(&db, &fan.to_string(), &star.to_string(), at).await.unwrap();
+    }
+
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), Pubkey::new_unique());
+    let app = follow_app!(db, anchor);
+
+    let mut listed = Vec::new();
+    let mut uri = format!("/api/profiles/{}/followers?limit=2", star);
+    loop {
+        let body: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        let items = body["items"].as_array().unwrap();
+        assert!(items.len() <= 2);
+        listed.extend(items.iter().map(|item| item["wallet"].as_str().unwrap().to_string()));
+        match body["next_cursor"].as_str() {
+            Some(cursor) => uri = format!("/api/profiles/{}/followers?limit=2&cursor={}", star, cursor),
+            None => break,
+        }
+    }
+    let newest_first: Vec<String> = fans.iter().rev().map(|fan| fan.to_string()).collect();
+    assert_eq!(listed, newest_first);
+
+    let uri = format!("/api/profiles/{}/following", fans[0]);
+    let body: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(body["items"][0]["wallet"], star.to_string());
+    assert_eq!(body["next_cursor"], Value::Null);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,191 @@
+// Integration tests for the Hecate GraphQL read gateway
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::doc;
+use mongodb::event::command::{CommandEventHandler, CommandStartedEvent};
+use mongodb::options::ClientOptions;
+use mongodb::Database;
+use serde_json::Value;
+use shadow_backend::artemis::ArtemisRateLimiter;
+use shadow_backend::config::ShadowConfig;
+use shadow_backend::handlers;
+use shadow_backend::hecate;
+use shadow_backend::metrics::MetricsCollector;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::{Arc, Mutex};
+
+const SCHEMA_SNAPSHOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/graphql_schema.graphql");
+
+macro_rules! graphql_app {
+    ($db:expr, $config:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(hecate::build_schema($db.clone())))
+                .app_data(web::Data::new($config))
+                .app_data(web::Data::new(ArtemisRateLimiter::new(60)))
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .route("/api/graphql", web::post().to(handlers::graphql)),
+        )
+        .await
+    };
+}
+
+fn enabled_config() -> ShadowConfig {
+    let mut config = common::test_config();
+    config.features.graphql_enabled = true;
+    config
+}
+
+/// Records the collection of every `find` the driver sends
+#[derive(Default)]
+struct FindRecorder {
+    collections: Mutex<Vec<String>>,
+}
+
+impl FindRecorder {
+    fn count(&self, collection: &str) -> usize {
+        self.collections.lock().unwrap().iter().filter(|c| *c == collection).count()
+    }
+}
+
+impl CommandEventHandler for FindRecorder {
+    fn handle_command_started_event(&self, event: CommandStartedEvent) {
+        if event.command_name == "find" {
+            if let Ok(collection) = event.command.get_str("find") {
+                self.collections.lock().unwrap().push(collection.to_string());
+            }
+        }
+    }
+}
+
+#[actix_web::test]
+async fn test_schema_snapshot() {
+    let sdl = hecate::build_schema(common::offline_db().await).sdl();
+    // Run with UPDATE_SNAPSHOTS=1 to accept an intentional schema change
+    if std::env::var("UPDATE_SNAPSHOTS").is_ok() {
+        std::fs::write(SCHEMA_SNAPSHOT, &sdl).unwrap();
+    }
+    let snapshot = std::fs::read_to_string(SCHEMA_SNAPSHOT).unwrap();
+    assert_eq!(sdl.trim(), snapshot.trim(), "GraphQL schema changed; review and re-run with UPDATE_SNAPSHOTS=1");
+}
+
+#[actix_web::test]
+async fn test_schema_is_read_only() {
+    let sdl = hecate::build_schema(common::offline_db().await).sdl();
+    assert!(!sdl.contains("type Mutation"));
+    assert!(!sdl.contains("type Subscription"));
+}
+
+#[actix_web::test]
+async fn test_disabled_endpoint_is_not_found() {
+    let db = common::offline_db().await;
+    let mut config = common::test_config();
+    config.features.graphql_enabled = false;
+    let app = graphql_app!(db, config);
+
+    let req = test::TestRequest::post()
+        .uri("/api/graphql")
+        .set_json(serde_json::json!({ "query": "{ site(programAddress: \"x\") { name } }" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+}
+
+#[actix_web::test]
+async fn test_deep_queries_are_rejected_before_execution() {
+    let db = common::offline_db().await;
+    let app = graphql_app!(db, enabled_config());
+
+    let query = "{ domain(name: \"a.shadow\") { site { domains { site { domains { site { domains { site { name } } } } } } } } }";
+    let req = test::TestRequest::post()
+        .uri("/api/graphql")
+        .set_json(serde_json::json!({ "query": query }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["errors"][0]["message"].as_str().unwrap().contains("nested too deep"), "{}", body);
+}
+
+#[actix_web::test]
+async fn test_mutations_are_not_supported() {
+    let db = common::offline_db().await;
+    let app = graphql_app!(db, enabled_config());
+
+    let req = test::TestRequest::post()
+        .uri("/api/graphql")
+        .set_json(serde_json::json!({ "query": "mutation { deleteSite(programAddress: \"x\") }" }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["errors"].is_array());
+    assert!(body["data"].is_null());
+}
+
+async fn monitored_db(db: &Database) -> (Database, Arc<FindRecorder>) {
+    let recorder = Arc::new(FindRecorder::default());
+    let mut options = ClientOptions::parse(std::env::var("DATABASE_URL").unwrap()).await.unwrap();
+    options.command_event_handler = Some(recorder.clone());
+    let client = mongodb::Client::with_options(options).unwrap();
+    (client.database(db.name()), recorder)
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_composed_query_batches_domain_and_profile_lookups() {
+    let db = common::test_db().await;
+    let now = mongodb::bson::DateTime::now();
+    let owner = Pubkey::new_unique().to_string();
+    let programs: Vec<String> = (0..3).map(|_| Pubkey::new_unique().to_string()).collect();
+
+    db.collection("users")
+        .insert_one(doc! { "_id": &owner, "profile_cid": "ipfs://profile", "is_public": true, "created_at": now, "updated_at": now }, None)
+        .await
+        .unwrap();
+    for (i, program) in programs.iter().enumerate() {
+        db.collection("sites")
+            .insert_one(doc! {
+                "_id": program,
+                "owner_pubkey": &owner,
+                "storage_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+                "name": format!("Site {}", i),
+                "description": null,
+                "created_at": now,
+                "updated_at": now,
+            }, None)
+            .await
+            .unwrap();
+        for suffix in ["a", "b"] {
+            db.collection("domains")
+                .insert_one(doc! {
+                    "_id": format!("site{}{}.shadow", i, suffix),
+                    "owner_pubkey": &owner,
+                    "program_address": program,
+                    "verified": true,
+                    "created_at": now,
+                    "updated_at": now,
+                }, None)
+                .await
+                .unwrap();
+        }
+    }
+
+    let (monitored, recorder) = monitored_db(&db).await;
+    let app = graphql_app!(monitored, enabled_config());
+    let query = "query($programs: [String!]!) { sites(programAddresses: $programs) { name owner { walletPubkey } domains { name verified } } }";
+    let req = test::TestRequest::post()
+        .uri("/api/graphql")
+        .set_json(serde_json::json!({ "query": query, "variables": { "programs": programs } }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert!(body["errors"].is_null(), "{}", body);
+    let sites = body["data"]["sites"].as_array().unwrap();
+    assert_eq!(sites.len(), 3);
+    assert_eq!(sites[1]["name"], "Site 1");
+    assert_eq!(sites[1]["owner"]["walletPubkey"], owner.as_str());
+    assert_eq!(sites[1]["domains"].as_array().unwrap().len(), 2);
+
+    assert_eq!(recorder.count("domains"), 1, "domains-for-site lookups are batched");
+    assert_eq!(recorder.count("users"), 1, "owner profile lookups are batched");
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
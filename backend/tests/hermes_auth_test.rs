@@ -0,0 +1,44 @@
+// X-Shadow-Auth headers signed by hermes-client must pass AresAuth
+mod common;
+
+use hermes_client::AuthSigner;
+use shadow_backend::ares::{AresAuth, AuthHeader};
+use solana_sdk::signature::{Keypair, Signer};
+
+#[test]
+fn test_client_header_passes_ares_verification() {
+    let keypair = Keypair::new();
+    let signer = AuthSigner::from_bytes(&keypair.to_bytes()).unwrap();
+    assert_eq!(signer.wallet(), keypair.pubkey().to_string());
+
+    let ares = AresAuth::new();
+    let header = AuthHeader::from_header(&signer.header().unwrap()).unwrap();
+    assert_eq!(header.wallet, keypair.pubkey().to_string());
+    assert!(ares.verify_challenge(&header.wallet, &header.signature, header.timestamp, &header.nonce).unwrap());
+    header.verify(&ares).unwrap();
+
+    // Each header carries its own nonce, and none can be replayed
+    assert_eq!(header.verify(&ares), Err("Challenge already used".to_string()));
+    AuthHeader::from_header(&signer.header().unwrap()).unwrap().verify(&ares).unwrap();
+}
+
+#[test]
+fn test_client_header_is_bound_to_its_wallet_and_time() {
+    let signer = AuthSigner::from_bytes(&Keypair::new().to_bytes()).unwrap();
+    let ares = AresAuth::new();
+
+    let stale = AuthHeader::from_header(&signer.header_at(chrono::Utc::now().timestamp() - 3600).unwrap()).unwrap();
+    assert_eq!(stale.verify(&ares), Err("Challenge expired".to_string()));
+
+    let mut forged = AuthHeader::from_header(&signer.header().unwrap()).unwrap();
+    forged.wallet = Keypair::new().pubkey().to_string();
+    assert_eq!(forged.verify(&ares), Err("Invalid signature".to_string()));
+}
+
+#[test]
+fn test_challenge_format_matches_ares() {
+    assert_eq!(
+        hermes_client::create_challenge("Wallet1", 1_700_000_000, "nonce"),
+        AresAuth::create_challenge("Wallet1", 1_700_000_000, "nonce")
+    );
+}
@@ -0,0 +1,45 @@
+// Integration tests for sharing Hermes broker events between instances through Redis
+mod common;
+
+use shadow_backend::websocket::{BrokerBackend, HermesBroker};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+async fn next_event(receiver: &mut broadcast::Receiver<String>) -> String {
+    tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+        .await
+        .expect("event was never relayed")
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs a Redis at REDIS_URL"]
+async fn test_events_reach_subscribers_on_other_instances() {
+    let redis_url = common::test_redis_url();
+    let first = HermesBroker::connect(&redis_url).await.unwrap();
+    let second = HermesBroker::connect(&redis_url).await.unwrap();
+    assert!(matches!(first.backend(), BrokerBackend::Redis(_)));
+
+    let topic = format!("program:{}", uuid::Uuid::new_v4().simple());
+    let mut local = first.subscribe(topic.clone()).await;
+    let mut remote = second.subscribe(topic.clone()).await;
+
+    first.publish(&topic, r#"{"type":"content_updated"}"#.to_string()).await;
+    assert_eq!(next_event(&mut remote).await, r#"{"type":"content_updated"}"#);
+    assert_eq!(next_event(&mut local).await, r#"{"type":"content_updated"}"#);
+
+    // Events flow both ways, and the publisher's own subscribers get each one once
+    second.publish(&topic, "second".to_string()).await;
+    assert_eq!(next_event(&mut local).await, "second");
+    assert_eq!(next_event(&mut remote).await, "second");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(local.try_recv().is_err());
+    assert!(remote.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_connect_fails_without_redis() {
+    assert!(HermesBroker::connect("not a redis url").await.is_err());
+    assert!(HermesBroker::connect("redis://127.0.0.1:1").await.is_err());
+    assert!(matches!(HermesBroker::new().backend(), BrokerBackend::InProcess));
+}
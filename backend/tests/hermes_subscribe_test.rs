@@ -0,0 +1,111 @@
+// Integration tests for hermes-client's event subscription against the real /api/ws handler
+use actix_web::dev::ServerHandle;
+use actix_web::{web, App, HttpServer};
+use futures_util::{Stream, StreamExt};
+use hermes_client::{Event, HermesClient, HermesEvent, Topic};
+use shadow_backend::websocket::{self, HermesBroker, CONTENT_UPDATED_EVENT};
+use solana_sdk::pubkey::Pubkey;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Serve ws_handler on `addr` (port 0 for any), returning where it listens
+fn serve(broker: web::Data<HermesBroker>, addr: SocketAddr) -> (SocketAddr, ServerHandle) {
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(broker.clone())
+            .route("/api/ws", web::get().to(websocket::ws_handler))
+    })
+    .workers(1)
+    .bind(addr)
+    .unwrap();
+    let addr = server.addrs()[0];
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+    (addr, handle)
+}
+
+/// Publish `data` on `topic` until the stream sees it; subscribing happens in the background,
+/// so an event published before the socket has subscribed is simply missed
+async fn publish_until_received(
+    broker: &HermesBroker,
+    topic: &str,
+    data: serde_json::Value,
+    events: &mut (impl Stream<Item = HermesEvent> + Unpin),
+) -> HermesEvent {
+    for _ in 0..100 {
+        broker.publish(topic, data.to_string()).await;
+        if let Ok(Some(event)) = tokio::time::timeout(Duration::from_millis(50), events.next()).await {
+            return event;
+        }
+    }
+    panic!("no event on {}", topic);
+}
+
+fn client(addr: SocketAddr) -> HermesClient {
+    HermesClient::builder().backend(format!("http://{}", addr)).retry_delay_ms(20).build().unwrap()
+}
+
+#[actix_web::test]
+async fn test_subscribe_delivers_typed_events() {
+    let broker = web::Data::new(HermesBroker::new());
+    let (addr, handle) = serve(broker.clone(), "127.0.0.1:0".parse().unwrap());
+    let (program, wallet) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+
+    let mut events = Box::pin(client(addr).subscribe(&[Topic::Program(program.clone()), Topic::Wallet(wallet.clone())]));
+    let program_topic = format!("program:{}", program);
+    let event = publish_until_received(&broker, &program_topic, serde_json::json!({
+        "type": CONTENT_UPDATED_EVENT,
+        "event_id": "evt-1",
+        "program_address": program,
+        "storage_cid": "ipfs://bafy",
+        "version_id": "v1",
+    }), &mut events).await;
+    assert_eq!(event.topic, Topic::Program(program.clone()));
+    assert_eq!(event.event, Event::ContentUpdated {
+        event_id: "evt-1".to_string(),
+        program_address: program.clone(),
+        storage_cid: "ipfs://bafy".to_string(),
+        version_id: "v1".to_string(),
+    });
+    assert_eq!(serde_json::to_value(&event).unwrap()["topic"], program_topic);
+
+    // Wallet notifications carry no type tag; unknown events keep their payload
+    let event = publish_until_received(&broker, &format!("wallet:{}", wallet), serde_json::json!({
+        "_id": "n1", "wallet": wallet, "domain": "site.shadow", "kind": "expiring", "message": "Renew soon", "read": false,
+    }), &mut events).await;
+    let Event::Notification(notification) = event.event else { panic!("{:?}", event) };
+    assert_eq!((notification.kind.as_str(), notification.message.as_str()), ("expiring", "Renew soon"));
+
+    let event = publish_until_received(&broker, &program_topic, serde_json::json!({ "type": "something_new" }), &mut events).await;
+    assert_eq!(event.event, Event::Other);
+    assert_eq!(event.data["type"], "something_new");
+
+    handle.stop(false).await;
+}
+
+#[actix_web::test]
+async fn test_subscribe_reconnects_after_restart() {
+    let broker = web::Data::new(HermesBroker::new());
+    let (addr, handle) = serve(broker.clone(), "127.0.0.1:0".parse().unwrap());
+    let program = Pubkey::new_unique().to_string();
+    let topic = format!("program:{}", program);
+    let site_updated = |event_id: &str| serde_json::json!({
+        "type": "site_updated",
+        "event_id": event_id,
+        "program_address": program,
+        "storage_cid": "ipfs://bafy",
+    });
+
+    let mut events = Box::pin(client(addr).subscribe(&[Topic::Program(program.clone())]));
+    let first = publish_until_received(&broker, &topic, site_updated("before"), &mut events).await;
+    assert!(matches!(first.event, Event::SiteUpdated { ref event_id, revision: None, .. } if event_id == "before"));
+
+    // Dropping the server closes the socket; the client reopens it and subscribes again
+    handle.stop(false).await;
+    let (_, handle) = serve(broker.clone(), addr);
+    let second = publish_until_received(&broker, &topic, site_updated("after"), &mut events).await;
+    assert!(matches!(second.event, Event::SiteUpdated { ref event_id, .. } if event_id == "after"));
+
+    handle.stop(false).await;
+}
@@ -0,0 +1,182 @@
+// Integration tests for Chronos per-visit history events and their per-domain aggregates
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::chronos::ChronosManager;
+use shadow_backend::handlers;
+use shadow_backend::prometheus::PrometheusAnalytics;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::time::Duration;
+
+macro_rules! history_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(ChronosManager::new($db.clone())))
+                .app_data(web::Data::new(PrometheusAnalytics::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(common::test_config()))
+                .route("/api/history", web::get().to(handlers::get_history))
+                .route("/api/history", web::post().to(handlers::record_visit))
+                .route("/api/history", web::delete().to(handlers::clear_history))
+                .route("/api/history/summary", web::get().to(handlers::get_history_summary))
+                .route("/api/history/{domain}", web::delete().to(handlers::delete_domain_history)),
+        )
+        .await
+    };
+}
+
+fn visit(wallet: &Keypair, domain: &str, path: Option<&str>, seconds: u64) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/history")
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+        .set_json(serde_json::json!({
+            "domain": domain,
+            "program_address": Pubkey::new_unique().to_string(),
+            "path": path,
+            "title": null,
+            "time_spent_seconds": seconds,
+        }))
+}
+
+fn get(wallet: &Keypair, uri: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(uri)
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+}
+
+fn field<'a>(visits: &'a Value, key: &str) -> Vec<&'a str> {
+    visits.as_array().unwrap().iter().map(|v| v[key].as_str().unwrap_or("")).collect()
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_visits_are_listed_newest_first_with_paths() {
+    let db = common::test_db().await;
+    let app = history_app!(db);
+    let wallet = Keypair::new();
+
+    for (domain, path) in [("docs.shadow", "/intro"), ("swap.shadow", "/"), ("docs.shadow", "/api")] {
+        assert_eq!(test::call_service(&app, visit(&wallet, domain, Some(path), 30).to_request()).await.status(), 200);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/history").to_request()).await;
+    assert_eq!(field(&body["visits"], "domain"), vec!["docs.shadow", "swap.shadow", "docs.shadow"]);
+    assert_eq!(field(&body["visits"], "path"), vec!["/api", "/", "/intro"]);
+    assert!(body["next_offset"].is_null());
+
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/history?limit=2").to_request()).await;
+    assert_eq!(body["visits"].as_array().unwrap().len(), 2);
+    assert_eq!(body["next_offset"], 2);
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/history?limit=2&offset=2").to_request()).await;
+    assert_eq!(field(&body["visits"], "path"), vec!["/intro"]);
+
+    // The cursor picks up where the first page stopped
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/history?limit=2").to_request()).await;
+    let uri = format!("/api/history?limit=2&cursor={}", body["next_cursor"].as_str().unwrap());
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, &uri).to_request()).await;
+    assert_eq!(field(&body["visits"], "path"), vec!["/intro"]);
+    assert!(body["next_cursor"].is_null());
+
+    // Both docs pages fold into one aggregate row
+    let summary: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/history/summary").to_request()).await;
+    let docs = summary.as_array().unwrap().iter().find(|r| r["domain"] == "docs.shadow").unwrap();
+    assert_eq!(docs["visit_count"], 2);
+    assert_eq!(docs["time_spent_seconds"], 60);
+    assert_eq!(summary.as_array().unwrap().len(), 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_deleting_a_domain_clears_both_stores() {
+    let db = common::test_db().await;
+    let app = history_app!(db);
+    let wallet = Keypair::new();
+    let pubkey = wallet.pubkey().to_string();
+
+    test::call_service(&app, visit(&wallet, "docs.shadow", Some("/intro"), 5).to_request()).await;
+    test::call_service(&app, visit(&wallet, "docs.shadow", Some("/api"), 5).to_request()).await;
+    test::call_service(&app, visit(&wallet, "swap.shadow", None, 5).to_request()).await;
+
+    let req = test::TestRequest::delete()
+        .uri("/api/history/docs.shadow")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&wallet)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["deleted_visits"], 2);
+    let summary: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/history/summary").to_request()).await;
+    assert_eq!(field(&summary, "domain"), vec!["swap.shadow"]);
+
+    let req = test::TestRequest::delete()
+        .uri("/api/history")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&wallet)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    for collection in ["history_visits", "browser_history"] {
+        let remaining = db.collection::<Document>(collection)
+            .count_documents(doc! { "wallet_pubkey": &pubkey }, None)
+            .await
+            .unwrap();
+        assert_eq!(remaining, 0, "{} not cleared", collection);
+    }
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_legacy_aggregate_rows_migrate_to_single_events() {
+    let db = common::test_db().await;
+    let wallet = Keypair::new();
+    let pubkey = wallet.pubkey().to_string();
+    let last_visit = mongodb::bson::DateTime::from_millis(
+        (chrono::Utc::now() - chrono::Duration::days(3)).timestamp_millis(),
+    );
+    db.collection::<Document>("browser_history")
+        .insert_one(doc! {
+            "_id": format!("{}:old.shadow", pubkey),
+            "wallet_pubkey": &pubkey,
+            "domain": "old.shadow",
+            "program_address": Pubkey::new_unique().to_string(),
+            "title": "Old site",
+            "visited_at": last_visit,
+            "visit_count": 7,
+            "last_visit": last_visit,
+            "time_spent_seconds": 420_i64,
+        }, None)
+        .await
+        .unwrap();
+
+    let chronos = ChronosManager::new(db.clone());
+    assert_eq!(chronos.migrate_legacy_history().await.unwrap(), 1);
+    assert_eq!(chronos.migrate_legacy_history().await.unwrap(), 0);
+
+    let (visits, _) = chronos.get_history(&pubkey, 10, 0, None).await.unwrap();
+    assert_eq!(visits.len(), 1);
+    assert!(visits[0].legacy);
+    assert_eq!(visits[0].domain, "old.shadow");
+    assert_eq!(visits[0].title.as_deref(), Some("Old site"));
+    assert_eq!(visits[0].duration_seconds, 420);
+    assert_eq!(visits[0].visited_at.timestamp_millis(), last_visit.timestamp_millis());
+
+    // The aggregate itself is untouched
+    let summary = chronos.get_history_summary(&pubkey, 10).await.unwrap();
+    assert_eq!(summary[0].visit_count, 7);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_history_rejects_out_of_range_limit() {
+    let app = history_app!(common::offline_db().await);
+    let req = get(&Keypair::new(), "/api/history?limit=0").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
@@ -1,11 +1,13 @@
 // Integration tests for Shadow backend
 #[cfg(test)]
 mod tests {
-    use shadow_backend::apollo::ApolloValidator;
+    use shadow_backend::apollo::{ApolloValidator, StorageCidKind};
     use shadow_backend::artemis::ArtemisRateLimiter;
+    use shadow_backend::clock::TestClock;
     use shadow_backend::hephaestus::HephaestusCache;
     use std::time::Duration;
     
+    
     #[test]
     fn test_apollo_validation() {
         // Test pubkey validation
@@ -15,16 +17,77 @@ mod tests {
         // Test domain validation
         assert!(ApolloValidator::validate_domain("example.shadow").is_ok());
         assert!(ApolloValidator::validate_domain("invalid..domain").is_err());
+        assert!(ApolloValidator::validate_domain("a.shadow").is_err());
+        assert!(ApolloValidator::validate_domain("MyApp.shadow").is_err());
+        assert!(ApolloValidator::validate_domain("-app.shadow").is_err());
+        assert!(ApolloValidator::validate_domain(&format!("{}.shadow", "a".repeat(64))).is_err());
         
         // Test IPFS CID validation
         assert!(ApolloValidator::validate_ipfs_cid("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG").is_ok());
+        assert!(ApolloValidator::validate_ipfs_cid("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi").is_ok());
+        assert!(ApolloValidator::validate_ipfs_cid("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi/index.html").is_ok());
         assert!(ApolloValidator::validate_ipfs_cid("invalid").is_err());
+        assert!(ApolloValidator::validate_ipfs_cid("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPb").is_err());
+        assert!(ApolloValidator::validate_ipfs_cid("bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzd").is_err());
+        assert!(ApolloValidator::validate_ipfs_cid("").is_err());
+        
+        // Test username validation
+        assert_eq!(ApolloValidator::validate_username("Shade_01").unwrap(), "shade_01");
+        assert!(ApolloValidator::validate_username("abc").is_ok());
+        assert!(ApolloValidator::validate_username(&"a".repeat(32)).is_ok());
+        assert!(ApolloValidator::validate_username("ab").is_err());
+        assert!(ApolloValidator::validate_username(&"a".repeat(33)).is_err());
+        assert!(ApolloValidator::validate_username("sha-de").is_err());
+        assert!(ApolloValidator::validate_username("shadé").is_err());
         
         // Test search query validation
         assert!(ApolloValidator::validate_search_query("test query").is_ok());
         assert!(ApolloValidator::validate_search_query("").is_err());
     }
     
+    #[test]
+    fn test_apollo_storage_cid_validation() {
+        const V0: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+        const V1: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+        const RAW: &str = "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy";
+        const TX: &str = "C7CdgGAO7D6513k6b4Wb7d4qLYOJm3C9eOlh7WdLMvQ";
+        let kind = |cid: &str| ApolloValidator::validate_storage_cid(cid).ok();
+
+        // CIDv0
+        assert_eq!(kind(V0), Some(StorageCidKind::IpfsV0));
+        assert_eq!(kind(&format!("ipfs://{}/index.html", V0)), Some(StorageCidKind::IpfsV0));
+        assert_eq!(kind(&V0[..45]), None);
+        assert_eq!(kind(&format!("{}0", &V0[..45])), None); // '0' is not base58
+        assert_eq!(kind("Qm1111111111111111111111111111111111111111111"), None); // not a sha2-256 multihash
+
+        // CIDv1
+        assert_eq!(kind(V1), Some(StorageCidKind::IpfsV1));
+        assert_eq!(kind(RAW), Some(StorageCidKind::IpfsV1));
+        assert_eq!(kind(&format!("ipfs://{}", V1)), Some(StorageCidKind::IpfsV1));
+        assert_eq!(kind(&V1[..V1.len() - 1]), None); // truncated multihash
+        assert_eq!(kind(&V1.to_uppercase()), None);
+        assert_eq!(kind(&V1.replace('y', "1")), None); // '1' is not base32
+        assert_eq!(kind("zdj7WWeQ43G6JJvLWQWZpyHuAMq6uYWRjkBXFad11vE2LHhQ7"), None); // other multibases
+
+        // Arweave
+        assert_eq!(kind(TX), Some(StorageCidKind::Arweave));
+        assert_eq!(kind(&format!("arweave://{}", TX)), Some(StorageCidKind::Arweave));
+        assert_eq!(kind(&TX[..42]), None);
+        assert_eq!(kind(&format!("{}A", TX)), None);
+        assert_eq!(kind(&format!("+{}", &TX[1..])), None); // standard base64, not URL-safe
+        assert_eq!(kind(&format!("{}R", &TX[..42])), None); // trailing bits set
+        assert_eq!(kind("arweave://site-tx"), None);
+
+        // The scheme pins the protocol
+        assert_eq!(kind(&format!("arweave://{}", V1)), None);
+        assert_eq!(kind(&format!("ipfs://{}", TX)), None);
+        assert_eq!(kind(&format!("https://{}", V1)), None);
+
+        assert!(ApolloValidator::validate_storage_cid("").is_err());
+        assert!(ApolloValidator::validate_storage_cid("ipfs://").is_err());
+        assert!(ApolloValidator::validate_storage_cid("not-a-cid").is_err());
+    }
+
     #[test]
     fn test_artemis_rate_limiter() {
         let limiter = ArtemisRateLimiter::new(10);
@@ -37,6 +100,39 @@ mod tests {
         
         // Test different keys don't interfere
         assert!(limiter.check_rate_limit("other_client").is_ok());
+        
+        limiter.reset_for_testing();
+        assert!(limiter.check_rate_limit(key).is_ok());
+    }
+    
+    #[test]
+    fn test_artemis_rate_limiter_window_boundary() {
+        let clock = TestClock::starting_now();
+        let limiter = ArtemisRateLimiter::new(10).with_clock(clock.clone());
+        let key = "test_client";
+        
+        // Half the budget early on, the rest just before a minute has passed
+        for _ in 0..5 {
+            assert!(limiter.check_rate_limit(key).is_ok());
+        }
+        clock.advance(Duration::from_secs(59));
+        for _ in 0..5 {
+            assert!(limiter.check_rate_limit(key).is_ok());
+        }
+        assert!(limiter.check_rate_limit(key).is_err());
+        
+        // Crossing the minute mark frees only the early half, so no double burst
+        clock.advance(Duration::from_secs(1));
+        for _ in 0..5 {
+            assert!(limiter.check_rate_limit(key).is_ok());
+        }
+        assert!(limiter.check_rate_limit(key).is_err());
+        
+        // Weighted requests need room for their whole cost
+        clock.advance(Duration::from_secs(59));
+        assert!(limiter.check_rate_limit_with_cost(key, 4).is_ok());
+        assert!(limiter.check_rate_limit_with_cost(key, 2).is_err());
+        assert_eq!(limiter.check_rate_limit(key).unwrap().remaining, 0);
     }
     
     #[tokio::test]
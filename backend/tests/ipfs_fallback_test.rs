@@ -0,0 +1,108 @@
+// Integration tests for IPFS pinning through Pinata with NFT.Storage as the fallback
+use actix_web::{test, web, App};
+use serde_json::Value;
+use shadow_backend::handlers;
+use shadow_backend::storage::{NFTStorageClient, PinataStorage, StorageBackend};
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CID: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+
+fn pinata(server: &MockServer, backend: StorageBackend) -> PinataStorage {
+    PinataStorage::new().with_credentials("key", "secret").with_api_url(&server.uri()).with_backend(backend)
+}
+
+fn nft_storage(server: &MockServer) -> NFTStorageClient {
+    NFTStorageClient::new().with_api_key("nft-key").with_api_url(&server.uri())
+}
+
+async fn mount_nft_storage_upload(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/upload"))
+        .and(header("authorization", "Bearer nft-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ok": true,
+            "value": { "cid": CID, "type": "application/octet-stream", "size": 5 }
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_auto_falls_back_to_nft_storage() {
+    let (pinata_server, nft_server) = (MockServer::start().await, MockServer::start().await);
+    Mock::given(method("POST"))
+        .and(path("/pinning/pinFileToIPFS"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&pinata_server)
+        .await;
+    mount_nft_storage_upload(&nft_server).await;
+
+    let fallback = nft_storage(&nft_server);
+    let cid = pinata(&pinata_server, StorageBackend::Auto).upload(b"hello", "upload", Some(&fallback)).await.unwrap();
+    assert_eq!(cid, format!("ipfs://{}", CID));
+    assert_eq!(nft_server.received_requests().await.unwrap()[0].body, b"hello");
+
+    // Without a fallback, or pinned to Pinata only, the Pinata error stands
+    let err = pinata(&pinata_server, StorageBackend::Auto).upload(b"hello", "upload", None).await.unwrap_err();
+    assert!(err.contains("Pinata"), "{}", err);
+    let pinata_only = pinata(&pinata_server, StorageBackend::Pinata);
+    assert!(pinata_only.upload(b"hello", "upload", Some(&fallback)).await.is_err());
+    assert_eq!(nft_server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_nft_storage_backend_skips_pinata() {
+    let (pinata_server, nft_server) = (MockServer::start().await, MockServer::start().await);
+    mount_nft_storage_upload(&nft_server).await;
+
+    let fallback = nft_storage(&nft_server);
+    let cid = pinata(&pinata_server, StorageBackend::NFTStorage).upload(b"hello", "upload", Some(&fallback)).await.unwrap();
+    assert_eq!(cid, format!("ipfs://{}", CID));
+    assert!(pinata_server.received_requests().await.unwrap().is_empty());
+
+    let err = NFTStorageClient::new().with_api_url(&nft_server.uri()).upload(b"hello").await.unwrap_err();
+    assert!(err.contains("not configured"), "{}", err);
+}
+
+#[actix_web::test]
+async fn test_upload_status_reports_each_backend() {
+    let (pinata_server, nft_server) = (MockServer::start().await, MockServer::start().await);
+    Mock::given(method("GET"))
+        .and(path("/data/pinList"))
+        .and(query_param("hashContains", CID))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "count": 1,
+            "rows": [{ "ipfs_pin_hash": CID, "size": 5 }]
+        })))
+        .mount(&pinata_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/check/{}", CID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ok": true,
+            "value": { "cid": CID, "pin": { "cid": CID, "status": "queued" }, "deals": [] }
+        })))
+        .mount(&nft_server)
+        .await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pinata(&pinata_server, StorageBackend::Auto)))
+            .app_data(web::Data::new(nft_storage(&nft_server)))
+            .route("/api/upload/status/{cid}", web::get().to(handlers::get_upload_status)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri(&format!("/api/upload/status/{}", CID)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["pinned_on"], serde_json::json!(["pinata"]));
+    assert_eq!(body["pinata"]["pinned"], true);
+    // Still queued on NFT.Storage
+    assert_eq!(body["nft_storage"]["pinned"], false);
+    assert_eq!(body["nft_storage"]["configured"], true);
+
+    let req = test::TestRequest::get().uri("/api/upload/status/not-a-cid").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
@@ -0,0 +1,196 @@
+// Integration tests for Charon legacy web bridges and their signed redirect assertions
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::charon::{sign_assertion, verify_assertion, BridgeAssertion, CharonBridge};
+use shadow_backend::config::BridgeConfig;
+use shadow_backend::handlers;
+use shadow_backend::olympus::OlympusCA;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use wiremock::matchers::{method, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn bridge_config(doh_url: &str) -> BridgeConfig {
+    BridgeConfig {
+        resolve_url: "https://resolve.test/{domain}".to_string(),
+        assertion_ttl_seconds: 300,
+        signing_key: None,
+        doh_url: doh_url.to_string(),
+    }
+}
+
+macro_rules! bridge_app {
+    ($db:expr, $charon:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($charon))
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/bridge", web::post().to(handlers::create_bridge))
+                .route("/api/bridge/assert/{legacy_domain}", web::get().to(handlers::get_bridge_assertion))
+                .route("/api/bridge/{legacy_domain}/verify", web::post().to(handlers::verify_bridge))
+                .route("/r/{legacy_domain}", web::get().to(handlers::legacy_redirect)),
+        )
+        .await
+    };
+}
+
+async fn insert_domain(db: &mongodb::Database, domain: &str, program: &str, owner: &str) {
+    let now = mongodb::bson::DateTime::now();
+    db.collection::<Document>("domains")
+        .insert_one(doc! {
+            "_id": domain,
+            "owner_pubkey": owner,
+            "program_address": program,
+            "verified": true,
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+}
+
+fn claims(exp: i64) -> BridgeAssertion {
+    BridgeAssertion {
+        iss: "shadow-backend".to_string(),
+        legacy_domain: "example.com".to_string(),
+        shadow_domain: "example.shadow".to_string(),
+        program_address: Pubkey::new_unique().to_string(),
+        iat: 1_700_000_000,
+        exp,
+    }
+}
+
+#[actix_web::test]
+async fn test_assertion_signature_round_trip() {
+    let signer = Keypair::new();
+    let original = claims(1_700_000_300);
+    let token = sign_assertion(&signer, &original);
+    assert_eq!(token.split('.').count(), 3);
+
+    assert_eq!(verify_assertion(&token, &signer.pubkey(), 1_700_000_100).unwrap(), original);
+    assert!(verify_assertion(&token, &Keypair::new().pubkey(), 1_700_000_100).is_err());
+
+    // Swapping in different claims breaks the signature
+    let forged = sign_assertion(&Keypair::new(), &BridgeAssertion { shadow_domain: "evil.shadow".to_string(), ..original });
+    let mut parts: Vec<&str> = token.split('.').collect();
+    parts[1] = forged.split('.').nth(1).unwrap();
+    let err = verify_assertion(&parts.join("."), &signer.pubkey(), 1_700_000_100).unwrap_err();
+    assert!(err.contains("signature"));
+}
+
+#[actix_web::test]
+async fn test_expired_assertion_is_rejected() {
+    let signer = Keypair::new();
+    let token = sign_assertion(&signer, &claims(1_700_000_300));
+    let err = verify_assertion(&token, &signer.pubkey(), 1_700_000_300).unwrap_err();
+    assert!(err.contains("expired"));
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_verified_bridge_redirects_with_assertion() {
+    let db = common::test_db().await;
+    let dns = MockServer::start().await;
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    insert_domain(&db, "example.shadow", &program, &owner.pubkey().to_string()).await;
+
+    let signer = Keypair::new();
+    let signer_pubkey = signer.pubkey();
+    let charon = CharonBridge::new(db.clone(), bridge_config(&dns.uri())).with_signing_keypair(signer);
+    let app = bridge_app!(db, charon);
+
+    let req = test::TestRequest::post()
+        .uri("/api/bridge")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(serde_json::json!({ "legacy_domain": "https://Example.com/", "shadow_domain": "example.shadow" }))
+        .to_request();
+    let created: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(created["txt_record"]["name"], "_shadow-bridge.example.com");
+    let txt_value = created["txt_record"]["value"].as_str().unwrap().to_string();
+
+    // Before the TXT record exists: no redirect, no assertion
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/r/example.com").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get("Location").is_none());
+    let req = test::TestRequest::get().uri("/api/bridge/assert/example.com").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    Mock::given(method("GET"))
+        .and(query_param("name", "_shadow-bridge.example.com"))
+        .and(query_param("type", "TXT"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "Status": 0,
+            "Answer": [{ "name": "_shadow-bridge.example.com.", "type": 16, "TTL": 300, "data": format!("\"{}\"", txt_value) }],
+        })))
+        .mount(&dns)
+        .await;
+    let req = test::TestRequest::post()
+        .uri("/api/bridge/example.com/verify")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/r/example.com").to_request()).await;
+    assert_eq!(resp.status(), 302);
+    let location = resp.headers().get("Location").unwrap().to_str().unwrap().to_string();
+    let (target, token) = location.split_once("?assertion=").unwrap();
+    assert_eq!(target, "https://resolve.test/example.shadow");
+    let verified = verify_assertion(token, &signer_pubkey, chrono::Utc::now().timestamp()).unwrap();
+    assert_eq!(verified.legacy_domain, "example.com");
+    assert_eq!(verified.program_address, program);
+
+    let req = test::TestRequest::get().uri("/api/bridge/assert/example.com").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["signer"], signer_pubkey.to_string());
+    assert_eq!(body["claims"]["shadow_domain"], "example.shadow");
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_unverified_bridge_does_not_redirect() {
+    let db = common::test_db().await;
+    let owner = Pubkey::new_unique().to_string();
+    db.collection::<Document>("legacy_bridges")
+        .insert_one(doc! {
+            "_id": "pending.com",
+            "shadow_domain": "pending.shadow",
+            "program_address": Pubkey::new_unique().to_string(),
+            "owner_pubkey": &owner,
+            "challenge": "abc",
+            "verified": false,
+            "created_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+    let app = bridge_app!(db, CharonBridge::new(db.clone(), bridge_config("http://127.0.0.1:1")));
+
+    for uri in ["/r/pending.com", "/r/unknown.com"] {
+        let resp = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers().get("Location").is_none());
+    }
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_invalid_legacy_domain_gets_neutral_page() {
+    let db = common::offline_db().await;
+    let app = bridge_app!(db, CharonBridge::new(db.clone(), bridge_config("http://127.0.0.1:1")));
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/r/%3Cscript%3E").to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert!(resp.headers().get("Location").is_none());
+    let body = test::read_body(resp).await;
+    let html = String::from_utf8_lossy(&body);
+    assert!(html.contains("&lt;script&gt;"));
+    assert!(!html.contains("<script>"));
+}
@@ -0,0 +1,138 @@
+// Integration tests for the link converter routes mounted under /api/links
+mod common;
+
+use actix_web::{test, web, App};
+use serde_json::Value;
+use shadow_backend::api;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::artemis::ArtemisRateLimiter;
+use shadow_backend::link_converter::LinkMintAuthority;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+macro_rules! links_app {
+    ($db:expr, $rpc:expr) => {
+        links_app!($db, $rpc, ArtemisRateLimiter::new(1000), common::test_config())
+    };
+    ($db:expr, $rpc:expr, $artemis:expr, $config:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new($rpc))
+                .app_data(web::Data::new(LinkMintAuthority::new(Keypair::new())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new($artemis))
+                .app_data(web::Data::new($config))
+                .service(web::scope("/api").configure(api::configure)),
+        )
+        .await
+    };
+}
+
+#[actix_web::test]
+async fn test_link_routes_are_mounted_and_authenticated() {
+    let app = links_app!(common::offline_db().await, "http://127.0.0.1:1".to_string());
+    let owner = Pubkey::new_unique();
+
+    let requests = [
+        test::TestRequest::post().uri("/api/links/convert").set_json(serde_json::json!({ "url": "https://example.com" })),
+        test::TestRequest::post().uri("/api/links/general-token")
+            .set_json(serde_json::json!({ "platform": "x", "token_name": "X Links", "token_symbol": "XLNK" })),
+        test::TestRequest::get().uri(&format!("/api/links/token/{}", Pubkey::new_unique())),
+        test::TestRequest::post().uri("/api/links/resolve").set_json(serde_json::json!({ "url": "https://example.com" })),
+        test::TestRequest::get().uri(&format!("/api/links?owner={}", owner)),
+    ];
+    for req in requests {
+        let req = req.to_request();
+        let uri = req.uri().to_string();
+        assert_eq!(test::call_service(&app, req).await.status(), 401, "{}", uri);
+    }
+
+    // Checked before the database is touched
+    let caller = Keypair::new();
+    for uri in ["/api/links?owner=not-a-wallet".to_string(), format!("/api/links?owner={}&cursor=not*base64", owner)] {
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("X-Shadow-Auth", common::auth_header(&caller)))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400, "{}", uri);
+    }
+}
+
+#[actix_web::test]
+async fn test_conversions_are_charged_per_wallet() {
+    let mut config = common::test_config();
+    config.link_tokens.convert_rate_limit_cost = 5;
+    let app = links_app!(common::offline_db().await, "http://127.0.0.1:1".to_string(), ArtemisRateLimiter::new(4), config);
+
+    // One conversion costs more than the whole limit, so it's refused before the database
+    let req = test::TestRequest::post()
+        .uri("/api/links/convert")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .set_json(serde_json::json!({ "url": "https://example.com" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body["error"].as_str().unwrap().contains("Rate limit"), "{}", body);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_converted_links_are_listed_by_owner() {
+    let db = common::test_db().await;
+    let (rpc, _sent) = common::submitting_rpc().await;
+    let app = links_app!(db, rpc.uri());
+    let (owner, other) = (Keypair::new(), Keypair::new());
+
+    let convert = |caller: &Keypair, url: &str| {
+        test::TestRequest::post()
+            .uri("/api/links/convert")
+            .insert_header(("X-Shadow-Auth", common::auth_header(caller)))
+            .set_json(serde_json::json!({ "url": url }))
+            .to_request()
+    };
+    let mut mints = Vec::new();
+    for url in ["https://one.example", "https://two.example"] {
+        let created: Value = test::call_and_read_body_json(&app, convert(&owner, url)).await;
+        assert_eq!(created["is_new"], true);
+        mints.push(created["token_mint"].as_str().unwrap().to_string());
+    }
+    let _: Value = test::call_and_read_body_json(&app, convert(&other, "https://three.example")).await;
+
+    // Resolving works both ways through the mounted routes
+    let req = test::TestRequest::post()
+        .uri("/api/links/resolve")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&other)))
+        .set_json(serde_json::json!({ "url": "https://ONE.example" }))
+        .to_request();
+    let resolved: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resolved["token_mint"], mints[0]);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/links/token/{}", mints[1]))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&other)))
+        .to_request();
+    let resolved: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resolved["original_url"], "https://two.example");
+
+    let list = |cursor: Option<&str>| {
+        let mut uri = format!("/api/links?owner={}&limit=1", owner.pubkey());
+        if let Some(cursor) = cursor {
+            uri.push_str(&format!("&cursor={}", cursor));
+        }
+        test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("X-Shadow-Auth", common::auth_header(&other)))
+            .to_request()
+    };
+    let first: Value = test::call_and_read_body_json(&app, list(None)).await;
+    let cursor = first["next_cursor"].as_str().unwrap().to_string();
+    let second: Value = test::call_and_read_body_json(&app, list(Some(&cursor))).await;
+    assert!(second["next_cursor"].is_null());
+    let listed: Vec<&Value> = first["items"].as_array().unwrap().iter().chain(second["items"].as_array().unwrap()).collect();
+    let listed_mints: Vec<&str> = listed.iter().map(|m| m["token_mint"].as_str().unwrap()).collect();
+    assert_eq!(listed_mints, vec![mints[1].as_str(), mints[0].as_str()]);
+    assert!(listed.iter().all(|m| m["owner_pubkey"] == owner.pubkey().to_string()));
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
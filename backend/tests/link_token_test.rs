@@ -0,0 +1,292 @@
+// Integration tests for minting SPL tokens behind converted links
+mod common;
+
+use mongodb::bson::doc;
+use shadow_backend::link_converter::{LinkConverter, LinkMintAuthority, MintStatus, LINK_TOKEN_INITIAL_SUPPLY};
+use shadow_backend::solana::SolanaClient;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use std::sync::Arc;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_general_token_mints_with_metadata() {
+    let db = common::test_db().await;
+    let (rpc, sent) = common::submitting_rpc().await;
+    let authority = Keypair::new();
+    let authority_pubkey = authority.pubkey();
+    let converter = LinkConverter::new(Arc::new(db.clone()), rpc.uri())
+        .with_mint_authority(LinkMintAuthority::new(authority));
+
+    let token_mint = converter.create_general_token("Twitter", "Twitter Links", "TWLNK").await.unwrap();
+
+    let tx = sent.lock().unwrap()[0].clone();
+    let keys = &tx.message.account_keys;
+    assert_eq!(keys[0], authority_pubkey, "mint authority pays for the mint");
+    assert_eq!(keys[1].to_string(), token_mint);
+    assert_eq!(tx.signatures.len(), 2, "mint account signs its own creation");
+    let programs: Vec<Pubkey> = tx.message.instructions.iter()
+        .map(|ix| keys[ix.program_id_index as usize])
+        .collect();
+    assert_eq!(programs, [
+        system_program::id(),
+        spl_token::id(),
+        spl_associated_token_account::id(),
+        spl_token::id(),
+        mpl_token_metadata::ID,
+    ]);
+    let metadata = mpl_token_metadata::accounts::Metadata::find_pda(&keys[1]).0;
+    assert!(keys.contains(&metadata));
+
+    // The initial supply goes to the authority's token account
+    let supply = spl_associated_token_account::get_associated_token_address(&authority_pubkey, &keys[1]);
+    assert!(keys.contains(&supply));
+    let mint_to = spl_token::instruction::TokenInstruction::unpack(&tx.message.instructions[3].data).unwrap();
+    assert_eq!(mint_to, spl_token::instruction::TokenInstruction::MintTo { amount: LINK_TOKEN_INITIAL_SUPPLY });
+
+    let stored = converter.get_existing_mapping("platform:twitter").await.unwrap().unwrap();
+    assert_eq!(stored.token_mint, token_mint);
+    assert_eq!(stored.status, MintStatus::Minted);
+    assert_eq!(stored.mint_signature, Some(tx.signatures[0].to_string()));
+    assert_eq!(stored.metadata.unwrap().symbol, "TWLNK");
+
+    // The platform already has a token, so nothing new is minted
+    assert_eq!(converter.create_general_token("twitter", "Twitter Links", "TWLNK").await.unwrap(), token_mint);
+    assert_eq!(sent.lock().unwrap().len(), 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_link_token_is_a_plain_mint() {
+    let db = common::test_db().await;
+    let (rpc, sent) = common::submitting_rpc().await;
+    let converter = LinkConverter::new(Arc::new(db.clone()), rpc.uri())
+        .with_mint_authority(LinkMintAuthority::new(Keypair::new()));
+
+    let created = converter.convert_link("https://Example.com/Docs", Some("/intro"), None).await.unwrap();
+    assert!(created.is_new);
+    let tx = sent.lock().unwrap()[0].clone();
+    let programs: Vec<Pubkey> = tx.message.instructions.iter()
+        .map(|ix| tx.message.account_keys[ix.program_id_index as usize])
+        .collect();
+    assert!(!programs.contains(&mpl_token_metadata::ID), "link tokens carry no metadata");
+    assert_eq!(tx.message.account_keys[1].to_string(), created.token_mint);
+
+    let mapping = db.collection::<mongodb::bson::Document>("link_mappings")
+        .find_one(doc! { "_id": &created.url_hash }, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(mapping.get_str("token_mint").unwrap(), created.token_mint);
+    assert_eq!(mapping.get_str("status").unwrap(), "minted");
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_failed_mint_is_pending_until_retried() {
+    let db = common::test_db().await;
+    let authority = Keypair::new();
+    let authority_bytes = authority.to_bytes();
+    let offline = LinkConverter::new(Arc::new(db.clone()), "http://127.0.0.1:1".to_string())
+        .with_mint_authority(LinkMintAuthority::new(authority));
+
+    // The RPC is unreachable, so the mapping is kept but marked pending
+    let created = offline.convert_link("https://pending.example", None, None).await.unwrap();
+    assert!(created.is_new);
+    assert_eq!(created.status, MintStatus::PendingMint);
+    let pending = offline.get_existing_mapping("https://pending.example").await.unwrap().unwrap();
+    assert_eq!(pending.status, MintStatus::PendingMint);
+    assert!(pending.mint_signature.is_none());
+
+    // Retrying while the RPC is still down leaves it pending
+    assert_eq!(offline.retry_mint(&pending).await.unwrap().status, MintStatus::PendingMint);
+
+    // The first attempt never landed, so the retry mints a fresh token
+    let (rpc, sent) = common::submitting_rpc().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": null },
+            "id": 1
+        })))
+        .with_priority(1)
+        .mount(&rpc)
+        .await;
+    let converter = LinkConverter::new(Arc::new(db.clone()), rpc.uri())
+        .with_mint_authority(LinkMintAuthority::new(Keypair::from_bytes(&authority_bytes).unwrap()));
+    assert_eq!(converter.retry_pending_mints(10).await.unwrap(), 1);
+
+    let minted = converter.get_existing_mapping("https://pending.example").await.unwrap().unwrap();
+    let tx = sent.lock().unwrap()[0].clone();
+    assert_eq!(minted.status, MintStatus::Minted);
+    assert_eq!(minted.token_mint, tx.message.account_keys[1].to_string());
+    assert_ne!(minted.token_mint, pending.token_mint);
+    assert_eq!(minted.mint_signature, Some(tx.signatures[0].to_string()));
+
+    // Nothing is left to retry, and converting again mints nothing
+    assert_eq!(converter.retry_pending_mints(10).await.unwrap(), 0);
+    let again = converter.convert_link("https://pending.example", None, None).await.unwrap();
+    assert_eq!((again.token_mint, again.status), (minted.token_mint, MintStatus::Minted));
+    assert_eq!(sent.lock().unwrap().len(), 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_racing_conversions_mint_once() {
+    let db = common::test_db().await;
+    let (rpc, sent) = common::submitting_rpc().await;
+    let converter = LinkConverter::new(Arc::new(db.clone()), rpc.uri())
+        .with_mint_authority(LinkMintAuthority::new(Keypair::new()));
+
+    // Only the conversion that claims the URL mints; the rest get its mapping
+    let results = futures_util::future::join_all(
+        (0..8).map(|_| converter.convert_link("https://race.example", Some("/a"), None)),
+    )
+    .await;
+    let results: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+    assert_eq!(results.iter().filter(|r| r.is_new).count(), 1);
+    assert_eq!(sent.lock().unwrap().len(), 1);
+    let minted = converter.get_existing_mapping("https://race.example").await.unwrap().unwrap();
+    assert_eq!(minted.status, MintStatus::Minted);
+    assert!(minted.claimed_until.is_none());
+    assert_eq!(minted.subpaths, ["/a"]);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_claimed_pending_mint_is_not_retried() {
+    let db = common::test_db().await;
+    let (rpc, sent) = common::submitting_rpc().await;
+    let converter = LinkConverter::new(Arc::new(db.clone()), rpc.uri())
+        .with_mint_authority(LinkMintAuthority::new(Keypair::new()));
+    let url_hash = LinkConverter::hash_url("https://claimed.example");
+    let claimed_until = |offset_ms: i64| {
+        mongodb::bson::DateTime::from_millis(mongodb::bson::DateTime::now().timestamp_millis() + offset_ms)
+    };
+    db.collection::<mongodb::bson::Document>("link_mappings")
+        .insert_one(doc! {
+            "_id": &url_hash,
+            "token_mint": Pubkey::new_unique().to_string(),
+            "original_url": "https://claimed.example",
+            "subpaths": [],
+            "status": "pending_mint",
+            "claimed_until": claimed_until(60_000),
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    // Someone else is still minting for it
+    let again = converter.convert_link("https://claimed.example", None, None).await.unwrap();
+    assert!(!again.is_new);
+    assert_eq!(again.status, MintStatus::PendingMint);
+    assert_eq!(converter.retry_pending_mints(10).await.unwrap(), 0);
+    assert!(sent.lock().unwrap().is_empty());
+
+    // Once the claim lapses the mapping is fair game again
+    db.collection::<mongodb::bson::Document>("link_mappings")
+        .update_one(doc! { "_id": &url_hash }, doc! { "$set": { "claimed_until": claimed_until(-1_000) } }, None)
+        .await
+        .unwrap();
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": null },
+            "id": 1
+        })))
+        .with_priority(1)
+        .mount(&rpc)
+        .await;
+    assert_eq!(converter.retry_pending_mints(10).await.unwrap(), 1);
+    let minted = converter.get_existing_mapping("https://claimed.example").await.unwrap().unwrap();
+    assert_eq!(minted.status, MintStatus::Minted);
+    assert!(minted.claimed_until.is_none());
+    assert_eq!(sent.lock().unwrap().len(), 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_new_links_are_capped_per_wallet_per_day() {
+    let db = common::test_db().await;
+    let (rpc, sent) = common::submitting_rpc().await;
+    let converter = LinkConverter::new(Arc::new(db.clone()), rpc.uri())
+        .with_mint_authority(LinkMintAuthority::new(Keypair::new()))
+        .with_daily_mint_quota(2);
+    let (owner, other) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+
+    for url in ["https://one.example", "https://two.example"] {
+        assert!(converter.convert_link(url, None, Some(&owner)).await.unwrap().is_new);
+    }
+    let err = converter.convert_link("https://three.example", None, Some(&owner)).await.unwrap_err();
+    assert!(err.contains("quota"), "{}", err);
+    assert!(converter.get_existing_mapping("https://three.example").await.unwrap().is_none());
+
+    // Known links cost nothing, and other wallets have their own quota
+    assert!(!converter.convert_link("https://one.example", Some("/more"), Some(&owner)).await.unwrap().is_new);
+    assert!(converter.convert_link("https://three.example", None, Some(&other)).await.unwrap().is_new);
+    assert_eq!(sent.lock().unwrap().len(), 3);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_minting_requires_a_mint_authority() {
+    let db = common::test_db().await;
+    let (rpc, sent) = common::submitting_rpc().await;
+
+    let converter = LinkConverter::new(Arc::new(db.clone()), rpc.uri());
+    let err = converter.convert_link("https://example.com", None, None).await.unwrap_err();
+    assert!(err.contains("not configured"), "{}", err);
+    assert!(sent.lock().unwrap().is_empty());
+    assert!(converter.get_existing_mapping("https://example.com").await.unwrap().is_none());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+async fn test_general_token_metadata_is_validated_before_minting() {
+    let (rpc, sent) = common::submitting_rpc().await;
+    let converter = LinkConverter::new(Arc::new(common::offline_db().await), rpc.uri())
+        .with_mint_authority(LinkMintAuthority::new(Keypair::new()));
+
+    assert!(converter.create_general_token("x", "A name longer than thirty-two bytes", "X").await.is_err());
+    assert!(converter.create_general_token("x", "X Links", "TOOLONGSYMBOL").await.is_err());
+    assert!(converter.create_general_token("x", "X Links", " ").await.is_err());
+    assert!(sent.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_send_transaction_returns_confirmed_signature() {
+    let (rpc, sent) = common::submitting_rpc().await;
+    let solana = SolanaClient::new(rpc.uri());
+    let authority = Keypair::new();
+    let mint = Keypair::new();
+
+    let rent = solana.get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN).await.unwrap();
+    let instructions = LinkConverter::mint_instructions(&authority.pubkey(), &mint.pubkey(), rent).unwrap();
+    let blockhash = solana.get_recent_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(&instructions, Some(&authority.pubkey()), &[&authority, &mint], blockhash);
+
+    let signature = solana.send_transaction(&tx).await.unwrap();
+    assert_eq!(signature, tx.signatures[0].to_string());
+    assert_eq!(sent.lock().unwrap()[0], tx);
+}
@@ -0,0 +1,137 @@
+// Integration tests for Enodia outbound link interstitial signals
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::{Client, Database};
+use serde_json::Value;
+use shadow_backend::artemis::ArtemisRateLimiter;
+use shadow_backend::athena::AthenaIndexer;
+use shadow_backend::config::LinkInfoConfig;
+use shadow_backend::enodia::EnodiaLinkInfo;
+use shadow_backend::handlers_link;
+use solana_sdk::pubkey::Pubkey;
+use wiremock::matchers::any;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn link_info_config() -> LinkInfoConfig {
+    LinkInfoConfig {
+        blocklist: vec!["drainer.example".to_string()],
+        allowlist: vec!["github.com".to_string()],
+        cache_ttl_seconds: 3600,
+        rate_limit_cost: 10,
+    }
+}
+
+macro_rules! linkinfo_app {
+    ($db:expr, $requests_per_minute:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(EnodiaLinkInfo::new($db.clone(), link_info_config())))
+                .app_data(web::Data::new(ArtemisRateLimiter::new($requests_per_minute)))
+                .route("/api/linkinfo", web::get().to(handlers_link::get_link_info)),
+        )
+        .await
+    };
+}
+
+fn link_info(url: &str) -> test::TestRequest {
+    let encoded: String = url
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    test::TestRequest::get()
+        .uri(&format!("/api/linkinfo?url={}", encoded))
+        .peer_addr("203.0.113.7:4000".parse().unwrap())
+}
+
+/// Like `common::offline_db`, but gives up on the missing server quickly
+async fn unreachable_db() -> Database {
+    Client::with_uri_str("mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100")
+        .await
+        .unwrap()
+        .database("shadow_offline")
+}
+
+#[actix_web::test]
+async fn test_rejects_links_that_are_not_outbound() {
+    let app = linkinfo_app!(common::offline_db().await, 600);
+    for url in ["not a url", "ftp://files.example/x", "javascript:alert(1)", "https://docs.shadow/guide"] {
+        let resp = test::call_service(&app, link_info(url).to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", url);
+    }
+}
+
+#[actix_web::test]
+async fn test_lookup_never_requests_the_url() {
+    let target = MockServer::start().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&target)
+        .await;
+    let app = linkinfo_app!(unreachable_db().await, 600);
+
+    for url in [format!("{}/probe", target.uri()), format!("{}/redirect?to=http://169.254.169.254/", target.uri())] {
+        test::call_service(&app, link_info(&url).to_request()).await;
+    }
+    assert!(target.received_requests().await.unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn test_lookups_are_rate_limited_heavily() {
+    // Each lookup costs ten units, so a 25/minute budget allows two
+    let app = linkinfo_app!(common::offline_db().await, 25);
+    let mut errors = Vec::new();
+    for _ in 0..3 {
+        let body: Value = test::call_and_read_body_json(&app, link_info("mailto:someone@example.com").to_request()).await;
+        errors.push(body["error"].as_str().unwrap().to_string());
+    }
+    assert!(errors[..2].iter().all(|e| e.contains("http or https")), "{:?}", errors);
+    assert!(errors[2].contains("Rate limit exceeded"), "{:?}", errors);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_blocklisted_well_known_and_unseen_domains() {
+    let db = common::test_db().await;
+    let athena = AthenaIndexer::new(db.clone());
+    let page = r#"<a href="https://github.com/shadow">Code</a> <a href="https://wallet.drainer.example/claim">Claim</a>"#;
+    for domain in ["first.shadow", "second.shadow"] {
+        athena
+            .index_site(domain, &Pubkey::new_unique().to_string(), None, None, None, page)
+            .await
+            .unwrap();
+    }
+    let app = linkinfo_app!(db, 600);
+
+    let resp = test::call_service(&app, link_info("  HTTPS://GitHub.com/shadow/Issues ").to_request()).await;
+    assert!(resp.headers().get("cache-control").unwrap().to_str().unwrap().starts_with("public"));
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["url"], "https://github.com/shadow/issues");
+    assert_eq!(body["list"], "allowed");
+    assert_eq!(body["linking_sites"], 2);
+    assert_eq!(body["first_seen_on"], "first.shadow");
+    assert!(body["first_seen_at"].is_string());
+
+    let body: Value = test::call_and_read_body_json(&app, link_info("https://wallet.drainer.example/claim").to_request()).await;
+    assert_eq!(body["list"], "blocked");
+    assert_eq!(body["linking_sites"], 2);
+
+    let body: Value = test::call_and_read_body_json(&app, link_info("https://never-linked.example/").to_request()).await;
+    assert_eq!(body["list"], "unlisted");
+    assert_eq!(body["linking_sites"], 0);
+    assert!(body["first_seen_at"].is_null());
+
+    // Answers are cached per host, so new links show up once the entry expires
+    athena
+        .index_site("third.shadow", &Pubkey::new_unique().to_string(), None, None, None, page)
+        .await
+        .unwrap();
+    let body: Value = test::call_and_read_body_json(&app, link_info("https://github.com/").to_request()).await;
+    assert_eq!(body["linking_sites"], 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,185 @@
+// Integration tests for Accept-Language negotiation on multi-language sites
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::apollo::ApolloValidator;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::mnemosyne::Mnemosyne;
+use shadow_backend::storage::{BundlrStorage, PinataStorage};
+use shadow_backend::tyche::TycheOwnership;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SITE_CID: &str = "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+
+macro_rules! site_app {
+    ($db:expr, $cache:expr) => {
+        site_app!($db, $cache, AnchorClient::with_programs("http://127.0.0.1:1".to_string(), Pubkey::new_unique(), Pubkey::new_unique()))
+    };
+    ($db:expr, $cache:expr, $anchor:expr) => {{
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(Mnemosyne::new($db.clone())))
+                .app_data(web::Data::new("http://127.0.0.1:1".to_string()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(ApolloValidator::new()))
+                .app_data(web::Data::new($anchor))
+                .app_data(web::Data::new(TycheOwnership::new($db.clone(), "http://127.0.0.1:1".to_string())))
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .app_data(web::Data::new(PinataStorage::new()))
+                .app_data(web::Data::new(BundlrStorage::new()))
+                .app_data($cache.clone())
+                .app_data(web::Data::new(common::test_config()))
+                .route("/api/sites/{program_address}", web::put().to(handlers::update_site))
+                .route("/api/sites/{program_address}/content", web::get().to(handlers::get_site_content)),
+        )
+        .await
+    }};
+}
+
+#[tokio::test]
+async fn test_invalid_languages_map_is_rejected() {
+    let db = common::offline_db().await;
+    let cache = web::Data::new(HephaestusCache::new(16, 3600));
+    let app = site_app!(db, cache);
+    let owner = Keypair::new();
+
+    for (languages, default_language) in [
+        (serde_json::json!({ "en": "../secrets.html" }), None),
+        (serde_json::json!({ "english!": "index.html" }), None),
+        (serde_json::json!({ "en": "index.html" }), Some("es")),
+        (serde_json::json!({}), None),
+    ] {
+        let req = test::TestRequest::put()
+            .uri(&format!("/api/sites/{}", owner.pubkey()))
+            .set_json(serde_json::json!({
+                "owner_pubkey": owner.pubkey().to_string(),
+                "storage_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+                "languages": languages,
+                "default_language": default_language,
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400, "{}", languages);
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_content_negotiated_and_cached_per_language() {
+    let db = common::test_db().await;
+    let program = Pubkey::new_unique().to_string();
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": &program,
+            "owner_pubkey": Pubkey::new_unique().to_string(),
+            "storage_cid": SITE_CID,
+            "name": "Polyglot",
+            "description": null,
+            "languages": { "en": "index.html", "es": "es/index.html" },
+            "default_language": "en",
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    // Seed each language's cache entry so no storage gateway is needed
+    let cache = web::Data::new(HephaestusCache::new(16, 3600));
+    for (lang, body) in [("en", "Hello"), ("es", "Hola")] {
+        cache.set(
+            HephaestusCache::site_content_key(SITE_CID, Some(lang)),
+            body.as_bytes().to_vec(),
+            "text/html".to_string(),
+            None,
+        ).await.unwrap();
+    }
+    let app = site_app!(db, cache);
+    let uri = format!("/api/sites/{}/content", program);
+
+    let req = test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(("Accept-Language", "es-MX, es;q=0.9, en;q=0.5"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("content-language").unwrap(), "es");
+    assert_eq!(resp.headers().get("vary").unwrap(), "Accept-Language");
+    assert_eq!(test::read_body(resp).await, "Hola");
+
+    // No acceptable language falls back to the site default
+    let req = test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(("Accept-Language", "ja"))
+        .to_request();
+    assert_eq!(test::call_and_read_body(&app, req).await, "Hello");
+
+    // ?lang= overrides the header
+    let req = test::TestRequest::get()
+        .uri(&format!("{}?lang=en", uri))
+        .insert_header(("Accept-Language", "es"))
+        .to_request();
+    assert_eq!(test::call_and_read_body(&app, req).await, "Hello");
+
+    assert_eq!(cache.get_stats().await.total_entries, 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_changing_languages_evicts_cached_content() {
+    let db = common::test_db().await;
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": &program,
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": SITE_CID,
+            "name": "Polyglot",
+            "description": null,
+            "languages": { "en": "index.html", "es": "es/index.html" },
+            "default_language": "en",
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    let cache = web::Data::new(HephaestusCache::new(16, 3600));
+    for lang in ["en", "es"] {
+        cache.set(HephaestusCache::site_content_key(SITE_CID, Some(lang)), b"old".to_vec(), "text/html".to_string(), None)
+            .await
+            .unwrap();
+    }
+    let (_rpc, anchor) = common::empty_registry().await;
+    let app = site_app!(db, cache, anchor);
+    let put = |name: &str, es_entry: &str| {
+        test::TestRequest::put()
+            .uri(&format!("/api/sites/{}", program))
+            .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+            .set_json(serde_json::json!({
+                "owner_pubkey": owner.pubkey().to_string(),
+                "storage_cid": SITE_CID,
+                "name": name,
+                "languages": { "en": "index.html", "es": es_entry },
+                "default_language": "en",
+            }))
+            .to_request()
+    };
+
+    // Renaming serves the same files, so the cache stays
+    assert_eq!(test::call_service(&app, put("Renamed", "es/index.html")).await.status(), 200);
+    assert_eq!(cache.get_stats().await.total_entries, 2);
+
+    // Same CID, but Spanish now comes from another entry file
+    assert_eq!(test::call_service(&app, put("Renamed", "es/home.html")).await.status(), 200);
+    assert_eq!(cache.get_stats().await.total_entries, 0);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
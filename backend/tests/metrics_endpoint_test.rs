@@ -0,0 +1,53 @@
+// Integration tests for the metrics endpoint's JSON and Prometheus formats
+use actix_web::{test, web, App, HttpResponse};
+use shadow_backend::handlers;
+use shadow_backend::metrics::{MetricsCollector, HTTP_REQUEST_DURATION};
+use shadow_backend::middleware::timing_middleware;
+
+macro_rules! metrics_app {
+    () => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .wrap(actix_web::middleware::from_fn(timing_middleware))
+                .route("/api/ping", web::get().to(|| async { HttpResponse::Ok().finish() }))
+                .route("/api/metrics", web::get().to(handlers::get_metrics)),
+        )
+        .await
+    };
+}
+
+#[actix_web::test]
+async fn test_metrics_are_json_by_default() {
+    let app = metrics_app!();
+    test::call_service(&app, test::TestRequest::get().uri("/api/ping").to_request()).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/metrics")
+        .insert_header(("Accept", "application/json"))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total_requests"], 1);
+    assert_eq!(body["histograms"][HTTP_REQUEST_DURATION]["count"], 1);
+}
+
+#[actix_web::test]
+async fn test_scrapers_get_prometheus_text() {
+    let app = metrics_app!();
+    for _ in 0..3 {
+        test::call_service(&app, test::TestRequest::get().uri("/api/ping").to_request()).await;
+    }
+
+    // What Prometheus itself sends
+    let req = test::TestRequest::get()
+        .uri("/api/metrics")
+        .insert_header(("Accept", "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5,*/*;q=0.1"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("Content-Type").unwrap(), "text/plain; version=0.0.4; charset=utf-8");
+    let text = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(text.contains("shadow_requests_total 3\n"), "{}", text);
+    assert!(text.contains("# TYPE shadow_http_request_duration_seconds histogram\n"));
+    assert!(text.contains("shadow_http_request_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+    assert!(text.contains("shadow_http_request_duration_seconds_count 3\n"));
+}
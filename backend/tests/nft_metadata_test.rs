@@ -0,0 +1,177 @@
+// Integration tests for Aphrodite reading Metaplex metadata accounts and their off-chain JSON
+mod common;
+
+use base64::{engine::general_purpose, Engine as _};
+use mpl_token_metadata::accounts::Metadata;
+use serde_json::Value;
+use shadow_backend::aphrodite::AphroditeNFTManager;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockBuilder, MockServer, ResponseTemplate};
+
+fn borsh_string(out: &mut Vec<u8>, s: &str, width: usize) {
+    // The program pads names, symbols and URIs to a fixed width with NULs
+    let mut padded = s.as_bytes().to_vec();
+    padded.resize(width.max(s.len()), 0);
+    out.extend_from_slice(&(padded.len() as u32).to_le_bytes());
+    out.extend_from_slice(&padded);
+}
+
+/// Metadata account bytes as Token Metadata lays them out
+fn metadata_account(mint: &Pubkey, name: &str, uri: &str, collection: &Pubkey) -> Vec<u8> {
+    let mut data = vec![4]; // Key::MetadataV1
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(mint.as_ref());
+    borsh_string(&mut data, name, 32);
+    borsh_string(&mut data, "SHDW", 10);
+    borsh_string(&mut data, uri, 200);
+    data.extend_from_slice(&500u16.to_le_bytes());
+    data.extend_from_slice(&[0, 1, 1, 0, 0]); // no creators, sold, mutable, no nonce or standard
+    data.push(1);
+    data.push(1);
+    data.extend_from_slice(collection.as_ref());
+    data.resize(679, 0); // uses, collection details and programmable config all None
+    data
+}
+
+fn account_response(data: Option<Vec<u8>>) -> ResponseTemplate {
+    let value = data.map(|data| serde_json::json!({
+        "data": [general_purpose::STANDARD.encode(&data), "base64"],
+        "executable": false,
+        "lamports": 5_616_720,
+        "owner": mpl_token_metadata::ID.to_string(),
+        "rentEpoch": 0,
+        "space": data.len(),
+    }));
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "result": { "context": { "slot": 1 }, "value": value },
+        "id": 1
+    }))
+}
+
+/// RPC stand-in; the client checks the node version before its first account read
+async fn rpc_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "solana-core": "1.18.26", "feature-set": 0 },
+            "id": 1
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn get_metadata_account(mint: &Pubkey) -> MockBuilder {
+    let (address, _) = Metadata::find_pda(mint);
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo", "params": [address.to_string()] })))
+}
+
+async fn mount_off_chain_json(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/meta/7.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "name": "Off-chain name",
+            "description": "A shadowy figure",
+            "image": "https://img.example/7.png",
+            "attributes": [
+                { "trait_type": "Background", "value": "Night" },
+                { "trait_type": "Level", "value": 3 }
+            ]
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_metadata_is_read_from_chain_and_cached() {
+    let db = common::test_db().await;
+    let rpc = rpc_server().await;
+    let web = MockServer::start().await;
+    mount_off_chain_json(&web).await;
+
+    let mint = Pubkey::new_unique();
+    let collection = Pubkey::new_unique();
+    let uri = format!("{}/meta/7.json", web.uri());
+    get_metadata_account(&mint)
+        .respond_with(account_response(Some(metadata_account(&mint, "Shade #7", &uri, &collection))))
+        .expect(1)
+        .mount(&rpc)
+        .await;
+
+    let manager = AphroditeNFTManager::new(Arc::new(db.clone()), rpc.uri());
+    let metadata = manager.get_nft_metadata(&mint.to_string()).await.unwrap();
+    assert_eq!(metadata.name, "Shade #7");
+    assert_eq!(metadata.symbol.as_deref(), Some("SHDW"));
+    assert_eq!(metadata.uri.as_deref(), Some(uri.as_str()));
+    assert_eq!(metadata.description.as_deref(), Some("A shadowy figure"));
+    assert_eq!(metadata.image.as_deref(), Some("https://img.example/7.png"));
+    assert_eq!(metadata.collection, Some(collection.to_string()));
+    let attributes: Vec<(&str, &str)> = metadata.attributes.iter()
+        .map(|a| (a.trait_type.as_str(), a.value.as_str()))
+        .collect();
+    assert_eq!(attributes, [("Background", "Night"), ("Level", "3")]);
+    assert!(metadata.on_chain);
+
+    // Served from the cache while fresh; the RPC mock only expects one call
+    let cached = manager.get_nft_metadata(&mint.to_string()).await.unwrap();
+    assert_eq!(cached.name, "Shade #7");
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_missing_account_keeps_previous_metadata() {
+    let db = common::test_db().await;
+    let rpc = rpc_server().await;
+    let web = MockServer::start().await;
+    mount_off_chain_json(&web).await;
+
+    let mint = Pubkey::new_unique();
+    let uri = format!("{}/meta/7.json", web.uri());
+    get_metadata_account(&mint)
+        .respond_with(account_response(Some(metadata_account(&mint, "Shade #7", &uri, &Pubkey::new_unique()))))
+        .up_to_n_times(1)
+        .mount(&rpc)
+        .await;
+    get_metadata_account(&mint)
+        .respond_with(account_response(None))
+        .mount(&rpc)
+        .await;
+
+    // Every read is stale, so each one goes back to the chain
+    let manager = AphroditeNFTManager::new(Arc::new(db.clone()), rpc.uri())
+        .with_metadata_ttl(Duration::ZERO);
+    assert_eq!(manager.get_nft_metadata(&mint.to_string()).await.unwrap().name, "Shade #7");
+    let again = manager.get_nft_metadata(&mint.to_string()).await.unwrap();
+    assert_eq!(again.name, "Shade #7");
+    assert!(again.on_chain);
+
+    // A mint that never had an account gets a placeholder
+    let bare = Pubkey::new_unique();
+    get_metadata_account(&bare).respond_with(account_response(None)).mount(&rpc).await;
+    let stub = manager.get_nft_metadata(&bare.to_string()).await.unwrap();
+    assert_eq!(stub.name, "Unknown NFT");
+    assert!(!stub.on_chain);
+
+    let stored = db.collection::<Value>("nft_metadata").count_documents(None, None).await.unwrap();
+    assert_eq!(stored, 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+async fn test_invalid_mint_is_rejected() {
+    let manager = AphroditeNFTManager::new(Arc::new(common::offline_db().await), "http://127.0.0.1:1".to_string());
+    let err = manager.get_nft_metadata("not-a-mint").await.unwrap_err();
+    assert!(err.contains("Invalid mint"));
+}
+
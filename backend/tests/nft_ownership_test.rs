@@ -0,0 +1,250 @@
+// Integration tests for Tyche NFT-controlled site ownership
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::db::{OwnershipMode, Site};
+use shadow_backend::error::ShadowError;
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::mnemosyne::Mnemosyne;
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::tyche::{NftHolder, TycheOwnership};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn rpc_result(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+/// Point `mint` at a single token account owned by `holder`
+async fn mount_holder(rpc: &MockServer, mint: &str, holder: &str) {
+    let token_account = Pubkey::new_unique().to_string();
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getTokenLargestAccounts", "params": [mint] })))
+        .respond_with(rpc_result(serde_json::json!({
+            "context": { "slot": 1 },
+            "value": [{ "address": token_account, "amount": "1", "decimals": 0, "uiAmountString": "1" }]
+        })))
+        .mount(rpc)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo", "params": [token_account] })))
+        .respond_with(rpc_result(serde_json::json!({
+            "context": { "slot": 1 },
+            "value": {
+                "data": { "program": "spl-token", "parsed": { "type": "account", "info": {
+                    "mint": mint, "owner": holder, "tokenAmount": { "amount": "1", "decimals": 0 }
+                } } },
+                "executable": false,
+                "lamports": 2_039_280,
+                "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                "rentEpoch": 0
+            }
+        })))
+        .mount(rpc)
+        .await;
+}
+
+/// The last token was burned and the mint closed
+async fn mount_burned(rpc: &MockServer) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getTokenLargestAccounts" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": -32602, "message": "Invalid param: could not find mint" }
+        })))
+        .mount(rpc)
+        .await;
+}
+
+/// PATCH the site's name at its current revision
+async fn rename(db: &mongodb::Database, program: &str, wallet: &Keypair, name: &str) -> test::TestRequest {
+    let site = shadow_backend::db::get_site(db, program).await.unwrap().unwrap();
+    test::TestRequest::patch()
+        .uri(&format!("/api/sites/{}", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+        .set_json(serde_json::json!({ "name": name, "expected_revision": site.revision }))
+}
+
+fn nft_site(mint: &str) -> Site {
+    mongodb::bson::from_document(doc! {
+        "_id": Pubkey::new_unique().to_string(),
+        "owner_pubkey": Pubkey::new_unique().to_string(),
+        "storage_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+        "name": null,
+        "description": null,
+        "ownership_mode": "nft",
+        "ownership_mint": mint,
+        "created_at": mongodb::bson::DateTime::now(),
+        "updated_at": mongodb::bson::DateTime::now(),
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_controller_follows_the_nft_holder() {
+    let rpc = MockServer::start().await;
+    let mint = Pubkey::new_unique().to_string();
+    let (holder, other) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+    mount_holder(&rpc, &mint, &holder).await;
+    let tyche = TycheOwnership::new(common::offline_db().await, rpc.uri());
+    let site = nft_site(&mint);
+
+    assert!(tyche.is_controller(&site, &holder).await.unwrap());
+    assert!(!tyche.is_controller(&site, &other).await.unwrap());
+    assert!(!tyche.is_controller(&site, &site.owner_pubkey).await.unwrap());
+
+    // Lookups are cached until forgotten
+    rpc.reset().await;
+    mount_holder(&rpc, &mint, &other).await;
+    assert!(tyche.is_controller(&site, &holder).await.unwrap());
+    tyche.forget(&mint);
+    assert!(tyche.is_controller(&site, &other).await.unwrap());
+    assert!(!tyche.is_controller(&site, &holder).await.unwrap());
+
+    let controller = tyche.controller(&site).await;
+    assert_eq!(controller.mode, OwnershipMode::Nft);
+    assert_eq!(controller.wallet.as_deref(), Some(other.as_str()));
+}
+
+#[tokio::test]
+async fn test_burned_nft_fails_with_gone() {
+    let rpc = MockServer::start().await;
+    mount_burned(&rpc).await;
+    let mint = Pubkey::new_unique().to_string();
+    let tyche = TycheOwnership::new(common::offline_db().await, rpc.uri());
+    let site = nft_site(&mint);
+
+    assert_eq!(tyche.holder(&mint).await.unwrap(), NftHolder::Burned);
+    match tyche.is_controller(&site, &site.owner_pubkey).await {
+        Err(ShadowError::Gone(msg)) => assert!(msg.contains("burned"), "{}", msg),
+        other => panic!("expected Gone, got {:?}", other),
+    }
+    let controller = tyche.controller(&site).await;
+    assert!(controller.nft_burned);
+    assert!(controller.wallet.is_none());
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_nft_mode_switch_transfer_and_recovery() {
+    let db = common::test_db().await;
+    let rpc = MockServer::start().await;
+    let (owner, holder, buyer, admin) = (Keypair::new(), Keypair::new(), Keypair::new(), Keypair::new());
+    let mint = Pubkey::new_unique().to_string();
+    let program = Pubkey::new_unique().to_string();
+    mount_holder(&rpc, &mint, &holder.pubkey().to_string()).await;
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": &program,
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            "name": "Team site",
+            "description": null,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    let mut config = common::test_config();
+    config.auth.admin_wallets = vec![admin.pubkey().to_string()];
+    let tyche = Arc::new(TycheOwnership::new(db.clone(), rpc.uri()).with_cache_ttl(Duration::ZERO));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(Mnemosyne::new(db.clone())))
+            .app_data(web::Data::new(HephaestusCache::new(16, 60)))
+            .app_data(web::Data::new(OlympusCA::new(db.clone())))
+            .app_data(web::Data::new(shadow_backend::metrics::MetricsCollector::new()))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), Pubkey::new_unique())))
+            .app_data(web::Data::from(Arc::clone(&tyche)))
+            .app_data(web::Data::new(config))
+            .route("/api/sites/{program_address}", web::get().to(handlers::get_site))
+            .route("/api/sites/{program_address}", web::put().to(handlers::update_site))
+            .route("/api/sites/{program_address}", web::patch().to(handlers::patch_site))
+            .route("/api/sites/{program_address}/ownership", web::get().to(handlers::get_site_ownership))
+            .route("/api/sites/{program_address}/ownership", web::put().to(handlers::set_site_ownership))
+            .route(
+                "/api/admin/sites/{program_address}/ownership/recover",
+                web::post().to(handlers::recover_site_ownership),
+            ),
+    )
+    .await;
+    let site_uri = format!("/api/sites/{}", program);
+    let ownership_uri = format!("/api/sites/{}/ownership", program);
+    let set_mode = |wallet: &Keypair, body: Value| {
+        test::TestRequest::put()
+            .uri(&ownership_uri)
+            .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+            .set_json(body)
+            .to_request()
+    };
+
+    // Only the wallet owner can hand the site to the NFT
+    let req = set_mode(&holder, serde_json::json!({ "mode": "nft", "mint": &mint }));
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    let req = set_mode(&owner, serde_json::json!({ "mode": "nft", "mint": &mint }));
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["controller"]["wallet"], holder.pubkey().to_string());
+
+    assert_eq!(test::call_service(&app, rename(&db, &program, &owner, "Old owner").await.to_request()).await.status(), 403);
+    assert_eq!(test::call_service(&app, rename(&db, &program, &holder, "Holder").await.to_request()).await.status(), 200);
+
+    // The NFT changes hands: the buyer takes over
+    rpc.reset().await;
+    mount_holder(&rpc, &mint, &buyer.pubkey().to_string()).await;
+    assert_eq!(test::call_service(&app, rename(&db, &program, &holder, "Seller").await.to_request()).await.status(), 403);
+    assert_eq!(test::call_service(&app, rename(&db, &program, &buyer, "Buyer").await.to_request()).await.status(), 200);
+    let body: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&site_uri).to_request()).await;
+    assert_eq!(body["controller"]["mode"], "nft");
+    assert_eq!(body["controller"]["wallet"], buyer.pubkey().to_string());
+
+    // PUT can't bypass the NFT check
+    let req = test::TestRequest::put()
+        .uri(&site_uri)
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(serde_json::json!({
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    // Burned: writes fail with a specific error until an admin recovers the site
+    rpc.reset().await;
+    mount_burned(&rpc).await;
+    let resp = test::call_service(&app, rename(&db, &program, &buyer, "After burn").await.to_request()).await;
+    assert_eq!(resp.status(), 410);
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body["error"].as_str().unwrap().contains("burned"));
+
+    let recover = |wallet: &Keypair| {
+        test::TestRequest::post()
+            .uri(&format!("/api/admin/sites/{}/ownership/recover", program))
+            .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+            .set_json(serde_json::json!({ "owner_pubkey": buyer.pubkey().to_string(), "reason": "NFT burned" }))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, recover(&buyer)).await.status(), 403);
+    assert_eq!(test::call_service(&app, recover(&admin)).await.status(), 200);
+    assert_eq!(test::call_service(&app, rename(&db, &program, &buyer, "Recovered").await.to_request()).await.status(), 200);
+
+    let body: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&ownership_uri).to_request()).await;
+    assert_eq!(body["controller"]["mode"], "wallet");
+    let kinds: Vec<&str> = body["events"].as_array().unwrap().iter().map(|e| e["kind"].as_str().unwrap()).collect();
+    assert_eq!(kinds, vec!["ownership_mode_changed", "ownership_recovered"]);
+    assert_eq!(body["events"][1]["actor"], admin.pubkey().to_string());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,214 @@
+// Integration tests for the Mnemosyne transactional outbox and its relay
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::config::OutboxConfig;
+use shadow_backend::handlers;
+use shadow_backend::mnemosyne::{Mnemosyne, OutboxPayload, OutboxRelay, OutboxStatus, PrimaryWrite};
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::websocket::HermesBroker;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+fn config() -> OutboxConfig {
+    OutboxConfig {
+        poll_interval_ms: 10,
+        batch_size: 10,
+        max_attempts: 3,
+        lease_seconds: 60,
+    }
+}
+
+async fn count(db: &Database, collection: &str, filter: Document) -> u64 {
+    db.collection::<Document>(collection)
+        .count_documents(filter, None)
+        .await
+        .unwrap()
+}
+
+async fn insert_site(db: &Database, program: &str) {
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": program,
+            "owner_pubkey": Pubkey::new_unique().to_string(),
+            "storage_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            "name": "Before",
+            "description": null,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_relay_started_after_write_completes_side_effects_once() {
+    let db = common::test_db().await;
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program).await;
+    db.collection::<Document>("domains")
+        .insert_one(doc! {
+            "_id": "outboxed.shadow",
+            "owner_pubkey": Pubkey::new_unique().to_string(),
+            "program_address": &program,
+            "verified": false,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    // The write lands while no relay is running
+    OlympusCA::new(db.clone()).verify_domain("outboxed.shadow", None).await.unwrap();
+    assert_eq!(count(&db, "outbox", doc! { "status": "pending" }).await, 1);
+    assert_eq!(count(&db, "domain_events", doc! {}).await, 0);
+
+    // A relay started later picks the entry up
+    let relay = OutboxRelay::new(db.clone(), config());
+    let shutdown = CancellationToken::new();
+    assert_eq!(relay.run_once(&shutdown).await.unwrap().processed, 1);
+    assert_eq!(relay.run_once(&shutdown).await.unwrap().processed, 0);
+
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.verified_domain.as_deref(), Some("outboxed.shadow"));
+    let events = OlympusCA::new(db.clone()).list_events("outboxed.shadow").await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, "verified");
+    assert_eq!(count(&db, "outbox", doc! { "status": "done" }).await, 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_entry_held_by_dead_relay_is_finished_without_duplicates() {
+    let db = common::test_db().await;
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program).await;
+    db.collection::<Document>("search_index")
+        .insert_one(doc! { "_id": format!("site.shadow:{}", program), "program_address": &program, "title": "Before" }, None)
+        .await
+        .unwrap();
+
+    let mnemosyne = Mnemosyne::new(db.clone());
+    let primary = PrimaryWrite {
+        collection: "sites".to_string(),
+        filter: doc! { "_id": &program },
+        update: doc! { "$set": { "name": "After" } },
+        upsert: false,
+    };
+    let id = mnemosyne.write(primary, OutboxPayload::SiteUpserted { program_address: program.clone() }).await.unwrap();
+
+    // A relay claimed the entry and died before finishing; its lease has run out
+    db.collection::<Document>("outbox")
+        .update_one(
+            doc! { "_id": &id },
+            doc! { "$set": { "status": "processing", "attempts": 1, "available_at": mongodb::bson::DateTime::from_millis(0) } },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let broker = Arc::new(HermesBroker::new());
+    let mut events = broker.subscribe(format!("program:{}", program)).await;
+    let relay = OutboxRelay::new(db.clone(), config()).with_broker(Arc::clone(&broker));
+    let shutdown = CancellationToken::new();
+    assert_eq!(relay.run_once(&shutdown).await.unwrap().processed, 1);
+    assert_eq!(relay.run_once(&shutdown).await.unwrap().processed, 0);
+
+    let index = db.collection::<Document>("search_index")
+        .find_one(doc! { "program_address": &program }, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(index.get_str("title").unwrap(), "After");
+
+    let published: serde_json::Value = serde_json::from_str(&events.try_recv().unwrap()).unwrap();
+    assert_eq!(published["type"], "site_updated");
+    assert_eq!(published["event_id"], id.as_str());
+    assert!(events.try_recv().is_err(), "published exactly once");
+
+    let entry = mnemosyne.get_entry(&id).await.unwrap().unwrap();
+    assert_eq!(entry.status, OutboxStatus::Done);
+    assert_eq!(entry.attempts, 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_admin_can_inspect_and_retry_failed_entries() {
+    let db = common::test_db().await;
+    let admin = Keypair::new();
+    let mut shadow_config = common::test_config();
+    shadow_config.auth.admin_wallets = vec![admin.pubkey().to_string()];
+
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program).await;
+    let mnemosyne = Mnemosyne::new(db.clone());
+    let primary = PrimaryWrite {
+        collection: "sites".to_string(),
+        filter: doc! { "_id": &program },
+        update: doc! { "$set": { "name": "Stuck" } },
+        upsert: false,
+    };
+    let id = mnemosyne.write(primary, OutboxPayload::SiteUpserted { program_address: program.clone() }).await.unwrap();
+    db.collection::<Document>("outbox")
+        .update_one(doc! { "_id": &id }, doc! { "$set": { "status": "failed", "attempts": 3, "last_error": "boom" } }, None)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Mnemosyne::new(db.clone())))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(shadow_config))
+            .route("/api/admin/outbox", web::get().to(handlers::list_outbox))
+            .route("/api/admin/outbox/{id}/retry", web::post().to(handlers::retry_outbox_entry)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/admin/outbox?status=failed")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&admin)))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["stats"]["failed"], 1);
+    assert_eq!(body["entries"][0]["last_error"], "boom");
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/admin/outbox/{}/retry", id))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&admin)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let relay = OutboxRelay::new(db.clone(), config());
+    assert_eq!(relay.run_once(&CancellationToken::new()).await.unwrap().processed, 1);
+    assert_eq!(mnemosyne.get_entry(&id).await.unwrap().unwrap().status, OutboxStatus::Done);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+async fn test_outbox_admin_requires_admin_wallet() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Mnemosyne::new(common::offline_db().await)))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(common::test_config()))
+            .route("/api/admin/outbox", web::get().to(handlers::list_outbox)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/admin/outbox")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
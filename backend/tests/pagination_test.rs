@@ -0,0 +1,340 @@
+// Integration tests for cursor pagination on the list and search endpoints
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, DateTime, Document};
+use serde_json::Value;
+use shadow_backend::apollo::ApolloValidator;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::artemis::ArtemisRateLimiter;
+use shadow_backend::athena::{AthenaIndexer, SearchOptions};
+use shadow_backend::chronos::ChronosManager;
+use shadow_backend::clock::TestClock;
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::olympus::OlympusCA;
+use solana_sdk::signature::Keypair;
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+
+/// Follow `next_cursor` from the first page until it runs out
+async fn collect_pages<T, F, Fut>(mut fetch: F) -> Vec<Vec<T>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = (Vec<T>, Option<String>)>,
+{
+    let mut pages = Vec::new();
+    let mut cursor = None;
+    loop {
+        let (items, next) = fetch(cursor).await;
+        pages.push(items);
+        match next {
+            Some(next) => cursor = Some(next),
+            None => return pages,
+        }
+        assert!(pages.len() < 20, "pagination did not terminate");
+    }
+}
+
+/// Pages concatenate to exactly `expected`, with no item repeated or skipped
+fn assert_contiguous(pages: &[Vec<String>], expected: &[&str]) {
+    let flat: Vec<&str> = pages.iter().flatten().map(String::as_str).collect();
+    assert_eq!(flat, expected);
+    assert_eq!(flat.iter().collect::<HashSet<_>>().len(), flat.len(), "pages overlap");
+}
+
+#[actix_web::test]
+async fn test_malformed_cursor_is_rejected() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(common::offline_db().await))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/sites/search", web::get().to(handlers::search_sites)),
+    )
+    .await;
+
+    for cursor in ["not*base64", "bm9jb2xvbg", "MTIzOg"] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/sites/search?q=a&cursor={}", cursor))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400, "cursor {}", cursor);
+    }
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_site_search_pages_are_contiguous() {
+    let db = common::test_db().await;
+    let base = DateTime::now().timestamp_millis();
+    // Two sites share a timestamp, so the id has to break the tie across a page boundary
+    for (id, offset) in [("site-a", 0), ("site-b", 1_000), ("site-c", 1_000), ("site-d", 2_000), ("site-e", 3_000)] {
+        db.collection::<Document>("sites")
+            .insert_one(doc! {
+                "_id": id,
+                "owner_pubkey": "owner",
+                "storage_cid": "ipfs://bafy",
+                "name": format!("Paged {}", id),
+                "description": null,
+                "created_at": DateTime::from_millis(base + offset),
+                "updated_at": DateTime::from_millis(base + offset),
+            }, None)
+            .await
+            .unwrap();
+    }
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/sites/search", web::get().to(handlers::search_sites)),
+    )
+    .await;
+
+    let pages = collect_pages(|cursor| {
+        let uri = match cursor {
+            Some(cursor) => format!("/api/sites/search?q=Paged&limit=2&cursor={}", cursor),
+            None => "/api/sites/search?q=Paged&limit=2".to_string(),
+        };
+        let app = &app;
+        async move {
+            let body: Value = test::call_and_read_body_json(app, test::TestRequest::get().uri(&uri).to_request()).await;
+            assert_eq!(body["total"], 5);
+            let ids = body["items"].as_array().unwrap().iter()
+                .map(|site| site["_id"].as_str().unwrap().to_string())
+                .collect();
+            (ids, body["next_cursor"].as_str().map(str::to_string))
+        }
+    })
+    .await;
+
+    assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2, 1]);
+    assert_contiguous(&pages, &["site-e", "site-d", "site-c", "site-b", "site-a"]);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_bookmark_pages_include_migrated_text_dates() {
+    let db = common::test_db().await;
+    let clock = TestClock::starting_now();
+    let chronos = ChronosManager::new(db.clone()).with_clock(clock.clone());
+
+    // Written before bookmarks stored real dates
+    db.collection::<Document>("bookmarks")
+        .insert_one(doc! {
+            "_id": "wallet:old.shadow",
+            "wallet_pubkey": "wallet",
+            "domain": "old.shadow",
+            "program_address": "program",
+            "title": null,
+            "description": null,
+            "folder": null,
+            "created_at": (chrono::Utc::now() - chrono::Duration::days(30)).to_rfc3339(),
+            "tags": [],
+        }, None)
+        .await
+        .unwrap();
+    assert_eq!(chronos.migrate_bookmark_dates().await.unwrap(), 1);
+    assert_eq!(chronos.migrate_bookmark_dates().await.unwrap(), 0);
+
+    for domain in ["one.shadow", "two.shadow", "three.shadow"] {
+        chronos.add_bookmark("wallet", domain, "program", None, None, None, Vec::new()).await.unwrap();
+        clock.advance(Duration::from_secs(60));
+    }
+
+    let pages = collect_pages(|after| {
+        let chronos = &chronos;
+        async move {
+            let after = after.map(|cursor| shadow_backend::utils::decode_cursor(&cursor).unwrap());
+            let (bookmarks, next) = chronos.get_bookmarks("wallet", None, 2, after).await.unwrap();
+            (bookmarks.into_iter().map(|b| b.domain).collect(), next)
+        }
+    })
+    .await;
+
+    assert_eq!(pages.len(), 2);
+    assert_contiguous(&pages, &["three.shadow", "two.shadow", "one.shadow", "old.shadow"]);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_search_index_pages_break_score_ties_by_id() {
+    let db = common::test_db().await;
+    for (id, score) in [("a", 0.5), ("b", 1.0), ("c", 1.0), ("d", 1.0), ("e", 2.0)] {
+        db.collection::<Document>("search_index")
+            .insert_one(doc! {
+                "_id": format!("{}.shadow", id),
+                "domain": format!("{}.shadow", id),
+                "program_address": "program",
+                "title": "Paged result",
+                "description": null,
+                "keywords": [],
+                "content_hash": "hash",
+                "indexed_at": chrono::Utc::now().to_rfc3339(),
+                "popularity_score": score,
+            }, None)
+            .await
+            .unwrap();
+    }
+    let athena = AthenaIndexer::new(db.clone());
+    athena.ensure_indexes().await.unwrap();
+
+    // Every title matches equally, so popularity ranks them and ties fall back to id order
+    let pages = collect_pages(|after| {
+        let athena = &athena;
+        async move {
+            let after = after.map(|cursor| shadow_backend::utils::decode_score_cursor(&cursor).unwrap());
+            let (results, next) = athena.search("Paged", SearchOptions::default(), 2, after).await.unwrap();
+            (results.into_iter().map(|r| r.id).collect(), next)
+        }
+    })
+    .await;
+
+    assert_contiguous(&pages, &["e.shadow", "d.shadow", "c.shadow", "b.shadow", "a.shadow"]);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_profile_and_domain_search_pages_are_contiguous() {
+    let db = common::test_db().await;
+    let base = DateTime::now().timestamp_millis();
+    for (i, id) in ["paged-a", "paged-b", "paged-c", "paged-d", "paged-e"].into_iter().enumerate() {
+        let at = DateTime::from_millis(base + i as i64 * 1_000);
+        db.collection::<Document>("users")
+            .insert_one(doc! { "_id": id, "profile_cid": null, "is_public": true, "created_at": at, "updated_at": at }, None)
+            .await
+            .unwrap();
+        db.collection::<Document>("domains")
+            .insert_one(doc! {
+                "_id": format!("{}.shadow", id),
+                "owner_pubkey": "owner",
+                "program_address": "program",
+                "verified": true,
+                "created_at": at,
+                "updated_at": at,
+            }, None)
+            .await
+            .unwrap();
+    }
+    // Private profiles and unverified domains are neither listed nor counted
+    let at = DateTime::now();
+    db.collection::<Document>("users")
+        .insert_one(doc! { "_id": "paged-hidden", "profile_cid": null, "is_public": false, "created_at": at, "updated_at": at }, None)
+        .await
+        .unwrap();
+    db.collection::<Document>("domains")
+        .insert_one(doc! {
+            "_id": "paged-pending.shadow",
+            "owner_pubkey": "owner",
+            "program_address": "program",
+            "verified": false,
+            "created_at": at,
+            "updated_at": at,
+        }, None)
+        .await
+        .unwrap();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(OlympusCA::new(db.clone())))
+            .app_data(web::Data::new(ApolloValidator::new()))
+            .app_data(web::Data::new(ArtemisRateLimiter::new(600)))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/profiles/search", web::get().to(handlers::search_profiles))
+            .route("/api/domains/search", web::get().to(handlers::search_domains)),
+    )
+    .await;
+
+    let searches: [(&str, &[&str]); 2] = [
+        ("/api/profiles/search", &["paged-e", "paged-d", "paged-c", "paged-b", "paged-a"]),
+        ("/api/domains/search", &["paged-e.shadow", "paged-d.shadow", "paged-c.shadow", "paged-b.shadow", "paged-a.shadow"]),
+    ];
+    for (path, expected) in searches {
+        // Two at a time, checking `total` on every page
+        let pages = collect_pages(|cursor| {
+            let mut uri = format!("{}?q=paged&limit=2", path);
+            if let Some(cursor) = cursor {
+                uri.push_str(&format!("&cursor={}", cursor));
+            }
+            let app = &app;
+            async move {
+                let body: Value = test::call_and_read_body_json(app, test::TestRequest::get().uri(&uri).to_request()).await;
+                assert_eq!(body["total"], expected.len(), "{}", uri);
+                let ids = body["items"].as_array().unwrap().iter()
+                    .map(|item| item["_id"].as_str().or(item["domain"].as_str()).unwrap().to_string())
+                    .collect();
+                (ids, body["next_cursor"].as_str().map(str::to_string))
+            }
+        })
+        .await;
+
+        assert_eq!(pages.len(), 3, "{}", path);
+        assert_contiguous(&pages, expected);
+    }
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_signed_in_profile_search_ranks_by_followers() {
+    let db = common::test_db().await;
+    let at = DateTime::now();
+    // Newest first would be a, b, c, d; "ranked-a" has never been followed and has no count
+    for (i, (id, followers)) in [("ranked-a", None), ("ranked-b", Some(3_i64)), ("ranked-c", Some(1)), ("ranked-d", Some(3))]
+        .into_iter()
+        .enumerate()
+    {
+        let created_at = DateTime::from_millis(at.timestamp_millis() - i as i64 * 1_000);
+        let mut user = doc! { "_id": id, "profile_cid": null, "is_public": true, "created_at": created_at, "updated_at": at };
+        if let Some(followers) = followers {
+            user.insert("follower_count", followers);
+        }
+        db.collection::<Document>("users").insert_one(user, None).await.unwrap();
+    }
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(ArtemisRateLimiter::new(600)))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/profiles/search", web::get().to(handlers::search_profiles)),
+    )
+    .await;
+    let viewer = Keypair::new();
+    let search = |cursor: Option<String>, signed_in: bool| {
+        let mut uri = "/api/profiles/search?q=ranked&limit=2".to_string();
+        if let Some(cursor) = cursor {
+            uri.push_str(&format!("&cursor={}", cursor));
+        }
+        let mut req = test::TestRequest::get().uri(&uri);
+        if signed_in {
+            req = req.insert_header(("X-Shadow-Auth", common::auth_header(&viewer)));
+        }
+        let app = &app;
+        async move {
+            let body: Value = test::call_and_read_body_json(app, req.to_request()).await;
+            let ids = body["items"].as_array().unwrap().iter()
+                .map(|item| item["_id"].as_str().unwrap().to_string())
+                .collect();
+            (ids, body["next_cursor"].as_str().map(str::to_string))
+        }
+    };
+
+    // Most followed first, ties broken by wallet
+    let pages = collect_pages(|cursor| search(cursor, true)).await;
+    assert_contiguous(&pages, &["ranked-d", "ranked-b", "ranked-c", "ranked-a"]);
+    let pages = collect_pages(|cursor| search(cursor, false)).await;
+    assert_contiguous(&pages, &["ranked-a", "ranked-b", "ranked-c", "ranked-d"]);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
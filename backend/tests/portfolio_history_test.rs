@@ -0,0 +1,168 @@
+// Integration tests for Plutus historical portfolio reconstruction
+mod common;
+
+use actix_web::{test, web, App};
+use chrono::{TimeZone, Utc};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::plutus::PortfolioSnapshot;
+use shadow_backend::wallet_handlers;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use std::collections::BTreeMap;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+fn at(day: u32, hour: u32) -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap()
+}
+
+fn snapshot(wallet: &str, taken_at: chrono::DateTime<Utc>, sol: u64, usdc: u64) -> PortfolioSnapshot {
+    PortfolioSnapshot {
+        id: format!("{}:{}", wallet, taken_at.format("%Y-%m-%d")),
+        wallet: wallet.to_string(),
+        taken_at,
+        sol_balance: sol,
+        tokens: BTreeMap::from([(USDC.to_string(), usdc)]),
+    }
+}
+
+/// The swap on the 29th, as getTransaction returns it: SOL out plus fee, USDC in
+fn parsed_transaction(wallet: &str, other: &str) -> Value {
+    serde_json::json!({
+        "blockTime": at(29, 10).timestamp(),
+        "meta": {
+            "err": null,
+            "fee": 5000,
+            "preBalances": [10_000_000_000_i64, 2_039_280, 1],
+            "postBalances": [8_999_995_000_i64, 2_039_280, 1],
+            "preTokenBalances": [
+                { "accountIndex": 1, "mint": USDC, "owner": wallet, "uiTokenAmount": { "amount": "5000000", "decimals": 6 } },
+                { "accountIndex": 2, "mint": USDC, "owner": other, "uiTokenAmount": { "amount": "9000000", "decimals": 6 } }
+            ],
+            "postTokenBalances": [
+                { "accountIndex": 1, "mint": USDC, "owner": wallet, "uiTokenAmount": { "amount": "7000000", "decimals": 6 } },
+                { "accountIndex": 2, "mint": USDC, "owner": other, "uiTokenAmount": { "amount": "7000000", "decimals": 6 } }
+            ]
+        },
+        "transaction": {
+            "message": {
+                "accountKeys": [
+                    { "pubkey": wallet, "signer": true, "writable": true },
+                    { "pubkey": Pubkey::new_unique().to_string(), "signer": false, "writable": true },
+                    { "pubkey": other, "signer": false, "writable": false }
+                ]
+            },
+            "signatures": ["swap"]
+        }
+    })
+}
+
+macro_rules! portfolio_app {
+    ($db:expr, $rpc:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new($rpc))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/wallet/{pubkey}/portfolio/at", web::get().to(wallet_handlers::get_portfolio_at)),
+        )
+        .await
+    };
+}
+
+fn portfolio_at(wallet: &str, date: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(&format!("/api/wallet/{}/portfolio/at?date={}", wallet, date))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+}
+
+#[actix_web::test]
+async fn test_rejects_bad_and_future_dates() {
+    let app = portfolio_app!(common::offline_db().await, "http://127.0.0.1:1".to_string());
+    let wallet = Pubkey::new_unique().to_string();
+    let tomorrow = (Utc::now() + chrono::Duration::days(1)).format("%Y-%m-%d").to_string();
+
+    for date in ["31-03-2026", "2026-02-30", tomorrow.as_str()] {
+        let resp = test::call_service(&app, portfolio_at(&wallet, date).to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", date);
+    }
+    let resp = test::call_service(&app, portfolio_at("not-a-wallet", "2026-03-30").to_request()).await;
+    assert_eq!(resp.status(), 400);
+}
+
+async fn mount_history(rpc: &MockServer, wallet: &str, other: &str) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getSignaturesForAddress", "params": [wallet] })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": [
+                { "signature": "after-window", "blockTime": at(31, 6).timestamp(), "err": null },
+                { "signature": "gift", "blockTime": at(30, 9).timestamp(), "err": null },
+                { "signature": "swap", "blockTime": at(29, 10).timestamp(), "err": null },
+                { "signature": "before-window", "blockTime": at(27, 8).timestamp(), "err": null }
+            ]
+        })))
+        .mount(rpc)
+        .await;
+
+    let mut gift = parsed_transaction(wallet, other);
+    gift["meta"]["preBalances"][0] = serde_json::json!(8_999_995_000_i64);
+    gift["meta"]["postBalances"][0] = serde_json::json!(9_499_995_000_i64);
+    gift["meta"]["preTokenBalances"] = serde_json::json!([]);
+    gift["meta"]["postTokenBalances"] = serde_json::json!([]);
+    for (signature, tx) in [("swap", parsed_transaction(wallet, other)), ("gift", gift)] {
+        Mock::given(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "method": "getTransaction", "params": [signature] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": tx })))
+            .expect(1)
+            .mount(rpc)
+            .await;
+    }
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_reconstructs_and_caches_past_dates() {
+    let db = common::test_db().await;
+    let rpc = MockServer::start().await;
+    let (wallet, other) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+    mount_history(&rpc, &wallet, &other).await;
+    // Bracketing the swap on the 29th and the incoming transfer on the 30th
+    let before = snapshot(&wallet, at(28, 12), 10_000_000_000, 5_000_000);
+    let after = snapshot(&wallet, at(31, 1), 9_499_995_000, 7_000_000);
+    for snapshot in [before, after] {
+        db.collection::<Document>("portfolio_snapshots")
+            .insert_one(doc! {
+                "_id": &snapshot.id,
+                "wallet": &snapshot.wallet,
+                "taken_at": mongodb::bson::DateTime::from_chrono(snapshot.taken_at),
+                "sol_balance": snapshot.sol_balance as i64,
+                "tokens": { USDC: snapshot.tokens[USDC] as i64 },
+            }, None)
+            .await
+            .unwrap();
+    }
+    let app = portfolio_app!(db, rpc.uri());
+
+    let body: Value = test::call_and_read_body_json(&app, portfolio_at(&wallet, "2026-03-29").to_request()).await;
+    assert_eq!(body["reconstructed"], true);
+    assert_eq!(body["confidence"], "exact");
+    assert_eq!(body["sol_balance"], 8_999_995_000_i64);
+    assert_eq!(body["tokens"][USDC], 7_000_000);
+    assert_eq!(body["transactions_replayed"], 1);
+
+    // Served from the cache: each transaction was only fetched once
+    let cached: Value = test::call_and_read_body_json(&app, portfolio_at(&wallet, "2026-03-29").to_request()).await;
+    assert_eq!(cached, body);
+
+    // Nothing to replay from this far out
+    let resp = test::call_service(&app, portfolio_at(&wallet, "2025-06-01").to_request()).await;
+    assert_eq!(resp.status(), 404);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,115 @@
+// CoinGecko prices for Plutus portfolios
+mod common;
+
+use shadow_backend::plutus::{CoinGecko, PlutusPortfolioManager};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+async fn mount_sol_price(server: &MockServer, price: f64) {
+    Mock::given(method("GET"))
+        .and(path("/simple/price"))
+        .and(query_param("ids", "solana"))
+        .and(query_param("vs_currencies", "usd"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "solana": { "usd": price } })))
+        .mount(server)
+        .await;
+}
+
+async fn mount_token_price(server: &MockServer, mint: &str, price: f64) {
+    Mock::given(method("GET"))
+        .and(path(format!("/coins/solana/contract/{}", mint)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "usd-coin",
+            "symbol": "usdc",
+            "market_data": { "current_price": { "usd": price, "eur": 0.92 } }
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_sol_and_token_prices() {
+    let server = MockServer::start().await;
+    mount_sol_price(&server, 142.37).await;
+    mount_token_price(&server, USDC, 0.9998).await;
+    let prices = CoinGecko::new(None).with_api_url(server.uri()).with_rate_limit(10, Duration::from_secs(60));
+
+    assert_eq!(prices.sol_price().await.unwrap(), 142.37);
+    assert_eq!(prices.token_price(USDC).await.unwrap(), Some(0.9998));
+
+    // Unlisted tokens have no price, which isn't an error
+    let unlisted = solana_sdk::pubkey::Pubkey::new_unique().to_string();
+    assert_eq!(prices.token_price(&unlisted).await.unwrap(), None);
+    assert!(prices.token_price("../simple/price").await.is_err());
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_pro_api_key_is_sent() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/simple/price"))
+        .and(header("x-cg-pro-api-key", "cg-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "solana": { "usd": 150.0 } })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let prices = CoinGecko::new(Some("cg-key".to_string())).with_api_url(server.uri());
+    assert_eq!(prices.sol_price().await.unwrap(), 150.0);
+
+    // Rate limited or down is an error, not a price
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).respond_with(ResponseTemplate::new(429)).mount(&server).await;
+    let err = CoinGecko::new(None).with_api_url(server.uri()).sol_price().await.unwrap_err();
+    assert!(err.contains("429"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_calls_past_the_rate_limit_queue() {
+    let server = MockServer::start().await;
+    mount_sol_price(&server, 142.37).await;
+    let prices = CoinGecko::new(None).with_api_url(server.uri()).with_rate_limit(2, Duration::from_millis(600));
+
+    let started = Instant::now();
+    prices.sol_price().await.unwrap();
+    prices.sol_price().await.unwrap();
+    assert!(started.elapsed() < Duration::from_millis(600));
+
+    // The third waits for the first call's minute to pass
+    prices.sol_price().await.unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(600));
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_prices_are_cached() {
+    let db = common::test_db().await;
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/simple/price"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "solana": { "usd": 142.37 } })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/coins/solana/contract/{}", USDC)))
+        .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({ "error": "coin not found" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let manager = PlutusPortfolioManager::new(Arc::new(db.clone()), "http://127.0.0.1:1".to_string())
+        .with_prices(CoinGecko::new(None).with_api_url(server.uri()));
+    for _ in 0..2 {
+        assert_eq!(manager.get_sol_price().await.unwrap(), 142.37);
+        assert_eq!(manager.get_token_price(USDC).await.unwrap(), None);
+    }
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,162 @@
+// Integration tests for per-wallet history retention and analytics opt-out
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::chronos::ChronosManager;
+use shadow_backend::handlers;
+use shadow_backend::prometheus::PrometheusAnalytics;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+macro_rules! privacy_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(ChronosManager::new($db.clone())))
+                .app_data(web::Data::new(PrometheusAnalytics::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(common::test_config()))
+                .route("/api/history", web::post().to(handlers::record_visit))
+                .route("/api/settings/privacy", web::get().to(handlers::get_privacy_settings))
+                .route("/api/settings/privacy", web::put().to(handlers::update_privacy_settings)),
+        )
+        .await
+    };
+}
+
+async fn insert_history(db: &mongodb::Database, wallet: &str, domain: &str, days_ago: i64) {
+    let last_visit = mongodb::bson::DateTime::from_millis(
+        (chrono::Utc::now() - chrono::Duration::days(days_ago)).timestamp_millis(),
+    );
+    db.collection::<Document>("browser_history")
+        .insert_one(doc! {
+            "_id": format!("{}:{}", wallet, domain),
+            "wallet_pubkey": wallet,
+            "domain": domain,
+            "program_address": Pubkey::new_unique().to_string(),
+            "title": null,
+            "visited_at": last_visit,
+            "visit_count": 1,
+            "last_visit": last_visit,
+            "time_spent_seconds": 10_i64,
+        }, None)
+        .await
+        .unwrap();
+}
+
+async fn history_domains(db: &mongodb::Database, wallet: &str) -> Vec<String> {
+    let mut domains: Vec<String> = db.collection::<Document>("browser_history")
+        .distinct("domain", doc! { "wallet_pubkey": wallet }, None)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|b| b.as_str().map(|s| s.to_string()))
+        .collect();
+    domains.sort();
+    domains
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_pruning_respects_each_wallets_retention() {
+    let db = common::test_db().await;
+    let short = Pubkey::new_unique().to_string();
+    let long = Pubkey::new_unique().to_string();
+    let untouched = Pubkey::new_unique().to_string();
+
+    shadow_backend::db::set_privacy_settings(&db, &short, Some(7), false).await.unwrap();
+    shadow_backend::db::set_privacy_settings(&db, &long, Some(90), false).await.unwrap();
+    insert_history(&db, &short, "old.shadow", 10).await;
+    insert_history(&db, &short, "recent.shadow", 2).await;
+    insert_history(&db, &long, "old.shadow", 30).await;
+    insert_history(&db, &untouched, "ancient.shadow", 400).await;
+
+    let chronos = ChronosManager::new(db.clone());
+    let deleted = chronos.prune_history(&common::test_config().privacy).await.unwrap();
+
+    assert_eq!(deleted, 1);
+    assert_eq!(history_domains(&db, &short).await, vec!["recent.shadow"]);
+    assert_eq!(history_domains(&db, &long).await, vec!["old.shadow"]);
+    // Wallets without a setting keep everything under the default config
+    assert_eq!(history_domains(&db, &untouched).await, vec!["ancient.shadow"]);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_opted_out_visits_keep_history_without_engagement() {
+    let db = common::test_db().await;
+    let app = privacy_app!(db);
+    let wallet = Keypair::new();
+    let pubkey = wallet.pubkey().to_string();
+
+    let req = test::TestRequest::put()
+        .uri("/api/settings/privacy")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&wallet)))
+        .set_json(serde_json::json!({ "history_retention_days": 30, "analytics_opt_out": true }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["analytics_opt_out"], true);
+    assert_eq!(body["effective_retention_days"], 30);
+
+    let req = test::TestRequest::post()
+        .uri("/api/history")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&wallet)))
+        .set_json(serde_json::json!({
+            "domain": "private.shadow",
+            "program_address": Pubkey::new_unique().to_string(),
+            "title": null,
+            "time_spent_seconds": 42,
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let engagement = db.collection::<Document>("user_engagement")
+        .count_documents(doc! { "wallet_pubkey": &pubkey }, None)
+        .await
+        .unwrap();
+    assert_eq!(engagement, 0);
+
+    let req = test::TestRequest::get()
+        .uri("/api/settings/privacy")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&wallet)))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["history_entries"], 1);
+    assert_eq!(body["history_retention_days"], 30);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_anonymize_engagement_detaches_wallet() {
+    let db = common::test_db().await;
+    let prometheus = PrometheusAnalytics::new(db.clone());
+    let wallet = Pubkey::new_unique().to_string();
+    prometheus.record_visit("a.shadow", &Pubkey::new_unique().to_string(), &wallet, 5.0).await.unwrap();
+    prometheus.record_visit("b.shadow", &Pubkey::new_unique().to_string(), &wallet, 5.0).await.unwrap();
+
+    assert_eq!(prometheus.anonymize_engagement(&wallet).await.unwrap(), 2);
+    let engagement = db.collection::<Document>("user_engagement");
+    assert_eq!(engagement.count_documents(doc! { "wallet_pubkey": &wallet }, None).await.unwrap(), 0);
+    assert_eq!(engagement.count_documents(doc! { "domain": "a.shadow" }, None).await.unwrap(), 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_zero_day_retention_is_rejected() {
+    let app = privacy_app!(common::offline_db().await);
+    let req = test::TestRequest::put()
+        .uri("/api/settings/privacy")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .set_json(serde_json::json!({ "history_retention_days": 0 }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
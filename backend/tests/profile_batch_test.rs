@@ -0,0 +1,137 @@
+// Integration tests for the batch profile lookup endpoint
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::doc;
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::artemis::ArtemisRateLimiter;
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+macro_rules! batch_app {
+    ($db:expr, $metrics:expr, $requests_per_minute:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(ArtemisRateLimiter::new($requests_per_minute)))
+                .app_data($metrics.clone())
+                .route("/api/profiles/batch", web::post().to(handlers::batch_profiles)),
+        )
+        .await
+    };
+}
+
+#[actix_web::test]
+async fn test_invalid_wallets_fail_per_item_without_querying() {
+    let metrics = web::Data::new(MetricsCollector::new());
+    let app = batch_app!(common::offline_db().await, metrics, 60);
+
+    let req = test::TestRequest::post()
+        .uri("/api/profiles/batch")
+        .set_json(serde_json::json!({ "wallets": ["not-a-wallet", "also bad"] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: Value = test::read_body_json(resp).await;
+
+    assert!(body["profiles"]["not-a-wallet"]["error"].is_string());
+    assert!(body["profiles"]["also bad"]["error"].is_string());
+    assert_eq!(metrics.get_metrics().database_queries, 0);
+}
+
+#[actix_web::test]
+async fn test_oversized_batch_is_rejected() {
+    let metrics = web::Data::new(MetricsCollector::new());
+    let app = batch_app!(common::offline_db().await, metrics, 60);
+
+    let wallets: Vec<String> = (0..101).map(|_| Pubkey::new_unique().to_string()).collect();
+    let req = test::TestRequest::post()
+        .uri("/api/profiles/batch")
+        .set_json(serde_json::json!({ "wallets": wallets }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
+
+#[actix_web::test]
+async fn test_batch_size_counts_against_rate_limit() {
+    let metrics = web::Data::new(MetricsCollector::new());
+    // 1 + ceil(25 / 10) = 4 units per batch
+    let app = batch_app!(common::offline_db().await, metrics, 5);
+
+    let wallets: Vec<String> = (0..25).map(|i| format!("bad-{}", i)).collect();
+    let batch = serde_json::json!({ "wallets": wallets });
+
+    let req = test::TestRequest::post().uri("/api/profiles/batch").set_json(&batch).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let req = test::TestRequest::post().uri("/api/profiles/batch").set_json(&batch).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_mixed_batch_uses_one_query_and_hides_private_profiles() {
+    let db = common::test_db().await;
+    let now = mongodb::bson::DateTime::now();
+    let public = Pubkey::new_unique().to_string();
+    let owner = Keypair::new();
+    let private = owner.pubkey().to_string();
+    let unknown = Pubkey::new_unique().to_string();
+
+    db.collection("users")
+        .insert_one(doc! {
+            "_id": &public,
+            "profile_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            "is_public": true,
+            "display_name": "Public Person",
+            "avatar": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+    db.collection("users")
+        .insert_one(doc! {
+            "_id": &private,
+            "profile_cid": "ipfs://private",
+            "is_public": false,
+            "display_name": "Secret",
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+
+    let metrics = web::Data::new(MetricsCollector::new());
+    let app = batch_app!(db, metrics, 60);
+    let batch = serde_json::json!({ "wallets": [&public, &private, &unknown, "bogus", &public] });
+
+    let req = test::TestRequest::post().uri("/api/profiles/batch").set_json(&batch).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let profiles = &body["profiles"];
+    assert_eq!(profiles.as_object().unwrap().len(), 4);
+    assert_eq!(profiles[&public]["display_name"], "Public Person");
+    assert!(profiles[&public]["avatar"].is_string());
+    assert_eq!(profiles[&private]["exists"], true);
+    assert_eq!(profiles[&private]["is_public"], false);
+    assert!(profiles[&private]["profile_cid"].is_null());
+    assert!(profiles[&private].get("display_name").is_none());
+    assert_eq!(profiles[&unknown]["exists"], false);
+    assert!(profiles["bogus"]["error"].is_string());
+    assert_eq!(metrics.get_metrics().database_queries, 1);
+
+    // The owner sees their own private profile
+    let req = test::TestRequest::post()
+        .uri("/api/profiles/batch")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(&batch)
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["profiles"][&private]["profile_cid"], "ipfs://private");
+    assert_eq!(body["profiles"][&private]["display_name"], "Secret");
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
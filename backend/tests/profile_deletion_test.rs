@@ -0,0 +1,198 @@
+// Integration tests for profiles deleted on-chain disappearing from the backend
+mod common;
+
+use actix_web::{test, web, App};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::apollo::ApolloValidator;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::db;
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn profile_account(wallet: &Pubkey) -> Vec<u8> {
+    let mut data = Sha256::digest(b"account:Profile")[..8].to_vec();
+    data.extend_from_slice(wallet.as_ref());
+    data.extend_from_slice(&14u32.to_le_bytes());
+    data.extend_from_slice(b"ipfs://bafyone");
+    data.push(1);
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data.extend_from_slice(&[0; 8 + 8 + 1]);
+    data
+}
+
+fn account_info(data: Option<Vec<u8>>, owner: &Pubkey) -> ResponseTemplate {
+    let value = data.map(|data| serde_json::json!({
+        "data": [general_purpose::STANDARD.encode(&data), "base64"],
+        "executable": false,
+        "lamports": 2_000_000,
+        "owner": owner.to_string(),
+        "rentEpoch": 0,
+        "space": data.len(),
+    }));
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "result": { "context": { "slot": 1 }, "value": value },
+        "id": 1
+    }))
+}
+
+/// Mock RPC answering the version check the client makes before its first query
+async fn rpc_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "solana-core": "1.18.26", "feature-set": 0 },
+            "id": 1
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_update_after_on_chain_deletion_reports_missing_profile() {
+    let db = common::test_db().await;
+    let wallet = Keypair::new();
+    let address = wallet.pubkey().to_string();
+    db::create_or_update_user(&db, &address, Some("ipfs://bafyone"), true).await.unwrap();
+
+    let profiles = Pubkey::new_unique();
+    let rpc = rpc_server().await;
+    let anchor = AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), profiles);
+    let profile_info = || {
+        Mock::given(method("POST")).and(body_partial_json(serde_json::json!({
+            "method": "getAccountInfo",
+            "params": [anchor.profile_pda(&wallet.pubkey()).to_string()]
+        })))
+    };
+    // The profile is there for the first update, then deleted
+    profile_info()
+        .respond_with(account_info(Some(profile_account(&wallet.pubkey())), &profiles))
+        .up_to_n_times(1)
+        .mount(&rpc)
+        .await;
+    profile_info().respond_with(account_info(None, &profiles)).mount(&rpc).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(ApolloValidator::new()))
+            .app_data(web::Data::new(AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), profiles)))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/profiles/{wallet}", web::get().to(handlers::get_profile))
+            .route("/api/profiles/{wallet}", web::put().to(handlers::update_profile)),
+    )
+    .await;
+
+    let update = || {
+        test::TestRequest::put()
+            .uri(&format!("/api/profiles/{}", address))
+            .insert_header(("X-Shadow-Auth", common::auth_header(&wallet)))
+            .set_json(serde_json::json!({ "is_public": false }))
+            .to_request()
+    };
+    let body: Value = test::call_and_read_body_json(&app, update()).await;
+    assert_eq!(body["success"], true);
+    assert!(!db::get_user(&db, &address).await.unwrap().unwrap().is_public);
+
+    let body: Value = test::call_and_read_body_json(&app, update()).await;
+    assert_eq!(body["exists"], false);
+    assert!(db::get_user(&db, &address).await.unwrap().is_none());
+
+    let req = test::TestRequest::get().uri(&format!("/api/profiles/{}", address)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["exists"], false);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+macro_rules! delete_app {
+    ($db:expr, $rpc:expr, $profiles:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(AnchorClient::with_programs($rpc.uri(), Pubkey::new_unique(), $profiles)))
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .route("/api/profiles/{wallet}", web::delete().to(handlers::delete_profile)),
+        )
+        .await
+    };
+}
+
+fn delete(wallet: &Keypair) -> test::TestRequest {
+    test::TestRequest::delete()
+        .uri(&format!("/api/profiles/{}", wallet.pubkey()))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+}
+
+#[actix_web::test]
+async fn test_delete_waits_for_the_wallet_to_close_its_profile() {
+    // Refused before the database is touched
+    let db = common::offline_db().await;
+    let wallet = Keypair::new();
+    let profiles = Pubkey::new_unique();
+    let rpc = rpc_server().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(account_info(Some(profile_account(&wallet.pubkey())), &profiles))
+        .mount(&rpc)
+        .await;
+    let app = delete_app!(db, rpc, profiles);
+
+    let resp = test::call_service(&app, delete(&wallet).to_request()).await;
+    assert_eq!(resp.status(), 409);
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body["error"].as_str().unwrap().contains("delete_profile"), "{}", body);
+
+    // Only the wallet itself may ask
+    let req = delete(&Keypair::new()).uri(&format!("/api/profiles/{}", wallet.pubkey())).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+#[actix_web::test]
+async fn test_delete_tells_a_failed_lookup_from_an_open_profile() {
+    let db = common::offline_db().await;
+    let wallet = Keypair::new();
+    // Nothing mounted, so every RPC call fails
+    let rpc = MockServer::start().await;
+    let app = delete_app!(db, rpc, Pubkey::new_unique());
+
+    assert_eq!(test::call_service(&app, delete(&wallet).to_request()).await.status(), 502);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_delete_after_the_profile_is_closed_drops_the_record() {
+    let db = common::test_db().await;
+    let wallet = Keypair::new();
+    let address = wallet.pubkey().to_string();
+    db::create_or_update_user(&db, &address, Some("ipfs://bafyone"), true).await.unwrap();
+
+    let profiles = Pubkey::new_unique();
+    let rpc = rpc_server().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(account_info(None, &profiles))
+        .mount(&rpc)
+        .await;
+    let app = delete_app!(db, rpc, profiles);
+
+    let body: Value = test::call_and_read_body_json(&app, delete(&wallet).to_request()).await;
+    assert_eq!(body["exists"], false);
+    assert!(db::get_user(&db, &address).await.unwrap().is_none());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
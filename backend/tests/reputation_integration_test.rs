@@ -0,0 +1,130 @@
+// Integration tests for Argus origin reputation in the wallet dApp endpoints
+mod common;
+
+use actix_web::{test, web, App};
+use shadow_backend::ares::AresAuth;
+use shadow_backend::argus::{self, ArgusReputation, KnownDAppSeed};
+use shadow_backend::wallet_handlers;
+use solana_sdk::signature::Keypair;
+use std::sync::Arc;
+
+const BLOCKED_ORIGIN: &str = "https://drainer.example";
+
+macro_rules! wallet_app {
+    ($db:expr) => {{
+        let mut seeds = argus::default_seeds();
+        seeds.push(KnownDAppSeed {
+            origin: BLOCKED_ORIGIN.to_string(),
+            name: "Free Airdrop".to_string(),
+            blocked: true,
+        });
+        let config = common::test_config();
+
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(ArgusReputation::new(Arc::new($db.clone()), &seeds)))
+                .app_data(web::Data::new(config))
+                .route("/api/wallet/transaction", web::post().to(wallet_handlers::create_transaction))
+                .route("/api/wallet/dapp/connect", web::post().to(wallet_handlers::connect_dapp))
+                .route("/api/wallet/dapp/reputation", web::get().to(wallet_handlers::assess_origin))
+                .route("/api/admin/dapps/block", web::post().to(wallet_handlers::block_origin)),
+        )
+        .await
+    }};
+}
+
+#[tokio::test]
+async fn test_blocked_origin_cannot_connect_or_transact() {
+    let db = common::offline_db().await;
+    let app = wallet_app!(db);
+    let user = Keypair::new();
+
+    let req = test::TestRequest::post()
+        .uri("/api/wallet/dapp/connect")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&user)))
+        .set_json(serde_json::json!({
+            "wallet_id": "wallet-1",
+            "dapp_origin": "https://drainer.example/claim",
+            "dapp_name": "Free Airdrop",
+            "dapp_icon": null,
+            "requested_permissions": ["viewbalance"]
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    let req = test::TestRequest::post()
+        .uri("/api/wallet/transaction")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&user)))
+        .set_json(serde_json::json!({
+            "wallet_id": "wallet-1",
+            "dapp_origin": BLOCKED_ORIGIN,
+            "transaction_data": "",
+            "message": null
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+#[tokio::test]
+async fn test_reputation_lookup_flags_lookalikes() {
+    let db = common::offline_db().await;
+    let app = wallet_app!(db);
+
+    let req = test::TestRequest::get()
+        .uri("/api/wallet/dapp/reputation?origin=https://jupiter-rewards.xyz&name=Jupiter")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["risk"], "suspicious");
+
+    let req = test::TestRequest::get()
+        .uri("/api/wallet/dapp/reputation?origin=https://xn--jupitr-8va.ag&name=Swap")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["risk"], "suspicious");
+
+    let req = test::TestRequest::get()
+        .uri("/api/wallet/dapp/reputation?origin=https://jup.ag&name=Jupiter")
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["risk"], "verified");
+}
+
+#[tokio::test]
+async fn test_only_admins_can_block_origins() {
+    let db = common::offline_db().await;
+    let app = wallet_app!(db);
+
+    let req = test::TestRequest::post()
+        .uri("/api/admin/dapps/block")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .set_json(serde_json::json!({ "origin": "https://phish.example" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_blocks_reach_other_instances_on_refresh() {
+    let db = common::test_db().await;
+    let here = Arc::new(ArgusReputation::new(Arc::new(db.clone()), &argus::default_seeds()));
+    let there = Arc::new(ArgusReputation::new(Arc::new(db.clone()), &argus::default_seeds()));
+
+    here.set_blocked("https://phish.example", true).await.unwrap();
+    assert!(here.assess("https://phish.example", "Swap").blocked);
+    assert!(!there.assess("https://phish.example", "Swap").blocked);
+
+    // The other instance picks the block up on its next refresh
+    let shutdown = tokio_util::sync::CancellationToken::new();
+    let handle = Arc::clone(&there).spawn_refresh(std::time::Duration::from_millis(50), shutdown.clone());
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    while !there.assess("http://login.phish.example", "Swap").blocked {
+        assert!(tokio::time::Instant::now() < deadline, "block never arrived");
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    shutdown.cancel();
+    handle.await.unwrap();
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
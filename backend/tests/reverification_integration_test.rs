@@ -0,0 +1,194 @@
+// Integration tests for Themis background re-verification of domains
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::config::VerificationConfig;
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::themis::ThemisVerifier;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use tokio_util::sync::CancellationToken;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const DOMAIN: &str = "watched.shadow";
+
+fn config(interstitial_after_failures: u32) -> VerificationConfig {
+    VerificationConfig {
+        interval_seconds: 3600,
+        batch_size: 10,
+        rpc_budget: 100,
+        interstitial_after_failures,
+    }
+}
+
+/// Make every getAccountInfo answer with a deployed program, or with nothing at all
+async fn mock_program(server: &MockServer, deployed: bool) {
+    server.reset().await;
+    let value = if deployed {
+        serde_json::json!({
+            "data": ["AQID", "base64"],
+            "executable": true,
+            "lamports": 1_141_440,
+            "owner": "BPFLoader2111111111111111111111111111111111",
+            "rentEpoch": 0,
+            "space": 3
+        })
+    } else {
+        serde_json::Value::Null
+    };
+    // The RPC client asks for the node version before picking an account encoding
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "solana-core": "1.18.26", "feature-set": 0 },
+            "id": 1
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "context": { "slot": 1 }, "value": value },
+            "id": 1
+        })))
+        .mount(server)
+        .await;
+}
+
+async fn insert_verified_domain(db: &Database, owner: &Keypair) {
+    db.collection::<Document>("domains")
+        .insert_one(doc! {
+            "_id": DOMAIN,
+            "owner_pubkey": owner.pubkey().to_string(),
+            "program_address": Pubkey::new_unique().to_string(),
+            "verified": true,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .expect("Failed to insert domain");
+}
+
+async fn domain_state(db: &Database) -> Document {
+    db.collection::<Document>("domains")
+        .find_one(doc! { "_id": DOMAIN }, None)
+        .await
+        .unwrap()
+        .expect("domain exists")
+}
+
+async fn count(db: &Database, collection: &str) -> u64 {
+    db.collection::<Document>(collection)
+        .count_documents(doc! {}, None)
+        .await
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_disappearing_program_is_downgraded_once() {
+    let db = common::test_db().await;
+    let rpc = MockServer::start().await;
+    let owner = Keypair::new();
+    insert_verified_domain(&db, &owner).await;
+
+    let themis = ThemisVerifier::new(db.clone(), rpc.uri(), config(5));
+    let shutdown = CancellationToken::new();
+
+    mock_program(&rpc, true).await;
+    let report = themis.run_once(&shutdown).await.unwrap();
+    assert_eq!(report.checked, 1);
+    assert_eq!(report.downgraded, 0);
+    assert!(domain_state(&db).await.get_bool("verified").unwrap());
+
+    // The program is closed between runs
+    mock_program(&rpc, false).await;
+    let report = themis.run_once(&shutdown).await.unwrap();
+    assert_eq!(report.downgraded, 1);
+
+    let report = themis.run_once(&shutdown).await.unwrap();
+    assert_eq!(report.checked, 1);
+    assert_eq!(report.downgraded, 0);
+
+    let state = domain_state(&db).await;
+    assert!(!state.get_bool("verified").unwrap());
+    assert_eq!(state.get_i32("verification_failures").unwrap(), 2);
+    assert_eq!(state.get_str("verification_error").unwrap(), "Program account no longer exists");
+
+    let events = OlympusCA::new(db.clone()).list_events(DOMAIN).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, "verification_revoked");
+    assert_eq!(count(&db, "domain_events").await, 1);
+
+    let notifications = themis.list_notifications(&owner.pubkey().to_string()).await.unwrap();
+    assert_eq!(notifications.len(), 1);
+    assert!(notifications[0].message.contains("/verify"));
+    assert_eq!(count(&db, "notifications").await, 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_interstitial_after_repeated_failures_and_verify_clears_it() {
+    let db = common::test_db().await;
+    let rpc = MockServer::start().await;
+    let owner = Keypair::new();
+    insert_verified_domain(&db, &owner).await;
+
+    let themis = ThemisVerifier::new(db.clone(), rpc.uri(), config(2));
+    let shutdown = CancellationToken::new();
+
+    mock_program(&rpc, false).await;
+    themis.run_once(&shutdown).await.unwrap();
+    let report = themis.run_once(&shutdown).await.unwrap();
+    assert_eq!(report.interstitials, 1);
+
+    assert!(domain_state(&db).await.get_bool("warning_interstitial").unwrap());
+    let events = OlympusCA::new(db.clone()).list_events(DOMAIN).await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].kind, "warning_interstitial");
+
+    // The owner redeploys and re-verifies
+    mock_program(&rpc, true).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(OlympusCA::new(db.clone())))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .app_data(web::Data::new(rpc.uri()))
+            .route("/api/domains/{domain}/verify", web::post().to(handlers::verify_domain)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/domains/{}/verify", DOMAIN))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let state = domain_state(&db).await;
+    assert!(state.get_bool("verified").unwrap());
+    assert_eq!(state.get_i32("verification_failures").unwrap(), 0);
+    assert!(!state.get_bool("warning_interstitial").unwrap());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+async fn test_cancelled_run_does_no_work() {
+    let db = common::offline_db().await;
+    let themis = ThemisVerifier::new(db, "http://127.0.0.1:1".to_string(), config(3));
+    let shutdown = CancellationToken::new();
+    shutdown.cancel();
+
+    let report = themis.run_once(&shutdown).await.unwrap();
+    assert_eq!(report.checked, 0);
+}
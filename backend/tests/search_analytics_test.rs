@@ -0,0 +1,230 @@
+// Integration tests for Clio search query analytics
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::apollo::ApolloValidator;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::athena::AthenaIndexer;
+use shadow_backend::clio::{ClioRecorder, SearchQueryRecord};
+use shadow_backend::config::{SearchAnalyticsConfig, ShadowConfig};
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn analytics_config(buffer_capacity: usize) -> SearchAnalyticsConfig {
+    SearchAnalyticsConfig {
+        buffer_capacity,
+        flush_interval_ms: 50,
+        flush_batch_size: 3,
+        retention_days: 30,
+        hash_key: Some("test-key".to_string()),
+    }
+}
+
+macro_rules! search_app {
+    ($db:expr, $clio:expr, $config:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(AthenaIndexer::new($db.clone())))
+                .app_data(web::Data::from(Arc::clone(&$clio)))
+                .app_data(web::Data::new(ApolloValidator::new()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new($config))
+                .route("/api/search", web::get().to(handlers::search_content))
+                .route("/api/search/click", web::post().to(handlers::record_search_click))
+                .route("/api/admin/search/top-queries", web::get().to(handlers::get_top_search_queries))
+                .route("/api/admin/search/zero-result-queries", web::get().to(handlers::get_zero_result_search_queries)),
+        )
+        .await
+    };
+}
+
+fn admin_config(admin: &Keypair) -> ShadowConfig {
+    let mut config = common::test_config();
+    config.auth.admin_wallets = vec![admin.pubkey().to_string()];
+    config
+}
+
+fn click(query_id: &str, domain: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/search/click")
+        .set_json(serde_json::json!({ "query_id": query_id, "domain": domain }))
+}
+
+#[actix_web::test]
+async fn test_full_buffer_drops_records_without_blocking() {
+    let metrics = Arc::new(MetricsCollector::new());
+    let clio = ClioRecorder::new(common::offline_db().await, analytics_config(1))
+        .with_metrics(Arc::clone(&metrics));
+
+    // Nothing drains the buffer, so everything after the first record is dropped
+    let started = Instant::now();
+    for i in 0..10_000 {
+        clio.record(&format!("query {}", i), 0, Duration::from_millis(3), None);
+    }
+    assert!(started.elapsed() < Duration::from_secs(1), "recording blocked: {:?}", started.elapsed());
+
+    let snapshot = metrics.get_metrics();
+    assert_eq!(snapshot.search_queries, 10_000);
+    assert_eq!(snapshot.search_analytics_dropped, 9_999);
+    assert_eq!(snapshot.search_zero_result_rate, 1.0);
+}
+
+#[actix_web::test]
+async fn test_click_beacon_rejects_unsigned_query_ids() {
+    let db = common::offline_db().await;
+    let clio = Arc::new(ClioRecorder::new(db.clone(), analytics_config(16)));
+    let app = search_app!(db, clio, common::test_config());
+
+    let issued = clio.record("solana nft", 2, Duration::from_millis(5), None);
+    let other_server = ClioRecorder::new(db.clone(), SearchAnalyticsConfig {
+        hash_key: Some("other-key".to_string()),
+        ..analytics_config(16)
+    });
+    let foreign = other_server.record("solana nft", 2, Duration::from_millis(5), None);
+    let (id, _) = issued.split_once('.').unwrap();
+
+    for query_id in [id.to_string(), format!("{}.deadbeef", id), foreign, "junk".to_string()] {
+        let resp = test::call_service(&app, click(&query_id, "docs.shadow").to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", query_id);
+    }
+    let resp = test::call_service(&app, click(&issued, "docs.shadow").to_request()).await;
+    assert_eq!(resp.status(), 202);
+    let resp = test::call_service(&app, click(&issued, "not a domain").to_request()).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_searches_and_clicks_are_recorded() {
+    let db = common::test_db().await;
+    db.collection::<Document>("search_index")
+        .insert_one(doc! {
+            "_id": "docs.shadow:program",
+            "domain": "docs.shadow",
+            "program_address": "program",
+            "title": "Shadow docs",
+            "description": "How to deploy",
+            "keywords": ["docs"],
+            "content_hash": "hash",
+            "popularity_score": 1.0,
+            "indexed_at": chrono::Utc::now().to_rfc3339(),
+        }, None)
+        .await
+        .unwrap();
+    AthenaIndexer::new(db.clone()).ensure_indexes().await.unwrap();
+    let clio = Arc::new(ClioRecorder::new(db.clone(), analytics_config(64)));
+    let app = search_app!(db, clio, common::test_config());
+
+    let req = test::TestRequest::get().uri("/api/search?q=Shadow%20%20Docs").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let query_id = resp.headers().get(handlers::SEARCH_QUERY_ID_HEADER).unwrap().to_str().unwrap().to_string();
+    let req = test::TestRequest::get().uri("/api/search?q=nothing-here").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // Clicks queue behind their search and only the first one counts
+    for domain in ["docs.shadow", "other.shadow"] {
+        assert_eq!(test::call_service(&app, click(&query_id, domain).to_request()).await.status(), 202);
+    }
+    assert_eq!(clio.flush().await.unwrap(), 4);
+
+    let (id, _) = query_id.split_once('.').unwrap();
+    let record: SearchQueryRecord = clio.get_collection().find_one(doc! { "_id": id }, None).await.unwrap().unwrap();
+    assert_eq!(record.query, "shadow docs");
+    assert_eq!(record.result_count, 1);
+    assert!(record.clicked);
+    assert_eq!(record.clicked_domain.as_deref(), Some("docs.shadow"));
+    let zero = clio.get_collection().find_one(doc! { "query": "nothing-here" }, None).await.unwrap().unwrap();
+    assert_eq!(zero.result_count, 0);
+    assert!(!zero.clicked);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_admin_aggregations_respect_window() {
+    let db = common::test_db().await;
+    let admin = Keypair::new();
+    let clio = Arc::new(ClioRecorder::new(db.clone(), analytics_config(64)));
+    let app = search_app!(db, clio, admin_config(&admin));
+
+    for (query, results) in [("nft", 4), ("nft", 0), ("NFT", 2), ("wiki", 0), ("wiki", 0), ("dex", 1)] {
+        clio.record(query, results, Duration::from_millis(10), Some("10.0.0.1"));
+    }
+    clio.flush().await.unwrap();
+    // Outside the 24h window but inside 7d
+    let old = mongodb::bson::DateTime::from_chrono(chrono::Utc::now() - chrono::Duration::days(3));
+    db.collection::<Document>("search_queries")
+        .insert_one(doc! {
+            "_id": "old-search",
+            "query": "dex",
+            "result_count": 0,
+            "latency_ms": 8_i64,
+            "clicked": false,
+            "created_at": old,
+        }, None)
+        .await
+        .unwrap();
+
+    let get = |uri: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("X-Shadow-Auth", common::auth_header(&admin)))
+            .to_request()
+    };
+    let body: Value = test::call_and_read_body_json(&app, get("/api/admin/search/top-queries")).await;
+    assert_eq!(body["window"], "24h");
+    let queries = body["queries"].as_array().unwrap();
+    assert_eq!(queries.len(), 3);
+    assert_eq!(queries[0]["query"], "nft");
+    assert_eq!(queries[0]["searches"], 3);
+    assert_eq!(queries[0]["zero_results"], 1);
+    assert_eq!(queries[1]["query"], "wiki");
+    assert_eq!(queries[2]["searches"], 1);
+
+    let body: Value = test::call_and_read_body_json(&app, get("/api/admin/search/zero-result-queries?window=7d")).await;
+    let queries: Vec<(&str, i64)> = body["queries"].as_array().unwrap().iter()
+        .map(|q| (q["query"].as_str().unwrap(), q["searches"].as_i64().unwrap()))
+        .collect();
+    assert_eq!(queries, vec![("wiki", 2), ("dex", 1), ("nft", 1)]);
+
+    // Client addresses are only stored hashed
+    let stored = db.collection::<Document>("search_queries")
+        .count_documents(doc! { "client_hash": "10.0.0.1" }, None).await.unwrap();
+    assert_eq!(stored, 0);
+
+    let req = test::TestRequest::get()
+        .uri("/api/admin/search/top-queries")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_search_latency_unaffected_by_full_buffer() {
+    let db = common::test_db().await;
+    let clio = Arc::new(ClioRecorder::new(db.clone(), analytics_config(1)));
+    clio.record("fills the buffer", 0, Duration::ZERO, None);
+    let app = search_app!(db, clio, common::test_config());
+
+    let started = Instant::now();
+    for _ in 0..20 {
+        let req = test::TestRequest::get().uri("/api/search?q=anything").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert!(resp.headers().contains_key(handlers::SEARCH_QUERY_ID_HEADER));
+    }
+    assert!(started.elapsed() < Duration::from_secs(5), "searches slowed down: {:?}", started.elapsed());
+    assert_eq!(clio.flush().await.unwrap(), 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,186 @@
+// Integration tests for Athena's text search ranking and filters
+mod common;
+
+use shadow_backend::athena::{AthenaIndexer, SearchIndex, SearchOptions, SearchSort};
+use shadow_backend::utils::decode_score_cursor;
+
+fn domains(results: &[SearchIndex]) -> Vec<&str> {
+    results.iter().map(|r| r.domain.as_str()).collect()
+}
+
+/// Three sites mentioning "lantern" in their title, description and body respectively
+async fn seeded_athena() -> (mongodb::Database, AthenaIndexer) {
+    let db = common::test_db().await;
+    let athena = AthenaIndexer::new(db.clone());
+    athena.ensure_indexes().await.unwrap();
+
+    for (domain, title, description, content, language) in [
+        ("swap.shadow", "Lantern Swap", "Trade tokens", "swap tokens on defi rails", "en"),
+        ("gallery.shadow", "Pixel Gallery", "A lantern lit gallery", "art and music from the night", "en"),
+        ("notas.shadow", "Notas", "Cuaderno de campo", "notas sobre el festival lantern", "es"),
+    ] {
+        athena.index_site(domain, "program", None, Some(title), Some(description), content).await.unwrap();
+        athena.analyze_content(domain, content, Some(language)).await.unwrap();
+    }
+    // A Spanish variant of the swap site
+    athena
+        .index_site("swap.shadow", "program", Some("es"), Some("Lantern Intercambio"), None, "intercambia tokens")
+        .await
+        .unwrap();
+    (db, athena)
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_results_are_ranked_by_relevance() {
+    let (db, athena) = seeded_athena().await;
+
+    let (results, next) = athena.search("lantern", SearchOptions::default(), 10, None).await.unwrap();
+    assert_eq!(next, None);
+    // Title matches outrank description matches, which outrank body-only keywords
+    assert_eq!(&domains(&results)[2..], ["gallery.shadow", "notas.shadow"]);
+    assert_eq!(&domains(&results)[..2], ["swap.shadow", "swap.shadow"]);
+    let scores: Vec<f64> = results.iter().map(|r| r.score.unwrap()).collect();
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]), "{:?}", scores);
+
+    // Paging by score returns the same order
+    let mut paged = Vec::new();
+    let mut after = None;
+    loop {
+        let (page, next) = athena.search("lantern", SearchOptions::default(), 1, after).await.unwrap();
+        paged.extend(page.into_iter().map(|r| r.id));
+        match next {
+            Some(cursor) => after = Some(decode_score_cursor(&cursor).unwrap()),
+            None => break,
+        }
+    }
+    assert_eq!(paged, results.iter().map(|r| r.id.clone()).collect::<Vec<_>>());
+
+    // Scores are computed per query, never stored
+    let stored = athena.get_index_collection().find_one(None, None).await.unwrap().unwrap();
+    assert!(stored.score.is_none());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_search_filters_by_category_and_language() {
+    let (db, athena) = seeded_athena().await;
+
+    let (results, _) = athena
+        .search_with_filters("lantern", SearchOptions::default(), vec!["creative".to_string()], None, 10, None)
+        .await
+        .unwrap();
+    assert_eq!(domains(&results), ["gallery.shadow"]);
+
+    // Language variants match on their own language, single-language sites on the detected one
+    let (results, _) = athena
+        .search_with_filters("lantern", SearchOptions::default(), Vec::new(), Some("es".to_string()), 10, None)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, ["swap.shadow:program:es", "notas.shadow:program"]);
+
+    let (results, _) = athena
+        .search_with_filters("lantern", SearchOptions::default(), vec!["defi".to_string()], Some("es".to_string()), 10, None)
+        .await
+        .unwrap();
+    assert_eq!(domains(&results), ["swap.shadow"]);
+    assert_eq!(results[0].language.as_deref(), Some("es"));
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+/// Sites that each win under a different sort: the best text match, the most popular and
+/// the most recently indexed. A fourth site matches none of the queries.
+async fn seeded_corpus() -> (mongodb::Database, AthenaIndexer) {
+    let db = common::test_db().await;
+    let now = chrono::Utc::now();
+    for (domain, title, description, keywords, popularity, age_days) in [
+        ("alpha.shadow", "Lantern", "Paper crafts", vec![], 1.0, 3),
+        ("beta.shadow", "Night market", "A lantern stall", vec![], 2.0, 2),
+        ("gamma.shadow", "Festival guide", "Schedules", vec!["lantern"], 1.5, 0),
+        ("other.shadow", "Nothing here", "Unrelated", vec![], 3.0, 1),
+    ] {
+        db.collection::<mongodb::bson::Document>("search_index")
+            .insert_one(mongodb::bson::doc! {
+                "_id": domain,
+                "domain": domain,
+                "program_address": "program",
+                "title": title,
+                "description": description,
+                "keywords": keywords,
+                "content_hash": "hash",
+                "indexed_at": (now - chrono::Duration::days(age_days)).to_rfc3339(),
+                "popularity_score": popularity,
+            }, None)
+            .await
+            .unwrap();
+    }
+    let athena = AthenaIndexer::new(db.clone());
+    athena.ensure_indexes().await.unwrap();
+    (db, athena)
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_sort_options_order_a_seeded_corpus() {
+    let (db, athena) = seeded_corpus().await;
+
+    for (sort, expected) in [
+        (SearchSort::Relevance, ["alpha.shadow", "gamma.shadow", "beta.shadow"]),
+        (SearchSort::Popularity, ["beta.shadow", "gamma.shadow", "alpha.shadow"]),
+        (SearchSort::Recency, ["gamma.shadow", "beta.shadow", "alpha.shadow"]),
+    ] {
+        let options = SearchOptions { sort };
+        let (results, _) = athena.search("lantern", options, 10, None).await.unwrap();
+        assert_eq!(domains(&results), expected, "{:?}", sort);
+
+        // Paging one result at a time keeps the order
+        let mut paged = Vec::new();
+        let mut after = None;
+        loop {
+            let (page, next) = athena.search("lantern", options, 1, after).await.unwrap();
+            paged.extend(page.into_iter().map(|r| r.domain));
+            match next {
+                Some(cursor) => after = Some(decode_score_cursor(&cursor).unwrap()),
+                None => break,
+            }
+        }
+        assert_eq!(paged, expected, "{:?}", sort);
+    }
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_short_queries_fall_back_to_substring_matching() {
+    let (db, athena) = seeded_corpus().await;
+
+    // Too short for the text index; substrings of titles, descriptions and keywords match
+    let (results, _) = athena.search("an", SearchOptions::default(), 10, None).await.unwrap();
+    // Without a text score, relevance ranks by popularity alone
+    assert_eq!(domains(&results), ["beta.shadow", "gamma.shadow", "alpha.shadow"]);
+    assert!(results.iter().all(|r| r.score.is_none()));
+    assert_eq!(athena.count("an").await.unwrap(), 3);
+
+    // Regex syntax in the query is matched literally
+    let (results, _) = athena.search(".*", SearchOptions::default(), 10, None).await.unwrap();
+    assert!(results.is_empty());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[test]
+fn test_sort_query_param() {
+    use actix_web::web::Query;
+    use shadow_backend::handlers::SearchQuery;
+
+    let query = Query::<SearchQuery>::from_query("q=lantern&sort=recency").unwrap();
+    assert_eq!(query.sort, SearchSort::Recency);
+    let query = Query::<SearchQuery>::from_query("q=lantern").unwrap();
+    assert_eq!(query.sort, SearchSort::Relevance);
+    assert!(Query::<SearchQuery>::from_query("q=lantern&sort=random").is_err());
+}
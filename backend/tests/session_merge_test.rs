@@ -0,0 +1,114 @@
+// Integration tests for merging Chronos browser sessions across reconnects
+mod common;
+
+use actix_web::{test, web, App};
+use futures_util::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use shadow_backend::ares::AresAuth;
+use shadow_backend::chronos::ChronosManager;
+use shadow_backend::handlers;
+use solana_sdk::signature::{Keypair, Signer};
+
+macro_rules! session_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(ChronosManager::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/sessions/merge", web::post().to(handlers::merge_sessions)),
+        )
+        .await
+    };
+}
+
+async fn insert_session(db: &mongodb::Database, id: &str, wallet: &str, minutes_ago: i64, tabs: &[&str], visits: i32) {
+    let at = mongodb::bson::DateTime::from_millis(
+        (chrono::Utc::now() - chrono::Duration::minutes(minutes_ago)).timestamp_millis(),
+    );
+    db.collection::<Document>("browser_sessions")
+        .insert_one(doc! {
+            "_id": id,
+            "wallet_pubkey": wallet,
+            "started_at": at,
+            "last_activity": at,
+            "active_tabs": tabs,
+            "total_visits": visits,
+        }, None)
+        .await
+        .unwrap();
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_merge_combines_sessions_and_removes_originals() {
+    let db = common::test_db().await;
+    let user = Keypair::new();
+    let wallet = user.pubkey().to_string();
+    insert_session(&db, "first", &wallet, 90, &["app.shadow"], 5).await;
+    insert_session(&db, "second", &wallet, 20, &["app.shadow", "swap.shadow"], 2).await;
+    let app = session_app!(db);
+
+    let req = test::TestRequest::post()
+        .uri("/api/sessions/merge")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&user)))
+        .set_json(serde_json::json!({ "session_ids": ["first", "second"] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    let merged_id = body["session_id"].as_str().unwrap().to_string();
+
+    let chronos = ChronosManager::new(db.clone());
+    assert_eq!(chronos.latest_session_id(&wallet).await.unwrap().as_deref(), Some(merged_id.as_str()));
+    let sessions: Vec<_> = db.collection::<Document>("browser_sessions")
+        .find(doc! {}, None)
+        .await
+        .unwrap()
+        .try_collect::<Vec<Document>>()
+        .await
+        .unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].get_i32("total_visits").unwrap(), 7);
+    assert_eq!(sessions[0].get_array("active_tabs").unwrap().len(), 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_merge_rejects_another_wallets_session() {
+    let db = common::test_db().await;
+    let user = Keypair::new();
+    insert_session(&db, "mine", &user.pubkey().to_string(), 30, &[], 1).await;
+    insert_session(&db, "theirs", &Keypair::new().pubkey().to_string(), 30, &[], 1).await;
+    let app = session_app!(db);
+
+    let req = test::TestRequest::post()
+        .uri("/api/sessions/merge")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&user)))
+        .set_json(serde_json::json!({ "session_ids": ["mine", "theirs"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    assert_eq!(db.collection::<Document>("browser_sessions").count_documents(doc! {}, None).await.unwrap(), 2);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_merge_needs_two_distinct_sessions() {
+    let db = common::offline_db().await;
+    let app = session_app!(db);
+
+    let req = test::TestRequest::post()
+        .uri("/api/sessions/merge")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .set_json(serde_json::json!({ "session_ids": ["same", "same"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let req = test::TestRequest::post()
+        .uri("/api/sessions/merge")
+        .set_json(serde_json::json!({ "session_ids": ["a", "b"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
@@ -0,0 +1,58 @@
+// Shared signing vectors: backend signatures must match what hermes-client verifies, and vice versa
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde_json::Value;
+use shadow_backend::charon::{sign_assertion, verify_assertion, BridgeAssertion};
+use shadow_signing::{canonicalize, sign_payload, verify_hmac_payload, verify_signed_payload, SignedPayload};
+use solana_sdk::signature::{Keypair, Signer};
+
+fn vectors() -> Value {
+    serde_json::from_str(include_str!("../../crates/shadow-signing/vectors.json")).unwrap()
+}
+
+fn vector_keypair(vectors: &Value) -> Keypair {
+    Keypair::from_bytes(&hex::decode(vectors["keypair"].as_str().unwrap()).unwrap()).unwrap()
+}
+
+#[test]
+fn test_solana_keypair_reproduces_vector_signatures() {
+    let vectors = vectors();
+    let keypair = vector_keypair(&vectors);
+    assert_eq!(keypair.pubkey().to_string(), vectors["signer"].as_str().unwrap());
+
+    for case in vectors["signed"].as_array().unwrap() {
+        let signed = sign_payload(&keypair.to_bytes(), &case["payload"]).unwrap();
+        assert_eq!(canonicalize(&signed.payload), case["canonical"].as_str().unwrap());
+        assert_eq!(signed.signature, case["signature"].as_str().unwrap());
+    }
+}
+
+#[test]
+fn test_client_signed_vectors_verify() {
+    let vectors = vectors();
+    let signer = vectors["signer"].as_str().unwrap();
+    let key = vectors["hmac_key"].as_str().unwrap().as_bytes();
+    for case in vectors["signed"].as_array().unwrap() {
+        // Round-trip through text so the payload is parsed the way a received one would be
+        let payload: Value = serde_json::from_str(&case["payload"].to_string()).unwrap();
+        let signed = SignedPayload {
+            payload: payload.clone(),
+            signer: signer.to_string(),
+            signature: case["signature"].as_str().unwrap().to_string(),
+        };
+        verify_signed_payload(&signed, signer).unwrap();
+        verify_hmac_payload(key, &payload, case["hmac"].as_str().unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn test_bridge_assertion_claims_are_canonical() {
+    let vectors = vectors();
+    let keypair = vector_keypair(&vectors);
+    let case = &vectors["signed"][1];
+    let claims: BridgeAssertion = serde_json::from_value(case["payload"].clone()).unwrap();
+
+    let token = sign_assertion(&keypair, &claims);
+    let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1).unwrap()).unwrap();
+    assert_eq!(String::from_utf8(payload).unwrap(), case["canonical"].as_str().unwrap());
+    assert_eq!(verify_assertion(&token, &keypair.pubkey(), claims.iat).unwrap(), claims);
+}
@@ -0,0 +1,102 @@
+// Integration tests for serving site content through the Hephaestus cache
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::storage::{BundlrStorage, PinataStorage};
+use solana_sdk::pubkey::Pubkey;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const SITE_CID: &str = "arweave://site-tx";
+const PAGE: &str = "<h1>Cached</h1>";
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_content_is_served_from_cache_with_an_etag() {
+    let db = common::test_db().await;
+    // The gateway may only be asked once; everything after comes from the cache
+    let gateway = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/site-tx"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("content-type", "text/html; charset=utf-8")
+            .set_body_string(PAGE))
+        .expect(1)
+        .mount(&gateway)
+        .await;
+
+    let program = Pubkey::new_unique().to_string();
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": &program,
+            "owner_pubkey": Pubkey::new_unique().to_string(),
+            "storage_cid": SITE_CID,
+            "name": "Cached",
+            "description": null,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    let mut config = common::test_config();
+    config.cache.default_ttl_seconds = 120;
+    let cache = web::Data::new(HephaestusCache::new(16, 3600));
+    let metrics = web::Data::new(MetricsCollector::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(PinataStorage::new()))
+            .app_data(web::Data::new(BundlrStorage::new().with_gateway_url(&gateway.uri())))
+            .app_data(cache.clone())
+            .app_data(metrics.clone())
+            .app_data(web::Data::new(config))
+            .route("/api/sites/{program_address}/content", web::get().to(handlers::get_site_content)),
+    )
+    .await;
+    let uri = format!("/api/sites/{}/content", program);
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    let etag = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+    assert_eq!(etag, HephaestusCache::etag(PAGE.as_bytes()));
+    assert_eq!(test::read_body(resp).await, PAGE);
+
+    // Cached under the storage CID, with the gateway's content type and the configured TTL
+    let cached = cache.get(&HephaestusCache::site_content_key(SITE_CID, None)).await.unwrap();
+    assert_eq!(cached.content_type, "text/html; charset=utf-8");
+    assert_eq!((cached.expires_at - cached.cached_at).num_seconds(), 120);
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("etag").unwrap(), etag.as_str());
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/html; charset=utf-8");
+    assert_eq!(test::read_body(resp).await, PAGE);
+
+    let snapshot = metrics.get_metrics();
+    assert_eq!((snapshot.cache_hits, snapshot.cache_misses), (1, 1));
+
+    // A matching validator gets an empty 304; a stale one gets the page
+    for (if_none_match, status) in [
+        (etag.clone(), 304),
+        (format!("\"stale\", W/{}", etag), 304),
+        ("*".to_string(), 304),
+        ("\"stale\"".to_string(), 200),
+    ] {
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("If-None-Match", if_none_match.as_str()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), status, "{}", if_none_match);
+        assert_eq!(resp.headers().get("etag").unwrap(), etag.as_str());
+        let body = test::read_body(resp).await;
+        assert_eq!(body.is_empty(), status == 304);
+    }
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
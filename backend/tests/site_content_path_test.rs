@@ -0,0 +1,102 @@
+// Integration tests for serving files inside a site by path with detected content types
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::storage::{BundlrStorage, PinataStorage};
+use solana_sdk::pubkey::Pubkey;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const WASM: &[u8] = b"\0asm\x01\0\0\0";
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_files_are_served_by_path_with_their_content_type() {
+    let db = common::test_db().await;
+    // Gateways label everything generically; unknown paths are 404s
+    let gateway = MockServer::start().await;
+    for (route, body) in [
+        ("/site-tx/index.html", b"<h1>Home</h1>".as_slice()),
+        ("/site-tx/docs/index.html", b"<h1>Docs</h1>".as_slice()),
+        ("/site-tx/css/site.css", b"body { color: red }".as_slice()),
+        ("/site-tx/pkg/app.wasm", WASM),
+        ("/site-tx/img/logo.png", PNG),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("content-type", "application/octet-stream")
+                .set_body_bytes(body))
+            .mount(&gateway)
+            .await;
+    }
+
+    let program = Pubkey::new_unique().to_string();
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": &program,
+            "owner_pubkey": Pubkey::new_unique().to_string(),
+            "storage_cid": "arweave://site-tx",
+            "name": "Bundle",
+            "description": null,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    let mut config = common::test_config();
+    config.cache.default_ttl_seconds = 300;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(PinataStorage::new()))
+            .app_data(web::Data::new(BundlrStorage::new().with_gateway_url(&gateway.uri())))
+            .app_data(web::Data::new(HephaestusCache::new(16, 3600)))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .app_data(web::Data::new(config))
+            .route("/api/sites/{program_address}/content/{path:.*}", web::get().to(handlers::get_site_content_path)),
+    )
+    .await;
+
+    for (file, content_type, body) in [
+        ("css/site.css", "text/css; charset=utf-8", b"body { color: red }".as_slice()),
+        ("pkg/app.wasm", "application/wasm", WASM),
+        ("img/logo.png", "image/png", PNG),
+        // The root and directories serve their index.html
+        ("", "text/html; charset=utf-8", b"<h1>Home</h1>".as_slice()),
+        ("docs/", "text/html; charset=utf-8", b"<h1>Docs</h1>".as_slice()),
+        ("docs", "text/html; charset=utf-8", b"<h1>Docs</h1>".as_slice()),
+    ] {
+        // Twice, so the cached copy is checked too
+        for _ in 0..2 {
+            let req = test::TestRequest::get()
+                .uri(&format!("/api/sites/{}/content/{}", program, file))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), 200, "{}", file);
+            assert_eq!(resp.headers().get("content-type").unwrap(), content_type, "{}", file);
+            assert_eq!(resp.headers().get("cache-control").unwrap(), "public, max-age=300", "{}", file);
+            assert_eq!(test::read_body(resp).await, body, "{}", file);
+        }
+    }
+
+    for (file, status) in [("css/missing.css", 404), ("missing", 404), ("../other-tx/index.html", 400)] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/sites/{}/content/{}", program, file))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), status, "{}", file);
+    }
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/sites/{}/content/index.html", Pubkey::new_unique()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
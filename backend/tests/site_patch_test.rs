@@ -0,0 +1,387 @@
+// Integration tests for PATCH site updates guarded by optimistic concurrency
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use mongodb::Database;
+use serde_json::Value;
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::config::OutboxConfig;
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::mnemosyne::{Mnemosyne, OutboxRelay};
+use shadow_backend::olympus::OlympusCA;
+use shadow_backend::tyche::TycheOwnership;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const LIVE_CID: &str = "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+const NEXT_CID: &str = "ipfs://bafybeib3azuaj5wr2b3roph6judaaltkmhtpehblfzsiif4week7dl6nry";
+
+macro_rules! site_app {
+    ($db:expr) => {
+        site_app!(
+            $db,
+            Arc::new(AnchorClient::with_programs("http://127.0.0.1:1".to_string(), Pubkey::new_unique(), Pubkey::new_unique())),
+            TycheOwnership::new($db.clone(), "http://127.0.0.1:1".to_string())
+        )
+    };
+    ($db:expr, $anchor:expr) => {{
+        let anchor = Arc::new($anchor);
+        let registry = Arc::clone(&anchor);
+        site_app!($db, anchor, TycheOwnership::new($db.clone(), "http://127.0.0.1:1".to_string()).with_registry(registry))
+    }};
+    ($db:expr, $anchor:expr, $tyche:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(Mnemosyne::new($db.clone())))
+                .app_data(web::Data::new(HephaestusCache::new(16, 60)))
+                .app_data(web::Data::new(OlympusCA::new($db.clone())))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::from($anchor))
+                .app_data(web::Data::new(MetricsCollector::new()))
+                .app_data(web::Data::new($tyche))
+                .route("/api/sites/{program_address}", web::put().to(handlers::update_site))
+                .route("/api/sites/{program_address}", web::patch().to(handlers::patch_site)),
+        )
+        .await
+    };
+}
+
+fn patch(owner: &Keypair, program: &str, body: Value) -> test::TestRequest {
+    test::TestRequest::patch()
+        .uri(&format!("/api/sites/{}", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(owner)))
+        .set_json(body)
+}
+
+async fn insert_site(db: &Database, program: &str, owner: &Keypair) {
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": program,
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": LIVE_CID,
+            "name": "Before",
+            "description": null,
+            "revision": 3_i64,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+}
+
+fn put(wallet: &Keypair, program: &str, owner: &Keypair, name: &str) -> test::TestRequest {
+    test::TestRequest::put()
+        .uri(&format!("/api/sites/{}", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+        .set_json(serde_json::json!({
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": LIVE_CID,
+            "name": name,
+        }))
+}
+
+/// Mock RPC serving a registry `Site` account owned by `owner` with `editor` listed
+async fn registry_with_editor(program: &Pubkey, owner: &Keypair, editor: &Keypair) -> (MockServer, AnchorClient) {
+    let (server, anchor) = common::empty_registry().await;
+    common::mount_registry_site(&server, &anchor, program, &owner.pubkey(), &[editor.pubkey()]).await;
+    (server, anchor)
+}
+
+fn relay_config() -> OutboxConfig {
+    OutboxConfig {
+        poll_interval_ms: 10,
+        batch_size: 10,
+        max_attempts: 3,
+        lease_seconds: 60,
+    }
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_concurrent_patches_apply_exactly_once() {
+    let db = common::test_db().await;
+    let app = site_app!(db);
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+
+    let first = patch(&owner, &program, serde_json::json!({
+        "name": "First", "storage_cid": NEXT_CID, "expected_revision": 3
+    }));
+    let second = patch(&owner, &program, serde_json::json!({
+        "name": "Second", "storage_cid": NEXT_CID, "expected_revision": 3
+    }));
+    let (a, b) = futures_util::join!(
+        test::call_service(&app, first.to_request()),
+        test::call_service(&app, second.to_request()),
+    );
+
+    let mut statuses = vec![a.status().as_u16(), b.status().as_u16()];
+    statuses.sort();
+    assert_eq!(statuses, vec![200, 409]);
+    let (winner, loser) = if a.status() == 200 { (a, b) } else { (b, a) };
+    let winner: Value = test::read_body_json(winner).await;
+    let loser: Value = test::read_body_json(loser).await;
+    assert_eq!(winner["revision"], 4);
+    assert_eq!(loser["current"]["revision"], 4);
+    assert_eq!(loser["current"]["name"], winner["name"]);
+
+    // Side effects are queued once and land once
+    let outbox = db.collection::<Document>("outbox");
+    assert_eq!(outbox.count_documents(doc! { "payload.type": "site_updated" }, None).await.unwrap(), 1);
+    let relay = OutboxRelay::new(db.clone(), relay_config());
+    let shutdown = CancellationToken::new();
+    assert_eq!(relay.run_once(&shutdown).await.unwrap().processed, 1);
+    assert_eq!(relay.run_once(&shutdown).await.unwrap().processed, 0);
+    let versions = db.collection::<Document>("site_versions");
+    assert_eq!(versions.count_documents(doc! { "program_address": &program }, None).await.unwrap(), 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_stale_precondition_returns_current_site() {
+    let db = common::test_db().await;
+    let app = site_app!(db);
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+
+    let req = patch(&owner, &program, serde_json::json!({ "name": "Late", "expected_revision": 2 }));
+    let resp = test::call_service(&app, req.to_request()).await;
+    assert_eq!(resp.status(), 409);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["current"]["name"], "Before");
+
+    let req = patch(&Keypair::new(), &program, serde_json::json!({ "name": "Mine", "expected_revision": 3 }));
+    assert_eq!(test::call_service(&app, req.to_request()).await.status(), 403);
+
+    // Clearing a field is a change; PUT shares the write path and bumps the revision
+    let req = patch(&owner, &program, serde_json::json!({ "name": "", "expected_revision": 3 }));
+    let body: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+    assert!(body["name"].is_null());
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/sites/{}", program))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(serde_json::json!({
+            "program_address": &program,
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": LIVE_CID,
+            "name": "Replaced",
+        }));
+    assert!(test::call_service(&app, req.to_request()).await.status().is_success());
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.revision, 5);
+    assert_eq!(site.name.as_deref(), Some("Replaced"));
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_patch_requires_precondition() {
+    let app = site_app!(common::offline_db().await);
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    for body in [
+        serde_json::json!({ "name": "No precondition" }),
+        serde_json::json!({ "storage_cid": "not-a-cid", "expected_revision": 1 }),
+    ] {
+        let resp = test::call_service(&app, patch(&owner, &program, body.clone()).to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", body);
+    }
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_put_rejects_wallets_that_are_not_editors() {
+    let db = common::test_db().await;
+    let (owner, editor) = (Keypair::new(), Keypair::new());
+    let program = Pubkey::new_unique();
+    let (_rpc, anchor) = registry_with_editor(&program, &owner, &editor).await;
+    let app = site_app!(db, anchor);
+    let program = program.to_string();
+    insert_site(&db, &program, &owner).await;
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/sites/{}", program))
+        .set_json(serde_json::json!({ "owner_pubkey": owner.pubkey().to_string(), "storage_cid": LIVE_CID }));
+    assert_eq!(test::call_service(&app, req.to_request()).await.status(), 401);
+    let resp = test::call_service(&app, put(&Keypair::new(), &program, &owner, "Mine").to_request()).await;
+    assert_eq!(resp.status(), 403);
+
+    // The owner named in the body is ignored; the site stays with the owner on record
+    let resp = test::call_service(&app, put(&editor, &program, &editor, "Mine").to_request()).await;
+    assert!(resp.status().is_success());
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.owner_pubkey, owner.pubkey().to_string());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_put_by_editor_updates_site() {
+    let db = common::test_db().await;
+    let (owner, editor) = (Keypair::new(), Keypair::new());
+    let program = Pubkey::new_unique();
+    let (_rpc, anchor) = registry_with_editor(&program, &owner, &editor).await;
+    let app = site_app!(db, anchor);
+    let program = program.to_string();
+    insert_site(&db, &program, &owner).await;
+
+    let resp = test::call_service(&app, put(&editor, &program, &owner, "Edited").to_request()).await;
+    assert!(resp.status().is_success());
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.name.as_deref(), Some("Edited"));
+    assert_eq!(site.owner_pubkey, owner.pubkey().to_string());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_put_validates_storage_cid() {
+    let app = site_app!(common::offline_db().await);
+    let owner = Keypair::new();
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/sites/{}", Pubkey::new_unique()))
+        .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+        .set_json(serde_json::json!({ "owner_pubkey": owner.pubkey().to_string(), "storage_cid": "not-a-cid" }));
+    assert_eq!(test::call_service(&app, req.to_request()).await.status(), 400);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_put_only_lets_the_owner_on_record_update() {
+    let db = common::test_db().await;
+    let (owner, editor, intruder) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let program = Pubkey::new_unique();
+    let (_rpc, anchor) = registry_with_editor(&program, &owner, &editor).await;
+    let app = site_app!(db, anchor);
+    let program = program.to_string();
+    insert_site(&db, &program, &owner).await;
+
+    // Naming yourself as the owner in the body doesn't make you one
+    let resp = test::call_service(&app, put(&intruder, &program, &intruder, "Taken").to_request()).await;
+    assert_eq!(resp.status(), 403);
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!((site.name.as_deref(), site.owner_pubkey.as_str()), (Some("Before"), owner.pubkey().to_string().as_str()));
+
+    let resp = test::call_service(&app, put(&owner, &program, &owner, "Renamed").to_request()).await;
+    assert!(resp.status().is_success());
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.name.as_deref(), Some("Renamed"));
+
+    // A site registered nowhere isn't created by PUT
+    let unregistered = Pubkey::new_unique().to_string();
+    let resp = test::call_service(&app, put(&intruder, &unregistered, &intruder, "Squatted").to_request()).await;
+    assert_eq!(resp.status(), 404);
+    assert!(shadow_backend::db::get_site(&db, &unregistered).await.unwrap().is_none());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_put_after_a_transfer_follows_the_registry() {
+    let db = common::test_db().await;
+    let (old_owner, new_owner, editor) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let program = Pubkey::new_unique();
+    // transfer_ownership moved the site on-chain; the record still names the old owner
+    let (_rpc, anchor) = registry_with_editor(&program, &new_owner, &editor).await;
+    let app = site_app!(db, anchor);
+    let program = program.to_string();
+    insert_site(&db, &program, &old_owner).await;
+
+    let resp = test::call_service(&app, put(&old_owner, &program, &old_owner, "Still mine").to_request()).await;
+    assert_eq!(resp.status(), 403);
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.name.as_deref(), Some("Before"));
+
+    // The new owner gets in, and the record catches up with the transfer
+    let resp = test::call_service(&app, put(&new_owner, &program, &old_owner, "Handed over").to_request()).await;
+    assert!(resp.status().is_success());
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.name.as_deref(), Some("Handed over"));
+    assert_eq!(site.owner_pubkey, new_owner.pubkey().to_string());
+
+    let resp = test::call_service(&app, put(&old_owner, &program, &old_owner, "Still mine").to_request()).await;
+    assert_eq!(resp.status(), 403);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_put_reports_a_registry_outage_as_bad_gateway() {
+    let db = common::test_db().await;
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+    let rpc = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "error": { "code": -32005, "message": "Node is behind" },
+            "id": 1
+        })))
+        .mount(&rpc)
+        .await;
+    let app = site_app!(db, AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), Pubkey::new_unique()));
+
+    let resp = test::call_service(&app, put(&owner, &program, &owner, "Renamed").to_request()).await;
+    assert_eq!(resp.status(), 502);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_new_storage_cid_evicts_the_old_content() {
+    let db = common::test_db().await;
+    let owner = Keypair::new();
+    let program = Pubkey::new_unique().to_string();
+    insert_site(&db, &program, &owner).await;
+
+    let cache = web::Data::new(HephaestusCache::new(16, 60));
+    for language in [None, Some("es")] {
+        cache.set(HephaestusCache::site_content_key(LIVE_CID, language), b"old".to_vec(), "text/html".to_string(), None)
+            .await
+            .unwrap();
+    }
+    cache.set(HephaestusCache::site_asset_key(&program, "app.js"), b"old".to_vec(), "text/javascript".to_string(), None)
+        .await
+        .unwrap();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(Mnemosyne::new(db.clone())))
+            .app_data(cache.clone())
+            .app_data(web::Data::new(OlympusCA::new(db.clone())))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(TycheOwnership::new(db.clone(), "http://127.0.0.1:1".to_string())))
+            .route("/api/sites/{program_address}", web::patch().to(handlers::patch_site)),
+    )
+    .await;
+
+    // Renaming keeps the CID, so the cached content stays
+    let req = patch(&owner, &program, serde_json::json!({ "name": "Renamed", "expected_revision": 3 }));
+    assert_eq!(test::call_service(&app, req.to_request()).await.status(), 200);
+    assert_eq!(cache.len().await, 3);
+
+    // Files served from the old content go along with its entry file
+    let req = patch(&owner, &program, serde_json::json!({ "storage_cid": NEXT_CID, "expected_revision": 4 }));
+    assert_eq!(test::call_service(&app, req.to_request()).await.status(), 200);
+    assert!(cache.is_empty().await);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,72 @@
+// Integration tests for on-demand site tokens via the link converter
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::handlers_link;
+use shadow_backend::link_converter::LinkMintAuthority;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_site_token_is_created_once_for_primary_domain() {
+    let db = common::test_db().await;
+    let now = mongodb::bson::DateTime::now();
+    let owner = Pubkey::new_unique().to_string();
+    let program = Pubkey::new_unique().to_string();
+
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": &program,
+            "owner_pubkey": &owner,
+            "storage_cid": "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            "name": "Tokenized",
+            "description": null,
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+    for (domain, verified) in [("pending.shadow", false), ("primary.shadow", true)] {
+        db.collection::<Document>("domains")
+            .insert_one(doc! {
+                "_id": domain,
+                "owner_pubkey": &owner,
+                "program_address": &program,
+                "verified": verified,
+                "created_at": now,
+                "updated_at": now,
+            }, None)
+            .await
+            .unwrap();
+    }
+
+    let (rpc, sent) = common::submitting_rpc().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(rpc.uri()))
+            .app_data(web::Data::new(LinkMintAuthority::new(Keypair::new())))
+            .route("/api/sites/{program_address}/token", web::get().to(handlers_link::get_site_token)),
+    )
+    .await;
+
+    let uri = format!("/api/sites/{}/token", program);
+    let first: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(first["domain"], "primary.shadow");
+    assert_eq!(first["is_new"], true);
+    let minted = sent.lock().unwrap()[0].message.account_keys[1].to_string();
+    assert_eq!(first["token_mint"], minted.as_str());
+
+    let second: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!(second["is_new"], false);
+    assert_eq!(second["token_mint"], first["token_mint"]);
+    assert_eq!(sent.lock().unwrap().len(), 1, "existing token is not minted again");
+
+    let req = test::TestRequest::get().uri(&format!("/api/sites/{}/token", Pubkey::new_unique())).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,183 @@
+// Integration tests for sending transactions through SolanaClient and classifying their failures
+use base64::{engine::general_purpose, Engine as _};
+use shadow_backend::solana::{SolanaClient, SolanaError};
+use solana_client::client_error::ClientError;
+use solana_client::rpc_request::RpcError;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use solana_sdk::system_transaction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// How the RPC answers one sendTransaction
+enum Send {
+    /// Accepted and confirmed
+    Lands,
+    /// Accepted, then failed on-chain with this error
+    Fails(serde_json::Value),
+    /// Accepted but never seen again; the blockhash has expired by the time the client checks
+    Lost,
+    /// Rejected in preflight with this error
+    Rejected(serde_json::Value),
+}
+
+/// JSON-RPC stand-in that answers sendTransaction calls from a script, in order
+struct ScriptedRpc {
+    script: Mutex<VecDeque<Send>>,
+    sent: Arc<Mutex<Vec<Transaction>>>,
+    /// Signatures that landed, with the error each failed with (null when it succeeded)
+    landed: Mutex<Vec<(String, serde_json::Value)>>,
+}
+
+impl Respond for ScriptedRpc {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let result = match body["method"].as_str().unwrap_or_default() {
+            "getVersion" => serde_json::json!({ "solana-core": "1.18.26", "feature-set": 0 }),
+            "getLatestBlockhash" => serde_json::json!({
+                "context": { "slot": 1 },
+                "value": { "blockhash": Hash::new_unique().to_string(), "lastValidBlockHeight": 100 }
+            }),
+            "sendTransaction" => {
+                let bytes = general_purpose::STANDARD.decode(body["params"][0].as_str().unwrap()).unwrap();
+                let tx: Transaction = bincode::deserialize(&bytes).unwrap();
+                let signature = tx.signatures[0].to_string();
+                self.sent.lock().unwrap().push(tx);
+                match self.script.lock().unwrap().pop_front().expect("unexpected sendTransaction") {
+                    Send::Lands => self.landed.lock().unwrap().push((signature.clone(), serde_json::Value::Null)),
+                    Send::Fails(err) => self.landed.lock().unwrap().push((signature.clone(), err)),
+                    Send::Lost => {}
+                    Send::Rejected(err) => {
+                        return ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": body["id"],
+                            "error": {
+                                "code": -32002,
+                                "message": "Transaction simulation failed",
+                                "data": { "err": err, "logs": [], "accounts": null, "unitsConsumed": 0, "returnData": null }
+                            }
+                        }));
+                    }
+                }
+                serde_json::json!(signature)
+            }
+            "getSignatureStatuses" => {
+                let signature = body["params"][0][0].as_str().unwrap();
+                let status = self.landed.lock().unwrap().iter().find(|(s, _)| s == signature).map(|(_, err)| {
+                    let result = if err.is_null() { serde_json::json!({ "Ok": null }) } else { serde_json::json!({ "Err": err }) };
+                    serde_json::json!({ "slot": 1, "confirmations": null, "err": err, "status": result, "confirmationStatus": "finalized" })
+                });
+                serde_json::json!({ "context": { "slot": 1 }, "value": [status] })
+            }
+            "isBlockhashValid" => serde_json::json!({ "context": { "slot": 1 }, "value": false }),
+            other => panic!("unexpected RPC method {}", other),
+        };
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "result": result, "id": body["id"] }))
+    }
+}
+
+async fn scripted_rpc(script: Vec<Send>) -> (MockServer, Arc<Mutex<Vec<Transaction>>>) {
+    let server = MockServer::start().await;
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    Mock::given(method("POST"))
+        .respond_with(ScriptedRpc { script: Mutex::new(script.into()), sent: Arc::clone(&sent), landed: Mutex::new(Vec::new()) })
+        .mount(&server)
+        .await;
+    (server, sent)
+}
+
+fn transfer(payer: &Keypair) -> Transaction {
+    system_transaction::transfer(payer, &Pubkey::new_unique(), 1_000, Hash::new_unique())
+}
+
+#[tokio::test]
+async fn test_send_transaction_classifies_preflight_failures() {
+    let (rpc, _) = scripted_rpc(vec![
+        Send::Rejected(serde_json::json!("InsufficientFundsForFee")),
+        Send::Rejected(serde_json::json!("AccountNotFound")),
+        Send::Rejected(serde_json::json!({ "InstructionError": [0, { "Custom": 6001 }] })),
+        Send::Rejected(serde_json::json!({ "InstructionError": [0, "InsufficientFunds"] })),
+        Send::Rejected(serde_json::json!("BlockhashNotFound")),
+        Send::Rejected(serde_json::json!("AlreadyProcessed")),
+    ])
+    .await;
+    let solana = SolanaClient::new(rpc.uri());
+    let tx = transfer(&Keypair::new());
+
+    assert_eq!(solana.send_transaction(&tx).await, Err(SolanaError::InsufficientFunds));
+    assert_eq!(solana.send_transaction(&tx).await, Err(SolanaError::AccountNotFound));
+    assert_eq!(solana.send_transaction(&tx).await, Err(SolanaError::ProgramError(6001)));
+    assert_eq!(solana.send_transaction(&tx).await, Err(SolanaError::InsufficientFunds));
+    assert_eq!(solana.send_transaction(&tx).await, Err(SolanaError::BlockhashNotFound));
+    assert!(matches!(solana.send_transaction(&tx).await, Err(SolanaError::Unknown(_))));
+}
+
+#[tokio::test]
+async fn test_unconfirmed_transaction_times_out() {
+    let (rpc, _) = scripted_rpc(vec![Send::Lost]).await;
+    let solana = SolanaClient::new(rpc.uri());
+    assert_eq!(solana.send_transaction(&transfer(&Keypair::new())).await, Err(SolanaError::Timeout));
+
+    let unconfirmed: ClientError = RpcError::ForUser("unable to confirm transaction. This can happen...".to_string()).into();
+    assert_eq!(SolanaError::from(unconfirmed), SolanaError::Timeout);
+    let failed: ClientError = TransactionError::InstructionError(2, InstructionError::Custom(7)).into();
+    assert_eq!(SolanaError::from(failed), SolanaError::ProgramError(7));
+}
+
+#[tokio::test]
+async fn test_confirmed_transaction_reports_its_on_chain_result() {
+    let (rpc, sent) = scripted_rpc(vec![
+        Send::Lands,
+        Send::Fails(serde_json::json!({ "InstructionError": [1, { "Custom": 6002 }] })),
+    ])
+    .await;
+    let solana = SolanaClient::new(rpc.uri());
+
+    let signature = solana.send_transaction(&transfer(&Keypair::new())).await.unwrap();
+    assert_eq!(signature, sent.lock().unwrap()[0].signatures[0].to_string());
+    // Passing preflight isn't enough; the status from the cluster decides
+    assert_eq!(solana.send_transaction(&transfer(&Keypair::new())).await, Err(SolanaError::ProgramError(6002)));
+}
+
+#[tokio::test]
+async fn test_retries_re_sign_on_a_fresh_blockhash() {
+    let (rpc, sent) = scripted_rpc(vec![
+        Send::Rejected(serde_json::json!("BlockhashNotFound")),
+        Send::Lost,
+        Send::Lands,
+    ])
+    .await;
+    let solana = SolanaClient::new(rpc.uri());
+    let payer = Keypair::new();
+    let tx = transfer(&payer);
+
+    let signature = solana.send_transaction_with_retries(&tx, &[&payer], 2).await.unwrap();
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 3);
+    assert_eq!(signature, sent[2].signatures[0].to_string());
+    // Each attempt went out on its own blockhash, properly signed
+    assert_ne!(sent[0].message.recent_blockhash, sent[1].message.recent_blockhash);
+    assert_ne!(sent[1].message.recent_blockhash, sent[2].message.recent_blockhash);
+    assert!(sent.iter().all(|tx| tx.verify().is_ok()));
+    assert_eq!(sent[0], tx);
+}
+
+#[tokio::test]
+async fn test_retries_stop_at_the_limit_and_on_other_errors() {
+    let (rpc, sent) = scripted_rpc(vec![Send::Lost, Send::Lost]).await;
+    let solana = SolanaClient::new(rpc.uri());
+    let payer = Keypair::new();
+    assert_eq!(solana.send_transaction_with_retries(&transfer(&payer), &[&payer], 1).await, Err(SolanaError::Timeout));
+    assert_eq!(sent.lock().unwrap().len(), 2);
+
+    // Program errors would fail again on any blockhash
+    let (rpc, sent) = scripted_rpc(vec![Send::Rejected(serde_json::json!({ "InstructionError": [0, { "Custom": 1 }] }))]).await;
+    let solana = SolanaClient::new(rpc.uri());
+    assert_eq!(solana.send_transaction_with_retries(&transfer(&payer), &[&payer], 3).await, Err(SolanaError::ProgramError(1)));
+    assert_eq!(sent.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,185 @@
+// Integration tests for the Solana WebSocket client against a mock validator socket
+use base64::{engine::general_purpose, Engine as _};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use shadow_backend::solana_ws::{ConnectionState, SolanaWebSocketClient};
+use shadow_backend::websocket::HermesBroker;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+/// Validator socket that answers each subscribe request, sends one notification for it and
+/// then closes the connection after its third message. Records (connection, method) for
+/// every request it receives.
+async fn flaky_validator() -> (String, Arc<Mutex<Vec<(u64, String)>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&received);
+    tokio::spawn(async move {
+        let mut connection = 0;
+        while let Ok((stream, _)) = listener.accept().await {
+            connection += 1;
+            let mut socket = accept_async(stream).await.unwrap();
+            let mut sent = 0;
+            while let Some(Ok(Message::Text(text))) = socket.next().await {
+                let request: Value = serde_json::from_str(&text).unwrap();
+                log.lock().unwrap().push((connection, request["method"].as_str().unwrap().to_string()));
+                // Subscription ids differ between connections, as they do on a real validator
+                let subscription = connection * 100 + sent;
+                let messages = [
+                    serde_json::json!({ "jsonrpc": "2.0", "result": subscription, "id": request["id"] }),
+                    serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "method": "accountNotification",
+                        "params": { "result": { "context": { "slot": connection }, "value": { "lamports": connection } }, "subscription": subscription },
+                    }),
+                ];
+                for message in messages {
+                    socket.send(Message::Text(message.to_string())).await.unwrap();
+                    sent += 1;
+                    if sent == 3 {
+                        let _ = socket.close(None).await;
+                        break;
+                    }
+                }
+                if sent >= 3 {
+                    break;
+                }
+            }
+        }
+    });
+    (url, received)
+}
+
+async fn wait_for_state(state: &mut tokio::sync::watch::Receiver<ConnectionState>, expected: ConnectionState) {
+    tokio::time::timeout(Duration::from_secs(5), state.wait_for(|state| *state == expected))
+        .await
+        .unwrap_or_else(|_| panic!("never {:?}", expected))
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_reconnects_and_replays_subscriptions() {
+    let (url, received) = flaky_validator().await;
+    let broker = Arc::new(HermesBroker::new());
+    let client = SolanaWebSocketClient::new(url, Arc::clone(&broker))
+        .with_backoff(Duration::from_millis(10), Duration::from_millis(50));
+    let mut state = client.state();
+    assert_eq!(*state.borrow(), ConnectionState::Connecting);
+
+    let account = client.subscribe_account(&Pubkey::new_unique().to_string()).await.unwrap();
+    let program = client.subscribe_program(&Pubkey::new_unique().to_string()).await.unwrap();
+    assert_ne!(account, program);
+    let mut account_events = broker.subscribe(SolanaWebSocketClient::subscription_topic(account)).await;
+    let handle = client.start();
+
+    // Notifications keep arriving on the same topic across reconnects
+    let mut slots = Vec::new();
+    while slots.len() < 3 {
+        let event = tokio::time::timeout(Duration::from_secs(5), account_events.recv()).await.unwrap().unwrap();
+        let event: Value = serde_json::from_str(&event).unwrap();
+        slots.push(event["params"]["result"]["context"]["slot"].as_u64().unwrap());
+    }
+    assert_eq!(slots, [1, 2, 3]);
+
+    // Each connection was sent every subscription again, in the order they were made
+    let received = received.lock().unwrap().clone();
+    for connection in 1..=2 {
+        let methods: Vec<&str> = received.iter()
+            .filter(|(conn, _)| *conn == connection)
+            .map(|(_, method)| method.as_str())
+            .collect();
+        assert_eq!(methods, ["accountSubscribe", "programSubscribe"], "connection {}", connection);
+    }
+
+    wait_for_state(&mut state, ConnectionState::Connected).await;
+    client.close();
+    tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_unreachable_validator_keeps_reconnecting_until_closed() {
+    // Nothing listens on a port we just released
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let client = SolanaWebSocketClient::new(format!("ws://127.0.0.1:{}", port), Arc::new(HermesBroker::new()))
+        .with_backoff(Duration::from_millis(5), Duration::from_millis(20));
+    let mut state = client.state();
+    let handle = client.start();
+
+    wait_for_state(&mut state, ConnectionState::Reconnecting).await;
+    assert!(!handle.is_finished());
+    client.close();
+    tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_pubkeys_are_rejected() {
+    let client = SolanaWebSocketClient::new("ws://127.0.0.1:1".to_string(), Arc::new(HermesBroker::new()));
+    assert!(client.subscribe_account("not-a-pubkey").await.is_err());
+    assert!(client.subscribe_program("not-a-pubkey").await.is_err());
+    assert!(client.subscribe_logs("not-a-pubkey").await.is_err());
+}
+
+#[tokio::test]
+async fn test_logs_are_published_as_registry_events() {
+    let (program, site, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut event = Sha256::digest(b"event:SiteUpdated")[..8].to_vec();
+    for key in [site, program, owner, owner] {
+        event.extend_from_slice(key.as_ref());
+    }
+    event.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    let data = format!("Program data: {}", general_purpose::STANDARD.encode(&event));
+    let logs = move |signature: &str, err: Value| serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "logsNotification",
+        "params": { "subscription": 7, "result": { "context": { "slot": 1 }, "value": {
+            "signature": signature,
+            "err": err,
+            "logs": [
+                "Program log: Instruction: UpdateSite",
+                data,
+            ],
+        } } },
+    });
+
+    // Answers the subscription, then reports a failed transaction and a successful one
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = accept_async(stream).await.unwrap();
+        let Some(Ok(Message::Text(text))) = socket.next().await else { return };
+        let request: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(request["method"], "logsSubscribe");
+        assert_eq!(request["params"][0]["mentions"][0], program.to_string());
+        for message in [
+            serde_json::json!({ "jsonrpc": "2.0", "result": 7, "id": request["id"] }),
+            logs("failed", serde_json::json!({ "InstructionError": [0, "Custom"] })),
+            logs("landed", Value::Null),
+        ] {
+            socket.send(Message::Text(message.to_string())).await.unwrap();
+        }
+        while socket.next().await.is_some() {}
+    });
+
+    let broker = Arc::new(HermesBroker::new());
+    let client = SolanaWebSocketClient::new(url, Arc::clone(&broker));
+    let id = client.subscribe_logs(&program.to_string()).await.unwrap();
+    let mut events = broker.subscribe(SolanaWebSocketClient::subscription_topic(id)).await;
+    let handle = client.start();
+
+    let event = tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap().unwrap();
+    let event: Value = serde_json::from_str(&event).unwrap();
+    assert_eq!(event["type"], "site_updated");
+    assert_eq!(event["signature"], "landed");
+    assert_eq!(event["program_address"], program.to_string());
+    assert_eq!(event["updated_by"], owner.to_string());
+    assert_eq!(event["at"], 1_700_000_000);
+
+    client.close();
+    tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+}
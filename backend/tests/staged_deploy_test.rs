@@ -0,0 +1,171 @@
+// Integration tests for staged deploys: Asclepius health checks gate the live storage CID
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::asclepius::AsclepiusChecker;
+use shadow_backend::config::DeployConfig;
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::mnemosyne::Mnemosyne;
+use shadow_backend::tyche::TycheOwnership;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::path::Path;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const LIVE_CID: &str = "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+const BROKEN_CID: &str = "ipfs://bafybeihvez4vzfjzttvcpqcvzbbmhvvlaghnb6spm33qdqukwiw6ykbdpm";
+const CLEAN_CID: &str = "ipfs://bafybeib3azuaj5wr2b3roph6judaaltkmhtpehblfzsiif4week7dl6nry";
+
+/// Serve every file of a fixture bundle under `/{cid}/` on the mock gateway
+async fn mount_bundle(gateway: &MockServer, cid: &str, fixture: &str) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/deploy").join(fixture);
+    let hash = cid.strip_prefix("ipfs://").unwrap();
+    let mut dirs = vec![root.clone()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let file = entry.unwrap().path();
+            if file.is_dir() {
+                dirs.push(file);
+                continue;
+            }
+            let relative = file.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/");
+            Mock::given(method("GET"))
+                .and(path(format!("/{}/{}", hash, relative)))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(std::fs::read(&file).unwrap()))
+                .mount(gateway)
+                .await;
+        }
+    }
+}
+
+fn deploy_config(gateway: &MockServer) -> DeployConfig {
+    DeployConfig {
+        ipfs_gateway_url: gateway.uri(),
+        arweave_gateway_url: gateway.uri(),
+        min_success_rate: 0.95,
+        html_check: true,
+        request_timeout_seconds: 5,
+    }
+}
+
+#[tokio::test]
+async fn test_checks_report_missing_manifest_asset() {
+    let gateway = MockServer::start().await;
+    mount_bundle(&gateway, BROKEN_CID, "broken").await;
+    mount_bundle(&gateway, CLEAN_CID, "clean").await;
+    let checker = AsclepiusChecker::new(deploy_config(&gateway));
+
+    let broken = checker.check(BROKEN_CID, false).await;
+    assert!(!broken.passed);
+    let failures: Vec<&str> = broken.failures().iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(failures, vec!["manifest_assets", "success_rate"]);
+    assert!(broken.checks[1].detail.as_deref().unwrap().contains("static/media/logo.svg"));
+
+    let clean = checker.check(CLEAN_CID, false).await;
+    assert!(clean.passed, "{:?}", clean.failures());
+    assert_eq!(clean.success_rate, 1.0);
+    assert!(clean.checks.iter().any(|c| c.name == "html_parse"));
+}
+
+#[tokio::test]
+async fn test_missing_index_fails() {
+    let gateway = MockServer::start().await;
+    let report = AsclepiusChecker::new(deploy_config(&gateway)).check(CLEAN_CID, false).await;
+    assert!(!report.passed);
+    assert_eq!(report.checks[0].name, "index_html");
+    assert!(!report.checks[0].passed);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_live_pointer_only_moves_when_checks_pass() {
+    let db = common::test_db().await;
+    let gateway = MockServer::start().await;
+    mount_bundle(&gateway, BROKEN_CID, "broken").await;
+    mount_bundle(&gateway, CLEAN_CID, "clean").await;
+
+    let owner = Keypair::new();
+    let program = solana_sdk::pubkey::Pubkey::new_unique().to_string();
+    let now = mongodb::bson::DateTime::now();
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": &program,
+            "owner_pubkey": owner.pubkey().to_string(),
+            "storage_cid": LIVE_CID,
+            "name": "Staged",
+            "description": null,
+            "created_at": now,
+            "updated_at": now,
+        }, None)
+        .await
+        .unwrap();
+
+    let mut config = common::test_config();
+    config.deploy = deploy_config(&gateway);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(Mnemosyne::new(db.clone())))
+            .app_data(web::Data::new(HephaestusCache::new(16, 60)))
+            .app_data(web::Data::new(AresAuth::new()))
+            .app_data(web::Data::new(TycheOwnership::new(db.clone(), "http://127.0.0.1:1".to_string())))
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::new(AnchorClient::with_programs(
+                "http://127.0.0.1:1".to_string(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+            )))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/sites/{program_address}/deploys", web::post().to(handlers::stage_deploy))
+            .route("/api/sites/{program_address}/versions", web::get().to(handlers::list_site_versions))
+            .route("/api/sites/{program_address}/preview", web::get().to(handlers::get_site_preview)),
+    )
+    .await;
+    let deploy = |cid: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/api/sites/{}/deploys", program))
+            .insert_header(("X-Shadow-Auth", common::auth_header(&owner)))
+            .set_json(serde_json::json!({ "storage_cid": cid }))
+            .to_request()
+    };
+
+    // A bundle with a missing referenced asset stays a preview
+    let body: Value = test::call_and_read_body_json(&app, deploy(BROKEN_CID)).await;
+    assert_eq!(body["live"], false);
+    assert_eq!(body["storage_cid"], LIVE_CID);
+    assert_eq!(body["failures"][0]["name"], "manifest_assets");
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.storage_cid, LIVE_CID);
+    assert_eq!(site.preview_cid.as_deref(), Some(BROKEN_CID));
+
+    let req = test::TestRequest::get().uri(&format!("/api/sites/{}/preview", program)).to_request();
+    let preview = test::call_and_read_body(&app, req).await;
+    assert!(String::from_utf8_lossy(&preview).contains("Fixture site"));
+
+    // A clean bundle goes live and clears the preview
+    let body: Value = test::call_and_read_body_json(&app, deploy(CLEAN_CID)).await;
+    assert_eq!(body["live"], true, "{}", body);
+    let site = shadow_backend::db::get_site(&db, &program).await.unwrap().unwrap();
+    assert_eq!(site.storage_cid, CLEAN_CID);
+    assert!(site.preview_cid.is_none());
+
+    let req = test::TestRequest::get().uri(&format!("/api/sites/{}/versions", program)).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let versions = body["versions"].as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[0]["status"], "live");
+    assert_eq!(versions[0]["report"]["passed"], true);
+    assert_eq!(versions[1]["status"], "preview");
+    assert_eq!(versions[1]["report"]["checks"][1]["passed"], false);
+    // No RPC to read on-chain versions from
+    assert!(body["published"].is_null());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
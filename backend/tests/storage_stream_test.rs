@@ -0,0 +1,179 @@
+// Integration tests for streaming large storage objects through the content and asset endpoints
+mod common;
+
+use actix_web::{test, web, App};
+use futures_util::StreamExt;
+use mongodb::bson::{doc, Document};
+use shadow_backend::handlers;
+use shadow_backend::hephaestus::HephaestusCache;
+use shadow_backend::metrics::MetricsCollector;
+use shadow_backend::storage::{BundlrStorage, PinataStorage};
+use solana_sdk::pubkey::Pubkey;
+use wiremock::matchers::{header_exists, method, path};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+const LARGE_BODY_BYTES: usize = 16 * 1_048_576;
+
+fn large_body() -> Vec<u8> {
+    (0..LARGE_BODY_BYTES).map(|i| (i % 251) as u8).collect()
+}
+
+/// Gateway stand-in that honours single `bytes=start-end` ranges like Arweave and IPFS gateways
+struct RangeGateway(Vec<u8>);
+
+impl Respond for RangeGateway {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let Some(range) = request.headers.get("range").and_then(|v| v.to_str().ok()) else {
+            return ResponseTemplate::new(200)
+                .insert_header("content-type", "video/mp4")
+                .set_body_bytes(self.0.clone());
+        };
+        let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+        let start: usize = start.parse().unwrap();
+        if start >= self.0.len() {
+            return ResponseTemplate::new(416)
+                .insert_header("content-range", format!("bytes */{}", self.0.len()).as_str());
+        }
+        let end = end.parse::<usize>().map_or(self.0.len() - 1, |end| end.min(self.0.len() - 1));
+        ResponseTemplate::new(206)
+            .insert_header("content-type", "video/mp4")
+            .insert_header("content-range", format!("bytes {}-{}/{}", start, end, self.0.len()).as_str())
+            .set_body_bytes(self.0[start..=end].to_vec())
+    }
+}
+
+async fn mount_gateway(gateway: &MockServer, route: &str, body: Vec<u8>) {
+    Mock::given(method("GET"))
+        .and(path(route))
+        .respond_with(RangeGateway(body))
+        .mount(gateway)
+        .await;
+}
+
+#[tokio::test]
+async fn test_get_stream_yields_chunks_without_buffering() {
+    let gateway = MockServer::start().await;
+    mount_gateway(&gateway, "/movie-tx", large_body()).await;
+    let bundlr = BundlrStorage::new().with_gateway_url(&gateway.uri());
+
+    let mut stream = bundlr.get_stream("arweave://movie-tx", None).await.unwrap();
+    assert_eq!(stream.status, 200);
+    assert_eq!(stream.content_length, Some(LARGE_BODY_BYTES as u64));
+    assert_eq!(stream.content_type.as_deref(), Some("video/mp4"));
+
+    // Chunks arrive as the socket delivers them; none comes close to the whole object
+    let (mut total, mut largest) = (0, 0);
+    while let Some(chunk) = stream.body.next().await {
+        let chunk = chunk.unwrap();
+        total += chunk.len();
+        largest = largest.max(chunk.len());
+    }
+    assert_eq!(total, LARGE_BODY_BYTES);
+    assert!(largest <= 1_048_576, "largest chunk was {} bytes", largest);
+}
+
+#[tokio::test]
+async fn test_ranged_get_stream_only_pulls_the_range() {
+    let gateway = MockServer::start().await;
+    let body = large_body();
+    Mock::given(method("GET"))
+        .and(path("/ipfs/bafymovie/clip.mp4"))
+        .and(header_exists("range"))
+        .respond_with(RangeGateway(body.clone()))
+        .expect(2)
+        .mount(&gateway)
+        .await;
+    let pinata = PinataStorage::new().with_gateway_url(&format!("{}/ipfs/", gateway.uri()));
+
+    let stream = pinata.get_stream("ipfs://bafymovie/clip.mp4", Some("bytes=1000-1999")).await.unwrap();
+    assert_eq!(stream.status, 206);
+    assert_eq!(stream.content_length, Some(1000));
+    assert_eq!(stream.content_range.as_deref(), Some(&*format!("bytes 1000-1999/{}", LARGE_BODY_BYTES)));
+    let chunks: Vec<_> = stream.body.collect().await;
+    let received: Vec<u8> = chunks.into_iter().flat_map(|c| c.unwrap().to_vec()).collect();
+    assert_eq!(received, body[1000..2000]);
+
+    // A range past the end is passed back to the caller rather than treated as a failure
+    let stream = pinata.get_stream("ipfs://bafymovie/clip.mp4", Some("bytes=99999999-")).await.unwrap();
+    assert_eq!(stream.status, 416);
+    assert_eq!(stream.content_range.as_deref(), Some(&*format!("bytes */{}", LARGE_BODY_BYTES)));
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_asset_endpoint_streams_large_files_and_serves_ranges() {
+    let db = common::test_db().await;
+    let gateway = MockServer::start().await;
+    let body = large_body();
+    mount_gateway(&gateway, "/site-tx/media/intro.mp4", body.clone()).await;
+    mount_gateway(&gateway, "/site-tx/logo.svg", b"<svg/>".to_vec()).await;
+
+    let program = Pubkey::new_unique().to_string();
+    db.collection::<Document>("sites")
+        .insert_one(doc! {
+            "_id": &program,
+            "owner_pubkey": Pubkey::new_unique().to_string(),
+            "storage_cid": "arweave://site-tx",
+            "name": "Cinema",
+            "description": null,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    let cache = web::Data::new(HephaestusCache::new(64, 3600).with_max_object_bytes(1_048_576));
+    let metrics = web::Data::new(MetricsCollector::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(PinataStorage::new()))
+            .app_data(web::Data::new(BundlrStorage::new().with_gateway_url(&gateway.uri())))
+            .app_data(cache.clone())
+            .app_data(metrics.clone())
+            .route("/api/sites/{program_address}/assets/{path:.*}", web::get().to(handlers::get_site_asset)),
+    )
+    .await;
+
+    // Over the cache threshold: streamed with the upstream length and never cached
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/sites/{}/assets/media/intro.mp4", program))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("content-length").unwrap(), &LARGE_BODY_BYTES.to_string());
+    assert_eq!(resp.headers().get("accept-ranges").unwrap(), "bytes");
+    assert_eq!(resp.headers().get("content-type").unwrap(), "video/mp4");
+    assert_eq!(test::read_body(resp).await.len(), LARGE_BODY_BYTES);
+    assert!(cache.get(&HephaestusCache::site_asset_key(&program, "media/intro.mp4")).await.is_none());
+
+    // A ranged request only asks the gateway for the slice it needs
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/sites/{}/assets/media/intro.mp4", program))
+        .insert_header(("Range", "bytes=2048-4095"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 206);
+    assert_eq!(resp.headers().get("content-range").unwrap(), &format!("bytes 2048-4095/{}", LARGE_BODY_BYTES));
+    assert_eq!(resp.headers().get("content-length").unwrap(), "2048");
+    assert_eq!(test::read_body(resp).await, body[2048..4096]);
+
+    let snapshot = metrics.get_metrics();
+    assert_eq!(snapshot.cache_bypasses, 2);
+    assert_eq!(snapshot.bytes_streamed, (LARGE_BODY_BYTES + 2048) as u64);
+
+    // Small files still go through the cache
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/sites/{}/assets/logo.svg", program))
+        .to_request();
+    assert_eq!(test::call_and_read_body(&app, req).await, "<svg/>");
+    assert!(cache.get(&HephaestusCache::site_asset_key(&program, "logo.svg")).await.is_some());
+    assert_eq!(metrics.get_metrics().cache_bypasses, 2);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/sites/{}/assets/../secrets", program))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
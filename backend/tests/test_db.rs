@@ -2,9 +2,10 @@
 mod tests {
     use mongodb::{Client, options::ClientOptions};
     use std::env;
-    use crate::db;
+    use shadow_backend::db;
 
     #[tokio::test]
+    #[ignore = "needs a MongoDB at DATABASE_URL"]
     async fn test_database_connection() {
         dotenv::dotenv().ok();
         
@@ -28,6 +29,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore = "needs a MongoDB at DATABASE_URL"]
     async fn test_create_user() {
         dotenv::dotenv().ok();
         
@@ -60,7 +62,7 @@ mod tests {
         let user = user.unwrap();
         assert_eq!(user.wallet_pubkey, test_wallet);
         assert_eq!(user.profile_cid, Some("test_cid_123".to_string()));
-        assert_eq!(user.is_public, true);
+        assert!(user.is_public);
         
         // Cleanup
         let collection = db.collection::<db::User>("users");
@@ -71,6 +73,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore = "needs a MongoDB at DATABASE_URL"]
     async fn test_create_site() {
         dotenv::dotenv().ok();
         
@@ -119,6 +122,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore = "needs a MongoDB at DATABASE_URL"]
     async fn test_search_users() {
         dotenv::dotenv().ok();
         
@@ -143,10 +147,10 @@ mod tests {
         ).await.expect("Failed to create user");
         
         // Test search
-        let results = db::search_users(&db, "test_search", 10).await
+        let (results, _) = db::search_users(&db, "test_search", 10, None).await
             .expect("Failed to search users");
         
-        assert!(results.len() > 0, "Should find at least one user");
+        assert!(!results.is_empty(), "Should find at least one user");
         
         // Cleanup
         let collection = db.collection::<db::User>("users");
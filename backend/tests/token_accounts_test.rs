@@ -0,0 +1,187 @@
+// Integration tests for reading a wallet's token accounts through SolanaClient
+
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use shadow_backend::solana::{SolanaClient, TOKEN_2022_PROGRAM_ID};
+use solana_sdk::program_option::COption;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use spl_token::state::{Account, AccountState, Mint};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+use wiremock::matchers::method;
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64, state: AccountState) -> Vec<u8> {
+    let mut data = vec![0; Account::LEN];
+    Account {
+        mint,
+        owner,
+        amount,
+        delegate: COption::None,
+        state,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    data
+}
+
+fn mint_account(decimals: u8) -> Vec<u8> {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::None,
+        supply: 1_000_000,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    data
+}
+
+fn ui_account(data: &[u8], owner: &str, lamports: u64) -> Value {
+    serde_json::json!({
+        "data": [general_purpose::STANDARD.encode(data), "base64"],
+        "executable": false,
+        "lamports": lamports,
+        "owner": owner,
+        "rentEpoch": 0,
+        "space": data.len(),
+    })
+}
+
+/// RPC stand-in serving token accounts per program and mints by address, recording mint lookups
+struct TokenRpc {
+    accounts: HashMap<String, Vec<(Pubkey, Vec<u8>)>>,
+    mints: HashMap<String, Vec<u8>>,
+    mint_lookups: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl Respond for TokenRpc {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        let context = serde_json::json!({ "slot": 1 });
+        let result = match body["method"].as_str().unwrap_or_default() {
+            "getVersion" => serde_json::json!({ "solana-core": "1.18.26", "feature-set": 0 }),
+            "getTokenAccountsByOwner" => {
+                assert_eq!(body["params"][2]["encoding"], "base64");
+                let program = body["params"][1]["programId"].as_str().unwrap();
+                let accounts: Vec<Value> = self.accounts.get(program).into_iter().flatten()
+                    .map(|(address, data)| serde_json::json!({
+                        "pubkey": address.to_string(),
+                        "account": ui_account(data, program, 2_039_280),
+                    }))
+                    .collect();
+                serde_json::json!({ "context": context, "value": accounts })
+            }
+            "getMultipleAccounts" => {
+                let requested: Vec<String> = serde_json::from_value(body["params"][0].clone()).unwrap();
+                self.mint_lookups.lock().unwrap().push(requested.clone());
+                let value: Vec<Value> = requested.iter()
+                    .map(|mint| self.mints.get(mint).map_or(Value::Null, |data| ui_account(data, &spl_token::id().to_string(), 1_461_600)))
+                    .collect();
+                serde_json::json!({ "context": context, "value": value })
+            }
+            other => panic!("unexpected RPC method {}", other),
+        };
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": result,
+            "id": body["id"]
+        }))
+    }
+}
+
+async fn token_rpc(rpc: TokenRpc) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(rpc).mount(&server).await;
+    server
+}
+
+#[tokio::test]
+async fn test_token_accounts_from_both_programs_with_batched_mints() {
+    let wallet = Pubkey::new_unique();
+    let (usdc, nft, extended) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let (usdc_a, usdc_b, nft_account, extended_account) =
+        (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+    // Token-2022 accounts and mints carry extensions after the base layout
+    let mut extended_data = token_account(extended, wallet, 42, AccountState::Initialized);
+    extended_data.extend_from_slice(&[2, 0, 0, 0, 0]);
+    let mut extended_mint = mint_account(9);
+    extended_mint.resize(Account::LEN + 1 + 8, 0);
+
+    let mint_lookups = Arc::new(Mutex::new(Vec::new()));
+    let server = token_rpc(TokenRpc {
+        accounts: HashMap::from([
+            (spl_token::id().to_string(), vec![
+                (usdc_a, token_account(usdc, wallet, 2_500_000, AccountState::Initialized)),
+                (usdc_b, token_account(usdc, wallet, 0, AccountState::Frozen)),
+                (nft_account, token_account(nft, wallet, 1, AccountState::Initialized)),
+            ]),
+            (TOKEN_2022_PROGRAM_ID.to_string(), vec![(extended_account, extended_data)]),
+        ]),
+        mints: HashMap::from([
+            (usdc.to_string(), mint_account(6)),
+            (nft.to_string(), mint_account(0)),
+            (extended.to_string(), extended_mint),
+        ]),
+        mint_lookups: Arc::clone(&mint_lookups),
+    })
+    .await;
+
+    let accounts = SolanaClient::new(server.uri()).get_token_accounts(&wallet).await.unwrap();
+    let by_address: HashMap<String, _> = accounts.iter().map(|a| (a.address.clone(), a)).collect();
+    assert_eq!(accounts.len(), 4);
+
+    let usdc_held = by_address[&usdc_a.to_string()];
+    assert_eq!(usdc_held.mint, usdc.to_string());
+    assert_eq!((usdc_held.amount, usdc_held.decimals), (2_500_000, 6));
+    assert_eq!(usdc_held.lamports, 2_039_280);
+    assert!(!usdc_held.is_frozen);
+    assert_eq!(usdc_held.token_program, spl_token::id().to_string());
+
+    assert!(by_address[&usdc_b.to_string()].is_frozen);
+    assert_eq!(by_address[&nft_account.to_string()].decimals, 0);
+
+    let extended_held = by_address[&extended_account.to_string()];
+    assert_eq!((extended_held.amount, extended_held.decimals), (42, 9));
+    assert_eq!(extended_held.token_program, TOKEN_2022_PROGRAM_ID);
+
+    // One lookup for all three distinct mints
+    let lookups = mint_lookups.lock().unwrap();
+    assert_eq!(lookups.len(), 1);
+    assert_eq!(lookups[0].len(), 3);
+}
+
+#[tokio::test]
+async fn test_token_accounts_fail_on_a_missing_mint() {
+    let wallet = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let server = token_rpc(TokenRpc {
+        accounts: HashMap::from([(
+            spl_token::id().to_string(),
+            vec![(Pubkey::new_unique(), token_account(mint, wallet, 5, AccountState::Initialized))],
+        )]),
+        mints: HashMap::new(),
+        mint_lookups: Arc::default(),
+    })
+    .await;
+
+    let err = SolanaClient::new(server.uri()).get_token_accounts(&wallet).await.unwrap_err();
+    assert!(err.contains(&mint.to_string()), "{}", err);
+}
+
+#[tokio::test]
+async fn test_wallet_without_token_accounts() {
+    let server = token_rpc(TokenRpc {
+        accounts: HashMap::new(),
+        mints: HashMap::new(),
+        mint_lookups: Arc::default(),
+    })
+    .await;
+    let accounts = SolanaClient::new(server.uri()).get_token_accounts(&Pubkey::new_unique()).await.unwrap();
+    assert!(accounts.is_empty());
+}
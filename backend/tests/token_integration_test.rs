@@ -0,0 +1,137 @@
+// Integration tests for Dionysus associated token account handling
+mod common;
+
+use actix_web::{test, web, App};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::wallet_handlers;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use wiremock::matchers::{body_partial_json, body_string_contains, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn rpc_result(value: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "result": value,
+        "id": 1
+    }))
+}
+
+fn token_account(owner: &str) -> Value {
+    serde_json::json!({
+        "context": { "slot": 1 },
+        "value": {
+            "data": ["", "base64"],
+            "executable": false,
+            "lamports": 2_039_280,
+            "owner": owner,
+            "rentEpoch": 0,
+            "space": 0
+        }
+    })
+}
+
+/// Mock RPC where `mint` is an SPL mint and `ata` exists only if `ata_exists`
+async fn token_rpc(mint: &Pubkey, ata: &Pubkey, ata_exists: bool) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(rpc_result(serde_json::json!({ "solana-core": "1.18.26", "feature-set": 0 })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getLatestBlockhash" })))
+        .respond_with(rpc_result(serde_json::json!({
+            "context": { "slot": 1 },
+            "value": { "blockhash": Pubkey::new_unique().to_string(), "lastValidBlockHeight": 100 }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_string_contains(mint.to_string()))
+        .respond_with(rpc_result(token_account(&spl_token::id().to_string())))
+        .mount(&server)
+        .await;
+    let ata_value = if ata_exists {
+        token_account(&spl_token::id().to_string())
+    } else {
+        serde_json::json!({ "context": { "slot": 1 }, "value": null })
+    };
+    Mock::given(method("POST"))
+        .and(body_string_contains(ata.to_string()))
+        .respond_with(rpc_result(ata_value))
+        .mount(&server)
+        .await;
+    server
+}
+
+macro_rules! token_app {
+    ($rpc_url:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(common::offline_db().await))
+                .app_data(web::Data::new($rpc_url))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/wallet/tokens/ensure-ata", web::post().to(wallet_handlers::ensure_ata)),
+        )
+        .await
+    };
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_missing_ata_returns_creation_transaction() {
+    let sender = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let ata = spl_associated_token_account::get_associated_token_address(&recipient, &mint);
+    let rpc = token_rpc(&mint, &ata, false).await;
+    let app = token_app!(rpc.uri());
+
+    let req = test::TestRequest::post()
+        .uri("/api/wallet/tokens/ensure-ata")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&sender)))
+        .set_json(serde_json::json!({ "wallet_pubkey": recipient.to_string(), "mint": mint.to_string() }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["ata_address"], ata.to_string());
+    assert_eq!(body["exists"], false);
+
+    let tx_bytes = general_purpose::STANDARD
+        .decode(body["creation_transaction"].as_str().expect("transaction returned"))
+        .unwrap();
+    let tx: Transaction = bincode::deserialize(&tx_bytes).unwrap();
+    assert_eq!(tx.message.account_keys[0], sender.pubkey(), "caller pays for the ATA");
+    let program = tx.message.account_keys[tx.message.instructions[0].program_id_index as usize];
+    assert_eq!(program, spl_associated_token_account::id());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_existing_ata_needs_no_transaction() {
+    let recipient = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let ata = spl_associated_token_account::get_associated_token_address(&recipient, &mint);
+    let rpc = token_rpc(&mint, &ata, true).await;
+    let app = token_app!(rpc.uri());
+
+    let req = test::TestRequest::post()
+        .uri("/api/wallet/tokens/ensure-ata")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&Keypair::new())))
+        .set_json(serde_json::json!({ "wallet_pubkey": recipient.to_string(), "mint": mint.to_string() }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["exists"], true);
+    assert!(body["creation_transaction"].is_null());
+}
+
+#[tokio::test]
+async fn test_ensure_ata_requires_auth() {
+    let app = token_app!("http://127.0.0.1:1".to_string());
+    let req = test::TestRequest::post()
+        .uri("/api/wallet/tokens/ensure-ata")
+        .set_json(serde_json::json!({ "wallet_pubkey": Pubkey::new_unique().to_string(), "mint": Pubkey::new_unique().to_string() }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
@@ -0,0 +1,198 @@
+// Integration tests for Poseidon broadcasting signed transactions and waiting for confirmation
+mod common;
+
+use actix_web::{test, web, App};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::argus::OriginRisk;
+use shadow_backend::poseidon::{BroadcastOptions, PoseidonTransactionManager, TransactionStatus};
+use shadow_backend::wallet_handlers;
+use shadow_backend::zeus::ZeusWalletManager;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use std::sync::Arc;
+use std::time::Duration;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const OPTIONS: BroadcastOptions = BroadcastOptions {
+    timeout: Duration::from_secs(5),
+    initial_backoff: Duration::from_millis(10),
+};
+
+fn rpc_result(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+/// Mock RPC answering the version check the client makes before sending
+async fn rpc_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(rpc_result(serde_json::json!({ "solana-core": "1.18.26", "feature-set": 0 })))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// sendTransaction accepts `signed` and answers with its signature
+async fn mount_send(rpc: &MockServer, signed: &Transaction) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "sendTransaction" })))
+        .respond_with(rpc_result(serde_json::json!(signed.signatures[0].to_string())))
+        .expect(1)
+        .mount(rpc)
+        .await;
+}
+
+/// getSignatureStatuses answers `status` (null while the cluster hasn't seen it), `times` times
+/// or for good when None
+async fn mount_status(rpc: &MockServer, status: Value, times: Option<u64>) {
+    let mock = Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getSignatureStatuses" })))
+        .respond_with(rpc_result(serde_json::json!({ "context": { "slot": 1 }, "value": [status] })));
+    match times {
+        Some(times) => mock.up_to_n_times(times).mount(rpc).await,
+        None => mock.mount(rpc).await,
+    }
+}
+
+fn status(confirmation: &str, err: Value) -> Value {
+    serde_json::json!({ "slot": 1, "confirmations": null, "err": err, "status": { "Ok": null }, "confirmationStatus": confirmation })
+}
+
+/// The requested transaction and the same transaction signed by `payer`
+fn transfer(payer: &Keypair) -> (Transaction, Transaction) {
+    let blockhash = Hash::new_unique();
+    let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000_000);
+    let requested = Transaction::new_unsigned(Message::new_with_blockhash(&[ix], Some(&payer.pubkey()), &blockhash));
+    let mut signed = requested.clone();
+    signed.sign(&[payer], blockhash);
+    (requested, signed)
+}
+
+#[tokio::test]
+async fn test_broadcast_waits_for_confirmation() {
+    let rpc = rpc_server().await;
+    let (_, signed) = transfer(&Keypair::new());
+    mount_send(&rpc, &signed).await;
+    // Not seen yet, then processed, then confirmed
+    mount_status(&rpc, Value::Null, Some(1)).await;
+    mount_status(&rpc, status("processed", Value::Null), Some(1)).await;
+    mount_status(&rpc, status("confirmed", Value::Null), None).await;
+
+    let outcome = PoseidonTransactionManager::broadcast(&rpc.uri(), &signed, &OPTIONS).await;
+    assert_eq!(outcome.signature, Some(signed.signatures[0].to_string()));
+    assert_eq!(outcome.error, None);
+
+    let polls = rpc.received_requests().await.unwrap().into_iter()
+        .filter(|r| String::from_utf8_lossy(&r.body).contains("getSignatureStatuses"))
+        .count();
+    assert_eq!(polls, 3);
+}
+
+#[tokio::test]
+async fn test_broadcast_reports_failed_transaction() {
+    let rpc = rpc_server().await;
+    let (_, signed) = transfer(&Keypair::new());
+    mount_send(&rpc, &signed).await;
+    mount_status(&rpc, status("confirmed", serde_json::json!({ "InstructionError": [0, { "Custom": 1 }] })), None).await;
+
+    let outcome = PoseidonTransactionManager::broadcast(&rpc.uri(), &signed, &OPTIONS).await;
+    assert_eq!(outcome.signature, Some(signed.signatures[0].to_string()));
+    let error = outcome.error.unwrap();
+    assert!(error.contains("Transaction failed"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_broadcast_gives_up_after_timeout() {
+    let rpc = rpc_server().await;
+    let (_, signed) = transfer(&Keypair::new());
+    mount_send(&rpc, &signed).await;
+    mount_status(&rpc, Value::Null, None).await;
+
+    let options = BroadcastOptions { timeout: Duration::from_millis(100), ..OPTIONS };
+    let outcome = PoseidonTransactionManager::broadcast(&rpc.uri(), &signed, &options).await;
+    assert!(outcome.signature.is_some());
+    let error = outcome.error.unwrap();
+    assert!(error.contains("not confirmed"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_broadcast_reports_rejected_send() {
+    let rpc = rpc_server().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "sendTransaction" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": -32002, "message": "Transaction simulation failed: Blockhash not found" }
+        })))
+        .mount(&rpc)
+        .await;
+    let (_, signed) = transfer(&Keypair::new());
+
+    let outcome = PoseidonTransactionManager::broadcast(&rpc.uri(), &signed, &OPTIONS).await;
+    assert_eq!(outcome.signature, None);
+    let error = outcome.error.unwrap();
+    assert!(error.contains("Blockhash not found"), "{}", error);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_sign_with_broadcast_records_on_chain_signature() {
+    let db = common::test_db().await;
+    let rpc = rpc_server().await;
+    let user = Keypair::new();
+    let user_id = user.pubkey().to_string();
+    let payer = Keypair::new();
+    let (requested, signed) = transfer(&payer);
+    mount_send(&rpc, &signed).await;
+    mount_status(&rpc, status("finalized", Value::Null), None).await;
+
+    let wallet = ZeusWalletManager::new(Arc::new(db.clone()), rpc.uri())
+        .import_wallet(&user_id, "Main", &bs58::encode(payer.to_bytes()).into_string(), "hunter2")
+        .await
+        .unwrap();
+    let poseidon = PoseidonTransactionManager::new(Arc::new(db.clone()));
+    let encoded = general_purpose::STANDARD.encode(bincode::serialize(&requested).unwrap());
+    let id = poseidon
+        .create_transaction(&user_id, &wallet.id, "https://dapp.example", &encoded, None, OriginRisk::Verified)
+        .await
+        .unwrap()
+        .id;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(rpc.uri()))
+            .app_data(web::Data::new(common::test_config()))
+            .app_data(web::Data::new(AresAuth::new()))
+            .route("/api/wallet/transaction/sign", web::post().to(wallet_handlers::sign_transaction)),
+    )
+    .await;
+    let sign = |password: &str| {
+        test::TestRequest::post()
+            .uri("/api/wallet/transaction/sign")
+            .insert_header(("X-Shadow-Auth", common::auth_header(&user)))
+            .set_json(serde_json::json!({ "transaction_id": &id, "password": password, "broadcast": true }))
+            .to_request()
+    };
+
+    assert_eq!(test::call_service(&app, sign("wrong")).await.status(), 400);
+    let body: Value = test::call_and_read_body_json(&app, sign("hunter2")).await;
+    assert_eq!(body["status"], "signed");
+    assert_eq!(body["on_chain_signature"], signed.signatures[0].to_string());
+    assert!(body["error"].is_null());
+
+    let stored = poseidon.get_pending_transaction(&id, &user_id).await.unwrap().unwrap();
+    assert_eq!(stored.status, TransactionStatus::Signed);
+    assert_eq!(stored.on_chain_signature, Some(signed.signatures[0].to_string()));
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
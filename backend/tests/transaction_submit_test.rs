@@ -0,0 +1,218 @@
+// Integration tests for Poseidon's pre-flight checks on transaction submission
+mod common;
+
+use actix_web::{test, web, App};
+use base64::{engine::general_purpose, Engine as _};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::argus::OriginRisk;
+use shadow_backend::poseidon::{PoseidonTransactionManager, PreflightBlock};
+use shadow_backend::wallet_handlers;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use std::sync::Arc;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const SIGNATURE: &str = "5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp8uirBgmQpjKhoR4tjF3ZpRzrFmBV6UjKdiSZkQUW";
+
+fn rpc_result(result: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+}
+
+/// Simulation outcome for every simulateTransaction call: an error, or the fee payer's balance after it
+async fn mount_simulation(rpc: &MockServer, err: Option<Value>, post_lamports: u64) {
+    let value = match err {
+        Some(err) => serde_json::json!({ "err": err, "logs": ["Program log: insufficient funds"], "accounts": null }),
+        None => serde_json::json!({ "err": null, "logs": [], "accounts": [{ "lamports": post_lamports, "owner": "11111111111111111111111111111111", "data": ["", "base64"], "executable": false, "rentEpoch": 0 }] }),
+    };
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "simulateTransaction" })))
+        .respond_with(rpc_result(serde_json::json!({ "context": { "slot": 1 }, "value": value })))
+        .mount(rpc)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getBalance" })))
+        .respond_with(rpc_result(serde_json::json!({ "context": { "slot": 1 }, "value": post_lamports })))
+        .mount(rpc)
+        .await;
+}
+
+async fn mount_send(rpc: &MockServer, expected_calls: u64) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "sendTransaction" })))
+        .respond_with(rpc_result(serde_json::json!(SIGNATURE)))
+        .expect(expected_calls)
+        .mount(rpc)
+        .await;
+}
+
+/// The requested transaction and the same transaction signed by `payer`
+fn transfer(payer: &Keypair) -> (Transaction, Transaction) {
+    let blockhash = Hash::new_unique();
+    let ix = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000_000);
+    let requested = Transaction::new_unsigned(Message::new_with_blockhash(&[ix], Some(&payer.pubkey()), &blockhash));
+    let mut signed = requested.clone();
+    signed.sign(&[payer], blockhash);
+    (requested, signed)
+}
+
+fn encode(tx: &Transaction) -> String {
+    general_purpose::STANDARD.encode(bincode::serialize(tx).unwrap())
+}
+
+#[tokio::test]
+async fn test_preflight_reports_failed_simulation() {
+    let rpc = MockServer::start().await;
+    mount_simulation(&rpc, Some(serde_json::json!({ "InstructionError": [0, { "Custom": 1 }] })), 20_000_000).await;
+    let (_, signed) = transfer(&Keypair::new());
+
+    let mut check = PoseidonTransactionManager::preflight(&rpc.uri(), &signed, 1_000_000).await.unwrap();
+    assert!(check.simulation_error.as_deref().unwrap().contains("InstructionError"));
+    assert_eq!(check.simulation_logs, vec!["Program log: insufficient funds"]);
+    // Balance comes from getBalance when simulation has no account state, less the fee
+    assert_eq!(check.post_balance_lamports, 20_000_000 - 5_000);
+    assert_eq!(check.blocker(), Some(PreflightBlock::SimulationFailed));
+
+    check.simulation_overridden = true;
+    assert_eq!(check.blocker(), None);
+}
+
+#[tokio::test]
+async fn test_preflight_flags_balance_below_reserve() {
+    let rpc = MockServer::start().await;
+    mount_simulation(&rpc, None, 900_000).await;
+    let payer = Keypair::new();
+    let (_, signed) = transfer(&payer);
+
+    let mut check = PoseidonTransactionManager::preflight(&rpc.uri(), &signed, 1_000_000).await.unwrap();
+    assert_eq!(check.fee_payer, payer.pubkey().to_string());
+    assert_eq!(check.simulation_error, None);
+    assert_eq!(check.post_balance_lamports, 895_000);
+    assert_eq!(check.blocker(), Some(PreflightBlock::LowBalance));
+
+    check.low_balance_acknowledged = true;
+    assert_eq!(check.blocker(), None);
+    let check = PoseidonTransactionManager::preflight(&rpc.uri(), &signed, 500_000).await.unwrap();
+    assert_eq!(check.blocker(), None);
+}
+
+macro_rules! submit_app {
+    ($db:expr, $rpc:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new($rpc.uri()))
+                .app_data(web::Data::new(common::test_config()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/wallet/transaction/submit", web::post().to(wallet_handlers::submit_transaction))
+                .route("/api/wallet/security-settings", web::put().to(wallet_handlers::update_security_settings)),
+        )
+        .await
+    };
+}
+
+async fn queue(db: &mongodb::Database, user: &Keypair, requested: &Transaction) -> String {
+    PoseidonTransactionManager::new(Arc::new(db.clone()))
+        .create_transaction(&user.pubkey().to_string(), "wallet-1", "https://dapp.example", &encode(requested), None, OriginRisk::Verified)
+        .await
+        .unwrap()
+        .id
+}
+
+fn submit(user: &Keypair, id: &str, signed: &Transaction, overrides: Value) -> test::TestRequest {
+    let mut body = serde_json::json!({ "transaction_id": id, "signed_transaction": encode(signed) });
+    body.as_object_mut().unwrap().extend(overrides.as_object().unwrap().clone());
+    test::TestRequest::post()
+        .uri("/api/wallet/transaction/submit")
+        .insert_header(("X-Shadow-Auth", common::auth_header(user)))
+        .set_json(body)
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_failed_simulation_blocks_until_overridden() {
+    let db = common::test_db().await;
+    let rpc = MockServer::start().await;
+    mount_simulation(&rpc, Some(serde_json::json!("BlockhashNotFound")), 50_000_000).await;
+    mount_send(&rpc, 1).await;
+    let app = submit_app!(db, rpc);
+    let user = Keypair::new();
+    let (requested, signed) = transfer(&user);
+    let id = queue(&db, &user, &requested).await;
+
+    // A different signed transaction isn't the one the user reviewed
+    let (_, other) = transfer(&user);
+    let resp = test::call_service(&app, submit(&user, &id, &other, serde_json::json!({})).to_request()).await;
+    assert_eq!(resp.status(), 400);
+
+    let resp = test::call_service(&app, submit(&user, &id, &signed, serde_json::json!({})).to_request()).await;
+    assert_eq!(resp.status(), 409);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["status"], "blocked");
+    assert_eq!(body["reason"], "simulation_failed");
+
+    let req = submit(&user, &id, &signed, serde_json::json!({ "allow_simulation_failure": true })).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "submitted");
+    assert_eq!(body["signature"], SIGNATURE);
+    assert_eq!(body["preflight"]["simulation_overridden"], true);
+
+    let stored = db.collection::<Document>("pending_transactions").find_one(doc! { "_id": &id }, None).await.unwrap().unwrap();
+    assert_eq!(stored.get_str("status").unwrap(), "submitted");
+    assert!(stored.get_document("preflight").unwrap().get_str("simulation_error").unwrap().contains("BlockhashNotFound"));
+    let events = db.collection::<Document>("transaction_events").count_documents(doc! { "transaction_id": &id }, None).await.unwrap();
+    assert_eq!(events, 3);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_low_balance_needs_acknowledgement() {
+    let db = common::test_db().await;
+    let rpc = MockServer::start().await;
+    mount_simulation(&rpc, None, 300_000).await;
+    mount_send(&rpc, 2).await;
+    let app = submit_app!(db, rpc);
+    let user = Keypair::new();
+    let (requested, signed) = transfer(&user);
+    let id = queue(&db, &user, &requested).await;
+
+    let resp = test::call_service(&app, submit(&user, &id, &signed, serde_json::json!({})).to_request()).await;
+    assert_eq!(resp.status(), 409);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["reason"], "low_balance");
+    assert_eq!(body["preflight"]["post_balance_lamports"], 295_000);
+
+    let req = submit(&user, &id, &signed, serde_json::json!({ "acknowledge_low_balance": true })).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["status"], "submitted");
+    assert_eq!(body["preflight"]["low_balance_acknowledged"], true);
+
+    // A user who lowers their own reserve isn't asked
+    let req = test::TestRequest::put()
+        .uri("/api/wallet/security-settings")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&user)))
+        .set_json(serde_json::json!({
+            "require_password_for_transactions": true,
+            "require_password_for_export": true,
+            "session_timeout_minutes": 15,
+            "biometric_enabled": false,
+            "min_reserve_lamports": 100_000,
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let (requested, signed) = transfer(&user);
+    let id = queue(&db, &user, &requested).await;
+    let body: Value = test::call_and_read_body_json(&app, submit(&user, &id, &signed, serde_json::json!({})).to_request()).await;
+    assert_eq!(body["status"], "submitted");
+    assert_eq!(body["preflight"]["reserve_lamports"], 100_000);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,138 @@
+// Reading a program's upgrade authority from its ProgramData account
+
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use shadow_backend::solana::{program_data_address, upgrade_authority, SolanaClient};
+use solana_sdk::bpf_loader_upgradeable;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Upgradeable loader `Program` state: u32 variant 2, then the ProgramData address
+fn program_account(programdata: &Pubkey) -> Vec<u8> {
+    let mut data = 2u32.to_le_bytes().to_vec();
+    data.extend_from_slice(programdata.as_ref());
+    data
+}
+
+/// Upgradeable loader `ProgramData` state: u32 variant 3, the deploy slot, the optional
+/// authority, then the program's ELF
+fn programdata_account(authority: Option<&Pubkey>) -> Vec<u8> {
+    let mut data = 3u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&250_000_000u64.to_le_bytes());
+    match authority {
+        Some(authority) => {
+            data.push(1);
+            data.extend_from_slice(authority.as_ref());
+        }
+        // Immutable programs still reserve the authority's bytes
+        None => data.extend_from_slice(&[0; 33]),
+    }
+    data.extend_from_slice(b"\x7fELF");
+    data.extend_from_slice(&[0; 64]);
+    data
+}
+
+fn programdata_pda(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+#[test]
+fn test_program_data_address_from_crafted_bytes() {
+    let program = Pubkey::new_unique();
+    let programdata = programdata_pda(&program);
+    assert_eq!(program_data_address(&program, &program_account(&programdata)).unwrap(), programdata);
+
+    // Pointing anywhere but the derived address is not something the loader writes
+    let err = program_data_address(&program, &program_account(&Pubkey::new_unique())).unwrap_err();
+    assert!(err.contains("expected"), "{}", err);
+
+    // A ProgramData or buffer account is not a program
+    assert!(program_data_address(&program, &programdata_account(None)).is_err());
+    assert!(program_data_address(&program, &[2, 0, 0]).is_err());
+}
+
+#[test]
+fn test_upgrade_authority_from_crafted_bytes() {
+    let authority = Pubkey::new_unique();
+    assert_eq!(upgrade_authority(&programdata_account(Some(&authority))).unwrap(), Some(authority));
+    assert_eq!(upgrade_authority(&programdata_account(None)).unwrap(), None);
+
+    let programdata = programdata_pda(&Pubkey::new_unique());
+    assert!(upgrade_authority(&program_account(&programdata)).is_err());
+    assert!(upgrade_authority(&programdata_account(Some(&authority))[..20]).is_err());
+}
+
+/// RPC stand-in serving accounts by address; unknown ones don't exist
+struct AccountRpc {
+    accounts: HashMap<String, (Vec<u8>, Pubkey, bool)>,
+}
+
+impl Respond for AccountRpc {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        let result = match body["method"].as_str().unwrap_or_default() {
+            "getVersion" => serde_json::json!({ "solana-core": "1.18.26", "feature-set": 0 }),
+            "getAccountInfo" => {
+                let address = body["params"][0].as_str().unwrap();
+                let value = self.accounts.get(address).map_or(Value::Null, |(data, owner, executable)| {
+                    serde_json::json!({
+                        "data": [general_purpose::STANDARD.encode(data), "base64"],
+                        "executable": executable,
+                        "lamports": 1_141_440,
+                        "owner": owner.to_string(),
+                        "rentEpoch": 0,
+                        "space": data.len(),
+                    })
+                });
+                serde_json::json!({ "context": { "slot": 1 }, "value": value })
+            }
+            other => panic!("unexpected RPC method {}", other),
+        };
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": result,
+            "id": body["id"]
+        }))
+    }
+}
+
+// The blocking RPC client needs a multi-threaded runtime to run inside
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upgrade_authority_and_ownership_over_rpc() {
+    let loader = bpf_loader_upgradeable::id();
+    let authority = Pubkey::new_unique();
+    let (upgradeable, immutable, closed, legacy, wallet) =
+        (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+    let mut accounts = HashMap::new();
+    for program in [upgradeable, immutable, closed] {
+        accounts.insert(program.to_string(), (program_account(&programdata_pda(&program)), loader, true));
+    }
+    accounts.insert(programdata_pda(&upgradeable).to_string(), (programdata_account(Some(&authority)), loader, false));
+    accounts.insert(programdata_pda(&immutable).to_string(), (programdata_account(None), loader, false));
+    accounts.insert(legacy.to_string(), (b"\x7fELF".to_vec(), solana_sdk::bpf_loader::id(), true));
+    accounts.insert(wallet.to_string(), (Vec::new(), solana_sdk::system_program::id(), false));
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).respond_with(AccountRpc { accounts }).mount(&server).await;
+    let solana = SolanaClient::new(server.uri());
+
+    let authority_of = |program: &Pubkey| solana.get_program_upgrade_authority(&program.to_string()).unwrap();
+    assert_eq!(authority_of(&upgradeable), Some(authority.to_string()));
+    assert_eq!(authority_of(&immutable), None);
+    assert_eq!(authority_of(&closed), None);
+    assert_eq!(authority_of(&legacy), None);
+    assert_eq!(authority_of(&wallet), None);
+    assert!(solana.get_program_upgrade_authority(&Pubkey::new_unique().to_string()).is_err());
+
+    // Ownership goes by the upgrade authority, not by which loader owns the account
+    let owns = |program: &Pubkey, owner: &Pubkey| solana.verify_program_ownership(&program.to_string(), &owner.to_string()).unwrap();
+    assert!(owns(&upgradeable, &authority));
+    assert!(!owns(&upgradeable, &loader));
+    assert!(!owns(&upgradeable, &wallet));
+    assert!(!owns(&immutable, &authority));
+    assert!(!owns(&legacy, &solana_sdk::bpf_loader::id()));
+    assert!(solana.verify_program_ownership(&upgradeable.to_string(), "not-a-key").is_err());
+}
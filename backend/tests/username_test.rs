@@ -0,0 +1,130 @@
+// Integration tests for looking up profiles by username
+mod common;
+
+use actix_web::{test, web, App};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use shadow_backend::anchor_client::AnchorClient;
+use shadow_backend::db;
+use shadow_backend::handlers;
+use shadow_backend::metrics::MetricsCollector;
+use solana_sdk::pubkey::Pubkey;
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn username_record(wallet: &Pubkey, name: &str) -> Vec<u8> {
+    let mut data = Sha256::digest(b"account:UsernameRecord")[..8].to_vec();
+    data.extend_from_slice(wallet.as_ref());
+    data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    data.extend_from_slice(name.as_bytes());
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data
+}
+
+fn account_info(value: Option<(Vec<u8>, &Pubkey)>) -> ResponseTemplate {
+    let value = value.map(|(data, owner)| serde_json::json!({
+        "data": [general_purpose::STANDARD.encode(&data), "base64"],
+        "executable": false,
+        "lamports": 1_000_000,
+        "owner": owner.to_string(),
+        "rentEpoch": 0,
+        "space": data.len(),
+    }));
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "jsonrpc": "2.0",
+        "result": { "context": { "slot": 1 }, "value": value },
+        "id": 1
+    }))
+}
+
+/// Mock RPC with the username record for `name` held by `wallet`, and no other records
+async fn rpc_with_username(anchor: &AnchorClient, name: &str, wallet: &Pubkey) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getVersion" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": { "solana-core": "1.18.26", "feature-set": 0 },
+            "id": 1
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "method": "getAccountInfo",
+            "params": [anchor.username_pda(name).to_string()]
+        })))
+        .respond_with(account_info(Some((username_record(wallet, name), anchor.profiles_program_id()))))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({ "method": "getAccountInfo" })))
+        .respond_with(account_info(None))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[actix_web::test]
+async fn test_unclaimed_and_malformed_usernames() {
+    let profiles = Pubkey::new_unique();
+    let placeholder = AnchorClient::with_programs(String::new(), Pubkey::new_unique(), profiles);
+    let rpc = rpc_with_username(&placeholder, "taken", &Pubkey::new_unique()).await;
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(common::offline_db().await))
+            .app_data(web::Data::new(AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), profiles)))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/profiles/{wallet}", web::get().to(handlers::get_profile)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/api/profiles/nobody_here").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+    for bad in ["ab", "sha-de", "not%20valid"] {
+        let req = test::TestRequest::get().uri(&format!("/api/profiles/{}", bad)).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400, "{}", bad);
+    }
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_profile_by_username_matches_profile_by_wallet() {
+    let db = common::test_db().await;
+    let wallet = Pubkey::new_unique();
+    db::create_or_update_user(&db, &wallet.to_string(), Some("ipfs://bafyprofile"), true)
+        .await
+        .unwrap();
+
+    let profiles = Pubkey::new_unique();
+    let placeholder = AnchorClient::with_programs(String::new(), Pubkey::new_unique(), profiles);
+    let rpc = rpc_with_username(&placeholder, "shade", &wallet).await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db.clone()))
+            .app_data(web::Data::new(AnchorClient::with_programs(rpc.uri(), Pubkey::new_unique(), profiles)))
+            .app_data(web::Data::new(MetricsCollector::new()))
+            .route("/api/profiles/{wallet}", web::get().to(handlers::get_profile)),
+    )
+    .await;
+
+    let by_wallet: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get().uri(&format!("/api/profiles/{}", wallet)).to_request(),
+    )
+    .await;
+    // Usernames are case-insensitive
+    let by_name: Value = test::call_and_read_body_json(
+        &app,
+        test::TestRequest::get().uri("/api/profiles/Shade").to_request(),
+    )
+    .await;
+    assert_eq!(by_name, by_wallet);
+    assert_eq!(by_name["wallet_pubkey"], wallet.to_string());
+    assert_eq!(by_name["exists"], true);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
@@ -0,0 +1,76 @@
+// Tests for wallet token account cleanup planning and transaction chunking
+use shadow_backend::dionysus::{build_cleanup_transactions, plan_cleanup, CleanupExclusion, SIGNATURE_FEE_LAMPORTS};
+use shadow_backend::solana::TokenAccountInfo;
+use solana_sdk::hash::Hash;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+
+const RENT_LAMPORTS: u64 = 2_039_280;
+
+fn account(amount: u64, decimals: u8, is_frozen: bool) -> TokenAccountInfo {
+    TokenAccountInfo {
+        address: Pubkey::new_unique().to_string(),
+        mint: Pubkey::new_unique().to_string(),
+        amount,
+        decimals,
+        lamports: RENT_LAMPORTS,
+        is_frozen,
+        token_program: spl_token::id().to_string(),
+    }
+}
+
+#[test]
+fn test_only_empty_unfrozen_accounts_are_candidates() {
+    let wallet = Pubkey::new_unique();
+    let empty = account(0, 6, false);
+    let nft = account(1, 0, false);
+    let frozen = account(0, 6, true);
+    let funded = account(500, 6, false);
+    let accounts = vec![empty.clone(), nft.clone(), frozen.clone(), funded.clone()];
+
+    let plan = plan_cleanup(&wallet, accounts).unwrap();
+
+    assert_eq!(plan.candidates.len(), 1);
+    assert_eq!(plan.candidates[0].address, empty.address);
+    let reasons: Vec<(String, CleanupExclusion)> =
+        plan.excluded.iter().map(|e| (e.address.clone(), e.reason)).collect();
+    assert_eq!(reasons, vec![
+        (nft.address, CleanupExclusion::HoldsNft),
+        (frozen.address, CleanupExclusion::Frozen),
+        (funded.address, CleanupExclusion::NonZeroBalance),
+    ]);
+
+    assert_eq!(plan.recoverable_lamports, RENT_LAMPORTS);
+    assert_eq!(plan.transaction_count, 1);
+    assert_eq!(plan.net_reclaimed_lamports, RENT_LAMPORTS - SIGNATURE_FEE_LAMPORTS);
+}
+
+#[test]
+fn test_nothing_to_close_costs_nothing() {
+    let plan = plan_cleanup(&Pubkey::new_unique(), vec![account(1, 0, false)]).unwrap();
+    assert!(plan.candidates.is_empty());
+    assert_eq!(plan.transaction_count, 0);
+    assert_eq!(plan.estimated_fee_lamports, 0);
+    assert_eq!(plan.net_reclaimed_sol, 0.0);
+}
+
+#[test]
+fn test_many_closes_are_chunked_under_packet_size() {
+    let wallet = Pubkey::new_unique();
+    let accounts = (0..40).map(|_| account(0, 9, false)).collect();
+    let plan = plan_cleanup(&wallet, accounts).unwrap();
+    assert_eq!(plan.candidates.len(), 40);
+
+    let transactions = build_cleanup_transactions(&wallet, &plan.candidates, Hash::new_unique()).unwrap();
+    assert!(transactions.len() > 1);
+    assert_eq!(transactions.len(), plan.transaction_count);
+    assert_eq!(plan.estimated_fee_lamports, transactions.len() as u64 * SIGNATURE_FEE_LAMPORTS);
+
+    let mut closed = 0;
+    for tx in &transactions {
+        assert!(bincode::serialized_size(tx).unwrap() as usize <= PACKET_DATA_SIZE);
+        assert_eq!(tx.message.account_keys[0], wallet);
+        closed += tx.message.instructions.len();
+    }
+    assert_eq!(closed, 40);
+}
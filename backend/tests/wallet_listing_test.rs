@@ -0,0 +1,255 @@
+// Integration tests for filtered, cursor-paginated wallet transaction and connection lists
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::argus::{self, ArgusReputation};
+use shadow_backend::ares::AresAuth;
+use shadow_backend::hestia::HestiaConnectionManager;
+use shadow_backend::poseidon::PoseidonTransactionManager;
+use shadow_backend::wallet_handlers;
+use solana_sdk::signature::{Keypair, Signer};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+const STATUSES: [&str; 5] = ["pending", "approved", "signed", "rejected", "expired"];
+const ORIGINS: [&str; 3] = ["https://swap.test", "https://mint.test", "https://game.test"];
+const BASE_MS: i64 = 1_700_000_000_000;
+
+macro_rules! wallet_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .app_data(web::Data::new(ArgusReputation::new(Arc::new($db.clone()), &argus::default_seeds())))
+                .route("/api/wallet/transactions", web::get().to(wallet_handlers::get_transactions))
+                .route("/api/wallet/transactions/pending", web::get().to(wallet_handlers::get_pending_transactions))
+                .route("/api/wallet/dapp/connections", web::get().to(wallet_handlers::get_connections))
+                .route("/api/wallet/dapp/disconnect", web::post().to(wallet_handlers::disconnect_dapp)),
+        )
+        .await
+    };
+}
+
+fn get(wallet: &Keypair, uri: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(uri)
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+}
+
+fn transaction_doc(user_id: &str, i: i64) -> Document {
+    let at = mongodb::bson::DateTime::from_millis(BASE_MS + i * 1000);
+    doc! {
+        "_id": format!("tx-{:03}", i),
+        "user_id": user_id,
+        "wallet_id": "wallet-1",
+        "dapp_origin": ORIGINS[i as usize % ORIGINS.len()],
+        "transaction_data": "",
+        "message": null,
+        "status": STATUSES[i as usize % STATUSES.len()],
+        "risk": "unknown",
+        "created_at": at,
+        "updated_at": at,
+    }
+}
+
+/// 60 transactions, one second apart, cycling through statuses and origins
+async fn seed_transactions(db: &mongodb::Database, user_id: &str) {
+    let docs: Vec<Document> = (0..60).map(|i| transaction_doc(user_id, i)).collect();
+    db.collection::<Document>("pending_transactions").insert_many(docs, None).await.unwrap();
+}
+
+fn ids(body: &Value) -> Vec<String> {
+    body["items"].as_array().unwrap().iter().map(|tx| tx["id"].as_str().unwrap().to_string()).collect()
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_transaction_filters_combine() {
+    let db = common::test_db().await;
+    let app = wallet_app!(db);
+    let wallet = Keypair::new();
+    seed_transactions(&db, &wallet.pubkey().to_string()).await;
+
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/wallet/transactions").to_request()).await;
+    assert_eq!(body["total"], 60);
+    assert_eq!(ids(&body)[0], "tx-059");
+
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/wallet/transactions/pending").to_request()).await;
+    assert_eq!(body["total"], 12);
+    assert!(body["items"].as_array().unwrap().iter().all(|tx| tx["status"] == "pending"));
+
+    // i % 5 == 2 and i % 3 == 1 → i ≡ 7 (mod 15): 7, 22, 37, 52
+    let uri = "/api/wallet/transactions?status=signed&dapp_origin=https://mint.test";
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, uri).to_request()).await;
+    assert_eq!(ids(&body), vec!["tx-052", "tx-037", "tx-022", "tx-007"]);
+
+    let uri = "/api/wallet/transactions?status=signed&dapp_origin=https://mint.test\
+               &created_after=2023-11-14T22:13:30Z&created_before=2023-11-14T22:14:00Z";
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, uri).to_request()).await;
+    assert_eq!(ids(&body), vec!["tx-037", "tx-022"]);
+    assert_eq!(body["total"], 2);
+
+    // Pending endpoint ignores a conflicting status filter
+    let uri = "/api/wallet/transactions/pending?status=rejected&dapp_origin=https://swap.test";
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, uri).to_request()).await;
+    assert!(body["items"].as_array().unwrap().iter().all(|tx| tx["status"] == "pending"));
+    assert_eq!(body["total"], 4);
+
+    // Another user's transactions never leak in
+    let body: Value = test::call_and_read_body_json(&app, get(&Keypair::new(), "/api/wallet/transactions").to_request()).await;
+    assert_eq!(body["total"], 0);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_transaction_cursor_is_stable_across_inserts() {
+    let db = common::test_db().await;
+    let app = wallet_app!(db);
+    let wallet = Keypair::new();
+    let user_id = wallet.pubkey().to_string();
+    seed_transactions(&db, &user_id).await;
+
+    let mut seen = Vec::new();
+    let mut uri = "/api/wallet/transactions?limit=25".to_string();
+    loop {
+        let body: Value = test::call_and_read_body_json(&app, get(&wallet, &uri).to_request()).await;
+        seen.extend(ids(&body));
+        if seen.len() == 25 {
+            // Newer requests arriving mid-walk land before the cursor, not in later pages
+            let newer: Vec<Document> = (60..65).map(|i| transaction_doc(&user_id, i)).collect();
+            db.collection::<Document>("pending_transactions").insert_many(newer, None).await.unwrap();
+        }
+        match body["nextCursor"].as_str() {
+            Some(cursor) => uri = format!("/api/wallet/transactions?limit=25&cursor={}", cursor),
+            None => break,
+        }
+    }
+
+    let expected: Vec<String> = (0..60).rev().map(|i| format!("tx-{:03}", i)).collect();
+    assert_eq!(seen, expected);
+
+    let req = get(&wallet, "/api/wallet/transactions?cursor=garbage!").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_list_queries_use_compound_indexes() {
+    let db = common::test_db().await;
+    PoseidonTransactionManager::new(Arc::new(db.clone())).ensure_indexes().await.unwrap();
+    HestiaConnectionManager::new(Arc::new(db.clone())).ensure_indexes().await.unwrap();
+
+    let names: HashSet<String> = db.collection::<Document>("pending_transactions")
+        .list_index_names().await.unwrap().into_iter().collect();
+    for name in [
+        "user_id_1_created_at_-1__id_-1",
+        "user_id_1_status_1_created_at_-1__id_-1",
+        "user_id_1_dapp_origin_1_created_at_-1__id_-1",
+    ] {
+        assert!(names.contains(name), "missing index {}", name);
+    }
+    let names: HashSet<String> = db.collection::<Document>("dapp_connections")
+        .list_index_names().await.unwrap().into_iter().collect();
+    assert!(names.contains("user_id_1_status_1_connected_at_-1__id_-1"));
+    assert!(names.contains("user_id_1_dapp_origin_1_connected_at_-1__id_-1"));
+
+    seed_transactions(&db, "explain-user").await;
+    let explain = db.run_command(doc! {
+        "explain": {
+            "find": "pending_transactions",
+            "filter": { "user_id": "explain-user", "status": "pending" },
+            "sort": { "created_at": -1, "_id": -1 },
+            "limit": 51,
+        },
+        "verbosity": "queryPlanner",
+    }, None).await.unwrap();
+    let plan = explain.get_document("queryPlanner").unwrap().get_document("winningPlan").unwrap().to_string();
+    assert!(plan.contains("user_id_1_status_1_created_at_-1__id_-1"), "unexpected plan: {}", plan);
+    assert!(!plan.contains("\"SORT\""), "list query sorted in memory: {}", plan);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_connections_filter_by_status() {
+    let db = common::test_db().await;
+    let app = wallet_app!(db);
+    let wallet = Keypair::new();
+    let user_id = wallet.pubkey().to_string();
+    let connections: Vec<Document> = [("conn-a", Some("approved")), ("conn-b", None), ("conn-c", Some("pending"))]
+        .iter()
+        .enumerate()
+        .map(|(i, (id, status))| {
+            let at = mongodb::bson::DateTime::from_millis(BASE_MS + i as i64 * 1000);
+            let mut conn = doc! {
+                "_id": *id,
+                "user_id": &user_id,
+                "wallet_id": "wallet-1",
+                "dapp_origin": ORIGINS[i],
+                "dapp_name": "Test dApp",
+                "dapp_icon": null,
+                "permissions": ["viewbalance"],
+                "connected_at": at,
+                "last_used": at,
+            };
+            // conn-b predates connection statuses
+            if let Some(status) = status {
+                conn.insert("status", *status);
+            }
+            conn
+        })
+        .collect();
+    db.collection::<Document>("dapp_connections").insert_many(connections, None).await.unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/api/wallet/dapp/disconnect")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&wallet)))
+        .set_json(serde_json::json!({ "connection_id": "conn-a" }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/wallet/dapp/connections").to_request()).await;
+    assert_eq!(ids(&body), vec!["conn-c", "conn-b"]);
+    assert_eq!(body["total"], 2);
+
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/wallet/dapp/connections?status=approved").to_request()).await;
+    assert_eq!(ids(&body), vec!["conn-b"]);
+    assert_eq!(body["items"][0]["status"], "approved");
+
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, "/api/wallet/dapp/connections?status=revoked").to_request()).await;
+    assert_eq!(ids(&body), vec!["conn-a"]);
+
+    let uri = "/api/wallet/dapp/connections?limit=1";
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, uri).to_request()).await;
+    let cursor = body["nextCursor"].as_str().unwrap().to_string();
+    let uri = format!("/api/wallet/dapp/connections?limit=1&cursor={}", cursor);
+    let body: Value = test::call_and_read_body_json(&app, get(&wallet, &uri).to_request()).await;
+    assert_eq!(ids(&body), vec!["conn-b"]);
+    assert!(body["nextCursor"].is_null());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[actix_web::test]
+async fn test_list_rejects_bad_query_params() {
+    let app = wallet_app!(common::offline_db().await);
+    let wallet = Keypair::new();
+    for uri in [
+        "/api/wallet/transactions?limit=0",
+        "/api/wallet/transactions?limit=500",
+        "/api/wallet/transactions?status=unknown",
+        "/api/wallet/transactions?created_after=yesterday",
+        "/api/wallet/dapp/connections?status=signed",
+    ] {
+        let resp = test::call_service(&app, get(&wallet, uri).to_request()).await;
+        assert_eq!(resp.status(), 400, "{}", uri);
+    }
+}
@@ -0,0 +1,172 @@
+// Integration tests for renaming and deleting wallets
+mod common;
+
+use actix_web::{test, web, App};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::wallet_handlers;
+use shadow_backend::zeus::ZeusWalletManager;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+
+macro_rules! wallet_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/wallet/{wallet_id}", web::patch().to(wallet_handlers::rename_wallet))
+                .route("/api/wallet/{wallet_id}", web::delete().to(wallet_handlers::delete_wallet)),
+        )
+        .await
+    };
+}
+
+fn rename(wallet: &Keypair, wallet_id: &str, name: &str) -> test::TestRequest {
+    test::TestRequest::patch()
+        .uri(&format!("/api/wallet/{}", wallet_id))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+        .set_json(serde_json::json!({ "name": name }))
+}
+
+fn delete(wallet: &Keypair, wallet_id: &str) -> test::TestRequest {
+    test::TestRequest::delete()
+        .uri(&format!("/api/wallet/{}", wallet_id))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+}
+
+async fn seed_wallet(db: &mongodb::Database, user_id: &str, id: &str, is_active: bool) {
+    let at = mongodb::bson::DateTime::from_millis(1_700_000_000_000);
+    db.collection::<Document>("wallets")
+        .insert_one(doc! {
+            "_id": id,
+            "user_id": user_id,
+            "pubkey": Keypair::new().pubkey().to_string(),
+            "name": format!("Wallet {}", id),
+            "encrypted_private_key": "",
+            "salt": "",
+            "is_active": is_active,
+            "created_at": at,
+            "updated_at": at,
+        }, None)
+        .await
+        .unwrap();
+}
+
+fn manager(db: &mongodb::Database) -> ZeusWalletManager {
+    ZeusWalletManager::new(Arc::new(db.clone()), String::new())
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_rename_wallet() {
+    let db = common::test_db().await;
+    let app = wallet_app!(db);
+    let owner = Keypair::new();
+    let user_id = owner.pubkey().to_string();
+    seed_wallet(&db, &user_id, "w1", true).await;
+
+    let body: Value = test::call_and_read_body_json(&app, rename(&owner, "w1", "  Savings  ").to_request()).await;
+    assert_eq!(body["name"], "Savings");
+    let wallet = manager(&db).get_wallet(&user_id, "w1").await.unwrap().unwrap();
+    assert_eq!(wallet.name, "Savings");
+    assert!(wallet.updated_at.timestamp_millis() > 1_700_000_000_000);
+
+    // Someone else's wallet looks the same as a missing one
+    let resp = test::call_service(&app, rename(&Keypair::new(), "w1", "Mine now").to_request()).await;
+    assert_eq!(resp.status(), 401);
+    let resp = test::call_service(&app, rename(&owner, "missing", "Savings").to_request()).await;
+    assert_eq!(resp.status(), 401);
+    assert_eq!(manager(&db).get_wallet(&user_id, "w1").await.unwrap().unwrap().name, "Savings");
+}
+
+#[actix_web::test]
+async fn test_rename_rejects_bad_names() {
+    // Names are checked before the database is touched
+    let db = common::offline_db().await;
+    let app = wallet_app!(db);
+    let owner = Keypair::new();
+
+    for name in ["", "   ", &"x".repeat(65)] {
+        let resp = test::call_service(&app, rename(&owner, "w1", name).to_request()).await;
+        assert_eq!(resp.status(), 400, "{:?}", name);
+    }
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_delete_only_wallet_fails() {
+    let db = common::test_db().await;
+    let app = wallet_app!(db);
+    let owner = Keypair::new();
+    let user_id = owner.pubkey().to_string();
+    seed_wallet(&db, &user_id, "only", true).await;
+
+    let resp = test::call_service(&app, delete(&owner, "only").to_request()).await;
+    assert_eq!(resp.status(), 400);
+    assert!(manager(&db).get_wallet(&user_id, "only").await.unwrap().is_some());
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_delete_active_wallet_activates_another() {
+    let db = common::test_db().await;
+    let app = wallet_app!(db);
+    let owner = Keypair::new();
+    let user_id = owner.pubkey().to_string();
+    seed_wallet(&db, &user_id, "main", true).await;
+    seed_wallet(&db, &user_id, "spare", false).await;
+
+    // Only the owner can delete
+    let resp = test::call_service(&app, delete(&Keypair::new(), "main").to_request()).await;
+    assert_eq!(resp.status(), 401);
+
+    let body: Value = test::call_and_read_body_json(&app, delete(&owner, "main").to_request()).await;
+    assert_eq!(body["success"], true);
+    assert!(manager(&db).get_wallet(&user_id, "main").await.unwrap().is_none());
+    assert!(manager(&db).get_wallet(&user_id, "spare").await.unwrap().unwrap().is_active);
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_legacy_xor_key_is_migrated_on_unlock() {
+    let db = common::test_db().await;
+    let manager = ZeusWalletManager::new(Arc::new(db.clone()), "http://127.0.0.1:1".to_string());
+    let keypair = Keypair::new();
+    let salt = [3u8; 16];
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<hmac::Hmac<sha2::Sha256>>(b"hunter2", &salt, 100_000, &mut key).unwrap();
+    let legacy: Vec<u8> = keypair.to_bytes().iter()
+        .enumerate()
+        .map(|(i, &b)| b ^ key[i % key.len()])
+        .collect();
+    db.collection::<Document>("wallets")
+        .insert_one(doc! {
+            "_id": "legacy",
+            "user_id": keypair.pubkey().to_string(),
+            "pubkey": keypair.pubkey().to_string(),
+            "name": "Old",
+            "encrypted_private_key": hex::encode(&legacy),
+            "salt": hex::encode(salt),
+            "is_active": true,
+            "created_at": mongodb::bson::DateTime::now(),
+            "updated_at": mongodb::bson::DateTime::now(),
+        }, None)
+        .await
+        .unwrap();
+
+    // A wrong password leaves the legacy key alone
+    assert!(manager.get_private_key("legacy", "hunter3").await.is_err());
+    let stored = db.collection::<Document>("wallets").find_one(doc! { "_id": "legacy" }, None).await.unwrap().unwrap();
+    assert_eq!(stored.get_str("encrypted_private_key").unwrap(), hex::encode(&legacy));
+
+    // Unlocking it re-encrypts it with AES-GCM, which still unlocks afterwards
+    assert_eq!(manager.get_private_key("legacy", "hunter2").await.unwrap(), keypair.to_bytes());
+    let stored = db.collection::<Document>("wallets").find_one(doc! { "_id": "legacy" }, None).await.unwrap().unwrap();
+    assert_ne!(stored.get_str("encrypted_private_key").unwrap(), hex::encode(&legacy));
+    assert_ne!(stored.get_str("salt").unwrap(), hex::encode(salt));
+    assert_eq!(manager.get_private_key("legacy", "hunter2").await.unwrap(), keypair.to_bytes());
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
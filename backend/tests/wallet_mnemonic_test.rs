@@ -0,0 +1,108 @@
+// Integration tests for exporting and importing wallets as recovery phrases
+mod common;
+
+use actix_web::{test, web, App};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::wallet_handlers;
+use shadow_backend::zeus::{keypair_from_mnemonic, keypair_to_mnemonic, ZeusWalletManager};
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+
+const RPC: &str = "http://127.0.0.1:1";
+
+macro_rules! wallet_app {
+    ($db:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($db.clone()))
+                .app_data(web::Data::new(RPC.to_string()))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/wallet/import/mnemonic", web::post().to(wallet_handlers::import_wallet_mnemonic))
+                .route("/api/wallet/{wallet_id}/export", web::get().to(wallet_handlers::export_wallet_mnemonic)),
+        )
+        .await
+    };
+}
+
+fn export(wallet: &Keypair, wallet_id: &str, password: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri(&format!("/api/wallet/{}/export", wallet_id))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+        .set_json(serde_json::json!({ "password": password }))
+}
+
+fn import(wallet: &Keypair, mnemonic: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/wallet/import/mnemonic")
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+        .set_json(serde_json::json!({ "name": "Recovered", "mnemonic": mnemonic, "password": "hunter2" }))
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_mnemonic_round_trip() {
+    let db = common::test_db().await;
+    let app = wallet_app!(db);
+    let owner = Keypair::new();
+    let manager = ZeusWalletManager::new(Arc::new(db.clone()), RPC.to_string());
+    let created = manager.create_wallet(&owner.pubkey().to_string(), "Main", "hunter2").await.unwrap();
+
+    let body: Value = test::call_and_read_body_json(&app, export(&owner, &created.id, "hunter2").to_request()).await;
+    let phrase = body["mnemonic"].as_str().unwrap();
+    assert_eq!(phrase.split(' ').count(), 24);
+    let restored = Keypair::from_bytes(&keypair_from_mnemonic(phrase).unwrap()).unwrap();
+    assert_eq!(restored.pubkey().to_string(), created.pubkey);
+
+    // The same key can't be imported twice
+    let resp = test::call_service(&app, import(&Keypair::new(), phrase).to_request()).await;
+    assert_eq!(resp.status(), 400);
+
+    let fresh = Keypair::new();
+    let user = Keypair::new();
+    let resp = test::call_service(&app, import(&user, &keypair_to_mnemonic(&fresh.to_bytes()).unwrap()).to_request()).await;
+    assert_eq!(resp.status(), 201);
+    let imported: Value = test::read_body_json(resp).await;
+    assert_eq!(imported["pubkey"], fresh.pubkey().to_string());
+    let key_bytes = manager.get_private_key(imported["id"].as_str().unwrap(), "hunter2").await.unwrap();
+    assert_eq!(key_bytes, fresh.to_bytes());
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_export_needs_the_owner_and_password() {
+    let db = common::test_db().await;
+    let app = wallet_app!(db);
+    let owner = Keypair::new();
+    let manager = ZeusWalletManager::new(Arc::new(db.clone()), RPC.to_string());
+    let created = manager.create_wallet(&owner.pubkey().to_string(), "Main", "hunter2").await.unwrap();
+
+    let resp = test::call_service(&app, export(&owner, &created.id, "hunter3").to_request()).await;
+    assert_eq!(resp.status(), 400);
+    // Someone else's wallet looks the same as a missing one
+    let resp = test::call_service(&app, export(&Keypair::new(), &created.id, "hunter2").to_request()).await;
+    assert_eq!(resp.status(), 401);
+    let resp = test::call_service(&app, export(&owner, "missing", "hunter2").to_request()).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[actix_web::test]
+async fn test_import_rejects_invalid_phrases() {
+    // Phrases are checked before the database is touched
+    let db = common::offline_db().await;
+    let app = wallet_app!(db);
+
+    for phrase in ["", "not a real phrase", &"abandon ".repeat(24)] {
+        let resp = test::call_service(&app, import(&Keypair::new(), phrase).to_request()).await;
+        assert_eq!(resp.status(), 400, "{:?}", phrase);
+    }
+    let resp = test::call_service(
+        &app,
+        test::TestRequest::post()
+            .uri("/api/wallet/import/mnemonic")
+            .set_json(serde_json::json!({ "name": "Recovered", "mnemonic": "x", "password": "hunter2" }))
+            .to_request(),
+    )
+    .await;
+    assert_eq!(resp.status(), 401);
+}
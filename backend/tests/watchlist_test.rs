@@ -0,0 +1,166 @@
+// Integration tests for Helios domain watchlists
+mod common;
+
+use actix_web::{test, web, App};
+use chrono::{TimeZone, Utc};
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use shadow_backend::ares::AresAuth;
+use shadow_backend::clock::{SharedClock, TestClock};
+use shadow_backend::config::WatchlistConfig;
+use shadow_backend::handlers;
+use shadow_backend::helios::HeliosWatchlist;
+use shadow_backend::olympus::OlympusCA;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn watchlist_config(max_per_wallet: u64) -> WatchlistConfig {
+    WatchlistConfig {
+        max_per_wallet,
+        scan_interval_seconds: 3600,
+    }
+}
+
+macro_rules! watchlist_app {
+    ($helios:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($helios))
+                .app_data(web::Data::new(AresAuth::new()))
+                .route("/api/domains/{domain}/watch", web::post().to(handlers::watch_domain))
+                .route("/api/domains/{domain}/watch", web::delete().to(handlers::unwatch_domain))
+                .route("/api/watchlist", web::get().to(handlers::get_watchlist)),
+        )
+        .await
+    };
+}
+
+fn watch(domain: &str, wallet: &Keypair) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(&format!("/api/domains/{}/watch", domain))
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+}
+
+fn watchlist(wallet: &Keypair) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri("/api/watchlist")
+        .insert_header(("X-Shadow-Auth", common::auth_header(wallet)))
+}
+
+#[actix_web::test]
+async fn test_watch_requires_auth_and_a_valid_name() {
+    let db = common::offline_db().await;
+    let app = watchlist_app!(HeliosWatchlist::new(db, watchlist_config(10)));
+
+    let req = test::TestRequest::post().uri("/api/domains/shop.shadow/watch").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    let req = test::TestRequest::get().uri("/api/watchlist").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+
+    let resp = test::call_service(&app, watch("-bad-.shadow", &Keypair::new()).to_request()).await;
+    assert_eq!(resp.status(), 400);
+}
+
+async fn register(db: &mongodb::Database, domain: &str, expires_at: chrono::DateTime<Utc>) {
+    OlympusCA::new(db.clone())
+        .register_domain(domain, &Pubkey::new_unique().to_string(), &Pubkey::new_unique().to_string())
+        .await
+        .unwrap();
+    db.collection::<Document>("domains")
+        .update_one(doc! { "_id": domain }, doc! { "$set": { "expires_at": expires_at } }, None)
+        .await
+        .unwrap();
+}
+
+#[actix_web::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_watchlist_shows_each_status_and_enforces_the_cap() {
+    let db = common::test_db().await;
+    let now = Utc.with_ymd_and_hms(2026, 6, 1, 0, 0, 0).unwrap();
+    let clock = Arc::new(TestClock::new(now));
+    register(&db, "taken.shadow", now + chrono::Duration::days(200)).await;
+    register(&db, "lapsing.shadow", now + chrono::Duration::days(10)).await;
+    let helios = HeliosWatchlist::new(db.clone(), watchlist_config(3)).with_clock(clock.clone() as SharedClock);
+    let app = watchlist_app!(helios);
+    let wallet = Keypair::new();
+
+    for domain in ["Free.shadow", "taken.shadow", "lapsing.shadow"] {
+        let resp = test::call_service(&app, watch(domain, &wallet).to_request()).await;
+        assert_eq!(resp.status(), 200, "{}", domain);
+        clock.advance(Duration::from_secs(1));
+    }
+    // Watching again is fine; a fourth name is over the cap
+    assert_eq!(test::call_service(&app, watch("taken.shadow", &wallet).to_request()).await.status(), 200);
+    let resp = test::call_service(&app, watch("fourth.shadow", &wallet).to_request()).await;
+    assert_eq!(resp.status(), 400);
+
+    let body: Value = test::call_and_read_body_json(&app, watchlist(&wallet).to_request()).await;
+    let entries = body.as_array().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0]["domain"], "lapsing.shadow");
+    assert_eq!(entries[0]["status"], "expiring");
+    assert_eq!(entries[0]["expires_at"], "2026-06-11T00:00:00Z");
+    assert_eq!(entries[0]["available_at"], "2026-07-11T00:00:00Z");
+    assert_eq!(entries[1]["domain"], "taken.shadow");
+    assert_eq!(entries[1]["status"], "registered");
+    assert_eq!(entries[2]["domain"], "free.shadow");
+    assert_eq!(entries[2]["status"], "available");
+
+    let req = test::TestRequest::delete()
+        .uri("/api/domains/free.shadow/watch")
+        .insert_header(("X-Shadow-Auth", common::auth_header(&wallet)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    assert_eq!(test::call_service(&app, watch("fourth.shadow", &wallet).to_request()).await.status(), 200);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
+
+#[tokio::test]
+#[ignore = "needs a MongoDB at DATABASE_URL"]
+async fn test_release_alert_fires_once() {
+    let db = common::test_db().await;
+    let now = Utc.with_ymd_and_hms(2026, 6, 1, 0, 0, 0).unwrap();
+    let clock = Arc::new(TestClock::new(now));
+    register(&db, "wanted.shadow", now + chrono::Duration::days(60)).await;
+    let helios = HeliosWatchlist::new(db.clone(), watchlist_config(10)).with_clock(clock.clone() as SharedClock);
+    let watcher = Keypair::new().pubkey().to_string();
+    helios.watch(&watcher, "wanted.shadow").await.unwrap();
+    let shutdown = CancellationToken::new();
+
+    let alerts = |kind: &'static str| {
+        let db = db.clone();
+        let watcher = watcher.clone();
+        async move {
+            db.collection::<Document>("notifications")
+                .count_documents(doc! { "wallet": watcher, "kind": kind }, None)
+                .await
+                .unwrap()
+        }
+    };
+
+    // Nothing changes while the registration is comfortably active
+    assert_eq!(helios.run_once(&shutdown).await.unwrap().alerts, 0);
+
+    // Expiring soon, then expired but in its grace period: one heads-up
+    clock.advance(DAY * 40);
+    helios.run_once(&shutdown).await.unwrap();
+    clock.advance(DAY * 25);
+    helios.run_once(&shutdown).await.unwrap();
+    assert_eq!(alerts("watched_domain_expiring").await, 1);
+    assert_eq!(alerts("watched_domain_available").await, 0);
+
+    // Past the grace period it is released, and repeated scans don't alert again
+    clock.advance(DAY * 30);
+    assert_eq!(helios.run_once(&shutdown).await.unwrap().alerts, 1);
+    clock.advance(DAY);
+    assert_eq!(helios.run_once(&shutdown).await.unwrap().alerts, 0);
+    assert_eq!(alerts("watched_domain_available").await, 1);
+
+    db.drop(None).await.expect("Failed to drop test database");
+}
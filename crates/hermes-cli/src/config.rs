@@ -0,0 +1,74 @@
+//! Settings kept in ~/.config/hermes/config.toml, so they needn't be passed on every run.
+//! Command-line flags take precedence over the file, and the file over built-in defaults.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Environment variable pointing at a config file to use instead of the default one
+pub const CONFIG_ENV: &str = "HERMES_CONFIG";
+
+pub const DEFAULT_BACKEND: &str = "http://localhost:8787";
+pub const DEFAULT_NETWORK: &str = "devnet";
+
+/// Keys `hermes config set` accepts
+pub const KEYS: [&str; 4] = ["backend", "network", "keypair_path", "domain"];
+
+/// Contents of the config file; anything unset falls back to the default
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    /// Solana keypair (id.json) to sign requests with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypair_path: Option<String>,
+    /// Domain `deploy` registers when --domain isn't given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+impl FileConfig {
+    /// Read the file at `path`; a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).with_context(|| format!("parsing {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    /// Write the file to `path`, creating its directory if needed
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        std::fs::write(path, toml::to_string(self)?).with_context(|| format!("writing {}", path.display()))
+    }
+
+    /// Set one of `KEYS`
+    pub fn set(&mut self, key: &str, value: String) -> Result<()> {
+        let field = match key {
+            "backend" => &mut self.backend,
+            "network" => &mut self.network,
+            "keypair_path" => &mut self.keypair_path,
+            "domain" => &mut self.domain,
+            _ => return Err(anyhow!("unknown config key {}; expected one of {}", key, KEYS.join(", "))),
+        };
+        *field = Some(value);
+        Ok(())
+    }
+}
+
+/// Config file location: HERMES_CONFIG if set, otherwise ~/.config/hermes/config.toml.
+/// None when neither it nor a home directory is known.
+pub fn config_path() -> Option<PathBuf> {
+    match std::env::var_os(CONFIG_ENV) {
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => std::env::var_os("HOME")
+            .filter(|home| !home.is_empty())
+            .map(|home| Path::new(&home).join(".config/hermes/config.toml")),
+    }
+}
@@ -1,17 +1,67 @@
-use anyhow::Result;
+mod config;
+mod output;
+mod watch;
+
+use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand};
-use hermes_client::{convert_site, deploy_site, register_domain, ClientConfig};
+use hermes_client::{
+    collect_files, tree_hash, verify_signed_payload, AuthSigner, HermesClient, SignedPayload, SiteContent,
+    Topic,
+};
+use futures_util::StreamExt;
+use config::{config_path, FileConfig, CONFIG_ENV, DEFAULT_BACKEND, DEFAULT_NETWORK};
+use output::{
+    print_error, print_report, progress_bar, ConfigReport, Deployed, DomainList, DomainVerification, Failure, OutputFormat, Published,
+    SiteStatus, Verified,
+};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use watch::{DeployState, ProjectWatcher};
+
+/// How often `domains verify` checks whether the verified flag has landed
+const VERIFY_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Parser, Debug)]
 #[command(name = "hermes", about = "Hermes CLI (Rust) for Shadow")]
 struct Cli {
-    /// Backend endpoint, e.g. http://localhost:8787
-    #[arg(long, global = true, default_value = "http://localhost:8787")]
-    backend: String,
+    /// Backend endpoint, e.g. http://localhost:8787 [default: config file, then http://localhost:8787]
+    #[arg(long, global = true)]
+    backend: Option<String>,
+
+    /// Network/cluster name [default: config file, then devnet]
+    #[arg(long, global = true)]
+    network: Option<String>,
+
+    /// Site-scoped deploy token; publishes content without a wallet keypair
+    #[arg(long, global = true, env = "SHADOW_DEPLOY_TOKEN", hide_env_values = true)]
+    deploy_token: Option<String>,
+
+    /// Solana keypair (id.json) to sign requests with; otherwise the config file's keypair_path,
+    /// then a base58 keypair in SHADOW_KEYPAIR
+    #[arg(long, global = true, env = "HERMES_KEYPAIR")]
+    keypair: Option<String>,
+
+    /// Tries per request when the backend is overloaded or unreachable; 1 disables retries
+    #[arg(long, global = true, default_value_t = 4)]
+    max_attempts: u8,
 
-    /// Network/cluster name
-    #[arg(long, global = true, default_value = "devnet")]
-    network: String,
+    /// Seconds to wait for the backend on each attempt
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
+
+    /// HTTP(S) proxy to reach the backend through, e.g. http://proxy.corp:3128
+    #[arg(long, global = true, env = "HERMES_PROXY")]
+    proxy: Option<String>,
+
+    /// Print results as a human summary or as one JSON object
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Shorthand for --output json
+    #[arg(long, global = true, conflicts_with = "output")]
+    json: bool,
 
     #[command(subcommand)]
     command: Commands,
@@ -30,12 +80,24 @@ enum Commands {
         /// Path to shadow.json
         #[arg(default_value = ".")]
         path: String,
-        /// Optional domain to register
+        /// Optional domain to register [default: config file's domain]
         #[arg(long)]
         domain: Option<String>,
         /// Mint the site token during deployment
         #[arg(long, default_value_t = false)]
         mint_token: bool,
+        /// Site program to publish to (deploy-token mode)
+        #[arg(long, requires = "deploy_token")]
+        program: Option<String>,
+        /// Publish an already-uploaded bundle instead of the files under path (deploy-token mode)
+        #[arg(long, requires = "program")]
+        cid: Option<String>,
+        /// Keep running and redeploy whenever files under path change; Ctrl-C to stop
+        #[arg(long, conflicts_with = "cid")]
+        watch: bool,
+        /// Check the project and price the deploy without deploying; exits 1 if it couldn't go ahead
+        #[arg(long, conflicts_with_all = ["watch", "program"])]
+        dry_run: bool,
     },
     /// Register a .shadow domain to a program address
     RegisterDomain {
@@ -44,29 +106,372 @@ enum Commands {
         /// Program or contract address
         program: String,
     },
+    /// List, inspect and verify .shadow domains
+    #[command(alias = "domain")]
+    Domains {
+        #[command(subcommand)]
+        command: DomainsCommand,
+    },
+    /// Check a deployed site; shorthand for `site status`
+    Status {
+        /// Program address, or a domain pointing at one
+        program_or_domain: String,
+    },
+    /// Show who owns a domain and where it points; shorthand for `domains info`
+    LookupDomain {
+        /// Domain name, e.g. mysite.shadow
+        domain: String,
+    },
+    /// Inspect a deployed site
+    Site {
+        #[command(subcommand)]
+        command: SiteCommand,
+    },
+    /// Print a site's events as they arrive, e.g. deploys going live; Ctrl-C to stop
+    Watch {
+        /// Program address of the site to follow
+        #[arg(long)]
+        program: String,
+    },
+    /// Show or change the config file (~/.config/hermes/config.toml, or HERMES_CONFIG)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Verify a signed certificate file offline, without contacting the backend
+    Verify {
+        /// Path to the signed payload JSON (`payload`, `signer`, `signature`)
+        file: String,
+        /// Public key (base58) the certificate must be signed by
+        #[arg(long)]
+        signer: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DomainsCommand {
+    /// Domains registered to a wallet
+    List {
+        /// Owner's wallet address (base58)
+        wallet: String,
+    },
+    /// Show a domain's registration
+    Info {
+        /// Domain name, e.g. mysite.shadow
+        domain: String,
+    },
+    /// Have the backend check the domain's program on-chain and mark it verified (owner only)
+    Verify {
+        /// Domain name, e.g. mysite.shadow
+        domain: String,
+        /// Seconds to keep checking for the verified flag afterwards
+        #[arg(long, default_value_t = 10)]
+        wait: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SiteCommand {
+    /// Check that a site is registered and its content can be fetched
+    Status {
+        /// Program address, or a domain pointing at one
+        program_or_domain: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Save a setting to the config file, creating the file if needed
+    Set {
+        /// One of backend, network, keypair_path, domain
+        key: String,
+        value: String,
+    },
+    /// Print the config file's settings
+    Show,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let config = ClientConfig {
-        backend: cli.backend,
-        network: cli.network,
+async fn main() -> ExitCode {
+    let mut cli = Cli::parse();
+    if cli.json {
+        cli.output = OutputFormat::Json;
+    }
+    // Colors are for people at a terminal, never for pipes or files
+    if !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+    let format = cli.output;
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            print_error(format, &err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let format = cli.output;
+    let command = match cli.command {
+        Commands::Config { command } => return configure(format, command),
+        command => command,
+    };
+    let file = match config_path() {
+        Some(path) => FileConfig::load(&path)?,
+        None => FileConfig::default(),
     };
 
-    match cli.command {
+    let mut builder = HermesClient::builder()
+        .backend(cli.backend.or(file.backend).unwrap_or_else(|| DEFAULT_BACKEND.to_string()))
+        .network(cli.network.or(file.network).unwrap_or_else(|| DEFAULT_NETWORK.to_string()))
+        .max_retries(cli.max_attempts.saturating_sub(1));
+    if let Some(token) = cli.deploy_token {
+        builder = builder.deploy_token(token);
+    }
+    if let Some(path) = cli.keypair.or(file.keypair_path) {
+        builder = builder.keypair_path(path);
+    } else if let Some(signer) = AuthSigner::from_env()? {
+        builder = builder.signer(signer);
+    }
+    if let Some(seconds) = cli.timeout {
+        builder = builder.timeout(Duration::from_secs(seconds));
+    }
+    if let Some(proxy) = cli.proxy {
+        builder = builder.proxy(proxy);
+    }
+    if let Some(progress) = progress_bar(format) {
+        builder = builder.on_progress(progress);
+    }
+    let client = builder.build()?;
+
+    match command {
         Commands::Convert { path } => {
-            convert_site(&config, &path).await?;
+            print_report(format, &client.convert_site(&path).await?)?;
+        }
+        Commands::Deploy { path, domain, mint_token, dry_run: true, .. } => {
+            let root = project_root(Path::new(&path))?;
+            let domain = domain.or(file.domain);
+            let plan = client.plan_deploy(&root, domain.as_deref(), mint_token).await?;
+            print_report(format, &plan)?;
+            if !plan.deployable {
+                return Err(Failure::new("not_deployable", plan.problems.join("; ")).into());
+            }
         }
-        Commands::Deploy { path, domain, mint_token } => {
-            deploy_site(&config, &path, domain.as_deref(), mint_token).await?;
+        Commands::Deploy { path, domain, mint_token, program, cid, watch, dry_run: false } => {
+            let target = match program {
+                Some(program) => DeployTarget::Publish { program, cid },
+                None => DeployTarget::Pipeline { domain: domain.or(file.domain), mint_token },
+            };
+            if watch {
+                watch_deploy(&client, format, &path, &target).await?;
+            } else {
+                deploy(&client, format, &path, &target).await?;
+            }
         }
         Commands::RegisterDomain { domain, program } => {
-            register_domain(&config, &domain, &program).await?;
+            print_report(format, &client.register_domain(&domain, &program).await?)?;
+        }
+        Commands::Domains { command: DomainsCommand::List { wallet } } => {
+            let domains = client.list_domains(&wallet).await?;
+            print_report(format, &DomainList { wallet, domains })?;
+        }
+        Commands::Domains { command: DomainsCommand::Info { domain } } | Commands::LookupDomain { domain } => {
+            print_report(format, &registered_domain(&client, &domain).await?)?;
+        }
+        Commands::Domains { command: DomainsCommand::Verify { domain, wait } } => {
+            let verification = verify_domain(&client, &domain, Duration::from_secs(wait)).await?;
+            if !verification.verified {
+                return Err(Failure::new("verification", format!("{} is still unverified after {}s", domain, wait)).into());
+            }
+            print_report(format, &verification)?;
+        }
+        Commands::Site { command: SiteCommand::Status { program_or_domain } } | Commands::Status { program_or_domain } => {
+            print_report(format, &site_status(&client, &program_or_domain).await?)?;
+        }
+        Commands::Watch { program } => {
+            if format == OutputFormat::Text {
+                eprintln!("watching {} for events; Ctrl-C to stop", program);
+            }
+            let mut events = Box::pin(client.subscribe(&[Topic::Program(program)]));
+            let print = async {
+                while let Some(event) = events.next().await {
+                    print_report(format, &event)?;
+                }
+                Ok::<_, anyhow::Error>(())
+            };
+            tokio::select! {
+                result = print => result?,
+                interrupted = tokio::signal::ctrl_c() => interrupted.context("waiting for Ctrl-C")?,
+            }
+        }
+        Commands::Config { .. } => unreachable!("handled by configure"),
+        Commands::Verify { file, signer } => {
+            let signed: SignedPayload = serde_json::from_slice(
+                &std::fs::read(&file).with_context(|| format!("reading {}", file))?,
+            )
+            .with_context(|| format!("parsing {}", file))?;
+            verify_signed_payload(&signed, &signer).map_err(|e| Failure::new("verification", e.to_string()))?;
+            print_report(format, &Verified { valid: true, signer, payload: signed.payload })?;
         }
     }
 
     Ok(())
 }
 
+/// Run a `config` subcommand; these only touch the file, so need no backend
+fn configure(format: OutputFormat, command: ConfigCommand) -> Result<()> {
+    let path = config_path().ok_or_else(|| anyhow!("no home directory to keep the config file in; set {}", CONFIG_ENV))?;
+    let mut file = FileConfig::load(&path)?;
+    if let ConfigCommand::Set { key, value } = command {
+        file.set(&key, value)?;
+        file.save(&path)?;
+    }
+    print_report(format, &ConfigReport { path: path.display().to_string(), config: file })
+}
+
+/// Where `deploy` sends a project
+enum DeployTarget {
+    /// The backend pipeline, which builds and deploys the project at the path
+    Pipeline { domain: Option<String>, mint_token: bool },
+    /// New content for an existing site, using the deploy token
+    Publish { program: String, cid: Option<String> },
+}
+
+impl DeployTarget {
+    /// How the watch state file records this target
+    fn name(&self) -> &str {
+        match self {
+            DeployTarget::Pipeline { .. } => "pipeline",
+            DeployTarget::Publish { program, .. } => program,
+        }
+    }
+}
+
+/// Deploy the project at `path` once and print the result. Returns the new storage CID.
+async fn deploy(client: &HermesClient, format: OutputFormat, path: &str, target: &DeployTarget) -> Result<String> {
+    match target {
+        DeployTarget::Publish { program, cid } => {
+            let content = match cid {
+                Some(cid) => SiteContent::Cid(cid.clone()),
+                None => {
+                    let files = collect_files(Path::new(path))?;
+                    if files.is_empty() {
+                        return Err(anyhow!("no files to publish under {}", path));
+                    }
+                    SiteContent::Files(files)
+                }
+            };
+            let published = client.publish_site_content(program, content, false).await?;
+            if !published.live {
+                let failed: Vec<_> = published.failures.iter().map(|c| c.name.as_str()).collect();
+                return Err(Failure::new(
+                    "checks_failed",
+                    format!("version {} failed checks ({}); left as preview", published.version_id, failed.join(", ")),
+                )
+                .into());
+            }
+            let storage = published.storage_cid.clone();
+            let dev_url = client.dev_url(program);
+            print_report(format, &Published { program: program.clone(), publish: published, dev_url })?;
+            Ok(storage)
+        }
+        DeployTarget::Pipeline { domain, mint_token } => {
+            let deploy = client.deploy_site(path, domain.as_deref(), *mint_token).await?;
+            let storage = deploy.storage.clone();
+            let dev_url = client.dev_url(&deploy.program);
+            print_report(format, &Deployed { deploy, dev_url })?;
+            Ok(storage)
+        }
+    }
+}
+
+/// Deploy whenever the project's files change, until Ctrl-C. Content whose hash matches the
+/// last deploy to the same target, including one from an earlier run, is not sent again.
+/// A failed deploy is reported and retried on the next change.
+async fn watch_deploy(client: &HermesClient, format: OutputFormat, path: &str, target: &DeployTarget) -> Result<()> {
+    let root = project_root(Path::new(path))?;
+    let mut watcher = ProjectWatcher::new(&root)?;
+    let mut last = DeployState::load(&root)?.filter(|state| state.target == target.name());
+    if format == OutputFormat::Text {
+        eprintln!("watching {} for changes; Ctrl-C to stop", root.display());
+    }
+    let redeploy = async {
+        loop {
+            let hash = tree_hash(&root)?;
+            match &last {
+                Some(state) if state.hash == hash => {
+                    if format == OutputFormat::Text {
+                        eprintln!("unchanged since the deploy to {}", state.storage);
+                    }
+                }
+                _ => match deploy(client, format, path, target).await {
+                    Ok(storage) => {
+                        let state = DeployState { target: target.name().to_string(), hash, storage };
+                        state.save(&root)?;
+                        last = Some(state);
+                    }
+                    Err(err) => print_error(format, &err),
+                },
+            }
+            watcher.next_change().await?;
+        }
+    };
+    // A deploy in flight is abandoned; the state file only ever records finished ones
+    tokio::select! {
+        result = redeploy => result,
+        interrupted = tokio::signal::ctrl_c() => interrupted.context("waiting for Ctrl-C"),
+    }
+}
+
+/// Directory holding the project at `path`, which may name its shadow.json
+fn project_root(path: &Path) -> Result<PathBuf> {
+    let path = path.canonicalize().with_context(|| format!("reading {}", path.display()))?;
+    match path.parent() {
+        Some(parent) if path.is_file() => Ok(parent.to_path_buf()),
+        _ => Ok(path),
+    }
+}
+
+/// The registration for `domain`, failing when there is none
+async fn registered_domain(client: &HermesClient, domain: &str) -> Result<hermes_client::DomainInfo> {
+    client
+        .get_domain(domain)
+        .await?
+        .ok_or_else(|| Failure::new("not_found", format!("domain {} is not registered", domain)).into())
+}
 
+/// Ask for `domain` to be verified, then check its registration until it shows as verified
+/// or `wait` runs out. Reports the last verified flag seen.
+async fn verify_domain(client: &HermesClient, domain: &str, wait: Duration) -> Result<DomainVerification> {
+    let response = client.verify_domain(domain).await?;
+    let deadline = tokio::time::Instant::now() + wait;
+    loop {
+        let record = registered_domain(client, domain).await?;
+        if record.verified || tokio::time::Instant::now() >= deadline {
+            return Ok(DomainVerification { verified: record.verified, response, domain: record });
+        }
+        tokio::time::sleep(VERIFY_POLL_INTERVAL.min(deadline - tokio::time::Instant::now())).await;
+    }
+}
+
+/// Look up a site by program address or domain and check whether it resolves.
+/// Fails when nothing is registered under the name.
+async fn site_status(client: &HermesClient, program_or_domain: &str) -> Result<SiteStatus> {
+    // Program addresses are base58, so anything with a dot is a domain
+    let domain = if program_or_domain.contains('.') {
+        Some(registered_domain(client, program_or_domain).await?)
+    } else {
+        None
+    };
+    let program = domain.as_ref().map_or(program_or_domain, |d| d.program_address.as_str()).to_string();
+    let site = client.get_site(&program).await?.ok_or_else(|| {
+        let message = match &domain {
+            Some(domain) => format!("domain {} points at {}, which has no registered site", domain.domain, program),
+            None => format!("no site is registered at {}", program),
+        };
+        Failure::new("not_found", message)
+    })?;
+    let content_fetchable = client.content_available(&program).await?;
+    Ok(SiteStatus { domain, site, content_fetchable, dev_url: client.dev_url(&program) })
+}
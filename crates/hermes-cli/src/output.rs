@@ -0,0 +1,371 @@
+//! What commands print: one JSON object per command for scripts, or a short summary for people.
+
+use crate::config::FileConfig;
+use colored::Colorize;
+use hermes_client::{
+    canonicalize, ConvertResponse, DeployPlan, DeployResponse, DomainInfo, Event, HermesError, HermesEvent, Phase, Progress, PublishResponse,
+    RegisterDomainResponse, SiteInfo, VerifyResponse,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::StatusCode;
+use serde::Serialize;
+
+/// How results and errors are written out
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// A human summary on stdout, errors as plain text on stderr
+    Text,
+    /// The response as a single JSON object on stdout, errors as `{"error", "kind"}` on stderr
+    Json,
+}
+
+/// `yes` in green when `ok`, otherwise `no` in yellow; plain when colors are off
+fn flag(ok: bool, yes: &str, no: &str) -> String {
+    if ok {
+        yes.green().to_string()
+    } else {
+        no.yellow().to_string()
+    }
+}
+
+/// A command's result, printable in either format
+pub trait Report: Serialize {
+    /// Lines of the text-mode summary
+    fn summary(&self) -> Vec<String>;
+}
+
+/// Print a command's result to stdout
+pub fn print_report(format: OutputFormat, report: &impl Report) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string(report)?),
+        OutputFormat::Text => {
+            for line in report.summary() {
+                println!("{}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print a failed command's error to stderr
+pub fn print_error(format: OutputFormat, err: &anyhow::Error) {
+    match format {
+        OutputFormat::Json => {
+            let error = serde_json::json!({ "error": format!("{:#}", err), "kind": error_kind(err) });
+            eprintln!("{}", error);
+        }
+        OutputFormat::Text => eprintln!("error: {:#}", err),
+    }
+}
+
+/// Progress bar on stderr for request bodies, in text mode only. Nothing is drawn when stderr
+/// isn't a terminal. Each upload clears its bar when done; a deploy that goes on to mint a
+/// token leaves it up, saying so, until the result is printed.
+pub fn progress_bar(format: OutputFormat) -> Option<impl Fn(Progress) + Send + Sync + 'static> {
+    if format != OutputFormat::Text {
+        return None;
+    }
+    let style = ProgressStyle::with_template("{msg:>13} [{bar:30}] {bytes}/{total_bytes}")
+        .expect("valid progress template")
+        .progress_chars("=> ");
+    let bar = ProgressBar::new(0).with_style(style);
+    Some(move |progress: Progress| {
+        if progress.bytes_sent == 0 || progress.phase == Phase::MintingToken {
+            bar.reset();
+        }
+        bar.set_length(progress.total_bytes);
+        bar.set_position(progress.bytes_sent);
+        bar.set_message(match progress.phase {
+            Phase::Converting => "converting",
+            Phase::Uploading => "uploading",
+            Phase::Registering => "registering",
+            Phase::MintingToken => "minting token",
+        });
+        match progress.phase {
+            Phase::MintingToken => bar.finish(),
+            _ if progress.bytes_sent == progress.total_bytes => bar.finish_and_clear(),
+            _ => {}
+        }
+    })
+}
+
+/// A failure the CLI itself detected, tagged with the kind json mode reports
+#[derive(Debug)]
+pub struct Failure {
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Machine-readable category for an error, from the first cause in its chain we recognize
+pub fn error_kind(err: &anyhow::Error) -> &'static str {
+    for cause in err.chain() {
+        if let Some(failure) = cause.downcast_ref::<Failure>() {
+            return failure.kind;
+        }
+        if let Some(err) = cause.downcast_ref::<HermesError>() {
+            return match err {
+                HermesError::Timeout(_) => "timeout",
+                HermesError::Rejected { status, .. } => match *status {
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => "unauthorized",
+                    StatusCode::NOT_FOUND => "not_found",
+                    StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+                    status if status.is_client_error() => "rejected",
+                    _ => "backend",
+                },
+                HermesError::RetriesExhausted { last_status: None, .. } => "connection",
+                HermesError::RetriesExhausted { .. } => "backend",
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            return if err.is_connect() {
+                "connection"
+            } else if err.is_decode() {
+                "invalid_response"
+            } else {
+                "request"
+            };
+        }
+        if cause.is::<std::io::Error>() {
+            return "io";
+        }
+        if cause.is::<serde_json::Error>() {
+            return "invalid_input";
+        }
+    }
+    "error"
+}
+
+impl Report for ConvertResponse {
+    fn summary(&self) -> Vec<String> {
+        vec![format!("converted: {}", self.path), format!("message:   {}", self.message)]
+    }
+}
+
+/// A site deployed through the backend pipeline
+#[derive(Serialize)]
+pub struct Deployed {
+    #[serde(flatten)]
+    pub deploy: DeployResponse,
+    pub dev_url: String,
+}
+
+impl Report for Deployed {
+    fn summary(&self) -> Vec<String> {
+        vec![
+            format!("program: {}", self.deploy.program),
+            format!("storage: {}", self.deploy.storage),
+            format!("domain:  {}", self.deploy.domain.as_deref().unwrap_or("none")),
+            format!("token:   {}", if self.deploy.minted_token { "minted" } else { "not minted" }),
+            format!("dev url: {}", self.dev_url),
+        ]
+    }
+}
+
+impl Report for DeployPlan {
+    fn summary(&self) -> Vec<String> {
+        let sol = |lamports: u64| format!("{:.9} SOL", lamports as f64 / 1_000_000_000.0);
+        let domain = match self.domain_available {
+            Some(true) => "available",
+            Some(false) => "taken",
+            None => "none",
+        };
+        let mut lines = vec![
+            format!("files:      {}", self.files),
+            format!("size:       {} bytes", self.total_bytes),
+            format!("estimate:   {}", sol(self.estimated_lamports)),
+            format!("balance:    {}", sol(self.balance_lamports)),
+            format!("domain:     {}", domain),
+            format!("deployable: {}", flag(self.deployable, "yes", "no")),
+        ];
+        lines.extend(self.problems.iter().map(|problem| format!("problem:    {}", problem)));
+        lines.extend(self.warnings.iter().map(|warning| format!("warning:    {}", warning)));
+        lines
+    }
+}
+
+impl Report for HermesEvent {
+    fn summary(&self) -> Vec<String> {
+        let line = match &self.event {
+            Event::SiteUpdated { storage_cid, .. } => format!("site updated: {}", storage_cid),
+            Event::ContentUpdated { storage_cid, version_id, .. } => {
+                format!("content live: {} (version {})", storage_cid, version_id)
+            }
+            Event::DomainVerified { domain, .. } => format!("domain verified: {}", domain),
+            Event::Notification(notification) => format!("{}: {}", notification.kind, notification.message),
+            Event::Other => format!("event: {}", self.data),
+        };
+        vec![line]
+    }
+}
+
+/// New content published to a site with a deploy token
+#[derive(Serialize)]
+pub struct Published {
+    pub program: String,
+    #[serde(flatten)]
+    pub publish: PublishResponse,
+    pub dev_url: String,
+}
+
+impl Report for Published {
+    fn summary(&self) -> Vec<String> {
+        vec![
+            format!("program: {}", self.program),
+            format!("storage: {}", self.publish.storage_cid),
+            format!("version: {}", self.publish.version_id),
+            format!("dev url: {}", self.dev_url),
+        ]
+    }
+}
+
+impl Report for RegisterDomainResponse {
+    fn summary(&self) -> Vec<String> {
+        let mut lines = vec![format!("domain:  {}", self.domain), format!("program: {}", self.program)];
+        if let Some(storage) = &self.storage {
+            lines.push(format!("storage: {}", storage));
+        }
+        if let Some(owner) = &self.owner {
+            lines.push(format!("owner:   {}", owner));
+        }
+        lines
+    }
+}
+
+/// Domains registered to a wallet
+#[derive(Serialize)]
+pub struct DomainList {
+    pub wallet: String,
+    pub domains: Vec<DomainInfo>,
+}
+
+impl Report for DomainList {
+    fn summary(&self) -> Vec<String> {
+        if self.domains.is_empty() {
+            return vec![format!("no domains registered to {}", self.wallet)];
+        }
+        let width = self.domains.iter().map(|d| d.domain.len()).max().unwrap_or_default();
+        self.domains
+            .iter()
+            .map(|d| {
+                let verified = flag(d.verified, "verified", "unverified");
+                format!("{:width$}  {}  {}", d.domain.bold(), d.program_address, verified, width = width)
+            })
+            .collect()
+    }
+}
+
+impl Report for DomainInfo {
+    fn summary(&self) -> Vec<String> {
+        vec![
+            format!("domain:   {}", self.domain.bold()),
+            format!("program:  {}", self.program_address),
+            format!("owner:    {}", self.owner_pubkey),
+            format!("verified: {}", flag(self.verified, "yes", "no")),
+            format!("created:  {}", self.created_at),
+            format!("updated:  {}", self.updated_at),
+        ]
+    }
+}
+
+/// A verification request and the domain's registration once it settled
+#[derive(Serialize)]
+pub struct DomainVerification {
+    /// Verified flag on the registration, which is what resolution goes by
+    pub verified: bool,
+    pub response: VerifyResponse,
+    pub domain: DomainInfo,
+}
+
+impl Report for DomainVerification {
+    fn summary(&self) -> Vec<String> {
+        vec![
+            format!("domain:   {}", self.domain.domain.bold()),
+            format!("program:  {}", self.domain.program_address),
+            format!("verified: {}", flag(self.verified, "yes", "no")),
+        ]
+    }
+}
+
+/// Where a site stands: its registration, the domain it was looked up by, and whether it serves
+#[derive(Serialize)]
+pub struct SiteStatus {
+    pub domain: Option<DomainInfo>,
+    pub site: SiteInfo,
+    pub content_fetchable: bool,
+    pub dev_url: String,
+}
+
+impl Report for SiteStatus {
+    fn summary(&self) -> Vec<String> {
+        let verified = match &self.domain {
+            Some(domain) => domain.verified,
+            None => self.site.verified_domain.is_some(),
+        };
+        let mut lines = vec![format!("program:  {}", self.site.program_address.bold())];
+        if let Some(name) = &self.site.name {
+            lines.push(format!("name:     {}", name));
+        }
+        if let Some(domain) = self.domain.as_ref().map(|d| &d.domain).or(self.site.verified_domain.as_ref()) {
+            lines.push(format!("domain:   {}", domain));
+        }
+        lines.extend([
+            format!("owner:    {}", self.site.owner_pubkey),
+            format!("storage:  {}", self.site.storage_cid),
+            format!("verified: {}", flag(verified, "yes", "no")),
+            format!("created:  {}", self.site.created_at),
+            format!("updated:  {}", self.site.updated_at),
+            format!("content:  {}", flag(self.content_fetchable, "fetchable", "not fetchable")),
+            format!("dev url:  {}", self.dev_url),
+        ]);
+        lines
+    }
+}
+
+/// A certificate whose signature checked out
+#[derive(Serialize)]
+pub struct Verified {
+    pub valid: bool,
+    pub signer: String,
+    pub payload: serde_json::Value,
+}
+
+impl Report for Verified {
+    fn summary(&self) -> Vec<String> {
+        vec![format!("valid signature from {}", self.signer), canonicalize(&self.payload)]
+    }
+}
+
+/// The config file and what it sets
+#[derive(Serialize)]
+pub struct ConfigReport {
+    pub path: String,
+    #[serde(flatten)]
+    pub config: FileConfig,
+}
+
+impl Report for ConfigReport {
+    fn summary(&self) -> Vec<String> {
+        let setting = |value: &Option<String>| value.clone().unwrap_or_else(|| "(not set)".to_string());
+        vec![
+            format!("config:       {}", self.path),
+            format!("backend:      {}", setting(&self.config.backend)),
+            format!("network:      {}", setting(&self.config.network)),
+            format!("keypair_path: {}", setting(&self.config.keypair_path)),
+            format!("domain:       {}", setting(&self.config.domain)),
+        ]
+    }
+}
@@ -0,0 +1,95 @@
+//! `deploy --watch`: noticing when a project's files change, and remembering what was last
+//! deployed in .hermes/state.json so a restarted watch doesn't redeploy unchanged content.
+
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Quiet period after a change before redeploying, so a save touching several files deploys once
+pub const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// What was last deployed from a project directory
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeployState {
+    /// Where it went: "pipeline", or the program published to
+    pub target: String,
+    /// `hermes_client::tree_hash` of the files deployed
+    pub hash: String,
+    /// Storage CID the deploy produced
+    pub storage: String,
+}
+
+impl DeployState {
+    /// State file for the project at `root`
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(".hermes/state.json")
+    }
+
+    /// The state saved under `root`; None when nothing has been deployed from it yet
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        let path = Self::path(root);
+        match std::fs::read(&path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes).with_context(|| format!("parsing {}", path.display()))?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        let path = Self::path(root);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+/// Recursive watch on a project directory, reporting changes to the files a deploy would send
+pub struct ProjectWatcher {
+    // Dropping the watcher stops the events
+    _watcher: notify::RecommendedWatcher,
+    changes: mpsc::UnboundedReceiver<()>,
+}
+
+impl ProjectWatcher {
+    pub fn new(root: &Path) -> Result<Self> {
+        let (tx, changes) = mpsc::unbounded_channel();
+        let base = root.to_path_buf();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            // Errors from the OS watcher just mean a missed event; the next hash catches up
+            let Ok(event) = event else { return };
+            let relevant = matches!(event.kind, EventKind::Any | EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_))
+                && event.paths.iter().any(|path| !is_hidden(&base, path));
+            if relevant {
+                let _ = tx.send(());
+            }
+        })?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("watching {}", root.display()))?;
+        Ok(Self { _watcher: watcher, changes })
+    }
+
+    /// Wait for a change, then until `DEBOUNCE` passes without another
+    pub async fn next_change(&mut self) -> Result<()> {
+        self.changes.recv().await.context("file watcher stopped")?;
+        loop {
+            match tokio::time::timeout(DEBOUNCE, self.changes.recv()).await {
+                Ok(Some(())) => continue,
+                Ok(None) => return Err(anyhow::anyhow!("file watcher stopped")),
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Whether `path` is in a dot-directory or is a dotfile under `root`, which deploys leave out
+fn is_hidden(root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
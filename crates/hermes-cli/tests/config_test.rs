@@ -0,0 +1,122 @@
+// The config file: `hermes config set/show`, and flags > file > defaults
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A config path of its own for each test, in a directory that doesn't exist yet
+fn config_path(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hermes-config-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("hermes/config.toml")
+}
+
+async fn hermes(config: &Path, args: &[&str]) -> Output {
+    tokio::process::Command::new(env!("CARGO_BIN_EXE_hermes-cli"))
+        .args(["--max-attempts", "1"])
+        .args(args)
+        .env("HERMES_CONFIG", config)
+        .env_remove("SHADOW_DEPLOY_TOKEN")
+        .env_remove("SHADOW_KEYPAIR")
+        .env_remove("HERMES_KEYPAIR")
+        .env_remove("HERMES_PROXY")
+        .output()
+        .await
+        .unwrap()
+}
+
+async fn hermes_ok(config: &Path, args: &[&str]) -> String {
+    let output = hermes(config, args).await;
+    assert!(output.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Backend that records the deploys it gets
+async fn backend() -> MockServer {
+    let server = MockServer::start().await;
+    let deployed = serde_json::json!({ "program": "Prog1", "storage": "ipfs://bafy", "domain": null, "mintedToken": false });
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/deploy"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(deployed))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Bodies of the deploys `server` has received, oldest first
+async fn deploys(server: &MockServer) -> Vec<serde_json::Value> {
+    let requests = server.received_requests().await.unwrap();
+    requests.iter().map(|r| serde_json::from_slice(&r.body).unwrap()).collect()
+}
+
+#[tokio::test]
+async fn test_set_creates_the_file_and_show_reads_it() {
+    let config = config_path("set");
+    assert!(!config.parent().unwrap().exists());
+
+    let shown: serde_json::Value =
+        serde_json::from_str(&hermes_ok(&config, &["--output", "json", "config", "show"]).await).unwrap();
+    assert_eq!(shown, serde_json::json!({ "path": config.display().to_string() }));
+    assert!(!config.exists(), "show doesn't create the file");
+
+    hermes_ok(&config, &["config", "set", "network", "mainnet-beta"]).await;
+    hermes_ok(&config, &["config", "set", "domain", "site.shadow"]).await;
+    let text = std::fs::read_to_string(&config).unwrap();
+    assert!(text.contains("network = \"mainnet-beta\""), "{}", text);
+    assert!(text.contains("domain = \"site.shadow\""), "{}", text);
+
+    let shown = hermes_ok(&config, &["config", "show"]).await;
+    assert!(shown.contains("network:      mainnet-beta"), "{}", shown);
+    assert!(shown.contains("backend:      (not set)"), "{}", shown);
+
+    let output = hermes(&config, &["--output", "json", "config", "set", "netwrok", "devnet"]).await;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown config key netwrok"));
+    std::fs::remove_dir_all(config.parent().unwrap().parent().unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn test_flags_override_the_file_which_overrides_defaults() {
+    let from_file = backend().await;
+    let from_flag = backend().await;
+    let config = config_path("precedence");
+
+    // Nothing set: built-in network, no domain
+    hermes_ok(&config, &["--backend", &from_flag.uri(), "deploy", "site"]).await;
+    let sent = deploys(&from_flag).await;
+    assert_eq!(sent[0]["network"], "devnet");
+    assert_eq!(sent[0]["domain"], serde_json::Value::Null);
+
+    hermes_ok(&config, &["config", "set", "backend", &from_file.uri()]).await;
+    hermes_ok(&config, &["config", "set", "network", "mainnet-beta"]).await;
+    hermes_ok(&config, &["config", "set", "domain", "site.shadow"]).await;
+
+    // The file fills in what the flags leave out
+    hermes_ok(&config, &["deploy", "site"]).await;
+    let sent = deploys(&from_file).await;
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0]["network"], "mainnet-beta");
+    assert_eq!(sent[0]["domain"], "site.shadow");
+
+    // Flags win over the file
+    let args = ["--backend", &from_flag.uri(), "--network", "testnet", "deploy", "site", "--domain", "other.shadow"];
+    hermes_ok(&config, &args).await;
+    assert_eq!(deploys(&from_file).await.len(), 1);
+    let sent = deploys(&from_flag).await;
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[1]["network"], "testnet");
+    assert_eq!(sent[1]["domain"], "other.shadow");
+    std::fs::remove_dir_all(config.parent().unwrap().parent().unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn test_broken_file_is_reported() {
+    let config = config_path("broken");
+    std::fs::create_dir_all(config.parent().unwrap()).unwrap();
+    std::fs::write(&config, "backend = ").unwrap();
+
+    let output = hermes(&config, &["config", "show"]).await;
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("parsing"), "{}", String::from_utf8_lossy(&output.stderr));
+    std::fs::remove_dir_all(config.parent().unwrap().parent().unwrap()).unwrap();
+}
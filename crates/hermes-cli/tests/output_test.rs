@@ -0,0 +1,283 @@
+// What the CLI prints in text and json mode, against a mock backend.
+// Set UPDATE_SNAPSHOTS=1 to rewrite the files under tests/snapshots/output.
+use std::path::PathBuf;
+use std::process::Output;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Stands in for the mock backend's address, which changes from run to run
+const BACKEND: &str = "http://backend.test";
+
+fn assert_snapshot(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots/output")
+        .join(format!("{}.txt", name));
+
+    if std::env::var("UPDATE_SNAPSHOTS").is_ok() {
+        std::fs::create_dir_all(path.parent().unwrap()).expect("Failed to create snapshot dir");
+        std::fs::write(&path, format!("{}\n", actual)).expect("Failed to write snapshot");
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).expect("Snapshot missing; run with UPDATE_SNAPSHOTS=1");
+    assert_eq!(expected.trim_end(), actual, "snapshot {} changed", name);
+}
+
+/// Run hermes-cli against `server` with nothing picked up from the environment
+async fn hermes(server: &MockServer, args: &[&str]) -> Output {
+    tokio::process::Command::new(env!("CARGO_BIN_EXE_hermes-cli"))
+        .args(["--backend", &server.uri(), "--max-attempts", "1"])
+        .args(args)
+        .env_remove("SHADOW_DEPLOY_TOKEN")
+        .env_remove("SHADOW_KEYPAIR")
+        .env_remove("HERMES_KEYPAIR")
+        .env_remove("HERMES_PROXY")
+        .env("HERMES_CONFIG", std::env::temp_dir().join("hermes-output-test-no-config.toml"))
+        .output()
+        .await
+        .unwrap()
+}
+
+fn text(server: &MockServer, bytes: &[u8]) -> String {
+    String::from_utf8(bytes.to_vec()).unwrap().replace(&server.uri(), BACKEND).trim_end().to_string()
+}
+
+/// Snapshot stdout of a successful run, and check json mode printed exactly one object
+async fn assert_succeeds(server: &MockServer, name: &str, args: &[&str]) {
+    for mode in ["text", "json"] {
+        let output = hermes(server, &[&["--output", mode], args].concat()).await;
+        let stdout = text(server, &output.stdout);
+        assert!(output.status.success(), "{} {}: {}", name, mode, text(server, &output.stderr));
+        assert!(output.stderr.is_empty(), "{} {}: {}", name, mode, text(server, &output.stderr));
+        if mode == "json" {
+            assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+            assert!(serde_json::from_str::<serde_json::Value>(&stdout).unwrap().is_object());
+        }
+        assert_snapshot(&format!("{}.{}", name, mode), &stdout);
+    }
+}
+
+/// Snapshot stderr of a failing run, and check json mode reported `kind`
+async fn assert_fails(server: &MockServer, name: &str, kind: &str, args: &[&str]) {
+    for mode in ["text", "json"] {
+        let output = hermes(server, &[&["--output", mode], args].concat()).await;
+        let stderr = text(server, &output.stderr);
+        assert_eq!(output.status.code(), Some(1), "{} {}: {}", name, mode, stderr);
+        assert!(output.stdout.is_empty(), "{} {}", name, mode);
+        if mode == "json" {
+            let error: serde_json::Value = serde_json::from_str(&stderr).unwrap();
+            assert_eq!(error["kind"], kind, "{}", stderr);
+        }
+        assert_snapshot(&format!("{}.{}", name, mode), &stderr);
+    }
+}
+
+async fn mock(server: &MockServer, verb: &str, route: &str, response: ResponseTemplate) {
+    Mock::given(method(verb)).and(path(route)).respond_with(response).mount(server).await;
+}
+
+fn json(body: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(body)
+}
+
+/// RFC 8032 test vector 1, secret then public key
+fn keypair() -> Vec<u8> {
+    hex::decode(concat!(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+    ))
+    .unwrap()
+}
+
+fn domain_json(domain: &str, verified: bool) -> serde_json::Value {
+    serde_json::json!({
+        "_id": domain,
+        "owner_pubkey": "Owner1",
+        "program_address": "Prog1",
+        "verified": verified,
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z"
+    })
+}
+
+#[tokio::test]
+async fn test_convert_output() {
+    let server = MockServer::start().await;
+    mock(&server, "POST", "/api/sdk/convert", json(serde_json::json!({ "message": "converted 3 files", "path": "site/shadow.json" }))).await;
+    assert_succeeds(&server, "convert", &["convert", "site"]).await;
+}
+
+#[tokio::test]
+async fn test_deploy_output() {
+    let server = MockServer::start().await;
+    let deployed = serde_json::json!({ "program": "Prog1", "storage": "ipfs://bafy", "domain": "site.shadow", "mintedToken": true });
+    mock(&server, "POST", "/api/sdk/deploy", json(deployed)).await;
+    assert_succeeds(&server, "deploy", &["deploy", "site", "--domain", "site.shadow", "--mint-token"]).await;
+}
+
+#[tokio::test]
+async fn test_publish_output() {
+    let server = MockServer::start().await;
+    let published = serde_json::json!({ "version_id": "v7", "live": true, "storage_cid": "ipfs://bafy2", "preview_cid": null });
+    mock(&server, "POST", "/api/sites/Prog1/content", json(published)).await;
+    assert_succeeds(&server, "publish", &["deploy", "--deploy-token", "tok", "--program", "Prog1", "--cid", "ipfs://bafy2"]).await;
+}
+
+#[tokio::test]
+async fn test_register_domain_output() {
+    let server = MockServer::start().await;
+    let registered = serde_json::json!({ "domain": "site.shadow", "program": "Prog1", "storage": "ipfs://bafy", "owner": "Owner1" });
+    mock(&server, "POST", "/api/domains", json(registered)).await;
+    assert_succeeds(&server, "register_domain", &["register-domain", "site.shadow", "Prog1"]).await;
+}
+
+#[tokio::test]
+async fn test_site_status_output() {
+    let server = MockServer::start().await;
+    let domain = domain_json("site.shadow", true);
+    let site = serde_json::json!({
+        "_id": "Prog1",
+        "owner_pubkey": "Owner1",
+        "storage_cid": "ipfs://bafy",
+        "name": "My site",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-02-01T00:00:00Z",
+        "verified_domain": "site.shadow"
+    });
+    mock(&server, "GET", "/api/domains/site.shadow", json(domain)).await;
+    mock(&server, "GET", "/api/sites/Prog1", json(site)).await;
+    mock(&server, "GET", "/api/sites/Prog1/content", ResponseTemplate::new(206).set_body_string("<")).await;
+    assert_succeeds(&server, "site_status", &["site", "status", "site.shadow"]).await;
+    // The top-level shortcut prints the same, and --json is --output json
+    assert_succeeds(&server, "site_status", &["status", "site.shadow"]).await;
+    let output = hermes(&server, &["status", "site.shadow", "--json"]).await;
+    assert_snapshot("site_status.json", &text(&server, &output.stdout));
+}
+
+#[tokio::test]
+async fn test_verify_output() {
+    let server = MockServer::start().await;
+    let signed = shadow_signing::sign_payload(&keypair(), &serde_json::json!({ "domain": "site.shadow", "serial": 1 })).unwrap();
+    let file = std::env::temp_dir().join(format!("hermes-cert-{}.json", std::process::id()));
+    std::fs::write(&file, serde_json::to_vec(&signed).unwrap()).unwrap();
+
+    let file = file.to_str().unwrap();
+    assert_succeeds(&server, "verify", &["verify", file, "--signer", &signed.signer]).await;
+    assert_fails(&server, "verify_wrong_signer", "verification", &["verify", file, "--signer", "Owner1"]).await;
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+async fn test_domains_output() {
+    let server = MockServer::start().await;
+    let owned = serde_json::json!([domain_json("site.shadow", true), domain_json("longer-name.shadow", false)]);
+    mock(&server, "GET", "/api/domains/owner/Owner1", json(owned)).await;
+    mock(&server, "GET", "/api/domains/owner/Nobody", json(serde_json::json!([]))).await;
+    mock(&server, "GET", "/api/domains/site.shadow", json(domain_json("site.shadow", true))).await;
+    mock(&server, "GET", "/api/domains/stuck.shadow", json(domain_json("stuck.shadow", false))).await;
+    mock(&server, "GET", "/api/domains/missing.shadow", ResponseTemplate::new(404)).await;
+    let verify = json(serde_json::json!({ "success": true, "verified": true, "attempts": 1 }));
+    mock(&server, "POST", "/api/domains/site.shadow/verify", verify).await;
+    let unverified = json(serde_json::json!({ "success": true, "verified": false, "attempts": 3 }));
+    mock(&server, "POST", "/api/domains/stuck.shadow/verify", unverified).await;
+
+    let file = std::env::temp_dir().join(format!("hermes-domains-keypair-{}.json", std::process::id()));
+    std::fs::write(&file, serde_json::to_vec(&keypair()).unwrap()).unwrap();
+    let file = file.to_str().unwrap();
+
+    assert_succeeds(&server, "domains_list", &["domains", "list", "Owner1"]).await;
+    assert_succeeds(&server, "domains_list_empty", &["domains", "list", "Nobody"]).await;
+    assert_succeeds(&server, "domains_info", &["domain", "info", "site.shadow"]).await;
+    assert_fails(&server, "domains_info_missing", "not_found", &["domains", "info", "missing.shadow"]).await;
+    assert_succeeds(&server, "domains_info", &["lookup-domain", "site.shadow"]).await;
+    let output = hermes(&server, &["--json", "lookup-domain", "site.shadow"]).await;
+    assert_snapshot("domains_info.json", &text(&server, &output.stdout));
+    assert_succeeds(&server, "domains_verify", &["--keypair", file, "domains", "verify", "site.shadow"]).await;
+    let stuck = ["--keypair", file, "domains", "verify", "stuck.shadow", "--wait", "0"];
+    assert_fails(&server, "domains_verify_failed", "verification", &stuck).await;
+    std::fs::remove_file(file).unwrap();
+
+    // Verify requests were signed with the keypair
+    let requests = server.received_requests().await.unwrap();
+    let verifies = requests.iter().filter(|r| r.method.as_str() == "POST").collect::<Vec<_>>();
+    assert_eq!(verifies.len(), 4);
+    assert!(verifies.iter().all(|r| r.headers.contains_key(hermes_client::AUTH_HEADER)));
+}
+
+fn plan_json(balance: u64, domain_available: Option<bool>, problems: &[&str]) -> serde_json::Value {
+    serde_json::json!({
+        "estimated_lamports": 2_672_640,
+        "balance_lamports": balance,
+        "files": 2,
+        "total_bytes": 98,
+        "domain_available": domain_available,
+        "deployable": problems.is_empty(),
+        "problems": problems,
+        "warnings": []
+    })
+}
+
+#[tokio::test]
+async fn test_deploy_dry_run_output() {
+    let project = std::env::temp_dir().join(format!("hermes-dry-run-{}", std::process::id()));
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(project.join("shadow.json"), r#"{ "name": "site", "version": "0.1.0", "storage": "ipfs" }"#).unwrap();
+    std::fs::write(project.join("index.html"), "<h1>hi</h1>").unwrap();
+    let keypair_file = project.join(".id.json");
+    std::fs::write(&keypair_file, serde_json::to_vec(&keypair()).unwrap()).unwrap();
+    let (project_dir, keypair_file) = (project.to_str().unwrap(), keypair_file.to_str().unwrap());
+    let dry_run = |domain: &'static str| ["--keypair", keypair_file, "deploy", project_dir, "--domain", domain, "--dry-run"];
+
+    let server = MockServer::start().await;
+    mock(&server, "POST", "/api/sdk/deploy/plan", json(plan_json(1_000_000_000, Some(true), &[]))).await;
+    assert_succeeds(&server, "deploy_plan", &dry_run("site.shadow")).await;
+    let request: serde_json::Value = serde_json::from_slice(&server.received_requests().await.unwrap()[0].body).unwrap();
+    assert_eq!(request["manifest"]["name"], "site");
+    assert_eq!(request["files"], serde_json::json!([{ "path": "index.html", "size": 11 }, { "path": "shadow.json", "size": 57 }]));
+    assert_eq!(request["wallet"], "FVen3X669xLzsi6N2V91DoiyzHzg1uAgqiT8jZ9nS96Z");
+    assert_eq!(request["domain"], "site.shadow");
+
+    // Plans that can't go ahead are still printed, then the run fails
+    let blocked = [
+        ("deploy_plan_domain_taken", 1_000_000_000, Some(false), "Domain taken.shadow is already registered"),
+        ("deploy_plan_insufficient_balance", 1_000_000, Some(true), "Wallet balance 1000000 lamports is below the estimated 2672640 lamports"),
+    ];
+    for (name, balance, domain_available, problem) in blocked {
+        let server = MockServer::start().await;
+        mock(&server, "POST", "/api/sdk/deploy/plan", json(plan_json(balance, domain_available, &[problem]))).await;
+        for mode in ["text", "json"] {
+            let output = hermes(&server, &[&["--output", mode][..], &dry_run("taken.shadow")].concat()).await;
+            assert_eq!(output.status.code(), Some(1), "{} {}", name, mode);
+            assert_snapshot(&format!("{}.{}", name, mode), &text(&server, &output.stdout));
+            let stderr = text(&server, &output.stderr);
+            if mode == "json" {
+                let error: serde_json::Value = serde_json::from_str(&stderr).unwrap();
+                assert_eq!(error["kind"], "not_deployable", "{}", stderr);
+            }
+            assert!(stderr.contains(problem), "{}", stderr);
+        }
+    }
+    std::fs::remove_dir_all(project).unwrap();
+}
+
+#[tokio::test]
+async fn test_error_output() {
+    let server = MockServer::start().await;
+    mock(&server, "POST", "/api/domains", ResponseTemplate::new(400).set_body_string("invalid domain")).await;
+    mock(&server, "POST", "/api/sdk/deploy", ResponseTemplate::new(500).set_body_string("pipeline crashed")).await;
+    mock(&server, "GET", "/api/sites/Missing", ResponseTemplate::new(404)).await;
+    let checks = serde_json::json!({
+        "version_id": "v8",
+        "live": false,
+        "storage_cid": "ipfs://bafy3",
+        "preview_cid": "ipfs://bafy3",
+        "failures": [{ "name": "html", "passed": false, "detail": "index.html missing" }]
+    });
+    mock(&server, "POST", "/api/sites/Prog1/content", json(checks)).await;
+
+    assert_fails(&server, "error_rejected", "rejected", &["register-domain", "bad..shadow", "Prog1"]).await;
+    assert_fails(&server, "error_backend", "backend", &["deploy", "site"]).await;
+    assert_fails(&server, "error_not_found", "not_found", &["site", "status", "Missing"]).await;
+    let publish = ["deploy", "--deploy-token", "tok", "--program", "Prog1", "--cid", "ipfs://bafy3"];
+    assert_fails(&server, "error_checks_failed", "checks_failed", &publish).await;
+}
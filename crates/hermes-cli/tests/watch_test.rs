@@ -0,0 +1,118 @@
+// `deploy --watch`: redeploying on changes, skipping unchanged content, and stopping on Ctrl-C
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Child;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn project(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hermes-watch-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("index.html"), "<h1>v1</h1>").unwrap();
+    dir
+}
+
+async fn backend() -> MockServer {
+    let server = MockServer::start().await;
+    let deployed = serde_json::json!({ "program": "Prog1", "storage": "ipfs://bafy", "domain": null, "mintedToken": false });
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/deploy"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(deployed))
+        .mount(&server)
+        .await;
+    server
+}
+
+fn watch(server: &MockServer, dir: &Path) -> Child {
+    tokio::process::Command::new(env!("CARGO_BIN_EXE_hermes-cli"))
+        .args(["--backend", &server.uri(), "--max-attempts", "1", "--output", "json", "deploy"])
+        .arg(dir)
+        .arg("--watch")
+        .env("HERMES_CONFIG", dir.join("no-config.toml"))
+        .env_remove("SHADOW_DEPLOY_TOKEN")
+        .env_remove("SHADOW_KEYPAIR")
+        .env_remove("HERMES_KEYPAIR")
+        .env_remove("HERMES_PROXY")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+async fn deploys(server: &MockServer) -> usize {
+    server.received_requests().await.unwrap().len()
+}
+
+/// Wait for `server` to have received `count` deploys, failing after a generous timeout
+async fn wait_for_deploys(server: &MockServer, count: usize) {
+    for _ in 0..100 {
+        if deploys(server).await >= count {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("expected {} deploys, got {}", count, deploys(server).await);
+}
+
+/// Ctrl-C the watch and check it exits cleanly
+async fn interrupt(mut child: Child) {
+    let pid = child.id().unwrap().to_string();
+    let killed = tokio::process::Command::new("kill").args(["-INT", &pid]).status().await.unwrap();
+    assert!(killed.success());
+    let status = tokio::time::timeout(Duration::from_secs(10), child.wait()).await.unwrap().unwrap();
+    assert!(status.success(), "{:?}", status);
+}
+
+#[tokio::test]
+async fn test_watch_redeploys_changes_only() {
+    let server = backend().await;
+    let dir = project("changes");
+
+    let child = watch(&server, &dir);
+    wait_for_deploys(&server, 1).await;
+    let state: serde_json::Value = serde_json::from_slice(&std::fs::read(dir.join(".hermes/state.json")).unwrap()).unwrap();
+    assert_eq!(state["target"], "pipeline");
+    assert_eq!(state["storage"], "ipfs://bafy");
+
+    // A burst of writes is one redeploy
+    for version in 2..5 {
+        std::fs::write(dir.join("index.html"), format!("<h1>v{}</h1>", version)).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    wait_for_deploys(&server, 2).await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(deploys(&server).await, 2);
+
+    // Rewriting the same content, or touching dotfiles, doesn't redeploy
+    std::fs::write(dir.join("index.html"), "<h1>v4</h1>").unwrap();
+    std::fs::write(dir.join(".notes"), "todo").unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(deploys(&server).await, 2);
+
+    interrupt(child).await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_watch_state_survives_restarts() {
+    let server = backend().await;
+    let dir = project("restart");
+
+    let child = watch(&server, &dir);
+    wait_for_deploys(&server, 1).await;
+    interrupt(child).await;
+
+    // Nothing changed while it was stopped, so starting again deploys nothing
+    let child = watch(&server, &dir);
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert_eq!(deploys(&server).await, 1);
+    interrupt(child).await;
+
+    // Changes made while stopped go out as soon as it starts
+    std::fs::write(dir.join("about.html"), "<p>about</p>").unwrap();
+    let child = watch(&server, &dir);
+    wait_for_deploys(&server, 2).await;
+    interrupt(child).await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
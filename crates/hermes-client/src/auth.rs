@@ -0,0 +1,121 @@
+//! Signed X-Shadow-Auth headers for wallet-authenticated requests.
+//!
+//! The backend's AresAuth expects a JSON header carrying the wallet, a timestamp, a fresh
+//! UUID nonce and a signature over the challenge built from them. Signatures follow the
+//! wallet signMessage convention AresAuth verifies: ed25519 over the SHA-256 of the
+//! challenge behind the off-chain message prefix.
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Header the backend reads signed wallet challenges from
+pub const AUTH_HEADER: &str = "X-Shadow-Auth";
+
+/// Environment variable holding a base58 keypair, for machines without an id.json
+pub const KEYPAIR_ENV: &str = "SHADOW_KEYPAIR";
+
+/// Signs X-Shadow-Auth challenges with a Solana keypair
+#[derive(Clone)]
+pub struct AuthSigner {
+    /// Secret then public key, as Solana keypairs store it
+    keypair: [u8; 64],
+    wallet: String,
+}
+
+#[derive(Serialize)]
+struct SignedChallenge<'a> {
+    wallet: &'a str,
+    signature: String,
+    timestamp: i64,
+    nonce: String,
+}
+
+impl AuthSigner {
+    /// Signer for a 64-byte keypair, rejecting one whose halves don't match
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let keypair: [u8; 64] = bytes
+            .try_into()
+            .map_err(|_| anyhow!("keypair must be 64 bytes, got {}", bytes.len()))?;
+        // A public half that wasn't derived from the secret signs things nobody can verify
+        let probe = shadow_signing::sign_bytes(&keypair, b"").map_err(|e| anyhow!("invalid keypair: {}", e))?;
+        shadow_signing::verify_bytes(&keypair[32..], b"", &probe)
+            .map_err(|_| anyhow!("invalid keypair: public key does not match secret key"))?;
+        Ok(Self { keypair, wallet: bs58::encode(&keypair[32..]).into_string() })
+    }
+
+    /// Load a keypair file as written by `solana-keygen`: a JSON array of 64 bytes
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).with_context(|| format!("reading keypair {}", path.display()))?;
+        let bytes: Vec<u8> = serde_json::from_str(&text).with_context(|| format!("parsing keypair {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("loading keypair {}", path.display()))
+    }
+
+    /// Signer for a base58-encoded keypair, as wallets export it
+    pub fn from_base58(encoded: &str) -> Result<Self> {
+        let bytes = bs58::decode(encoded.trim())
+            .into_vec()
+            .map_err(|e| anyhow!("keypair is not valid base58: {}", e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Signer from the base58 keypair in SHADOW_KEYPAIR, if it is set
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(KEYPAIR_ENV) {
+            Ok(encoded) if !encoded.trim().is_empty() => Self::from_base58(&encoded)
+                .with_context(|| format!("loading {}", KEYPAIR_ENV))
+                .map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Wallet address (base58 public key) requests are signed for
+    pub fn wallet(&self) -> &str {
+        &self.wallet
+    }
+
+    /// Header value for a request sent now. Each call uses a fresh nonce, since the
+    /// backend accepts a given header only once.
+    pub fn header(&self) -> Result<String> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        self.header_at(timestamp)
+    }
+
+    /// Header value for a challenge signed at `timestamp` (Unix seconds)
+    pub fn header_at(&self, timestamp: i64) -> Result<String> {
+        let nonce = uuid::Uuid::new_v4().to_string();
+        let challenge = create_challenge(&self.wallet, timestamp, &nonce);
+        let signature = shadow_signing::sign_bytes(&self.keypair, &offchain_message_hash(challenge.as_bytes()))
+            .map_err(|e| anyhow!("{}", e))?;
+        Ok(serde_json::to_string(&SignedChallenge {
+            wallet: &self.wallet,
+            signature: bs58::encode(signature).into_string(),
+            timestamp,
+            nonce,
+        })?)
+    }
+}
+
+impl std::fmt::Debug for AuthSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the secret half
+        f.debug_struct("AuthSigner").field("wallet", &self.wallet).finish_non_exhaustive()
+    }
+}
+
+/// Challenge text the backend expects signed; must match AresAuth::create_challenge
+pub fn create_challenge(wallet: &str, timestamp: i64, nonce: &str) -> String {
+    format!("Shadow authentication challenge for {} at {} with nonce {}", wallet, timestamp, nonce)
+}
+
+/// What a wallet's signMessage actually signs: SHA-256 of the message behind the off-chain prefix
+fn offchain_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"\xffsolana offchain message");
+    hasher.update([message.len() as u8]);
+    hasher.update(message);
+    hasher.finalize().into()
+}
@@ -0,0 +1,220 @@
+//! Live events from the backend's /api/ws socket: what a deploy changed, as it lands.
+
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use crate::RetryPolicy;
+
+/// How often to ping the socket. A connection that stays silent for two intervals is dropped
+/// and reopened, since a half-open TCP connection would otherwise never deliver anything again.
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Something to follow on the event socket
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Topic {
+    /// Notifications left for a wallet, e.g. about ownership changes or domain expiry
+    Wallet(String),
+    /// Updates to the site deployed at a program address
+    Program(String),
+}
+
+impl std::fmt::Display for Topic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Topic::Wallet(wallet) => write!(f, "wallet:{}", wallet),
+            Topic::Program(program) => write!(f, "program:{}", program),
+        }
+    }
+}
+
+impl From<Topic> for String {
+    fn from(topic: Topic) -> Self {
+        topic.to_string()
+    }
+}
+
+impl TryFrom<String> for Topic {
+    type Error = String;
+
+    fn try_from(topic: String) -> Result<Self, Self::Error> {
+        match topic.split_once(':') {
+            Some(("wallet", wallet)) => Ok(Topic::Wallet(wallet.to_string())),
+            Some(("program", program)) => Ok(Topic::Program(program.to_string())),
+            _ => Err(format!("unknown topic {}", topic)),
+        }
+    }
+}
+
+/// An event delivered on a subscribed topic
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HermesEvent {
+    pub topic: Topic,
+    #[serde(skip)]
+    pub event: Event,
+    /// The payload as the backend sent it
+    pub data: serde_json::Value,
+}
+
+/// What happened, for the events this client knows. Delivery is at-least-once;
+/// `event_id` is the same on a redelivery, so it can be used to skip repeats.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The site's record changed, e.g. new content or metadata
+    SiteUpdated {
+        event_id: String,
+        program_address: String,
+        storage_cid: String,
+        /// Revision a conditional update landed at
+        #[serde(default)]
+        revision: Option<i64>,
+    },
+    /// New content went live
+    ContentUpdated {
+        event_id: String,
+        program_address: String,
+        storage_cid: String,
+        version_id: String,
+    },
+    DomainVerified {
+        event_id: String,
+        domain: String,
+        program_address: String,
+    },
+    /// A notification left for the wallet
+    #[serde(skip_deserializing)]
+    Notification(Notification),
+    /// An event this client doesn't know; see `HermesEvent::data`
+    #[serde(skip_deserializing)]
+    Other,
+}
+
+/// A notification for a wallet, e.g. that a domain it owns is about to expire
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Notification {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub wallet: String,
+    pub domain: String,
+    pub kind: String,
+    pub message: String,
+}
+
+impl HermesEvent {
+    fn new(topic: Topic, data: serde_json::Value) -> Self {
+        let event = serde_json::from_value(data.clone())
+            .or_else(|_| serde_json::from_value(data.clone()).map(Event::Notification))
+            .unwrap_or(Event::Other);
+        Self { topic, event, data }
+    }
+}
+
+/// Messages the socket accepts, mirroring the backend's `HermesMessage`
+#[derive(Serialize)]
+enum ClientMessage {
+    Subscribe { wallet: Option<String>, program: Option<String> },
+    Ping,
+}
+
+impl ClientMessage {
+    fn subscribe(topic: &Topic) -> Self {
+        match topic {
+            Topic::Wallet(wallet) => ClientMessage::Subscribe { wallet: Some(wallet.clone()), program: None },
+            Topic::Program(program) => ClientMessage::Subscribe { wallet: None, program: Some(program.clone()) },
+        }
+    }
+
+    fn text(&self) -> Message {
+        Message::Text(serde_json::to_string(self).expect("client messages serialize"))
+    }
+}
+
+/// What the socket sends back, mirroring the backend's `HermesResponse`
+#[derive(Deserialize)]
+enum ServerMessage {
+    Subscribed {},
+    Unsubscribed {},
+    Event { topic: String, data: serde_json::Value },
+    Pong,
+    Error {},
+}
+
+/// Event socket URL for a backend's HTTP(S) base URL
+pub(crate) fn socket_url(backend: &str) -> String {
+    let backend = backend.trim_end_matches('/');
+    let base = match backend.split_once("://") {
+        Some(("https", rest)) => format!("wss://{}", rest),
+        Some((_, rest)) => format!("ws://{}", rest),
+        None => format!("ws://{}", backend),
+    };
+    format!("{}/api/ws", base)
+}
+
+/// Stream of events on `topics` from the socket at `url`. A background task holds the
+/// connection, reopening it with `retry`'s backoff whenever it drops, for as long as the
+/// stream is kept.
+pub(crate) fn subscribe(url: String, topics: Vec<Topic>, retry: RetryPolicy, keepalive: Duration) -> impl Stream<Item = HermesEvent> {
+    let (events, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut failures = 0;
+        loop {
+            tokio::select! {
+                connected = run_connection(&url, &topics, &events, keepalive) => {
+                    failures = if connected { 1 } else { failures + 1 };
+                }
+                // Nobody is listening anymore
+                _ = events.closed() => return,
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(retry.backoff(failures)) => {}
+                _ = events.closed() => return,
+            }
+        }
+    });
+    futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|event| (event, receiver))
+    })
+}
+
+/// Subscribe over one connection and forward its events until it closes or goes quiet.
+/// Returns whether the connection was made, so failures to connect back off further.
+async fn run_connection(url: &str, topics: &[Topic], events: &mpsc::UnboundedSender<HermesEvent>, keepalive: Duration) -> bool {
+    let Ok((mut socket, _)) = connect_async(url).await else { return false };
+    for topic in topics {
+        if socket.send(ClientMessage::subscribe(topic).text()).await.is_err() {
+            return true;
+        }
+    }
+
+    let mut ping = tokio::time::interval(keepalive);
+    ping.tick().await;
+    let mut silent_ticks = 0;
+    loop {
+        tokio::select! {
+            message = socket.next() => {
+                silent_ticks = 0;
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    // Pongs for socket-level pings are answered by tungstenite itself
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return true,
+                    Some(Ok(_)) => continue,
+                };
+                let Ok(ServerMessage::Event { topic, data }) = serde_json::from_str(&text) else { continue };
+                let Ok(topic) = Topic::try_from(topic) else { continue };
+                if events.send(HermesEvent::new(topic, data)).is_err() {
+                    return true;
+                }
+            }
+            _ = ping.tick() => {
+                silent_ticks += 1;
+                if silent_ticks > 2 || socket.send(ClientMessage::Ping.text()).await.is_err() {
+                    return true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Reading a site's files from disk and fingerprinting them, so callers can tell whether
+//! anything changed since the last deploy.
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Every file under `root` keyed by its slash-separated relative path, sorted by path.
+/// Dotfiles and dot-directories (.git, .hermes) are skipped.
+pub fn collect_files(root: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("reading {}", dir.display()))? {
+            let path = entry?.path();
+            if path.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let relative = path.strip_prefix(root)?;
+                let key = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let data = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+                files.push((key, data));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Hex SHA-256 over the files' paths and contents. Renaming, adding or removing a file
+/// changes it as much as editing one does.
+pub fn content_hash(files: &[(String, Vec<u8>)]) -> String {
+    let mut hasher = Sha256::new();
+    for (path, data) in files {
+        // Length prefixes keep ("a", "bc") and ("ab", "c") apart
+        hasher.update((path.len() as u64).to_le_bytes());
+        hasher.update(path.as_bytes());
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(data);
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `content_hash` of the files under `root`
+pub fn tree_hash(root: &Path) -> Result<String> {
+    Ok(content_hash(&collect_files(root)?))
+}
@@ -1,17 +1,200 @@
-use anyhow::{anyhow, Result};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use bytes::Bytes;
+use rand::Rng;
+use reqwest::{header::{CONTENT_LENGTH, CONTENT_TYPE, RANGE, RETRY_AFTER}, Client, RequestBuilder, Response, StatusCode};
+use futures_util::Stream;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod auth;
+mod events;
+mod files;
+mod progress;
+
+pub use auth::{create_challenge, AuthSigner, AUTH_HEADER, KEYPAIR_ENV};
+pub use events::{Event, HermesEvent, Notification, Topic, KEEPALIVE_INTERVAL};
+pub use files::{collect_files, content_hash, tree_hash};
+pub use progress::{Phase, Progress};
+use progress::ProgressCallback;
+pub use shadow_signing::{canonicalize, SignedPayload};
+
+/// Header the backend reads site-scoped deploy tokens from
+pub const DEPLOY_TOKEN_HEADER: &str = "X-Shadow-Deploy-Token";
+
+/// Retries after the first attempt unless a config says otherwise
+pub const DEFAULT_MAX_RETRIES: u8 = 3;
+
+/// Delay before the first retry unless a config says otherwise
+pub const DEFAULT_RETRY_DELAY_MS: u64 = 200;
+
+/// Longest wait between two attempts, whatever the backoff or Retry-After asks for
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ClientConfig {
     pub backend: String,
     pub network: String,
+    /// Site-scoped token for CI publishing; no wallet keypair needed
+    #[serde(default)]
+    pub deploy_token: Option<String>,
+    /// Solana keypair file (id.json) to sign X-Shadow-Auth headers with
+    #[serde(default)]
+    pub keypair_path: Option<String>,
+    /// Times a failed call is tried again; 0 disables retries
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u8,
+    /// Milliseconds before the first retry, doubled for each one after and capped at 30 seconds
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// HTTP client every `HermesClient` built from this config shares, so they pool
+    /// connections and TLS sessions
+    #[serde(skip)]
+    pub http: Arc<Client>,
+}
+
+fn default_max_retries() -> u8 {
+    DEFAULT_MAX_RETRIES
+}
+
+fn default_retry_delay_ms() -> u64 {
+    DEFAULT_RETRY_DELAY_MS
+}
+
+impl ClientConfig {
+    /// Config for `backend` on devnet, with the default retries and a fresh HTTP client
+    pub fn new(backend: impl Into<String>) -> Self {
+        Self {
+            backend: backend.into(),
+            network: "devnet".to_string(),
+            deploy_token: None,
+            keypair_path: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_delay_ms: DEFAULT_RETRY_DELAY_MS,
+            http: Arc::default(),
+        }
+    }
+
+    /// The backoff `max_retries` and `retry_delay_ms` describe
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: u32::from(self.max_retries) + 1,
+            base_delay: Duration::from_millis(self.retry_delay_ms),
+            ..RetryPolicy::default()
+        }
+    }
+}
+
+/// Jittered exponential backoff for calls to the backend, derived from a `ClientConfig`.
+/// Connection errors, timeouts and the statuses in `retry_on` are retried, except that calls
+/// which must not run twice only retry what the backend cannot have acted on. Once the
+/// attempts run out the error carries a `HermesError::RetriesExhausted` in its chain.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Total tries including the first; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after
+    pub base_delay: Duration,
+    /// Upper bound on any single delay, including one asked for by Retry-After
+    pub max_delay: Duration,
+    /// Responses worth retrying: 429 and every 5xx by default, 500 included. Only 429 and
+    /// 5xx codes are honoured, so validation failures are never retried, and deploys and
+    /// domain registrations only retry 429, 502 and 503 from the list.
+    pub retry_on: Vec<StatusCode>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: u32::from(DEFAULT_MAX_RETRIES) + 1,
+            base_delay: Duration::from_millis(DEFAULT_RETRY_DELAY_MS),
+            max_delay: MAX_RETRY_DELAY,
+            retry_on: std::iter::once(StatusCode::TOO_MANY_REQUESTS)
+                .chain((500..600).filter_map(|code| StatusCode::from_u16(code).ok()))
+                .collect(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn retries_status(&self, status: StatusCode, replay: Replay) -> bool {
+        let retryable = match replay {
+            Replay::Safe => status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            Replay::Unsafe => UNPROCESSED_STATUSES.contains(&status),
+        };
+        retryable && self.retry_on.contains(&status)
+    }
+
+    /// Connection failures are always retried; a timeout may have left the request half done
+    fn retries_error(&self, e: &reqwest::Error, replay: Replay) -> bool {
+        e.is_connect() || (e.is_timeout() && replay == Replay::Safe)
+    }
+
+    /// Backoff before retry number `retry` (1-based): half the exponential delay plus up to as much again
+    fn backoff(&self, retry: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(retry - 1)).min(self.max_delay);
+        let half = exponential / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+
+    /// Seconds form of Retry-After; HTTP dates fall back to the regular backoff
+    fn retry_after(&self, resp: &Response) -> Option<Duration> {
+        let seconds: u64 = resp.headers().get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+        Some(Duration::from_secs(seconds).min(self.max_delay))
+    }
+}
+
+/// Whether a call can be sent again after the backend may already have acted on it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Replay {
+    /// Reads and calls that end in the same state however often they run
+    Safe,
+    /// Calls such as a deploy or domain registration, which would happen twice
+    Unsafe,
+}
+
+/// Answers that mean the backend turned the request away without acting on it
+const UNPROCESSED_STATUSES: [StatusCode; 3] =
+    [StatusCode::TOO_MANY_REQUESTS, StatusCode::BAD_GATEWAY, StatusCode::SERVICE_UNAVAILABLE];
+
+/// Failures callers may want to tell apart from the rest; find them with `anyhow::Error::downcast_ref`
+#[derive(Debug)]
+pub enum HermesError {
+    /// The backend didn't answer within the client's timeout, on any attempt
+    Timeout(Duration),
+    /// The backend answered `action` with an error status
+    Rejected { action: &'static str, status: StatusCode, body: String },
+    /// Every attempt failed with something worth retrying. Found further down the chain,
+    /// below the last attempt's error; `last_status` is None when it never got a response.
+    RetriesExhausted { attempts: u32, last_status: Option<StatusCode> },
+}
+
+impl std::fmt::Display for HermesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HermesError::Timeout(timeout) => write!(f, "backend did not respond within {:?}", timeout),
+            HermesError::Rejected { action, body, .. } => write!(f, "{} failed: {}", action, body),
+            HermesError::RetriesExhausted { attempts, last_status: Some(status) } => {
+                write!(f, "gave up after {} attempts, last answered {}", attempts, status)
+            }
+            HermesError::RetriesExhausted { attempts, last_status: None } => {
+                write!(f, "gave up after {} attempts without a response", attempts)
+            }
+        }
+    }
 }
 
+impl std::error::Error for HermesError {}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ConvertResponse {
     pub message: String,
     pub path: String,
+    /// Requests it took to get this response, retries included
+    #[serde(default)]
+    pub attempts: u32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -19,7 +202,11 @@ pub struct DeployResponse {
     pub program: String,
     pub storage: String,
     pub domain: Option<String>,
+    #[serde(alias = "mintedToken")]
     pub minted_token: bool,
+    /// Requests it took to get this response, retries included
+    #[serde(default)]
+    pub attempts: u32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -28,60 +215,566 @@ pub struct RegisterDomainResponse {
     pub program: String,
     pub storage: Option<String>,
     pub owner: Option<String>,
+    /// Requests it took to get this response, retries included
+    #[serde(default)]
+    pub attempts: u32,
 }
 
-pub async fn convert_site(config: &ClientConfig, path: &str) -> Result<ConvertResponse> {
-    let client = Client::new();
-    let url = format!("{}/api/sdk/convert", config.backend);
-    let body = serde_json::json!({ "path": path, "network": config.network });
-    let resp = client.post(url).json(&body).send().await?;
-    if resp.status().is_success() {
-        Ok(resp.json().await?)
-    } else {
-        Err(anyhow!("convert failed: {}", resp.text().await?))
+/// What a pipeline deploy would cost and whether it would go through, from a dry run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeployPlan {
+    /// Rent for the accounts the deploy creates, transaction fees and storage
+    pub estimated_lamports: u64,
+    pub balance_lamports: u64,
+    pub files: usize,
+    pub total_bytes: u64,
+    /// None when no domain was asked for
+    pub domain_available: Option<bool>,
+    pub deployable: bool,
+    /// Why the deploy would fail; empty when it's deployable
+    #[serde(default)]
+    pub problems: Vec<String>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+/// A registered site, as GET /api/sites/{program_address} returns it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SiteInfo {
+    #[serde(rename = "_id")]
+    pub program_address: String,
+    pub owner_pubkey: String,
+    pub storage_cid: String,
+    pub name: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Verified domain pointing at the site, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_domain: Option<String>,
+    /// Everything else the backend sent, so the response serializes back out whole
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A registered domain, as GET /api/domains/{domain} returns it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DomainInfo {
+    #[serde(rename = "_id")]
+    pub domain: String,
+    pub owner_pubkey: String,
+    pub program_address: String,
+    pub verified: bool,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Everything else the backend sent, so the response serializes back out whole
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// What POST /api/domains/{domain}/verify returns
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VerifyResponse {
+    pub success: bool,
+    pub verified: bool,
+    /// Requests it took to get this response, retries included
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+/// New content for a site published with a deploy token
+#[derive(Clone, Debug)]
+pub enum SiteContent {
+    /// A bundle that is already uploaded (ipfs:// or arweave://)
+    Cid(String),
+    /// Files to upload as a new bundle, as (relative path, bytes)
+    Files(Vec<(String, Vec<u8>)>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeployCheck {
+    pub name: String,
+    pub passed: bool,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PublishResponse {
+    pub version_id: String,
+    pub live: bool,
+    pub storage_cid: String,
+    pub preview_cid: Option<String>,
+    #[serde(default)]
+    pub failures: Vec<DeployCheck>,
+}
+
+/// Connection to a Shadow backend. Uses its config's HTTP client, so requests share pooled connections and TLS sessions.
+#[derive(Clone, Debug)]
+pub struct HermesClient {
+    config: ClientConfig,
+    retry: RetryPolicy,
+    timeout: Option<Duration>,
+    auth: Option<AuthSigner>,
+    progress: Option<ProgressCallback>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HermesClientBuilder {
+    backend: Option<String>,
+    network: Option<String>,
+    deploy_token: Option<String>,
+    keypair_path: Option<String>,
+    signer: Option<AuthSigner>,
+    max_retries: Option<u8>,
+    retry_delay_ms: Option<u64>,
+    http: Option<Arc<Client>>,
+    timeout: Option<Duration>,
+    proxy: Option<String>,
+    progress: Option<ProgressCallback>,
+}
+
+impl HermesClientBuilder {
+    /// Start from an existing config; everything it sets can still be overridden.
+    /// Its HTTP client is reused unless a timeout or proxy calls for a new one.
+    pub fn from_config(config: ClientConfig) -> Self {
+        Self {
+            backend: Some(config.backend),
+            network: Some(config.network),
+            deploy_token: config.deploy_token,
+            keypair_path: config.keypair_path,
+            max_retries: Some(config.max_retries),
+            retry_delay_ms: Some(config.retry_delay_ms),
+            http: Some(config.http),
+            ..Self::default()
+        }
+    }
+
+    pub fn backend(mut self, backend: impl Into<String>) -> Self {
+        self.backend = Some(backend.into());
+        self
+    }
+
+    /// Network/cluster name sent with each request; devnet unless set
+    pub fn network(mut self, network: impl Into<String>) -> Self {
+        self.network = Some(network.into());
+        self
+    }
+
+    pub fn deploy_token(mut self, token: impl Into<String>) -> Self {
+        self.deploy_token = Some(token.into());
+        self
+    }
+
+    /// Sign every request with the keypair file at `path`; it is loaded by `build`
+    pub fn keypair_path(mut self, path: impl Into<String>) -> Self {
+        self.keypair_path = Some(path.into());
+        self
+    }
+
+    /// Sign every request with an already loaded keypair, e.g. from `AuthSigner::from_env`.
+    /// Takes precedence over a keypair path.
+    pub fn signer(mut self, signer: AuthSigner) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Times a failed call is tried again; 0 disables retries
+    pub fn max_retries(mut self, retries: u8) -> Self {
+        self.max_retries = Some(retries);
+        self
+    }
+
+    /// Milliseconds before the first retry, doubled for each one after
+    pub fn retry_delay_ms(mut self, delay_ms: u64) -> Self {
+        self.retry_delay_ms = Some(delay_ms);
+        self
+    }
+
+    /// Give up on a single attempt after `timeout`, from connecting to reading the whole response
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Send every request through the proxy at `url`, e.g. http://proxy.corp:3128
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Report how far request bodies have been sent, e.g. to draw a progress bar during a
+    /// large publish. Called from the task sending the request, so keep it quick.
+    pub fn on_progress(mut self, callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(ProgressCallback::new(callback));
+        self
+    }
+
+    pub fn build(self) -> Result<HermesClient> {
+        let backend = self.backend.ok_or_else(|| anyhow!("a backend URL is required"))?;
+        let http = match self.http {
+            Some(http) if self.timeout.is_none() && self.proxy.is_none() => http,
+            _ => {
+                let mut http = Client::builder();
+                if let Some(timeout) = self.timeout {
+                    http = http.timeout(timeout);
+                }
+                if let Some(proxy) = &self.proxy {
+                    http = http.proxy(reqwest::Proxy::all(proxy).map_err(|e| anyhow!("invalid proxy {}: {}", proxy, e))?);
+                }
+                Arc::new(http.build()?)
+            }
+        };
+        let auth = match (self.signer, &self.keypair_path) {
+            (Some(signer), _) => Some(signer),
+            (None, Some(path)) => Some(AuthSigner::from_file(path)?),
+            (None, None) => None,
+        };
+        let config = ClientConfig {
+            backend,
+            network: self.network.unwrap_or_else(|| "devnet".to_string()),
+            deploy_token: self.deploy_token,
+            keypair_path: self.keypair_path,
+            max_retries: self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            retry_delay_ms: self.retry_delay_ms.unwrap_or(DEFAULT_RETRY_DELAY_MS),
+            http,
+        };
+        Ok(HermesClient {
+            retry: config.retry_policy(),
+            config,
+            timeout: self.timeout,
+            auth,
+            progress: self.progress,
+        })
     }
 }
 
+impl HermesClient {
+    pub fn builder() -> HermesClientBuilder {
+        HermesClientBuilder::default()
+    }
+
+    /// Client for `config` with no timeout or proxy
+    pub fn from_config(config: ClientConfig) -> Result<Self> {
+        HermesClientBuilder::from_config(config).build()
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Wallet requests are signed for, when a keypair is configured
+    pub fn wallet(&self) -> Option<&str> {
+        self.auth.as_ref().map(AuthSigner::wallet)
+    }
+
+    /// Site content URL that live-reloads whenever `program` gets a new deploy
+    pub fn dev_url(&self, program: &str) -> String {
+        dev_url(&self.config, program)
+    }
+
+    /// Follow `topics` on the backend's event socket. Dropped connections are reopened with the
+    /// retry policy's backoff and resubscribed, so the stream never ends on its own;
+    /// events published while reconnecting are missed.
+    pub fn subscribe(&self, topics: &[Topic]) -> impl Stream<Item = HermesEvent> {
+        let url = events::socket_url(&self.config.backend);
+        events::subscribe(url, topics.to_vec(), self.retry.clone(), KEEPALIVE_INTERVAL)
+    }
+
+    pub async fn convert_site(&self, path: &str) -> Result<ConvertResponse> {
+        let url = format!("{}/api/sdk/convert", self.config.backend);
+        let body = json_bytes(&serde_json::json!({ "path": path, "network": self.config.network }))?;
+        let (resp, attempts) =
+            self.send_with_retry("convert", Replay::Safe, || self.with_body(self.config.http.post(&url), Phase::Converting, &body, None)).await?;
+        if resp.status().is_success() {
+            Ok(ConvertResponse { attempts, ..resp.json().await.map_err(|e| self.request_error(e))? })
+        } else {
+            Err(rejected("convert", resp).await)
+        }
+    }
+
+    pub async fn deploy_site(&self, path: &str, domain: Option<&str>, mint_token: bool) -> Result<DeployResponse> {
+        let url = format!("{}/api/sdk/deploy", self.config.backend);
+        let body = json_bytes(&serde_json::json!({
+            "path": path,
+            "network": self.config.network,
+            "domain": domain,
+            "mintToken": mint_token
+        }))?;
+        let then = mint_token.then_some(Phase::MintingToken);
+        let (resp, attempts) =
+            self.send_with_retry("deploy", Replay::Unsafe, || self.with_body(self.config.http.post(&url), Phase::Uploading, &body, then)).await?;
+        if resp.status().is_success() {
+            Ok(DeployResponse { attempts, ..resp.json().await.map_err(|e| self.request_error(e))? })
+        } else {
+            Err(rejected("deploy", resp).await)
+        }
+    }
+
+    /// Dry run of `deploy_site` for the project at `root`: the backend checks its shadow.json and
+    /// files, and prices the deploy against the client's wallet, without changing anything
+    pub async fn plan_deploy(&self, root: &Path, domain: Option<&str>, mint_token: bool) -> Result<DeployPlan> {
+        let wallet = self.wallet().ok_or_else(|| anyhow!("planning a deploy requires a keypair"))?;
+        let manifest_path = root.join("shadow.json");
+        let manifest: serde_json::Value = serde_json::from_slice(
+            &std::fs::read(&manifest_path).with_context(|| format!("reading {}", manifest_path.display()))?,
+        )
+        .with_context(|| format!("parsing {}", manifest_path.display()))?;
+        let files: Vec<_> = collect_files(root)?
+            .into_iter()
+            .map(|(path, bytes)| serde_json::json!({ "path": path, "size": bytes.len() }))
+            .collect();
+
+        let url = format!("{}/api/sdk/deploy/plan", self.config.backend);
+        let body = serde_json::json!({
+            "manifest": manifest,
+            "files": files,
+            "wallet": wallet,
+            "domain": domain,
+            "mintToken": mint_token,
+            "network": self.config.network
+        });
+        let (resp, _) = self.send_with_retry("deploy plan", Replay::Safe, || self.config.http.post(&url).json(&body)).await?;
+        if resp.status().is_success() {
+            Ok(resp.json().await.map_err(|e| self.request_error(e))?)
+        } else {
+            Err(rejected("deploy plan", resp).await)
+        }
+    }
+
+    pub async fn register_domain(&self, domain: &str, program: &str) -> Result<RegisterDomainResponse> {
+        let url = format!("{}/api/domains", self.config.backend);
+        let body = json_bytes(&serde_json::json!({
+            "domain": domain,
+            "program": program,
+            "network": self.config.network
+        }))?;
+        let (resp, attempts) =
+            self.send_with_retry("register domain", Replay::Unsafe, || self.with_body(self.config.http.post(&url), Phase::Registering, &body, None)).await?;
+        if resp.status().is_success() {
+            Ok(RegisterDomainResponse { attempts, ..resp.json().await.map_err(|e| self.request_error(e))? })
+        } else {
+            Err(rejected("register domain", resp).await)
+        }
+    }
+
+    /// The site registered at `program`, or None if there is none
+    pub async fn get_site(&self, program: &str) -> Result<Option<SiteInfo>> {
+        self.lookup(&format!("{}/api/sites/{}", self.config.backend, program), "site lookup").await
+    }
+
+    /// The registration for `domain`, or None if it isn't registered
+    pub async fn get_domain(&self, domain: &str) -> Result<Option<DomainInfo>> {
+        self.lookup(&format!("{}/api/domains/{}", self.config.backend, domain), "domain lookup").await
+    }
+
+    /// Domains registered to `wallet`
+    pub async fn list_domains(&self, wallet: &str) -> Result<Vec<DomainInfo>> {
+        let url = format!("{}/api/domains/owner/{}", self.config.backend, wallet);
+        let (resp, _) = self.send_with_retry("domain list", Replay::Safe, || self.config.http.get(&url)).await?;
+        if resp.status().is_success() {
+            Ok(resp.json().await.map_err(|e| self.request_error(e))?)
+        } else {
+            Err(rejected("domain list", resp).await)
+        }
+    }
+
+    /// Ask the backend to check `domain`'s program on-chain and mark it verified.
+    /// Only the domain's owner may, so the client needs their keypair.
+    pub async fn verify_domain(&self, domain: &str) -> Result<VerifyResponse> {
+        if self.auth.is_none() {
+            return Err(anyhow!("verifying a domain requires a keypair"));
+        }
+        let url = format!("{}/api/domains/{}/verify", self.config.backend, domain);
+        let (resp, attempts) = self.send_with_retry("verify domain", Replay::Safe, || self.config.http.post(&url)).await?;
+        if resp.status().is_success() {
+            Ok(VerifyResponse { attempts, ..resp.json().await.map_err(|e| self.request_error(e))? })
+        } else {
+            Err(rejected("verify domain", resp).await)
+        }
+    }
+
+    /// Whether `program`'s content can be fetched through /content right now. Asks for a
+    /// single byte, so large sites aren't downloaded just to check.
+    pub async fn content_available(&self, program: &str) -> Result<bool> {
+        let url = format!("{}/api/sites/{}/content", self.config.backend, program);
+        let (resp, _) = self.send_with_retry("content check", Replay::Safe, || self.config.http.get(&url).header(RANGE, "bytes=0-0")).await?;
+        Ok(resp.status().is_success())
+    }
+
+    /// Publish new content for `program` using the client's deploy token
+    pub async fn publish_site_content(
+        &self,
+        program: &str,
+        content: SiteContent,
+        html_check: bool,
+    ) -> Result<PublishResponse> {
+        let token = self
+            .config
+            .deploy_token
+            .as_deref()
+            .ok_or_else(|| anyhow!("publishing content requires a deploy token"))?;
+        let url = format!("{}/api/sites/{}/content", self.config.backend, program);
+        let body = match content {
+            SiteContent::Cid(cid) => serde_json::json!({ "storage_cid": cid, "html_check": html_check }),
+            SiteContent::Files(files) => {
+                let files: Vec<_> = files
+                    .iter()
+                    .map(|(path, data)| {
+                        serde_json::json!({ "path": path, "content": general_purpose::STANDARD.encode(data) })
+                    })
+                    .collect();
+                serde_json::json!({ "files": files, "html_check": html_check })
+            }
+        };
+        let body = json_bytes(&body)?;
+        let request = self.config.http.post(url).header(DEPLOY_TOKEN_HEADER, token);
+        let resp = self
+            .authorize(self.with_body(request, Phase::Uploading, &body, None))?
+            .send()
+            .await
+            .map_err(|e| self.request_error(e))?;
+        if resp.status().is_success() {
+            Ok(resp.json().await.map_err(|e| self.request_error(e))?)
+        } else {
+            Err(rejected("publish", resp).await)
+        }
+    }
+
+    /// GET `url`, treating 404 as nothing there
+    async fn lookup<T: DeserializeOwned>(&self, url: &str, what: &'static str) -> Result<Option<T>> {
+        let (resp, _) = self.send_with_retry(what, Replay::Safe, || self.config.http.get(url)).await?;
+        match resp.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(resp.json().await.map_err(|e| self.request_error(e))?)),
+            _ => Err(rejected(what, resp).await),
+        }
+    }
+
+    /// Send the request `build` makes, retrying transient failures per the retry policy and
+    /// `replay`. Returns the final response with the number of attempts it took; when retries
+    /// run out, the last failure is returned as an error for `action`.
+    async fn send_with_retry(
+        &self,
+        action: &'static str,
+        replay: Replay,
+        build: impl Fn() -> RequestBuilder,
+    ) -> Result<(Response, u32)> {
+        let policy = &self.retry;
+        let max_attempts = policy.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let retrying = attempt < max_attempts;
+            let exhausted = |last_status| {
+                anyhow::Error::new(HermesError::RetriesExhausted { attempts: attempt, last_status })
+            };
+            let delay = match self.authorize(build())?.send().await {
+                Ok(resp) if policy.retries_status(resp.status(), replay) && retrying => {
+                    policy.retry_after(&resp).unwrap_or_else(|| policy.backoff(attempt))
+                }
+                Err(e) if policy.retries_error(&e, replay) && retrying => policy.backoff(attempt),
+                Ok(resp) if policy.retries_status(resp.status(), replay) && max_attempts > 1 => {
+                    let status = resp.status();
+                    let body = resp.text().await.map_err(|e| self.request_error(e))?;
+                    return Err(exhausted(Some(status)).context(HermesError::Rejected { action, status, body }));
+                }
+                Err(e) if policy.retries_error(&e, replay) && max_attempts > 1 => {
+                    return Err(match self.timeout {
+                        Some(timeout) if e.is_timeout() => exhausted(None).context(HermesError::Timeout(timeout)),
+                        _ => exhausted(None).context(e),
+                    });
+                }
+                Ok(resp) => return Ok((resp, attempt)),
+                Err(e) => return Err(self.request_error(e)),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Attach a JSON body, streamed with progress reports when a callback is set
+    fn with_body(&self, request: RequestBuilder, phase: Phase, body: &Bytes, then: Option<Phase>) -> RequestBuilder {
+        let request = request.header(CONTENT_TYPE, "application/json");
+        match &self.progress {
+            Some(progress) => request
+                .header(CONTENT_LENGTH, body.len())
+                .body(progress.body(phase, body.clone(), then)),
+            None => request.body(body.clone()),
+        }
+    }
+
+    /// Attach a freshly signed X-Shadow-Auth header when a keypair is configured.
+    /// Called per attempt, since the backend accepts each header only once.
+    fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder> {
+        match &self.auth {
+            Some(signer) => Ok(request.header(AUTH_HEADER, signer.header()?)),
+            None => Ok(request),
+        }
+    }
+
+    fn request_error(&self, e: reqwest::Error) -> anyhow::Error {
+        match self.timeout {
+            Some(timeout) if e.is_timeout() => HermesError::Timeout(timeout).into(),
+            _ => e.into(),
+        }
+    }
+}
+
+/// Serialized once, so retries and progress reporting send the same bytes
+fn json_bytes(body: &serde_json::Value) -> Result<Bytes> {
+    Ok(Bytes::from(serde_json::to_vec(body)?))
+}
+
+/// Error for a response with a failure status, carrying its body as the message
+async fn rejected(action: &'static str, resp: Response) -> anyhow::Error {
+    let status = resp.status();
+    match resp.text().await {
+        Ok(body) => HermesError::Rejected { action, status, body }.into(),
+        Err(e) => e.into(),
+    }
+}
+
+#[deprecated(note = "use HermesClient::convert_site, which reuses its connections")]
+pub async fn convert_site(config: &ClientConfig, path: &str) -> Result<ConvertResponse> {
+    HermesClient::from_config(config.clone())?.convert_site(path).await
+}
+
+#[deprecated(note = "use HermesClient::deploy_site, which reuses its connections")]
 pub async fn deploy_site(
     config: &ClientConfig,
     path: &str,
     domain: Option<&str>,
     mint_token: bool,
 ) -> Result<DeployResponse> {
-    let client = Client::new();
-    let url = format!("{}/api/sdk/deploy", config.backend);
-    let body = serde_json::json!({
-        "path": path,
-        "network": config.network,
-        "domain": domain,
-        "mintToken": mint_token
-    });
-    let resp = client.post(url).json(&body).send().await?;
-    if resp.status().is_success() {
-        Ok(resp.json().await?)
-    } else {
-        Err(anyhow!("deploy failed: {}", resp.text().await?))
-    }
+    HermesClient::from_config(config.clone())?.deploy_site(path, domain, mint_token).await
 }
 
+#[deprecated(note = "use HermesClient::register_domain, which reuses its connections")]
 pub async fn register_domain(
     config: &ClientConfig,
     domain: &str,
     program: &str,
 ) -> Result<RegisterDomainResponse> {
-    let client = Client::new();
-    let url = format!("{}/api/domains", config.backend);
-    let body = serde_json::json!({
-        "domain": domain,
-        "program": program,
-        "network": config.network
-    });
-    let resp = client.post(url).json(&body).send().await?;
-    if resp.status().is_success() {
-        Ok(resp.json().await?)
-    } else {
-        Err(anyhow!("register domain failed: {}", resp.text().await?))
-    }
+    HermesClient::from_config(config.clone())?.register_domain(domain, program).await
 }
 
+/// Publish new content for `program` using the deploy token from `config`
+#[deprecated(note = "use HermesClient::publish_site_content, which reuses its connections")]
+pub async fn publish_site_content(
+    config: &ClientConfig,
+    program: &str,
+    content: SiteContent,
+    html_check: bool,
+) -> Result<PublishResponse> {
+    HermesClient::from_config(config.clone())?.publish_site_content(program, content, html_check).await
+}
+
+/// Site content URL that live-reloads whenever `program` gets a new deploy
+pub fn dev_url(config: &ClientConfig, program: &str) -> String {
+    format!("{}/api/sites/{}/content?dev=1", config.backend.trim_end_matches('/'), program)
+}
 
+/// Check offline that `signed` carries a valid signature from `signer` (base58), e.g. a
+/// certificate saved from the backend. Key order and spacing in the payload don't matter.
+pub fn verify_signed_payload(signed: &SignedPayload, signer: &str) -> Result<()> {
+    shadow_signing::verify_signed_payload(signed, signer).map_err(|e| anyhow!("verification failed: {}", e))
+}
@@ -0,0 +1,101 @@
+//! Upload progress for request bodies, so callers can show that a large deploy is moving.
+
+use bytes::Bytes;
+use reqwest::Body;
+use serde::Serialize;
+use std::io::Cursor;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio_util::io::ReaderStream;
+
+/// Bytes handed to the connection between progress reports
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// What a request is doing on the backend's side
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Sending a convert request
+    Converting,
+    /// Sending a deploy or site content
+    Uploading,
+    /// Sending a domain registration
+    Registering,
+    /// A deploy's body has gone and the pipeline is deploying and minting the site token
+    MintingToken,
+}
+
+/// How far a request body has got. `bytes_sent` only grows within a phase and ends at
+/// `total_bytes`; a retried request starts its phase over from zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    pub phase: Phase,
+    pub bytes_sent: u64,
+    pub total_bytes: u64,
+}
+
+/// Receives `Progress` reports; set with `HermesClientBuilder::on_progress`
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressCallback {
+    pub(crate) fn new(callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn report(&self, phase: Phase, bytes_sent: u64, total_bytes: u64) {
+        (self.0)(Progress { phase, bytes_sent, total_bytes })
+    }
+
+    /// Request body for `data` that reports each chunk as the connection reads it. Once it has
+    /// all been read, `then` is reported as the phase the backend moves on to, if given.
+    pub(crate) fn body(&self, phase: Phase, data: Bytes, then: Option<Phase>) -> Body {
+        let total_bytes = data.len() as u64;
+        self.report(phase, 0, total_bytes);
+        let reader = ProgressReader {
+            inner: Cursor::new(data),
+            callback: self.clone(),
+            phase,
+            then,
+            sent: 0,
+            total_bytes,
+        };
+        Body::wrap_stream(ReaderStream::with_capacity(reader, CHUNK_SIZE))
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// AsyncRead that reports how much of `inner` has been read
+struct ProgressReader<R> {
+    inner: R,
+    callback: ProgressCallback,
+    phase: Phase,
+    then: Option<Phase>,
+    sent: u64,
+    total_bytes: u64,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = (buf.filled().len() - before) as u64;
+        if read > 0 {
+            self.sent += read;
+            self.callback.report(self.phase, self.sent, self.total_bytes);
+            if self.sent == self.total_bytes {
+                if let Some(then) = self.then.take() {
+                    self.callback.report(then, self.total_bytes, self.total_bytes);
+                }
+            }
+        }
+        poll
+    }
+}
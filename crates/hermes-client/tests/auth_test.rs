@@ -0,0 +1,100 @@
+// Signing requests with a wallet keypair
+use hermes_client::{AuthSigner, HermesClient, AUTH_HEADER};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// RFC 8032 test vector 1, secret then public key
+fn keypair_bytes() -> Vec<u8> {
+    hex::decode(concat!(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+    ))
+    .unwrap()
+}
+
+fn keypair_file(bytes: &[u8]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("hermes-keypair-{}.json", uuid::Uuid::new_v4().simple()));
+    std::fs::write(&path, serde_json::to_string(bytes).unwrap()).unwrap();
+    path
+}
+
+#[test]
+fn test_signer_loads_keypair_formats() {
+    let bytes = keypair_bytes();
+    let wallet = bs58::encode(&bytes[32..]).into_string();
+
+    let path = keypair_file(&bytes);
+    assert_eq!(AuthSigner::from_file(&path).unwrap().wallet(), wallet);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(AuthSigner::from_base58(&bs58::encode(&bytes).into_string()).unwrap().wallet(), wallet);
+
+    assert!(AuthSigner::from_bytes(&bytes[..32]).is_err());
+    let mut mismatched = bytes.clone();
+    mismatched[40] ^= 1;
+    assert!(AuthSigner::from_bytes(&mismatched).is_err());
+    assert!(AuthSigner::from_base58("not base58 0OIl").is_err());
+    assert!(AuthSigner::from_file("/nonexistent/id.json").is_err());
+}
+
+#[test]
+fn test_header_carries_the_signed_challenge() {
+    let signer = AuthSigner::from_bytes(&keypair_bytes()).unwrap();
+    let header: serde_json::Value = serde_json::from_str(&signer.header_at(1_700_000_000).unwrap()).unwrap();
+    assert_eq!(header["wallet"], signer.wallet());
+    assert_eq!(header["timestamp"], 1_700_000_000);
+    assert!(uuid::Uuid::parse_str(header["nonce"].as_str().unwrap()).is_ok());
+    assert_eq!(bs58::decode(header["signature"].as_str().unwrap()).into_vec().unwrap().len(), 64);
+    // Debug output never includes the secret key
+    assert_eq!(format!("{:?}", signer), format!("AuthSigner {{ wallet: {:?}, .. }}", signer.wallet()));
+}
+
+#[tokio::test]
+async fn test_every_attempt_is_signed_afresh() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/convert"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/convert"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "message": "ok", "path": "out" })))
+        .mount(&server)
+        .await;
+    let path = keypair_file(&keypair_bytes());
+    let client = HermesClient::builder()
+        .backend(server.uri())
+        .keypair_path(path.to_string_lossy())
+        .retry_delay_ms(5)
+        .build()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(client.convert_site(".").await.unwrap().attempts, 2);
+    let headers: Vec<serde_json::Value> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| serde_json::from_slice(r.headers.get(AUTH_HEADER).unwrap().as_bytes()).unwrap())
+        .collect();
+    assert_eq!(headers.len(), 2);
+    assert_eq!(headers[0]["wallet"], client.wallet().unwrap());
+    assert_ne!(headers[0]["nonce"], headers[1]["nonce"]);
+}
+
+#[tokio::test]
+async fn test_requests_are_unsigned_without_a_keypair() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "message": "ok", "path": "out" })))
+        .mount(&server)
+        .await;
+    let client = HermesClient::builder().backend(server.uri()).build().unwrap();
+    client.convert_site(".").await.unwrap();
+
+    assert!(client.wallet().is_none());
+    assert!(server.received_requests().await.unwrap()[0].headers.get(AUTH_HEADER).is_none());
+    assert!(HermesClient::builder().backend(server.uri()).keypair_path("/nonexistent/id.json").build().is_err());
+}
@@ -0,0 +1,83 @@
+// HermesClient construction, timeouts and proxies
+use hermes_client::{ClientConfig, HermesClient, HermesError};
+use std::time::{Duration, Instant};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn converted() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({ "message": "ok", "path": "out" }))
+}
+
+#[tokio::test]
+async fn test_timeout_fires_against_hanging_backend() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/convert"))
+        .respond_with(converted().set_delay(Duration::from_secs(10)))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let client = HermesClient::builder()
+        .backend(server.uri())
+        .timeout(Duration::from_millis(100))
+        .max_retries(1)
+        .retry_delay_ms(5)
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    let err = client.convert_site(".").await.unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(5), "{:?}", started.elapsed());
+    assert!(
+        matches!(err.downcast_ref::<HermesError>(), Some(HermesError::Timeout(timeout)) if *timeout == Duration::from_millis(100)),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_requests_go_through_the_proxy() {
+    // The mock stands in for the proxy; the backend host itself doesn't resolve
+    let proxy = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/convert"))
+        .respond_with(converted())
+        .expect(2)
+        .mount(&proxy)
+        .await;
+    let client = HermesClient::builder()
+        .backend("http://backend.invalid")
+        .proxy(proxy.uri())
+        .build()
+        .unwrap();
+
+    // Both calls share one client
+    assert_eq!(client.convert_site(".").await.unwrap().message, "ok");
+    assert_eq!(client.convert_site(".").await.unwrap().attempts, 1);
+    let requests = proxy.received_requests().await.unwrap();
+    assert_eq!(requests[0].url.host_str(), Some("backend.invalid"));
+}
+
+#[test]
+fn test_builder_validates_its_settings() {
+    assert!(HermesClient::builder().build().is_err(), "backend is required");
+    assert!(HermesClient::builder().backend("http://localhost:8787").proxy("::not a url::").build().is_err());
+
+    let client = HermesClient::builder().backend("http://localhost:8787/").build().unwrap();
+    assert_eq!(client.config().network, "devnet");
+    assert_eq!(client.dev_url("Prog1"), "http://localhost:8787/api/sites/Prog1/content?dev=1");
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn test_free_functions_still_work() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).and(path("/api/sdk/convert")).respond_with(converted()).mount(&server).await;
+    let config = ClientConfig::new(server.uri());
+
+    assert_eq!(hermes_client::convert_site(&config, ".").await.unwrap().attempts, 1);
+    let err = hermes_client::publish_site_content(&config, "Prog1", hermes_client::SiteContent::Cid("ipfs://x".into()), false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("deploy token"), "{}", err);
+}
@@ -0,0 +1,83 @@
+// Listing and verifying domains
+use hermes_client::{AuthSigner, HermesClient, HermesError, AUTH_HEADER};
+use wiremock::matchers::{header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// RFC 8032 test vector 1, secret then public key
+fn signer() -> AuthSigner {
+    let bytes = hex::decode(concat!(
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+    ))
+    .unwrap();
+    AuthSigner::from_bytes(&bytes).unwrap()
+}
+
+fn domain_json(domain: &str, verified: bool) -> serde_json::Value {
+    serde_json::json!({
+        "_id": domain,
+        "owner_pubkey": "Owner1",
+        "program_address": "Prog1",
+        "verified": verified,
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z"
+    })
+}
+
+#[tokio::test]
+async fn test_list_domains() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/domains/owner/Owner1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            domain_json("one.shadow", true),
+            domain_json("two.shadow", false),
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/domains/owner/Nobody"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+        .mount(&server)
+        .await;
+
+    let client = HermesClient::builder().backend(server.uri()).build().unwrap();
+    let domains = client.list_domains("Owner1").await.unwrap();
+    assert_eq!(domains.iter().map(|d| d.domain.as_str()).collect::<Vec<_>>(), ["one.shadow", "two.shadow"]);
+    assert!(domains[0].verified && !domains[1].verified);
+    assert!(client.list_domains("Nobody").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_verify_domain_is_signed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/domains/site.shadow/verify"))
+        .and(header_exists(AUTH_HEADER))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "success": true, "verified": true })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/domains/theirs.shadow/verify"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({ "error": "Not the domain owner" })))
+        .mount(&server)
+        .await;
+
+    let anonymous = HermesClient::builder().backend(server.uri()).build().unwrap();
+    let err = anonymous.verify_domain("site.shadow").await.unwrap_err();
+    assert!(err.to_string().contains("requires a keypair"), "{}", err);
+
+    let client = HermesClient::builder().backend(server.uri()).signer(signer()).build().unwrap();
+    let verified = client.verify_domain("site.shadow").await.unwrap();
+    assert!(verified.success && verified.verified);
+    assert_eq!(verified.attempts, 1);
+
+    let err = client.verify_domain("theirs.shadow").await.unwrap_err();
+    assert!(
+        matches!(err.downcast_ref::<HermesError>(), Some(HermesError::Rejected { status, .. }) if *status == 403),
+        "{:?}",
+        err
+    );
+    assert!(err.to_string().contains("Not the domain owner"), "{}", err);
+}
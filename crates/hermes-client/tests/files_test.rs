@@ -0,0 +1,60 @@
+// Collecting a site's files and hashing them for change detection
+use hermes_client::{collect_files, content_hash, tree_hash};
+use std::path::{Path, PathBuf};
+
+fn site(files: &[(&str, &str)]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("hermes-files-{}", uuid::Uuid::new_v4().simple()));
+    for (path, content) in files {
+        write(&root, path, content);
+    }
+    root
+}
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_collect_files_is_sorted_and_skips_dotfiles() {
+    let root = site(&[
+        ("index.html", "<h1>hi</h1>"),
+        ("assets/app.js", "run()"),
+        (".hermes/state.json", "{}"),
+        ("assets/.DS_Store", "junk"),
+    ]);
+    let files = collect_files(&root).unwrap();
+    let paths: Vec<_> = files.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(paths, ["assets/app.js", "index.html"]);
+    assert_eq!(files[1].1, b"<h1>hi</h1>");
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_tree_hash_tracks_content_and_names_only() {
+    let root = site(&[("index.html", "<h1>hi</h1>"), ("assets/app.js", "run()")]);
+    let original = tree_hash(&root).unwrap();
+    assert_eq!(original.len(), 64);
+
+    // Same files written again, and state kept alongside them, hash the same
+    write(&root, "index.html", "<h1>hi</h1>");
+    write(&root, ".hermes/state.json", "{\"hash\":\"x\"}");
+    assert_eq!(tree_hash(&root).unwrap(), original);
+
+    write(&root, "index.html", "<h1>hello</h1>");
+    let edited = tree_hash(&root).unwrap();
+    assert_ne!(edited, original);
+
+    std::fs::rename(root.join("assets/app.js"), root.join("assets/main.js")).unwrap();
+    assert_ne!(tree_hash(&root).unwrap(), edited);
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_content_hash_keeps_paths_and_contents_apart() {
+    let split = |path: &str, data: &str| content_hash(&[(path.to_string(), data.as_bytes().to_vec())]);
+    assert_ne!(split("a", "bc"), split("ab", "c"));
+    assert_eq!(content_hash(&[]), content_hash(&[]));
+    assert_ne!(content_hash(&[]), split("", ""));
+}
@@ -0,0 +1,82 @@
+// Progress reports while request bodies are sent
+use hermes_client::{HermesClient, Phase, Progress, SiteContent};
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Client that records every progress report it makes
+fn recording_client(server: &MockServer) -> (HermesClient, Arc<Mutex<Vec<Progress>>>) {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&reports);
+    let client = HermesClient::builder()
+        .backend(server.uri())
+        .deploy_token("tok")
+        .on_progress(move |progress| recorded.lock().unwrap().push(progress))
+        .build()
+        .unwrap();
+    (client, reports)
+}
+
+#[tokio::test]
+async fn test_large_publish_reports_monotonic_progress() {
+    let server = MockServer::start().await;
+    let published = serde_json::json!({ "version_id": "v1", "live": true, "storage_cid": "ipfs://bafy", "preview_cid": null });
+    Mock::given(method("POST"))
+        .and(path("/api/sites/Prog1/content"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(published))
+        .mount(&server)
+        .await;
+    let (client, reports) = recording_client(&server);
+
+    // A few megabytes once base64-encoded
+    let files = vec![
+        ("index.html".to_string(), b"<h1>hi</h1>".to_vec()),
+        ("video.bin".to_string(), (0..3_000_000u32).map(|i| i as u8).collect()),
+    ];
+    client.publish_site_content("Prog1", SiteContent::Files(files), false).await.unwrap();
+
+    let received = server.received_requests().await.unwrap();
+    let body = &received[0].body;
+    let reports = reports.lock().unwrap();
+    assert!(reports.len() > 10, "{} reports", reports.len());
+    assert!(reports.iter().all(|p| p.phase == Phase::Uploading && p.total_bytes == body.len() as u64));
+    assert_eq!(reports[0].bytes_sent, 0);
+    assert!(reports.windows(2).all(|pair| pair[0].bytes_sent <= pair[1].bytes_sent));
+    assert_eq!(reports.last().unwrap().bytes_sent, body.len() as u64);
+
+    // The streamed body is the same JSON the backend always got
+    let sent: serde_json::Value = serde_json::from_slice(body).unwrap();
+    assert_eq!(sent["files"][0]["path"], "index.html");
+    assert_eq!(received[0].headers["content-type"], "application/json");
+}
+
+#[tokio::test]
+async fn test_each_request_reports_its_phase() {
+    let server = MockServer::start().await;
+    let deployed = serde_json::json!({ "program": "Prog1", "storage": "ipfs://bafy", "domain": null, "mintedToken": true });
+    let converted = serde_json::json!({ "message": "ok", "path": "out" });
+    let registered = serde_json::json!({ "domain": "site.shadow", "program": "Prog1" });
+    for (route, response) in [("/api/sdk/deploy", deployed), ("/api/sdk/convert", converted), ("/api/domains", registered)] {
+        Mock::given(method("POST"))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response))
+            .mount(&server)
+            .await;
+    }
+    let (client, reports) = recording_client(&server);
+    let phases = |reports: &Arc<Mutex<Vec<Progress>>>| {
+        let mut phases: Vec<Phase> = reports.lock().unwrap().drain(..).map(|p| p.phase).collect();
+        phases.dedup();
+        phases
+    };
+
+    client.convert_site("site").await.unwrap();
+    assert_eq!(phases(&reports), [Phase::Converting]);
+    client.deploy_site("site", None, false).await.unwrap();
+    assert_eq!(phases(&reports), [Phase::Uploading]);
+    // Minting happens after the upload, in the same request
+    client.deploy_site("site", None, true).await.unwrap();
+    assert_eq!(phases(&reports), [Phase::Uploading, Phase::MintingToken]);
+    client.register_domain("site.shadow", "Prog1").await.unwrap();
+    assert_eq!(phases(&reports), [Phase::Registering]);
+}
@@ -0,0 +1,249 @@
+// Retries against a flaky backend
+use hermes_client::{ClientConfig, HermesClient, HermesClientBuilder, HermesError, RetryPolicy};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn builder(server: &MockServer) -> HermesClientBuilder {
+    HermesClient::builder().backend(server.uri()).max_retries(2).retry_delay_ms(5)
+}
+
+fn client(server: &MockServer) -> HermesClient {
+    builder(server).build().unwrap()
+}
+
+/// `failure` for the first `times` calls to `route`, then `success`
+async fn flaky(server: &MockServer, route: &str, failure: ResponseTemplate, times: u64, success: serde_json::Value) {
+    Mock::given(method("POST"))
+        .and(path(route))
+        .respond_with(failure)
+        .up_to_n_times(times)
+        .expect(times)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path(route))
+        .respond_with(ResponseTemplate::new(200).set_body_json(success))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_deploy_succeeds_after_two_failures() {
+    let server = MockServer::start().await;
+    let deployed = serde_json::json!({ "program": "Prog1", "storage": "Store1", "domain": null, "mintedToken": false });
+    flaky(&server, "/api/sdk/deploy", ResponseTemplate::new(503), 2, deployed).await;
+
+    let resp = client(&server).deploy_site(".", None, false).await.unwrap();
+    assert_eq!(resp.program, "Prog1");
+    assert_eq!(resp.attempts, 3);
+}
+
+#[tokio::test]
+async fn test_internal_server_errors_are_retried_by_default() {
+    let server = MockServer::start().await;
+    let converted = serde_json::json!({ "message": "ok", "path": "out" });
+    flaky(&server, "/api/sdk/convert", ResponseTemplate::new(500), 1, converted).await;
+
+    assert!(RetryPolicy::default().retry_on.contains(&reqwest::StatusCode::INTERNAL_SERVER_ERROR));
+    assert_eq!(client(&server).convert_site(".").await.unwrap().attempts, 2);
+}
+
+#[tokio::test]
+async fn test_first_try_reports_one_attempt() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/convert"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "message": "ok", "path": "out" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert_eq!(client(&server).convert_site(".").await.unwrap().attempts, 1);
+}
+
+#[tokio::test]
+async fn test_validation_failures_are_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/domains"))
+        .respond_with(ResponseTemplate::new(400).set_body_string("invalid domain"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let err = client(&server).register_domain("bad..shadow", "Prog1").await.unwrap_err();
+    assert!(err.to_string().contains("invalid domain"), "{}", err);
+    assert!(
+        matches!(err.downcast_ref::<HermesError>(), Some(HermesError::Rejected { status, .. }) if *status == 400),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_attempts() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/convert"))
+        .respond_with(ResponseTemplate::new(504).set_body_string("upstream timed out"))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let err = client(&server).convert_site(".").await.unwrap_err();
+    assert!(err.to_string().contains("upstream timed out"), "{}", err);
+    assert!(
+        matches!(err.downcast_ref::<HermesError>(), Some(HermesError::Rejected { status, .. }) if *status == 504),
+        "{:?}",
+        err
+    );
+    assert!(format!("{:#}", err).contains("gave up after 3 attempts"), "{:#}", err);
+    let exhausted = err.chain().find_map(|cause| match cause.downcast_ref::<HermesError>() {
+        Some(HermesError::RetriesExhausted { attempts, last_status }) => Some((*attempts, *last_status)),
+        _ => None,
+    });
+    assert_eq!(exhausted, Some((3, Some(reqwest::StatusCode::GATEWAY_TIMEOUT))));
+}
+
+#[tokio::test]
+async fn test_disabled_retries_report_no_exhaustion() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/convert"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("down"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = builder(&server).max_retries(0).build().unwrap();
+    let err = client.convert_site(".").await.unwrap_err();
+    assert!(!err.chain().any(|cause| matches!(cause.downcast_ref(), Some(HermesError::RetriesExhausted { .. }))));
+}
+
+#[tokio::test]
+async fn test_retry_after_is_respected() {
+    let server = MockServer::start().await;
+    let registered = serde_json::json!({ "domain": "site.shadow", "program": "Prog1", "storage": null, "owner": null });
+    let throttled = ResponseTemplate::new(429).insert_header("Retry-After", "1");
+    flaky(&server, "/api/domains", throttled, 1, registered).await;
+
+    let started = Instant::now();
+    let resp = client(&server).register_domain("site.shadow", "Prog1").await.unwrap();
+    assert_eq!(resp.attempts, 2);
+    assert!(started.elapsed() >= Duration::from_secs(1), "{:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn test_connection_errors_are_retried() {
+    // Nothing listens on a port we just released
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let server = MockServer::start().await;
+    let client = builder(&server).backend(format!("http://127.0.0.1:{}", port)).build().unwrap();
+
+    let started = Instant::now();
+    let err = client.convert_site(".").await.unwrap_err();
+    assert!(err.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_connect()), "{:?}", err);
+    let exhausted = err.chain().find_map(|cause| match cause.downcast_ref::<HermesError>() {
+        Some(HermesError::RetriesExhausted { attempts, last_status }) => Some((*attempts, *last_status)),
+        _ => None,
+    });
+    assert_eq!(exhausted, Some((3, None)));
+    // Two backoffs of at least half the 5ms and 10ms delays
+    assert!(started.elapsed() >= Duration::from_millis(7), "{:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn test_deploys_are_not_retried_once_the_backend_may_have_acted() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/deploy"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("failed halfway"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/domains"))
+        .respond_with(ResponseTemplate::new(504).set_body_string("upstream timed out"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let err = client.deploy_site(".", None, false).await.unwrap_err();
+    assert!(
+        matches!(err.downcast_ref::<HermesError>(), Some(HermesError::Rejected { status, .. }) if *status == 500),
+        "{:?}",
+        err
+    );
+    let err = client.register_domain("site.shadow", "Prog1").await.unwrap_err();
+    assert!(
+        matches!(err.downcast_ref::<HermesError>(), Some(HermesError::Rejected { status, .. }) if *status == 504),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn test_deploys_retry_requests_the_backend_turned_away() {
+    let server = MockServer::start().await;
+    for status in [502, 429] {
+        Mock::given(method("POST"))
+            .and(path("/api/sdk/deploy"))
+            .respond_with(ResponseTemplate::new(status))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    let deployed = serde_json::json!({ "program": "Prog1", "storage": "Store1", "domain": null, "mintedToken": false });
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/deploy"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(deployed))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert_eq!(client(&server).deploy_site(".", None, false).await.unwrap().attempts, 3);
+}
+
+#[tokio::test]
+async fn test_deploy_timeouts_are_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/sdk/deploy"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = builder(&server).timeout(Duration::from_millis(100)).build().unwrap();
+    let err = client.deploy_site(".", None, false).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<HermesError>(), Some(HermesError::Timeout(_))), "{:?}", err);
+    assert!(!err.chain().any(|cause| matches!(cause.downcast_ref(), Some(HermesError::RetriesExhausted { .. }))));
+}
+
+#[test]
+fn test_retries_come_from_the_config_file() {
+    let config: ClientConfig = serde_json::from_value(serde_json::json!({
+        "backend": "http://localhost:8787",
+        "network": "devnet",
+        "max_retries": 1,
+        "retry_delay_ms": 50,
+    }))
+    .unwrap();
+    let policy = config.retry_policy();
+    assert_eq!((policy.max_attempts, policy.base_delay), (2, Duration::from_millis(50)));
+
+    let config: ClientConfig =
+        serde_json::from_value(serde_json::json!({ "backend": "http://localhost:8787", "network": "devnet" })).unwrap();
+    assert_eq!((config.max_retries, config.retry_delay_ms), (3, 200));
+    assert_eq!(config.retry_policy().max_attempts, RetryPolicy::default().max_attempts);
+
+    // Clients built from one config share its HTTP client
+    let client = HermesClient::from_config(config.clone()).unwrap();
+    assert!(Arc::ptr_eq(&client.config().http, &config.http));
+    assert_eq!(client.config().max_retries, 3);
+}
@@ -0,0 +1,48 @@
+// Shared signing vectors: payloads the backend signed must verify here, and ours must match
+use hermes_client::{canonicalize, verify_signed_payload, SignedPayload};
+use serde_json::Value;
+
+fn vectors() -> Value {
+    serde_json::from_str(include_str!("../../shadow-signing/vectors.json")).unwrap()
+}
+
+#[test]
+fn test_canonical_vectors() {
+    let vectors = vectors();
+    for case in vectors["canonical"].as_array().unwrap() {
+        let input: Value = serde_json::from_str(case["input"].as_str().unwrap()).unwrap();
+        assert_eq!(canonicalize(&input), case["canonical"].as_str().unwrap(), "{}", case["input"]);
+    }
+}
+
+#[test]
+fn test_backend_signed_vectors_verify() {
+    let vectors = vectors();
+    let signer = vectors["signer"].as_str().unwrap();
+    for case in vectors["signed"].as_array().unwrap() {
+        let signed = SignedPayload {
+            payload: case["payload"].clone(),
+            signer: signer.to_string(),
+            signature: case["signature"].as_str().unwrap().to_string(),
+        };
+        assert_eq!(canonicalize(&signed.payload), case["canonical"].as_str().unwrap());
+        verify_signed_payload(&signed, signer).unwrap();
+
+        let mut tampered = signed.clone();
+        tampered.payload["tampered"] = Value::Bool(true);
+        assert!(verify_signed_payload(&tampered, signer).is_err());
+    }
+}
+
+#[test]
+fn test_client_signatures_match_vectors() {
+    let vectors = vectors();
+    let keypair = hex::decode(vectors["keypair"].as_str().unwrap()).unwrap();
+    let key = vectors["hmac_key"].as_str().unwrap().as_bytes();
+    for case in vectors["signed"].as_array().unwrap() {
+        let signed = shadow_signing::sign_payload(&keypair, &case["payload"]).unwrap();
+        assert_eq!(signed.signer, vectors["signer"].as_str().unwrap());
+        assert_eq!(signed.signature, case["signature"].as_str().unwrap());
+        assert_eq!(shadow_signing::hmac_payload(key, &case["payload"]).unwrap(), case["hmac"].as_str().unwrap());
+    }
+}
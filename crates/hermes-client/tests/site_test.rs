@@ -0,0 +1,105 @@
+// Looking up sites and domains
+use hermes_client::HermesClient;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> HermesClient {
+    HermesClient::builder().backend(server.uri()).build().unwrap()
+}
+
+fn site_json() -> serde_json::Value {
+    serde_json::json!({
+        "_id": "Prog1",
+        "owner_pubkey": "Owner1",
+        "storage_cid": "ipfs://bafy",
+        "name": null,
+        "description": "A site",
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-02-01T00:00:00Z",
+        "verified_domain": "site.shadow",
+        "revision": 3,
+        "controller": "Owner1"
+    })
+}
+
+#[tokio::test]
+async fn test_get_site_keeps_the_whole_response() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/sites/Prog1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(site_json()))
+        .mount(&server)
+        .await;
+
+    let site = client(&server).get_site("Prog1").await.unwrap().unwrap();
+    assert_eq!(site.program_address, "Prog1");
+    assert_eq!(site.storage_cid, "ipfs://bafy");
+    assert_eq!(site.verified_domain.as_deref(), Some("site.shadow"));
+    assert_eq!(serde_json::to_value(&site).unwrap(), site_json());
+}
+
+#[tokio::test]
+async fn test_lookups_return_none_when_not_registered() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({ "error": "Not found" })))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    assert!(client.get_site("Missing").await.unwrap().is_none());
+    assert!(client.get_domain("missing.shadow").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_get_domain_and_other_failures() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/domains/site.shadow"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "_id": "site.shadow",
+            "owner_pubkey": "Owner1",
+            "program_address": "Prog1",
+            "verified": true,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "expires_at": null
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/domains/bad..shadow"))
+        .respond_with(ResponseTemplate::new(400).set_body_string("invalid domain"))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    let domain = client.get_domain("site.shadow").await.unwrap().unwrap();
+    assert_eq!(domain.program_address, "Prog1");
+    assert!(domain.verified);
+    assert_eq!(domain.extra["expires_at"], serde_json::Value::Null);
+
+    let err = client.get_domain("bad..shadow").await.unwrap_err();
+    assert!(err.to_string().contains("invalid domain"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_content_check_fetches_a_single_byte() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/sites/Prog1/content"))
+        .and(header("Range", "bytes=0-0"))
+        .respond_with(ResponseTemplate::new(206).set_body_string("<"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/sites/Gone/content"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let client = client(&server);
+    assert!(client.content_available("Prog1").await.unwrap());
+    assert!(!client.content_available("Gone").await.unwrap());
+}
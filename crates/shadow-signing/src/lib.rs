@@ -0,0 +1,240 @@
+//! Canonical JSON and signing helpers shared by the Shadow backend and its clients.
+//!
+//! Anything that signs a JSON payload signs its canonical form, so a verifier that
+//! re-serializes the payload with different key order or spacing still gets the same bytes:
+//!
+//! - object keys sorted by Unicode code point, arrays left in order
+//! - no whitespace outside strings
+//! - strings escaped as serde_json does: `"`, `\` and control characters only
+//! - integers written exactly; other numbers as the shortest decimal that round-trips,
+//!   never in exponent form, with `-0` written as `0`
+//!
+//! Integers wider than 64 bits cannot be represented exactly once parsed, so payloads
+//! carrying them should use strings.
+
+use ed25519_dalek::{Keypair, PublicKey, Signature, Signer};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::fmt::Write as _;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum SigningError {
+    #[error("payload is not serializable JSON: {0}")]
+    Serialize(String),
+    #[error("invalid key: {0}")]
+    InvalidKey(String),
+    #[error("malformed signature")]
+    MalformedSignature,
+    #[error("payload was signed by {actual}, expected {expected}")]
+    UnexpectedSigner { expected: String, actual: String },
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+/// A JSON payload with the ed25519 signature of its canonical form
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedPayload {
+    pub payload: Value,
+    /// Signer public key (base58)
+    pub signer: String,
+    /// Signature over the canonical payload bytes (base58)
+    pub signature: String,
+}
+
+/// Canonical text of a JSON value
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+/// Canonical bytes of anything that serializes to JSON
+pub fn to_canonical_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SigningError> {
+    let value = serde_json::to_value(value).map_err(|e| SigningError::Serialize(e.to_string()))?;
+    Ok(canonicalize(&value).into_bytes())
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(n, out),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Sorted here rather than relying on Map, which keeps insertion order under preserve_order
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_canonical(item, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_number(n: &serde_json::Number, out: &mut String) {
+    if let Some(u) = n.as_u64() {
+        let _ = write!(out, "{}", u);
+    } else if let Some(i) = n.as_i64() {
+        let _ = write!(out, "{}", i);
+    } else {
+        // JSON numbers are always finite; Display is shortest round-trip without an exponent
+        let f = n.as_f64().unwrap_or_default();
+        let _ = write!(out, "{}", if f == 0.0 { 0.0 } else { f });
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push_str(&serde_json::to_string(s).unwrap_or_default());
+}
+
+/// Sign `message` with a 64-byte ed25519 keypair (secret then public, as Solana keypairs store it)
+pub fn sign_bytes(keypair: &[u8], message: &[u8]) -> Result<[u8; 64], SigningError> {
+    let keypair = Keypair::from_bytes(keypair).map_err(|e| SigningError::InvalidKey(e.to_string()))?;
+    Ok(keypair.sign(message).to_bytes())
+}
+
+/// Check an ed25519 signature over `message`, rejecting malleable signatures
+pub fn verify_bytes(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), SigningError> {
+    let public_key = PublicKey::from_bytes(public_key).map_err(|e| SigningError::InvalidKey(e.to_string()))?;
+    let signature = Signature::from_bytes(signature).map_err(|_| SigningError::MalformedSignature)?;
+    public_key
+        .verify_strict(message, &signature)
+        .map_err(|_| SigningError::InvalidSignature)
+}
+
+/// Sign the canonical form of `payload`
+pub fn sign_payload<T: Serialize + ?Sized>(keypair: &[u8], payload: &T) -> Result<SignedPayload, SigningError> {
+    let payload = serde_json::to_value(payload).map_err(|e| SigningError::Serialize(e.to_string()))?;
+    let signature = sign_bytes(keypair, canonicalize(&payload).as_bytes())?;
+    Ok(SignedPayload {
+        payload,
+        signer: bs58::encode(&keypair[32..]).into_string(),
+        signature: bs58::encode(signature).into_string(),
+    })
+}
+
+/// Check that `signed` was signed by `signer` (base58) over its canonical payload
+pub fn verify_signed_payload(signed: &SignedPayload, signer: &str) -> Result<(), SigningError> {
+    if signed.signer != signer {
+        return Err(SigningError::UnexpectedSigner {
+            expected: signer.to_string(),
+            actual: signed.signer.clone(),
+        });
+    }
+    let public_key = bs58::decode(signer)
+        .into_vec()
+        .map_err(|e| SigningError::InvalidKey(e.to_string()))?;
+    let signature = bs58::decode(&signed.signature)
+        .into_vec()
+        .map_err(|_| SigningError::MalformedSignature)?;
+    verify_bytes(&public_key, canonicalize(&signed.payload).as_bytes(), &signature)
+}
+
+/// HMAC-SHA256 of the canonical form of `payload`, hex encoded
+pub fn hmac_payload<T: Serialize + ?Sized>(key: &[u8], payload: &T) -> Result<String, SigningError> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&to_canonical_vec(payload)?);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Check a hex HMAC-SHA256 tag from [`hmac_payload`] in constant time
+pub fn verify_hmac_payload<T: Serialize + ?Sized>(key: &[u8], payload: &T, tag: &str) -> Result<(), SigningError> {
+    let tag = hex::decode(tag).map_err(|_| SigningError::MalformedSignature)?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&to_canonical_vec(payload)?);
+    mac.verify_slice(&tag).map_err(|_| SigningError::InvalidSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn keypair() -> Vec<u8> {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[7; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        Keypair { secret, public }.to_bytes().to_vec()
+    }
+
+    #[test]
+    fn test_keys_are_sorted_at_every_level() {
+        let value: Value = serde_json::from_str(r#"{ "b": 1, "a": { "d": [3, { "z": 0, "y": 1 }], "c": null } }"#).unwrap();
+        assert_eq!(canonicalize(&value), r#"{"a":{"c":null,"d":[3,{"y":1,"z":0}]},"b":1}"#);
+    }
+
+    #[test]
+    fn test_unicode_keys_sort_by_code_point() {
+        let value = json!({ "\u{1F600}": 1, "\u{E000}": 2, "é": 3, "z": 4, "Z": 5 });
+        assert_eq!(canonicalize(&value), "{\"Z\":5,\"z\":4,\"é\":3,\"\u{E000}\":2,\"\u{1F600}\":1}");
+    }
+
+    #[test]
+    fn test_strings_only_escape_what_json_requires() {
+        let value = json!(["a\"b\\c", "tab\there\n", "\u{1}", "</script>", "日本"]);
+        assert_eq!(canonicalize(&value), r#"["a\"b\\c","tab\there\n","\u0001","</script>","日本"]"#);
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        let value: Value = serde_json::from_str(
+            "[18446744073709551615, -9223372036854775808, 1.0, 1.50, -0.0, 0.1, 1e21, 1e-7]",
+        ).unwrap();
+        assert_eq!(
+            canonicalize(&value),
+            "[18446744073709551615,-9223372036854775808,1,1.5,0,0.1,1000000000000000000000,0.0000001]",
+        );
+    }
+
+    #[test]
+    fn test_nested_arrays_keep_their_order() {
+        let value = json!([[3, 2, 1], [], [[{}]], ["b", "a"]]);
+        assert_eq!(canonicalize(&value), r#"[[3,2,1],[],[[{}]],["b","a"]]"#);
+    }
+
+    #[test]
+    fn test_signature_ignores_key_order_and_spacing() {
+        let keypair = keypair();
+        let signed = sign_payload(&keypair, &json!({ "domain": "a.shadow", "exp": 10 })).unwrap();
+        let reordered = SignedPayload {
+            payload: serde_json::from_str(r#"{ "exp" : 10, "domain" : "a.shadow" }"#).unwrap(),
+            ..signed.clone()
+        };
+        verify_signed_payload(&reordered, &signed.signer).unwrap();
+
+        let tampered = SignedPayload { payload: json!({ "domain": "b.shadow", "exp": 10 }), ..signed.clone() };
+        assert_eq!(verify_signed_payload(&tampered, &signed.signer), Err(SigningError::InvalidSignature));
+
+        let other = bs58::encode(&[9; 32]).into_string();
+        assert!(matches!(
+            verify_signed_payload(&signed, &other),
+            Err(SigningError::UnexpectedSigner { .. })
+        ));
+    }
+
+    #[test]
+    fn test_hmac_round_trip() {
+        let tag = hmac_payload(b"secret", &json!({ "b": 2, "a": 1 })).unwrap();
+        verify_hmac_payload(b"secret", &json!({ "a": 1, "b": 2 }), &tag).unwrap();
+        assert!(verify_hmac_payload(b"other", &json!({ "a": 1, "b": 2 }), &tag).is_err());
+        assert_eq!(verify_hmac_payload(b"secret", &json!({}), "zz"), Err(SigningError::MalformedSignature));
+    }
+}
@@ -46,7 +46,7 @@ async function search(query: string): Promise<SearchResult[]> {
     // Fallback: Search profiles and sites (for partial matches)
     const profilesRes = await fetch(`${backendUrl}/api/profiles/search?q=${encodeURIComponent(query)}&limit=5`)
     if (profilesRes.ok) {
-      const profiles = await profilesRes.json()
+      const { items: profiles } = await profilesRes.json()
       results.push(...profiles.map((p: any) => ({
         id: `profile-${p.wallet_pubkey}`,
         label: shortenAddress(p.wallet_pubkey),
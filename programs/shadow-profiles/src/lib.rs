@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 
 declare_id!("8Z9Ax0rS4tN3nQ2xW6uV5gH7iL9kM1eB");
 
@@ -17,6 +18,9 @@ pub mod shadow_profiles {
         profile.is_public = is_public;
         profile.created_at = Clock::get()?.unix_timestamp;
         profile.updated_at = Clock::get()?.unix_timestamp;
+        profile.follower_count = 0;
+        profile.following_count = 0;
+        profile.username = None;
 
         msg!("Profile created for wallet: {}", profile.wallet);
         Ok(())
@@ -39,6 +43,143 @@ pub mod shadow_profiles {
         profile.updated_at = Clock::get()?.unix_timestamp;
         Ok(())
     }
+
+    /// Delete the caller's profile and return its rent, releasing its username and
+    /// unfollowing everyone in the same step. Pass as writable remaining accounts the username
+    /// record, when one is claimed, then each of the caller's follows followed by the followee's
+    /// profile; their rent goes back to the wallet too. Follows pointing at the profile can
+    /// still be closed by their owners. Creating the profile again starts over with a fresh `created_at`.
+    pub fn delete_profile<'info>(ctx: Context<'_, '_, 'info, 'info, DeleteProfile<'info>>) -> Result<()> {
+        let wallet = ctx.accounts.wallet.key();
+        let refund = ctx.accounts.wallet.to_account_info();
+        let mut remaining = ctx.remaining_accounts.iter();
+
+        if let Some(name) = &ctx.accounts.profile.username {
+            let record = remaining.next().ok_or(ShadowError::UsernameStillClaimed)?;
+            let record = Account::<UsernameRecord>::try_from(record)?;
+            require!(record.wallet == wallet && &record.name == name, ShadowError::UsernameStillClaimed);
+            record.close(refund.clone())?;
+        }
+
+        let mut following = ctx.accounts.profile.following_count;
+        while let Some(follow) = remaining.next() {
+            let follow = Account::<Follow>::try_from(follow)?;
+            require_keys_eq!(follow.follower, wallet, ShadowError::Unauthorized);
+            let followee = remaining.next().ok_or(ShadowError::FolloweeMismatch)?;
+            let (followee_profile, _) = Pubkey::find_program_address(&[b"profile", follow.followee.as_ref()], &crate::ID);
+            require_keys_eq!(followee.key(), followee_profile, ShadowError::FolloweeMismatch);
+            // As in `unfollow_profile`, a followee who deleted their profile has no count to update
+            if followee.owner == &crate::ID && !followee.data_is_empty() {
+                let mut data = followee.try_borrow_mut_data()?;
+                let mut followed = Profile::try_deserialize(&mut &data[..])?;
+                followed.follower_count = followed.follower_count.saturating_sub(1);
+                followed.try_serialize(&mut &mut data[..])?;
+            }
+            follow.close(refund.clone())?;
+            following = following.saturating_sub(1);
+        }
+        require!(following == 0, ShadowError::StillFollowing);
+
+        let profile = &ctx.accounts.profile;
+        emit!(ProfileDeleted {
+            profile: profile.key(),
+            wallet: profile.wallet,
+            at: Clock::get()?.unix_timestamp,
+        });
+        msg!("Profile deleted for wallet: {}", profile.wallet);
+        Ok(())
+    }
+
+    /// Grow a profile created by an older version of the program to the current size.
+    /// New fields start zeroed (no followers, no username); calling it on a current profile does nothing.
+    pub fn migrate_profile(ctx: Context<MigrateProfile>) -> Result<()> {
+        let profile = ctx.accounts.profile.to_account_info();
+        require!(
+            profile.try_borrow_data()?.starts_with(&Profile::DISCRIMINATOR),
+            ShadowError::NotAProfile
+        );
+
+        let new_len = 8 + Profile::LEN;
+        if profile.data_len() >= new_len {
+            return Ok(());
+        }
+
+        let rent = Rent::get()?.minimum_balance(new_len);
+        let top_up = rent.saturating_sub(profile.lamports());
+        if top_up > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.wallet.to_account_info(),
+                        to: profile.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        profile.realloc(new_len, true)?;
+
+        msg!("Profile migrated for wallet: {}", ctx.accounts.wallet.key());
+        Ok(())
+    }
+
+    /// Follow another profile; following the same profile twice fails because the follow PDA exists
+    pub fn follow_profile(ctx: Context<FollowProfile>, followee: Pubkey) -> Result<()> {
+        require_keys_neq!(followee, ctx.accounts.wallet.key(), ShadowError::CannotFollowSelf);
+
+        let follow = &mut ctx.accounts.follow;
+        follow.follower = ctx.accounts.wallet.key();
+        follow.followee = followee;
+        follow.created_at = Clock::get()?.unix_timestamp;
+
+        let follower = &mut ctx.accounts.follower_profile;
+        follower.following_count = follower.following_count.checked_add(1).ok_or(ShadowError::CounterOverflow)?;
+        let followed = &mut ctx.accounts.followee_profile;
+        followed.follower_count = followed.follower_count.checked_add(1).ok_or(ShadowError::CounterOverflow)?;
+        Ok(())
+    }
+
+    /// Claim a unique username for the caller's profile. The record PDA is seeded on the name,
+    /// so a second claim of the same name fails. Names are lowercase; clients lowercase first.
+    pub fn claim_username(ctx: Context<ClaimUsername>, name: String) -> Result<()> {
+        require!(is_valid_username(&name), ShadowError::InvalidUsername);
+        let profile = &mut ctx.accounts.profile;
+        require!(profile.username.is_none(), ShadowError::UsernameAlreadySet);
+
+        let record = &mut ctx.accounts.record;
+        record.wallet = ctx.accounts.wallet.key();
+        record.name = name.clone();
+        record.created_at = Clock::get()?.unix_timestamp;
+
+        profile.username = Some(name);
+        profile.updated_at = record.created_at;
+        Ok(())
+    }
+
+    /// Give up the profile's username; the record is closed so anyone can claim the name again
+    pub fn release_username(ctx: Context<ReleaseUsername>) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+        profile.username = None;
+        profile.updated_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Stop following; the follow account is closed and its rent goes back to the follower.
+    /// Works after the followee deleted their profile, in which case there is no count to update.
+    pub fn unfollow_profile(ctx: Context<UnfollowProfile>) -> Result<()> {
+        let follower = &mut ctx.accounts.follower_profile;
+        follower.following_count = follower.following_count.saturating_sub(1);
+
+        let followee = &ctx.accounts.followee_profile;
+        if followee.owner == &crate::ID && !followee.data_is_empty() {
+            let mut data = followee.try_borrow_mut_data()?;
+            let mut followed = Profile::try_deserialize(&mut &data[..])?;
+            followed.follower_count = followed.follower_count.saturating_sub(1);
+            followed.try_serialize(&mut &mut data[..])?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Accounts)]
@@ -71,6 +212,155 @@ pub struct UpdateProfile<'info> {
     pub wallet: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct DeleteProfile<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", wallet.key().as_ref()],
+        bump,
+        has_one = wallet @ ShadowError::Unauthorized,
+        close = wallet
+    )]
+    pub profile: Account<'info, Profile>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateProfile<'info> {
+    /// CHECK: Profiles created by older versions may not deserialize as `Profile`,
+    /// so the discriminator is checked by hand; the seeds tie it to the signing wallet
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"profile", wallet.key().as_ref()],
+        bump
+    )]
+    pub profile: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(followee: Pubkey)]
+pub struct FollowProfile<'info> {
+    #[account(
+        init,
+        payer = wallet,
+        space = 8 + Follow::LEN,
+        seeds = [b"follow", wallet.key().as_ref(), followee.as_ref()],
+        bump
+    )]
+    pub follow: Account<'info, Follow>,
+    
+    #[account(
+        mut,
+        seeds = [b"profile", wallet.key().as_ref()],
+        bump,
+        has_one = wallet @ ShadowError::Unauthorized
+    )]
+    pub follower_profile: Account<'info, Profile>,
+    
+    #[account(
+        mut,
+        seeds = [b"profile", followee.as_ref()],
+        bump
+    )]
+    pub followee_profile: Account<'info, Profile>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnfollowProfile<'info> {
+    #[account(
+        mut,
+        seeds = [b"follow", wallet.key().as_ref(), follow.followee.as_ref()],
+        bump,
+        constraint = follow.follower == wallet.key() @ ShadowError::Unauthorized,
+        close = wallet
+    )]
+    pub follow: Account<'info, Follow>,
+    
+    #[account(
+        mut,
+        seeds = [b"profile", wallet.key().as_ref()],
+        bump,
+        has_one = wallet @ ShadowError::Unauthorized
+    )]
+    pub follower_profile: Account<'info, Profile>,
+    
+    /// CHECK: The followee's profile, or an empty account once they have deleted it;
+    /// the seeds pin the address and `unfollow_profile` only writes it when it holds a profile
+    #[account(
+        mut,
+        seeds = [b"profile", follow.followee.as_ref()],
+        bump
+    )]
+    pub followee_profile: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ClaimUsername<'info> {
+    #[account(
+        init,
+        payer = wallet,
+        space = 8 + UsernameRecord::LEN,
+        seeds = [b"username", name.as_bytes()],
+        bump
+    )]
+    pub record: Account<'info, UsernameRecord>,
+    
+    #[account(
+        mut,
+        seeds = [b"profile", wallet.key().as_ref()],
+        bump,
+        has_one = wallet @ ShadowError::Unauthorized
+    )]
+    pub profile: Account<'info, Profile>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseUsername<'info> {
+    #[account(
+        mut,
+        seeds = [b"username", record.name.as_bytes()],
+        bump,
+        has_one = wallet @ ShadowError::Unauthorized,
+        close = wallet
+    )]
+    pub record: Account<'info, UsernameRecord>,
+    
+    #[account(
+        mut,
+        seeds = [b"profile", wallet.key().as_ref()],
+        bump,
+        has_one = wallet @ ShadowError::Unauthorized
+    )]
+    pub profile: Account<'info, Profile>,
+    
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+}
+
 #[account]
 pub struct Profile {
     pub wallet: Pubkey,
@@ -78,15 +368,75 @@ pub struct Profile {
     pub is_public: bool,
     pub created_at: i64,
     pub updated_at: i64,
+    pub follower_count: u64,
+    pub following_count: u64,
+    pub username: Option<String>,
 }
 
 impl Profile {
-    pub const LEN: usize = 32 + (4 + 100) + 1 + 8 + 8;
+    /// Profiles created before the counters and username were added are shorter; see `migrate_profile`
+    pub const LEN: usize = 32 + (4 + 100) + 1 + 8 + 8 + 8 + 8 + (1 + 4 + MAX_USERNAME_LEN);
+}
+
+pub const MIN_USERNAME_LEN: usize = 3;
+pub const MAX_USERNAME_LEN: usize = 32;
+
+/// 3-32 characters of a-z, 0-9 and underscore
+pub fn is_valid_username(name: &str) -> bool {
+    (MIN_USERNAME_LEN..=MAX_USERNAME_LEN).contains(&name.len())
+        && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+}
+
+/// Owner of a username; seeds ["username", name]
+#[account]
+pub struct UsernameRecord {
+    pub wallet: Pubkey,
+    pub name: String,
+    pub created_at: i64,
+}
+
+impl UsernameRecord {
+    pub const LEN: usize = 32 + (4 + MAX_USERNAME_LEN) + 8;
+}
+
+/// One wallet following another; seeds ["follow", follower, followee]
+#[account]
+pub struct Follow {
+    pub follower: Pubkey,
+    pub followee: Pubkey,
+    pub created_at: i64,
+}
+
+impl Follow {
+    pub const LEN: usize = 32 + 32 + 8;
+}
+
+#[event]
+pub struct ProfileDeleted {
+    pub profile: Pubkey,
+    pub wallet: Pubkey,
+    pub at: i64,
 }
 
 #[error_code]
 pub enum ShadowError {
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Account is not a profile")]
+    NotAProfile,
+    #[msg("A profile cannot follow itself")]
+    CannotFollowSelf,
+    #[msg("Counter overflow")]
+    CounterOverflow,
+    #[msg("Username must be 3-32 characters of a-z, 0-9 and _")]
+    InvalidUsername,
+    #[msg("Profile already has a username; release it first")]
+    UsernameAlreadySet,
+    #[msg("Pass the profile's username record to release it with the profile")]
+    UsernameStillClaimed,
+    #[msg("Pass every follow to close with the profile")]
+    StillFollowing,
+    #[msg("Each follow must be followed by its followee's profile")]
+    FolloweeMismatch,
 }
 
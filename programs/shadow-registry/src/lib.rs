@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 
 declare_id!("7Y8Zx9qR3sN2mP1wV5tU4fG6hK8jL0dA");
 
@@ -12,6 +13,9 @@ pub mod shadow_registry {
         description: String,
         storage_cid: String,
     ) -> Result<()> {
+        require!(name.len() <= MAX_NAME_LEN, ShadowError::NameTooLong);
+        require!(description.len() <= MAX_DESCRIPTION_LEN, ShadowError::DescriptionTooLong);
+        require!(storage_cid.len() <= MAX_CID_LEN, ShadowError::CidTooLong);
         let site = &mut ctx.accounts.site;
         site.owner = ctx.accounts.owner.key();
         site.program_address = ctx.accounts.program_account.key();
@@ -20,8 +24,17 @@ pub mod shadow_registry {
         site.storage_cid = storage_cid;
         site.created_at = Clock::get()?.unix_timestamp;
         site.updated_at = Clock::get()?.unix_timestamp;
+        site.version = 0;
+        site.editors = Vec::new();
 
         msg!("Site registered: {}", site.program_address);
+        emit!(SiteRegistered {
+            site: site.key(),
+            program_address: site.program_address,
+            owner: site.owner,
+            name: site.name.clone(),
+            at: site.created_at,
+        });
         Ok(())
     }
 
@@ -32,6 +45,15 @@ pub mod shadow_registry {
         storage_cid: Option<String>,
     ) -> Result<()> {
         let site = &mut ctx.accounts.site;
+        if let Some(cid) = &storage_cid {
+            require!(cid.len() <= MAX_CID_LEN, ShadowError::CidTooLong);
+        }
+        // Longer text needs `resize_site` first
+        let needed = Site::space(
+            name.as_ref().map_or(site.name.len(), String::len),
+            description.as_ref().map_or(site.description.len(), String::len),
+        );
+        require!(needed <= site.to_account_info().data_len(), ShadowError::SiteTooSmall);
         
         if let Some(n) = name {
             site.name = n;
@@ -44,6 +66,230 @@ pub mod shadow_registry {
         }
         
         site.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(SiteUpdated {
+            site: site.key(),
+            program_address: site.program_address,
+            owner: site.owner,
+            updated_by: ctx.accounts.authority.key(),
+            at: site.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Make room for a name and description of the given byte lengths. The owner pays for
+    /// growing the account and gets the rent back when it shrinks.
+    pub fn resize_site(ctx: Context<ResizeSite>, name_len: u32, description_len: u32) -> Result<()> {
+        require!(name_len as usize <= MAX_NAME_LEN, ShadowError::NameTooLong);
+        require!(description_len as usize <= MAX_DESCRIPTION_LEN, ShadowError::DescriptionTooLong);
+        let site = &ctx.accounts.site;
+        require!(
+            site.name.len() <= name_len as usize && site.description.len() <= description_len as usize,
+            ShadowError::SiteTooSmall
+        );
+
+        msg!("Site resized: {}", site.program_address);
+        Ok(())
+    }
+
+    /// Let `editor` update the site alongside the owner. At most `MAX_EDITORS` at a time.
+    pub fn add_editor(ctx: Context<ManageEditors>, editor: Pubkey) -> Result<()> {
+        require_keys_neq!(editor, Pubkey::default(), ShadowError::InvalidEditor);
+        let site = &mut ctx.accounts.site;
+        require_keys_neq!(editor, site.owner, ShadowError::InvalidEditor);
+        require!(!site.editors.contains(&editor), ShadowError::EditorAlreadyAdded);
+        require!(site.editors.len() < MAX_EDITORS, ShadowError::TooManyEditors);
+        site.editors.push(editor);
+        site.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(EditorAdded {
+            site: site.key(),
+            program_address: site.program_address,
+            editor,
+            at: site.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Take `editor` off the site's editor list
+    pub fn remove_editor(ctx: Context<ManageEditors>, editor: Pubkey) -> Result<()> {
+        let site = &mut ctx.accounts.site;
+        let index = site.editors.iter().position(|e| *e == editor).ok_or(ShadowError::EditorNotFound)?;
+        site.editors.remove(index);
+        site.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(EditorRemoved {
+            site: site.key(),
+            program_address: site.program_address,
+            editor,
+            at: site.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Deploy new content as the site's next version. Each version keeps its CID in a
+    /// `SiteVersion` account so the site can be rolled back to it later.
+    pub fn publish_version(ctx: Context<PublishVersion>, storage_cid: String) -> Result<()> {
+        require!(storage_cid.len() <= MAX_CID_LEN, ShadowError::CidTooLong);
+        let site = &mut ctx.accounts.site;
+        site.version = site.version.checked_add(1).ok_or(ShadowError::VersionOverflow)?;
+        site.storage_cid = storage_cid.clone();
+        site.updated_at = Clock::get()?.unix_timestamp;
+
+        let version = &mut ctx.accounts.site_version;
+        version.site = site.key();
+        version.version = site.version;
+        version.storage_cid = storage_cid;
+        version.published_at = site.updated_at;
+
+        emit!(VersionPublished {
+            site: site.key(),
+            program_address: site.program_address,
+            version: version.version,
+            storage_cid: version.storage_cid.clone(),
+            at: version.published_at,
+        });
+        Ok(())
+    }
+
+    /// Point the site back at a published version's content. The version counter is left
+    /// alone, so the next publish still gets a new number.
+    pub fn rollback(ctx: Context<Rollback>, version: u32) -> Result<()> {
+        // A number past the counter can only be left over from before the site was re-registered
+        require!(version <= ctx.accounts.site.version, ShadowError::VersionNotPublished);
+        let site = &mut ctx.accounts.site;
+        site.storage_cid = ctx.accounts.site_version.storage_cid.clone();
+        site.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(SiteRolledBack {
+            site: site.key(),
+            program_address: site.program_address,
+            version,
+            storage_cid: site.storage_cid.clone(),
+            at: site.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Grow a site registered by an older version of the program to the current layout.
+    /// New fields start zeroed (no published versions, no editors); calling it on a current site
+    /// does nothing. Sites must be migrated before `update_site` or `add_editor` can load them.
+    pub fn migrate_site(ctx: Context<MigrateSite>) -> Result<()> {
+        let site = ctx.accounts.site.to_account_info();
+        require!(
+            site.try_borrow_data()?.starts_with(&Site::DISCRIMINATOR),
+            ShadowError::NotASite
+        );
+
+        let new_len = Site::space_for(&site.try_borrow_data()?)?;
+        if site.data_len() >= new_len {
+            return Ok(());
+        }
+
+        let rent = Rent::get()?.minimum_balance(new_len);
+        let top_up = rent.saturating_sub(site.lamports());
+        if top_up > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: site.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        site.realloc(new_len, true)?;
+
+        msg!("Site migrated: {}", ctx.accounts.site.key());
+        Ok(())
+    }
+
+    /// Take a site down; the account is closed and its rent goes back to the owner.
+    /// Pass the site's `SiteVersion` accounts as writable remaining accounts to close them too.
+    /// The same program address can be registered again afterwards; versions left behind are
+    /// overwritten as it publishes and can't be rolled back to before then.
+    pub fn delete_site<'info>(ctx: Context<'_, '_, 'info, 'info, DeleteSite<'info>>) -> Result<()> {
+        let site = ctx.accounts.site.key();
+        let owner = ctx.accounts.owner.to_account_info();
+        for account in ctx.remaining_accounts {
+            let version = Account::<SiteVersion>::try_from(account)?;
+            require_keys_eq!(version.site, site, ShadowError::ForeignVersion);
+            version.close(owner.clone())?;
+        }
+
+        msg!("Site deleted: {}", ctx.accounts.site.program_address);
+        Ok(())
+    }
+
+    /// Hand the site straight to `new_owner`, which must also be passed as the `new_owner` account
+    pub fn transfer_ownership(ctx: Context<TransferOwnership>, new_owner: Pubkey) -> Result<()> {
+        require_keys_neq!(new_owner, Pubkey::default(), ShadowError::InvalidNewOwner);
+        require_keys_eq!(ctx.accounts.new_owner.key(), new_owner, ShadowError::InvalidNewOwner);
+        let site = &mut ctx.accounts.site;
+        let previous_owner = site.owner;
+        site.owner = new_owner;
+        site.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(OwnershipTransferred {
+            site: site.key(),
+            program_address: site.program_address,
+            previous_owner,
+            new_owner,
+            at: site.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Start a two-step transfer; nothing changes until `new_owner` calls `accept_ownership`.
+    /// Only one transfer can be pending, so cancel it first to propose someone else.
+    pub fn propose_ownership(ctx: Context<ProposeOwnership>, new_owner: Pubkey) -> Result<()> {
+        require_keys_neq!(new_owner, Pubkey::default(), ShadowError::InvalidNewOwner);
+        let transfer = &mut ctx.accounts.transfer;
+        transfer.site = ctx.accounts.site.key();
+        transfer.proposed_by = ctx.accounts.owner.key();
+        transfer.new_owner = new_owner;
+        transfer.proposed_at = Clock::get()?.unix_timestamp;
+
+        emit!(OwnershipProposed {
+            site: transfer.site,
+            program_address: ctx.accounts.site.program_address,
+            owner: transfer.proposed_by,
+            new_owner,
+            at: transfer.proposed_at,
+        });
+        Ok(())
+    }
+
+    /// Complete a pending transfer; the pending account's rent goes back to whoever proposed it
+    pub fn accept_ownership(ctx: Context<AcceptOwnership>) -> Result<()> {
+        let site = &mut ctx.accounts.site;
+        // The site changed hands some other way since the proposal was made
+        require_keys_eq!(ctx.accounts.transfer.proposed_by, site.owner, ShadowError::StaleTransfer);
+
+        let previous_owner = site.owner;
+        site.owner = ctx.accounts.new_owner.key();
+        site.updated_at = Clock::get()?.unix_timestamp;
+
+        emit!(OwnershipTransferred {
+            site: site.key(),
+            program_address: site.program_address,
+            previous_owner,
+            new_owner: site.owner,
+            at: site.updated_at,
+        });
+        Ok(())
+    }
+
+    /// Drop a pending transfer before it is accepted
+    pub fn cancel_ownership_transfer(ctx: Context<CancelOwnershipTransfer>) -> Result<()> {
+        emit!(OwnershipTransferCancelled {
+            site: ctx.accounts.site.key(),
+            program_address: ctx.accounts.site.program_address,
+            new_owner: ctx.accounts.transfer.new_owner,
+            at: Clock::get()?.unix_timestamp,
+        });
         Ok(())
     }
 }
@@ -54,7 +300,7 @@ pub struct RegisterSite<'info> {
     #[account(
         init,
         payer = owner,
-        space = 8 + Site::LEN,
+        space = Site::space(name.len(), description.len()),
         seeds = [b"site", program_account.key().as_ref()],
         bump
     )]
@@ -71,6 +317,40 @@ pub struct RegisterSite<'info> {
 
 #[derive(Accounts)]
 pub struct UpdateSite<'info> {
+    #[account(
+        mut,
+        seeds = [b"site", site.program_address.as_ref()],
+        bump,
+        constraint = site.can_edit(&authority.key()) @ ShadowError::Unauthorized
+    )]
+    pub site: Account<'info, Site>,
+    
+    /// The owner or one of the site's editors
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name_len: u32, description_len: u32)]
+pub struct ResizeSite<'info> {
+    #[account(
+        mut,
+        seeds = [b"site", site.program_address.as_ref()],
+        bump,
+        has_one = owner @ ShadowError::Unauthorized,
+        realloc = Site::space(name_len as usize, description_len as usize),
+        realloc::payer = owner,
+        realloc::zero = false
+    )]
+    pub site: Account<'info, Site>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ManageEditors<'info> {
     #[account(
         mut,
         seeds = [b"site", site.program_address.as_ref()],
@@ -82,6 +362,179 @@ pub struct UpdateSite<'info> {
     pub owner: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct PublishVersion<'info> {
+    #[account(
+        mut,
+        seeds = [b"site", site.program_address.as_ref()],
+        bump,
+        has_one = owner @ ShadowError::Unauthorized
+    )]
+    pub site: Account<'info, Site>,
+    
+    /// May still hold a version from before the site was deleted and registered again;
+    /// publishing takes it over
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + SiteVersion::LEN,
+        seeds = [b"version", site.key().as_ref(), &site.version.wrapping_add(1).to_le_bytes()],
+        bump
+    )]
+    pub site_version: Account<'info, SiteVersion>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(version: u32)]
+pub struct Rollback<'info> {
+    #[account(
+        mut,
+        seeds = [b"site", site.program_address.as_ref()],
+        bump,
+        has_one = owner @ ShadowError::Unauthorized
+    )]
+    pub site: Account<'info, Site>,
+    
+    /// Versions that were never published have no account here, so they fail to load
+    #[account(
+        seeds = [b"version", site.key().as_ref(), &version.to_le_bytes()],
+        bump,
+        has_one = site
+    )]
+    pub site_version: Account<'info, SiteVersion>,
+    
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateSite<'info> {
+    /// CHECK: Sites registered by older versions may not deserialize as `Site`,
+    /// so the discriminator is checked by hand. Growing a site changes nothing but its size,
+    /// so anyone may pay for it.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"site", program_account.key().as_ref()],
+        bump
+    )]
+    pub site: UncheckedAccount<'info>,
+    
+    /// CHECK: The registered program account; only used to derive the site address
+    pub program_account: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteSite<'info> {
+    #[account(
+        mut,
+        seeds = [b"site", site.program_address.as_ref()],
+        bump,
+        has_one = owner @ ShadowError::Unauthorized,
+        close = owner
+    )]
+    pub site: Account<'info, Site>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferOwnership<'info> {
+    #[account(
+        mut,
+        seeds = [b"site", site.program_address.as_ref()],
+        bump,
+        has_one = owner @ ShadowError::InvalidOwner
+    )]
+    pub site: Account<'info, Site>,
+    
+    pub owner: Signer<'info>,
+    
+    /// CHECK: Only its address is recorded as the site's owner; checked against the argument
+    pub new_owner: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeOwnership<'info> {
+    #[account(
+        seeds = [b"site", site.program_address.as_ref()],
+        bump,
+        has_one = owner @ ShadowError::Unauthorized
+    )]
+    pub site: Account<'info, Site>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + OwnershipTransfer::LEN,
+        seeds = [b"transfer", site.key().as_ref()],
+        bump
+    )]
+    pub transfer: Account<'info, OwnershipTransfer>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwnership<'info> {
+    #[account(
+        mut,
+        seeds = [b"site", site.program_address.as_ref()],
+        bump
+    )]
+    pub site: Account<'info, Site>,
+    
+    #[account(
+        mut,
+        seeds = [b"transfer", site.key().as_ref()],
+        bump,
+        has_one = new_owner @ ShadowError::Unauthorized,
+        has_one = proposed_by,
+        close = proposed_by
+    )]
+    pub transfer: Account<'info, OwnershipTransfer>,
+    
+    pub new_owner: Signer<'info>,
+    
+    /// CHECK: Only receives the pending account's rent; pinned by `has_one` on `transfer`
+    #[account(mut)]
+    pub proposed_by: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelOwnershipTransfer<'info> {
+    #[account(
+        seeds = [b"site", site.program_address.as_ref()],
+        bump,
+        has_one = owner @ ShadowError::Unauthorized
+    )]
+    pub site: Account<'info, Site>,
+    
+    #[account(
+        mut,
+        seeds = [b"transfer", site.key().as_ref()],
+        bump,
+        close = owner
+    )]
+    pub transfer: Account<'info, OwnershipTransfer>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
 #[account]
 pub struct Site {
     pub owner: Pubkey,
@@ -91,15 +544,183 @@ pub struct Site {
     pub storage_cid: String,
     pub created_at: i64,
     pub updated_at: i64,
+    /// Latest published version; 0 until the first `publish_version`
+    pub version: u32,
+    /// Wallets besides the owner allowed to call `update_site`
+    pub editors: Vec<Pubkey>,
 }
 
 impl Site {
-    pub const LEN: usize = 32 + 32 + (4 + 100) + (4 + 500) + (4 + 100) + 8 + 8;
+    /// Account size, discriminator included, for a name and description of these byte lengths.
+    /// The CID and editor list always get their full size since they change without a resize.
+    pub fn space(name_len: usize, description_len: usize) -> usize {
+        8 + 32 + 32 + (4 + name_len) + (4 + description_len) + (4 + MAX_CID_LEN) + 8 + 8 + 4
+            + (4 + 32 * MAX_EDITORS)
+    }
+
+    /// `space` for a site's current name and description, read from its raw account data.
+    /// Works for sites registered before versioning or editors, which end earlier.
+    pub fn space_for(data: &[u8]) -> Result<usize> {
+        let len_at = |offset: usize| -> Result<usize> {
+            let bytes = data.get(offset..offset + 4).ok_or(ShadowError::NotASite)?;
+            Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        };
+        let name_len = len_at(8 + 32 + 32)?;
+        let description_len = len_at(8 + 32 + 32 + 4 + name_len)?;
+        Ok(Self::space(name_len, description_len))
+    }
+
+    pub fn can_edit(&self, wallet: &Pubkey) -> bool {
+        self.owner == *wallet || self.editors.contains(wallet)
+    }
+}
+
+pub const MAX_NAME_LEN: usize = 100;
+
+pub const MAX_DESCRIPTION_LEN: usize = 500;
+
+pub const MAX_CID_LEN: usize = 100;
+
+pub const MAX_EDITORS: usize = 5;
+
+/// Content of one published version; seeds ["version", site, version as u32 LE]
+#[account]
+pub struct SiteVersion {
+    pub site: Pubkey,
+    pub version: u32,
+    pub storage_cid: String,
+    pub published_at: i64,
+}
+
+impl SiteVersion {
+    pub const LEN: usize = 32 + 4 + (4 + MAX_CID_LEN) + 8;
+}
+
+/// A two-step ownership transfer waiting on the new owner
+#[account]
+pub struct OwnershipTransfer {
+    pub site: Pubkey,
+    pub proposed_by: Pubkey,
+    pub new_owner: Pubkey,
+    pub proposed_at: i64,
+}
+
+impl OwnershipTransfer {
+    pub const LEN: usize = 32 + 32 + 32 + 8;
+}
+
+#[event]
+pub struct SiteRegistered {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub owner: Pubkey,
+    pub name: String,
+    pub at: i64,
+}
+
+#[event]
+pub struct SiteUpdated {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub owner: Pubkey,
+    /// The owner or the editor that signed the update
+    pub updated_by: Pubkey,
+    pub at: i64,
+}
+
+#[event]
+pub struct VersionPublished {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub version: u32,
+    pub storage_cid: String,
+    pub at: i64,
+}
+
+#[event]
+pub struct SiteRolledBack {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub version: u32,
+    pub storage_cid: String,
+    pub at: i64,
+}
+
+#[event]
+pub struct EditorAdded {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub editor: Pubkey,
+    pub at: i64,
+}
+
+#[event]
+pub struct EditorRemoved {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub editor: Pubkey,
+    pub at: i64,
+}
+
+#[event]
+pub struct OwnershipTransferred {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub previous_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub at: i64,
+}
+
+#[event]
+pub struct OwnershipProposed {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub at: i64,
+}
+
+#[event]
+pub struct OwnershipTransferCancelled {
+    pub site: Pubkey,
+    pub program_address: Pubkey,
+    pub new_owner: Pubkey,
+    pub at: i64,
 }
 
 #[error_code]
 pub enum ShadowError {
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("New owner must be a real account")]
+    InvalidNewOwner,
+    #[msg("Site owner changed after the transfer was proposed")]
+    StaleTransfer,
+    #[msg("Account is not a site")]
+    NotASite,
+    #[msg("Storage CID is too long")]
+    CidTooLong,
+    #[msg("Version counter overflow")]
+    VersionOverflow,
+    #[msg("Editor must be a real account other than the owner")]
+    InvalidEditor,
+    #[msg("Wallet is already an editor")]
+    EditorAlreadyAdded,
+    #[msg("Site already has the maximum number of editors")]
+    TooManyEditors,
+    #[msg("Wallet is not an editor")]
+    EditorNotFound,
+    #[msg("Site name is too long")]
+    NameTooLong,
+    #[msg("Site description is too long")]
+    DescriptionTooLong,
+    #[msg("Site account is too small; call resize_site first")]
+    SiteTooSmall,
+    #[msg("Signer is not the site's owner")]
+    InvalidOwner,
+    #[msg("Version has not been published")]
+    VersionNotPublished,
+    #[msg("Version belongs to another site")]
+    ForeignVersion,
 }
 
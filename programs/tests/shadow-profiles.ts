@@ -0,0 +1,326 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { ShadowProfiles } from "../target/types/shadow_profiles";
+
+describe("shadow-profiles", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.ShadowProfiles as Program<ShadowProfiles>;
+
+  // Discriminator plus Profile::LEN
+  const PROFILE_SPACE = 8 + 32 + (4 + 100) + 1 + 8 + 8 + 8 + 8 + (1 + 4 + 32);
+
+  const profilePda = (wallet: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("profile"), wallet.toBuffer()], program.programId)[0];
+
+  const followPda = (follower: PublicKey, followee: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("follow"), follower.toBuffer(), followee.toBuffer()],
+      program.programId
+    )[0];
+
+  const usernamePda = (name: string) =>
+    PublicKey.findProgramAddressSync([Buffer.from("username"), Buffer.from(name)], program.programId)[0];
+
+  const walletWithProfile = async () => {
+    const wallet = Keypair.generate();
+    const airdrop = await provider.connection.requestAirdrop(wallet.publicKey, LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(airdrop, "confirmed");
+    await program.methods
+      .createProfile("ipfs://profile", true)
+      .accounts({ profile: profilePda(wallet.publicKey), wallet: wallet.publicKey })
+      .signers([wallet])
+      .rpc();
+    return wallet;
+  };
+
+  const follow = (wallet: Keypair, followee: PublicKey) =>
+    program.methods
+      .followProfile(followee)
+      .accounts({
+        follow: followPda(wallet.publicKey, followee),
+        followerProfile: profilePda(wallet.publicKey),
+        followeeProfile: profilePda(followee),
+        wallet: wallet.publicKey,
+      })
+      .signers([wallet])
+      .rpc();
+
+  const unfollow = (wallet: Keypair, followee: PublicKey) =>
+    program.methods
+      .unfollowProfile()
+      .accounts({
+        follow: followPda(wallet.publicKey, followee),
+        followerProfile: profilePda(wallet.publicKey),
+        followeeProfile: profilePda(followee),
+        wallet: wallet.publicKey,
+      })
+      .signers([wallet])
+      .rpc();
+
+  const claim = (wallet: Keypair, name: string) =>
+    program.methods
+      .claimUsername(name)
+      .accounts({ record: usernamePda(name), profile: profilePda(wallet.publicKey), wallet: wallet.publicKey })
+      .signers([wallet])
+      .rpc();
+
+  const release = (wallet: Keypair, name: string) =>
+    program.methods
+      .releaseUsername()
+      .accounts({ record: usernamePda(name), profile: profilePda(wallet.publicKey), wallet: wallet.publicKey })
+      .signers([wallet])
+      .rpc();
+
+  const deleteWith = (wallet: Keypair, closing: PublicKey[]) =>
+    program.methods
+      .deleteProfile()
+      .accounts({ profile: profilePda(wallet.publicKey), wallet: wallet.publicKey })
+      .remainingAccounts(closing.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })))
+      .signers([wallet])
+      .rpc();
+
+  // Closes the username record and the follows to `followees` along with the profile
+  const deleteProfile = (wallet: Keypair, name: string | null = null, followees: PublicKey[] = []) =>
+    deleteWith(wallet, [
+      ...(name ? [usernamePda(name)] : []),
+      ...followees.flatMap((followee) => [followPda(wallet.publicKey, followee), profilePda(followee)]),
+    ]);
+
+  const counts = async (wallet: PublicKey) => {
+    const profile = await program.account.profile.fetch(profilePda(wallet));
+    return [profile.followerCount.toNumber(), profile.followingCount.toNumber()];
+  };
+
+  it("rejects following the same profile twice", async () => {
+    const [alice, bob] = [await walletWithProfile(), await walletWithProfile()];
+    await follow(alice, bob.publicKey);
+
+    try {
+      await follow(alice, bob.publicKey);
+      expect.fail("followed twice");
+    } catch (err) {
+      expect(String(err)).to.contain("already in use");
+    }
+    expect(await counts(alice.publicKey)).to.deep.equal([0, 1]);
+    expect(await counts(bob.publicKey)).to.deep.equal([1, 0]);
+  });
+
+  it("rejects following yourself", async () => {
+    const alice = await walletWithProfile();
+    try {
+      await follow(alice, alice.publicKey);
+      expect.fail("followed self");
+    } catch (err) {
+      expect(String(err)).to.contain("CannotFollowSelf");
+    }
+  });
+
+  it("keeps counters consistent after unfollowing", async () => {
+    const [alice, bob, carol] = [await walletWithProfile(), await walletWithProfile(), await walletWithProfile()];
+    await follow(alice, carol.publicKey);
+    await follow(bob, carol.publicKey);
+    await follow(carol, alice.publicKey);
+    expect(await counts(carol.publicKey)).to.deep.equal([2, 1]);
+
+    await unfollow(alice, carol.publicKey);
+    expect(await program.account.follow.fetchNullable(followPda(alice.publicKey, carol.publicKey))).to.be.null;
+    expect(await counts(alice.publicKey)).to.deep.equal([1, 0]);
+    expect(await counts(carol.publicKey)).to.deep.equal([1, 1]);
+
+    // Following again after unfollowing works and counts once
+    await follow(alice, carol.publicKey);
+    expect(await counts(carol.publicKey)).to.deep.equal([2, 1]);
+
+    // Nobody else can close someone's follow
+    try {
+      await program.methods
+        .unfollowProfile()
+        .accounts({
+          follow: followPda(bob.publicKey, carol.publicKey),
+          followerProfile: profilePda(alice.publicKey),
+          followeeProfile: profilePda(carol.publicKey),
+          wallet: alice.publicKey,
+        })
+        .signers([alice])
+        .rpc();
+      expect.fail("unfollowed on someone else's behalf");
+    } catch (err) {
+      expect(String(err)).to.match(/ConstraintSeeds|Unauthorized/);
+    }
+    expect(await counts(carol.publicKey)).to.deep.equal([2, 1]);
+  });
+
+  it("leaves current profiles alone when migrating", async () => {
+    const alice = await walletWithProfile();
+    const profile = profilePda(alice.publicKey);
+    const before = await provider.connection.getAccountInfo(profile);
+
+    await program.methods.migrateProfile().accounts({ profile, wallet: alice.publicKey }).signers([alice]).rpc();
+    const after = await provider.connection.getAccountInfo(profile);
+    expect(after!.data.length).to.equal(before!.data.length);
+    expect(after!.lamports).to.equal(before!.lamports);
+  });
+
+  it("gives each username to one wallet at a time", async () => {
+    const [alice, bob] = [await walletWithProfile(), await walletWithProfile()];
+    const name = `shade_${Date.now() % 1_000_000}`;
+    await claim(alice, name);
+
+    const record = await program.account.usernameRecord.fetch(usernamePda(name));
+    expect(record.wallet.toBase58()).to.equal(alice.publicKey.toBase58());
+    expect((await program.account.profile.fetch(profilePda(alice.publicKey))).username).to.equal(name);
+
+    try {
+      await claim(bob, name);
+      expect.fail("second wallet claimed a taken username");
+    } catch (err) {
+      expect(String(err)).to.contain("already in use");
+    }
+
+    // Only the holder can release a name
+    try {
+      await program.methods
+        .releaseUsername()
+        .accounts({ record: usernamePda(name), profile: profilePda(bob.publicKey), wallet: bob.publicKey })
+        .signers([bob])
+        .rpc();
+      expect.fail("released someone else's username");
+    } catch (err) {
+      expect(String(err)).to.contain("Unauthorized");
+    }
+
+    await release(alice, name);
+    expect(await program.account.usernameRecord.fetchNullable(usernamePda(name))).to.be.null;
+    expect((await program.account.profile.fetch(profilePda(alice.publicKey))).username).to.be.null;
+
+    await claim(bob, name);
+    const reclaimed = await program.account.usernameRecord.fetch(usernamePda(name));
+    expect(reclaimed.wallet.toBase58()).to.equal(bob.publicKey.toBase58());
+  });
+
+  it("rejects malformed usernames and a second name per profile", async () => {
+    const alice = await walletWithProfile();
+    // Names over 32 bytes cannot even be turned into a record address, so the client stops those
+    for (const name of ["ab", "Shade", "sha-de", "shade!"]) {
+      try {
+        await claim(alice, name);
+        expect.fail(`claimed ${name}`);
+      } catch (err) {
+        expect(String(err)).to.contain("InvalidUsername");
+      }
+    }
+
+    const first = `first_${Date.now() % 1_000_000}`;
+    await claim(alice, first);
+    try {
+      await claim(alice, `second_${Date.now() % 1_000_000}`);
+      expect.fail("claimed two usernames");
+    } catch (err) {
+      expect(String(err)).to.contain("UsernameAlreadySet");
+    }
+  });
+
+  it("deletes a profile, refunds its rent and lets it be created again", async () => {
+    const alice = await walletWithProfile();
+    const profile = profilePda(alice.publicKey);
+    const { createdAt } = await program.account.profile.fetch(profile);
+    const rent = (await provider.connection.getAccountInfo(profile))!.lamports;
+    const balance = await provider.connection.getBalance(alice.publicKey);
+
+    const events: any[] = [];
+    const listener = program.addEventListener("ProfileDeleted", (event) => events.push(event));
+    await deleteProfile(alice);
+    await new Promise((resolve) => setTimeout(resolve, 500));
+    await program.removeEventListener(listener);
+    expect(await provider.connection.getAccountInfo(profile)).to.be.null;
+    try {
+      await program.account.profile.fetch(profile);
+      expect.fail("deleted profile still loads");
+    } catch (err) {
+      expect(String(err)).to.contain("Account does not exist");
+    }
+    // The refund covers the rent, at least a lamport per byte of the account; the fee payer
+    // is the provider wallet
+    expect(rent).to.be.at.least(PROFILE_SPACE);
+    expect(await provider.connection.getBalance(alice.publicKey)).to.equal(balance + rent);
+    expect(events).to.have.length(1);
+    expect(events[0].wallet.toBase58()).to.equal(alice.publicKey.toBase58());
+    expect(events[0].profile.toBase58()).to.equal(profile.toBase58());
+
+    await new Promise((resolve) => setTimeout(resolve, 1500));
+    await program.methods
+      .createProfile("ipfs://again", false)
+      .accounts({ profile, wallet: alice.publicKey })
+      .signers([alice])
+      .rpc();
+    const recreated = await program.account.profile.fetch(profile);
+    expect(recreated.profileCid).to.equal("ipfs://again");
+    expect(recreated.createdAt.toNumber()).to.be.greaterThan(createdAt.toNumber());
+    expect(recreated.username).to.be.null;
+  });
+
+  it("only lets the owner delete a profile", async () => {
+    const [alice, bob] = [await walletWithProfile(), await walletWithProfile()];
+    try {
+      await program.methods
+        .deleteProfile()
+        .accounts({ profile: profilePda(alice.publicKey), wallet: bob.publicKey })
+        .signers([bob])
+        .rpc();
+      expect.fail("deleted someone else's profile");
+    } catch (err) {
+      expect(String(err)).to.match(/ConstraintSeeds|Unauthorized/);
+    }
+    expect(await program.account.profile.fetchNullable(profilePda(alice.publicKey))).to.not.be.null;
+  });
+
+  it("releases the username and unfollows in the same step as deleting", async () => {
+    const [alice, bob, carol] = [await walletWithProfile(), await walletWithProfile(), await walletWithProfile()];
+    const name = `gone_${Date.now() % 1_000_000}`;
+    await claim(alice, name);
+    await follow(alice, bob.publicKey);
+    await follow(alice, carol.publicKey);
+    await follow(bob, alice.publicKey);
+
+    // Everything has to be passed, so nothing is left behind pointing at a closed profile
+    for (const [attempt, error] of [
+      [() => deleteProfile(alice), "UsernameStillClaimed"],
+      [() => deleteProfile(alice, name, [bob.publicKey]), "StillFollowing"],
+      [
+        () => deleteWith(alice, [
+          usernamePda(name),
+          followPda(alice.publicKey, bob.publicKey),
+          profilePda(carol.publicKey),
+          followPda(alice.publicKey, carol.publicKey),
+          profilePda(carol.publicKey),
+        ]),
+        "FolloweeMismatch",
+      ],
+    ] as const) {
+      try {
+        await attempt();
+        expect.fail("deleted a profile without its username and follows");
+      } catch (err) {
+        expect(String(err)).to.contain(error);
+      }
+    }
+    const before = await provider.connection.getBalance(alice.publicKey);
+    await deleteProfile(alice, name, [bob.publicKey, carol.publicKey]);
+    expect(await program.account.profile.fetchNullable(profilePda(alice.publicKey))).to.be.null;
+    expect(await program.account.usernameRecord.fetchNullable(usernamePda(name))).to.be.null;
+    expect(await program.account.follow.fetchNullable(followPda(alice.publicKey, bob.publicKey))).to.be.null;
+    expect(await program.account.follow.fetchNullable(followPda(alice.publicKey, carol.publicKey))).to.be.null;
+    expect(await provider.connection.getBalance(alice.publicKey)).to.be.greaterThan(before);
+    expect(await counts(bob.publicKey)).to.deep.equal([0, 1]);
+    expect(await counts(carol.publicKey)).to.deep.equal([0, 0]);
+
+    // The name is free again, and Bob can still unfollow the deleted profile and get the follow rent back
+    await claim(carol, name);
+    await unfollow(bob, alice.publicKey);
+    expect(await program.account.follow.fetchNullable(followPda(bob.publicKey, alice.publicKey))).to.be.null;
+    expect(await counts(bob.publicKey)).to.deep.equal([0, 0]);
+  });
+});
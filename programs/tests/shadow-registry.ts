@@ -0,0 +1,558 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { ShadowRegistry } from "../target/types/shadow_registry";
+
+describe("shadow-registry", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.ShadowRegistry as Program<ShadowRegistry>;
+  const owner = provider.wallet.publicKey;
+
+  const sitePda = (programAccount: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("site"), programAccount.toBuffer()], program.programId)[0];
+
+  const register = (programAccount: PublicKey, storageCid: string) =>
+    program.methods
+      .registerSite("Shadow", "A site", storageCid)
+      .accounts({ site: sitePda(programAccount), programAccount, owner })
+      .rpc();
+
+  const transferPda = (site: PublicKey) =>
+    PublicKey.findProgramAddressSync([Buffer.from("transfer"), site.toBuffer()], program.programId)[0];
+
+  const versionPda = (site: PublicKey, version: number) => {
+    const number = Buffer.alloc(4);
+    number.writeUInt32LE(version);
+    return PublicKey.findProgramAddressSync([Buffer.from("version"), site.toBuffer(), number], program.programId)[0];
+  };
+
+  const publish = async (site: PublicKey, storageCid: string) => {
+    const { version } = await program.account.site.fetch(site);
+    return program.methods
+      .publishVersion(storageCid)
+      .accounts({ site, siteVersion: versionPda(site, version + 1), owner })
+      .rpc();
+  };
+
+  const rollback = (site: PublicKey, version: number) =>
+    program.methods.rollback(version).accounts({ site, siteVersion: versionPda(site, version), owner }).rpc();
+
+  const editUpdate = (site: PublicKey, editor: Keypair, name: string) =>
+    program.methods.updateSite(name, null, null).accounts({ site, authority: editor.publicKey }).signers([editor]).rpc();
+
+  const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms));
+
+  it("deletes a site, refunds its rent and allows re-registering", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+
+    await register(programAccount, "ipfs://first");
+    const first = await program.account.site.fetch(site);
+    const rent = await provider.connection.getBalance(site);
+    expect(rent).to.be.greaterThan(0);
+
+    const before = await provider.connection.getBalance(owner);
+    const signature = await program.methods.deleteSite().accounts({ site, owner }).rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(signature, { commitment: "confirmed" });
+    const after = await provider.connection.getBalance(owner, "confirmed");
+    expect(after).to.equal(before + rent - tx!.meta!.fee);
+    expect(await program.account.site.fetchNullable(site)).to.be.null;
+
+    // Let the clock move so the new registration gets its own timestamps
+    await sleep(1500);
+    await register(programAccount, "ipfs://second");
+    const second = await program.account.site.fetch(site);
+    expect(second.storageCid).to.equal("ipfs://second");
+    expect(second.createdAt.toNumber()).to.be.greaterThan(first.createdAt.toNumber());
+    expect(second.updatedAt.toNumber()).to.equal(second.createdAt.toNumber());
+  });
+
+  it("only lets the owner delete a site", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://kept");
+
+    const stranger = Keypair.generate();
+    try {
+      await program.methods.deleteSite().accounts({ site, owner: stranger.publicKey }).signers([stranger]).rpc();
+      expect.fail("stranger deleted the site");
+    } catch (err) {
+      expect(String(err)).to.contain("Unauthorized");
+    }
+    expect(await program.account.site.fetchNullable(site)).to.not.be.null;
+  });
+
+  it("publishes again after a site with versions is deleted and re-registered", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://first");
+    for (const cid of ["ipfs://bafyold1", "ipfs://bafyold2", "ipfs://bafyold3"]) {
+      await publish(site, cid);
+    }
+
+    // Close version 3 with the site and leave 1 and 2 behind
+    await program.methods
+      .deleteSite()
+      .accounts({ site, owner })
+      .remainingAccounts([{ pubkey: versionPda(site, 3), isSigner: false, isWritable: true }])
+      .rpc();
+    expect(await program.account.siteVersion.fetchNullable(versionPda(site, 3))).to.be.null;
+    expect(await program.account.siteVersion.fetchNullable(versionPda(site, 1))).to.not.be.null;
+
+    await register(programAccount, "ipfs://second");
+    await publish(site, "ipfs://bafynew1");
+    let current = await program.account.site.fetch(site);
+    expect(current.version).to.equal(1);
+    expect(current.storageCid).to.equal("ipfs://bafynew1");
+    expect((await program.account.siteVersion.fetch(versionPda(site, 1))).storageCid).to.equal("ipfs://bafynew1");
+
+    // Version 2 is still the old site's content, so it can't be rolled back to
+    try {
+      await rollback(site, 2);
+      expect.fail("rolled back to the deleted site's version");
+    } catch (err) {
+      expect(String(err)).to.contain("VersionNotPublished");
+    }
+    expect((await program.account.site.fetch(site)).storageCid).to.equal("ipfs://bafynew1");
+
+    // Publishing takes the leftover over, and then it rolls back like any other version
+    await publish(site, "ipfs://bafynew2");
+    await publish(site, "ipfs://bafynew3");
+    await rollback(site, 2);
+    current = await program.account.site.fetch(site);
+    expect(current.version).to.equal(3);
+    expect(current.storageCid).to.equal("ipfs://bafynew2");
+  });
+
+  it("only closes the deleted site's own versions", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://mine");
+    const otherAccount = Keypair.generate().publicKey;
+    const other = sitePda(otherAccount);
+    await register(otherAccount, "ipfs://other");
+    await publish(other, "ipfs://bafyforeign");
+
+    try {
+      await program.methods
+        .deleteSite()
+        .accounts({ site, owner })
+        .remainingAccounts([{ pubkey: versionPda(other, 1), isSigner: false, isWritable: true }])
+        .rpc();
+      expect.fail("closed another site's version");
+    } catch (err) {
+      expect(String(err)).to.contain("ForeignVersion");
+    }
+    expect(await program.account.site.fetchNullable(site)).to.not.be.null;
+    expect(await program.account.siteVersion.fetchNullable(versionPda(other, 1))).to.not.be.null;
+  });
+
+  it("emits events when a site is registered and updated", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    const registered: any[] = [];
+    const updated: any[] = [];
+    const listeners = [
+      program.addEventListener("SiteRegistered", (event) => registered.push(event)),
+      program.addEventListener("SiteUpdated", (event) => updated.push(event)),
+    ];
+    await sleep(1500);
+    await register(programAccount, "ipfs://events");
+    await program.methods
+      .updateSite("Renamed", null, null)
+      .accounts({ site, authority: owner })
+      .rpc({ commitment: "confirmed" });
+    await sleep(500);
+    await Promise.all(listeners.map((listener) => program.removeEventListener(listener)));
+
+    expect(registered).to.have.length(1);
+    expect(registered[0].programAddress.toBase58()).to.equal(programAccount.toBase58());
+    expect(registered[0].owner.toBase58()).to.equal(owner.toBase58());
+    expect(registered[0].name).to.equal("Shadow");
+    expect(updated).to.have.length(1);
+    expect(updated[0].site.toBase58()).to.equal(site.toBase58());
+    expect(updated[0].updatedBy.toBase58()).to.equal(owner.toBase58());
+    expect(updated[0].at.toNumber()).to.be.at.least(registered[0].at.toNumber());
+  });
+
+  it("transfers ownership to a new owner", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://sold");
+    const before = await program.account.site.fetch(site);
+
+    const buyer = Keypair.generate();
+    const events: any[] = [];
+    const listener = program.addEventListener("OwnershipTransferred", (event) => events.push(event));
+    await sleep(1500);
+    await program.methods
+      .transferOwnership(buyer.publicKey)
+      .accounts({ site, owner, newOwner: buyer.publicKey })
+      .rpc({ commitment: "confirmed" });
+    await sleep(500);
+    await program.removeEventListener(listener);
+
+    const after = await program.account.site.fetch(site);
+    expect(after.owner.toBase58()).to.equal(buyer.publicKey.toBase58());
+    expect(after.updatedAt.toNumber()).to.be.greaterThan(before.updatedAt.toNumber());
+    expect(events).to.have.length(1);
+    expect(events[0].previousOwner.toBase58()).to.equal(owner.toBase58());
+    expect(events[0].newOwner.toBase58()).to.equal(buyer.publicKey.toBase58());
+
+    // The old owner has no say any more
+    try {
+      await program.methods.updateSite("Mine", null, null).accounts({ site, authority: owner }).rpc();
+      expect.fail("previous owner updated the site");
+    } catch (err) {
+      expect(String(err)).to.contain("Unauthorized");
+    }
+  });
+
+  it("only lets the owner transfer a site", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://kept");
+
+    const stranger = Keypair.generate();
+    for (const [call, error] of [
+      [
+        program.methods
+          .transferOwnership(stranger.publicKey)
+          .accounts({ site, owner: stranger.publicKey, newOwner: stranger.publicKey }),
+        "InvalidOwner",
+      ],
+      [
+        program.methods.proposeOwnership(stranger.publicKey).accounts({
+          site,
+          transfer: transferPda(site),
+          owner: stranger.publicKey,
+        }),
+        "Unauthorized",
+      ],
+    ] as const) {
+      try {
+        await call.signers([stranger]).rpc();
+        expect.fail("stranger transferred the site");
+      } catch (err) {
+        expect(String(err)).to.contain(error);
+      }
+    }
+    const after = await program.account.site.fetch(site);
+    expect(after.owner.toBase58()).to.equal(owner.toBase58());
+  });
+
+  it("won't transfer to an account other than the one named", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://kept");
+
+    const buyer = Keypair.generate().publicKey;
+    try {
+      await program.methods
+        .transferOwnership(buyer)
+        .accounts({ site, owner, newOwner: Keypair.generate().publicKey })
+        .rpc();
+      expect.fail("transferred to the wrong account");
+    } catch (err) {
+      expect(String(err)).to.contain("InvalidNewOwner");
+    }
+    const after = await program.account.site.fetch(site);
+    expect(after.owner.toBase58()).to.equal(owner.toBase58());
+  });
+
+  it("only changes owner once a proposed transfer is accepted", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    const transfer = transferPda(site);
+    await register(programAccount, "ipfs://dao");
+
+    const multisig = Keypair.generate();
+    await program.methods.proposeOwnership(multisig.publicKey).accounts({ site, transfer, owner }).rpc();
+    expect((await program.account.site.fetch(site)).owner.toBase58()).to.equal(owner.toBase58());
+
+    // Nobody but the proposed owner can accept
+    const stranger = Keypair.generate();
+    try {
+      await program.methods
+        .acceptOwnership()
+        .accounts({ site, transfer, newOwner: stranger.publicKey, proposedBy: owner })
+        .signers([stranger])
+        .rpc();
+      expect.fail("stranger accepted the transfer");
+    } catch (err) {
+      expect(String(err)).to.contain("Unauthorized");
+    }
+
+    await program.methods
+      .acceptOwnership()
+      .accounts({ site, transfer, newOwner: multisig.publicKey, proposedBy: owner })
+      .signers([multisig])
+      .rpc();
+    expect((await program.account.site.fetch(site)).owner.toBase58()).to.equal(multisig.publicKey.toBase58());
+    expect(await program.account.ownershipTransfer.fetchNullable(transfer)).to.be.null;
+  });
+
+  it("cancels a pending transfer", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    const transfer = transferPda(site);
+    await register(programAccount, "ipfs://typo");
+
+    const typo = Keypair.generate();
+    await program.methods.proposeOwnership(typo.publicKey).accounts({ site, transfer, owner }).rpc();
+    const pending = await program.account.ownershipTransfer.fetch(transfer);
+    expect(pending.newOwner.toBase58()).to.equal(typo.publicKey.toBase58());
+
+    await program.methods.cancelOwnershipTransfer().accounts({ site, transfer, owner }).rpc();
+    expect(await program.account.ownershipTransfer.fetchNullable(transfer)).to.be.null;
+
+    try {
+      await program.methods
+        .acceptOwnership()
+        .accounts({ site, transfer, newOwner: typo.publicKey, proposedBy: owner })
+        .signers([typo])
+        .rpc();
+      expect.fail("cancelled transfer was accepted");
+    } catch (err) {
+      expect(String(err)).to.contain("AccountNotInitialized");
+    }
+    expect((await program.account.site.fetch(site)).owner.toBase58()).to.equal(owner.toBase58());
+  });
+
+  it("keeps every published version and rolls back to the exact CID", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://registered");
+    expect((await program.account.site.fetch(site)).version).to.equal(0);
+
+    const cids = ["ipfs://bafyfirst", "ipfs://bafysecond", "ipfs://bafybroken"];
+    for (const cid of cids) {
+      await publish(site, cid);
+    }
+    let current = await program.account.site.fetch(site);
+    expect(current.version).to.equal(3);
+    expect(current.storageCid).to.equal("ipfs://bafybroken");
+
+    const second = await program.account.siteVersion.fetch(versionPda(site, 2));
+    expect(second.version).to.equal(2);
+    expect(second.storageCid).to.equal("ipfs://bafysecond");
+    expect(second.site.toBase58()).to.equal(site.toBase58());
+
+    await rollback(site, 2);
+    current = await program.account.site.fetch(site);
+    expect(current.storageCid).to.equal("ipfs://bafysecond");
+    expect(current.version).to.equal(3);
+
+    // Publishing after a rollback continues the numbering
+    await publish(site, "ipfs://bafyfixed");
+    current = await program.account.site.fetch(site);
+    expect(current.version).to.equal(4);
+    expect(current.storageCid).to.equal("ipfs://bafyfixed");
+  });
+
+  it("rejects rolling back to a version that was never published", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://registered");
+    await publish(site, "ipfs://bafyonly");
+
+    for (const version of [0, 2, 99]) {
+      try {
+        await rollback(site, version);
+        expect.fail(`rolled back to version ${version}`);
+      } catch (err) {
+        expect(String(err)).to.contain("AccountNotInitialized");
+      }
+    }
+    expect((await program.account.site.fetch(site)).storageCid).to.equal("ipfs://bafyonly");
+
+    // Another site's version cannot be used either
+    const otherAccount = Keypair.generate().publicKey;
+    const other = sitePda(otherAccount);
+    await register(otherAccount, "ipfs://other");
+    await publish(other, "ipfs://bafyforeign");
+    try {
+      await program.methods.rollback(1).accounts({ site, siteVersion: versionPda(other, 1), owner }).rpc();
+      expect.fail("rolled back to another site's version");
+    } catch (err) {
+      expect(String(err)).to.match(/ConstraintSeeds|ConstraintHasOne/);
+    }
+  });
+
+  it("only lets the owner publish or roll back", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://registered");
+    await publish(site, "ipfs://bafyowned");
+
+    const stranger = Keypair.generate();
+    const airdrop = await provider.connection.requestAirdrop(stranger.publicKey, 1_000_000_000);
+    await provider.connection.confirmTransaction(airdrop, "confirmed");
+    const attempts = [
+      () =>
+        program.methods
+          .publishVersion("ipfs://bafyhijack")
+          .accounts({ site, siteVersion: versionPda(site, 2), owner: stranger.publicKey })
+          .signers([stranger])
+          .rpc(),
+      () =>
+        program.methods
+          .rollback(1)
+          .accounts({ site, siteVersion: versionPda(site, 1), owner: stranger.publicKey })
+          .signers([stranger])
+          .rpc(),
+    ];
+    for (const attempt of attempts) {
+      try {
+        await attempt();
+        expect.fail("stranger changed the site's content");
+      } catch (err) {
+        expect(String(err)).to.contain("Unauthorized");
+      }
+    }
+    expect((await program.account.site.fetch(site)).version).to.equal(1);
+  });
+
+  it("lets a listed editor update the site", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://team");
+
+    const editor = Keypair.generate();
+    await program.methods.addEditor(editor.publicKey).accounts({ site, owner }).rpc();
+    expect((await program.account.site.fetch(site)).editors.map((e) => e.toBase58())).to.deep.equal([
+      editor.publicKey.toBase58(),
+    ]);
+
+    await editUpdate(site, editor, "Team site");
+    expect((await program.account.site.fetch(site)).name).to.equal("Team site");
+
+    // Editors update content but do not manage the editor list
+    try {
+      await program.methods
+        .addEditor(Keypair.generate().publicKey)
+        .accounts({ site, owner: editor.publicKey })
+        .signers([editor])
+        .rpc();
+      expect.fail("editor added another editor");
+    } catch (err) {
+      expect(String(err)).to.contain("Unauthorized");
+    }
+  });
+
+  it("stops a removed editor from updating the site", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://team");
+
+    const editor = Keypair.generate();
+    await program.methods.addEditor(editor.publicKey).accounts({ site, owner }).rpc();
+    await program.methods.removeEditor(editor.publicKey).accounts({ site, owner }).rpc();
+    expect((await program.account.site.fetch(site)).editors).to.be.empty;
+
+    try {
+      await editUpdate(site, editor, "Not any more");
+      expect.fail("removed editor updated the site");
+    } catch (err) {
+      expect(String(err)).to.contain("Unauthorized");
+    }
+    expect((await program.account.site.fetch(site)).name).to.equal("Shadow");
+
+    try {
+      await program.methods.removeEditor(editor.publicKey).accounts({ site, owner }).rpc();
+      expect.fail("removed an editor twice");
+    } catch (err) {
+      expect(String(err)).to.contain("EditorNotFound");
+    }
+  });
+
+  it("caps the number of editors", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://crowded");
+
+    const editors = Array.from({ length: 5 }, () => Keypair.generate().publicKey);
+    for (const editor of editors) {
+      await program.methods.addEditor(editor).accounts({ site, owner }).rpc();
+    }
+
+    for (const [editor, error] of [
+      [Keypair.generate().publicKey, "TooManyEditors"],
+      [editors[0], "EditorAlreadyAdded"],
+      [owner, "InvalidEditor"],
+    ] as const) {
+      try {
+        await program.methods.addEditor(editor).accounts({ site, owner }).rpc();
+        expect.fail(`added ${editor.toBase58()}`);
+      } catch (err) {
+        expect(String(err)).to.contain(error);
+      }
+    }
+    expect((await program.account.site.fetch(site)).editors).to.have.length(5);
+  });
+
+  it("sizes a site to its text and grows it for a longer description", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await program.methods
+      .registerSite("Tiny", "x", "ipfs://tiny")
+      .accounts({ site, programAccount, owner })
+      .rpc();
+    const small = (await provider.connection.getAccountInfo(site))!;
+    const fixed = 8 + 32 + 32 + 4 + 4 + (4 + 100) + 8 + 8 + 4 + (4 + 32 * 5);
+    expect(small.data.length).to.equal(fixed + "Tiny".length + "x".length);
+
+    const longer = "A much longer description than the one this site was registered with. ".repeat(4);
+    try {
+      await program.methods.updateSite(null, longer, null).accounts({ site, authority: owner }).rpc();
+      expect.fail("description outgrew the account");
+    } catch (err) {
+      expect(String(err)).to.contain("SiteTooSmall");
+    }
+
+    const before = await provider.connection.getBalance(owner);
+    const signature = await program.methods
+      .resizeSite(4, longer.length)
+      .accounts({ site, owner })
+      .rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(signature, { commitment: "confirmed" });
+    const grown = (await provider.connection.getAccountInfo(site, "confirmed"))!;
+    expect(grown.data.length).to.equal(fixed + 4 + longer.length);
+    // The owner paid exactly the rent difference
+    const paid = before - (await provider.connection.getBalance(owner, "confirmed")) - tx!.meta!.fee;
+    expect(paid).to.equal(grown.lamports - small.lamports);
+    expect(grown.lamports).to.equal(await provider.connection.getMinimumBalanceForRentExemption(grown.data.length));
+
+    await program.methods.updateSite(null, longer, null).accounts({ site, authority: owner }).rpc();
+    const updated = await program.account.site.fetch(site);
+    expect(updated.description).to.equal(longer);
+    expect(updated.name).to.equal("Tiny");
+    expect(updated.storageCid).to.equal("ipfs://tiny");
+  });
+
+  it("won't shrink a site below its current text", async () => {
+    const programAccount = Keypair.generate().publicKey;
+    const site = sitePda(programAccount);
+    await register(programAccount, "ipfs://kept");
+
+    const stranger = Keypair.generate();
+    for (const [call, error] of [
+      [program.methods.resizeSite(6, 1).accounts({ site, owner }), "SiteTooSmall"],
+      [program.methods.resizeSite(6, 501).accounts({ site, owner }), "DescriptionTooLong"],
+      [
+        program.methods.resizeSite(6, 100).accounts({ site, owner: stranger.publicKey }).signers([stranger]),
+        "Unauthorized",
+      ],
+    ] as const) {
+      try {
+        await call.rpc();
+        expect.fail("resize should have failed");
+      } catch (err) {
+        expect(String(err)).to.contain(error);
+      }
+    }
+    expect((await program.account.site.fetch(site)).description).to.equal("A site");
+  });
+});
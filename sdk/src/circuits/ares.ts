@@ -9,19 +9,23 @@ export interface AuthChallenge {
   message: string
   timestamp: number
   wallet: string
+  nonce: string
 }
 
 /**
- * Create an authentication challenge
+ * Create an authentication challenge. The nonce makes each signed header
+ * single-use; the API rejects a header it has already seen.
  */
 export function createChallenge(wallet: string): AuthChallenge {
   const timestamp = Date.now()
-  const message = `Shadow authentication challenge for ${wallet} at ${timestamp}`
+  const nonce = require("crypto").randomUUID()
+  const message = `Shadow authentication challenge for ${wallet} at ${timestamp} with nonce ${nonce}`
   
   return {
     message,
     timestamp,
     wallet,
+    nonce,
   }
 }
 
@@ -76,6 +80,7 @@ export async function createAuthHeader(
     wallet: walletPubkey,
     signature,
     timestamp: challenge.timestamp,
+    nonce: challenge.nonce,
   })
 }
 
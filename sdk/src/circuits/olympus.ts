@@ -20,6 +20,12 @@ export interface Domain {
   updated_at: string
 }
 
+/** One page of a cursor-paginated list; pass `next_cursor` back to get the next page */
+export interface Page<T> {
+  items: T[]
+  next_cursor: string | null
+}
+
 /**
  * Register a domain to a contract/program address
  * Example: registerDomain("whatsapp.shadow", "WD3EtZGu8Gvhji4GHdLjGBfSc8cGSpyRJ612ANhpump", wallet)
@@ -57,10 +63,10 @@ export async function getDomain(domain: string): Promise<Domain> {
  * Get domain by program/contract address
  */
 export async function getDomainByProgram(programAddress: string): Promise<Domain | null> {
-  const response = await axios.get(
+  const response = await axios.get<Page<Domain>>(
     `${BACKEND_URL}/api/domains/search?q=${encodeURIComponent(programAddress)}&limit=1`
   )
-  const domains = response.data
+  const domains = response.data.items
   return domains.length > 0 ? domains[0] : null
 }
 
@@ -109,10 +115,14 @@ export async function listOwnerDomains(ownerPubkey: string): Promise<Domain[]> {
 /**
  * Search domains
  */
-export async function searchDomains(query: string, limit: number = 10): Promise<Domain[]> {
-  const response = await axios.get(
-    `${BACKEND_URL}/api/domains/search?q=${encodeURIComponent(query)}&limit=${limit}`
-  )
+export async function searchDomains(
+  query: string,
+  limit: number = 10,
+  cursor?: string
+): Promise<Page<Domain>> {
+  const params = new URLSearchParams({ q: query, limit: String(limit) })
+  if (cursor) params.set("cursor", cursor)
+  const response = await axios.get<Page<Domain>>(`${BACKEND_URL}/api/domains/search?${params}`)
   return response.data
 }
 